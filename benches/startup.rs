@@ -0,0 +1,50 @@
+//! How long a vault of 20,000 notes takes to list before the TUI can draw its
+//! first frame, parsing every metadata file up front or leaving them to the
+//! background with `lazy_load_above`.
+//!
+//! Run with `cargo bench --bench startup`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ryokan::{file, metadata::NoteMetadata, storage::FsStorage, vault::Vault};
+use std::{fs, path::Path, time::Duration};
+use tempfile::tempdir;
+use zeroize::Zeroizing;
+
+const NOTES: usize = 20_000;
+
+/// Writes `NOTES` notes to `dir`. Listing never decrypts content, so it's
+/// filler rather than encrypted with a PIN, which would take hours.
+fn synthetic_vault(dir: &Path) {
+    let storage = FsStorage::new(dir);
+    for i in 0..NOTES {
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let (content_name, metadata_name) = file::note_file_names(&uuid);
+        fs::write(dir.join(content_name), [0u8; 64]).expect("writing content");
+        let mut metadata = NoteMetadata::new(format!("Archived mail {i}"));
+        metadata.tags = vec!["mail".to_string(), format!("year/{}", 2000 + i % 25)];
+        metadata
+            .save(&storage, &metadata_name)
+            .expect("writing metadata");
+    }
+}
+
+fn cold_start(c: &mut Criterion) {
+    let dir = tempdir().expect("creating the vault");
+    synthetic_vault(dir.path());
+    // A new vault each time, so nothing is cached from the previous run
+    let open = || Vault::open(dir.path(), Zeroizing::new("123456".to_string()));
+
+    let mut group = c.benchmark_group("cold start, 20k notes");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+    group.bench_function("parse every metadata file", |b| {
+        b.iter(|| open().list().expect("listing"))
+    });
+    group.bench_function("list with placeholders", |b| {
+        b.iter(|| open().list_lazily(|_| true).expect("listing"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cold_start);
+criterion_main!(benches);
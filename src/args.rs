@@ -1,4 +1,16 @@
-use clap::{ArgAction, Parser};
+use chrono::{DateTime, Local, Utc};
+use clap::{ArgAction, Parser, ValueEnum};
+use ryokan::{
+    datetime,
+    dedupe::KeepPolicy,
+    error::AppError,
+    export::ExportFormat,
+    import::ImportFormat,
+    metadata::{self, NoteType},
+    settings::Flags,
+    share,
+    vault::{ListQuery, SortMode},
+};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -6,25 +18,652 @@ pub struct Args {
     #[clap(subcommand)]
     pub command: Option<Subcommands>,
 
-    /// The path to the notes directory
-    #[clap(short, long, default_value = "notes")]
-    pub notes_dir: PathBuf,
+    /// The path to the notes directory. Overrides RYOKAN_NOTES_DIR and the config file.
+    #[clap(short, long)]
+    pub notes_dir: Option<PathBuf>,
 
-    /// The path to the config file
+    /// The path to the config file. Overrides RYOKAN_CONFIG.
     #[clap(short, long)]
     pub config_file: Option<PathBuf>,
 
-    /// The text editor to use. The default is the system's default text editor (EDITOR
-    /// environment variable), and if that doesn't work, use nano.
+    /// The text editor to use. The default is RYOKAN_EDITOR, then the system's default
+    /// text editor (EDITOR environment variable), and if that doesn't work, use nano.
     #[clap(short, long)]
     pub editor: Option<String>,
 
+    /// The profile (separate vault) to use. Overrides RYOKAN_PROFILE.
+    #[clap(short, long, global = true)]
+    pub profile: Option<String>,
+
+    /// Refuse every change to the notes, e.g. to browse them while sharing the screen.
+    #[clap(long, global = true)]
+    pub read_only: bool,
+
+    /// Write log messages to this file. The TUI logs to ryokan.log in the state
+    /// directory by default, subcommands to stderr.
+    #[clap(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Draw borders in ASCII and write words instead of emoji, e.g. for screen
+    /// readers.
+    #[clap(long, global = true)]
+    pub plain: bool,
+
+    /// Start the TUI in privacy mode, which hides the content of notes, e.g.
+    /// while sharing the screen.
+    #[clap(long)]
+    pub private: bool,
+
+    /// Start the TUI listing only the notes whose title or an alias contains
+    /// this, ignoring case.
+    #[clap(long)]
+    pub search: Option<String>,
+
+    /// Print how long each startup phase took to stderr when exiting.
+    #[clap(long, global = true)]
+    pub timing: bool,
+
+    /// Copy the configuration of a legacy cryptnote install without asking
+    #[clap(long)]
+    pub migrate_legacy: bool,
+
+    /// Don't show the setup wizard on the first run; only the PIN is asked for,
+    /// e.g. for scripted setups.
+    #[clap(long)]
+    pub no_wizard: bool,
+
     #[clap(short, long, action = ArgAction::Count, default_value_t = 1)]
     pub verbose_level: u8,
 }
 
 #[derive(Parser, Debug)]
 pub enum Subcommands {
-    /// Scans for unencrypted files in the notes directory and encrypts them.
-    EncryptUnencrypted,
+    /// Scans for unencrypted files in the notes directory and encrypts them,
+    /// leaving out those `.ryokanignore` matches.
+    EncryptUnencrypted {
+        /// Print what would be encrypted, renamed or ignored without changing
+        /// anything.
+        #[clap(long)]
+        dry_run: bool,
+        /// Don't ask before encrypting more files or bytes than
+        /// `encrypt_confirm_files` and `encrypt_confirm_bytes`.
+        #[clap(long)]
+        yes: bool,
+    },
+    /// Prints the UUID and title of every note, one per line.
+    List {
+        /// `tsv` for the UUID and title of each note, `plain` for titles only,
+        /// `table` for a table to read, or `json` for every note with its metadata.
+        #[clap(long, value_enum, default_value_t = ListFormat::Tsv)]
+        format: ListFormat,
+        /// The same as `--format json`.
+        #[clap(long, conflicts_with = "format")]
+        json: bool,
+        /// Order the notes by `updated`, `created`, `title`, `size` or `due`.
+        #[clap(long)]
+        sort: Option<SortMode>,
+        /// Turn the order around.
+        #[clap(long)]
+        reverse: bool,
+        /// List at most this many notes.
+        #[clap(long)]
+        limit: Option<usize>,
+        #[clap(flatten)]
+        filter: NoteFilter,
+    },
+    /// Writes the notes matching the filters, the same as `list` takes, to a
+    /// directory, a file per note, or to a single JSON file.
+    Export {
+        #[clap(flatten)]
+        filter: NoteFilter,
+        /// `text`, `html` or `md-frontmatter` for a file per note, or `json` for
+        /// an array of every note with its title, tags, dates and content.
+        #[clap(long, default_value_t = ExportFormat::Text)]
+        format: ExportFormat,
+        /// The directory to write the notes to, created if needed, or the file
+        /// to write with `--format json`.
+        #[clap(short, long)]
+        output: PathBuf,
+        /// Keep raw HTML of the notes in the HTML output instead of escaping it.
+        #[clap(long)]
+        allow_html: bool,
+        /// Only print the files that would be written.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Prints overdue notes and notes due in the next days, soonest first.
+    Due {
+        /// How many days ahead to look.
+        #[clap(long, default_value_t = 7)]
+        days: u64,
+    },
+    /// Prints the unchecked checklist items of every note, grouped by note.
+    Todos,
+    /// Lists or renames tags, with `/` separating namespaces.
+    Tag {
+        #[clap(subcommand)]
+        action: TagAction,
+    },
+    /// Exports the titles, tags and other metadata of every note as CSV, or
+    /// applies such a file back, e.g. to edit them in a spreadsheet.
+    Meta {
+        #[clap(subcommand)]
+        action: MetaAction,
+    },
+    /// Sets when notes were last updated to now, or repairs their timestamps,
+    /// e.g. after an import. Prints each change before making it.
+    Touch {
+        /// UUIDs or titles of the notes.
+        #[clap(required_unless_present = "all", conflicts_with = "all")]
+        notes: Vec<String>,
+        /// Every note of the vault.
+        #[clap(long)]
+        all: bool,
+        /// The time to set instead of now, as RFC 3339 or `YYYY-MM-DD`, `today`
+        /// or `yesterday` in local time.
+        #[clap(long, conflicts_with = "from_file_mtime")]
+        date: Option<String>,
+        /// Set when the notes were created instead of when they were updated.
+        #[clap(long, conflicts_with = "from_file_mtime")]
+        created: bool,
+        /// Set both timestamps from the encrypted file of each note, as the
+        /// filesystem has them.
+        #[clap(long)]
+        from_file_mtime: bool,
+        /// Change more than a handful of notes without asking.
+        #[clap(long)]
+        yes: bool,
+    },
+    /// Imports a directory tree of Markdown files, such as an Obsidian vault, or
+    /// the export file of another app.
+    Import {
+        /// The directory, or the export file for `joplin`, `standard-notes` and
+        /// `age`.
+        path: PathBuf,
+        /// `markdown`, `obsidian` to also read tags, aliases and dates from the
+        /// frontmatter, `joplin` for a JEX export, `standard-notes` for a
+        /// decrypted backup, or `age` for a single note encrypted with age.
+        #[clap(long, default_value_t = ImportFormat::Markdown)]
+        format: ImportFormat,
+        /// The age identity file to decrypt an `age` file with. The passphrase is
+        /// asked for instead if the file was encrypted to one.
+        #[clap(long)]
+        identity: Option<PathBuf>,
+        /// Print what would be imported without importing anything.
+        #[clap(long)]
+        dry_run: bool,
+        /// Also write the source path → UUID mapping to this file.
+        #[clap(long)]
+        mapping: Option<PathBuf>,
+    },
+    /// Moves notes between machines in a passphrase-encrypted file.
+    Bundle {
+        #[clap(subcommand)]
+        action: BundleAction,
+    },
+    /// Commits, pulls and pushes the notes directory, as set up in `[sync]`.
+    Sync,
+    /// Rebuilds the note index from the metadata files.
+    Reindex,
+    /// Creates a note and opens it in the editor.
+    New {
+        /// Defaults to `new_note_title` from the config, or to the first line of
+        /// the clipboard with `--from-clipboard`.
+        #[clap(long)]
+        title: Option<String>,
+        /// Make the note of the text on the clipboard.
+        #[clap(long)]
+        from_clipboard: bool,
+        /// The type of the note: plain, markdown, todo or journal.
+        #[clap(long = "type", value_parser = note_type, default_value = "plain")]
+        note_type: NoteType,
+    },
+    /// Prints `title<TAB>uuid` for every note, e.g. to choose one with fzf.
+    Pick {
+        /// Choose the note with the `picker` from the config instead, and open it
+        /// in the editor.
+        #[clap(long)]
+        and_edit: bool,
+    },
+    /// Starts the TUI with a note, given by UUID or title, selected. If several
+    /// notes have that title, only they are listed.
+    Open { note: String },
+    /// Prints what changed in a note, given by UUID or title, since a previous
+    /// version, as a unified diff.
+    Diff {
+        note: String,
+        /// The version to compare with: the newest one saved at or before this
+        /// time (RFC 3339) or day (`YYYY-MM-DD`, `today`, `yesterday`). Defaults
+        /// to the newest version.
+        #[clap(long)]
+        against: Option<String>,
+    },
+    /// Opens a note, given by UUID or title, in the editor.
+    Edit {
+        note: String,
+        /// Edit the note even if it's read-only.
+        #[clap(long)]
+        force: bool,
+    },
+    /// Appends a line of text to the inbox note without opening the TUI.
+    Quick {
+        /// The text to capture. One line is read from stdin if omitted.
+        text: Option<String>,
+        /// Read the PIN from this file instead of asking for it.
+        #[clap(long)]
+        pin_file: Option<PathBuf>,
+    },
+    /// Opens the journal note of today, creating it if needed.
+    Today {
+        /// Open the note of yesterday instead.
+        #[clap(long, conflicts_with = "date")]
+        yesterday: bool,
+        /// Open the note of this day instead, as `YYYY-MM-DD`.
+        #[clap(long)]
+        date: Option<String>,
+    },
+    /// Appends stdin to a note, given by UUID or title.
+    Append {
+        note: String,
+        /// Put a dated separator line before the new content.
+        #[clap(long)]
+        timestamp: bool,
+        /// Create the note, titled `note`, if it doesn't exist.
+        #[clap(long)]
+        create: bool,
+    },
+    /// Inserts stdin at the start of a note, given by UUID or title.
+    Prepend {
+        note: String,
+        /// Put a dated separator line before the new content.
+        #[clap(long)]
+        timestamp: bool,
+        /// Create the note, titled `note`, if it doesn't exist.
+        #[clap(long)]
+        create: bool,
+    },
+    /// Prints a note, given by UUID or title, for sharing.
+    Show {
+        note: String,
+        /// `text` for the content as is, `html` for a standalone web page, or
+        /// `md-frontmatter` for Markdown with the metadata in a YAML header.
+        #[clap(long, default_value_t = ExportFormat::Text)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout. Given a directory, write to a
+        /// new file in it named after the note.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        /// Keep raw HTML of the note in the HTML output instead of escaping it.
+        #[clap(long)]
+        allow_html: bool,
+        /// Read it in a pager.
+        #[clap(long, conflicts_with = "output")]
+        pager: bool,
+        /// Print the line, word and character counts, reading time and
+        /// checklist progress of the note instead.
+        #[clap(long, conflicts_with_all = ["output", "pager"])]
+        stats: bool,
+    },
+    /// Encrypts a note, given by UUID or title, into an age file for someone
+    /// who doesn't use Ryokan.
+    Share {
+        note: String,
+        /// The age public key to encrypt the note to, starting with `age1`.
+        #[clap(
+            long,
+            value_parser = share::parse_recipient,
+            required_unless_present = "passphrase",
+            conflicts_with = "passphrase"
+        )]
+        recipient: Option<age::x25519::Recipient>,
+        /// Encrypt the note to a passphrase instead, which is asked for.
+        #[clap(long)]
+        passphrase: bool,
+        /// The age file to write.
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Finds notes with identical content and moves all but one of each group to
+    /// the trash, merging their tags into the note kept.
+    Dedupe {
+        /// Keep the `newest` or `oldest` note of each group without asking.
+        #[clap(long)]
+        auto_keep: Option<KeepPolicy>,
+        /// Only print the groups of duplicates.
+        #[clap(long)]
+        dry_run: bool,
+        /// Save the content hashes, encrypted, so unchanged notes aren't decrypted
+        /// again next time.
+        #[clap(long)]
+        cache_hashes: bool,
+    },
+    /// Reports the space taken by previous versions, the trash, temporary files
+    /// and caches, and with `--apply` deletes what `trash_retention_days`,
+    /// `history_versions` and `cache_max_bytes` let go of.
+    Gc {
+        /// Delete, printing each file deleted, instead of only reporting.
+        #[clap(long)]
+        apply: bool,
+    },
+    /// Prints how many notes there are, the space they take, the tags most
+    /// notes have and how many saves there were each week, without decrypting
+    /// anything.
+    Stats,
+    /// Lists the snapshots of the notes directory, see `[backup]` in the
+    /// config.
+    Backup {
+        #[clap(subcommand)]
+        action: BackupAction,
+    },
+    /// Puts the notes directory back as it was in a snapshot, after taking a
+    /// snapshot of how it is, which undoes it.
+    Restore {
+        /// The snapshot, named after when it was taken as `backup list` shows.
+        #[clap(long)]
+        from_snapshot: String,
+    },
+    /// Checks that no note changed without Ryokan, e.g. through bit-rot, by
+    /// decrypting every note and comparing it with the integrity manifest.
+    Verify {
+        /// Only compare the encrypted files with the manifest, without decrypting
+        /// them, like at startup.
+        #[clap(long)]
+        fast: bool,
+    },
+    /// Encrypts every note again with fresh salts and nonces, then checks that
+    /// each one decrypts.
+    Rekey,
+    /// Prints the audit log: what happened to the vault and when.
+    Log {
+        /// Only events about this note, given by UUID.
+        #[clap(long)]
+        uuid: Option<String>,
+        /// Only events since this date, as `YYYY-MM-DD`, `today` or RFC 3339.
+        #[clap(long)]
+        since: Option<String>,
+        /// Print the events as JSON lines.
+        #[clap(long)]
+        json: bool,
+    },
+    /// Prints what this build supports as JSON: its version, subcommands and the
+    /// versions of each file format, for programs that drive Ryokan.
+    ApiInfo,
+    /// Checks the notes directory and reports problems.
+    Doctor {
+        /// Also fix what can be fixed, e.g. create the notes directory, mark an
+        /// existing one as a vault or resume an interrupted bulk operation.
+        #[clap(long)]
+        fix: bool,
+        /// Undo a bulk operation that was interrupted, e.g. `encrypt-unencrypted`,
+        /// instead of resuming it as `--fix` does.
+        #[clap(long, conflicts_with = "fix")]
+        roll_back: bool,
+    },
+    /// Inspects the configuration.
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manages profiles, separate vaults with their own PIN and notes directory.
+    Profile {
+        #[clap(subcommand)]
+        action: ProfileAction,
+    },
+    /// Manages the plaintext description of the vault, readable without the PIN.
+    Vault {
+        #[clap(subcommand)]
+        action: VaultAction,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum BundleAction {
+    /// Packs every note into a bundle encrypted with a one-time passphrase.
+    Export {
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Merges the notes of a bundle into the vault, keeping the newest version of
+    /// notes present on both sides.
+    Import { path: PathBuf },
+}
+
+#[derive(Parser, Debug)]
+pub enum TagAction {
+    /// Prints the tags in use as a tree, with how many notes are below each.
+    List,
+    /// Renames a tag on every note, along with the tags below it.
+    Rename { from: String, to: String },
+}
+
+#[derive(Parser, Debug)]
+pub enum MetaAction {
+    /// Writes uuid, title, tags (joined with `;`), created, updated, due,
+    /// color, read_only and slot columns for every note.
+    Export {
+        /// The CSV file to write, instead of stdout.
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// Applies the titles, tags, due dates, colors, read-only flags and slots
+    /// of a CSV file written by `meta export`, after showing what changes.
+    Import {
+        path: PathBuf,
+        /// Apply the changes without asking.
+        #[clap(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum ProfileAction {
+    /// Lists the configured profiles.
+    List,
+    /// Adds a profile. Its PIN is set the first time it's used.
+    Add {
+        name: String,
+        /// The notes directory of the profile. Defaults to `profiles/<name>/notes`
+        /// next to the config file.
+        #[clap(long)]
+        notes_dir: Option<PathBuf>,
+    },
+    /// Removes a profile from the config. Its notes are left on disk.
+    Remove { name: String },
+}
+
+#[derive(Parser, Debug)]
+pub enum BackupAction {
+    /// Prints each snapshot, oldest first: its name, when it was taken and how
+    /// many files and bytes it holds.
+    List,
+}
+
+#[derive(Parser, Debug)]
+pub enum VaultAction {
+    /// Sets the description shown by `profile list` and `config show`. It's
+    /// stored unencrypted in `vault.toml`.
+    Describe { description: String },
+}
+
+#[derive(Parser, Debug)]
+pub enum ConfigAction {
+    /// Prints every effective setting and where it came from.
+    Show,
+    /// Prints where the config, the notes and what Ryokan records as it runs
+    /// are kept.
+    Paths,
+    /// Sets a value in the config file.
+    Set {
+        #[clap(value_enum)]
+        key: ConfigKey,
+        value: String,
+    },
+}
+
+/// Which notes `list` and `export` take.
+#[derive(clap::Args, Debug)]
+pub struct NoteFilter {
+    /// Only notes updated on or after this day, as `YYYY-MM-DD`, `today` or
+    /// `yesterday` in local time.
+    #[clap(long, value_parser = start_of_day)]
+    since: Option<DateTime<Utc>>,
+    /// Only notes updated on or before this day.
+    #[clap(long, value_parser = end_of_day)]
+    until: Option<DateTime<Utc>>,
+    /// Only notes with this tag, or a tag below it such as `project/home` for
+    /// `project`. Given more than once, notes need every one.
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+    /// Only notes of this notebook. Notebooks are tags in Ryokan, as for
+    /// imported notebooks, so this is the same as `--tag`.
+    #[clap(long = "notebook")]
+    notebooks: Vec<String>,
+    /// Only notes with at least one of the tags given this way.
+    #[clap(long = "any-tag")]
+    any_tags: Vec<String>,
+    /// Only notes with this color label, or `none` for those without one.
+    #[clap(long, value_parser = color_label)]
+    color: Option<String>,
+    /// Only notes of this type: plain, markdown, todo, journal, or other for
+    /// those of a type a newer version added.
+    #[clap(long = "type", value_parser = note_type)]
+    note_type: Option<NoteType>,
+}
+
+impl NoteFilter {
+    /// The query of every note passing the filter, in the default order.
+    pub fn query(self) -> ListQuery {
+        ListQuery {
+            since: self.since,
+            until: self.until,
+            tags: self.tags.into_iter().chain(self.notebooks).collect(),
+            any_tags: self.any_tags,
+            color: self.color,
+            note_type: self.note_type,
+            ..ListQuery::default()
+        }
+    }
+}
+
+/// How `list` prints the notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// A table with dates, sizes and tags, for people.
+    Table,
+    /// Only the titles, e.g. to pipe into fzf.
+    Plain,
+    /// A JSON array of the notes with their metadata.
+    Json,
+    /// `uuid<TAB>title` lines, which won't change, for scripts.
+    Tsv,
+}
+
+/// Config file keys that can be changed with `config set`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConfigKey {
+    Editor,
+    EditorCursorArg,
+    Picker,
+    NotesDir,
+    RecordStats,
+    ListSnippets,
+    HistoryVersions,
+    TrashRetentionDays,
+    CacheMaxBytes,
+    Inbox,
+    JournalTitle,
+    JournalTemplate,
+    JournalNotebook,
+    ReadOnlyMode,
+    NotesDirMustExist,
+    PreviewMaxBytes,
+    PreviewDecryptLimit,
+    PreviewHeader,
+    PollIntervalMs,
+    LazyLoadAbove,
+    MaxNoteSize,
+    MaxTitleLength,
+    ParanoidWrites,
+    EncryptConfirmFiles,
+    EncryptConfirmBytes,
+    WarnDuplicateTitles,
+    DateFormat,
+    AuditLog,
+    EncryptAuditLog,
+    RekeyReminderDays,
+    HighContrast,
+    Language,
+    PrivacyMasksTitles,
+    DesktopNotifications,
+    NewNoteTitle,
+    CaptureHeader,
+}
+
+/// Checks a color label given on the command line, keeping `none` as is.
+fn color_label(name: &str) -> Result<String, AppError> {
+    metadata::parse_color(name).map(|color| color.unwrap_or_else(|| "none".to_string()))
+}
+
+fn note_type(name: &str) -> Result<NoteType, AppError> {
+    NoteType::parse(name)
+}
+
+/// Parses a `--since` day as its first instant in local time.
+fn start_of_day(day: &str) -> Result<DateTime<Utc>, AppError> {
+    datetime::parse_day(day, &Local::now(), false)
+}
+
+/// Parses an `--until` day as the first instant after it in local time.
+fn end_of_day(day: &str) -> Result<DateTime<Utc>, AppError> {
+    datetime::parse_day(day, &Local::now(), true)
+}
+
+impl Subcommands {
+    /// Whether running this changes the notes, which read-only mode refuses.
+    pub fn changes_notes(&self) -> bool {
+        matches!(
+            self,
+            Subcommands::EncryptUnencrypted { dry_run: false, .. }
+                | Subcommands::Rekey
+                | Subcommands::Import { dry_run: false, .. }
+                | Subcommands::Bundle {
+                    action: BundleAction::Import { .. }
+                }
+                | Subcommands::Sync
+                | Subcommands::Vault { .. }
+                | Subcommands::Reindex
+                | Subcommands::Touch { .. }
+                | Subcommands::New { .. }
+                | Subcommands::Edit { .. }
+                | Subcommands::Pick { and_edit: true }
+                | Subcommands::Quick { .. }
+                | Subcommands::Today { .. }
+                | Subcommands::Append { .. }
+                | Subcommands::Prepend { .. }
+                | Subcommands::Doctor { fix: true, .. }
+                | Subcommands::Doctor {
+                    roll_back: true,
+                    ..
+                }
+                | Subcommands::Dedupe { dry_run: false, .. }
+                | Subcommands::Gc { apply: true }
+                | Subcommands::Restore { .. }
+                | Subcommands::Tag {
+                    action: TagAction::Rename { .. }
+                }
+        )
+    }
+}
+
+impl Args {
+    /// The settings given explicitly on the command line.
+    pub fn flags(&self) -> Flags {
+        Flags {
+            config_file: self.config_file.clone(),
+            notes_dir: self.notes_dir.clone(),
+            editor: self.editor.clone(),
+            profile: self.profile.clone(),
+            read_only_mode: self.read_only,
+        }
+    }
 }
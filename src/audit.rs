@@ -0,0 +1,244 @@
+//! An append-only record of what happened to a vault, e.g. notes created or
+//! deleted, PIN failures and exports, for peace of mind.
+//!
+//! Events are JSON lines holding a timestamp, the kind of event and the UUID of
+//! the note, never its title or content. With encryption enabled, each line is
+//! sealed with a key derived from the PIN instead, except failed PIN attempts,
+//! which are recorded before any PIN is known.
+
+use crate::error::AppError;
+use crate::note::NoteKey;
+use crate::permissions;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Name of the audit log next to the config file, or in the directory of a profile.
+pub const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    Edited,
+    /// The title of the note changed.
+    Renamed,
+    /// Anything else in the metadata changed, e.g. tags.
+    MetadataChanged,
+    /// The note was created or replaced by one from elsewhere, e.g. a bundle.
+    Imported,
+    /// A previous version became the current content, or the note came back
+    /// from the trash.
+    Restored,
+    /// A conflict copy was kept or thrown away.
+    ConflictResolved,
+    Trashed,
+    Deleted,
+    PinSet,
+    PinFailed,
+    /// Notes were written out of the vault in plaintext or in a bundle.
+    Exported,
+    /// Every note was encrypted again, with fresh salts and nonces.
+    Rekeyed,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EventKind::Created => "created",
+            EventKind::Edited => "edited",
+            EventKind::Renamed => "renamed",
+            EventKind::MetadataChanged => "metadata changed",
+            EventKind::Imported => "imported",
+            EventKind::Restored => "restored",
+            EventKind::ConflictResolved => "conflict resolved",
+            EventKind::Trashed => "trashed",
+            EventKind::Deleted => "deleted",
+            EventKind::PinSet => "PIN set",
+            EventKind::PinFailed => "PIN failed",
+            EventKind::Exported => "exported",
+            EventKind::Rekeyed => "rekeyed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub at: DateTime<Utc>,
+    pub kind: EventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// A short detail, such as the version restored. Never note content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Event {
+    /// An event happening now, to the note `uuid` if any.
+    pub fn new(kind: EventKind, uuid: Option<&str>) -> Self {
+        Self {
+            at: Utc::now(),
+            kind,
+            uuid: uuid.map(str::to_string),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Whether the event is about the note `uuid` and happened at or after
+    /// `since`, for whichever is given.
+    pub fn matches(&self, uuid: Option<&str>, since: Option<&DateTime<Utc>>) -> bool {
+        uuid.is_none_or(|uuid| self.uuid.as_deref() == Some(uuid))
+            && since.is_none_or(|since| self.at >= *since)
+    }
+}
+
+/// A line as stored: an event, or an event sealed with the PIN.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    /// Hex of the encrypted JSON of the event.
+    Sealed {
+        sealed: String,
+    },
+    Plain(Event),
+}
+
+/// The audit log of a vault, appended to by [`Vault`](crate::vault::Vault) and
+/// the frontends.
+pub struct AuditLog {
+    path: PathBuf,
+    encrypt: bool,
+    /// The key lines are sealed with, so each line doesn't run Argon2.
+    key: Mutex<Option<NoteKey>>,
+}
+
+impl AuditLog {
+    /// The log in the file `path`, sealing events when `encrypt` is set and a
+    /// PIN is given.
+    pub fn new(path: impl Into<PathBuf>, encrypt: bool) -> Self {
+        Self {
+            path: path.into(),
+            encrypt,
+            key: Mutex::new(None),
+        }
+    }
+
+    fn key_cache(&self) -> MutexGuard<'_, Option<NoteKey>> {
+        self.key
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Appends `event`, sealed with `pin` if encryption is enabled.
+    ///
+    /// What it records already happened, so failing is only logged.
+    pub fn record(&self, event: &Event, pin: Option<&str>) {
+        if let Err(e) = self.append(event, pin) {
+            warn!("Could not write to the audit log: {e}");
+        }
+    }
+
+    fn append(&self, event: &Event, pin: Option<&str>) -> Result<(), AppError> {
+        let json = serde_json::to_vec(event)
+            .map_err(|e| AppError::Config(format!("Could not serialize the event: {e}")))?;
+        let line = match pin.filter(|_| self.encrypt) {
+            Some(pin) => {
+                let mut cache = self.key_cache();
+                let key = match cache.take() {
+                    Some(key) => key,
+                    None => NoteKey::new(pin)?,
+                };
+                let sealed = key.encrypt(&json);
+                *cache = Some(key);
+                Line::Sealed {
+                    sealed: to_hex(&sealed?),
+                }
+            }
+            None => Line::Plain(event.clone()),
+        };
+        let mut line = serde_json::to_vec(&line)
+            .map_err(|e| AppError::Config(format!("Could not serialize the event: {e}")))?;
+        line.push(b'\n');
+
+        if let Some(parent) = self.path.parent() {
+            permissions::create_private_dir(parent)?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        // A single write, so concurrent processes don't interleave lines
+        options.open(&self.path)?.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every event in the log, oldest first. Sealed events need `pin`.
+    pub fn read(&self, pin: Option<&str>) -> Result<Vec<Event>, AppError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut key: Option<NoteKey> = None;
+        let mut events = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let unreadable = |e: &dyn fmt::Display| {
+                AppError::Config(format!("Line {} of the audit log: {e}", i + 1))
+            };
+            let event = match serde_json::from_str(line).map_err(|e| unreadable(&e))? {
+                Line::Plain(event) => event,
+                Line::Sealed { sealed } => {
+                    let pin = pin.ok_or_else(|| unreadable(&"it's encrypted"))?;
+                    let sealed = from_hex(&sealed).ok_or_else(|| unreadable(&"invalid hex"))?;
+                    let current = match key.take() {
+                        Some(known) if known.matches(&sealed) => known,
+                        _ => NoteKey::of(&sealed, pin)?,
+                    };
+                    let json = current.decrypt(&sealed).map_err(|e| unreadable(&e))?;
+                    key = Some(current);
+                    serde_json::from_slice(&json).map_err(|e| unreadable(&e))?
+                }
+            };
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod audit_test;
@@ -0,0 +1,68 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_events_are_appended_as_json_lines() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let log = AuditLog::new(dir.path().join("state").join(AUDIT_FILE_NAME), false);
+    assert!(log.read(None)?.is_empty());
+
+    let created = Event::new(EventKind::Created, Some("a"));
+    let exported = Event::new(EventKind::Exported, None).with_detail("bundle of 1 note(s)");
+    log.record(&created, Some("123456"));
+    log.record(&exported, None);
+
+    let content = fs::read_to_string(dir.path().join("state").join(AUDIT_FILE_NAME))?;
+    assert_eq!(content.lines().count(), 2);
+    assert!(content.starts_with(r#"{"at":"#));
+    assert!(content.contains(r#""kind":"created","uuid":"a"}"#));
+    assert_eq!(log.read(None)?, [created.clone(), exported.clone()]);
+
+    assert!(created.matches(Some("a"), None));
+    assert!(!exported.matches(Some("a"), None));
+    assert!(exported.matches(None, Some(&created.at)));
+    assert!(!created.matches(None, Some(&(exported.at + chrono::TimeDelta::seconds(1)))));
+    Ok(())
+}
+
+#[test]
+fn test_sealed_events_need_the_pin() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let path = dir.path().join(AUDIT_FILE_NAME);
+    let log = AuditLog::new(&path, true);
+
+    let created = Event::new(EventKind::Created, Some("a"));
+    let edited = Event::new(EventKind::Edited, Some("a"));
+    // Recorded before any PIN is known
+    let failed = Event::new(EventKind::PinFailed, None);
+    log.record(&created, Some("123456"));
+    log.record(&failed, None);
+    log.record(&edited, Some("123456"));
+
+    let content = fs::read_to_string(&path)?;
+    assert!(!content.contains("created"));
+    assert!(content.contains("pin_failed"));
+    assert_eq!(log.read(Some("123456"))?, [created, failed, edited]);
+    assert!(matches!(log.read(None), Err(AppError::Config(_))));
+    assert!(log.read(Some("654321")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_line_is_reported_with_its_number() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let path = dir.path().join(AUDIT_FILE_NAME);
+    let log = AuditLog::new(&path, false);
+    log.record(&Event::new(EventKind::Deleted, Some("a")), None);
+    let mut file = OpenOptions::new().append(true).open(&path)?;
+    file.write_all(b"{\"at\":\n")?;
+
+    let read = log.read(None);
+    assert!(
+        matches!(&read, Err(AppError::Config(message)) if message.starts_with("Line 2 ")),
+        "{read:?}"
+    );
+    Ok(())
+}
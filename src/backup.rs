@@ -0,0 +1,337 @@
+//! Snapshots of the notes directory, taken on a schedule so a mistake can be
+//! undone with `ryokan restore --from-snapshot`.
+//!
+//! A snapshot is a directory named after the time it was taken, in UTC, e.g.
+//! `20261017T093000Z`, holding the files of the notes directory as they were.
+//! The notes are already encrypted, so files are hard-linked where the file
+//! system allows and copied otherwise: Ryokan replaces files rather than
+//! writing into them, so a linked file keeps the content it had. A snapshot is
+//! built under a `.partial` name and renamed once complete, so an interrupted
+//! one is never listed or restored. The Git repository of syncing, the lock
+//! file and temporary files, such as the decrypted copy of a note being edited,
+//! are left out.
+//!
+//! Taking a snapshot doesn't hold the vault lock, so it never keeps the TUI
+//! waiting; a note saved meanwhile may be taken as it was before or after.
+
+use crate::error::AppError;
+use crate::gc::TEMP_FILE_PREFIX;
+use crate::lock::{LOCK_FILE_NAME, VaultLock};
+use crate::permissions;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The `keep_last` used when none is configured.
+pub const DEFAULT_KEEP_LAST: usize = 10;
+
+/// The `min_interval_hours` used when none is configured.
+pub const DEFAULT_MIN_INTERVAL_HOURS: u64 = 24;
+
+/// Format of the names of snapshots.
+const NAME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Appended to the name of a snapshot until it's complete.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// How old an unfinished snapshot has to be to be taken for an interrupted one,
+/// rather than one another process is taking.
+const PARTIAL_GRACE: Duration = Duration::hours(1);
+
+/// Left out of snapshots, at the root of the notes directory.
+const GIT_DIR: &str = ".git";
+
+/// The `[backup]` section of the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Take snapshots on a schedule; the section alone turns them on.
+    pub enabled: bool,
+    /// Where snapshots go; `backups` in the state directory when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// How many snapshots to keep; 0 keeps them all.
+    pub keep_last: usize,
+    /// A snapshot is only taken once the last one is older than this.
+    pub min_interval_hours: u64,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: None,
+            keep_last: DEFAULT_KEEP_LAST,
+            min_interval_hours: DEFAULT_MIN_INTERVAL_HOURS,
+        }
+    }
+}
+
+/// A complete snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The time it was taken, as `restore --from-snapshot` takes it.
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    /// The number of files in it and their total size in bytes, counting linked
+    /// files as if they were copies.
+    pub fn size(&self) -> Result<(usize, u64), AppError> {
+        let files = files(&self.path)?;
+        let mut bytes = 0;
+        for file in &files {
+            bytes += fs::metadata(self.path.join(file))?.len();
+        }
+        Ok((files.len(), bytes))
+    }
+}
+
+/// The snapshots of one vault, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Backups {
+    dir: PathBuf,
+    keep_last: usize,
+    min_interval: Duration,
+}
+
+impl Backups {
+    /// Snapshots in `dir`, keeping the last `keep_last` or all of them with 0,
+    /// and taken on a schedule at most once per `min_interval`.
+    pub fn new(dir: PathBuf, keep_last: usize, min_interval: Duration) -> Self {
+        Self {
+            dir,
+            keep_last,
+            min_interval,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The complete snapshots, oldest first.
+    pub fn list(&self) -> Result<Vec<Snapshot>, AppError> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(AppError::Io(e)),
+        };
+        let mut snapshots = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let Ok(taken_at) = NaiveDateTime::parse_from_str(&name, NAME_FORMAT) else {
+                continue;
+            };
+            if entry.file_type()?.is_dir() {
+                snapshots.push(Snapshot {
+                    name,
+                    taken_at: taken_at.and_utc(),
+                    path: entry.path(),
+                });
+            }
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.taken_at);
+        Ok(snapshots)
+    }
+
+    /// The complete snapshot called `name`.
+    pub fn find(&self, name: &str) -> Result<Snapshot, AppError> {
+        self.list()?
+            .into_iter()
+            .find(|snapshot| snapshot.name == name)
+            .ok_or_else(|| {
+                AppError::Backup(format!(
+                    "There's no snapshot {name} in {}, see `ryokan backup list`",
+                    self.dir.display()
+                ))
+            })
+    }
+
+    /// Takes a snapshot of `notes_dir` if the last one was taken
+    /// `min_interval` or more before `now`, then deletes the oldest beyond
+    /// `keep_last`.
+    pub fn take_if_due(
+        &self,
+        notes_dir: &Path,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Snapshot>, AppError> {
+        if self
+            .list()?
+            .last()
+            .is_some_and(|last| now - last.taken_at < self.min_interval)
+        {
+            return Ok(None);
+        }
+        let snapshot = self.snapshot(notes_dir, now)?;
+        self.prune(now)?;
+        Ok(Some(snapshot))
+    }
+
+    /// Puts the files of `notes_dir` back as they were in `snapshot`, removing
+    /// those it didn't have, and returns the snapshot of how they were before,
+    /// which undoes it. Holds the vault lock.
+    pub fn restore(
+        &self,
+        snapshot: &Snapshot,
+        notes_dir: &Path,
+        now: DateTime<Utc>,
+    ) -> Result<Snapshot, AppError> {
+        let _lock = VaultLock::acquire(Some(notes_dir))?;
+        let before = self.snapshot(notes_dir, now)?;
+        let restored: BTreeSet<PathBuf> = files(&snapshot.path)?.into_iter().collect();
+        for file in files(notes_dir)? {
+            if !restored.contains(&file) {
+                fs::remove_file(notes_dir.join(&file))?;
+            }
+        }
+        for file in &restored {
+            let target = notes_dir.join(file);
+            let mut temp_name = TEMP_FILE_PREFIX.to_string();
+            temp_name.push_str("-restore-");
+            temp_name.push_str(&file.file_name().unwrap_or_default().to_string_lossy());
+            let temp = target.with_file_name(temp_name);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_file(&temp);
+            link_or_copy(&snapshot.path.join(file), &temp)?;
+            fs::rename(&temp, &target)?;
+            // Left behind if the target already was this very file
+            let _ = fs::remove_file(&temp);
+        }
+        info!(
+            "Restored snapshot {} into {}",
+            snapshot.name,
+            notes_dir.display()
+        );
+        // Only now, so the snapshot restored isn't the one deleted
+        self.prune(now)?;
+        Ok(before)
+    }
+
+    /// Copies the files of `notes_dir` into a new snapshot taken at `now`.
+    fn snapshot(&self, notes_dir: &Path, now: DateTime<Utc>) -> Result<Snapshot, AppError> {
+        permissions::create_private_dir(&self.dir)?;
+        if fs::canonicalize(&self.dir)?.starts_with(fs::canonicalize(notes_dir)?) {
+            return Err(AppError::Backup(format!(
+                "The backup directory {} can't be in the notes directory",
+                self.dir.display()
+            )));
+        }
+        let name = now.format(NAME_FORMAT).to_string();
+        let path = self.dir.join(&name);
+        let snapshot = Snapshot {
+            name,
+            taken_at: now,
+            path,
+        };
+        // Taken within the same second
+        if snapshot.path.is_dir() {
+            return Ok(snapshot);
+        }
+        let partial = self.dir.join(format!("{}{PARTIAL_SUFFIX}", snapshot.name));
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        for file in files(notes_dir)? {
+            let target = partial.join(&file);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match link_or_copy(&notes_dir.join(&file), &target) {
+                // Deleted since it was listed
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        fs::create_dir_all(&partial)?;
+        fs::rename(&partial, &snapshot.path)?;
+        info!("Took snapshot {}", snapshot.path.display());
+        Ok(snapshot)
+    }
+
+    /// Deletes the oldest snapshots beyond `keep_last`, and unfinished ones
+    /// older than [`PARTIAL_GRACE`].
+    fn prune(&self, now: DateTime<Utc>) -> Result<(), AppError> {
+        let snapshots = self.list()?;
+        if self.keep_last > 0 {
+            let extra = snapshots.len().saturating_sub(self.keep_last);
+            for snapshot in &snapshots[..extra] {
+                fs::remove_dir_all(&snapshot.path)?;
+                info!("Deleted snapshot {}", snapshot.path.display());
+            }
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let modified = entry.metadata()?.modified()?;
+            if entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(PARTIAL_SUFFIX)
+                && now - DateTime::<Utc>::from(modified) > PARTIAL_GRACE
+            {
+                warn!(
+                    "Deleting the unfinished snapshot {}",
+                    entry.path().display()
+                );
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The files a snapshot of `root` holds, relative to it and sorted.
+fn files(root: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let at_root = dir.as_os_str().is_empty();
+        for entry in fs::read_dir(root.join(&dir))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if !(at_root && name == GIT_DIR) {
+                    dirs.push(dir.join(&name));
+                }
+            } else if file_type.is_file()
+                && !name.to_string_lossy().starts_with(TEMP_FILE_PREFIX)
+                && !(at_root && name == LOCK_FILE_NAME)
+            {
+                files.push(dir.join(&name));
+            }
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// Hard-links `from` to `to`, or copies it where that's not possible, e.g.
+/// across drives.
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    // The integrity checks go by modification times
+    let modified = fs::metadata(from)?.modified()?;
+    fs::File::options()
+        .write(true)
+        .open(to)?
+        .set_modified(modified)
+}
+
+#[cfg(test)]
+mod backup_test;
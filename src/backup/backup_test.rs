@@ -0,0 +1,138 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+fn at(hours: i64) -> DateTime<Utc> {
+    DateTime::UNIX_EPOCH + Duration::days(20_000) + Duration::hours(hours)
+}
+
+fn backups(dir: &Path, keep_last: usize) -> Backups {
+    Backups::new(dir.join("backups"), keep_last, Duration::hours(24))
+}
+
+/// Replaces `path` as Ryokan does, leaving files linked to it alone.
+fn replace(path: &Path, content: &str) -> Result<(), AppError> {
+    let temp = path.with_extension("new");
+    fs::write(&temp, content)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+/// A notes directory with a note, its history, and what snapshots leave out.
+fn notes(dir: &Path) -> Result<PathBuf, AppError> {
+    let notes = dir.join("notes");
+    fs::create_dir_all(notes.join("history/1234"))?;
+    fs::create_dir_all(notes.join(".git"))?;
+    fs::write(notes.join("1234.enc"), "RYOKAN_ENCRYPTED note")?;
+    fs::write(notes.join("1234.meta"), "title")?;
+    fs::write(notes.join("history/1234/1.enc"), "RYOKAN_ENCRYPTED old")?;
+    fs::write(notes.join(".git/HEAD"), "ref: refs/heads/main")?;
+    fs::write(notes.join(LOCK_FILE_NAME), "")?;
+    fs::write(notes.join(".tmpAbC123"), "plaintext being edited")?;
+    Ok(notes)
+}
+
+#[test]
+fn test_snapshots_hold_the_vault_files() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = notes(dir.path())?;
+    let backups = backups(dir.path(), 10);
+
+    let snapshot = backups.take_if_due(&notes, at(0))?;
+
+    let Some(snapshot) = snapshot else {
+        panic!("no snapshot was taken");
+    };
+    assert_eq!(snapshot.taken_at, at(0));
+    assert_eq!(
+        files(&snapshot.path)?,
+        [
+            PathBuf::from("1234.enc"),
+            PathBuf::from("1234.meta"),
+            Path::new("history").join("1234").join("1.enc"),
+        ]
+    );
+    assert_eq!(snapshot.size()?, (3, 46));
+    assert_eq!(backups.list()?, [snapshot]);
+    Ok(())
+}
+
+#[test]
+fn test_snapshots_follow_the_interval_and_retention() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = notes(dir.path())?;
+    let backups = backups(dir.path(), 2);
+    // Interrupted, or being taken by another process
+    let partial = backups
+        .dir()
+        .join(format!("19000101T000000Z{PARTIAL_SUFFIX}"));
+    fs::create_dir_all(&partial)?;
+
+    assert!(backups.take_if_due(&notes, at(0))?.is_some());
+    assert_eq!(backups.take_if_due(&notes, at(23))?, None);
+    assert!(backups.take_if_due(&notes, at(24))?.is_some());
+    assert!(backups.take_if_due(&notes, at(48))?.is_some());
+
+    let taken: Vec<_> = backups
+        .list()?
+        .into_iter()
+        .map(|snapshot| snapshot.taken_at)
+        .collect();
+    assert_eq!(taken, [at(24), at(48)]);
+    // Its modification time is now, not decades ago
+    assert!(partial.exists());
+    backups.take_if_due(&notes, Utc::now() + Duration::hours(2))?;
+    assert!(!partial.exists());
+    Ok(())
+}
+
+#[test]
+fn test_restoring_puts_the_files_back_and_can_be_undone() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = notes(dir.path())?;
+    let backups = backups(dir.path(), 10);
+    let Some(snapshot) = backups.take_if_due(&notes, at(0))? else {
+        panic!("no snapshot was taken");
+    };
+    replace(&notes.join("1234.enc"), "RYOKAN_ENCRYPTED changed")?;
+    fs::remove_file(notes.join("history/1234/1.enc"))?;
+    fs::write(notes.join("5678.enc"), "RYOKAN_ENCRYPTED new")?;
+
+    let before = backups.restore(&backups.find(&snapshot.name)?, &notes, at(1))?;
+
+    assert_eq!(
+        fs::read_to_string(notes.join("1234.enc"))?,
+        "RYOKAN_ENCRYPTED note"
+    );
+    assert!(notes.join("history/1234/1.enc").exists());
+    assert!(!notes.join("5678.enc").exists());
+    assert!(notes.join(".git/HEAD").exists());
+    assert_eq!(files(&notes)?, files(&snapshot.path)?);
+    assert_eq!(
+        fs::read_to_string(before.path.join("5678.enc"))?,
+        "RYOKAN_ENCRYPTED new"
+    );
+
+    backups.restore(&before, &notes, at(2))?;
+    assert_eq!(
+        fs::read_to_string(notes.join("1234.enc"))?,
+        "RYOKAN_ENCRYPTED changed"
+    );
+    assert!(notes.join("5678.enc").exists());
+    Ok(())
+}
+
+#[test]
+fn test_unknown_snapshots_and_nested_directories_are_refused() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = notes(dir.path())?;
+
+    let result = backups(dir.path(), 10).find("20990101T000000Z");
+    assert!(matches!(result, Err(AppError::Backup(_))), "{result:?}");
+
+    let inside = Backups::new(notes.join("backups"), 10, Duration::hours(24));
+    let result = inside.take_if_due(&notes, at(0));
+    assert!(matches!(result, Err(AppError::Backup(_))), "{result:?}");
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Portable encrypted bundles, for moving a vault to another machine.
+//!
+//! A bundle holds the plaintext of every note with its metadata, encrypted as a
+//! whole under a passphrase chosen for the transfer, so it doesn't depend on
+//! the PIN of either machine. Its layout is:
+//!
+//! - the magic bytes `RYOKAN_BUNDLE` and a format version byte,
+//! - the Argon2 memory cost, iterations and lanes used to derive the key, as
+//!   big-endian `u32`s, so a build with other parameters still opens it,
+//! - the payload encrypted like a note (salt, nonce, AES-256-GCM ciphertext).
+//!
+//! The GCM tag authenticates the payload, so a corrupted or truncated bundle
+//! fails to open before anything is written to the vault.
+
+use crate::audit::{Event, EventKind};
+use crate::error::AppError;
+use crate::metadata::NoteMetadata;
+use crate::note::{self, NoteKey};
+use crate::pin::{ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM};
+use crate::vault::Vault;
+use log::{error, info};
+use zeroize::Zeroizing;
+
+const BUNDLE_MAGIC: &[u8] = b"RYOKAN_BUNDLE";
+/// Version of the bundle format written by this build.
+pub const BUNDLE_VERSION: u8 = 1;
+const KDF_PARAMS: [u32; 3] = [ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM];
+/// The most memory, in KiB, a bundle may ask Argon2 for, so a crafted one
+/// can't exhaust it.
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// A note as carried in a bundle.
+pub struct BundledNote {
+    pub uuid: String,
+    pub metadata: NoteMetadata,
+    pub content: Zeroizing<Vec<u8>>,
+}
+
+/// What [`import`] did with each note of a bundle, by UUID.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Notes that didn't exist locally.
+    pub added: Vec<String>,
+    /// Notes that differed and were replaced by the newer bundled version.
+    pub replaced: Vec<String>,
+    /// Notes that differed but whose local version was newer, so it was kept.
+    pub kept_local: Vec<String>,
+    /// Notes identical on both sides.
+    pub unchanged: Vec<String>,
+    /// Notes that differed but were left as they were locally because they're
+    /// read-only.
+    pub skipped_read_only: Vec<String>,
+    /// Notes that couldn't be merged, with the reason. They're left as they
+    /// were locally.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Packs every note of `vault` into a bundle encrypted with `passphrase`.
+pub fn export(vault: &Vault, passphrase: &str) -> Result<Vec<u8>, AppError> {
+    let mut payload = Zeroizing::new(Vec::new());
+    let notes = vault.list()?;
+    for note in &notes {
+        let content = Zeroizing::new(vault.read(&note.uuid)?);
+        let metadata = Zeroizing::new(toml::to_string(&note.metadata)?);
+        push_field(&mut payload, note.uuid.as_bytes());
+        push_field(&mut payload, metadata.as_bytes());
+        push_field(&mut payload, &content);
+    }
+    info!("Bundling {} note(s)", notes.len());
+
+    let mut bundle = [BUNDLE_MAGIC, &[BUNDLE_VERSION]].concat();
+    for param in KDF_PARAMS {
+        bundle.extend_from_slice(&param.to_be_bytes());
+    }
+    bundle.extend(note::encrypt_note_content(&payload, passphrase)?);
+    vault.record(
+        Event::new(EventKind::Exported, None)
+            .with_detail(format!("bundle of {} note(s)", notes.len())),
+    );
+    Ok(bundle)
+}
+
+/// Decrypts and parses a bundle, without touching any vault.
+pub fn open(bundle: &[u8], passphrase: &str) -> Result<Vec<BundledNote>, AppError> {
+    let mut reader = Reader(bundle);
+    if reader.take(BUNDLE_MAGIC.len())? != BUNDLE_MAGIC {
+        return Err(AppError::Bundle("Not a Ryokan bundle".to_string()));
+    }
+    let version = reader.take(1)?[0];
+    if version != BUNDLE_VERSION {
+        return Err(AppError::Bundle(format!(
+            "Unsupported bundle version {version}, this build reads version {BUNDLE_VERSION}"
+        )));
+    }
+    let params = [reader.u32()?, reader.u32()?, reader.u32()?];
+    if params[0] > MAX_KDF_MEMORY_KIB {
+        return Err(AppError::Bundle(format!(
+            "The bundle asks for {} MiB to derive its key, more than the {} MiB allowed",
+            params[0] / 1024,
+            MAX_KDF_MEMORY_KIB / 1024
+        )));
+    }
+
+    let payload = Zeroizing::new(
+        NoteKey::of_with_params(reader.0, passphrase, params)
+            .and_then(|key| key.decrypt(reader.0))
+            .map_err(|_| {
+                AppError::Bundle("Wrong passphrase, or the bundle is corrupted".to_string())
+            })?,
+    );
+
+    let mut reader = Reader(&payload);
+    let mut notes = Vec::new();
+    while !reader.0.is_empty() {
+        let uuid = String::from_utf8(reader.field()?.to_vec())
+            .ok()
+            .filter(|uuid| uuid::Uuid::parse_str(uuid).is_ok())
+            .ok_or_else(|| AppError::Bundle("Invalid note UUID in bundle".to_string()))?;
+        let metadata = std::str::from_utf8(reader.field()?)
+            .map_err(|_| AppError::Bundle(format!("Invalid metadata for {uuid}")))?;
+        let metadata = toml::from_str(metadata)?;
+        let content = Zeroizing::new(reader.field()?.to_vec());
+        notes.push(BundledNote {
+            uuid,
+            metadata,
+            content,
+        });
+    }
+    Ok(notes)
+}
+
+/// Merges bundled notes into `vault`, keeping whichever side was updated last.
+///
+/// The vault lock is held throughout. A note that fails is recorded in
+/// [`ImportReport::failed`] and the others are still merged.
+pub fn import(vault: &Vault, notes: &[BundledNote]) -> Result<ImportReport, AppError> {
+    let _lock = vault.lock()?;
+    let mut report = ImportReport::default();
+    for bundled in notes {
+        if let Err(e) = merge(vault, bundled, &mut report) {
+            error!("Failed to import {} from the bundle: {e}", bundled.uuid);
+            report.failed.push((bundled.uuid.clone(), e.to_string()));
+        }
+    }
+    Ok(report)
+}
+
+/// Merges one bundled note, recording in `report` what was done with it.
+fn merge(vault: &Vault, bundled: &BundledNote, report: &mut ImportReport) -> Result<(), AppError> {
+    let uuid = bundled.uuid.clone();
+    let local = match vault.get(&uuid) {
+        Ok(local) => local,
+        Err(AppError::NoteNotFound(_)) => {
+            vault.import_note(&uuid, &bundled.metadata, &bundled.content)?;
+            report.added.push(uuid);
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    let local_content = Zeroizing::new(vault.read(&uuid)?);
+    if *local_content == *bundled.content && local.metadata == bundled.metadata {
+        report.unchanged.push(uuid);
+    } else if local.metadata.read_only {
+        report.skipped_read_only.push(uuid);
+    } else if bundled.metadata.updated_at > local.metadata.updated_at {
+        vault.import_note(&uuid, &bundled.metadata, &bundled.content)?;
+        report.replaced.push(uuid);
+    } else {
+        report.kept_local.push(uuid);
+    }
+    Ok(())
+}
+
+/// Appends a length-prefixed field.
+fn push_field(buffer: &mut Vec<u8>, field: &[u8]) {
+    buffer.extend_from_slice(&(field.len() as u64).to_be_bytes());
+    buffer.extend_from_slice(field);
+}
+
+/// Reads a bundle front to back.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AppError> {
+        let (taken, rest) = self
+            .0
+            .split_at_checked(len)
+            .ok_or_else(|| AppError::Bundle("The bundle is truncated".to_string()))?;
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, AppError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn field(&mut self) -> Result<&'a [u8], AppError> {
+        let bytes = self.take(8)?;
+        let mut len = [0u8; 8];
+        len.copy_from_slice(bytes);
+        let len = usize::try_from(u64::from_be_bytes(len))
+            .map_err(|_| AppError::Bundle("The bundle is truncated".to_string()))?;
+        self.take(len)
+    }
+}
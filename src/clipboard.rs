@@ -0,0 +1,148 @@
+//! Reading text from the system clipboard, to make a note of it.
+//!
+//! Copying goes through the terminal with OSC 52, but terminals rarely let
+//! programs read the clipboard that way, so it's read with the platform's
+//! paste program instead: `pbpaste` on macOS, PowerShell on Windows, and
+//! `wl-paste`, `xclip` or `xsel` elsewhere, whichever is installed. Nothing is
+//! linked against a clipboard library, and a missing clipboard is an error to
+//! show, never a crash.
+
+use crate::{error::AppError, text};
+use std::{
+    env, io,
+    process::{Command, Stdio},
+};
+use zeroize::Zeroizing;
+
+/// More than this many bytes of clipboard text are only made a note of once
+/// confirmed, in case something else than intended was copied.
+pub const LARGE_PASTE: usize = 256 * 1024;
+
+/// Longest title, in columns, taken from the first line of the clipboard.
+const TITLE_COLUMNS: usize = 60;
+
+/// A program printing the text of the clipboard.
+struct Paster {
+    program: &'static str,
+    args: &'static [&'static str],
+    /// Set in the environment when the program can reach a clipboard.
+    session: Option<&'static str>,
+}
+
+#[cfg(target_os = "macos")]
+const PASTERS: &[Paster] = &[Paster {
+    program: "pbpaste",
+    args: &[],
+    session: None,
+}];
+
+#[cfg(windows)]
+const PASTERS: &[Paster] = &[Paster {
+    program: "powershell",
+    args: &[
+        "-NoProfile",
+        "-Command",
+        "[Console]::OutputEncoding = [Text.Encoding]::UTF8; Get-Clipboard -Raw",
+    ],
+    session: None,
+}];
+
+// Only text types are asked for, so an image on the clipboard gives nothing
+#[cfg(not(any(target_os = "macos", windows)))]
+const PASTERS: &[Paster] = &[
+    Paster {
+        program: "wl-paste",
+        args: &["--no-newline", "--type", "text"],
+        session: Some("WAYLAND_DISPLAY"),
+    },
+    Paster {
+        program: "xclip",
+        args: &["-selection", "clipboard", "-out", "-target", "UTF8_STRING"],
+        session: Some("DISPLAY"),
+    },
+    Paster {
+        program: "xsel",
+        args: &["--clipboard", "--output"],
+        session: Some("DISPLAY"),
+    },
+];
+
+/// The text on the clipboard.
+///
+/// Fails with [`AppError::Clipboard`] if there's no clipboard to read, e.g.
+/// over SSH, if no paste program is installed, or if the clipboard holds
+/// something else than text, like an image.
+pub fn read_text() -> Result<Zeroizing<String>, AppError> {
+    let mut reachable = false;
+    for paster in PASTERS {
+        if paster
+            .session
+            .is_some_and(|var| env::var_os(var).is_none_or(|value| value.is_empty()))
+        {
+            continue;
+        }
+        reachable = true;
+        let output = match Command::new(paster.program)
+            .args(paster.args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // The paste programs fail when there's no text to give
+        if !output.status.success() {
+            return Err(AppError::Clipboard(
+                "the clipboard holds no text".to_string(),
+            ));
+        }
+        return text_from(output.stdout);
+    }
+    Err(AppError::Clipboard(if reachable {
+        format!(
+            "no paste program found, install {}",
+            PASTERS
+                .iter()
+                .map(|paster| paster.program)
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )
+    } else {
+        "no clipboard to read outside a graphical session".to_string()
+    }))
+}
+
+/// `bytes` read from the clipboard, if they're text.
+pub fn text_from(bytes: Vec<u8>) -> Result<Zeroizing<String>, AppError> {
+    let bytes = Zeroizing::new(bytes);
+    let text = std::str::from_utf8(&bytes)
+        .ok()
+        .filter(|text| !text.contains('\0'))
+        .ok_or_else(|| AppError::Clipboard("the clipboard doesn't hold text".to_string()))?;
+    if text.trim().is_empty() {
+        return Err(AppError::Clipboard(
+            "the clipboard holds no text".to_string(),
+        ));
+    }
+    Ok(Zeroizing::new(text.to_string()))
+}
+
+/// A title for a note of `text`: its first line that isn't blank, without
+/// Markdown heading marks, cut to a reasonable length.
+///
+/// ```
+/// assert_eq!(ryokan::clipboard::default_title("\n# Recipe\n\nFlour"), "Recipe");
+/// ```
+pub fn default_title(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    text::ellipsize(line, TITLE_COLUMNS).into_owned()
+}
+
+#[cfg(test)]
+mod clipboard_test;
@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_only_text_is_taken_from_the_clipboard() -> Result<(), AppError> {
+    assert_eq!(*text_from(b"milk\neggs\n".to_vec())?, "milk\neggs\n");
+    // The start of a PNG
+    for not_text in [
+        vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'],
+        b"a\0b".to_vec(),
+        b" \n\t".to_vec(),
+        Vec::new(),
+    ] {
+        assert!(matches!(text_from(not_text), Err(AppError::Clipboard(_))));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_default_titles_come_from_the_first_line() {
+    assert_eq!(default_title("  Groceries  \nmilk"), "Groceries");
+    assert_eq!(default_title("## \n\n### Plans"), "Plans");
+    let long = default_title(&"word ".repeat(40));
+    assert_eq!(text::width(&long), TITLE_COLUMNS);
+    assert!(long.ends_with('…'));
+}
@@ -1,23 +1,182 @@
+//! Loading and saving the Ryokan configuration file.
+
+use crate::audit::AuditLog;
+use crate::backup::{BackupConfig, Backups};
+use crate::datetime::DEFAULT_DATE_FORMAT;
 use crate::error::AppError;
-use log::error;
+use crate::gc::Policy;
+use crate::journal::{DEFAULT_JOURNAL_TITLE, Journal};
+use crate::metadata::DEFAULT_MAX_TITLE_LENGTH;
+use crate::migrate;
+use crate::paths::Paths;
+use crate::permissions;
+use crate::sync::SyncConfig;
+use crate::template::{DEFAULT_CAPTURE_HEADER, DEFAULT_NEW_NOTE_TITLE, Template};
+use chrono::{DateTime, Utc};
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fs,
     io::prelude::*,
     path::{Path, PathBuf},
 };
+use toml_edit::{DocumentMut, Item, Table};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
 const NOTES_FOLDER: &str = "notes";
+const DEFAULT_HISTORY_VERSIONS: usize = 5;
+const DEFAULT_INBOX: &str = "Inbox";
+const DEFAULT_PREVIEW_MAX_BYTES: usize = 16 * 1024;
+pub const DEFAULT_PREVIEW_DECRYPT_LIMIT: u64 = 8 * 1024 * 1024;
+pub const DEFAULT_MAX_NOTE_SIZE: u64 = 256 * 1024 * 1024;
+const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+const DEFAULT_LAZY_LOAD_ABOVE: usize = 2000;
+const DEFAULT_ENCRYPT_CONFIRM_FILES: usize = 200;
+const DEFAULT_ENCRYPT_CONFIRM_BYTES: u64 = 100 * 1024 * 1024;
+const DEFAULT_REKEY_REMINDER_DAYS: u32 = 365;
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+const DEFAULT_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+const DEFAULT_PICKER: &str = "fzf";
+
+/// A separate vault with its own PIN and notes directory, selected with `--profile`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub pin_hash: String,
+    pub notes_dir: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_rekey: Option<DateTime<Utc>>,
+}
+
+impl Profile {
+    /// A profile with no PIN yet, storing its notes in `profiles/<name>/notes`
+    /// next to the config file.
+    pub fn new(name: &str) -> Self {
+        Self {
+            pin_hash: String::new(),
+            notes_dir: format!("profiles/{name}/{NOTES_FOLDER}"),
+            last_rekey: None,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub pin_hash: String,
     pub notes_dir: String,
+    /// The editor command, optionally followed by arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    /// How to tell an editor Ryokan doesn't know where to put the cursor, e.g.
+    /// `+{line}:{column}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor_cursor_arg: Option<String>,
+    /// The fuzzy finder `pick --and-edit` runs, optionally followed by arguments.
+    pub picker: String,
+    /// Whether to store the size and word count of notes in their plaintext metadata.
+    pub record_stats: bool,
+    /// Whether to keep an excerpt of every note, encrypted, in its metadata
+    /// for the TUI list, see [`snippet`](crate::snippet).
+    pub list_snippets: bool,
+    /// How many previous versions of each note to keep; 0 disables the history.
+    pub history_versions: usize,
+    /// `gc` deletes notes trashed more than this many days ago; 0 keeps them.
+    pub trash_retention_days: u32,
+    /// `gc` deletes caches in the notes directory larger than this, in bytes.
+    pub cache_max_bytes: u64,
+    /// Refuse every change to the notes, like `--read-only`.
+    pub read_only_mode: bool,
+    /// Never create the notes directory, e.g. when it's on a removable drive.
+    pub notes_dir_must_exist: bool,
+    /// How much of the selected note the TUI preview shows, in bytes.
+    pub preview_max_bytes: usize,
+    /// Notes larger than this, in bytes, are only decrypted for the TUI preview
+    /// once asked to.
+    pub preview_decrypt_limit: u64,
+    /// Show the title, tags and last update of the selected note above its
+    /// preview in the TUI, apart from the content.
+    pub preview_header: bool,
+    /// How long the TUI waits for a key, in milliseconds, before checking on
+    /// the selected note and signals.
+    pub poll_interval_ms: u64,
+    /// With more notes than this to parse, the TUI lists them right away with
+    /// placeholder titles and parses their metadata in the background.
+    pub lazy_load_above: usize,
+    /// Notes larger than this, in bytes, are never decrypted.
+    pub max_note_size: u64,
+    /// Titles longer than this, in characters, are cut when saved.
+    pub max_title_length: usize,
+    /// Read back and decrypt every note file right after writing it.
+    pub paranoid_writes: bool,
+    /// `encrypt-unencrypted` asks before encrypting more files than this, in
+    /// case it was pointed at the wrong directory.
+    pub encrypt_confirm_files: usize,
+    /// `encrypt-unencrypted` asks before encrypting more bytes than this in total.
+    pub encrypt_confirm_bytes: u64,
+    /// Ask before creating a note with the title of an existing one.
+    pub warn_duplicate_titles: bool,
+    /// How timestamps are shown, as a `strftime` format.
+    pub date_format: String,
+    /// UUID or title of the note `quick` appends to; created with this title if missing.
+    pub inbox: String,
+    /// Title of journal notes, as a `strftime` format giving the whole date.
+    pub journal_title: String,
+    /// UUID or title of the note new journal notes start as a copy of.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_template: Option<String>,
+    /// Notebook of journal notes, which they're tagged with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal_notebook: Option<String>,
+    /// Title of new notes created without one, see [`template`](crate::template).
+    pub new_note_title: String,
+    /// Line written above the text captured with `quick`, see
+    /// [`template`](crate::template).
+    pub capture_header: String,
+    /// Record changes to the notes, PIN failures and exports in the audit log.
+    pub audit_log: bool,
+    /// Encrypt the events of the audit log with the PIN.
+    pub encrypt_audit_log: bool,
+    /// Never rely on color alone in the TUI, e.g. for colorblind users.
+    pub high_contrast: bool,
+    /// Language of the TUI, prompts and messages, e.g. `de`; taken from
+    /// `LANG` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// In privacy mode, also hide the titles of notes after their first few
+    /// characters.
+    pub privacy_masks_titles: bool,
+    /// Also announce notes coming due while the TUI is open with a desktop
+    /// notification, see [`notify`](crate::notify).
+    pub desktop_notifications: bool,
+    /// When the notes were last encrypted again with `rekey`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_rekey: Option<DateTime<Utc>>,
+    /// After how many days since the last `rekey` `doctor` and `stats` suggest
+    /// another; 0 never does.
+    pub rekey_reminder_days: u32,
+    /// Opt-in syncing of the notes directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncConfig>,
+    /// Opt-in snapshots of the notes directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup: Option<BackupConfig>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Top-level keys Ryokan doesn't know, e.g. of a wrapper script, kept so
+    /// saving doesn't drop them.
+    #[serde(flatten)]
+    extra: toml::Table,
     #[serde(skip)]
     pub config_path: PathBuf,
+    /// Where the state and cache of this config file are kept.
+    #[serde(skip)]
+    pub paths: Paths,
+    #[serde(skip)]
+    active_profile: Option<String>,
 }
 
 impl Default for Config {
@@ -25,12 +184,87 @@ impl Default for Config {
         Self {
             pin_hash: String::new(),
             notes_dir: NOTES_FOLDER.to_string(),
+            editor: None,
+            editor_cursor_arg: None,
+            picker: DEFAULT_PICKER.to_string(),
+            record_stats: true,
+            list_snippets: false,
+            history_versions: DEFAULT_HISTORY_VERSIONS,
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            cache_max_bytes: DEFAULT_CACHE_MAX_BYTES,
+            read_only_mode: false,
+            notes_dir_must_exist: false,
+            preview_max_bytes: DEFAULT_PREVIEW_MAX_BYTES,
+            preview_decrypt_limit: DEFAULT_PREVIEW_DECRYPT_LIMIT,
+            preview_header: true,
+            poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+            lazy_load_above: DEFAULT_LAZY_LOAD_ABOVE,
+            max_note_size: DEFAULT_MAX_NOTE_SIZE,
+            max_title_length: DEFAULT_MAX_TITLE_LENGTH,
+            paranoid_writes: false,
+            encrypt_confirm_files: DEFAULT_ENCRYPT_CONFIRM_FILES,
+            encrypt_confirm_bytes: DEFAULT_ENCRYPT_CONFIRM_BYTES,
+            warn_duplicate_titles: true,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            inbox: DEFAULT_INBOX.to_string(),
+            journal_title: DEFAULT_JOURNAL_TITLE.to_string(),
+            journal_template: None,
+            journal_notebook: None,
+            new_note_title: DEFAULT_NEW_NOTE_TITLE.to_string(),
+            capture_header: DEFAULT_CAPTURE_HEADER.to_string(),
+            audit_log: true,
+            encrypt_audit_log: false,
+            high_contrast: false,
+            language: None,
+            privacy_masks_titles: false,
+            desktop_notifications: false,
+            last_rekey: None,
+            rekey_reminder_days: DEFAULT_REKEY_REMINDER_DAYS,
+            sync: None,
+            backup: None,
+            profiles: BTreeMap::new(),
             config_path: PathBuf::new(),
+            paths: Paths::default(),
+            active_profile: None,
+            extra: toml::Table::new(),
+        }
+    }
+}
+
+/// Converts a path to store it in the config, which can only hold UTF-8.
+///
+/// A lossy conversion would silently point somewhere else.
+pub fn path_to_string(path: &Path) -> Result<String, AppError> {
+    path.to_str().map(str::to_string).ok_or_else(|| {
+        AppError::Config(format!(
+            "{} isn't valid UTF-8, so it can't be stored in the config",
+            path.display()
+        ))
+    })
+}
+
+/// Updates `table` of the config file to `saved`, keeping the comments and
+/// order of the keys it already has.
+fn merge(table: &mut Table, saved: &Table) {
+    table.retain(|key, _| saved.contains_key(key));
+    for (key, item) in saved {
+        match (table.get_mut(key), item) {
+            (Some(Item::Table(existing)), Item::Table(saved)) => merge(existing, saved),
+            (Some(Item::Value(existing)), Item::Value(saved)) => {
+                let decor = existing.decor().clone();
+                *existing = saved.clone();
+                *existing.decor_mut() = decor;
+            }
+            _ => {
+                table.insert(key, item.clone());
+            }
         }
     }
 }
 
 impl Config {
+    /// Loads the config from `config_path_param` or the default location, creating it
+    /// with default values if it doesn't exist yet.
     pub fn new(config_path_param: Option<&PathBuf>) -> Result<Config, AppError> {
         let config_file_path = match config_path_param {
             Some(p) => p.clone(),
@@ -58,26 +292,182 @@ impl Config {
         };
 
         config.config_path = config_file_path;
+        let default_path = Self::default_config_file_path().ok();
+        config.paths = Paths::resolve(&config.config_path, default_path.as_deref());
+        let profiles = config.profiles.keys().map(String::as_str);
+        if let Err(e) = config.paths.migrate_state(profiles) {
+            warn!("Could not move the audit log to the state directory: {e}");
+        }
 
-        if !Path::new(&config.notes_dir).is_absolute()
-            && let Some(parent) = config.config_path.parent()
+        if let Some(parent) = config.config_path.parent() {
+            let notes_dirs = std::iter::once(&mut config.notes_dir)
+                .chain(config.profiles.values_mut().map(|p| &mut p.notes_dir));
+            for notes_dir in notes_dirs {
+                if !Path::new(notes_dir).is_absolute() {
+                    *notes_dir = path_to_string(&parent.join(&notes_dir))?;
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Makes `name` the profile whose PIN and notes directory are used; `None`
+    /// goes back to the top-level ones.
+    pub fn select_profile(&mut self, name: Option<&str>) -> Result<(), AppError> {
+        if let Some(name) = name
+            && !self.profiles.contains_key(name)
         {
-            config.notes_dir = parent.join(&config.notes_dir).to_string_lossy().to_string();
+            return Err(AppError::Config(format!("Unknown profile \"{name}\"")));
         }
+        self.active_profile = name.map(str::to_string);
+        Ok(())
+    }
 
-        // Create the notes directory if it doesn't exist
-        fs::create_dir_all(&config.notes_dir).map_err(AppError::Io)?;
+    /// The selected profile, if any.
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
 
-        Ok(config)
+    fn profile(&self) -> Option<&Profile> {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+    }
+
+    /// The PIN hash of the active profile; empty if no PIN has been set.
+    pub fn pin_hash(&self) -> &str {
+        self.profile().map_or(&self.pin_hash, |p| &p.pin_hash)
     }
 
+    /// Replaces the PIN hash of the active profile. Doesn't save the config.
+    pub fn set_pin_hash(&mut self, hash: String) {
+        let profile = self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get_mut(name));
+        match profile {
+            Some(profile) => profile.pin_hash = hash,
+            None => self.pin_hash = hash,
+        }
+    }
+
+    /// When the notes of the active profile were last encrypted again.
+    pub fn last_rekey(&self) -> Option<DateTime<Utc>> {
+        self.profile().map_or(self.last_rekey, |p| p.last_rekey)
+    }
+
+    /// Records that the notes of the active profile were encrypted again at
+    /// `at`. Doesn't save the config.
+    pub fn set_last_rekey(&mut self, at: DateTime<Utc>) {
+        let profile = self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get_mut(name));
+        match profile {
+            Some(profile) => profile.last_rekey = Some(at),
+            None => self.last_rekey = Some(at),
+        }
+    }
+
+    /// How many days ago the notes of the active profile were last encrypted
+    /// again, if that's longer than `rekey_reminder_days`. Notes never
+    /// encrypted again aren't reminded about, since each was encrypted with
+    /// its own salt and nonce when written.
+    pub fn rekey_overdue(&self, now: DateTime<Utc>) -> Option<i64> {
+        let days = (now - self.last_rekey()?).num_days();
+        (self.rekey_reminder_days > 0 && days >= i64::from(self.rekey_reminder_days))
+            .then_some(days)
+    }
+
+    /// The resolved notes directory of the active profile.
     pub fn notes_dir_path(&self) -> &Path {
-        Path::new(&self.notes_dir)
+        Path::new(self.profile().map_or(&self.notes_dir, |p| &p.notes_dir))
+    }
+
+    /// Where the audit log of the active profile is kept: in the state
+    /// directory, or in `profiles/<name>/` below it for a profile.
+    pub fn audit_log_path(&self) -> Option<PathBuf> {
+        Some(self.paths.audit_log(self.active_profile.as_deref()))
+    }
+
+    /// The snapshots of the active vault, as `[backup]` sets them up. A
+    /// profile's go in a directory of their own.
+    pub fn backups(&self) -> Backups {
+        let backup = self.backup.clone().unwrap_or_default();
+        let dir = match &backup.directory {
+            Some(dir) => {
+                let dir = match self.config_path.parent() {
+                    Some(parent) => parent.join(dir),
+                    None => PathBuf::from(dir),
+                };
+                match &self.active_profile {
+                    Some(name) => dir.join("profiles").join(name),
+                    None => dir,
+                }
+            }
+            None => self
+                .paths
+                .profile_state_dir(self.active_profile.as_deref())
+                .join("backups"),
+        };
+        Backups::new(
+            dir,
+            backup.keep_last,
+            i64::try_from(backup.min_interval_hours)
+                .ok()
+                .and_then(chrono::Duration::try_hours)
+                .unwrap_or(chrono::Duration::MAX),
+        )
+    }
+
+    /// The snapshots to take on a schedule, if `[backup]` turns them on.
+    pub fn scheduled_backups(&self) -> Option<Backups> {
+        self.backup
+            .as_ref()
+            .is_some_and(|backup| backup.enabled)
+            .then(|| self.backups())
+    }
+
+    /// The audit log of the active profile, `None` if it's disabled.
+    pub fn audit_log(&self) -> Option<AuditLog> {
+        self.audit_log
+            .then(|| self.audit_log_path())
+            .flatten()
+            .map(|path| AuditLog::new(path, self.encrypt_audit_log))
+    }
+
+    /// How journal notes are titled and created, checking `journal_title`.
+    pub fn journal(&self) -> Result<Journal, AppError> {
+        Journal::new(
+            &self.journal_title,
+            self.journal_template.clone(),
+            self.journal_notebook.clone(),
+        )
+    }
+
+    /// What `gc` deletes.
+    pub fn gc_policy(&self) -> Policy {
+        Policy {
+            trash_retention_days: self.trash_retention_days,
+            history_versions: self.history_versions,
+            cache_max_bytes: self.cache_max_bytes,
+        }
+    }
+
+    /// The title of new notes created without one, checking `new_note_title`.
+    pub fn new_note_title(&self) -> Result<Template, AppError> {
+        Template::new("new_note_title", &self.new_note_title, true)
+    }
+
+    /// The line above captured text, checking `capture_header`.
+    pub fn capture_header(&self) -> Result<Template, AppError> {
+        Template::new("capture_header", &self.capture_header, false)
     }
 
     fn ensure_parent_dir(path: &Path) -> Result<(), AppError> {
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(AppError::Io)?;
+            permissions::create_private_dir(parent).map_err(AppError::Io)?;
         }
         Ok(())
     }
@@ -86,15 +476,38 @@ impl Config {
     /// Returns default configuration if it fails to parse.
     fn parse_config(config_str: &str) -> Result<Config, AppError> {
         toml::from_str(config_str).map_err(|e| {
-            error!("Error while parsing the configuration: {e}");
+            // Without the excerpt of the file, which may be the PIN hash
+            let line = e
+                .span()
+                .map_or(0, |span| config_str[..span.start].matches('\n').count() + 1);
+            error!(
+                "Error while parsing the configuration at line {line}: {}",
+                e.message()
+            );
             AppError::TomlDeserialize(e)
         })
     }
 
-    /// Save the config to a file
+    /// Save the config to a file, keeping the comments and the order of the
+    /// keys already in it.
     pub fn save(&self) -> Result<(), AppError> {
-        let config_str = toml::to_string(self).map_err(AppError::TomlSerialize)?;
         let config_path = &self.config_path;
+        let config_str = toml::to_string(self).map_err(AppError::TomlSerialize)?;
+        let config_str = match fs::read_to_string(config_path) {
+            // Written over if it no longer parses, as when there's no file
+            Ok(existing) => match existing.parse::<DocumentMut>() {
+                Ok(mut document) => {
+                    let saved: DocumentMut = config_str
+                        .parse()
+                        .map_err(|e| AppError::Config(format!("Invalid config: {e}")))?;
+                    merge(document.as_table_mut(), saved.as_table());
+                    document.to_string()
+                }
+                Err(_) => config_str,
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => config_str,
+            Err(e) => return Err(AppError::Io(e)),
+        };
 
         Self::ensure_parent_dir(config_path)?;
 
@@ -103,6 +516,8 @@ impl Config {
             .ok_or_else(|| AppError::Config("Invalid config path".to_string()))?;
         let mut temp_file = tempfile::NamedTempFile::new_in(parent).map_err(AppError::Io)?;
 
+        // On Windows, the config directory is below the user profile, whose ACL
+        // already keeps other users out
         #[cfg(unix)]
         {
             fs::set_permissions(temp_file.path(), fs::Permissions::from_mode(0o600))
@@ -119,11 +534,10 @@ impl Config {
         Ok(())
     }
 
-    fn default_config_file_path() -> Result<PathBuf, AppError> {
-        let mut config_path = dirs::config_dir()
+    /// Location of the config file when none is given explicitly.
+    pub fn default_config_file_path() -> Result<PathBuf, AppError> {
+        let config_dir = dirs::config_dir()
             .ok_or_else(|| AppError::Config("Could not determine config directory.".to_string()))?;
-        config_path.push("ryokan");
-        config_path.push("ryokan.toml");
-        Ok(config_path)
+        Ok(migrate::config_path(&config_dir))
     }
 }
@@ -0,0 +1,153 @@
+//! Showing timestamps to the user, in the local time zone and the format set by
+//! `date_format`.
+//!
+//! Timestamps are stored in UTC. Where the local time zone can't be determined,
+//! e.g. in a container without time zone data, they're shown in UTC with a
+//! `UTC` suffix rather than passed off as local time.
+
+use crate::due;
+use crate::error::AppError;
+use chrono::{
+    DateTime, Days, Local, NaiveDate, SecondsFormat, TimeZone, Utc,
+    format::{Item, StrftimeItems},
+};
+
+/// The `date_format` used when none is configured.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The time zone timestamps are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zone {
+    Local,
+    /// The local time zone is unknown.
+    Utc,
+}
+
+/// A checked `strftime` format, applied in the local time zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFormat {
+    format: String,
+    zone: Zone,
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self {
+            format: DEFAULT_DATE_FORMAT.to_string(),
+            zone: local_zone(),
+        }
+    }
+}
+
+impl DateFormat {
+    /// Checks `format`, such as `%Y-%m-%d %H:%M`, so a mistake is reported when
+    /// the config is loaded instead of when something is displayed.
+    pub fn new(format: &str) -> Result<Self, AppError> {
+        Self::with_zone(format, local_zone())
+    }
+
+    fn with_zone(format: &str, zone: Zone) -> Result<Self, AppError> {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(AppError::Config(format!(
+                "Invalid date_format \"{format}\", see the chrono strftime documentation"
+            )));
+        }
+        Ok(Self {
+            format: format.to_string(),
+            zone,
+        })
+    }
+
+    /// Formats `at` for display.
+    pub fn format(&self, at: &DateTime<Utc>) -> String {
+        match self.zone {
+            Zone::Local => at.with_timezone(&Local).format(&self.format).to_string(),
+            Zone::Utc => format!("{} UTC", at.format(&self.format)),
+        }
+    }
+
+    /// Formats `at` as RFC 3339 with the offset of the time zone it's shown in,
+    /// for output that other programs parse, such as frontmatter.
+    pub fn rfc3339(&self, at: &DateTime<Utc>) -> String {
+        match self.zone {
+            Zone::Local => at
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            Zone::Utc => at.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        }
+    }
+}
+
+/// Parses a day given as `YYYY-MM-DD`, `today` or `yesterday`, in the time zone
+/// of `now`, into its first instant, or with `end` into the first instant of the
+/// next day, so that the whole day is included either way.
+pub fn parse_day<Tz: TimeZone>(
+    input: &str,
+    now: &DateTime<Tz>,
+    end: bool,
+) -> Result<DateTime<Utc>, AppError> {
+    let today = now.date_naive();
+    let date = match input.trim().to_ascii_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        day => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok(),
+    }
+    .ok_or_else(|| {
+        AppError::Config(format!(
+            "Invalid date \"{input}\", expected YYYY-MM-DD, today or yesterday"
+        ))
+    })?;
+    let date = if end {
+        date.checked_add_days(Days::new(1))
+            .ok_or_else(|| AppError::Config(format!("Invalid date \"{input}\"")))?
+    } else {
+        date
+    };
+    due::start_of_day(date, &now.timezone())
+}
+
+/// Whether the local time zone is known. chrono quietly uses UTC when it isn't,
+/// which would pass UTC off as local time.
+fn local_zone() -> Zone {
+    if local_zone_known() {
+        Zone::Local
+    } else {
+        Zone::Utc
+    }
+}
+
+#[cfg(unix)]
+fn local_zone_known() -> bool {
+    use std::path::Path;
+
+    let Some(tz) = std::env::var("TZ").ok().filter(|tz| !tz.is_empty()) else {
+        return Path::new("/etc/localtime").exists();
+    };
+    let tz = tz.strip_prefix(':').unwrap_or(&tz);
+    if tz.starts_with('/') {
+        return Path::new(tz).exists();
+    }
+    // A POSIX rule such as `CET-1CEST`, which needs no time zone data
+    if tz.chars().any(|c| c.is_ascii_digit()) && !tz.contains('/') {
+        return true;
+    }
+    let tzdir = std::env::var("TZDIR").ok();
+    tzdir
+        .iter()
+        .map(String::as_str)
+        .chain([
+            "/usr/share/zoneinfo",
+            "/usr/lib/zoneinfo",
+            "/usr/share/lib/zoneinfo",
+        ])
+        .any(|dir| Path::new(dir).join(tz).exists())
+}
+
+#[cfg(not(unix))]
+fn local_zone_known() -> bool {
+    // Windows always knows its time zone
+    true
+}
+
+#[cfg(test)]
+mod datetime_test;
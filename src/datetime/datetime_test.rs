@@ -0,0 +1,71 @@
+#![cfg(test)]
+
+use super::*;
+
+fn at(s: &str) -> Result<DateTime<Utc>, AppError> {
+    Ok(DateTime::parse_from_rfc3339(s)
+        .map_err(|_| AppError::Config("invalid test fixture".to_string()))?
+        .with_timezone(&Utc))
+}
+
+#[test]
+fn test_invalid_format_is_a_config_error() {
+    assert!(matches!(
+        DateFormat::new("%Y-%m-%d %Q"),
+        Err(AppError::Config(_))
+    ));
+    assert!(DateFormat::new("%d/%m/%Y %H:%M").is_ok());
+}
+
+#[test]
+fn test_unknown_zone_falls_back_to_utc_with_a_suffix() -> Result<(), AppError> {
+    let dates = DateFormat::with_zone("%d/%m/%Y %H:%M", Zone::Utc)?;
+    let at = at("2024-06-12T08:30:00Z")?;
+
+    assert_eq!(dates.format(&at), "12/06/2024 08:30 UTC");
+    assert_eq!(dates.rfc3339(&at), "2024-06-12T08:30:00+00:00");
+    Ok(())
+}
+
+#[test]
+fn test_local_zone_is_applied() -> Result<(), AppError> {
+    let dates = DateFormat::with_zone("%Y-%m-%d %H:%M %z", Zone::Local)?;
+    let at = at("2024-06-12T08:30:00Z")?;
+
+    let expected = at.with_timezone(&Local).format("%Y-%m-%d %H:%M %z");
+    assert_eq!(dates.format(&at), expected.to_string());
+    assert!(!dates.format(&at).ends_with("UTC"));
+    Ok(())
+}
+
+#[test]
+fn test_parse_day_covers_the_whole_local_day() -> Result<(), AppError> {
+    let zone = chrono::FixedOffset::east_opt(2 * 3600)
+        .ok_or_else(|| AppError::Config("invalid test fixture".to_string()))?;
+    let now = at("2024-06-12T23:30:00Z")?.with_timezone(&zone);
+
+    assert_eq!(
+        parse_day("2024-03-01", &now, false)?,
+        at("2024-02-29T22:00:00Z")?
+    );
+    assert_eq!(
+        parse_day("2024-03-01", &now, true)?,
+        at("2024-03-01T22:00:00Z")?
+    );
+    // Already the 13th in that zone
+    assert_eq!(
+        parse_day("today", &now, false)?,
+        at("2024-06-12T22:00:00Z")?
+    );
+    assert_eq!(
+        parse_day("Yesterday", &now, true)?,
+        at("2024-06-12T22:00:00Z")?
+    );
+    for invalid in ["", "2024-02-30", "01/03/2024", "tomorrow"] {
+        assert!(matches!(
+            parse_day(invalid, &now, false),
+            Err(AppError::Config(_))
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//! Finding notes with identical content and merging them into one.
+//!
+//! Notes are compared by the BLAKE3 hash of their decrypted content. The hashes
+//! only live in memory, unless they're cached in [`HASH_CACHE_FILE_NAME`], which
+//! is encrypted with the PIN like the notes themselves.
+
+use crate::error::AppError;
+use crate::metadata::NoteMetadata;
+use crate::parallel;
+use crate::vault::{Note, Vault};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::BTreeMap, fmt, str::FromStr};
+use zeroize::Zeroizing;
+
+/// Name of the encrypted hash cache at the root of the vault.
+pub const HASH_CACHE_FILE_NAME: &str = ".ryokan-hashes.enc";
+
+/// Which note of a group of duplicates is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// The most recently updated one.
+    Newest,
+    /// The least recently updated one.
+    Oldest,
+}
+
+impl KeepPolicy {
+    pub const ALL: [KeepPolicy; 2] = [KeepPolicy::Newest, KeepPolicy::Oldest];
+}
+
+impl fmt::Display for KeepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeepPolicy::Newest => write!(f, "newest"),
+            KeepPolicy::Oldest => write!(f, "oldest"),
+        }
+    }
+}
+
+impl FromStr for KeepPolicy {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AppError::Config(format!("Unknown keep policy \"{s}\"")))
+    }
+}
+
+/// Notes whose content is byte for byte the same.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    /// Hex BLAKE3 hash of the content.
+    pub hash: String,
+    /// Size of the content, in bytes.
+    pub len: u64,
+    /// Two or more notes, most recently updated first.
+    pub notes: Vec<Note>,
+}
+
+impl DuplicateGroup {
+    /// The note `policy` keeps.
+    pub fn keeper(&self, policy: KeepPolicy) -> &Note {
+        match policy {
+            KeepPolicy::Newest => &self.notes[0],
+            KeepPolicy::Oldest => &self.notes[self.notes.len() - 1],
+        }
+    }
+}
+
+/// What [`scan`] found.
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Groups of duplicates, those with the most recently updated note first.
+    pub groups: Vec<DuplicateGroup>,
+    /// Notes that couldn't be decrypted, which were left out of the comparison.
+    pub failed: Vec<(Note, AppError)>,
+}
+
+/// A hash in the cache, valid as long as the note wasn't updated since.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedHash {
+    updated_at: DateTime<Utc>,
+    hash: String,
+    len: u64,
+}
+
+type HashCache = BTreeMap<String, CachedHash>;
+
+/// Decrypts and hashes every note, on several threads, and groups the duplicates.
+///
+/// With `cache_hashes`, notes that weren't updated since the last cached scan
+/// aren't decrypted again, and the hashes are saved back to the cache.
+pub fn scan(vault: &Vault, cache_hashes: bool) -> Result<Scan, AppError> {
+    let notes = vault.list()?;
+    let mut cache = if cache_hashes {
+        load_cache(vault)
+    } else {
+        HashCache::new()
+    };
+
+    let hashes = parallel::map(&notes, parallel::argon2_workers(), |note| {
+        match cache.get(&note.uuid) {
+            Some(cached) if cached.updated_at == note.metadata.updated_at => Ok(cached.clone()),
+            _ => {
+                let content = Zeroizing::new(vault.read(&note.uuid)?);
+                Ok(CachedHash {
+                    updated_at: note.metadata.updated_at,
+                    hash: blake3::hash(&content).to_hex().to_string(),
+                    len: content.len() as u64,
+                })
+            }
+        }
+    });
+
+    let mut scan = Scan::default();
+    let mut by_hash: BTreeMap<(String, u64), Vec<Note>> = BTreeMap::new();
+    cache.clear();
+    for (note, hash) in notes.into_iter().zip(hashes) {
+        match hash {
+            Ok(hash) => {
+                by_hash
+                    .entry((hash.hash.clone(), hash.len))
+                    .or_default()
+                    .push(note.clone());
+                cache.insert(note.uuid, hash);
+            }
+            Err(e) => {
+                warn!("Leaving out {}, it can't be decrypted: {e}", note.uuid);
+                scan.failed.push((note, e));
+            }
+        }
+    }
+
+    if cache_hashes {
+        save_cache(vault, &cache);
+    }
+
+    scan.groups = by_hash
+        .into_iter()
+        .filter(|(_, notes)| notes.len() > 1)
+        .map(|((hash, len), mut notes)| {
+            notes.sort_by_key(|note| Reverse(note.metadata.updated_at));
+            DuplicateGroup { hash, len, notes }
+        })
+        .collect();
+    scan.groups
+        .sort_by_key(|group| Reverse(group.notes[0].metadata.updated_at));
+    Ok(scan)
+}
+
+/// What [`merge`] did with a group, by note UUID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    /// The metadata of the note kept, with the tags of the others added.
+    pub metadata: NoteMetadata,
+    pub trashed: Vec<String>,
+    /// Notes left as they were because they're read-only: copies that weren't
+    /// trashed, or the kept note, which didn't get the tags of the others.
+    pub skipped_read_only: Vec<String>,
+}
+
+/// Keeps the note `keep_uuid` of `group`, adding the tags of the other notes to
+/// it, and moves the others to the trash. Read-only notes are left alone.
+pub fn merge(vault: &Vault, group: &DuplicateGroup, keep_uuid: &str) -> Result<Merge, AppError> {
+    let Some(keeper) = group.notes.iter().find(|note| note.uuid == keep_uuid) else {
+        return Err(AppError::NoteNotFound(keep_uuid.to_string()));
+    };
+    let _lock = vault.lock()?;
+    let others: Vec<_> = group
+        .notes
+        .iter()
+        .filter(|note| note.uuid != keep_uuid)
+        .collect();
+
+    let mut skipped_read_only = Vec::new();
+    let metadata = if keeper.metadata.read_only {
+        skipped_read_only.push(keeper.uuid.clone());
+        vault.get(keep_uuid)?.metadata
+    } else {
+        vault.metadata_mut(keep_uuid, |metadata| {
+            for note in &others {
+                for tag in &note.metadata.tags {
+                    if !metadata.tags.contains(tag) {
+                        metadata.tags.push(tag.clone());
+                    }
+                }
+            }
+        })?
+    };
+    let mut trashed = Vec::new();
+    for note in others {
+        if note.metadata.read_only {
+            skipped_read_only.push(note.uuid.clone());
+        } else {
+            vault.trash(&note.uuid)?;
+            trashed.push(note.uuid.clone());
+        }
+    }
+    info!(
+        "Kept {keep_uuid}, trashed {} duplicate(s), left {} read-only note(s)",
+        trashed.len(),
+        skipped_read_only.len()
+    );
+    Ok(Merge {
+        metadata,
+        trashed,
+        skipped_read_only,
+    })
+}
+
+/// Reads the hash cache, starting over if it's missing or unreadable.
+fn load_cache(vault: &Vault) -> HashCache {
+    match vault.read_private_file(HASH_CACHE_FILE_NAME) {
+        Ok(Some(content)) => {
+            let content = Zeroizing::new(content);
+            serde_json::from_slice(&content).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable hash cache: {e}");
+                HashCache::new()
+            })
+        }
+        Ok(None) => HashCache::new(),
+        Err(e) => {
+            warn!("Ignoring hash cache that can't be decrypted: {e}");
+            HashCache::new()
+        }
+    }
+}
+
+/// Saves the hash cache. It only speeds up the next scan, so failing to is no
+/// reason to stop.
+fn save_cache(vault: &Vault, cache: &HashCache) {
+    let saved = serde_json::to_vec(cache)
+        .map_err(|e| AppError::Config(format!("Could not serialize the hash cache: {e}")))
+        .map(Zeroizing::new)
+        .and_then(|content| vault.write_private_file(HASH_CACHE_FILE_NAME, &content));
+    if let Err(e) = saved {
+        warn!("Could not save the hash cache: {e}");
+    }
+}
+
+#[cfg(test)]
+mod dedupe_test;
@@ -0,0 +1,121 @@
+#![cfg(test)]
+
+use super::*;
+use crate::file;
+use crate::storage::{MemoryStorage, Storage};
+use chrono::TimeDelta;
+use std::sync::Arc;
+
+fn vault() -> (Arc<MemoryStorage>, Vault) {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    (storage, vault)
+}
+
+/// Creates a note updated `day` days after the epoch, with `tags`.
+fn note(
+    vault: &Vault,
+    title: &str,
+    content: &[u8],
+    day: i64,
+    tags: &[&str],
+) -> Result<Note, AppError> {
+    let note = vault.create(title, content)?;
+    let metadata = vault.metadata_mut(&note.uuid, |metadata| {
+        metadata.updated_at = DateTime::UNIX_EPOCH + TimeDelta::days(day);
+        metadata.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    })?;
+    Ok(Note {
+        uuid: note.uuid,
+        metadata,
+    })
+}
+
+#[test]
+fn test_scan_groups_duplicates_and_merge_trashes_them() -> Result<(), AppError> {
+    let (storage, vault) = vault();
+    let old = note(&vault, "Groceries", b"milk", 1, &["food"])?;
+    let new = note(&vault, "Groceries (copy)", b"milk", 3, &["shopping"])?;
+    note(&vault, "Todo", b"call mom", 2, &[])?;
+    let broken = note(&vault, "Broken", b"milk", 4, &[])?;
+    let (broken_content, _) = file::note_file_names(&broken.uuid);
+    storage.write(&broken_content, &[file::MAGIC_BYTES, b"garbage"].concat())?;
+
+    let scan = scan(&vault, false)?;
+    assert_eq!(scan.failed.len(), 1);
+    assert_eq!(scan.failed[0].0.uuid, broken.uuid);
+    assert_eq!(scan.groups.len(), 1);
+    let group = &scan.groups[0];
+    assert_eq!(group.hash, blake3::hash(b"milk").to_hex().to_string());
+    let uuids: Vec<_> = group.notes.iter().map(|note| note.uuid.as_str()).collect();
+    assert_eq!(uuids, [new.uuid.as_str(), old.uuid.as_str()]);
+    assert_eq!(group.keeper(KeepPolicy::Oldest).uuid, old.uuid);
+    assert!(!storage.exists(HASH_CACHE_FILE_NAME));
+
+    let merged = merge(&vault, group, &group.keeper(KeepPolicy::Oldest).uuid)?;
+    assert_eq!(merged.metadata.tags, ["food", "shopping"]);
+    assert_eq!(merged.trashed, [new.uuid.as_str()]);
+    assert!(vault.get(&new.uuid).is_err());
+    let (new_content, new_metadata) = file::note_file_names(&new.uuid);
+    assert!(storage.exists(&file::trash_file_name(&new_content)));
+    assert!(storage.exists(&file::trash_file_name(&new_metadata)));
+    assert_eq!(vault.list()?.len(), 3);
+    assert!(!vault.index()?.titles.contains_key(&new.uuid));
+    Ok(())
+}
+
+#[test]
+fn test_merge_refuses_a_keeper_outside_the_group() -> Result<(), AppError> {
+    let (_, vault) = vault();
+    note(&vault, "A", b"same", 1, &[])?;
+    note(&vault, "B", b"same", 2, &[])?;
+    let other = note(&vault, "C", b"different", 3, &[])?;
+
+    let scan = scan(&vault, false)?;
+    assert!(merge(&vault, &scan.groups[0], &other.uuid).is_err());
+    assert_eq!(vault.list()?.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_merge_leaves_read_only_notes_alone() -> Result<(), AppError> {
+    let (_, vault) = vault();
+    let keeper = note(&vault, "Kept", b"same", 1, &["kept"])?;
+    let locked = note(&vault, "Locked", b"same", 2, &["locked"])?;
+    let copy = note(&vault, "Copy", b"same", 3, &["copy"])?;
+    let locked_metadata = vault.metadata_mut(&locked.uuid, |metadata| metadata.read_only = true)?;
+
+    let scan_before = scan(&vault, false)?;
+    let merged = merge(&vault, &scan_before.groups[0], &keeper.uuid)?;
+    assert_eq!(merged.trashed, [copy.uuid.as_str()]);
+    assert_eq!(merged.skipped_read_only, [locked.uuid.as_str()]);
+    assert_eq!(vault.get(&locked.uuid)?.metadata, locked_metadata);
+
+    // A read-only keeper doesn't get the tags of the others either
+    let merged = merge(&vault, &scan(&vault, false)?.groups[0], &locked.uuid)?;
+    assert_eq!(merged.metadata, locked_metadata);
+    assert_eq!(merged.trashed, [keeper.uuid.as_str()]);
+    assert_eq!(merged.skipped_read_only, [locked.uuid.as_str()]);
+    Ok(())
+}
+
+#[test]
+fn test_cached_hashes_are_encrypted_and_reused() -> Result<(), AppError> {
+    let (storage, vault) = vault();
+    let first = note(&vault, "A", b"same", 1, &[])?;
+    note(&vault, "B", b"same", 2, &[])?;
+
+    assert_eq!(scan(&vault, true)?.groups.len(), 1);
+    let cache = storage.read(HASH_CACHE_FILE_NAME)?;
+    assert!(file::is_encrypted_file(&cache));
+    let hash = blake3::hash(b"same").to_hex().to_string();
+    assert!(!String::from_utf8_lossy(&cache).contains(&hash));
+
+    // The cached hash is used as long as the note isn't updated
+    let (content, _) = file::note_file_names(&first.uuid);
+    storage.write(&content, &[file::MAGIC_BYTES, b"garbage"].concat())?;
+    let scan = scan(&vault, true)?;
+    assert!(scan.failed.is_empty());
+    assert_eq!(scan.groups.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! Line diffs between two versions of a note, for the history view of the TUI
+//! and `ryokan diff`.
+//!
+//! The diff is the shortest edit script found with Myers' algorithm, on lines
+//! of decrypted content held in memory; nothing is written to disk. Rewrites
+//! too large to diff quickly are shown as every old line removed and every new
+//! one added.
+
+use std::fmt::Write;
+
+/// Beyond this many changed lines, the diff isn't searched for.
+const MAX_EDITS: usize = 1000;
+
+/// A line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// In both versions.
+    Same(&'a str),
+    /// Only in the old version.
+    Removed(&'a str),
+    /// Only in the new version.
+    Added(&'a str),
+}
+
+impl<'a> DiffLine<'a> {
+    /// The line, without its `+`, `-` or ` ` prefix.
+    pub fn text(&self) -> &'a str {
+        match self {
+            DiffLine::Same(line) | DiffLine::Removed(line) | DiffLine::Added(line) => line,
+        }
+    }
+
+    /// The prefix of the line in a unified diff.
+    pub fn prefix(&self) -> char {
+        match self {
+            DiffLine::Same(_) => ' ',
+            DiffLine::Removed(_) => '-',
+            DiffLine::Added(_) => '+',
+        }
+    }
+}
+
+/// Both versions as text, or `None` if either is binary, whose lines mean
+/// nothing.
+pub fn as_text<'a>(old: &'a [u8], new: &'a [u8]) -> Option<(&'a str, &'a str)> {
+    Some((
+        std::str::from_utf8(old).ok()?,
+        std::str::from_utf8(new).ok()?,
+    ))
+}
+
+/// The lines of `old` and `new`, in order, telling which were removed and
+/// which were added.
+///
+/// ```
+/// use ryokan::diff::{DiffLine, lines};
+///
+/// assert_eq!(
+///     lines("milk\neggs\n", "milk\nbread\n"),
+///     [
+///         DiffLine::Same("milk"),
+///         DiffLine::Removed("eggs"),
+///         DiffLine::Added("bread"),
+///     ]
+/// );
+/// ```
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut diff: Vec<_> = old[..prefix]
+        .iter()
+        .map(|&line| DiffLine::Same(line))
+        .collect();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    match shortest_edit(a, b) {
+        Some(middle) => diff.extend(middle),
+        None => {
+            diff.extend(a.iter().map(|&line| DiffLine::Removed(line)));
+            diff.extend(b.iter().map(|&line| DiffLine::Added(line)));
+        }
+    }
+    diff.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|&line| DiffLine::Same(line)),
+    );
+    diff
+}
+
+/// Myers' algorithm, or `None` past [`MAX_EDITS`] changes.
+fn shortest_edit<'a>(a: &[&'a str], b: &[&'a str]) -> Option<Vec<DiffLine<'a>>> {
+    let (n, m) = (a.len(), b.len());
+    let max = (n + m).min(MAX_EDITS);
+    // How far along `a` each diagonal k = x - y got, at index k + max
+    let mut v = vec![0usize; 2 * max + 2];
+    let mut trace = Vec::new();
+    for d in 0..=max {
+        trace.push(v.clone());
+        for k in (max - d..=max + d).step_by(2) {
+            let mut x = if k == max - d || (k != max + d && v[k - 1] < v[k + 1]) {
+                v[k + 1]
+            } else {
+                v[k - 1] + 1
+            };
+            let mut y = (x + max).checked_sub(k)?;
+            while x < n && y < m && a[x] == b[y] {
+                x += 1;
+                y += 1;
+            }
+            v[k] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(a, b, &trace, max, n, m));
+            }
+        }
+    }
+    None
+}
+
+/// Follows the moves recorded in `trace` back from the end of both versions.
+fn backtrack<'a>(
+    a: &[&'a str],
+    b: &[&'a str],
+    trace: &[Vec<usize>],
+    max: usize,
+    mut x: usize,
+    mut y: usize,
+) -> Vec<DiffLine<'a>> {
+    let mut diff = Vec::new();
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let k = x + max - y;
+        let prev_k = if k == max - d || (k != max + d && v[k - 1] < v[k + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[prev_k];
+        let prev_y = prev_x + max - prev_k;
+        while x > prev_x && y > prev_y {
+            diff.push(DiffLine::Same(a[x - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            diff.push(DiffLine::Added(b[y - 1]));
+        } else {
+            diff.push(DiffLine::Removed(a[x - 1]));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    // What's left before the first edit is the same in both
+    diff.extend(a[..x].iter().rev().map(|&line| DiffLine::Same(line)));
+    diff.reverse();
+    diff
+}
+
+/// `diff` as a unified diff between files labeled `old_label` and
+/// `new_label`, with `context` unchanged lines around each change. Empty if
+/// nothing changed.
+pub fn unified(diff: &[DiffLine], old_label: &str, new_label: &str, context: usize) -> String {
+    let changed: Vec<_> = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+    // Runs of lines to show, merging changes whose context overlaps
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let (start, end) = (i.saturating_sub(context), (i + context + 1).min(diff.len()));
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {old_label}\n+++ {new_label}\n");
+    let (mut old_line, mut new_line, mut shown) = (1, 1, 0);
+    for (start, end) in hunks {
+        for line in &diff[shown..start] {
+            match line {
+                DiffLine::Same(_) => (old_line, new_line) = (old_line + 1, new_line + 1),
+                DiffLine::Removed(_) => old_line += 1,
+                DiffLine::Added(_) => new_line += 1,
+            }
+        }
+        let hunk = &diff[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        // An empty side starts at the line before, as diff writes it
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            if old_len == 0 { old_line - 1 } else { old_line },
+            if new_len == 0 { new_line - 1 } else { new_line },
+        );
+        for line in hunk {
+            let _ = writeln!(out, "{}{}", line.prefix(), line.text());
+        }
+        old_line += old_len;
+        new_line += new_len;
+        shown = end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod diff_test;
@@ -0,0 +1,76 @@
+#![cfg(test)]
+
+use super::*;
+
+/// `old` edited by `diff` gives `new` back, and every line is in the diff once.
+fn replays(diff: &[DiffLine], old: &str, new: &str) -> bool {
+    let kept = |skip: fn(&DiffLine) -> bool| -> Vec<&str> {
+        diff.iter()
+            .filter(|line| !skip(line))
+            .map(DiffLine::text)
+            .collect()
+    };
+    kept(|line| matches!(line, DiffLine::Added(_))) == old.lines().collect::<Vec<_>>()
+        && kept(|line| matches!(line, DiffLine::Removed(_))) == new.lines().collect::<Vec<_>>()
+}
+
+#[test]
+fn test_diff_is_the_shortest_edit() {
+    let (old, new) = ("a\nb\nc\na\nb\nb\na\n", "c\nb\na\nb\na\nc\n");
+    let diff = lines(old, new);
+    assert!(replays(&diff, old, new));
+    // The classic example has 5 edits at least
+    let edits = diff
+        .iter()
+        .filter(|line| !matches!(line, DiffLine::Same(_)))
+        .count();
+    assert_eq!(edits, 5);
+}
+
+#[test]
+fn test_empty_and_identical_versions() {
+    assert!(lines("", "").is_empty());
+    assert_eq!(lines("", "new\n"), [DiffLine::Added("new")]);
+    assert_eq!(lines("old", ""), [DiffLine::Removed("old")]);
+    let same = lines("a\nb\n", "a\nb\n");
+    assert!(same.iter().all(|line| matches!(line, DiffLine::Same(_))));
+    assert_eq!(unified(&same, "old", "new", 3), "");
+}
+
+#[test]
+fn test_large_rewrites_fall_back_to_replacing_everything() {
+    let old: String = (0..2000).map(|i| format!("old {i}\n")).collect();
+    let new: String = (0..2000).map(|i| format!("new {i}\n")).collect();
+    let diff = lines(&old, &new);
+    assert_eq!(diff.len(), 4000);
+    assert!(replays(&diff, &old, &new));
+}
+
+#[test]
+fn test_unified_diff_hunks() {
+    let old: String = (1..=20).map(|i| format!("{i}\n")).collect();
+    let new: String = old
+        .lines()
+        .filter(|line| *line != "18")
+        .map(|line| {
+            if line == "3" {
+                "three\n".to_string()
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect();
+    let diff = lines(&old, &new);
+    assert_eq!(
+        unified(&diff, "Note (yesterday)", "Note (current)", 1),
+        "--- Note (yesterday)\n+++ Note (current)\n\
+         @@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n\
+         @@ -17,3 +17,2 @@\n 17\n-18\n 19\n"
+    );
+}
+
+#[test]
+fn test_binary_versions_arent_diffed() {
+    assert_eq!(as_text(b"a", b"b"), Some(("a", "b")));
+    assert_eq!(as_text(b"a", &[0xff, 0xfe]), None);
+}
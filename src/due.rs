@@ -0,0 +1,133 @@
+//! Due dates on notes, and parsing of the short forms users type for them.
+//!
+//! A due date is a calendar day in the user's time zone. It's stored as the UTC
+//! instant of that day's local midnight, and compared by local day again, so a
+//! note due "today" stays due today for the whole local day.
+
+use crate::error::AppError;
+use crate::vault::Note;
+use chrono::{DateTime, Days, NaiveDate, TimeDelta, TimeZone, Utc};
+use std::collections::HashSet;
+
+/// How a due date relates to the current day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DueStatus {
+    Overdue,
+    Today,
+    Upcoming,
+}
+
+/// Parses `2024-07-01`, `today`, `tomorrow`, `+3d` or `+2w` relative to `now`.
+///
+/// The result is midnight of that day in the time zone of `now`.
+pub fn parse_due_date<Tz: TimeZone>(
+    input: &str,
+    now: &DateTime<Tz>,
+) -> Result<DateTime<Utc>, AppError> {
+    let input = input.trim();
+    let today = now.date_naive();
+    let date = match input.to_ascii_lowercase().as_str() {
+        "today" => Some(today),
+        "tomorrow" => today.checked_add_days(Days::new(1)),
+        relative if relative.starts_with('+') => {
+            parse_offset(&relative[1..]).and_then(|days| today.checked_add_days(Days::new(days)))
+        }
+        _ => NaiveDate::parse_from_str(input, "%Y-%m-%d").ok(),
+    }
+    .ok_or_else(|| {
+        AppError::Config(format!(
+            "Invalid due date \"{input}\", expected YYYY-MM-DD, today, tomorrow or +<n>d"
+        ))
+    })?;
+    start_of_day(date, &now.timezone())
+}
+
+/// Parses `3d` or `2w` into a number of days.
+fn parse_offset(offset: &str) -> Option<u64> {
+    let (count, unit) = offset.split_at_checked(offset.len().checked_sub(1)?)?;
+    let count: u64 = count.parse().ok()?;
+    match unit {
+        "d" => Some(count),
+        "w" => count.checked_mul(7),
+        _ => None,
+    }
+}
+
+/// The first instant of `date` in `tz`.
+pub(crate) fn start_of_day<Tz: TimeZone>(
+    date: NaiveDate,
+    tz: &Tz,
+) -> Result<DateTime<Utc>, AppError> {
+    let midnight = date.and_time(chrono::NaiveTime::MIN);
+    // Some zones skip midnight when daylight saving starts; the day then starts an hour later
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(midnight + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .ok_or_else(|| AppError::Config(format!("{date} has no midnight in this time zone")))
+}
+
+/// Whether `due_at` is before, on or after the local day of `now`.
+pub fn due_status<Tz: TimeZone>(due_at: &DateTime<Utc>, now: &DateTime<Tz>) -> DueStatus {
+    let due_day = due_at.with_timezone(&now.timezone()).date_naive();
+    match due_day.cmp(&now.date_naive()) {
+        std::cmp::Ordering::Less => DueStatus::Overdue,
+        std::cmp::Ordering::Equal => DueStatus::Today,
+        std::cmp::Ordering::Greater => DueStatus::Upcoming,
+    }
+}
+
+/// Formats `due_at` as the local day it falls on.
+pub fn format_due_date<Tz: TimeZone>(due_at: &DateTime<Utc>, tz: &Tz) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    due_at.with_timezone(tz).format("%Y-%m-%d").to_string()
+}
+
+/// Notes that came due, each announced once, e.g. while the TUI is open.
+#[derive(Debug, Default)]
+pub struct Reminders {
+    /// UUIDs of the notes announced, or due already when taken in.
+    announced: HashSet<String>,
+}
+
+impl Reminders {
+    /// Reminders of the notes coming due after `now`: those already due were
+    /// seen by then.
+    pub fn new<Tz: TimeZone>(notes: &[Note], now: &DateTime<Tz>) -> Self {
+        let mut reminders = Self::default();
+        reminders.acknowledge(notes, now);
+        reminders
+    }
+
+    /// Takes the notes of `notes` due at `now` as announced, without
+    /// announcing them, e.g. because the user just gave them their due date.
+    pub fn acknowledge<Tz: TimeZone>(&mut self, notes: &[Note], now: &DateTime<Tz>) {
+        self.newly_due(notes, now);
+    }
+
+    /// The notes of `notes` due today or overdue at `now` that weren't
+    /// announced yet, which they now are.
+    pub fn newly_due<'a, Tz: TimeZone>(
+        &mut self,
+        notes: &'a [Note],
+        now: &DateTime<Tz>,
+    ) -> Vec<&'a Note> {
+        notes
+            .iter()
+            .filter(|note| {
+                note.metadata
+                    .due_at
+                    .is_some_and(|due_at| due_status(&due_at, now) != DueStatus::Upcoming)
+                    && self.announced.insert(note.uuid.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod due_test;
@@ -0,0 +1,143 @@
+#![cfg(test)]
+
+use super::*;
+use chrono::{FixedOffset, NaiveDateTime};
+
+fn bad_fixture() -> AppError {
+    AppError::Config("invalid test fixture".to_string())
+}
+
+fn zone(offset_hours: i32) -> Result<FixedOffset, AppError> {
+    FixedOffset::east_opt(offset_hours * 3600).ok_or_else(bad_fixture)
+}
+
+/// `local` (`YYYY-MM-DD HH:MM`) in the zone `offset_hours` away from UTC.
+fn at(offset_hours: i32, local: &str) -> Result<DateTime<FixedOffset>, AppError> {
+    let local =
+        NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").map_err(|_| bad_fixture())?;
+    zone(offset_hours)?
+        .from_local_datetime(&local)
+        .single()
+        .ok_or_else(bad_fixture)
+}
+
+fn utc(s: &str) -> Result<DateTime<Utc>, AppError> {
+    Ok(DateTime::parse_from_rfc3339(s)
+        .map_err(|_| bad_fixture())?
+        .with_timezone(&Utc))
+}
+
+#[test]
+fn test_absolute_date_is_local_midnight() -> Result<(), AppError> {
+    let now = at(2, "2024-06-30 15:00")?;
+    assert_eq!(
+        parse_due_date("2024-07-01", &now)?,
+        utc("2024-06-30T22:00:00Z")?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_relative_dates() -> Result<(), AppError> {
+    let now = at(0, "2024-06-30 15:00")?;
+    assert_eq!(parse_due_date("today", &now)?, utc("2024-06-30T00:00:00Z")?);
+    assert_eq!(
+        parse_due_date("Tomorrow", &now)?,
+        utc("2024-07-01T00:00:00Z")?
+    );
+    assert_eq!(parse_due_date("+3d", &now)?, utc("2024-07-03T00:00:00Z")?);
+    assert_eq!(parse_due_date(" +2w ", &now)?, utc("2024-07-14T00:00:00Z")?);
+    Ok(())
+}
+
+#[test]
+fn test_relative_dates_follow_the_local_day() -> Result<(), AppError> {
+    // 23:30 on June 30 in UTC-5 is already July 1 in UTC
+    let now = at(-5, "2024-06-30 23:30")?;
+    assert_eq!(
+        parse_due_date("tomorrow", &now)?,
+        utc("2024-07-01T05:00:00Z")?
+    );
+
+    // 00:30 on July 1 in UTC+9 is still June 30 in UTC
+    let now = at(9, "2024-07-01 00:30")?;
+    assert_eq!(parse_due_date("today", &now)?, utc("2024-06-30T15:00:00Z")?);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_input() -> Result<(), AppError> {
+    let now = at(0, "2024-06-30 15:00")?;
+    for input in ["", "soon", "+", "+d", "+3", "+3y", "2024-13-01", "+-1d"] {
+        assert!(
+            parse_due_date(input, &now).is_err(),
+            "{input:?} was accepted"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_status_uses_the_local_day() -> Result<(), AppError> {
+    let due = parse_due_date("2024-07-01", &at(2, "2024-06-01 12:00")?)?;
+
+    let status = |local| Ok::<_, AppError>(due_status(&due, &at(2, local)?));
+    assert_eq!(status("2024-06-30 23:59")?, DueStatus::Upcoming);
+    assert_eq!(status("2024-07-01 00:00")?, DueStatus::Today);
+    assert_eq!(status("2024-07-01 23:59")?, DueStatus::Today);
+    assert_eq!(status("2024-07-02 00:00")?, DueStatus::Overdue);
+
+    // Further west, the same instant falls on the day before
+    assert_eq!(
+        due_status(&due, &at(-10, "2024-06-30 12:00")?),
+        DueStatus::Today
+    );
+    assert_eq!(format_due_date(&due, &zone(2)?), "2024-07-01");
+    assert_eq!(format_due_date(&due, &zone(-10)?), "2024-06-30");
+    Ok(())
+}
+
+#[test]
+fn test_reminders_announce_each_note_once_it_comes_due() -> Result<(), AppError> {
+    let note = |uuid: &str, due: &str| -> Result<Note, AppError> {
+        let mut metadata = crate::metadata::NoteMetadata::new(uuid);
+        metadata.due_at = Some(parse_due_date(due, &at(2, "2024-06-01 12:00")?)?);
+        Ok(Note {
+            uuid: uuid.to_string(),
+            metadata,
+        })
+    };
+    let notes = [
+        note("overdue", "2024-06-01")?,
+        note("tomorrow", "2024-07-02")?,
+        note("later", "2024-07-09")?,
+    ];
+
+    let mut reminders = Reminders::new(&notes, &at(2, "2024-07-01 23:00")?);
+    assert!(
+        reminders
+            .newly_due(&notes, &at(2, "2024-07-01 23:59")?)
+            .is_empty()
+    );
+
+    let due = reminders.newly_due(&notes, &at(2, "2024-07-02 00:00")?);
+    assert_eq!(
+        due.iter()
+            .map(|note| note.uuid.as_str())
+            .collect::<Vec<_>>(),
+        ["tomorrow"]
+    );
+    assert!(
+        reminders
+            .newly_due(&notes, &at(2, "2024-07-03 08:00")?)
+            .is_empty()
+    );
+
+    reminders.acknowledge(&notes, &at(2, "2024-07-09 00:00")?);
+    assert!(
+        reminders
+            .newly_due(&notes, &at(2, "2024-07-09 00:01")?)
+            .is_empty()
+    );
+    Ok(())
+}
@@ -0,0 +1,406 @@
+use crossterm::terminal;
+use ryokan::{
+    error::AppError,
+    export, t,
+    template::Position,
+    vault::{EditOutcome, Vault},
+};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+
+/// The decrypted copy of the note being edited, if any. Release builds abort on
+/// panic, so it's removed from the panic hook rather than by unwinding.
+static PLAINTEXT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Deletes the decrypted copy of the note being edited, if there is one.
+pub fn remove_plaintext() {
+    let path = PLAINTEXT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take();
+    if let Some(path) = path {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn set_plaintext(path: Option<PathBuf>) {
+    *PLAINTEXT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = path;
+}
+
+/// Splits an editor setting like `code --wait` into the program and its arguments.
+pub fn split_command(editor: &str) -> Option<(&str, Vec<&str>)> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next()?;
+    Some((program, parts.collect()))
+}
+
+/// Extensions tried, in order, for a program named without one: those in
+/// `PATHEXT` on Windows, none elsewhere.
+fn executable_extensions() -> Vec<String> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    env::var("PATHEXT")
+        .ok()
+        .filter(|pathext| !pathext.trim().is_empty())
+        .unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".to_string())
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Finds `program` in the directories of `path_var` unless it's already a path.
+///
+/// When `extensions` is given and `program` has none, only the names with one of
+/// them appended are tried: on Windows, `code` next to `code.cmd` is a shell
+/// script for other environments and can't be started.
+pub fn find_program(
+    program: &str,
+    path_var: Option<&OsStr>,
+    extensions: &[String],
+) -> Option<PathBuf> {
+    let program = Path::new(program);
+    let candidates: Vec<OsString> = if extensions.is_empty() || program.extension().is_some() {
+        vec![program.as_os_str().to_owned()]
+    } else {
+        extensions
+            .iter()
+            .map(|extension| {
+                let mut name = program.as_os_str().to_owned();
+                name.push(extension);
+                name
+            })
+            .collect()
+    };
+
+    if program.components().count() > 1 {
+        return candidates
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|candidate| candidate.is_file());
+    }
+    env::split_paths(path_var?)
+        .flat_map(|dir| candidates.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Finds the executable for `program` through the real `PATH`.
+pub fn resolve_program(program: &str) -> Option<PathBuf> {
+    find_program(
+        program,
+        env::var_os("PATH").as_deref(),
+        &executable_extensions(),
+    )
+}
+
+/// Finds the executable the editor setting refers to.
+pub fn resolve_editor(editor: &str) -> Option<PathBuf> {
+    let (program, _) = split_command(editor)?;
+    resolve_program(program)
+}
+
+/// How the editors known to take one are told where to put the cursor, by
+/// program name: an argument put before the file, or replacing it if it has
+/// `{path}`. `{line}` and `{column}` are counted from 1, `{byte}` is the column
+/// in bytes.
+const CURSOR_ARGS: [(&str, &str); 11] = [
+    ("vim", "+call cursor({line}, {byte})"),
+    ("nvim", "+call cursor({line}, {byte})"),
+    ("gvim", "+call cursor({line}, {byte})"),
+    ("vi", "+{line}"),
+    ("nano", "+{line},{column}"),
+    ("kak", "+{line}:{column}"),
+    ("micro", "+{line}:{column}"),
+    ("emacs", "+{line}:{column}"),
+    ("emacsclient", "+{line}:{column}"),
+    ("hx", "{path}:{line}:{column}"),
+    ("helix", "{path}:{line}:{column}"),
+];
+
+const CURSOR_PLACEHOLDERS: [&str; 4] = ["{line}", "{column}", "{byte}", "{path}"];
+
+/// Where the editor puts the cursor when it opens a note.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor<'a> {
+    pub position: Position,
+    /// The `editor_cursor_arg` setting, used instead of [`CURSOR_ARGS`].
+    pub arg: Option<&'a str>,
+}
+
+/// Checks an `editor_cursor_arg` setting, which needs `{line}` and only knows
+/// the placeholders of [`CURSOR_ARGS`].
+pub fn check_cursor_arg(arg: &str) -> Result<(), AppError> {
+    let invalid =
+        |reason: &str| AppError::Config(format!("Invalid editor_cursor_arg \"{arg}\": {reason}"));
+    if !arg.contains("{line}") {
+        return Err(invalid("it needs {line}"));
+    }
+    let mut rest = arg.to_string();
+    for placeholder in CURSOR_PLACEHOLDERS {
+        rest = rest.replace(placeholder, "");
+    }
+    if rest.contains(['{', '}']) {
+        return Err(invalid("expected {line}, {column}, {byte} or {path}"));
+    }
+    Ok(())
+}
+
+/// The arguments that open `path` in `program` with the cursor at `cursor`.
+/// Just the path if the editor isn't known to take a position, or the path
+/// can't be put in one.
+fn path_args(program: &str, path: &Path, cursor: Option<Cursor>) -> Vec<OsString> {
+    let just_path = vec![path.as_os_str().to_owned()];
+    let Some(cursor) = cursor else {
+        return just_path;
+    };
+    let name = Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let Some(arg) = cursor.arg.or_else(|| {
+        CURSOR_ARGS
+            .iter()
+            .find(|(editor, _)| *editor == name)
+            .map(|(_, arg)| *arg)
+    }) else {
+        return just_path;
+    };
+    let Position { line, column, byte } = cursor.position;
+    let arg = arg
+        .replace("{line}", &line.to_string())
+        .replace("{column}", &column.to_string())
+        .replace("{byte}", &byte.to_string());
+    if !arg.contains("{path}") {
+        return vec![arg.into(), path.as_os_str().to_owned()];
+    }
+    match path.to_str() {
+        Some(path) => vec![arg.replace("{path}", path).into()],
+        None => just_path,
+    }
+}
+
+/// What to do with an edit after the editor failed, e.g. crashed or was
+/// killed, leaving who knows what in the temporary file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfterFailure {
+    /// Save what the editor left, as if it had exited normally.
+    Keep,
+    /// Leave the note as it was.
+    Discard,
+    /// Open the editor again on what it left.
+    Retry,
+}
+
+/// Starts programs and asks what to do when they fail, so what gets run can be
+/// checked without running anything or anyone answering.
+pub trait CommandRunner {
+    /// Runs `program` with `args` and waits for it to exit, failing with
+    /// [`AppError::EditorFailed`] if it doesn't exit successfully.
+    fn run(&mut self, program: &Path, args: &[OsString]) -> Result<(), AppError>;
+
+    /// Asks what to do with the edit after the editor failed with `error`.
+    fn after_failure(&mut self, error: &AppError) -> Result<AfterFailure, AppError>;
+}
+
+/// Runs programs for real, asking on the terminal.
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&mut self, program: &Path, args: &[OsString]) -> Result<(), AppError> {
+        // Given the full path of a `.cmd` or `.bat` file, the standard library
+        // goes through cmd.exe and escapes the arguments for it
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => AppError::EditorNotFound(program.display().to_string()),
+                _ => AppError::Io(e),
+            })?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(AppError::EditorFailed {
+                program: program.display().to_string(),
+                status: status.to_string(),
+            })
+        }
+    }
+
+    fn after_failure(&mut self, error: &AppError) -> Result<AfterFailure, AppError> {
+        // The TUI is still in raw mode, where nothing typed would show
+        let raw = terminal::is_raw_mode_enabled()?;
+        if raw {
+            terminal::disable_raw_mode()?;
+        }
+        let answer = ask_after_failure(error);
+        if raw {
+            terminal::enable_raw_mode()?;
+        }
+        answer
+    }
+}
+
+/// Asks on the terminal what to do after the editor failed with `error`.
+/// Anything but `k` or `r` leaves the note as it was.
+fn ask_after_failure(error: &AppError) -> Result<AfterFailure, AppError> {
+    eprint!("{error}. {} ", t!("prompt-editor-failed"));
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "k" => AfterFailure::Keep,
+        "r" => AfterFailure::Retry,
+        _ => AfterFailure::Discard,
+    })
+}
+
+/// Runs `editor` on `path` through `runner`, starting `resolved` if the program
+/// was found, with the cursor at `cursor` if the editor can be told.
+fn open_with(
+    runner: &mut dyn CommandRunner,
+    editor: &str,
+    path: &Path,
+    resolved: Option<PathBuf>,
+    cursor: Option<Cursor>,
+) -> Result<(), AppError> {
+    let (program, args) = split_command(editor)
+        .ok_or_else(|| AppError::Config("The editor command is empty".to_string()))?;
+    let path_args = path_args(program, path, cursor);
+    // Otherwise the name is kept, for the error to mention it
+    let program = resolved.unwrap_or_else(|| PathBuf::from(program));
+    let mut args: Vec<OsString> = args.into_iter().map(OsString::from).collect();
+    args.extend(path_args);
+    runner.run(&program, &args)
+}
+
+/// Decrypts a note into a temporary file, opens it in the editor and saves the
+/// result if it changed.
+///
+/// If the note was changed meanwhile by something that doesn't take the vault
+/// lock, like a sync client, the edit is saved as a conflict copy instead of
+/// overwriting it, see [`Vault::save_edit`].
+///
+/// Read-only notes are refused before the editor opens unless `force` is set,
+/// notes with binary content, which a text editor could mangle, unless
+/// `allow_binary` is, and notes that can't be saved with
+/// [`AppError::NotWritable`]. If saving fails anyway, the edit is kept in the
+/// temporary file, see [`AppError::EditNotSaved`].
+///
+/// If the editor fails, e.g. crashes or is killed, what it left is only saved
+/// if the user chooses to keep it, see [`AfterFailure`]; the note is left as
+/// it was otherwise. An editor that can't be found fails with
+/// [`AppError::EditorNotFound`].
+pub fn edit_note(
+    vault: &Vault,
+    uuid: &str,
+    editor: &str,
+    force: bool,
+    allow_binary: bool,
+    cursor: Option<Cursor>,
+) -> Result<EditOutcome, AppError> {
+    edit_note_with(
+        &mut SystemRunner,
+        vault,
+        uuid,
+        editor,
+        force,
+        allow_binary,
+        cursor,
+    )
+}
+
+fn edit_note_with(
+    runner: &mut dyn CommandRunner,
+    vault: &Vault,
+    uuid: &str,
+    editor: &str,
+    force: bool,
+    allow_binary: bool,
+    cursor: Option<Cursor>,
+) -> Result<EditOutcome, AppError> {
+    let note = vault.get(uuid)?;
+    if note.metadata.read_only && !force {
+        return Err(AppError::ReadOnlyNote(uuid.to_string()));
+    }
+
+    // Changes made meanwhile by other processes, like `ryokan append`, would be
+    // overwritten on save, so they wait for the editor to close
+    let _lock = vault.lock()?;
+
+    // Better told now than after the edit is written
+    vault.check_writable(uuid)?;
+    let revision = vault.revision(uuid)?;
+    let content = Zeroizing::new(vault.read(uuid)?);
+    if !allow_binary && !export::is_text(&content) {
+        return Err(AppError::BinaryNote(uuid.to_string()));
+    }
+
+    // Keep the plaintext next to the notes rather than in a shared temp directory.
+    // Without one, the temp directory is per user on Windows (%TEMP%), and the
+    // file is only readable by its owner elsewhere
+    let mut temp_file = match vault.dir() {
+        Some(dir) => NamedTempFile::new_in(dir),
+        None => NamedTempFile::new(),
+    }
+    .map_err(AppError::Io)?;
+    temp_file.write_all(&content).map_err(AppError::Io)?;
+
+    set_plaintext(Some(temp_file.path().to_path_buf()));
+    // Whether to save what the editor left
+    let keep = loop {
+        let opened = open_with(
+            runner,
+            editor,
+            temp_file.path(),
+            resolve_editor(editor),
+            cursor,
+        );
+        match opened {
+            Err(e @ AppError::EditorFailed { .. }) => match runner.after_failure(&e) {
+                Ok(AfterFailure::Keep) => break Ok(true),
+                Ok(AfterFailure::Discard) => break Ok(false),
+                Ok(AfterFailure::Retry) => {}
+                Err(e) => break Err(e),
+            },
+            opened => break opened.map(|()| true),
+        }
+    };
+    let result = keep.and_then(|keep| {
+        keep.then(|| fs::read(temp_file.path()).map_err(AppError::Io))
+            .transpose()
+    });
+    set_plaintext(None);
+
+    // Saved as is, byte for byte; an unchanged note isn't rewritten at all
+    let Some(edited) = result? else {
+        return Ok(EditOutcome::Unchanged);
+    };
+    let edited = Zeroizing::new(edited);
+    if *edited == *content {
+        return Ok(EditOutcome::Unchanged);
+    }
+    match vault.save_edit(uuid, &revision, &edited) {
+        // The temporary file may be the only good copy of the edit left
+        Err(e) => {
+            let path = temp_file.into_temp_path().keep().map_err(|e| e.error)?;
+            Err(AppError::EditNotSaved {
+                path,
+                source: Box::new(e),
+            })
+        }
+        outcome => outcome,
+    }
+}
+
+#[cfg(test)]
+mod editor_test;
@@ -0,0 +1,455 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+/// Records what would have been run instead of running it.
+#[derive(Debug, Default)]
+struct RecordingRunner {
+    runs: Vec<(PathBuf, Vec<OsString>)>,
+}
+
+impl CommandRunner for RecordingRunner {
+    fn run(&mut self, program: &Path, args: &[OsString]) -> Result<(), AppError> {
+        self.runs.push((program.to_path_buf(), args.to_vec()));
+        Ok(())
+    }
+
+    fn after_failure(&mut self, error: &AppError) -> Result<AfterFailure, AppError> {
+        panic!("nothing failed, but got {error}");
+    }
+}
+
+fn windows_extensions() -> Vec<String> {
+    [".com", ".exe", ".bat", ".cmd"]
+        .map(str::to_string)
+        .to_vec()
+}
+
+#[test]
+fn test_find_program_prefers_windows_shims() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    // Like VS Code's bin directory: a shell script and the shim Windows can start
+    fs::write(dir.path().join("code"), "")?;
+    fs::write(dir.path().join("code.cmd"), "")?;
+    let path_var = env::join_paths([dir.path()]).map_err(|e| AppError::Config(e.to_string()))?;
+
+    assert_eq!(
+        find_program("code", Some(&path_var), &windows_extensions()),
+        Some(dir.path().join("code.cmd"))
+    );
+    assert_eq!(
+        find_program("code", Some(&path_var), &[]),
+        Some(dir.path().join("code"))
+    );
+    assert_eq!(
+        find_program("code.cmd", Some(&path_var), &windows_extensions()),
+        Some(dir.path().join("code.cmd"))
+    );
+    assert_eq!(find_program("vim", Some(&path_var), &[]), None);
+    assert_eq!(find_program("code", None, &[]), None);
+    Ok(())
+}
+
+#[test]
+fn test_find_program_with_a_path() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let shim = dir.path().join("edit.bat");
+    fs::write(&shim, "")?;
+    let without_extension = dir.path().join("edit");
+
+    let found = find_program(
+        &without_extension.to_string_lossy(),
+        None,
+        &windows_extensions(),
+    );
+    assert_eq!(found, Some(shim));
+    assert_eq!(
+        find_program(&without_extension.to_string_lossy(), None, &[]),
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn test_open_with_passes_the_file_last() -> Result<(), AppError> {
+    let mut runner = RecordingRunner::default();
+    let resolved = PathBuf::from("/opt/code/bin/code.cmd");
+    open_with(
+        &mut runner,
+        "code --wait",
+        Path::new("notes/tmp1234"),
+        Some(resolved.clone()),
+        None,
+    )?;
+    open_with(&mut runner, "nano", Path::new("tmp5678"), None, None)?;
+
+    assert_eq!(
+        runner.runs,
+        [
+            (
+                resolved,
+                vec![OsString::from("--wait"), OsString::from("notes/tmp1234")]
+            ),
+            (PathBuf::from("nano"), vec![OsString::from("tmp5678")]),
+        ]
+    );
+    assert!(open_with(&mut runner, "  ", Path::new("tmp"), None, None).is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_open_with_keeps_non_utf8_paths() -> Result<(), AppError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut runner = RecordingRunner::default();
+    let path = Path::new(OsStr::from_bytes(b"notes/\xffnote"));
+    open_with(&mut runner, "vi", path, None, None)?;
+
+    assert_eq!(runner.runs[0].1, [path.as_os_str().to_owned()]);
+    Ok(())
+}
+
+#[test]
+fn test_open_with_puts_the_cursor_where_the_editor_takes_it() -> Result<(), AppError> {
+    let position = Position {
+        line: 3,
+        column: 2,
+        byte: 4,
+    };
+    let cursor = |arg| Some(Cursor { position, arg });
+    let path = Path::new("tmp1234");
+    let mut runner = RecordingRunner::default();
+    for editor in ["nvim", "/usr/bin/nano", "hx", "ed", "code --wait"] {
+        open_with(&mut runner, editor, path, None, cursor(None))?;
+    }
+    open_with(
+        &mut runner,
+        "code --wait --goto",
+        path,
+        None,
+        cursor(Some("{path}:{line}:{column}")),
+    )?;
+
+    let args: Vec<Vec<String>> = runner
+        .runs
+        .into_iter()
+        .map(|(_, args)| {
+            args.iter()
+                .map(|arg| arg.to_string_lossy().into())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        args,
+        [
+            vec!["+call cursor(3, 4)", "tmp1234"],
+            vec!["+3,2", "tmp1234"],
+            vec!["tmp1234:3:2"],
+            vec!["tmp1234"],
+            vec!["--wait", "tmp1234"],
+            vec!["--wait", "--goto", "tmp1234:3:2"],
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_cursor_arg() {
+    assert!(check_cursor_arg("+{line}:{column}").is_ok());
+    assert!(check_cursor_arg("{path}:{line}:{byte}").is_ok());
+    assert!(check_cursor_arg("+{column}").is_err());
+    assert!(check_cursor_arg("+{line}:{col}").is_err());
+}
+
+#[test]
+fn test_binary_content_round_trips_unchanged() -> Result<(), AppError> {
+    use rand::Rng;
+    use ryokan::datetime::DateFormat;
+    use ryokan::export::ExportFormat;
+    use ryokan::storage::MemoryStorage;
+    use std::sync::Arc;
+
+    let vault = Vault::with_storage(
+        Arc::new(MemoryStorage::new()),
+        Zeroizing::new("123456".to_string()),
+    );
+    let dates = DateFormat::default();
+    for len in [1, 17, 4096] {
+        // A leading 0xff is never valid UTF-8
+        let mut payload = vec![0xff; len];
+        rand::rng().fill_bytes(&mut payload[1..]);
+        let note = vault.create("Blob", &payload)?;
+
+        let mut runner = RecordingRunner::default();
+        assert!(matches!(
+            edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None),
+            Err(AppError::BinaryNote(_))
+        ));
+        assert!(runner.runs.is_empty());
+        edit_note_with(&mut runner, &vault, &note.uuid, "true", false, true, None)?;
+        assert_eq!(runner.runs.len(), 1);
+
+        let content = vault.read(&note.uuid)?;
+        assert_eq!(content, payload);
+        let note = vault.get(&note.uuid)?;
+        let text = export::render(ExportFormat::Text, &note, &content, false, &dates)?;
+        assert_eq!(text, payload);
+        let markdown = export::render(ExportFormat::MdFrontmatter, &note, &content, false, &dates)?;
+        assert!(markdown.starts_with(b"---\n"));
+        assert!(markdown.ends_with(&payload));
+        assert!(matches!(
+            export::render(ExportFormat::Html, &note, &content, false, &dates),
+            Err(AppError::BinaryNote(_))
+        ));
+    }
+    Ok(())
+}
+
+/// Stands in for an editor: writes `edited` to the file it opens, after running
+/// `meanwhile`, e.g. to change the note like a sync would.
+struct EditingRunner<F: FnMut()> {
+    edited: &'static [u8],
+    meanwhile: F,
+}
+
+impl<F: FnMut()> CommandRunner for EditingRunner<F> {
+    fn run(&mut self, _program: &Path, args: &[OsString]) -> Result<(), AppError> {
+        (self.meanwhile)();
+        let path = args
+            .last()
+            .ok_or_else(|| AppError::Config("No file to edit".to_string()))?;
+        fs::write(path, self.edited).map_err(AppError::Io)
+    }
+
+    fn after_failure(&mut self, error: &AppError) -> Result<AfterFailure, AppError> {
+        panic!("nothing failed, but got {error}");
+    }
+}
+
+/// Stands in for an editor that's killed after writing `partial` while there
+/// are `answers` left, and writes `edited` and exits normally once there aren't.
+struct CrashingRunner {
+    partial: &'static [u8],
+    edited: &'static [u8],
+    /// What the user answers after each crash, last first.
+    answers: Vec<AfterFailure>,
+    runs: usize,
+}
+
+impl CommandRunner for CrashingRunner {
+    fn run(&mut self, _program: &Path, args: &[OsString]) -> Result<(), AppError> {
+        self.runs += 1;
+        let path = args
+            .last()
+            .ok_or_else(|| AppError::Config("No file to edit".to_string()))?;
+        if self.answers.is_empty() {
+            return fs::write(path, self.edited).map_err(AppError::Io);
+        }
+        fs::write(path, self.partial)?;
+        Err(AppError::EditorFailed {
+            program: "vim".to_string(),
+            status: "signal: 9 (SIGKILL)".to_string(),
+        })
+    }
+
+    fn after_failure(&mut self, _error: &AppError) -> Result<AfterFailure, AppError> {
+        self.answers
+            .pop()
+            .ok_or_else(|| AppError::Config("Asked once too often".to_string()))
+    }
+}
+
+#[test]
+fn test_edit_of_a_note_changed_meanwhile_becomes_a_conflict_copy() -> Result<(), AppError> {
+    use ryokan::storage::{MemoryStorage, Storage};
+    use ryokan::sync::CONFLICT_TITLE_SUFFIX;
+    use std::sync::Arc;
+
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    let note = vault.create("Plans", b"draft")?;
+
+    let mut runner = EditingRunner {
+        edited: b"edited here",
+        meanwhile: || {},
+    };
+    let outcome = edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None)?;
+    assert!(matches!(outcome, EditOutcome::Saved));
+    assert_eq!(vault.read(&note.uuid)?, b"edited here");
+
+    // Another machine's version arrives while the editor is open
+    let synced = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    let mut runner = EditingRunner {
+        edited: b"edited again",
+        meanwhile: || {
+            let _ = synced.force_write(&note.uuid, b"synced");
+        },
+    };
+    let outcome = edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None)?;
+    let EditOutcome::Conflict(copy) = outcome else {
+        return Err(AppError::Config(format!(
+            "Expected a conflict copy, got {outcome:?}"
+        )));
+    };
+    assert_eq!(vault.read(&note.uuid)?, b"synced");
+    assert_eq!(vault.read(&copy.uuid)?, b"edited again");
+    assert_eq!(
+        copy.metadata.conflict_of.as_deref(),
+        Some(note.uuid.as_str())
+    );
+    assert_eq!(
+        copy.metadata.original_filename,
+        format!("Plans{CONFLICT_TITLE_SUFFIX}")
+    );
+    assert!(storage.exists(&ryokan::file::note_file_names(&copy.uuid).0));
+
+    let mut runner = EditingRunner {
+        edited: b"synced",
+        meanwhile: || {},
+    };
+    let outcome = edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None)?;
+    assert!(matches!(outcome, EditOutcome::Unchanged));
+    Ok(())
+}
+
+#[test]
+fn test_what_a_failed_editor_left_is_only_saved_if_kept() -> Result<(), AppError> {
+    use ryokan::storage::MemoryStorage;
+    use std::sync::Arc;
+
+    let vault = Vault::with_storage(
+        Arc::new(MemoryStorage::new()),
+        Zeroizing::new("123456".to_string()),
+    );
+    let note = vault.create("Plans", b"the whole plan")?;
+    let crashing = |answers: Vec<AfterFailure>| CrashingRunner {
+        partial: b"the wh",
+        edited: b"the whole plan, edited",
+        answers,
+        runs: 0,
+    };
+
+    let mut runner = crashing(vec![AfterFailure::Discard]);
+    let outcome = edit_note_with(&mut runner, &vault, &note.uuid, "vim", false, false, None)?;
+    assert!(matches!(outcome, EditOutcome::Unchanged));
+    assert_eq!(vault.read(&note.uuid)?, b"the whole plan");
+
+    let mut runner = crashing(vec![AfterFailure::Retry]);
+    edit_note_with(&mut runner, &vault, &note.uuid, "vim", false, false, None)?;
+    assert_eq!(runner.runs, 2);
+    assert_eq!(vault.read(&note.uuid)?, b"the whole plan, edited");
+
+    let mut runner = crashing(vec![AfterFailure::Keep]);
+    edit_note_with(&mut runner, &vault, &note.uuid, "vim", false, false, None)?;
+    assert_eq!(vault.read(&note.uuid)?, b"the wh");
+    Ok(())
+}
+
+#[test]
+fn test_a_missing_editor_is_named() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let result = SystemRunner.run(&dir.path().join("no-such-editor"), &[]);
+    let Err(e @ AppError::EditorNotFound(_)) = result else {
+        return Err(AppError::Config(format!(
+            "Expected the editor not to be found, got {result:?}"
+        )));
+    };
+    assert!(e.to_string().contains("no-such-editor"), "{e}");
+    assert!(e.to_string().contains("config set editor"), "{e}");
+    Ok(())
+}
+
+/// A storage whose writes fail once `broken` is set, like a drive remounted
+/// read-only.
+struct BreakingStorage {
+    inner: ryokan::storage::MemoryStorage,
+    broken: std::sync::atomic::AtomicBool,
+}
+
+impl BreakingStorage {
+    fn check(&self) -> Result<(), AppError> {
+        if self.broken.load(std::sync::atomic::Ordering::Relaxed) {
+            Err(AppError::Io(std::io::ErrorKind::ReadOnlyFilesystem.into()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl ryokan::storage::Storage for BreakingStorage {
+    fn list(&self, dir: &str) -> Result<Vec<ryokan::storage::Entry>, AppError> {
+        self.inner.list(dir)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        self.inner.list_dirs(dir)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        self.inner.read(name)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        self.check()?;
+        self.inner.write(name, data)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.check()?;
+        self.inner.delete(name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.check()?;
+        self.inner.rename(from, to)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.inner.exists(name)
+    }
+
+    fn check_writable(&self, _name: &str) -> Result<(), AppError> {
+        self.check()
+    }
+}
+
+#[test]
+fn test_unwritable_notes_are_refused_before_editing_and_failed_edits_kept() -> Result<(), AppError>
+{
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+
+    let storage = Arc::new(BreakingStorage {
+        inner: ryokan::storage::MemoryStorage::new(),
+        broken: false.into(),
+    });
+    let vault = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    let note = vault.create("Plans", b"draft")?;
+
+    storage.broken.store(true, Ordering::Relaxed);
+    let mut runner = RecordingRunner::default();
+    assert!(matches!(
+        edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None),
+        Err(AppError::NotWritable { .. })
+    ));
+    assert!(runner.runs.is_empty());
+
+    // Remounted read-only while the editor is open
+    storage.broken.store(false, Ordering::Relaxed);
+    let mut runner = EditingRunner {
+        edited: b"edited",
+        meanwhile: || storage.broken.store(true, Ordering::Relaxed),
+    };
+    let result = edit_note_with(&mut runner, &vault, &note.uuid, "true", false, false, None);
+    let Err(AppError::EditNotSaved { path, .. }) = result else {
+        return Err(AppError::Config(format!(
+            "Expected the edit to be kept, got {result:?}"
+        )));
+    };
+    assert_eq!(fs::read(&path)?, b"edited");
+    assert_eq!(vault.read(&note.uuid)?, b"draft");
+    fs::remove_file(path)?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use std::io;
+//! The error type shared by the whole crate.
+
+use std::{io, path::PathBuf};
 
 use thiserror::Error;
 
@@ -16,8 +18,74 @@ pub enum AppError {
     Pin(String),
     #[error("PIN hash error: {0}")]
     PinHash(String),
+    #[error("Note not found: {0}")]
+    NoteNotFound(String),
+    #[error("More than one note matches \"{0}\", use its UUID instead")]
+    AmbiguousNote(String),
+    #[error("Note {0} is read-only")]
+    ReadOnlyNote(String),
+    #[error("Note {0} has binary content")]
+    BinaryNote(String),
+    /// A note larger than the limit set by `key`, refused before reading it.
+    #[error(
+        "Note {uuid} is {size} bytes, more than {key} allows ({limit} bytes); raise it with `ryokan config set {} <bytes>`",
+        .key.replace('_', "-")
+    )]
+    TooLarge {
+        uuid: String,
+        size: u64,
+        limit: u64,
+        key: &'static str,
+    },
+    #[error("Permission error: {0}")]
+    Permissions(String),
+    /// A note that can't be saved, e.g. on a read-only mount, found before
+    /// editing it.
+    #[error("Note {uuid} can't be saved: {reason}")]
+    NotWritable { uuid: String, reason: String },
+    /// An edit that couldn't be saved, kept in a plaintext file so it isn't lost.
+    #[error("{source}; your edit is kept in {}", .path.display())]
+    EditNotSaved {
+        path: PathBuf,
+        #[source]
+        source: Box<AppError>,
+    },
+    /// The editor program doesn't exist, named as it was tried.
+    #[error(
+        "The editor \"{0}\" wasn't found; install it or set another with `ryokan config set editor <command>` or the EDITOR variable"
+    )]
+    EditorNotFound(String),
+    /// The editor exited with a failure, e.g. it crashed or was killed.
+    #[error("The editor {program} failed ({status})")]
+    EditorFailed { program: String, status: String },
+    #[error("Write verification failed: {0}")]
+    WriteVerification(String),
+    #[error("Read-only mode, changes are disabled")]
+    ReadOnlyMode,
+    #[error("An interrupted {0} has to be resumed or rolled back first")]
+    Interrupted(String),
+    #[error("Vault not found at {}, is the drive mounted?", .0.display())]
+    VaultNotFound(PathBuf),
+    #[error("Bundle error: {0}")]
+    Bundle(String),
+    #[error("Import error: {0}")]
+    Import(String),
+    #[error("age error: {0}")]
+    Age(String),
+    #[error("Sync error: {0}")]
+    Sync(String),
+    #[error("Backup error: {0}")]
+    Backup(String),
+    #[error("Could not read the clipboard: {0}")]
+    Clipboard(String),
     #[error("TUI error: {0}")]
     Tui(String),
+    #[error("Metadata file {path}: {source}")]
+    Metadata {
+        path: String,
+        #[source]
+        source: Box<AppError>,
+    },
 
     #[error("TOML deserialize error: {0}")]
     TomlDeserialize(#[from] toml::de::Error),
@@ -0,0 +1,264 @@
+//! Rendering notes for sharing outside Ryokan.
+
+use crate::datetime::DateFormat;
+use crate::error::AppError;
+use crate::metadata::NoteMetadata;
+use crate::vault::Note;
+use pulldown_cmark::{Event, Options, Parser, html};
+use serde::Serialize;
+use std::{fmt, str::FromStr};
+
+/// Output formats of `show`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The content exactly as stored.
+    #[default]
+    Text,
+    /// A standalone HTML document.
+    Html,
+    /// The content preceded by a YAML header of its metadata.
+    MdFrontmatter,
+    /// A JSON object of the metadata and the content, see [`JsonNote`].
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Text,
+        ExportFormat::Html,
+        ExportFormat::MdFrontmatter,
+        ExportFormat::Json,
+    ];
+
+    /// Extension of files in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text | ExportFormat::MdFrontmatter => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Text => write!(f, "text"),
+            ExportFormat::Html => write!(f, "html"),
+            ExportFormat::MdFrontmatter => write!(f, "md-frontmatter"),
+            ExportFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AppError::Config(format!("Unknown export format \"{s}\"")))
+    }
+}
+
+/// Whether decrypted content is text, i.e. valid UTF-8.
+///
+/// Anything else is binary, and is only ever handled as bytes so it can't be
+/// altered by a lossy conversion.
+pub fn is_text(content: &[u8]) -> bool {
+    std::str::from_utf8(content).is_ok()
+}
+
+/// Shows `content` as lines of 16 bytes: the offset, the bytes in hex and the
+/// printable ASCII ones.
+pub fn hex_dump(content: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, bytes) in content.chunks(16).enumerate() {
+        let hex: Vec<_> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        let ascii: String = bytes
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump.push_str(&format!(
+            "{:08x}  {:<47}  {ascii}\n",
+            line * 16,
+            hex.join(" ")
+        ));
+    }
+    dump
+}
+
+/// Renders a note in `format`, keeping its content byte for byte where the
+/// format allows it.
+///
+/// Binary content can't be rendered to HTML, which fails with
+/// [`AppError::BinaryNote`].
+pub fn render(
+    format: ExportFormat,
+    note: &Note,
+    content: &[u8],
+    allow_html: bool,
+    dates: &DateFormat,
+) -> Result<Vec<u8>, AppError> {
+    match format {
+        ExportFormat::Text => Ok(content.to_vec()),
+        ExportFormat::Html => {
+            let content = std::str::from_utf8(content)
+                .map_err(|_| AppError::BinaryNote(note.uuid.clone()))?;
+            Ok(to_html(&note.metadata, content, allow_html).into_bytes())
+        }
+        ExportFormat::MdFrontmatter => {
+            Ok([frontmatter(&note.metadata, dates).as_bytes(), content].concat())
+        }
+        ExportFormat::Json => to_json(&JsonNote::new(note, content, dates)?),
+    }
+}
+
+/// A note as exported to JSON for other programs, with its dates in RFC 3339.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct JsonNote {
+    pub uuid: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub created: String,
+    pub updated: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    pub content: String,
+}
+
+impl JsonNote {
+    /// `note` with its `content`, which fails with [`AppError::BinaryNote`] if
+    /// it isn't text.
+    pub fn new(note: &Note, content: &[u8], dates: &DateFormat) -> Result<Self, AppError> {
+        let metadata = &note.metadata;
+        let content =
+            std::str::from_utf8(content).map_err(|_| AppError::BinaryNote(note.uuid.clone()))?;
+        Ok(Self {
+            uuid: note.uuid.clone(),
+            title: metadata.original_filename.clone(),
+            tags: metadata.tags.clone(),
+            created: dates.rfc3339(&metadata.created_at),
+            updated: dates.rfc3339(&metadata.updated_at),
+            due: metadata.due_at.map(|due_at| dates.rfc3339(&due_at)),
+            content: content.to_string(),
+        })
+    }
+}
+
+/// Pretty-printed JSON of `value`, e.g. a [`JsonNote`] or a list of them.
+pub fn to_json(value: &impl Serialize) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec_pretty(value)
+        .map_err(|e| AppError::Config(format!("Could not serialize notes: {e}")))
+}
+
+const STYLE: &str = "body{max-width:46em;margin:2em auto;padding:0 1em;\
+font-family:system-ui,sans-serif;line-height:1.6;color:#222}\
+pre,code{background:#f4f4f4;border-radius:3px}pre{padding:.8em;overflow-x:auto}\
+blockquote{margin-left:0;padding-left:1em;border-left:3px solid #ccc;color:#555}\
+table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:.3em .6em}\
+img{max-width:100%}";
+
+/// Renders Markdown `content` to a standalone HTML document titled after the note.
+///
+/// Raw HTML in the note is escaped and shown as text unless `allow_html` is set,
+/// so opening the export in a browser can't run scripts hidden in the note.
+pub fn to_html(metadata: &NoteMetadata, content: &str, allow_html: bool) -> String {
+    let parser = Parser::new_ext(content, Options::all()).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) if !allow_html => Event::Text(raw),
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n\
+         <style>{STYLE}</style>\n\
+         </head>\n\
+         <body>\n\
+         {body}\
+         </body>\n\
+         </html>\n",
+        escape_html(&metadata.original_filename)
+    )
+}
+
+/// Prefixes `content` with a YAML header holding the metadata of the note.
+///
+/// Timestamps are written in RFC 3339, whatever `date_format` says, so other
+/// programs can read them, but with the local offset of `dates`.
+pub fn to_markdown_with_frontmatter(
+    metadata: &NoteMetadata,
+    content: &str,
+    dates: &DateFormat,
+) -> String {
+    frontmatter(metadata, dates) + content
+}
+
+/// The YAML header of [`to_markdown_with_frontmatter`], up to its closing `---`.
+fn frontmatter(metadata: &NoteMetadata, dates: &DateFormat) -> String {
+    let mut header = String::from("---\n");
+    header.push_str(&format!(
+        "title: {}\n",
+        yaml_string(&metadata.original_filename)
+    ));
+    header.push_str(&format!(
+        "created: {}\n",
+        dates.rfc3339(&metadata.created_at)
+    ));
+    header.push_str(&format!(
+        "updated: {}\n",
+        dates.rfc3339(&metadata.updated_at)
+    ));
+    for (key, values) in [("tags", &metadata.tags), ("aliases", &metadata.aliases)] {
+        if !values.is_empty() {
+            header.push_str(&format!("{key}:\n"));
+            for value in values {
+                header.push_str(&format!("  - {}\n", yaml_string(value)));
+            }
+        }
+    }
+    if let Some(due_at) = metadata.due_at {
+        header.push_str(&format!("due: {}\n", dates.rfc3339(&due_at)));
+    }
+    if metadata.read_only {
+        header.push_str("read_only: true\n");
+    }
+    header.push_str("---\n");
+    header
+}
+
+/// Quotes a string for YAML; a JSON string is a valid double-quoted YAML scalar.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value.escape_default()))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod export_test;
@@ -0,0 +1,71 @@
+#![cfg(test)]
+
+use super::*;
+use crate::import::parse_frontmatter;
+
+#[test]
+fn test_raw_html_is_escaped_by_default() {
+    let metadata = NoteMetadata::new("<b>Title</b>");
+    let content = "# Hello\n\n<script>alert(1)</script>\n\nInline <img src=x onerror=alert(1)> too";
+
+    let escaped = to_html(&metadata, content, false);
+    assert!(!escaped.contains("<script>"));
+    assert!(!escaped.contains("<img"));
+    assert!(escaped.contains("&lt;script&gt;"));
+    assert!(escaped.contains("<title>&lt;b&gt;Title&lt;/b&gt;</title>"));
+    assert!(escaped.contains("<h1>Hello</h1>"));
+
+    let raw = to_html(&metadata, content, true);
+    assert!(raw.contains("<script>alert(1)</script>"));
+}
+
+#[test]
+fn test_frontmatter_round_trips_through_the_importer() {
+    let mut metadata = NoteMetadata::new("Quotes \"and\" colons: too");
+    metadata.tags = vec!["work".to_string(), "a b".to_string()];
+
+    let exported = to_markdown_with_frontmatter(&metadata, "Body\n", &DateFormat::default());
+    assert!(exported.ends_with("---\nBody\n"));
+
+    let parsed = parse_frontmatter(&exported);
+    assert_eq!(parsed.as_ref().map(|f| &f.tags), Some(&metadata.tags));
+    assert_eq!(parsed.and_then(|f| f.created), Some(metadata.created_at));
+}
+
+#[test]
+fn test_hex_dump_shows_offsets_and_printable_bytes() {
+    assert!(is_text("héllo".as_bytes()));
+    assert!(!is_text(b"\xffhello"));
+
+    let dump = hex_dump(b"Hello, \x00\xff world! 0123");
+    assert_eq!(
+        dump,
+        "00000000  48 65 6c 6c 6f 2c 20 00 ff 20 77 6f 72 6c 64 21  Hello, .. world!\n\
+         00000010  20 30 31 32 33                                    0123\n"
+    );
+}
+
+#[test]
+fn test_json_export_has_the_metadata_and_content() -> Result<(), AppError> {
+    let mut metadata = NoteMetadata::new("Plan");
+    metadata.tags = vec!["work".to_string()];
+    let note = Note {
+        uuid: "uuid".to_string(),
+        metadata,
+    };
+    let dates = DateFormat::default();
+
+    let rendered = render(ExportFormat::Json, &note, b"Ship it\n", false, &dates)?;
+    let json: serde_json::Value = serde_json::from_slice(&rendered)
+        .map_err(|e| AppError::Config(format!("Invalid JSON: {e}")))?;
+    assert_eq!(json["title"], "Plan");
+    assert_eq!(json["tags"], serde_json::json!(["work"]));
+    assert_eq!(json["content"], "Ship it\n");
+    assert_eq!(json["created"], dates.rfc3339(&note.metadata.created_at));
+    assert!(json.get("due").is_none());
+    assert!(matches!(
+        JsonNote::new(&note, &[0xff], &dates),
+        Err(AppError::BinaryNote(_))
+    ));
+    Ok(())
+}
@@ -1,35 +1,136 @@
+//! Layout of notes in storage: file naming and the magic header.
+
+use crate::dedupe::HASH_CACHE_FILE_NAME;
 use crate::error::AppError;
+use crate::ignorefile::IGNORE_FILE_NAME;
+use crate::index::INDEX_FILE_NAME;
+use crate::lock::LOCK_FILE_NAME;
+use crate::manifest::MANIFEST_FILE_NAME;
+use crate::marker::MARKER_FILE_NAME;
 use crate::metadata::NoteMetadata;
-use crate::{args::Args, note};
+use crate::note;
+use crate::recovery::RECOVERY_FILE_NAME;
+use crate::storage::Storage;
+use crate::vaultinfo::VAULT_INFO_FILE_NAME;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use log::info;
-use std::fs;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
-use std::process::Command;
 use uuid::Uuid;
 
 pub const MAGIC_BYTES: &[u8] = b"RYOKAN_ENCRYPTED";
+/// Version of the layout of an encrypted note: [`MAGIC_BYTES`], then the salt,
+/// nonce and ciphertext. Files don't carry it, so a new layout needs new magic
+/// bytes.
+pub const NOTE_FORMAT_VERSION: u32 = 1;
+
+const CONTENT_SUFFIX: &str = ".enc.txt";
+const METADATA_SUFFIX: &str = ".meta.toml";
+
+/// Directory holding the previous versions of every note, one subdirectory per UUID.
+pub const HISTORY_DIR: &str = "history";
+/// Directory notes are moved to instead of being deleted outright.
+pub const TRASH_DIR: &str = "trash";
+const VERSION_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+/// Which half of a note a file in the notes directory holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteFileKind {
+    /// The encrypted content, `<uuid>.enc.txt`.
+    Content,
+    /// The plaintext metadata, `<uuid>.meta.toml`.
+    Metadata,
+}
 
+/// Ryokan's own files at the root of the notes directory, other than notes.
+const OWN_FILES: [&str; 8] = [
+    INDEX_FILE_NAME,
+    LOCK_FILE_NAME,
+    MARKER_FILE_NAME,
+    HASH_CACHE_FILE_NAME,
+    MANIFEST_FILE_NAME,
+    RECOVERY_FILE_NAME,
+    IGNORE_FILE_NAME,
+    VAULT_INFO_FILE_NAME,
+];
+
+/// Tells which half of a note a file holds from its suffix alone, whatever
+/// comes before it.
+pub fn note_file_kind(file_name: &str) -> Option<NoteFileKind> {
+    if file_name.ends_with(CONTENT_SUFFIX) {
+        Some(NoteFileKind::Content)
+    } else if file_name.ends_with(METADATA_SUFFIX) {
+        Some(NoteFileKind::Metadata)
+    } else {
+        None
+    }
+}
+
+/// Splits a note file name into its UUID and kind.
+///
+/// Returns `None` for files that don't belong to a note: everything before the
+/// suffix has to be a UUID.
+pub fn parse_note_file_name(file_name: &str) -> Option<(&str, NoteFileKind)> {
+    let kind = note_file_kind(file_name)?;
+    let suffix = match kind {
+        NoteFileKind::Content => CONTENT_SUFFIX,
+        NoteFileKind::Metadata => METADATA_SUFFIX,
+    };
+    let uuid = file_name.strip_suffix(suffix)?;
+    // Only the hyphenated form that `generate_uuid` produces
+    (uuid.len() == 36 && Uuid::try_parse(uuid).is_ok()).then_some((uuid, kind))
+}
+
+/// Whether `file_name` is one of Ryokan's own files, such as the index.
+pub fn is_own_file(file_name: &str) -> bool {
+    OWN_FILES.contains(&file_name)
+}
+
+/// Whether a file at the root of the notes directory is neither part of a note
+/// nor one of Ryokan's own files.
+pub fn is_unexpected_file(file_name: &str) -> bool {
+    parse_note_file_name(file_name).is_none() && !is_own_file(file_name)
+}
+
+/// Returns whether `data` starts with the Ryokan magic header.
 pub fn is_encrypted_file(data: &[u8]) -> bool {
     data.starts_with(MAGIC_BYTES)
 }
 
+/// Returns whether the file `name` starts with the Ryokan magic header, reading
+/// only the header.
+pub fn has_magic_header(storage: &dyn Storage, name: &str) -> Result<bool, AppError> {
+    Ok(is_encrypted_file(
+        &storage.read_prefix(name, MAGIC_BYTES.len())?,
+    ))
+}
+
+/// Encrypts `content` into a new note described by `metadata`.
+///
+/// Returns the UUID of the created note.
 pub fn create_new_note(
-    notes_dir: &Path,
+    storage: &dyn Storage,
+    pin: &str,
+    metadata: &NoteMetadata,
+    content: &[u8],
+) -> Result<String, AppError> {
+    let uuid = generate_uuid();
+    write_note(storage, pin, &uuid, metadata, content)?;
+    Ok(uuid)
+}
+
+/// Encrypts `content` into the note `uuid`, replacing both of its files.
+pub fn write_note(
+    storage: &dyn Storage,
     pin: &str,
-    original_filename: &str,
+    uuid: &str,
+    metadata: &NoteMetadata,
     content: &[u8],
 ) -> Result<(), AppError> {
     let encrypted_content = note::encrypt_note_content(content, pin)?;
-    let metadata = NoteMetadata::new(original_filename);
-
-    let uuid = generate_uuid();
-    let (encrypted_note_path, metadata_path) = note_paths(notes_dir, &uuid);
+    let (encrypted_note_name, metadata_name) = note_file_names(uuid);
 
     // Save metadata first, then encrypted content
-    metadata.save(&metadata_path)?;
-    save_note_to_file(&encrypted_content, &encrypted_note_path)?;
+    metadata.save(storage, &metadata_name)?;
+    save_note_to_file(storage, &encrypted_content, &encrypted_note_name)?;
 
     Ok(())
 }
@@ -39,43 +140,60 @@ pub fn generate_uuid() -> String {
     Uuid::new_v4().to_string()
 }
 
-/// Generate note file paths from a UUID
-pub fn note_paths(notes_dir: &Path, uuid: &str) -> (PathBuf, PathBuf) {
+/// Generate note file names from a UUID
+pub fn note_file_names(uuid: &str) -> (String, String) {
     (
-        notes_dir.join(format!("{uuid}.enc.txt")),
-        notes_dir.join(format!("{uuid}.meta.toml")),
+        format!("{uuid}{CONTENT_SUFFIX}"),
+        format!("{uuid}{METADATA_SUFFIX}"),
     )
 }
 
-/// Saves a note to a file in encrypted format with the given content
-pub fn save_note_to_file(content: &[u8], path: impl AsRef<Path>) -> Result<(), AppError> {
-    let path = path.as_ref();
+/// Directory of the previous versions of a note.
+pub fn history_dir(uuid: &str) -> String {
+    format!("{HISTORY_DIR}/{uuid}")
+}
 
-    // Atomic write pattern
-    let parent = path
-        .parent()
-        .ok_or_else(|| AppError::Config("Invalid note path".to_string()))?;
-    let mut temp_file = tempfile::NamedTempFile::new_in(parent).map_err(AppError::Io)?;
+/// Where a file of a note ends up once the note is trashed.
+pub fn trash_file_name(name: &str) -> String {
+    format!("{TRASH_DIR}/{name}")
+}
 
-    temp_file.write_all(MAGIC_BYTES).map_err(AppError::Io)?;
-    temp_file.write_all(content).map_err(AppError::Io)?;
+/// Identifies a version by the time it was saved, sortable as a string.
+pub fn version_id(saved_at: DateTime<Utc>) -> String {
+    saved_at.format(VERSION_FORMAT).to_string()
+}
 
-    temp_file.persist(path).map_err(|e| AppError::Io(e.error))?;
+/// Name of the encrypted content of a version of a note.
+pub fn version_file_name(uuid: &str, version_id: &str) -> String {
+    format!("{}/{version_id}{CONTENT_SUFFIX}", history_dir(uuid))
+}
 
-    info!("Note saved to {}", path.display());
+/// Extracts the version ID and save time from a file name in a history directory.
+pub fn parse_version_file_name(file_name: &str) -> Option<(&str, DateTime<Utc>)> {
+    let id = file_name.strip_suffix(CONTENT_SUFFIX)?;
+    let saved_at = NaiveDateTime::parse_from_str(id, VERSION_FORMAT).ok()?;
+    Some((id, saved_at.and_utc()))
+}
+
+/// Saves a note to a file in encrypted format with the given content
+pub fn save_note_to_file(
+    storage: &dyn Storage,
+    content: &[u8],
+    name: &str,
+) -> Result<(), AppError> {
+    storage.write(name, &[MAGIC_BYTES, content].concat())?;
+
+    info!("Note saved to {name}");
     Ok(())
 }
 
 /// Loads and decrypts the content of a note
 pub fn load_and_decrypt_note_content(
-    path: impl AsRef<Path>,
+    storage: &dyn Storage,
+    name: &str,
     pin: &str,
 ) -> Result<Vec<u8>, AppError> {
-    let path = path.as_ref();
-    let mut encrypted_data = Vec::new();
-    let mut file = File::open(path).map_err(AppError::Io)?;
-    file.read_to_end(&mut encrypted_data)
-        .map_err(AppError::Io)?;
+    let encrypted_data = storage.read(name)?;
 
     if is_encrypted_file(&encrypted_data) {
         let content_without_magic = &encrypted_data[MAGIC_BYTES.len()..];
@@ -90,28 +208,18 @@ pub fn load_and_decrypt_note_content(
 }
 
 /// Deletes both the encrypted note file and its metadata
-pub fn delete_note_files(notes_dir: &Path, uuid: &str) -> Result<(), AppError> {
-    let (enc_path, meta_path) = note_paths(notes_dir, uuid);
+pub fn delete_note_files(storage: &dyn Storage, uuid: &str) -> Result<(), AppError> {
+    let (enc_name, meta_name) = note_file_names(uuid);
 
-    if enc_path.exists() {
-        fs::remove_file(&enc_path).map_err(AppError::Io)?;
+    if storage.exists(&enc_name) {
+        storage.delete(&enc_name)?;
     }
-    if meta_path.exists() {
-        fs::remove_file(&meta_path).map_err(AppError::Io)?;
+    if storage.exists(&meta_name) {
+        storage.delete(&meta_name)?;
     }
     info!("Deleted note {uuid}");
     Ok(())
 }
 
-/// Opens the file in the default text editor
-pub fn open_in_editor(args: &Args, path: &Path) -> Result<(), AppError> {
-    let env_editor = std::env::var("EDITOR").unwrap_or_else(|_| "nano".to_string());
-    let editor = args.editor.as_ref().unwrap_or(&env_editor);
-    Command::new(editor)
-        .arg(path)
-        .spawn()
-        .map_err(AppError::Io)?
-        .wait()
-        .map_err(AppError::Io)?;
-    Ok(())
-}
+#[cfg(test)]
+mod file_test;
@@ -0,0 +1,76 @@
+#![cfg(test)]
+
+use super::*;
+
+const UUID: &str = "0f8e5c1a-3b7d-4e2a-9c6f-1d2e3f4a5b6c";
+
+#[test]
+fn test_parse_note_file_name() {
+    let content = format!("{UUID}{CONTENT_SUFFIX}");
+    let metadata = format!("{UUID}{METADATA_SUFFIX}");
+    let upper = format!("{}{CONTENT_SUFFIX}", UUID.to_uppercase());
+    let simple = format!("{}{CONTENT_SUFFIX}", UUID.replace('-', ""));
+    let doubled = format!("{UUID}{CONTENT_SUFFIX}{CONTENT_SUFFIX}");
+    let backup = format!("{UUID}{CONTENT_SUFFIX}.orig");
+    let hidden = format!(".{UUID}{METADATA_SUFFIX}");
+    let in_history = format!("{HISTORY_DIR}/{UUID}{CONTENT_SUFFIX}");
+    let in_trash = trash_file_name(&metadata);
+
+    let cases: [(&str, Option<NoteFileKind>); 15] = [
+        (&content, Some(NoteFileKind::Content)),
+        (&metadata, Some(NoteFileKind::Metadata)),
+        (&upper, Some(NoteFileKind::Content)),
+        (&simple, None),
+        (&doubled, None),
+        (&backup, None),
+        (&hidden, None),
+        (&in_history, None),
+        (&in_trash, None),
+        ("note.backup.meta.toml", None),
+        ("a.b.enc.txt", None),
+        (".enc.txt", None),
+        ("メモ.enc.txt", None),
+        (INDEX_FILE_NAME, None),
+        ("README.md", None),
+    ];
+    for (name, kind) in cases {
+        assert_eq!(
+            parse_note_file_name(name).map(|(_, kind)| kind),
+            kind,
+            "{name}"
+        );
+        if kind.is_some() {
+            assert_eq!(
+                parse_note_file_name(name).map(|(uuid, _)| uuid.len()),
+                Some(36)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_unexpected_files() {
+    for name in [
+        INDEX_FILE_NAME,
+        LOCK_FILE_NAME,
+        MARKER_FILE_NAME,
+        HASH_CACHE_FILE_NAME,
+    ] {
+        assert!(!is_unexpected_file(name), "{name}");
+    }
+    assert!(!is_unexpected_file(&format!("{UUID}{METADATA_SUFFIX}")));
+    for name in [
+        "todo.txt",
+        ".DS_Store",
+        "note.backup.meta.toml",
+        "x.enc.txt.orig",
+    ] {
+        assert!(is_unexpected_file(name), "{name}");
+    }
+    // Suffixes alone still tell what a misnamed file was meant to be
+    assert_eq!(
+        note_file_kind("note.backup.meta.toml"),
+        Some(NoteFileKind::Metadata)
+    );
+    assert_eq!(note_file_kind("x.enc.txt.orig"), None);
+}
@@ -0,0 +1,120 @@
+//! Turning note titles into file names, for notes written out of the vault.
+//!
+//! Titles are free text, and imported ones come from paths, so a title like
+//! `../../.bashrc` must never decide where a file ends up. [`sanitize`] makes
+//! any title a single file name that's valid on Linux, macOS and Windows.
+
+use crate::error::AppError;
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Longest file name made from a title, in bytes, leaving room for a numeric
+/// suffix and an extension within the usual limit of 255.
+pub const MAX_NAME_LEN: usize = 200;
+
+/// Name used when nothing of the title can be kept.
+pub const FALLBACK_NAME: &str = "Untitled";
+
+/// Characters that separate paths or that Windows refuses in file names.
+const FORBIDDEN_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names Windows reserves, whatever the extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes `title` a single, safe path component.
+///
+/// Separators and characters Windows forbids become `_`, control characters
+/// and bidirectional overrides are dropped, and leading and trailing dots and
+/// spaces are trimmed, so the name can't be `..` or a hidden file. Names
+/// reserved on Windows get a leading `_`, and a name left empty becomes
+/// [`FALLBACK_NAME`].
+///
+/// ```
+/// use ryokan::filename::sanitize;
+///
+/// assert_eq!(sanitize("Projects/Ideas"), "Projects_Ideas");
+/// assert_eq!(sanitize("../../.bashrc"), "_.._.bashrc");
+/// assert_eq!(sanitize("nul.txt"), "_nul.txt");
+/// ```
+pub fn sanitize(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .filter(|&c| !c.is_control() && !is_bidi_control(c))
+        .map(|c| if FORBIDDEN_CHARS.contains(&c) { '_' } else { c })
+        .collect();
+    let mut name = trim(&name);
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        name.insert(0, '_');
+    }
+    if name.len() > MAX_NAME_LEN {
+        let end = name
+            .grapheme_indices(true)
+            .map(|(i, grapheme)| i + grapheme.len())
+            .take_while(|&end| end <= MAX_NAME_LEN)
+            .last()
+            .unwrap_or_default();
+        name = trim(&name[..end]);
+    }
+    if name.is_empty() {
+        return FALLBACK_NAME.to_string();
+    }
+    name
+}
+
+/// Creates a new file in `dir` named `name` with `extension`, adding ` (2)`,
+/// ` (3)`… to the name while a file of that name exists, so nothing is
+/// overwritten.
+///
+/// `name` should come from [`sanitize`].
+pub fn create_unique(dir: &Path, name: &str, extension: &str) -> Result<(PathBuf, File), AppError> {
+    let mut path = dir.join(format!("{name}.{extension}"));
+    let mut n = 1;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                n += 1;
+                path = dir.join(format!("{name} ({n}).{extension}"));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The path [`create_unique`] would create for `name` in `dir`, skipping the
+/// paths in `taken` too, e.g. to list the files exporting several notes would
+/// write before writing any.
+pub fn unique_path(dir: &Path, name: &str, extension: &str, taken: &HashSet<PathBuf>) -> PathBuf {
+    let mut path = dir.join(format!("{name}.{extension}"));
+    let mut n = 1;
+    while path.exists() || taken.contains(&path) {
+        n += 1;
+        path = dir.join(format!("{name} ({n}).{extension}"));
+    }
+    path
+}
+
+fn trim(name: &str) -> String {
+    name.trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string()
+}
+
+/// Characters that reorder text, e.g. to make `txt.exe` read as `exe.txt`.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+#[cfg(test)]
+mod filename_test;
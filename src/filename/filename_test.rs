@@ -0,0 +1,120 @@
+#![cfg(test)]
+
+use super::*;
+use std::path::Component;
+use tempfile::tempdir;
+
+/// Pieces of titles that have caused trouble as file names somewhere.
+const NASTY: [&str; 24] = [
+    "",
+    " ",
+    ".",
+    "..",
+    "../",
+    "..\\",
+    "/etc/passwd",
+    "C:\\Windows",
+    "~",
+    ".bashrc",
+    "CON",
+    "nul.txt",
+    "Com1 ",
+    "lpt9.tar.gz",
+    "a\0b",
+    "line\nbreak\r\ttab",
+    "\u{1b}[31m",
+    "\u{202e}gnp.exe",
+    "<>:\"|?*",
+    "trailing. . ",
+    "é",
+    "🙂",
+    "日本語",
+    "Notes",
+];
+
+/// Whether `name` is one file name, usable as is on every platform.
+fn is_safe(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    let single = matches!(components.next(), Some(Component::Normal(c)) if *c == *name)
+        && components.next().is_none();
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    single
+        && !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with(['.', ' '])
+        && !name.ends_with(['.', ' '])
+        && !name
+            .chars()
+            .any(|c| c.is_control() || is_bidi_control(c) || FORBIDDEN_CHARS.contains(&c))
+        && !RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[test]
+fn test_every_combination_of_nasty_titles_is_a_safe_name() {
+    for a in NASTY {
+        for b in NASTY {
+            for c in NASTY {
+                let title = format!("{a}{b}{c}");
+                let name = sanitize(&title);
+                assert!(is_safe(&name), "{title:?} became {name:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_long_titles_are_cut_at_a_character() {
+    for piece in ["x", "é", "🙂", "a/", "e\u{301}", "📔日記 – café "] {
+        let name = sanitize(&piece.repeat(1000));
+        assert!(is_safe(&name), "{name:?}");
+        assert!(name.len() > MAX_NAME_LEN - 20, "{name:?}");
+    }
+    // No accent is cut off its letter
+    assert!(sanitize(&"e\u{301}".repeat(1000)).ends_with('\u{301}'));
+}
+
+#[test]
+fn test_readable_titles_are_kept() {
+    assert_eq!(sanitize("Shopping list"), "Shopping list");
+    assert_eq!(sanitize("Projects/Ideas"), "Projects_Ideas");
+    assert_eq!(sanitize("Meeting 2024.06.12"), "Meeting 2024.06.12");
+    assert_eq!(sanitize("CON"), "_CON");
+    assert_eq!(sanitize("Console"), "Console");
+    assert_eq!(sanitize("../.."), "_");
+    assert_eq!(sanitize("\n..\n"), FALLBACK_NAME);
+}
+
+#[test]
+fn test_collisions_get_numbered() -> Result<(), AppError> {
+    let dir = tempdir()?;
+
+    let (first, _) = create_unique(dir.path(), "Note", "md")?;
+    let (second, _) = create_unique(dir.path(), "Note", "md")?;
+    let (third, _) = create_unique(dir.path(), "Note", "md")?;
+    let (other, _) = create_unique(dir.path(), "Note", "html")?;
+
+    assert_eq!(first, dir.path().join("Note.md"));
+    assert_eq!(second, dir.path().join("Note (2).md"));
+    assert_eq!(third, dir.path().join("Note (3).md"));
+    assert_eq!(other, dir.path().join("Note.html"));
+    Ok(())
+}
+
+#[test]
+fn test_planned_paths_skip_existing_and_taken_ones() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    create_unique(dir.path(), "Note", "md")?;
+    let mut taken = HashSet::new();
+
+    for expected in ["Note (2).md", "Note (3).md"] {
+        let path = unique_path(dir.path(), "Note", "md", &taken);
+        assert_eq!(path, dir.path().join(expected));
+        taken.insert(path);
+    }
+    // What writing them then creates
+    let (written, _) = create_unique(dir.path(), "Note", "md")?;
+    assert_eq!(written, dir.path().join("Note (2).md"));
+    Ok(())
+}
@@ -0,0 +1,385 @@
+//! Reclaiming the space a vault accumulates: previous versions beyond the
+//! history limit or of notes that are gone, notes in the trash for too long,
+//! temporary files left by interrupted writes, caches that grew too large or
+//! went stale, and empty history directories.
+//!
+//! [`plan`] only looks, and [`apply`] deletes what a plan found one file at a
+//! time, each deletion complete by itself, so stopping halfway leaves a vault
+//! that's just partly cleaned up. Whatever can't be rebuilt is only deleted
+//! under a limit of the [`Policy`]: trashed notes after
+//! `trash_retention_days`, previous versions beyond `history_versions`.
+
+use crate::dedupe::HASH_CACHE_FILE_NAME;
+use crate::error::AppError;
+use crate::file::{self, HISTORY_DIR, NoteFileKind, TRASH_DIR};
+use crate::index::{INDEX_FILE_NAME, NoteIndex};
+use crate::metadata::NoteMetadata;
+use crate::storage::{Entry, Storage};
+use crate::t;
+use chrono::{DateTime, Duration, Utc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// Prefix of the temporary files a write goes through before replacing the
+/// file it's for.
+pub const TEMP_FILE_PREFIX: &str = ".tmp";
+
+/// How old a temporary file has to be to be deleted, so a write that's still
+/// going on, maybe by a process not holding the vault lock, isn't disturbed.
+pub const TEMP_FILE_GRACE: Duration = Duration::hours(1);
+
+/// What [`plan`] deletes, from the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// Notes trashed longer ago than this many days are deleted; 0 keeps them.
+    pub trash_retention_days: u32,
+    /// How many previous versions of each note are kept.
+    pub history_versions: usize,
+    /// A cache larger than this, in bytes, is deleted, to be rebuilt.
+    pub cache_max_bytes: u64,
+}
+
+/// Where the space of a vault goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    History,
+    Trash,
+    TempFiles,
+    Caches,
+    EmptyDirs,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::History,
+        Category::Trash,
+        Category::TempFiles,
+        Category::Caches,
+        Category::EmptyDirs,
+    ];
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::History => write!(f, "{}", t!("gc-history")),
+            Category::Trash => write!(f, "{}", t!("gc-trash")),
+            Category::TempFiles => write!(f, "{}", t!("gc-temp-files")),
+            Category::Caches => write!(f, "{}", t!("gc-caches")),
+            Category::EmptyDirs => write!(f, "{}", t!("gc-empty-dirs")),
+        }
+    }
+}
+
+/// Why [`plan`] deletes something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// A previous version older than the `history_versions` newest ones.
+    BeyondHistoryLimit,
+    /// A previous version of a note that's neither in the vault nor the trash
+    /// anymore, or is deleted from the trash along with it.
+    NoteGone,
+    /// A file of a note trashed more than `trash_retention_days` ago.
+    TrashExpired,
+    /// A temporary file older than [`TEMP_FILE_GRACE`].
+    TempFile,
+    /// The index, which doesn't match the notes; it's rebuilt when they're
+    /// next listed.
+    StaleIndex,
+    /// A cache larger than `cache_max_bytes`.
+    CacheTooLarge,
+    /// A directory with nothing left in it.
+    EmptyDir,
+}
+
+impl Reason {
+    pub fn category(self) -> Category {
+        match self {
+            Reason::BeyondHistoryLimit | Reason::NoteGone => Category::History,
+            Reason::TrashExpired => Category::Trash,
+            Reason::TempFile => Category::TempFiles,
+            Reason::StaleIndex | Reason::CacheTooLarge => Category::Caches,
+            Reason::EmptyDir => Category::EmptyDirs,
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::BeyondHistoryLimit => write!(f, "{}", t!("gc-beyond-history-limit")),
+            Reason::NoteGone => write!(f, "{}", t!("gc-note-gone")),
+            Reason::TrashExpired => write!(f, "{}", t!("gc-trash-expired")),
+            Reason::TempFile => write!(f, "{}", t!("gc-temp-file")),
+            Reason::StaleIndex => write!(f, "{}", t!("gc-stale-index")),
+            Reason::CacheTooLarge => write!(f, "{}", t!("gc-cache-too-large")),
+            Reason::EmptyDir => write!(f, "{}", t!("gc-empty-dir")),
+        }
+    }
+}
+
+/// A file or directory to delete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    /// Its name in the storage.
+    pub name: String,
+    /// Its size in bytes, 0 for a directory.
+    pub len: u64,
+    pub reason: Reason,
+}
+
+/// How many files of a category there are and how large they are together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// What [`plan`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// The space each category takes now.
+    pub usage: BTreeMap<Category, Usage>,
+    /// What the policy deletes, in the order [`apply`] deletes it: files
+    /// before the directories they leave empty.
+    pub removals: Vec<Removal>,
+}
+
+impl Plan {
+    /// The space deleting the removals of `category` frees.
+    pub fn freed(&self, category: Category) -> Usage {
+        let mut freed = Usage::default();
+        for removal in &self.removals {
+            if removal.reason.category() == category {
+                freed.add(removal.len);
+            }
+        }
+        freed
+    }
+}
+
+/// Whether `entry` is a temporary file old enough to be deleted.
+fn is_stale_temp_file(entry: &Entry, now: DateTime<Utc>) -> bool {
+    entry.name.starts_with(TEMP_FILE_PREFIX)
+        && entry
+            .modified
+            .is_some_and(|modified| now - DateTime::<Utc>::from(modified) > TEMP_FILE_GRACE)
+}
+
+/// Finds what `policy` deletes from `storage`, the files of a vault whose
+/// notes are `notes`, by UUID, at `now`.
+pub fn plan(
+    storage: &dyn Storage,
+    notes: &BTreeSet<String>,
+    policy: &Policy,
+    now: DateTime<Utc>,
+) -> Result<Plan, AppError> {
+    let mut plan = Plan::default();
+    let mut usage = |category: Category, bytes: u64| {
+        plan.usage.entry(category).or_default().add(bytes);
+    };
+    let mut removals = Vec::new();
+
+    for entry in storage.list("")? {
+        if entry.name.starts_with(TEMP_FILE_PREFIX) {
+            usage(Category::TempFiles, entry.len);
+            if is_stale_temp_file(&entry, now) {
+                removals.push(Removal {
+                    name: entry.name,
+                    len: entry.len,
+                    reason: Reason::TempFile,
+                });
+            }
+        } else if entry.name == INDEX_FILE_NAME {
+            usage(Category::Caches, entry.len);
+            if !NoteIndex::load(storage).is_some_and(|index| index.matches(notes)) {
+                removals.push(Removal {
+                    name: entry.name,
+                    len: entry.len,
+                    reason: Reason::StaleIndex,
+                });
+            }
+        } else if entry.name == HASH_CACHE_FILE_NAME {
+            usage(Category::Caches, entry.len);
+            if entry.len > policy.cache_max_bytes {
+                removals.push(Removal {
+                    name: entry.name,
+                    len: entry.len,
+                    reason: Reason::CacheTooLarge,
+                });
+            }
+        }
+    }
+
+    // Trashed notes by UUID, with their files and when they were trashed
+    let mut trashed: BTreeMap<String, (Vec<Entry>, Option<DateTime<Utc>>)> = BTreeMap::new();
+    for entry in storage.list(TRASH_DIR)? {
+        let name = file::trash_file_name(&entry.name);
+        if entry.name.starts_with(TEMP_FILE_PREFIX) {
+            usage(Category::TempFiles, entry.len);
+            if is_stale_temp_file(&entry, now) {
+                removals.push(Removal {
+                    name,
+                    len: entry.len,
+                    reason: Reason::TempFile,
+                });
+            }
+            continue;
+        }
+        usage(Category::Trash, entry.len);
+        let Some((uuid, kind)) = file::parse_note_file_name(&entry.name) else {
+            continue;
+        };
+        let (files, trashed_at) = trashed.entry(uuid.to_string()).or_default();
+        if kind == NoteFileKind::Metadata {
+            *trashed_at = NoteMetadata::load(storage, &name)
+                .ok()
+                .and_then(|metadata| metadata.trashed_at);
+        }
+        files.push(entry);
+    }
+    let retention = Duration::days(i64::from(policy.trash_retention_days));
+    let mut expired = BTreeSet::new();
+    for (uuid, (mut files, trashed_at)) in trashed {
+        // Notes trashed before the date was recorded count from when their
+        // files last changed
+        let trashed_at = trashed_at.or_else(|| {
+            files
+                .iter()
+                .filter_map(|entry| entry.modified)
+                .max()
+                .map(DateTime::<Utc>::from)
+        });
+        if policy.trash_retention_days == 0
+            || trashed_at.is_none_or(|trashed_at| now - trashed_at <= retention)
+        {
+            continue;
+        }
+        // Content first, so a note interrupted halfway is left without
+        // content rather than listed in the trash without its metadata
+        files.sort_by_key(|entry| file::note_file_kind(&entry.name) != Some(NoteFileKind::Content));
+        for entry in files {
+            removals.push(Removal {
+                name: file::trash_file_name(&entry.name),
+                len: entry.len,
+                reason: Reason::TrashExpired,
+            });
+        }
+        expired.insert(uuid);
+    }
+
+    let mut empty_dirs = Vec::new();
+    let mut history_dirs = storage.list_dirs(HISTORY_DIR)?;
+    history_dirs.sort();
+    for uuid in history_dirs {
+        let dir = file::history_dir(&uuid);
+        let mut versions = Vec::new();
+        let mut kept = 0;
+        for entry in storage.list(&dir)? {
+            let name = format!("{dir}/{}", entry.name);
+            if entry.name.starts_with(TEMP_FILE_PREFIX) {
+                usage(Category::TempFiles, entry.len);
+                if is_stale_temp_file(&entry, now) {
+                    removals.push(Removal {
+                        name,
+                        len: entry.len,
+                        reason: Reason::TempFile,
+                    });
+                } else {
+                    kept += 1;
+                }
+                continue;
+            }
+            usage(Category::History, entry.len);
+            match file::parse_version_file_name(&entry.name) {
+                Some((id, _)) => versions.push((id.to_string(), name, entry.len)),
+                // Not a version, so not Ryokan's to delete
+                None => kept += 1,
+            }
+        }
+        let in_trash = storage.exists(&file::trash_file_name(&file::note_file_names(&uuid).0));
+        let gone = !notes.contains(&uuid) && (!in_trash || expired.contains(&uuid));
+        // Newest first, as `Vault::history` lists them
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        for (i, (_, name, len)) in versions.into_iter().enumerate() {
+            let reason = if gone {
+                Reason::NoteGone
+            } else if i >= policy.history_versions {
+                Reason::BeyondHistoryLimit
+            } else {
+                kept += 1;
+                continue;
+            };
+            removals.push(Removal { name, len, reason });
+        }
+        if kept == 0 && storage.list_dirs(&dir)?.is_empty() {
+            usage(Category::EmptyDirs, 0);
+            empty_dirs.push(Removal {
+                name: dir,
+                len: 0,
+                reason: Reason::EmptyDir,
+            });
+        }
+    }
+
+    plan.removals = removals;
+    plan.removals.extend(empty_dirs);
+    Ok(plan)
+}
+
+/// Deletes what `plan` found, one file at a time, calling `removed` after
+/// each. What's already gone is skipped, and so is a file of a trashed note
+/// that was brought back since.
+pub fn apply(
+    storage: &dyn Storage,
+    plan: &Plan,
+    removed: &mut dyn FnMut(&Removal),
+) -> Result<(), AppError> {
+    for removal in &plan.removals {
+        if removal.reason == Reason::EmptyDir {
+            if !storage.list(&removal.name)?.is_empty()
+                || !storage.list_dirs(&removal.name)?.is_empty()
+            {
+                continue;
+            }
+            storage.remove_dir(&removal.name)?;
+        } else {
+            if !storage.exists(&removal.name) || restored(storage, removal) {
+                continue;
+            }
+            storage.delete(&removal.name)?;
+        }
+        removed(removal);
+    }
+    Ok(())
+}
+
+/// Whether `removal` is part of a trashed note that's back in the vault.
+fn restored(storage: &dyn Storage, removal: &Removal) -> bool {
+    let uuid = match removal.reason {
+        Reason::TrashExpired => removal
+            .name
+            .strip_prefix(&format!("{TRASH_DIR}/"))
+            .and_then(file::parse_note_file_name)
+            .map(|(uuid, _)| uuid),
+        Reason::NoteGone => removal
+            .name
+            .strip_prefix(&format!("{HISTORY_DIR}/"))
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(uuid, _)| uuid),
+        _ => None,
+    };
+    uuid.is_some_and(|uuid| storage.exists(&file::note_file_names(uuid).0))
+}
+
+#[cfg(test)]
+mod gc_test;
@@ -0,0 +1,153 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+use crate::vault::Vault;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+const POLICY: Policy = Policy {
+    trash_retention_days: 30,
+    history_versions: 2,
+    cache_max_bytes: 100,
+};
+
+/// Writes `count` previous versions of `uuid`, a day apart.
+fn versions(storage: &dyn Storage, uuid: &str, count: i64) -> Result<Vec<String>, AppError> {
+    let mut names = Vec::new();
+    for day in 0..count {
+        let id = file::version_id(DateTime::UNIX_EPOCH + Duration::days(day));
+        let name = file::version_file_name(uuid, &id);
+        storage.write(&name, b"RYOKAN_ENCRYPTED old")?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Writes the files of a note in the trash, trashed at `trashed_at`.
+fn trashed(
+    storage: &dyn Storage,
+    uuid: &str,
+    trashed_at: DateTime<Utc>,
+) -> Result<[String; 2], AppError> {
+    let (content, metadata) = file::note_file_names(uuid);
+    let [content, metadata] = [content, metadata].map(|name| file::trash_file_name(&name));
+    storage.write(&content, b"RYOKAN_ENCRYPTED trashed")?;
+    let mut note = NoteMetadata::new("Trashed");
+    note.trashed_at = Some(trashed_at);
+    note.save(storage, &metadata)?;
+    Ok([content, metadata])
+}
+
+fn removed(plan: &Plan, reason: Reason) -> Vec<&str> {
+    plan.removals
+        .iter()
+        .filter(|removal| removal.reason == reason)
+        .map(|removal| removal.name.as_str())
+        .collect()
+}
+
+#[test]
+fn test_plan_follows_the_policy_and_apply_deletes_it() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    let now = Utc::now() + Duration::hours(2);
+    let live = file::generate_uuid();
+    let gone = file::generate_uuid();
+    let expired = file::generate_uuid();
+    let recent = file::generate_uuid();
+    let live_versions = versions(&storage, &live, 4)?;
+    let gone_versions = versions(&storage, &gone, 1)?;
+    let expired_versions = versions(&storage, &expired, 1)?;
+    versions(&storage, &recent, 1)?;
+    let expired_files = trashed(&storage, &expired, now - Duration::days(31))?;
+    trashed(&storage, &recent, now - Duration::days(29))?;
+    storage.write(".tmp1a2b3c", b"half written")?;
+    storage.write(HASH_CACHE_FILE_NAME, &[0; 101])?;
+    storage.write(INDEX_FILE_NAME, b"titles = {}")?;
+
+    let notes = BTreeSet::from([live.clone()]);
+    let plan = plan(&storage, &notes, &POLICY, now)?;
+
+    // The newest two versions are kept
+    assert_eq!(
+        removed(&plan, Reason::BeyondHistoryLimit),
+        [&live_versions[1], &live_versions[0]]
+    );
+    let mut note_gone = removed(&plan, Reason::NoteGone);
+    note_gone.sort_unstable();
+    let mut expected = [&gone_versions[0], &expired_versions[0]];
+    expected.sort_unstable();
+    assert_eq!(note_gone, expected);
+    // The content goes first
+    assert_eq!(removed(&plan, Reason::TrashExpired), expired_files);
+    assert_eq!(removed(&plan, Reason::TempFile), [".tmp1a2b3c"]);
+    assert_eq!(
+        removed(&plan, Reason::CacheTooLarge),
+        [HASH_CACHE_FILE_NAME]
+    );
+    assert_eq!(removed(&plan, Reason::StaleIndex), [INDEX_FILE_NAME]);
+    assert_eq!(plan.usage[&Category::History].files, 7);
+    assert_eq!(plan.usage[&Category::Trash].files, 4);
+    assert_eq!(plan.freed(Category::Trash).files, 2);
+    assert_eq!(
+        plan.freed(Category::Caches).bytes,
+        101 + "titles = {}".len() as u64
+    );
+
+    let mut deleted = Vec::new();
+    apply(&storage, &plan, &mut |removal| {
+        deleted.push(removal.name.clone())
+    })?;
+    assert_eq!(deleted.len(), plan.removals.len());
+    for name in &deleted {
+        assert!(!storage.exists(name), "{name} is still there");
+    }
+    assert!(storage.exists(&live_versions[3]));
+    assert_eq!(storage.list(TRASH_DIR)?.len(), 2);
+
+    // Nothing is left to delete
+    let again = super::plan(&storage, &notes, &POLICY, now)?;
+    assert_eq!(again.removals, []);
+    Ok(())
+}
+
+#[test]
+fn test_nothing_is_deleted_early() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    let uuid = file::generate_uuid();
+    versions(&storage, &uuid, 1)?;
+    trashed(&storage, &uuid, Utc::now() - Duration::days(365))?;
+    storage.write(".tmp1a2b3c", b"being written")?;
+    let keep_forever = Policy {
+        trash_retention_days: 0,
+        ..POLICY
+    };
+
+    let plan = plan(&storage, &BTreeSet::new(), &keep_forever, Utc::now())?;
+
+    assert_eq!(plan.removals, []);
+    assert_eq!(plan.usage[&Category::TempFiles].files, 1);
+    Ok(())
+}
+
+#[test]
+fn test_a_note_restored_after_planning_is_kept() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    let note = vault.create("Groceries", b"milk")?;
+    vault.write(&note.uuid, b"milk, eggs")?;
+    vault.trash(&note.uuid)?;
+    let later = Utc::now() + Duration::days(31);
+
+    let plan = plan(storage.as_ref(), &BTreeSet::new(), &POLICY, later)?;
+    assert_eq!(removed(&plan, Reason::TrashExpired).len(), 2);
+    assert_eq!(removed(&plan, Reason::NoteGone).len(), 1);
+    vault.untrash(&note.uuid)?;
+    let mut deleted = 0;
+    apply(storage.as_ref(), &plan, &mut |_| deleted += 1)?;
+
+    assert_eq!(deleted, 0);
+    assert_eq!(vault.read(&note.uuid)?, b"milk, eggs");
+    assert_eq!(vault.history(&note.uuid)?.len(), 1);
+    Ok(())
+}
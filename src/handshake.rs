@@ -0,0 +1,85 @@
+//! What this build of Ryokan supports, for programs that drive it, such as a
+//! graphical frontend, to check before relying on it.
+//!
+//! Everything here is fixed at compile time: the version of the crate, the
+//! versions of each file format read and written, and the version of the JSON
+//! printed by options like `list --json`. `ryokan api-info` prints it as JSON
+//! along with the subcommands.
+
+use crate::bundle::BUNDLE_VERSION;
+use crate::file::NOTE_FORMAT_VERSION;
+use crate::metadata::SCHEMA_VERSION;
+use crate::vaultinfo::VAULT_INFO_VERSION;
+use serde::Serialize;
+
+/// Version of this build.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of the JSON printed by `--json` options and `api-info`. It goes up
+/// when a field is removed or changes meaning, not when one is added.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// A file format and the versions of it this build handles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FormatSupport {
+    pub name: &'static str,
+    /// Version written.
+    pub writes: u32,
+    /// Oldest version read.
+    pub reads_from: u32,
+    /// Newest version read, or `None` if newer ones are read too, keeping what
+    /// this build doesn't know about.
+    pub reads_up_to: Option<u32>,
+}
+
+/// Every versioned file format.
+pub const FORMATS: [FormatSupport; 4] = [
+    FormatSupport {
+        name: "note",
+        writes: NOTE_FORMAT_VERSION,
+        reads_from: NOTE_FORMAT_VERSION,
+        reads_up_to: Some(NOTE_FORMAT_VERSION),
+    },
+    FormatSupport {
+        name: "metadata",
+        writes: SCHEMA_VERSION,
+        reads_from: 1,
+        reads_up_to: None,
+    },
+    FormatSupport {
+        name: "bundle",
+        writes: BUNDLE_VERSION as u32,
+        reads_from: BUNDLE_VERSION as u32,
+        reads_up_to: Some(BUNDLE_VERSION as u32),
+    },
+    FormatSupport {
+        name: "vault info",
+        writes: VAULT_INFO_VERSION,
+        reads_from: 1,
+        reads_up_to: None,
+    },
+];
+
+/// What `ryokan api-info` prints.
+#[derive(Debug, Serialize)]
+pub struct Handshake<'a> {
+    pub version: &'static str,
+    pub json_schema_version: u32,
+    pub subcommands: Vec<&'a str>,
+    pub formats: &'static [FormatSupport],
+}
+
+impl<'a> Handshake<'a> {
+    /// The handshake of this build, which has the given `subcommands`.
+    pub fn new(subcommands: Vec<&'a str>) -> Self {
+        Self {
+            version: VERSION,
+            json_schema_version: JSON_SCHEMA_VERSION,
+            subcommands,
+            formats: &FORMATS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod handshake_test;
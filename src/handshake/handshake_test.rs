@@ -0,0 +1,56 @@
+#![cfg(test)]
+
+use super::*;
+use crate::error::AppError;
+use std::{fs, path::Path};
+
+/// Names of the `pub const …_VERSION` items in the files below `dir`.
+fn version_constants(dir: &Path, found: &mut Vec<String>) -> Result<(), AppError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            version_constants(&path, found)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            for line in fs::read_to_string(&path)?.lines() {
+                let name = line
+                    .trim_start()
+                    .strip_prefix("pub const ")
+                    .and_then(|rest| rest.split_once(':'))
+                    .map(|(name, _)| name);
+                if let Some(name) = name.filter(|name| name.ends_with("_VERSION")) {
+                    found.push(name.to_string());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_every_format_version_is_in_the_handshake() -> Result<(), AppError> {
+    let format = |name: &str| FORMATS.iter().find(|format| format.name == name).copied();
+    // A new version constant has to be added to `FORMATS` and here
+    let covered = [
+        ("NOTE_FORMAT_VERSION", format("note").map(|f| f.writes)),
+        ("SCHEMA_VERSION", format("metadata").map(|f| f.writes)),
+        ("BUNDLE_VERSION", format("bundle").map(|f| f.writes)),
+        ("VAULT_INFO_VERSION", format("vault info").map(|f| f.writes)),
+        ("JSON_SCHEMA_VERSION", Some(JSON_SCHEMA_VERSION)),
+    ];
+    assert_eq!(covered[0].1, Some(NOTE_FORMAT_VERSION));
+    assert_eq!(covered[1].1, Some(SCHEMA_VERSION));
+    assert_eq!(covered[2].1, Some(u32::from(BUNDLE_VERSION)));
+    assert_eq!(covered[3].1, Some(VAULT_INFO_VERSION));
+    assert_eq!(FORMATS.len(), covered.len() - 1);
+
+    let mut found = Vec::new();
+    version_constants(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut found,
+    )?;
+    found.sort();
+    let mut expected: Vec<_> = covered.iter().map(|(name, _)| name.to_string()).collect();
+    expected.sort();
+    assert_eq!(found, expected);
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Translations of what Ryokan says to the user.
+//!
+//! Each language has a catalog of messages in `locales/<code>.toml`, embedded
+//! at compile time: one `key = "text"` line per message, where the text can
+//! name values as `{name}`. Messages are looked up with [`t!`](crate::t) in
+//! the language chosen with [`set_language`], falling back to English when a
+//! catalog lacks a message, so a translation can be partial.
+//!
+//! Only what people read is translated: output meant for scripts, such as
+//! `list --format tsv` or `--json`, stays the same in every language, as do
+//! error messages, which end up in logs and bug reports.
+
+use log::warn;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{OnceLock, RwLock},
+};
+
+/// The language every message exists in, used when nothing else is asked for.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Every language with a catalog, by code, and its catalog.
+pub const LANGUAGES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+];
+
+/// Environment variables naming the language of messages, in order of
+/// precedence, as for other programs.
+pub const LANGUAGE_VARS: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+static LANGUAGE: RwLock<&str> = RwLock::new(DEFAULT_LANGUAGE);
+
+/// A message of the current language with its values filled in.
+///
+/// ```
+/// use ryokan::t;
+///
+/// assert_eq!(t!("pin-confirm"), "Please confirm your PIN: ");
+/// assert_eq!(t!("changed-notes", count = 3), "Changed 3 note(s).");
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::message($key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message(
+            $key,
+            &[$((stringify!($name), &($value) as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+/// The catalogs of [`LANGUAGES`], parsed on first use.
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    static CATALOGS: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+    CATALOGS.get_or_init(|| {
+        LANGUAGES
+            .iter()
+            .map(|&(code, text)| {
+                let catalog = toml::from_str(text).unwrap_or_else(|e| {
+                    warn!("The {code} catalog is broken, using English: {e}");
+                    HashMap::new()
+                });
+                (code, catalog)
+            })
+            .collect()
+    })
+}
+
+/// The language with a catalog that a language tag such as `de_DE.UTF-8` or
+/// `de-AT` asks for, if there's one. `C` and `POSIX` ask for English.
+pub fn supported(tag: &str) -> Option<&'static str> {
+    let code = tag
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if code == "c" || code == "posix" {
+        return Some(DEFAULT_LANGUAGE);
+    }
+    LANGUAGES
+        .iter()
+        .map(|&(supported, _)| supported)
+        .find(|&supported| supported == code)
+}
+
+/// Translates messages into `language` from now on, or into English if it
+/// has no catalog.
+pub fn set_language(language: &str) {
+    let language = supported(language).unwrap_or(DEFAULT_LANGUAGE);
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = language;
+    }
+}
+
+/// The language messages are translated into.
+pub fn language() -> &'static str {
+    LANGUAGE
+        .read()
+        .map_or(DEFAULT_LANGUAGE, |language| *language)
+}
+
+/// The text of `key` in `language`, or in English if that catalog lacks it.
+pub fn lookup(language: &str, key: &str) -> Option<&'static str> {
+    let catalogs = catalogs();
+    [language, DEFAULT_LANGUAGE]
+        .into_iter()
+        .find_map(|language| catalogs.get(language)?.get(key))
+        .map(String::as_str)
+}
+
+/// The message `key` of the current language with every `{name}` of `values`
+/// filled in, see [`t!`](crate::t). A key missing from every catalog, which
+/// a test rules out, is shown as is.
+pub fn message(key: &str, values: &[(&str, &dyn Display)]) -> String {
+    translate(language(), key, values)
+}
+
+/// Like [`message`], in `language`.
+pub fn translate(language: &str, key: &str, values: &[(&str, &dyn Display)]) -> String {
+    let Some(text) = lookup(language, key) else {
+        warn!("No message \"{key}\" in any catalog");
+        return key.to_string();
+    };
+    values.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), &value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod i18n_test;
@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use super::*;
+use std::{fs, path::Path};
+
+/// Every key given to [`t!`](crate::t) in the Rust files below `dir`, with
+/// the file it's in.
+fn keys_used(dir: &Path, keys: &mut Vec<(String, String)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            keys_used(&path, keys);
+            continue;
+        }
+        if path.extension().is_none_or(|extension| extension != "rs") {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (before, rest) in source.match_indices("t!(").map(|(i, _)| source.split_at(i)) {
+            // Not the end of another macro, such as `format!(`
+            if before.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            let Some(rest) = rest[3..].trim_start().strip_prefix('"') else {
+                continue;
+            };
+            // Keys are lowercase words joined by dashes, which also leaves
+            // out the pattern above
+            let key: String = rest
+                .chars()
+                .take_while(|&c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                .collect();
+            if rest[key.len()..].starts_with('"') && !key.is_empty() {
+                keys.push((key, path.display().to_string()));
+            }
+        }
+    }
+}
+
+/// The `{name}`s of a message.
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut names: Vec<_> = text
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+#[test]
+fn test_every_catalog_parses() {
+    for (code, text) in LANGUAGES {
+        let parsed: Result<HashMap<String, String>, _> = toml::from_str(text);
+        assert!(parsed.is_ok(), "{code}: {parsed:?}");
+    }
+}
+
+#[test]
+fn test_every_key_used_is_in_english() {
+    let mut keys = Vec::new();
+    keys_used(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut keys,
+    );
+    assert!(keys.len() > 100, "found only {} keys", keys.len());
+    for (key, file) in keys {
+        assert!(
+            lookup(DEFAULT_LANGUAGE, &key).is_some(),
+            "\"{key}\" of {file} isn't in locales/en.toml"
+        );
+    }
+}
+
+#[test]
+fn test_translations_keep_the_placeholders() {
+    let english = &catalogs()[DEFAULT_LANGUAGE];
+    for (code, catalog) in catalogs() {
+        for (key, text) in catalog {
+            let Some(original) = english.get(key) else {
+                panic!("\"{key}\" of {code} isn't in English");
+            };
+            assert_eq!(placeholders(text), placeholders(original), "{code}: {key}");
+        }
+    }
+}
+
+#[test]
+fn test_supported_takes_the_language_of_a_locale() {
+    assert_eq!(supported("de_DE.UTF-8"), Some("de"));
+    assert_eq!(supported("de-AT"), Some("de"));
+    assert_eq!(supported("EN"), Some("en"));
+    assert_eq!(supported("C.UTF-8"), Some("en"));
+    assert_eq!(supported("POSIX"), Some("en"));
+    assert_eq!(supported("xx_YY"), None);
+    assert_eq!(supported(""), None);
+}
+
+#[test]
+fn test_translate_fills_in_values_and_falls_back_to_english() {
+    assert_eq!(
+        translate("de", "changed-notes", &[("count", &2)]),
+        "2 Notiz(en) geändert."
+    );
+    assert_eq!(
+        translate("xx", "changed-notes", &[("count", &2)]),
+        "Changed 2 note(s)."
+    );
+    assert_eq!(translate("de", "no-such-key", &[]), "no-such-key");
+}
@@ -0,0 +1,86 @@
+//! The `.ryokanignore` file, listing files of the notes directory that are kept
+//! as plaintext on purpose, like a `README.md` or a `.gitignore`.
+//!
+//! Patterns are written like in a `.gitignore` and matched with the `ignore`
+//! crate, relative to the notes directory. Files they match are left alone by
+//! `encrypt-unencrypted`, and the same patterns leave files out of an import,
+//! relative to the imported directory. Until the file exists the rules are
+//! [`DEFAULT_RULES`], which `encrypt-unencrypted` writes the first time it runs
+//! so they can be edited.
+
+use crate::error::AppError;
+use crate::storage::Storage;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Name of the ignore file at the root of the notes directory.
+pub const IGNORE_FILE_NAME: &str = ".ryokanignore";
+
+/// What the ignore file holds when Ryokan writes it.
+pub const DEFAULT_RULES: &str = "\
+# Files matching these patterns, written like in a .gitignore, are kept as
+# plaintext by `ryokan encrypt-unencrypted` and left out by `ryokan import`.
+.git/
+README*
+# Hidden files, like this one
+.*
+";
+
+/// Parsed patterns of an ignore file.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    /// Parses the lines of an ignore file, failing on the first pattern that
+    /// isn't a valid glob.
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        let mut builder = GitignoreBuilder::new("");
+        for line in text.lines() {
+            builder
+                .add_line(None, line)
+                .map_err(|e| AppError::Config(format!("{IGNORE_FILE_NAME}: {e}")))?;
+        }
+        let matcher = builder
+            .build()
+            .map_err(|e| AppError::Config(format!("{IGNORE_FILE_NAME}: {e}")))?;
+        Ok(Self { matcher })
+    }
+
+    /// Rules matching nothing.
+    pub fn none() -> Self {
+        Self {
+            matcher: Gitignore::empty(),
+        }
+    }
+
+    /// The rules of the notes directory in `storage`, or [`DEFAULT_RULES`] if
+    /// it has no ignore file.
+    pub fn load(storage: &dyn Storage) -> Result<Self, AppError> {
+        if !storage.exists(IGNORE_FILE_NAME) {
+            return Self::parse(DEFAULT_RULES);
+        }
+        let data = storage.read(IGNORE_FILE_NAME)?;
+        Self::parse(&String::from_utf8_lossy(&data))
+    }
+
+    /// Whether the file, or directory with `is_dir`, at `path` relative to the
+    /// directory the rules apply to is ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matcher.matched(path, is_dir).is_ignore()
+    }
+}
+
+/// Writes [`DEFAULT_RULES`] to the ignore file of `storage` unless there's one
+/// already. Returns whether it was written.
+pub fn write_default(storage: &dyn Storage) -> Result<bool, AppError> {
+    if storage.exists(IGNORE_FILE_NAME) {
+        return Ok(false);
+    }
+    storage.write(IGNORE_FILE_NAME, DEFAULT_RULES.as_bytes())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod ignorefile_test;
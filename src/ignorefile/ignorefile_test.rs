@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+
+#[test]
+fn test_default_rules_keep_readmes_and_hidden_files() -> Result<(), AppError> {
+    let rules = IgnoreRules::parse(DEFAULT_RULES)?;
+    for (path, is_dir) in [
+        ("README.md", false),
+        ("README", false),
+        (".gitignore", false),
+        (".git", true),
+        ("docs/README.txt", false),
+    ] {
+        assert!(
+            rules.is_ignored(Path::new(path), is_dir),
+            "{path} wasn't ignored"
+        );
+    }
+    for path in ["todo.txt", "notes/todo.md", "readme.md"] {
+        assert!(
+            !rules.is_ignored(Path::new(path), false),
+            "{path} was ignored"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rules_come_from_the_notes_directory() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    assert!(IgnoreRules::load(&storage)?.is_ignored(Path::new("README.md"), false));
+
+    assert!(write_default(&storage)?);
+    assert_eq!(storage.read(IGNORE_FILE_NAME)?, DEFAULT_RULES.as_bytes());
+    storage.write(IGNORE_FILE_NAME, b"*.log\n!keep.log\n")?;
+    assert!(!write_default(&storage)?);
+
+    let rules = IgnoreRules::load(&storage)?;
+    assert!(rules.is_ignored(Path::new("debug.log"), false));
+    assert!(!rules.is_ignored(Path::new("keep.log"), false));
+    assert!(!rules.is_ignored(Path::new("README.md"), false));
+    assert!(!IgnoreRules::none().is_ignored(Path::new(".gitignore"), false));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_patterns_are_refused() {
+    assert!(matches!(
+        IgnoreRules::parse("[z-a]\n"),
+        Err(AppError::Config(_))
+    ));
+}
@@ -0,0 +1,414 @@
+//! Importing notes from other apps: trees of Markdown files, such as an Obsidian
+//! vault, and the export files of Joplin and Standard Notes.
+//!
+//! Every `.md` file becomes a note titled after its path relative to the root of
+//! the tree, without the extension, so `Projects/Ideas.md` becomes
+//! `Projects/Ideas`. Hidden directories (like `.obsidian/`) are skipped, and so is
+//! the attachments folder configured in Obsidian. The content is imported as is,
+//! frontmatter included.
+//!
+//! For the Obsidian format, the YAML frontmatter is read for `tags`, `aliases`,
+//! `created` and `updated`. Only the simple forms Obsidian writes are
+//! understood; anything else falls back to the file's own timestamps.
+//!
+//! Joplin and Standard Notes exports are single files, read by the `joplin` and
+//! `standard_notes` submodules. Ryokan has no notebooks or attachments, so notebooks
+//! become tags and links to attachments are replaced with placeholders, each
+//! reported as a warning of the plan.
+//!
+//! An age file, such as one made by `ryokan share`, becomes a single note titled
+//! after the file. It's decrypted in memory with [`plan_age`], given the key to
+//! open it.
+
+use crate::error::AppError;
+use crate::ignorefile::IgnoreRules;
+use crate::metadata::NoteMetadata;
+use crate::parallel::Progress;
+use crate::share::{self, OpenKey};
+use crate::vault::Vault;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Layouts that can be imported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Plain Markdown files, without looking at frontmatter.
+    #[default]
+    Markdown,
+    /// An Obsidian vault, with YAML frontmatter.
+    Obsidian,
+    /// A Joplin JEX export.
+    Joplin,
+    /// A decrypted Standard Notes backup.
+    StandardNotes,
+    /// A single note encrypted with age.
+    Age,
+}
+
+impl ImportFormat {
+    pub const ALL: [ImportFormat; 5] = [
+        ImportFormat::Markdown,
+        ImportFormat::Obsidian,
+        ImportFormat::Joplin,
+        ImportFormat::StandardNotes,
+        ImportFormat::Age,
+    ];
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportFormat::Markdown => write!(f, "markdown"),
+            ImportFormat::Obsidian => write!(f, "obsidian"),
+            ImportFormat::Joplin => write!(f, "joplin"),
+            ImportFormat::StandardNotes => write!(f, "standard-notes"),
+            ImportFormat::Age => write!(f, "age"),
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AppError::Config(format!("Unknown import format \"{s}\"")))
+    }
+}
+
+/// A file, or an item of an export file, that will become a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedNote {
+    pub source: PathBuf,
+    pub metadata: NoteMetadata,
+    /// The content when it was taken out of an export file; otherwise `source` is
+    /// read when importing.
+    pub content: Option<Vec<u8>>,
+}
+
+/// Everything an import would do, computed without touching the vault.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImportPlan {
+    pub notes: Vec<PlannedNote>,
+    /// Files that aren't Markdown and were left out.
+    pub skipped: Vec<PathBuf>,
+    /// Files and directories left out because the ignore rules match them.
+    pub ignored: Vec<PathBuf>,
+    /// Things that couldn't be imported faithfully, meant to be shown to the user.
+    pub warnings: Vec<String>,
+}
+
+/// Works out the note each Markdown file below `root`, or each note of the export
+/// file `root`, becomes. Age files need a key and are planned with [`plan_age`].
+///
+/// Below a directory, paths relative to it that `rules` match are left out, see
+/// [`ignorefile`](crate::ignorefile).
+pub fn plan(
+    root: &Path,
+    format: ImportFormat,
+    rules: &IgnoreRules,
+) -> Result<ImportPlan, AppError> {
+    let attachments = match format {
+        ImportFormat::Joplin => return joplin::plan(root),
+        ImportFormat::StandardNotes => return standard_notes::plan(root),
+        ImportFormat::Age => {
+            return Err(AppError::Import(
+                "An age file can only be imported with its identity or passphrase".to_string(),
+            ));
+        }
+        ImportFormat::Obsidian => obsidian_attachment_folder(root),
+        ImportFormat::Markdown => None,
+    };
+
+    let mut plan = ImportPlan::default();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let file_type = entry.file_type()?;
+            if !hidden
+                && let Ok(relative) = path.strip_prefix(root)
+                && rules.is_ignored(relative, file_type.is_dir())
+            {
+                plan.ignored.push(path);
+                continue;
+            }
+            if file_type.is_dir() {
+                if !hidden && attachments.as_deref() != Some(path.as_path()) {
+                    dirs.push(path);
+                }
+            } else if hidden {
+                continue;
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                plan.notes.push(plan_note(root, &path, format)?);
+            } else {
+                plan.skipped.push(path);
+            }
+        }
+    }
+    plan.notes.sort_by(|a, b| a.source.cmp(&b.source));
+    Ok(plan)
+}
+
+/// Decrypts the age file `path` with `key` into a note titled after the file.
+pub fn plan_age(path: &Path, key: &OpenKey) -> Result<ImportPlan, AppError> {
+    let content = share::decrypt(&fs::read(path)?, key)?;
+    let title = path
+        .file_stem()
+        .map_or_else(|| "Imported note".into(), |stem| stem.to_string_lossy());
+    let updated_at = fs::metadata(path)?
+        .modified()
+        .map_or_else(|_| Utc::now(), DateTime::from);
+    Ok(ImportPlan {
+        notes: vec![PlannedNote {
+            source: path.to_path_buf(),
+            metadata: NoteMetadata::with_timestamps(title, updated_at, updated_at),
+            content: Some(content.to_vec()),
+        }],
+        ..ImportPlan::default()
+    })
+}
+
+/// What [`run`] did with each planned note.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// The UUID of the note made from each file.
+    pub imported: Vec<(PathBuf, String)>,
+    /// Files that couldn't be imported, with the reason.
+    pub failed: Vec<(PathBuf, AppError)>,
+}
+
+/// Creates the planned notes on several threads, calling `progress` after each.
+pub fn run(
+    vault: &Vault,
+    plan: &ImportPlan,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<ImportReport, AppError> {
+    let _lock = vault.lock()?;
+    let created = vault.create_many(
+        &plan.notes,
+        |planned| &planned.metadata.original_filename,
+        |planned| {
+            let content = match &planned.content {
+                Some(content) => content.clone(),
+                None => fs::read(&planned.source)?,
+            };
+            Ok((planned.metadata.clone(), content))
+        },
+        progress,
+    )?;
+
+    let mut report = ImportReport::default();
+    for (planned, result) in plan.notes.iter().zip(created) {
+        match result {
+            Ok(note) => {
+                info!("Imported {} as {}", planned.source.display(), note.uuid);
+                report.imported.push((planned.source.clone(), note.uuid));
+            }
+            Err(e) => report.failed.push((planned.source.clone(), e)),
+        }
+    }
+    Ok(report)
+}
+
+fn plan_note(root: &Path, path: &Path, format: ImportFormat) -> Result<PlannedNote, AppError> {
+    let title = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .with_extension("")
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let file_metadata = fs::metadata(path)?;
+    let updated_at = file_metadata
+        .modified()
+        .map_or_else(|_| Utc::now(), DateTime::from);
+    let created_at = file_metadata
+        .created()
+        .map_or(updated_at, |created| updated_at.min(created.into()));
+    let mut metadata = NoteMetadata::with_timestamps(title, created_at, updated_at);
+
+    if format == ImportFormat::Obsidian {
+        let text = fs::read_to_string(path).unwrap_or_default();
+        match parse_frontmatter(&text) {
+            Some(frontmatter) => frontmatter.apply(&mut metadata),
+            None if text.starts_with("---") => {
+                warn!("Ignoring malformed frontmatter in {}", path.display());
+            }
+            None => {}
+        }
+    }
+
+    Ok(PlannedNote {
+        source: path.to_path_buf(),
+        metadata,
+        content: None,
+    })
+}
+
+/// Joins the names of nested folders, or tags, from the root down, given each
+/// one's name and parent. Cycles are cut off.
+fn hierarchy_path(id: &str, folders: &HashMap<String, (String, Option<String>)>) -> Option<String> {
+    let mut names = Vec::new();
+    let mut current = Some(id);
+    while let Some(id) = current
+        && names.len() <= folders.len()
+    {
+        let Some((name, parent)) = folders.get(id) else {
+            break;
+        };
+        names.push(name.as_str());
+        current = parent.as_deref();
+    }
+    names.reverse();
+    (!names.is_empty()).then(|| names.join("/"))
+}
+
+/// Where Obsidian keeps pasted images and other attachments, if it's a dedicated folder.
+fn obsidian_attachment_folder(root: &Path) -> Option<PathBuf> {
+    let config = fs::read_to_string(root.join(".obsidian").join("app.json")).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&config).ok()?;
+    let folder = config.get("attachmentFolderPath")?.as_str()?;
+    // "/" is the vault root and "./..." is relative to each note, neither is a single folder
+    (!folder.is_empty() && folder != "/" && !folder.starts_with("./"))
+        .then(|| root.join(folder.trim_start_matches('/')))
+}
+
+/// The frontmatter fields Ryokan understands.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Frontmatter {
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl Frontmatter {
+    fn apply(self, metadata: &mut NoteMetadata) {
+        metadata.tags = self.tags;
+        metadata.aliases = self.aliases;
+        if let Some(created) = self.created {
+            metadata.created_at = created;
+        }
+        if let Some(updated) = self.updated {
+            metadata.updated_at = updated;
+        }
+        metadata.updated_at = metadata.updated_at.max(metadata.created_at);
+    }
+
+    fn push(&mut self, key: &str, value: &str) {
+        match key {
+            "tags" | "tag" => self.tags.push(value.trim_start_matches('#').to_string()),
+            "aliases" | "alias" => self.aliases.push(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Parses the YAML frontmatter at the start of `text`.
+///
+/// Returns `None` if there is none or it isn't in a form this understands.
+pub fn parse_frontmatter(text: &str) -> Option<Frontmatter> {
+    let mut lines = text.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    let mut frontmatter = Frontmatter::default();
+    // The key whose block list (`- item` lines) is being read
+    let mut list_key: Option<String> = None;
+    for line in lines {
+        if line.trim_end() == "---" {
+            return Some(frontmatter);
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = line.trim_start().strip_prefix("- ")
+            && line.starts_with(char::is_whitespace)
+        {
+            let key = list_key.as_deref()?;
+            frontmatter.push(key, unquote(item));
+            continue;
+        }
+
+        let (key, value) = line.split_once(':')?;
+        if key.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        list_key = None;
+
+        if value.is_empty() {
+            list_key = Some(key);
+        } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            for item in items.split(',').filter(|item| !item.trim().is_empty()) {
+                frontmatter.push(&key, unquote(item));
+            }
+        } else {
+            match key.as_str() {
+                "created" | "date" => frontmatter.created = Some(parse_date(unquote(value))?),
+                "updated" | "modified" => frontmatter.updated = Some(parse_date(unquote(value))?),
+                // Obsidian also accepts a single line of space or comma separated tags
+                "tags" | "tag" | "aliases" | "alias" => {
+                    for item in value.split([',', ' ']).filter(|item| !item.is_empty()) {
+                        frontmatter.push(&key, unquote(item));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    // No closing line
+    None
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value)
+}
+
+/// Parses the date formats Obsidian and its plugins commonly write, as UTC.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .into_iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(chrono::NaiveTime::MIN))
+    })
+    .map(|date| date.and_utc())
+}
+
+mod joplin;
+mod standard_notes;
+
+#[cfg(test)]
+mod import_test;
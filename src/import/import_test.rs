@@ -0,0 +1,218 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+fn date(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[test]
+fn test_block_and_inline_lists() {
+    let text = "---\ntags:\n  - work\n  - \"#ideas\"\naliases: [Plans, 'Big plans']\ncreated: 2023-01-05\nupdated: 2023-02-01T10:30:00+01:00\nother: ignored\n---\n# Body\n";
+    assert_eq!(
+        parse_frontmatter(text),
+        Some(Frontmatter {
+            tags: vec!["work".to_string(), "ideas".to_string()],
+            aliases: vec!["Plans".to_string(), "Big plans".to_string()],
+            created: date("2023-01-05T00:00:00Z"),
+            updated: date("2023-02-01T09:30:00Z"),
+        })
+    );
+}
+
+#[test]
+fn test_single_line_tags() {
+    let frontmatter = parse_frontmatter("---\ntags: one, #two three\n---\n");
+    assert_eq!(
+        frontmatter.map(|f| f.tags),
+        Some(vec![
+            "one".to_string(),
+            "two".to_string(),
+            "three".to_string()
+        ])
+    );
+}
+
+#[test]
+fn test_missing_or_malformed_frontmatter() {
+    assert_eq!(parse_frontmatter("# Just a note\n"), None);
+    // Never closed
+    assert_eq!(parse_frontmatter("---\ntags: [a]\n# Body\n"), None);
+    // Not `key: value`
+    assert_eq!(parse_frontmatter("---\njust some text\n---\n"), None);
+    // Unparseable date
+    assert_eq!(parse_frontmatter("---\ncreated: last tuesday\n---\n"), None);
+    // List item without a key
+    assert_eq!(parse_frontmatter("---\n  - stray\n---\n"), None);
+}
+
+#[test]
+fn test_plan_walks_the_tree() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::create_dir_all(root.join(".obsidian"))?;
+    fs::write(
+        root.join(".obsidian/app.json"),
+        r#"{"attachmentFolderPath": "assets"}"#,
+    )?;
+    fs::create_dir_all(root.join("assets"))?;
+    fs::write(root.join("assets/diagram.md"), "not a note")?;
+    fs::create_dir_all(root.join("Projects"))?;
+    fs::write(
+        root.join("Projects/Ideas.md"),
+        "---\ntags: [work]\ncreated: 2020-05-01\n---\nBody",
+    )?;
+    fs::write(root.join("Inbox.md"), "---\nbroken\n---\nBody")?;
+    fs::write(root.join("photo.png"), [0u8; 4])?;
+
+    let plan = plan(root, ImportFormat::Obsidian, &IgnoreRules::none())?;
+    let titles: Vec<_> = plan
+        .notes
+        .iter()
+        .map(|note| note.metadata.original_filename.as_str())
+        .collect();
+    assert_eq!(titles, ["Inbox", "Projects/Ideas"]);
+    assert_eq!(plan.notes[1].metadata.tags, ["work"]);
+    assert_eq!(
+        Some(plan.notes[1].metadata.created_at),
+        date("2020-05-01T00:00:00Z")
+    );
+    assert!(plan.notes[0].metadata.tags.is_empty());
+    assert_eq!(plan.skipped, [root.join("photo.png")]);
+
+    Ok(())
+}
+
+#[test]
+fn test_plan_leaves_out_ignored_files() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    fs::create_dir_all(root.join("drafts"))?;
+    fs::write(root.join("drafts/Old.md"), "Old")?;
+    fs::write(root.join("README.md"), "About these notes")?;
+    fs::write(root.join("Todo.md"), "Milk")?;
+
+    let rules = IgnoreRules::parse("README*\ndrafts/\n")?;
+    let plan = plan(root, ImportFormat::Markdown, &rules)?;
+    assert_eq!(plan.notes.len(), 1);
+    assert_eq!(plan.notes[0].metadata.original_filename, "Todo");
+    assert_eq!(plan.ignored, [root.join("README.md"), root.join("drafts")]);
+
+    Ok(())
+}
+
+fn jex_item(title: &str, body: &str, props: &[(&str, &str)]) -> String {
+    let props: Vec<String> = props
+        .iter()
+        .map(|(key, value)| format!("{key}: {value}"))
+        .collect();
+    [title, body, &props.join("\n")]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[test]
+fn test_plan_joplin_export() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let archive = dir.path().join("export.jex");
+    let resource = "c".repeat(32);
+    let items = [
+        jex_item("Work", "", &[("id", &"a".repeat(32)), ("type_", "2")]),
+        jex_item(
+            "Projects",
+            "",
+            &[
+                ("id", &"b".repeat(32)),
+                ("parent_id", &"a".repeat(32)),
+                ("type_", "2"),
+            ],
+        ),
+        jex_item(
+            "Plan",
+            &format!("See ![diagram](:/{resource}) and\n\ntype_: not a property"),
+            &[
+                ("id", &"d".repeat(32)),
+                ("parent_id", &"b".repeat(32)),
+                ("created_time", "2021-01-02T03:04:05.000Z"),
+                ("updated_time", "2021-02-02T03:04:05.000Z"),
+                ("type_", "1"),
+            ],
+        ),
+        jex_item("diagram.png", "", &[("id", &resource), ("type_", "4")]),
+        jex_item("urgent", "", &[("id", &"e".repeat(32)), ("type_", "5")]),
+        jex_item(
+            "",
+            "",
+            &[
+                ("id", &"f".repeat(32)),
+                ("note_id", &"d".repeat(32)),
+                ("tag_id", &"e".repeat(32)),
+                ("type_", "6"),
+            ],
+        ),
+    ];
+
+    let mut builder = tar::Builder::new(fs::File::create(&archive)?);
+    for (i, item) in items.iter().enumerate() {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(item.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, format!("{i}.md"), item.as_bytes())?;
+    }
+    builder.finish()?;
+    drop(builder);
+
+    let plan = plan(&archive, ImportFormat::Joplin, &IgnoreRules::none())?;
+    assert_eq!(plan.notes.len(), 1);
+    let note = &plan.notes[0];
+    assert_eq!(note.metadata.original_filename, "Plan");
+    assert_eq!(note.metadata.tags, ["Work/Projects", "urgent"]);
+    assert_eq!(Some(note.metadata.created_at), date("2021-01-02T03:04:05Z"));
+    assert_eq!(
+        note.content.as_deref(),
+        Some(&b"See [attachment not imported: diagram.png] and\n\ntype_: not a property"[..])
+    );
+    assert_eq!(plan.warnings.len(), 1);
+    assert!(plan.warnings[0].contains("diagram.png"));
+
+    Ok(())
+}
+
+#[test]
+fn test_plan_standard_notes_backup() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let backup = dir.path().join("backup.json");
+    fs::write(
+        &backup,
+        r#"{"version": "004", "items": [
+            {"uuid": "n1", "content_type": "Note", "created_at": "2022-03-01T10:00:00.000Z",
+             "updated_at": "2022-03-05T10:00:00.000Z",
+             "content": {"title": "Recipe", "text": "Flour", "references": [],
+                         "appData": {"org.standardnotes.sn": {"client_updated_at": "2022-03-04T10:00:00.000Z"}}}},
+            {"uuid": "n2", "content_type": "Note", "content": {"title": "Old", "text": "", "trashed": true}},
+            {"uuid": "n3", "content_type": "Note", "content": "004:encrypted"},
+            {"uuid": "t1", "content_type": "Tag", "content": {"title": "food", "references": []}},
+            {"uuid": "t2", "content_type": "Tag", "content": {"title": "baking", "references": [
+                {"uuid": "n1", "content_type": "Note"},
+                {"uuid": "t1", "content_type": "Tag"}]}},
+            {"uuid": "x", "content_type": "SN|Component", "content": {}}
+        ]}"#,
+    )?;
+
+    let plan = plan(&backup, ImportFormat::StandardNotes, &IgnoreRules::none())?;
+    assert_eq!(plan.notes.len(), 1);
+    let note = &plan.notes[0];
+    assert_eq!(note.metadata.original_filename, "Recipe");
+    assert_eq!(note.metadata.tags, ["food/baking"]);
+    assert_eq!(Some(note.metadata.updated_at), date("2022-03-04T10:00:00Z"));
+    assert_eq!(note.content.as_deref(), Some(&b"Flour"[..]));
+    assert_eq!(plan.warnings.len(), 1);
+
+    Ok(())
+}
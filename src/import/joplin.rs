@@ -0,0 +1,244 @@
+//! Joplin JEX exports.
+//!
+//! A JEX file is a tar archive holding one `<id>.md` file per item (note,
+//! notebook, tag, resource, or the link between a note and a tag) and the files
+//! of the resources below `resources/`. Each item is its title, a blank line, its
+//! body, a blank line and its properties as `key: value` lines, with `type_`
+//! telling what kind of item it is.
+
+use super::{ImportPlan, PlannedNote, hierarchy_path, parse_date};
+use crate::error::AppError;
+use crate::metadata::NoteMetadata;
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Component, Path},
+};
+
+const TYPE_NOTE: &str = "1";
+const TYPE_FOLDER: &str = "2";
+const TYPE_RESOURCE: &str = "4";
+const TYPE_TAG: &str = "5";
+const TYPE_NOTE_TAG: &str = "6";
+
+/// Length of the hexadecimal ids of Joplin items.
+const ID_LENGTH: usize = 32;
+
+/// One serialized Joplin item.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Item {
+    title: String,
+    body: String,
+    props: HashMap<String, String>,
+}
+
+impl Item {
+    fn prop(&self, key: &str) -> Option<&str> {
+        self.props.get(key).map(String::as_str)
+    }
+}
+
+pub(super) fn plan(archive: &Path) -> Result<ImportPlan, AppError> {
+    let mut plan = ImportPlan::default();
+    let mut items = Vec::new();
+    let mut tar = tar::Archive::new(File::open(archive)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        // Items are at the root of the archive, the files of resources below it
+        let mut parts = path
+            .components()
+            .filter(|part| !matches!(part, Component::CurDir));
+        let (Some(Component::Normal(name)), None) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        if !name.to_string_lossy().ends_with(".md") {
+            continue;
+        }
+        let mut text = String::new();
+        entry.read_to_string(&mut text)?;
+        match parse_item(&text) {
+            Some(item) => items.push(item),
+            None => plan.skipped.push(archive.join(name)),
+        }
+    }
+    if items.is_empty() {
+        return Err(AppError::Import(format!(
+            "{} is not a Joplin export",
+            archive.display()
+        )));
+    }
+
+    let mut folders = HashMap::new();
+    let mut tags = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut note_tags: HashMap<&str, Vec<&str>> = HashMap::new();
+    for item in &items {
+        let Some(id) = item.prop("id") else { continue };
+        match item.prop("type_") {
+            Some(TYPE_FOLDER) => {
+                let parent = item.prop("parent_id").filter(|id| !id.is_empty());
+                folders.insert(
+                    id.to_string(),
+                    (item.title.clone(), parent.map(str::to_string)),
+                );
+            }
+            Some(TYPE_TAG) => {
+                tags.insert(id, item.title.as_str());
+            }
+            Some(TYPE_RESOURCE) => {
+                resources.insert(id, item.title.as_str());
+            }
+            Some(TYPE_NOTE_TAG) => {
+                if let (Some(note_id), Some(tag_id)) = (item.prop("note_id"), item.prop("tag_id")) {
+                    note_tags.entry(note_id).or_default().push(tag_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for item in &items {
+        let (Some(id), Some(TYPE_NOTE)) = (item.prop("id"), item.prop("type_")) else {
+            continue;
+        };
+        let title = if item.title.is_empty() {
+            "Untitled"
+        } else {
+            item.title.as_str()
+        };
+        if item.prop("encryption_applied") == Some("1") {
+            plan.warnings
+                .push(format!("{title}: skipped, it's encrypted by Joplin"));
+            continue;
+        }
+        if item.prop("deleted_time").is_some_and(|time| time != "0") {
+            continue;
+        }
+
+        let timestamp =
+            |keys: [&str; 2]| keys.into_iter().find_map(|key| parse_date(item.prop(key)?));
+        let updated_at = timestamp(["user_updated_time", "updated_time"]).unwrap_or_else(Utc::now);
+        let created_at = timestamp(["user_created_time", "created_time"])
+            .unwrap_or(updated_at)
+            .min(updated_at);
+        let mut metadata = NoteMetadata::with_timestamps(title, created_at, updated_at);
+
+        // Ryokan has no notebooks, so the notebook path becomes a tag
+        if let Some(notebook) = item
+            .prop("parent_id")
+            .and_then(|parent| hierarchy_path(parent, &folders))
+        {
+            metadata.tags.push(notebook);
+        }
+        let mut note_tag_names: Vec<String> = note_tags
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|tag_id| tags.get(tag_id))
+            .map(|tag| tag.to_string())
+            .collect();
+        note_tag_names.sort();
+        note_tag_names.dedup();
+        metadata.tags.extend(note_tag_names);
+
+        let mut replaced = Vec::new();
+        let body = replace_resource_links(&item.body, &resources, &mut replaced);
+        for name in replaced {
+            plan.warnings.push(format!(
+                "{title}: attachment {name} was not imported, its link is now a placeholder"
+            ));
+        }
+
+        plan.notes.push(PlannedNote {
+            source: archive.join(format!("{id}.md")),
+            metadata,
+            content: Some(body.into_bytes()),
+        });
+    }
+    plan.notes.sort_by(|a, b| {
+        a.metadata
+            .original_filename
+            .cmp(&b.metadata.original_filename)
+    });
+    Ok(plan)
+}
+
+/// Parses an item the way Joplin reads it back: properties from the end up to
+/// the first blank line, then the title on the first line and the body after it.
+fn parse_item(text: &str) -> Option<Item> {
+    let mut lines: Vec<&str> = text.lines().collect();
+    let mut props = HashMap::new();
+    while let Some(line) = lines.last() {
+        let Some((key, value)) = line
+            .split_once(": ")
+            .or_else(|| line.strip_suffix(':').map(|key| (key, "")))
+        else {
+            break;
+        };
+        if key.is_empty() || !key.bytes().all(|b| b.is_ascii_lowercase() || b == b'_') {
+            break;
+        }
+        props.insert(
+            key.to_string(),
+            value.replace("\\n", "\n").replace("\\r", "\r"),
+        );
+        lines.pop();
+    }
+    if !props.contains_key("type_") {
+        return None;
+    }
+
+    if lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let mut lines = lines.into_iter();
+    let title = lines.next().unwrap_or_default().to_string();
+    let body: Vec<&str> = lines.skip_while(|line| line.is_empty()).collect();
+    Some(Item {
+        title,
+        body: body.join("\n"),
+        props,
+    })
+}
+
+/// Replaces links to Joplin resources (`:/<id>`) with a placeholder naming the
+/// file. A whole Markdown link or image around it is replaced. The names of the
+/// replaced resources are added to `replaced`.
+fn replace_resource_links(
+    body: &str,
+    resources: &HashMap<&str, &str>,
+    replaced: &mut Vec<String>,
+) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(pos) = rest.find(":/") {
+        let Some(id) = rest[pos + 2..]
+            .get(..ID_LENGTH)
+            .filter(|id| id.bytes().all(|b| b.is_ascii_hexdigit()))
+        else {
+            out.push_str(&rest[..pos + 2]);
+            rest = &rest[pos + 2..];
+            continue;
+        };
+        let name = resources.get(id).copied().unwrap_or(id);
+        let mut before = &rest[..pos];
+        let mut after = &rest[pos + 2 + ID_LENGTH..];
+        if let Some(link) = before.strip_suffix("](")
+            && let Some(open) = link.rfind('[')
+            && !link[open..].contains('\n')
+            && let Some(close) = after.strip_prefix(')')
+        {
+            before = link[..open].strip_suffix('!').unwrap_or(&link[..open]);
+            after = close;
+        }
+        out.push_str(before);
+        out.push_str(&format!("[attachment not imported: {name}]"));
+        replaced.push(name.to_string());
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
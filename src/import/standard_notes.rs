@@ -0,0 +1,145 @@
+//! Decrypted Standard Notes backups.
+//!
+//! The backup is a JSON file with an `items` array. Notes and tags are items
+//! whose `content` holds their title and, for notes, their text; tags list the
+//! notes they're on, and their parent tag, in `references`.
+
+use super::{ImportPlan, PlannedNote, hierarchy_path, parse_date};
+use crate::error::AppError;
+use crate::metadata::NoteMetadata;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, fs, path::Path};
+
+#[derive(Debug, Deserialize)]
+struct Backup {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    uuid: String,
+    content_type: String,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    deleted: bool,
+    /// An object, or a string if the item is still encrypted.
+    #[serde(default)]
+    content: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Content {
+    title: String,
+    text: String,
+    trashed: bool,
+    references: Vec<Reference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reference {
+    uuid: String,
+    content_type: String,
+}
+
+pub(super) fn plan(backup_file: &Path) -> Result<ImportPlan, AppError> {
+    let backup: Backup = serde_json::from_str(&fs::read_to_string(backup_file)?).map_err(|e| {
+        AppError::Import(format!(
+            "{} is not a Standard Notes backup: {e}",
+            backup_file.display()
+        ))
+    })?;
+
+    let mut plan = ImportPlan::default();
+    let mut notes = Vec::new();
+    let mut tags = HashMap::new();
+    let mut note_tags: HashMap<String, Vec<String>> = HashMap::new();
+    for item in &backup.items {
+        if item.deleted || !matches!(item.content_type.as_str(), "Note" | "Tag") {
+            continue;
+        }
+        if !item.content.is_object() {
+            plan.warnings.push(format!(
+                "{} {}: skipped, it's encrypted; export a decrypted backup",
+                item.content_type, item.uuid
+            ));
+            continue;
+        }
+        let content = Content::deserialize(&item.content).map_err(|e| {
+            AppError::Import(format!(
+                "Malformed {} {}: {e}",
+                item.content_type, item.uuid
+            ))
+        })?;
+
+        if item.content_type == "Note" {
+            notes.push((item, content));
+            continue;
+        }
+        let mut parent = None;
+        for reference in &content.references {
+            match reference.content_type.as_str() {
+                "Note" => note_tags
+                    .entry(reference.uuid.clone())
+                    .or_default()
+                    .push(item.uuid.clone()),
+                "Tag" => parent = Some(reference.uuid.clone()),
+                _ => {}
+            }
+        }
+        tags.insert(item.uuid.clone(), (content.title, parent));
+    }
+
+    for (item, content) in notes {
+        if content.trashed {
+            continue;
+        }
+        let title = if content.title.is_empty() {
+            "Untitled"
+        } else {
+            content.title.as_str()
+        };
+        let updated_at = item
+            .content
+            .pointer("/appData/org.standardnotes.sn/client_updated_at")
+            .and_then(Value::as_str)
+            .or(item.updated_at.as_deref())
+            .and_then(parse_date)
+            .unwrap_or_else(Utc::now);
+        let created_at = item
+            .created_at
+            .as_deref()
+            .and_then(parse_date)
+            .unwrap_or(updated_at)
+            .min(updated_at);
+        let mut metadata = NoteMetadata::with_timestamps(title, created_at, updated_at);
+
+        // Nested tags are named after their whole path, like Joplin notebooks
+        let mut note_tag_names: Vec<String> = note_tags
+            .get(&item.uuid)
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| hierarchy_path(tag, &tags))
+            .collect();
+        note_tag_names.sort();
+        note_tag_names.dedup();
+        metadata.tags = note_tag_names;
+
+        plan.notes.push(PlannedNote {
+            source: backup_file.join(&item.uuid),
+            metadata,
+            content: Some(content.text.into_bytes()),
+        });
+    }
+    plan.notes.sort_by(|a, b| {
+        a.metadata
+            .original_filename
+            .cmp(&b.metadata.original_filename)
+    });
+    Ok(plan)
+}
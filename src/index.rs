@@ -0,0 +1,49 @@
+//! A uuid → title index of the notes in a vault.
+//!
+//! Listing titles from the index avoids parsing every metadata file. The
+//! [`Vault`](crate::vault::Vault) keeps it in sync on every mutation and rebuilds
+//! it when it's missing or doesn't match the notes actually present.
+
+use crate::error::AppError;
+use crate::storage::Storage;
+use crate::vault::Note;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Name of the index file at the root of the vault.
+pub const INDEX_FILE_NAME: &str = ".ryokan-index.toml";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteIndex {
+    /// Titles keyed by note UUID.
+    pub titles: BTreeMap<String, String>,
+}
+
+impl NoteIndex {
+    /// Builds an index from freshly loaded notes.
+    pub fn from_notes(notes: &[Note]) -> Self {
+        Self {
+            titles: notes
+                .iter()
+                .map(|note| (note.uuid.clone(), note.metadata.original_filename.clone()))
+                .collect(),
+        }
+    }
+
+    /// Reads the index, returning `None` if it doesn't exist or can't be parsed.
+    pub fn load(storage: &dyn Storage) -> Option<Self> {
+        let bytes = storage.read(INDEX_FILE_NAME).ok()?;
+        toml::from_str(std::str::from_utf8(&bytes).ok()?).ok()
+    }
+
+    /// Atomically writes the index.
+    pub fn save(&self, storage: &dyn Storage) -> Result<(), AppError> {
+        let toml_string = toml::to_string(self).map_err(AppError::TomlSerialize)?;
+        storage.write(INDEX_FILE_NAME, toml_string.as_bytes())
+    }
+
+    /// Returns whether the index covers exactly the notes in `uuids`.
+    pub fn matches(&self, uuids: &BTreeSet<String>) -> bool {
+        self.titles.len() == uuids.len() && self.titles.keys().all(|uuid| uuids.contains(uuid))
+    }
+}
@@ -0,0 +1,155 @@
+//! Daily notes: one note per day, titled with its date, opened with
+//! `ryokan today` or from the TUI.
+//!
+//! Days are local calendar days, so a note written just before midnight goes to
+//! the day it was written on wherever the user is. A journal note that doesn't
+//! exist yet is created from the template note, if one is configured, and
+//! tagged [`JOURNAL_TAG`]. The editor then opens where the template marks the
+//! cursor with [`CURSOR_MARKER`], if it does. Ryokan has no notebooks, so the configured notebook
+//! becomes a tag too, as with imported notebooks.
+
+use crate::error::AppError;
+use crate::metadata::{NoteMetadata, NoteType};
+use crate::template::{self, CURSOR_MARKER, Position};
+use crate::vault::{Note, Vault};
+use chrono::{
+    NaiveDate,
+    format::{Item, StrftimeItems},
+};
+
+/// Tag given to every journal note.
+pub const JOURNAL_TAG: &str = "journal";
+
+/// The `journal_title` used when none is configured.
+pub const DEFAULT_JOURNAL_TITLE: &str = "%Y-%m-%d";
+
+/// How journal notes are titled and what they start as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    title_format: String,
+    template: Option<String>,
+    notebook: Option<String>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            title_format: DEFAULT_JOURNAL_TITLE.to_string(),
+            template: None,
+            notebook: None,
+        }
+    }
+}
+
+impl Journal {
+    /// Checks `title_format`, a `strftime` format such as `Journal/%Y-%m-%d`,
+    /// which must give the whole date so titles can be read back. `template` is
+    /// the UUID or title of the note new journal notes copy, and `notebook` an
+    /// extra tag for them.
+    pub fn new(
+        title_format: &str,
+        template: Option<String>,
+        notebook: Option<String>,
+    ) -> Result<Self, AppError> {
+        let journal = Self {
+            title_format: title_format.to_string(),
+            template,
+            notebook,
+        };
+        let sample = NaiveDate::from_ymd_opt(2024, 6, 12).unwrap_or_default();
+        if StrftimeItems::new(title_format).any(|item| item == Item::Error)
+            || journal.date_of(&journal.title(sample)) != Some(sample)
+        {
+            return Err(AppError::Config(format!(
+                "Invalid journal_title \"{title_format}\", it needs the year, month and day, e.g. Journal/%Y-%m-%d"
+            )));
+        }
+        Ok(journal)
+    }
+
+    /// Title of the journal note of `date`.
+    pub fn title(&self, date: NaiveDate) -> String {
+        date.format(&self.title_format).to_string()
+    }
+
+    /// The day `title` is the journal note of, if it's one.
+    pub fn date_of(&self, title: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(title, &self.title_format).ok()
+    }
+
+    /// Whether `note` is a journal note, going by its type or else its title.
+    pub fn contains(&self, note: &Note) -> bool {
+        note.metadata.note_type == NoteType::Journal
+            || self.date_of(&note.metadata.original_filename).is_some()
+    }
+
+    /// Finds the journal note of `date`, creating it if there's none yet, with
+    /// where to put the cursor if it was just created from a template marking it.
+    ///
+    /// Creating holds the vault lock and looks again first, so two processes
+    /// opening the same day don't both create a note. In read-only mode, only an
+    /// existing note is found.
+    pub fn open(
+        &self,
+        vault: &Vault,
+        date: NaiveDate,
+    ) -> Result<(Note, Option<Position>), AppError> {
+        let title = self.title(date);
+        match vault.find(&title) {
+            Err(AppError::NoteNotFound(_)) => {}
+            found => return found.map(|note| (note, None)),
+        }
+        let _lock = vault.lock()?;
+        match vault.find(&title) {
+            Err(AppError::NoteNotFound(_)) => {}
+            found => return found.map(|note| (note, None)),
+        }
+
+        let (content, cursor) = match &self.template {
+            Some(template) => {
+                let template = vault.find(template)?;
+                fill_template(vault.read(&template.uuid)?, &title, date)
+            }
+            None => (Vec::new(), None),
+        };
+        let mut metadata = NoteMetadata::new(title);
+        metadata.note_type = NoteType::Journal;
+        metadata.tags.push(JOURNAL_TAG.to_string());
+        if let Some(notebook) = self.notebook.as_ref().filter(|n| n.as_str() != JOURNAL_TAG) {
+            metadata.tags.push(notebook.clone());
+        }
+        let note = vault.create_with_metadata(&metadata, &content)?;
+        Ok((note, cursor))
+    }
+}
+
+/// Replaces `{title}` and `{date}`, as `YYYY-MM-DD`, in the content of a
+/// template, and removes its [`CURSOR_MARKER`]s, giving where the first one
+/// was. Binary content is copied as is.
+pub fn fill_template(
+    content: Vec<u8>,
+    title: &str,
+    date: NaiveDate,
+) -> (Vec<u8>, Option<Position>) {
+    let text = match String::from_utf8(content) {
+        Ok(text) => text,
+        Err(e) => return (e.into_bytes(), None),
+    };
+    let fill = |text: &str| {
+        text.replace(CURSOR_MARKER, "")
+            .replace("{title}", title)
+            .replace("{date}", &date.format("%Y-%m-%d").to_string())
+    };
+    // Filled apart, so a title with the marker in it doesn't move the cursor
+    match text.split_once(CURSOR_MARKER) {
+        Some((before, after)) => {
+            let before = fill(before);
+            let cursor = template::position(&before);
+            ((before + &fill(after)).into_bytes(), Some(cursor))
+        }
+        None => (fill(&text).into_bytes(), None),
+    }
+}
+
+#[cfg(test)]
+mod journal_test;
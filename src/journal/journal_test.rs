@@ -0,0 +1,108 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+use zeroize::Zeroizing;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap_or_default()
+}
+
+#[test]
+fn test_titles_are_read_back_as_dates() -> Result<(), AppError> {
+    let journal = Journal::new("Journal/%Y-%m-%d", None, None)?;
+
+    assert_eq!(journal.title(date(2024, 6, 1)), "Journal/2024-06-01");
+    assert_eq!(
+        journal.date_of("Journal/2024-06-01"),
+        Some(date(2024, 6, 1))
+    );
+    assert_eq!(journal.date_of("2024-06-01"), None);
+    assert_eq!(journal.date_of("Journal/groceries"), None);
+    Ok(())
+}
+
+#[test]
+fn test_formats_without_the_whole_date_are_rejected() {
+    for format in ["Journal", "%Y-%m", "%Y-%m-%Q"] {
+        assert!(
+            matches!(Journal::new(format, None, None), Err(AppError::Config(_))),
+            "{format}"
+        );
+    }
+}
+
+#[test]
+fn test_opening_a_day_twice_creates_one_note_from_the_template() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), Zeroizing::new("123456".to_string()));
+    vault.create("Daily", b"# {title}\n\nWritten on {date}: {{cursor}}\n")?;
+    let journal = Journal::new(
+        "Journal/%d.%m.%Y",
+        Some("daily".to_string()),
+        Some("Diary".to_string()),
+    )?;
+
+    let (first, cursor) = journal.open(&vault, date(2024, 6, 12))?;
+    let (second, cursor_again) = journal.open(&vault, date(2024, 6, 12))?;
+
+    assert_eq!(first.uuid, second.uuid);
+    assert_eq!(first.metadata.original_filename, "Journal/12.06.2024");
+    assert_eq!(first.metadata.tags, [JOURNAL_TAG, "Diary"]);
+    assert_eq!(first.metadata.note_type, NoteType::Journal);
+    assert_eq!(
+        vault.read(&first.uuid)?,
+        b"# Journal/12.06.2024\n\nWritten on 2024-06-12: \n"
+    );
+    assert_eq!(
+        cursor,
+        Some(Position {
+            line: 3,
+            column: 24,
+            byte: 24
+        })
+    );
+    assert_eq!(cursor_again, None);
+    assert_eq!(vault.list()?.len(), 2);
+    assert!(journal.contains(&first));
+    Ok(())
+}
+
+#[test]
+fn test_a_missing_template_creates_nothing() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), Zeroizing::new("123456".to_string()));
+    let journal = Journal::new(DEFAULT_JOURNAL_TITLE, Some("Daily".to_string()), None)?;
+
+    let result = journal.open(&vault, date(2024, 6, 12));
+
+    assert!(
+        matches!(result, Err(AppError::NoteNotFound(_))),
+        "{result:?}"
+    );
+    assert!(vault.list()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_the_cursor_marker_is_removed_and_placed_after_filling_in() {
+    let (content, cursor) = fill_template(
+        "Über {title}\r\n– {{cursor}}{date}{{cursor}}"
+            .as_bytes()
+            .to_vec(),
+        "{{cursor}}",
+        date(2024, 6, 12),
+    );
+
+    assert_eq!(content, "Über {{cursor}}\r\n– 2024-06-12".as_bytes());
+    assert_eq!(
+        cursor,
+        Some(Position {
+            line: 2,
+            column: 3,
+            byte: 5
+        })
+    );
+    assert_eq!(
+        fill_template(b"\xff{{cursor}}".to_vec(), "Day", date(2024, 6, 12)),
+        (b"\xff{{cursor}}".to_vec(), None)
+    );
+}
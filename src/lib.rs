@@ -0,0 +1,73 @@
+//! Core of Ryokan, an encrypted note vault.
+//!
+//! This crate holds everything that doesn't need a terminal: configuration,
+//! PIN hashing and key derivation, note encryption, metadata and scanning of
+//! the notes directory. Nothing in here prints or prompts; interactive input
+//! goes through [`pin::PinPrompt`], which the frontend implements.
+//!
+//! ```no_run
+//! use ryokan::{config::Config, vault::Vault};
+//! use zeroize::Zeroizing;
+//!
+//! # fn main() -> Result<(), ryokan::error::AppError> {
+//! let config = Config::new(None)?;
+//! let vault = Vault::open(config.notes_dir_path(), Zeroizing::new("123456".to_string()));
+//!
+//! let note = vault.create("Groceries", b"milk")?;
+//! assert!(vault.list()?.iter().any(|n| n.uuid == note.uuid));
+//! # Ok(())
+//! # }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(clippy::unwrap_used, clippy::expect_used)]
+
+pub mod audit;
+pub mod backup;
+pub mod bundle;
+pub mod clipboard;
+pub mod config;
+pub mod datetime;
+pub mod dedupe;
+pub mod diff;
+pub mod due;
+pub mod error;
+pub mod export;
+pub mod file;
+pub mod filename;
+pub mod gc;
+pub mod handshake;
+pub mod i18n;
+pub mod ignorefile;
+pub mod import;
+pub mod index;
+pub mod journal;
+pub mod links;
+pub mod lock;
+pub mod manifest;
+pub mod marker;
+pub mod metadata;
+pub mod migrate;
+pub mod note;
+pub mod notify;
+pub mod parallel;
+pub mod paths;
+pub mod permissions;
+pub mod pin;
+pub mod recovery;
+pub mod settings;
+pub mod share;
+pub mod sheet;
+pub mod snippet;
+pub mod stats;
+pub mod storage;
+pub mod sync;
+pub mod tags;
+pub mod template;
+pub mod text;
+pub mod todos;
+pub mod vault;
+pub mod vaultinfo;
+pub mod vaultstats;
+
+pub use error::AppError;
@@ -0,0 +1,105 @@
+//! Web links in note text, and opening them in the browser.
+//!
+//! Note content can come from anywhere, e.g. an import, so a link is handed to
+//! the system's opener as a single argument, never through a shell, and only
+//! `http` and `https` links are recognized.
+
+use crate::error::AppError;
+use std::{
+    ops::Range,
+    process::{Command, Stdio},
+};
+
+const SCHEMES: [&str; 2] = ["https://", "http://"];
+
+/// The program that opens a URL in the default browser, taking it as its only
+/// argument. `start` on Windows is a `cmd` builtin, so Explorer is used instead.
+pub const OPENER: &str = if cfg!(target_os = "macos") {
+    "open"
+} else if cfg!(windows) {
+    "explorer"
+} else {
+    "xdg-open"
+};
+
+/// Byte ranges of the links in `text`, in order.
+///
+/// A link ends at whitespace, quotes or angle brackets. Punctuation ending a
+/// sentence and closing brackets that aren't part of it, as in Markdown links,
+/// are left out.
+///
+/// ```
+/// let text = "See [the docs](https://example.com/a_(b)).";
+/// let links = ryokan::links::find(text);
+/// assert_eq!(&text[links[0].clone()], "https://example.com/a_(b)");
+/// ```
+pub fn find(text: &str) -> Vec<Range<usize>> {
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some((start, scheme_len)) = SCHEMES
+        .iter()
+        .filter_map(|scheme| text[from..].find(scheme).map(|i| (from + i, scheme.len())))
+        .min()
+    {
+        let end = start + link_len(&text[start..]);
+        // Not a link if it's the end of a word, e.g. `xhttps://`
+        let in_word = text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        if !in_word && end > start + scheme_len {
+            links.push(start..end);
+        }
+        from = end.max(start + scheme_len);
+    }
+    links
+}
+
+/// The links in `text`, each once, in order of first appearance.
+pub fn urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for range in find(text) {
+        let url = &text[range];
+        if !urls.iter().any(|known| known == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Length of the link at the start of `text`.
+fn link_len(text: &str) -> usize {
+    let mut end = text
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+        .unwrap_or(text.len());
+    loop {
+        let link = &text[..end];
+        let unbalanced =
+            |open: char, close: char| link.matches(open).count() < link.matches(close).count();
+        match link.chars().next_back() {
+            Some('.' | ',' | ';' | ':' | '!' | '?' | '\'' | '*') => end -= 1,
+            Some(')') if unbalanced('(', ')') => end -= 1,
+            Some(']') if unbalanced('[', ']') => end -= 1,
+            _ => return end,
+        }
+    }
+}
+
+/// Opens `url` with [`OPENER`], without waiting for the browser.
+pub fn open(url: &str) -> Result<(), AppError> {
+    if !SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+        return Err(AppError::Config(format!("{url} isn't a web link")));
+    }
+    let mut child = Command::new(OPENER)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Some openers only exit once the browser does
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod links_test;
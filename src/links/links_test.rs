@@ -0,0 +1,46 @@
+#![cfg(test)]
+
+use super::*;
+
+fn found(text: &str) -> Vec<&str> {
+    find(text).into_iter().map(|range| &text[range]).collect()
+}
+
+#[test]
+fn test_links_end_before_trailing_punctuation() {
+    assert_eq!(
+        found("Read https://example.com/page. Then http://a.org/x?y=1, or 'https://b.net'!"),
+        [
+            "https://example.com/page",
+            "http://a.org/x?y=1",
+            "https://b.net"
+        ]
+    );
+    assert_eq!(found("(see https://example.com)"), ["https://example.com"]);
+    assert_eq!(
+        found("[docs](https://en.wikipedia.org/wiki/Rust_(language))"),
+        ["https://en.wikipedia.org/wiki/Rust_(language)"]
+    );
+    assert_eq!(found("<https://example.com/é>"), ["https://example.com/é"]);
+}
+
+#[test]
+fn test_schemes_alone_or_inside_words_are_not_links() {
+    assert!(found("https:// and https://.").is_empty());
+    assert!(found("xhttps://example.com").is_empty());
+    assert!(found("ftp://example.com javascript:alert(1)").is_empty());
+}
+
+#[test]
+fn test_urls_are_listed_once() {
+    assert_eq!(
+        urls("https://a.org https://b.org\nhttps://a.org"),
+        ["https://a.org", "https://b.org"]
+    );
+}
+
+#[test]
+fn test_only_web_links_are_opened() {
+    let result = open("file:///etc/passwd");
+    assert!(matches!(result, Err(AppError::Config(_))), "{result:?}");
+}
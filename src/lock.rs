@@ -0,0 +1,49 @@
+//! An advisory lock on a notes directory, serializing read-modify-write changes
+//! between Ryokan processes, e.g. `ryokan append` while a note is open in the
+//! editor from the TUI.
+//!
+//! The lock is the OS file lock on [`LOCK_FILE_NAME`], so it's released even if
+//! the process holding it dies.
+
+use crate::error::AppError;
+use log::info;
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::Path,
+};
+
+/// Name of the lock file at the root of the vault.
+pub const LOCK_FILE_NAME: &str = ".ryokan.lock";
+
+/// Holds the lock until dropped.
+#[derive(Debug)]
+pub struct VaultLock {
+    _file: Option<File>,
+}
+
+impl VaultLock {
+    /// Locks the notes directory `dir`, waiting for other processes to release it.
+    ///
+    /// Without a directory, e.g. for a vault in memory, there is nothing to share
+    /// and the lock is a no-op.
+    pub fn acquire(dir: Option<&Path>) -> Result<Self, AppError> {
+        let Some(dir) = dir else {
+            return Ok(Self { _file: None });
+        };
+        let path = dir.join(LOCK_FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                info!("Waiting for another process to release {}", path.display());
+                file.lock()?;
+            }
+            Err(TryLockError::Error(e)) => return Err(AppError::Io(e)),
+        }
+        Ok(Self { _file: Some(file) })
+    }
+}
@@ -0,0 +1,109 @@
+//! Where log messages go: stderr for subcommands, and a file while the TUI owns
+//! the terminal, since stderr would draw over it and be lost with the alternate
+//! screen.
+
+use log::LevelFilter;
+use ryokan::{error::AppError, paths, permissions};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Size past which the log file is rotated.
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// `ryokan.log` in the state directory, e.g. `~/.local/state/ryokan/`, or in the
+/// local data directory on platforms without one.
+pub fn default_log_file() -> Option<PathBuf> {
+    paths::default_state_dir().map(|dir| dir.join(paths::LOG_FILE_NAME))
+}
+
+/// Sets up the logger, writing to `log_file` if given, and to stderr otherwise.
+pub fn init(level: LevelFilter, log_file: Option<&Path>) -> Result<(), AppError> {
+    let mut builder = env_logger::builder();
+    builder.filter_level(level);
+    if let Some(path) = log_file
+        && level != LevelFilter::Off
+    {
+        let file = RotatingFile::open(path, MAX_LOG_BYTES)?;
+        builder
+            .target(env_logger::Target::Pipe(Box::new(file)))
+            .write_style(env_logger::WriteStyle::Never)
+            .format_timestamp_millis();
+    }
+    builder.init();
+    Ok(())
+}
+
+/// A log file that's moved aside to `<name>.1` once it grows past `max_len`, so
+/// only the current and the previous file are kept.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_len: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_len: u64) -> Result<Self, AppError> {
+        if let Some(parent) = path.parent() {
+            permissions::create_private_dir(parent)?;
+        }
+        let file = Self::open_file(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            len,
+            max_len,
+        })
+    }
+
+    /// Opens the file for appending, readable only by the user on Unix: note
+    /// titles and UUIDs show up in the log.
+    fn open_file(path: &Path) -> io::Result<File> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(path)
+    }
+
+    /// Where the previous file is kept.
+    pub fn rotated_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(OsString::from(".1"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, Self::rotated_path(&self.path))?;
+        self.file = Self::open_file(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_len {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod logging_test;
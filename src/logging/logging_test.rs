@@ -0,0 +1,28 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_log_file_keeps_the_current_and_previous_file() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let path = dir.path().join("logs").join("ryokan.log");
+    let rotated = RotatingFile::rotated_path(&path);
+
+    let mut file = RotatingFile::open(&path, 16)?;
+    file.write_all(b"first line\n")?;
+    file.write_all(b"second line\n")?;
+    assert_eq!(fs::read(&path)?, b"second line\n");
+    assert_eq!(fs::read(&rotated)?, b"first line\n");
+
+    file.write_all(b"third line\n")?;
+    assert_eq!(fs::read(&path)?, b"third line\n");
+    assert_eq!(fs::read(&rotated)?, b"second line\n");
+
+    // Picks up where the file was left
+    let mut file = RotatingFile::open(&path, 16)?;
+    file.write_all(b"more lines\n")?;
+    assert_eq!(fs::read(&path)?, b"more lines\n");
+    assert_eq!(fs::read(&rotated)?, b"third line\n");
+    Ok(())
+}
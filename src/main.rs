@@ -1,25 +1,94 @@
 #![forbid(unsafe_code)]
-#![warn(clippy::unwrap_used)]
+#![warn(clippy::unwrap_used, clippy::expect_used)]
 
 mod args;
-mod config;
-mod error;
-mod file;
-mod metadata;
-mod note;
-mod pin;
+mod editor;
+mod logging;
+mod pager;
+mod palette;
+mod picker;
+mod prompt;
+mod theme;
+mod timing;
 mod tui;
+mod undo;
+mod wizard;
 
-use crate::{args::Args, config::Config, error::AppError};
-use clap::Parser;
-use log::LevelFilter;
-use log::info;
-use std::{fs, path::Path};
+use crate::{
+    args::{
+        Args, BackupAction, BundleAction, ConfigAction, ConfigKey, ListFormat, MetaAction,
+        ProfileAction, Subcommands, TagAction, VaultAction,
+    },
+    prompt::TerminalPrompt,
+    theme::Theme,
+    timing::{TimedPrompt, Timings},
+    wizard::WizardOutcome,
+};
+use clap::{CommandFactory, Parser};
+use log::{LevelFilter, warn};
+use ryokan::{
+    audit::{AuditLog, Event, EventKind},
+    backup::Backups,
+    bundle, clipboard,
+    config::{self, Config, Profile},
+    datetime::{self, DateFormat},
+    dedupe::{self, KeepPolicy},
+    diff,
+    due::{self, DueStatus},
+    error::AppError,
+    export::{self, ExportFormat},
+    file, filename, gc,
+    handshake::Handshake,
+    i18n,
+    ignorefile::{IGNORE_FILE_NAME, IgnoreRules},
+    import::{self, ImportFormat, ImportPlan, ImportReport},
+    journal::Journal,
+    manifest::IntegrityReport,
+    marker::{self, DirState},
+    metadata::{NoteMetadata, NoteType},
+    migrate, note,
+    permissions::{self, Loose},
+    pin::{self, PinPrompt},
+    recovery::Operation,
+    settings::{self, Settings, Source},
+    share::{self, OpenKey, ShareKey},
+    sheet,
+    stats::NoteStats,
+    storage::{FsStorage, Storage},
+    sync::GitSync,
+    t, tags,
+    template::Template,
+    text,
+    todos::{NoteTodos, TodoCache},
+    vault::{
+        self, EditOutcome, EncryptionSummary, ListQuery, Note, NoteVersion, Orphan, RekeySummary,
+        SortMode, Vault,
+    },
+    vaultinfo::VaultInfo,
+    vaultstats::{self, VaultStats},
+};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+/// Widest a title gets in `list --format table`, so the other columns stay in view.
+const LIST_TITLE_COLUMNS: usize = 60;
+
+/// How many notes `touch` changes before it asks, unless given `--yes`.
+const TOUCH_CONFIRM_ABOVE: usize = 5;
 
 fn main() -> Result<(), AppError> {
     let args = Args::parse();
-
-    let mut config = Config::new(args.config_file.as_ref())?;
+    if let Some(Subcommands::ApiInfo) = &args.command {
+        return print_api_info();
+    }
+    let mut timings = Timings::new(args.timing);
 
     let filter_level = match args.verbose_level {
         0 => LevelFilter::Off,
@@ -28,82 +97,2358 @@ fn main() -> Result<(), AppError> {
         _ => LevelFilter::Debug,
     };
 
-    env_logger::builder().filter_level(filter_level).init();
+    // The TUI would be drawn over by log messages on stderr
+    let log_file = args.log_file.clone().or_else(|| {
+        args.command
+            .is_none()
+            .then(logging::default_log_file)
+            .flatten()
+    });
+    if let Err(e) = logging::init(filter_level, log_file.as_deref()) {
+        eprintln!("{}", t!("cli-log-file-failed", error = e));
+    }
+    // Before anything could be written with a broken random number generator
+    note::self_test()?;
+    timings.phase("self-test");
+
+    let flags = args.flags();
+    let config_file = settings::config_file(&flags, &settings::process_env)?;
+    if config_file.source == Source::Default {
+        migrate_legacy_config(args.migrate_legacy)?;
+    }
+    let first_run = !config_file.value.exists();
+
+    let mut config = Config::new(Some(&config_file.value))?;
+
+    if let Some(Subcommands::Profile { action }) = &args.command {
+        return manage_profiles(&mut config, action);
+    }
+
+    let profile = settings::profile(&flags, &settings::process_env);
+    config.select_profile(profile.as_ref().map(|p| p.value.as_str()))?;
+    let mut settings = Settings::resolve(&flags, &settings::process_env, &config)?;
+    i18n::set_language(settings.language.value);
+    timings.phase("config load");
+
+    match &args.command {
+        Some(Subcommands::Config {
+            action: ConfigAction::Show,
+        }) => {
+            show_settings(&settings);
+            return Ok(());
+        }
+        Some(Subcommands::Config {
+            action: ConfigAction::Paths,
+        }) => {
+            let paths = &config.paths;
+            println!(
+                "{}",
+                t!("cli-path-config-file", path = paths.config_file.display())
+            );
+            println!(
+                "{}",
+                t!(
+                    "cli-path-notes-dir",
+                    path = settings.notes_dir.value.display()
+                )
+            );
+            println!(
+                "{}",
+                t!("cli-path-state-dir", path = paths.state_dir.display())
+            );
+            match config.audit_log.then(|| config.audit_log_path()).flatten() {
+                Some(path) => println!("{}", t!("cli-path-audit-log", path = path.display())),
+                None => println!("{}", t!("cli-path-no-audit-log")),
+            }
+            match args.log_file.clone().or_else(logging::default_log_file) {
+                Some(path) => println!("{}", t!("cli-path-log-file", path = path.display())),
+                None => println!("{}", t!("cli-path-no-log-file")),
+            }
+            println!(
+                "{}",
+                t!("cli-path-cache-dir", path = paths.cache_dir.display())
+            );
+            return Ok(());
+        }
+        Some(Subcommands::Config {
+            action: ConfigAction::Set { key, value },
+        }) => {
+            match key {
+                ConfigKey::Editor => config.editor = Some(value.clone()),
+                ConfigKey::EditorCursorArg => {
+                    if !value.is_empty() {
+                        editor::check_cursor_arg(value)?;
+                    }
+                    config.editor_cursor_arg = (!value.is_empty()).then(|| value.clone());
+                }
+                ConfigKey::Picker => config.picker = value.clone(),
+                ConfigKey::NotesDir => config.notes_dir = value.clone(),
+                ConfigKey::RecordStats => {
+                    config.record_stats = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::ListSnippets => {
+                    config.list_snippets = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::Inbox => config.inbox = value.clone(),
+                ConfigKey::JournalTitle => {
+                    Journal::new(value, None, None)?;
+                    config.journal_title = value.clone();
+                }
+                ConfigKey::NewNoteTitle => {
+                    Template::new("new_note_title", value, true)?;
+                    config.new_note_title = value.clone();
+                }
+                ConfigKey::CaptureHeader => {
+                    Template::new("capture_header", value, false)?;
+                    config.capture_header = value.clone();
+                }
+                ConfigKey::JournalTemplate => {
+                    config.journal_template = (!value.is_empty()).then(|| value.clone());
+                }
+                ConfigKey::JournalNotebook => {
+                    config.journal_notebook = (!value.is_empty()).then(|| value.clone());
+                }
+                ConfigKey::ReadOnlyMode => {
+                    config.read_only_mode = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::PreviewMaxBytes => {
+                    config.preview_max_bytes = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::PreviewDecryptLimit => {
+                    config.preview_decrypt_limit = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::MaxNoteSize => {
+                    config.max_note_size = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::ParanoidWrites => {
+                    config.paranoid_writes = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::DateFormat => {
+                    DateFormat::new(value)?;
+                    config.date_format = value.clone();
+                }
+                ConfigKey::WarnDuplicateTitles => {
+                    config.warn_duplicate_titles = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::AuditLog => {
+                    config.audit_log = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::EncryptAuditLog => {
+                    config.encrypt_audit_log = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::NotesDirMustExist => {
+                    config.notes_dir_must_exist = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::PreviewHeader => {
+                    config.preview_header = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::HighContrast => {
+                    config.high_contrast = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::Language => {
+                    if !value.is_empty() && i18n::supported(value).is_none() {
+                        let languages: Vec<_> =
+                            i18n::LANGUAGES.iter().map(|(code, _)| *code).collect();
+                        return Err(AppError::Config(format!(
+                            "No translation for \"{value}\", expected one of {}",
+                            languages.join(", ")
+                        )));
+                    }
+                    config.language = (!value.is_empty()).then(|| value.clone());
+                }
+                ConfigKey::PrivacyMasksTitles => {
+                    config.privacy_masks_titles = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::DesktopNotifications => {
+                    config.desktop_notifications = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected true or false, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::RekeyReminderDays => {
+                    config.rekey_reminder_days = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::EncryptConfirmFiles => {
+                    config.encrypt_confirm_files = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::EncryptConfirmBytes => {
+                    config.encrypt_confirm_bytes = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number of bytes, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::LazyLoadAbove => {
+                    config.lazy_load_above = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::PollIntervalMs => {
+                    config.poll_interval_ms = match value.parse() {
+                        Ok(0) | Err(_) => {
+                            return Err(AppError::Config(format!(
+                                "Expected a number of milliseconds above 0, got \"{value}\""
+                            )));
+                        }
+                        Ok(ms) => ms,
+                    };
+                }
+                ConfigKey::MaxTitleLength => {
+                    config.max_title_length = match value.parse() {
+                        Ok(0) | Err(_) => {
+                            return Err(AppError::Config(format!(
+                                "Expected a number above 0, got \"{value}\""
+                            )));
+                        }
+                        Ok(length) => length,
+                    };
+                }
+                ConfigKey::HistoryVersions => {
+                    config.history_versions = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::TrashRetentionDays => {
+                    config.trash_retention_days = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+                ConfigKey::CacheMaxBytes => {
+                    config.cache_max_bytes = value.parse().map_err(|_| {
+                        AppError::Config(format!("Expected a number, got \"{value}\""))
+                    })?;
+                }
+            }
+            config.save()?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    let dates = DateFormat::new(&config.date_format)?;
+    let journal = config.journal()?;
+    let new_note_title = config.new_note_title()?;
+    if let Some(arg) = &config.editor_cursor_arg {
+        editor::check_cursor_arg(arg)?;
+    }
+
+    if settings.read_only_mode.value
+        && let Some(command) = &args.command
+        && command.changes_notes()
+    {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-read-only-mode",
+                source = settings.read_only_mode.source
+            )
+        );
+        std::process::exit(1);
+    }
+
+    if let Some(Subcommands::Vault {
+        action: VaultAction::Describe { description },
+    }) = &args.command
+    {
+        let dir = &settings.notes_dir.value;
+        if marker::inspect(dir)? == DirState::Missing {
+            return Err(AppError::VaultNotFound(dir.clone()));
+        }
+        let storage = FsStorage::new(dir);
+        let mut info = VaultInfo::load(&storage)?.unwrap_or_default();
+        info.description = description.clone();
+        info.save(&storage)?;
+        println!("{}", t!("cli-described-vault", dir = dir.display()));
+        return Ok(());
+    }
+    if let Some(Subcommands::Backup {
+        action: BackupAction::List,
+    }) = &args.command
+    {
+        return list_backups(&config.backups(), &dates);
+    }
+
+    if let Some(Subcommands::Doctor { fix, roll_back }) = &args.command {
+        return doctor(
+            &mut config,
+            &settings.notes_dir.value,
+            *fix,
+            *roll_back,
+            settings.read_only_mode.value,
+            &dates,
+        );
+    }
 
-    let pin = pin::handle_pin_setup_and_verification(&mut config)?;
+    let sync = config
+        .sync
+        .as_ref()
+        .map(|_| GitSync::new(&settings.notes_dir.value));
+    if let Some(Subcommands::Sync) = &args.command {
+        let sync = sync.ok_or_else(|| {
+            AppError::Config(
+                "Sync is not configured, add a [sync] section with backend = \"git\"".to_string(),
+            )
+        })?;
+        let report = sync.sync()?;
+        println!(
+            "{}",
+            t!("cli-sync-committed", count = report.committed_notes)
+        );
+        if !report.pushed {
+            println!("{}", t!("cli-sync-no-upstream"));
+        }
+        if !report.conflicts.is_empty() {
+            println!(
+                "{}",
+                t!("cli-sync-conflicts", count = report.conflicts.len())
+            );
+        }
+        return Ok(());
+    }
 
-    if let Some(args::Subcommands::EncryptUnencrypted) = args.command {
-        encrypt_unencrypted_files(config.notes_dir_path(), &pin)?;
+    if let Some(Subcommands::Import {
+        path,
+        format,
+        identity,
+        dry_run: true,
+        ..
+    }) = &args.command
+    {
+        let rules = IgnoreRules::load(&FsStorage::new(config.notes_dir_path()))?;
+        let plan = plan_import(path, *format, identity.as_deref(), &rules)?;
+        for note in &plan.notes {
+            println!(
+                "{}",
+                t!(
+                    "cli-import-plan",
+                    source = note.source.display(),
+                    title = note.metadata.original_filename,
+                    created = dates.format(&note.metadata.created_at),
+                    tags = note.metadata.tags.join(", ")
+                )
+            );
+        }
+        for path in &plan.skipped {
+            println!("{}", t!("cli-skipped", name = path.display()));
+        }
+        for path in &plan.ignored {
+            println!("{}", t!("cli-ignored", name = path.display()));
+        }
+        for warning in &plan.warnings {
+            println!("{}", t!("cli-warning", warning = warning));
+        }
         return Ok(());
     }
 
-    let mut app = tui::App::new(config, pin, args)?;
-    app.run()?;
+    if let Some(Subcommands::Quick { text, pin_file }) = &args.command {
+        return quick_capture(&mut config, &settings, text.as_deref(), pin_file.as_deref());
+    }
+
+    if editor::resolve_editor(&settings.editor.value).is_none() {
+        warn!(
+            "Editor \"{}\" (from {}) was not found on PATH",
+            settings.editor.value, settings.editor.source
+        );
+    }
+
+    // Checked before asking for the PIN, and creating the directory after
+    let set_up = pin::load_pin_hash(&config).is_some();
+    if set_up {
+        prepare_notes_dir(&config, &settings, set_up)?;
+    }
+    let audit_log = config.audit_log();
+    let wizard = if first_run
+        && !args.no_wizard
+        && args.command.is_none()
+        && !settings.read_only_mode.value
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+    {
+        let theme = Theme::new(config.high_contrast, args.plain);
+        let outcome = wizard::run(
+            &mut config,
+            settings.notes_dir.clone(),
+            audit_log.as_ref(),
+            theme,
+        )?;
+        if outcome.quit {
+            return Ok(());
+        }
+        settings = Settings::resolve(&flags, &settings::process_env, &config)?;
+        i18n::set_language(settings.language.value);
+        timings.phase_excluding("first-run setup", outcome.waiting);
+        outcome
+    } else {
+        WizardOutcome::default()
+    };
+    let pin = match wizard.pin {
+        Some(pin) => pin,
+        None => {
+            let mut prompt = TimedPrompt::new(TerminalPrompt);
+            let pin = pin::handle_pin_setup_and_verification(
+                &mut config,
+                &mut prompt,
+                audit_log.as_ref(),
+            )?;
+            timings.phase_excluding("PIN verification", prompt.waiting);
+            pin
+        }
+    };
+
+    if let Some(Subcommands::Log { uuid, since, json }) = &args.command {
+        return print_audit_log(
+            &config,
+            &pin,
+            uuid.as_deref(),
+            since.as_deref(),
+            *json,
+            &dates,
+        );
+    }
+
+    prepare_notes_dir(&config, &settings, set_up)?;
+    let loose = permissions::check_all([
+        config.config_path.as_path(),
+        settings.notes_dir.value.as_path(),
+    ])?;
+    let startup_warnings: Vec<_> = loose.iter().map(permission_warning).collect();
+    // The TUI shows them at the bottom of the screen instead
+    if !matches!(args.command, None | Some(Subcommands::Open { .. })) {
+        for warning in &startup_warnings {
+            eprintln!("{}", t!("cli-warning", warning = warning));
+        }
+    }
+    let mut vault = Vault::open(settings.notes_dir.value.clone(), pin);
+    vault.set_record_stats(config.record_stats);
+    vault.set_snippets(config.list_snippets);
+    vault.set_history_limit(config.history_versions);
+    vault.set_max_note_size(config.max_note_size);
+    vault.set_max_title_length(config.max_title_length);
+    vault.set_paranoid_writes(config.paranoid_writes);
+    if let Some(audit_log) = audit_log {
+        vault.set_audit_log(audit_log);
+    }
+    if settings.read_only_mode.value {
+        vault.enter_read_only_mode();
+    }
+    match vault.interrupted_operation() {
+        Ok(Some(operation)) => {
+            offer_recovery(&vault, &operation, settings.read_only_mode.value, &dates)?;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("{}", t!("cli-warning", warning = e)),
+    }
+    if let Some((path, format)) = &wizard.import {
+        import_notes(&vault, path, *format, None)?;
+    }
+    // Taken before the command changes anything; restoring takes its own
+    if let Some(backups) = config.scheduled_backups()
+        && !settings.read_only_mode.value
+        && let Some(command) = &args.command
+        && command.changes_notes()
+        && !matches!(command, Subcommands::Restore { .. })
+        && let Err(e) = backups.take_if_due(&settings.notes_dir.value, chrono::Utc::now())
+    {
+        let warning = t!("cli-backup-failed", error = e);
+        eprintln!("{}", t!("cli-warning", warning = warning));
+    }
+
+    let start = match &args.command {
+        Some(Subcommands::Open { note }) => Some(tui::Start::Note(note.clone())),
+        _ => args.search.clone().map(tui::Start::Search),
+    };
+    match args.command {
+        Some(Subcommands::EncryptUnencrypted { dry_run: true, .. }) => {
+            let scan = vault.scan_unencrypted_files()?;
+            for entry in &scan.unencrypted {
+                println!("{}", t!("cli-would-encrypt", name = entry.name));
+            }
+            for (name, new_name) in &scan.misnamed {
+                println!(
+                    "{}",
+                    t!("cli-would-rename", name = name, new_name = new_name)
+                );
+            }
+            for name in &scan.ignored {
+                println!("{}", t!("cli-ignored", name = name));
+            }
+            for (name, reason) in &scan.skipped {
+                println!(
+                    "{}",
+                    t!("cli-skipped-because", name = name, reason = reason)
+                );
+            }
+            return Ok(());
+        }
+        Some(Subcommands::EncryptUnencrypted {
+            dry_run: false,
+            yes,
+        }) => {
+            if !yes && !confirm_encryption(&vault, &config, &settings.notes_dir.value)? {
+                println!("{}", t!("cli-nothing-encrypted"));
+                return Ok(());
+            }
+            let summary = vault.encrypt_unencrypted_files_with_progress(&|progress| {
+                eprintln!("[{}/{}] {}", progress.done, progress.total, progress.name);
+            })?;
+            if !print_encryption_summary(&summary) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Rekey) => {
+            let summary = vault.rekey_with_progress(&|progress| {
+                eprintln!("[{}/{}] {}", progress.done, progress.total, progress.name);
+            })?;
+            if !print_rekey_summary(&summary) {
+                std::process::exit(1);
+            }
+            config.set_last_rekey(chrono::Utc::now());
+            config.save()?;
+            return Ok(());
+        }
+        Some(Subcommands::List {
+            format,
+            json,
+            sort,
+            reverse,
+            limit,
+            filter,
+        }) => {
+            let query = ListQuery {
+                sort: sort.unwrap_or_default(),
+                reverse,
+                limit,
+                ..filter.query()
+            };
+            let format = if json { ListFormat::Json } else { format };
+            return list_notes(&vault, &query, format, &dates);
+        }
+        Some(Subcommands::Due { days }) => {
+            print_due_notes(&vault, days)?;
+            return Ok(());
+        }
+        Some(Subcommands::Todos) => {
+            print_todos(&vault)?;
+            return Ok(());
+        }
+        Some(Subcommands::Tag { action }) => {
+            match action {
+                TagAction::List => {
+                    for entry in tags::tree(&vault.list()?) {
+                        let indent = "  ".repeat(entry.depth);
+                        println!("{indent}{} ({})", entry.name(), entry.notes);
+                    }
+                }
+                TagAction::Rename { from, to } => {
+                    let summary = vault.rename_tag(&from, &to)?;
+                    println!(
+                        "{}",
+                        t!(
+                            "cli-renamed-tag",
+                            from = from,
+                            to = to,
+                            count = summary.renamed.len()
+                        )
+                    );
+                    print_skipped_read_only(&summary.skipped_read_only);
+                }
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Meta { action }) => {
+            match action {
+                MetaAction::Export { output } => {
+                    let notes = vault.list()?;
+                    match output {
+                        Some(output) => sheet::write(&notes, fs::File::create(output)?)?,
+                        None => sheet::write(&notes, io::stdout().lock())?,
+                    }
+                }
+                MetaAction::Import { path, yes } => {
+                    import_sheet(&vault, &path, yes)?;
+                }
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Touch {
+            notes,
+            all,
+            date,
+            created,
+            from_file_mtime,
+            yes,
+        }) => {
+            let notes = if all {
+                vault.list()?
+            } else {
+                notes
+                    .iter()
+                    .map(|note| vault.find(note))
+                    .collect::<Result<_, _>>()?
+            };
+            let change = if from_file_mtime {
+                Touch::FromFile
+            } else {
+                let at = match date {
+                    Some(date) => parse_timestamp(&date)?,
+                    None => chrono::Utc::now(),
+                };
+                if created {
+                    Touch::Created(at)
+                } else {
+                    Touch::Updated(at)
+                }
+            };
+            touch(&vault, &notes, change, yes, &dates)?;
+            return Ok(());
+        }
+        Some(Subcommands::Import {
+            path,
+            format,
+            identity,
+            mapping,
+            ..
+        }) => {
+            let report = import_notes(&vault, &path, format, identity.as_deref())?;
+            let lines: String = report
+                .imported
+                .iter()
+                .map(|(source, uuid)| format!("{}\t{uuid}\n", source.display()))
+                .collect();
+            print!("{lines}");
+            if let Some(mapping) = mapping {
+                fs::write(mapping, lines)?;
+            }
+            if !report.failed.is_empty() {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Dedupe {
+            auto_keep,
+            dry_run,
+            cache_hashes,
+        }) => {
+            return dedupe(&vault, &dates, auto_keep, dry_run, cache_hashes);
+        }
+        Some(Subcommands::Gc { apply }) => {
+            return gc(&vault, &config.gc_policy(), apply);
+        }
+        Some(Subcommands::Stats) => {
+            print_statistics(&vault.statistics()?);
+            print_rekey_reminder(&config);
+            return Ok(());
+        }
+        Some(Subcommands::Bundle { action }) => {
+            return run_bundle(&vault, &action);
+        }
+        Some(Subcommands::Share {
+            note,
+            recipient,
+            output,
+            ..
+        }) => {
+            let note = vault.find(&note)?;
+            let key = match recipient {
+                Some(recipient) => ShareKey::Recipient(recipient),
+                None => ShareKey::Passphrase(ask_new_passphrase(
+                    &mut TerminalPrompt,
+                    &t!("cli-share-passphrase"),
+                )?),
+            };
+            fs::write(&output, share::export(&vault, &note, &key)?)?;
+            println!("{}", t!("cli-wrote", path = output.display()));
+            return Ok(());
+        }
+        Some(Subcommands::Verify { fast }) => {
+            let report = vault.check_integrity(!fast)?;
+            print_integrity_report(&vault, &report)?;
+            if !report.is_clean() {
+                eprintln!("{}", t!("cli-verify-failed"));
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Reindex) => {
+            let index = vault.reindex()?;
+            println!("{}", t!("cli-indexed", count = index.titles.len()));
+            return Ok(());
+        }
+        Some(Subcommands::New {
+            title,
+            from_clipboard,
+            note_type,
+        }) => {
+            let content = if from_clipboard {
+                let text = clipboard::read_text()?;
+                if text.len() > clipboard::LARGE_PASTE
+                    && !TerminalPrompt.confirm(&t!("cli-large-clipboard", bytes = text.len()))?
+                {
+                    return Ok(());
+                }
+                text
+            } else {
+                Zeroizing::default()
+            };
+            let title = match title {
+                Some(title) => title,
+                None if from_clipboard => clipboard::default_title(&content),
+                None => vault.next_title(&new_note_title, chrono::Local::now())?,
+            };
+            return new_note(
+                &vault,
+                &title,
+                note_type,
+                content.as_bytes(),
+                &settings.editor.value,
+                config.warn_duplicate_titles,
+            );
+        }
+        Some(Subcommands::Pick { and_edit }) => {
+            let lines = picker::lines(&vault.list()?);
+            if !and_edit {
+                print!("{lines}");
+                return Ok(());
+            }
+            let Some(uuid) = picker::pick(&config.picker, &lines)? else {
+                return Ok(());
+            };
+            let note = vault.get(&uuid)?;
+            return edit_note(&vault, &note, &settings.editor.value, false, None);
+        }
+        Some(Subcommands::Diff { note, against }) => {
+            let note = vault.find(&note)?;
+            print_diff(&vault, &note, against.as_deref(), &dates)?;
+            return Ok(());
+        }
+        Some(Subcommands::Edit { note, force }) => {
+            let note = vault.find(&note)?;
+            if note.metadata.read_only && !force {
+                eprintln!(
+                    "{}",
+                    t!(
+                        "cli-edit-read-only",
+                        title = note.metadata.original_filename
+                    )
+                );
+                std::process::exit(1);
+            }
+            return edit_note(&vault, &note, &settings.editor.value, force, None);
+        }
+        Some(Subcommands::Today { yesterday, date }) => {
+            let today = chrono::Local::now().date_naive();
+            let date = match date {
+                Some(date) => {
+                    chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+                        AppError::Config(format!("Invalid date \"{date}\", expected YYYY-MM-DD"))
+                    })?
+                }
+                None if yesterday => today.pred_opt().unwrap_or(today),
+                None => today,
+            };
+            let (note, position) = journal.open(&vault, date)?;
+            let cursor = position.map(|position| editor::Cursor {
+                position,
+                arg: config.editor_cursor_arg.as_deref(),
+            });
+            return edit_note(&vault, &note, &settings.editor.value, false, cursor);
+        }
+        Some(Subcommands::Append {
+            note,
+            timestamp,
+            create,
+        }) => {
+            return insert_stdin(&vault, &note, timestamp, create, false);
+        }
+        Some(Subcommands::Prepend {
+            note,
+            timestamp,
+            create,
+        }) => {
+            return insert_stdin(&vault, &note, timestamp, create, true);
+        }
+        Some(Subcommands::Export {
+            filter,
+            format,
+            output,
+            allow_html,
+            dry_run,
+        }) => {
+            let notes = vault.query(&filter.query())?;
+            return export_notes(&vault, &notes, format, &output, allow_html, dry_run, &dates);
+        }
+        Some(Subcommands::Show {
+            note,
+            format,
+            output,
+            allow_html,
+            pager,
+            stats,
+        }) => {
+            let note = vault.find(&note)?;
+            let content = vault.read(&note.uuid)?;
+            if stats {
+                let text = String::from_utf8(content)
+                    .map_err(|_| AppError::BinaryNote(note.metadata.original_filename))?;
+                for (name, value) in NoteStats::of(&text).fields() {
+                    println!("{name}: {value}");
+                }
+                return Ok(());
+            }
+            let rendered = export::render(format, &note, &content, allow_html, &dates)?;
+            match output {
+                Some(output) => {
+                    if output.is_dir() {
+                        // Titles are free text, e.g. `../x`, so only a sanitized
+                        // one is used as the name
+                        let name = filename::sanitize(&note.metadata.original_filename);
+                        let (path, mut file) =
+                            filename::create_unique(&output, &name, format.extension())?;
+                        file.write_all(&rendered)?;
+                        println!("{}", path.display());
+                    } else {
+                        fs::write(output, rendered)?;
+                    }
+                    vault.record(
+                        Event::new(EventKind::Exported, Some(&note.uuid))
+                            .with_detail(format.to_string()),
+                    );
+                }
+                None if pager && io::stdout().is_terminal() => {
+                    if !pager::run_external(&rendered)? {
+                        let shown = String::from_utf8(rendered)
+                            .unwrap_or_else(|e| export::hex_dump(e.as_bytes()));
+                        let theme = Theme::new(config.high_contrast, args.plain);
+                        pager::run_internal(&note.metadata.original_filename, shown, &theme)?;
+                    }
+                }
+                None => io::stdout().write_all(&rendered)?,
+            }
+            return Ok(());
+        }
+        Some(Subcommands::Restore { from_snapshot }) => {
+            let backups = config.backups();
+            let snapshot = backups.find(&from_snapshot)?;
+            let before =
+                backups.restore(&snapshot, &settings.notes_dir.value, chrono::Utc::now())?;
+            println!(
+                "{}",
+                t!(
+                    "cli-restored-snapshot",
+                    name = snapshot.name,
+                    before = before.name
+                )
+            );
+            return Ok(());
+        }
+        Some(
+            Subcommands::ApiInfo
+            | Subcommands::Backup { .. }
+            | Subcommands::Config { .. }
+            | Subcommands::Profile { .. }
+            | Subcommands::Vault { .. }
+            | Subcommands::Open { .. }
+            | Subcommands::Sync
+            | Subcommands::Doctor { .. }
+            | Subcommands::Quick { .. }
+            | Subcommands::Log { .. },
+        )
+        | None => {}
+    }
+
+    let options = tui::AppOptions {
+        editor: settings.editor.value,
+        editor_cursor_arg: config.editor_cursor_arg.clone(),
+        profile: settings.profile.map(|p| p.value),
+        sync,
+        preview_max_bytes: config.preview_max_bytes,
+        preview_decrypt_limit: config.preview_decrypt_limit,
+        preview_header: config.preview_header,
+        poll_interval: Duration::from_millis(config.poll_interval_ms),
+        lazy_load_above: config.lazy_load_above,
+        warn_duplicate_titles: config.warn_duplicate_titles,
+        dates,
+        journal,
+        theme: Theme::new(config.high_contrast, args.plain),
+        private: args.private,
+        privacy_masks_titles: config.privacy_masks_titles,
+        desktop_notifications: config.desktop_notifications,
+        list_snippets: config.list_snippets,
+        new_note_title,
+        notes_dir: settings.notes_dir.value.clone(),
+        gc_policy: config.gc_policy(),
+        backups: config
+            .scheduled_backups()
+            .filter(|_| !settings.read_only_mode.value),
+        startup_warnings,
+    };
+    let mut app = tui::App::new(vault, options, start)?;
+    timings.phase(format!(
+        "vault scan ({} metadata files parsed)",
+        app.metadata_parses()
+    ));
+    app.run(&mut timings)?;
 
     Ok(())
 }
 
-fn encrypt_unencrypted_files(notes_dir: impl AsRef<Path>, pin: &str) -> Result<(), AppError> {
-    info!(
-        "Scanning for unencrypted files in {}...",
-        notes_dir.as_ref().display()
+/// Appends `text`, or a line read from stdin, to the inbox note. Prints nothing.
+fn quick_capture(
+    config: &mut Config,
+    settings: &Settings,
+    text: Option<&str>,
+    pin_file: Option<&Path>,
+) -> Result<(), AppError> {
+    let text = match text {
+        Some(text) => text.to_string(),
+        None => {
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line
+        }
+    };
+    if text.trim().is_empty() {
+        return Err(AppError::Config("Nothing to capture".to_string()));
+    }
+    let header = config.capture_header()?;
+
+    let set_up = pin::load_pin_hash(config).is_some();
+    if set_up {
+        prepare_notes_dir(config, settings, set_up)?;
+    }
+    let audit_log = config.audit_log();
+    let pin = match pin_file {
+        Some(path) => {
+            if pin::load_pin_hash(config).is_none() {
+                return Err(AppError::Pin(
+                    "No PIN is set yet, run ryokan once to set one".to_string(),
+                ));
+            }
+            pin::read_pin_file(path)?
+        }
+        None => {
+            pin::handle_pin_setup_and_verification(config, &mut TerminalPrompt, audit_log.as_ref())?
+        }
+    };
+
+    prepare_notes_dir(config, settings, set_up)?;
+    let mut vault = Vault::open(settings.notes_dir.value.clone(), pin.clone());
+    // Decrypting an existing inbox already proves the PIN, so it's only checked
+    // against the hash before creating one. Each check is a full Argon2 run.
+    if pin_file.is_some()
+        && matches!(vault.find(&config.inbox), Err(AppError::NoteNotFound(_)))
+        && !pin::verify_pin(config, &pin)?
+    {
+        record_pin_failure(audit_log.as_ref());
+        return Err(AppError::Pin("Incorrect PIN.".to_string()));
+    }
+    vault.set_record_stats(config.record_stats);
+    vault.set_snippets(config.list_snippets);
+    vault.set_history_limit(config.history_versions);
+    vault.set_max_note_size(config.max_note_size);
+    vault.set_max_title_length(config.max_title_length);
+    vault.set_paranoid_writes(config.paranoid_writes);
+    if let Some(audit_log) = audit_log {
+        vault.set_audit_log(audit_log);
+    }
+    let captured = vault.capture(&config.inbox, &text, &header, chrono::Local::now());
+    if let Err(AppError::Decryption(_)) = &captured
+        && pin_file.is_some()
+        && !pin::verify_pin(config, &pin)?
+    {
+        // Recorded outside the vault, which would seal it with the wrong PIN
+        record_pin_failure(config.audit_log().as_ref());
+        return Err(AppError::Pin("Incorrect PIN.".to_string()));
+    }
+    captured?;
+    Ok(())
+}
+
+/// Records a wrong PIN that didn't go through the PIN prompt, e.g. from a file.
+fn record_pin_failure(audit_log: Option<&AuditLog>) {
+    if let Some(audit_log) = audit_log {
+        audit_log.record(&Event::new(EventKind::PinFailed, None), None);
+    }
+}
+
+/// Asks before `encrypt-unencrypted` turns more files or bytes than the config
+/// allows into notes, echoing where, in case `dir` is the wrong directory.
+fn confirm_encryption(vault: &Vault, config: &Config, dir: &Path) -> Result<bool, AppError> {
+    let scan = vault.scan_unencrypted_files()?;
+    let files = scan.unencrypted.len();
+    let bytes = scan.unencrypted_bytes();
+    if files <= config.encrypt_confirm_files && bytes <= config.encrypt_confirm_bytes {
+        return Ok(true);
+    }
+    let path = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    TerminalPrompt.confirm(&t!(
+        "cli-encrypt-confirm",
+        files = files,
+        bytes = bytes,
+        dir = path.display()
+    ))
+}
+
+/// What to tell about a file or directory other users have access to.
+fn permission_warning(loose: &Loose) -> String {
+    t!(
+        "cli-loose-permissions",
+        path = loose.path.display(),
+        mode = format!("{:o}", loose.mode),
+        expected = format!("{:o}", loose.private_mode)
+    )
+}
+
+/// Creates the notes directory for a new vault, saying where, or exits with an
+/// explanation if it's missing when it shouldn't be.
+fn prepare_notes_dir(config: &Config, settings: &Settings, set_up: bool) -> Result<(), AppError> {
+    let dir = &settings.notes_dir.value;
+    match marker::prepare(dir, config.notes_dir_must_exist, set_up) {
+        Ok(true) => {
+            // Absolute, so a wrong relative `--notes-dir` is obvious
+            let path = std::path::absolute(dir).unwrap_or_else(|_| dir.clone());
+            eprintln!("{}", t!("cli-creating-vault", path = path.display()));
+            Ok(())
+        }
+        Ok(false) => Ok(()),
+        Err(e @ AppError::VaultNotFound(_)) => {
+            eprintln!("{e}");
+            eprintln!("{}", t!("cli-use-as-vault", dir = dir.display()));
+            std::process::exit(1);
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Works out what importing `path` would do. An age file is decrypted with the
+/// `identity` file, or with a passphrase asked for if it was encrypted to one.
+fn plan_import(
+    path: &Path,
+    format: ImportFormat,
+    identity: Option<&Path>,
+    rules: &IgnoreRules,
+) -> Result<ImportPlan, AppError> {
+    if format != ImportFormat::Age {
+        return import::plan(path, format, rules);
+    }
+    let key = if share::needs_passphrase(&fs::read(path)?)? {
+        OpenKey::Passphrase(TerminalPrompt.read_secret(&t!("cli-age-passphrase"))?)
+    } else {
+        let identity = identity.ok_or_else(|| {
+            AppError::Age(format!(
+                "{} is encrypted to a public key, pass its --identity file",
+                path.display()
+            ))
+        })?;
+        OpenKey::Identities(share::read_identities(identity)?)
+    };
+    import::plan_age(path, &key)
+}
+
+/// Imports the notes below `path`, reporting progress, warnings, failures and a
+/// summary on stderr.
+fn import_notes(
+    vault: &Vault,
+    path: &Path,
+    format: ImportFormat,
+    identity: Option<&Path>,
+) -> Result<ImportReport, AppError> {
+    let plan = plan_import(path, format, identity, &vault.ignore_rules()?)?;
+    let report = import::run(vault, &plan, &|progress| {
+        eprintln!("[{}/{}] {}", progress.done, progress.total, progress.name);
+    })?;
+    for warning in &plan.warnings {
+        eprintln!("{}", t!("cli-warning", warning = warning));
+    }
+    for (source, error) in &report.failed {
+        eprintln!(
+            "{}",
+            t!("cli-import-failed", path = source.display(), error = error)
+        );
+    }
+    eprintln!(
+        "{}",
+        t!(
+            "cli-imported",
+            imported = report.imported.len(),
+            skipped = plan.skipped.len(),
+            warnings = plan.warnings.len()
+        )
     );
+    Ok(report)
+}
 
-    let mut unencrypted_files = Vec::new();
-
-    for entry in fs::read_dir(&notes_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file() {
-            let file_content = fs::read(&path)?;
-            if file::is_encrypted_file(&file_content) {
-                // It's an encrypted file
-                let is_encrypted = path
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .is_some_and(|s| s.ends_with(".enc.txt"));
-                if !is_encrypted {
-                    // Rename it: it's an encrypted file, but without the correct extension
-                    let new_path = path.with_extension("enc.txt");
-                    info!(
-                        "Renaming encrypted file: {} -> {}",
-                        path.display(),
-                        new_path.display()
+/// Reports the state of the notes directory, creating or marking it with `fix`.
+///
+/// The integrity manifest of a vault is encrypted, so checking it asks for the
+/// PIN, if one is set.
+fn doctor(
+    config: &mut Config,
+    notes_dir: &Path,
+    fix: bool,
+    roll_back: bool,
+    read_only_mode: bool,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    println!("{}", t!("doctor-notes-dir", dir = notes_dir.display()));
+    for loose in permissions::check_all([config.config_path.as_path(), notes_dir])? {
+        if fix {
+            permissions::restrict(&loose)?;
+            let restricted = t!(
+                "doctor-restricted",
+                path = loose.path.display(),
+                mode = format!("{:o}", loose.private_mode)
+            );
+            println!("{}", t!("check-fixed", message = restricted));
+        } else {
+            println!(
+                "{}",
+                t!("check-problem", message = permission_warning(&loose))
+            );
+        }
+    }
+    let (problem, fixed) = match marker::inspect(notes_dir)? {
+        DirState::Marked => {
+            println!("{}", t!("check-ok", message = t!("doctor-marked")));
+            let storage = FsStorage::new(notes_dir);
+            let rules = IgnoreRules::load(&storage)?;
+            for entry in storage.list("")? {
+                if !file::is_unexpected_file(&entry.name) {
+                    continue;
+                }
+                if rules.is_ignored(Path::new(&entry.name), false) {
+                    let message = t!(
+                        "doctor-kept-plaintext",
+                        name = entry.name,
+                        ignore_file = IGNORE_FILE_NAME
                     );
-                    fs::rename(&path, &new_path)?;
+                    println!("{}", t!("check-ok", message = message));
+                } else {
+                    let message = t!("doctor-stray-file", name = entry.name);
+                    println!("{}", t!("check-warning", message = message));
                 }
-            } else {
-                // It's truly unencrypted, so add to list
-                unencrypted_files.push(path);
             }
+            let interrupted = Operation::load(&storage)?;
+            if let Some(operation) = &interrupted {
+                let interrupted = describe_interrupted(operation, dates);
+                println!("{}", t!("check-problem", message = interrupted));
+            }
+            if pin::load_pin_hash(config).is_some() {
+                let audit_log = config.audit_log();
+                let pin = pin::handle_pin_setup_and_verification(
+                    config,
+                    &mut TerminalPrompt,
+                    audit_log.as_ref(),
+                )?;
+                let mut vault = Vault::open(notes_dir, pin);
+                if read_only_mode {
+                    vault.enter_read_only_mode();
+                }
+                for orphan in vault.orphaned_files()? {
+                    let message = match orphan {
+                        Orphan::Metadata { uuid, title } => t!(
+                            "doctor-orphan-metadata",
+                            note = title.map_or(uuid, |title| format!("\"{title}\""))
+                        ),
+                        Orphan::Content { uuid } => t!("doctor-orphan-content", uuid = uuid),
+                    };
+                    println!("{}", t!("check-warning", message = message));
+                }
+                if interrupted.is_some() {
+                    if fix {
+                        print_encryption_summary(&vault.resume_operation()?);
+                        println!("{}", t!("check-fixed", message = t!("doctor-resumed")));
+                    } else if roll_back {
+                        let restored = vault.roll_back_operation()?;
+                        let rolled_back = t!("doctor-rolled-back", count = restored.len());
+                        println!("{}", t!("check-fixed", message = rolled_back));
+                    } else {
+                        let message = t!("doctor-resume-or-roll-back");
+                        println!("{}", t!("check-problem", message = message));
+                    }
+                }
+                print_integrity_report(&vault, &vault.check_integrity(false)?)?;
+            }
+            print_rekey_reminder(config);
+            return Ok(());
         }
+        DirState::Missing => (t!("doctor-missing"), t!("doctor-created")),
+        DirState::Unmarked { .. } => (t!("doctor-unmarked"), t!("doctor-now-marked")),
+    };
+    if fix {
+        marker::mark(notes_dir)?;
+        println!("{}", t!("check-fixed", message = fixed));
+    } else {
+        let problem = t!("doctor-run-fix", problem = problem);
+        println!("{}", t!("check-problem", message = problem));
     }
+    Ok(())
+}
 
-    if !unencrypted_files.is_empty() {
-        info!(
-            "Found {} unencrypted files. Encrypting...",
-            unencrypted_files.len()
+/// Prints the [`Handshake`] of this build, before any configuration is read so
+/// it works whatever state the vault is in.
+fn print_api_info() -> Result<(), AppError> {
+    let command = Args::command();
+    let subcommands = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name())
+        .collect();
+    let json = serde_json::to_string_pretty(&Handshake::new(subcommands))
+        .map_err(|e| AppError::Config(format!("Could not serialize the API info: {e}")))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Prints what [`Vault::encrypt_unencrypted_files`] did. Returns whether every
+/// file could be encrypted.
+fn print_encryption_summary(summary: &EncryptionSummary) -> bool {
+    for (name, reason) in &summary.skipped {
+        eprintln!(
+            "{}",
+            t!("cli-encrypt-skipped", name = name, reason = reason)
         );
-        for file_path in unencrypted_files {
-            info!("Encrypting {}...", file_path.display());
-            let content = fs::read(&file_path)?;
+    }
+    println!(
+        "{}",
+        t!(
+            "cli-encrypted",
+            encrypted = summary.encrypted.len(),
+            renamed = summary.renamed.len(),
+            ignored = summary.ignored.len()
+        )
+    );
+    for (name, error) in &summary.failed {
+        eprintln!("{}", t!("cli-encrypt-failed", name = name, error = error));
+    }
+    summary.failed.is_empty()
+}
 
-            let original_filename = file_path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+/// Reminds the user to run `ryokan rekey` if it's been longer than
+/// `rekey_reminder_days` since the notes were last encrypted again.
+fn print_rekey_reminder(config: &Config) {
+    if let Some(days) = config.rekey_overdue(chrono::Utc::now()) {
+        let reminder = t!("cli-rekey-overdue", days = days);
+        println!("{}", t!("cli-warning", warning = reminder));
+    }
+}
 
-            file::create_new_note(notes_dir.as_ref(), pin, &original_filename, &content)?;
+/// Prints what [`Vault::rekey`] did. Returns whether every file was encrypted
+/// again and checked.
+fn print_rekey_summary(summary: &RekeySummary) -> bool {
+    println!("{}", t!("cli-rekeyed", count = summary.rekeyed.len()));
+    for (name, error) in &summary.failed {
+        eprintln!("{}", t!("cli-rekey-failed", name = name, error = error));
+    }
+    for (name, error) in &summary.unverified {
+        eprintln!("{}", t!("cli-rekey-unverified", name = name, error = error));
+    }
+    summary.is_complete()
+}
 
-            fs::remove_file(&file_path)?;
-            info!("Encrypted {}", file_path.display());
+/// Says which bulk operation was interrupted, when, and how far it got.
+fn describe_interrupted(operation: &Operation, dates: &DateFormat) -> String {
+    t!(
+        "cli-interrupted",
+        command = operation.kind,
+        started = dates.format(&operation.started_at),
+        done = operation.done(),
+        total = operation.files.len()
+    )
+}
+
+/// Tells about a bulk operation that was interrupted, e.g. by a power cut, and
+/// offers to resume or roll it back when there's someone to ask.
+fn offer_recovery(
+    vault: &Vault,
+    operation: &Operation,
+    read_only_mode: bool,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    eprintln!("{}.", describe_interrupted(operation, dates));
+    if !read_only_mode && io::stdin().is_terminal() {
+        if TerminalPrompt.confirm(&t!("cli-resume-now"))? {
+            print_encryption_summary(&vault.resume_operation()?);
+            return Ok(());
+        }
+        if TerminalPrompt.confirm(&t!("cli-roll-back-instead"))? {
+            let restored = vault.roll_back_operation()?;
+            eprintln!("{}", t!("cli-put-back", count = restored.len()));
+            return Ok(());
+        }
+    }
+    eprintln!("{}", t!("cli-resume-or-roll-back"));
+    Ok(())
+}
+
+/// Prints what [`Vault::check_integrity`] found, one line per note that looks
+/// corrupt.
+fn print_integrity_report(vault: &Vault, report: &IntegrityReport) -> Result<(), AppError> {
+    let titles = vault.index()?.titles;
+    let describe = |uuid: &String| match titles.get(uuid) {
+        Some(title) => format!("\"{title}\" ({uuid})"),
+        None => uuid.clone(),
+    };
+    if let Some(warning) = &report.warning {
+        let message = t!("integrity-warning", warning = warning);
+        println!("{}", t!("check-warning", message = message));
+    }
+    if !report.updated.is_empty() {
+        let message = t!("integrity-accepted", count = report.updated.len());
+        println!("{}", t!("check-ok", message = message));
+    }
+    for uuid in &report.corrupt {
+        let message = t!("integrity-corrupt", note = describe(uuid));
+        println!("{}", t!("check-problem", message = message));
+    }
+    for (uuid, e) in &report.undecryptable {
+        let message = t!("integrity-undecryptable", note = describe(uuid), error = e);
+        println!("{}", t!("check-problem", message = message));
+    }
+    if report.is_clean() {
+        let message = t!("integrity-clean", count = report.checked);
+        println!("{}", t!("check-ok", message = message));
+    }
+    Ok(())
+}
+
+/// Prints the events of the audit log about the note `uuid` and `since` a date,
+/// if given, one per line or as JSON lines.
+fn print_audit_log(
+    config: &Config,
+    pin: &str,
+    uuid: Option<&str>,
+    since: Option<&str>,
+    json: bool,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    let since = since
+        .map(|since| match chrono::DateTime::parse_from_rfc3339(since) {
+            Ok(at) => Ok(at.with_timezone(&chrono::Utc)),
+            Err(_) => due::parse_due_date(since, &chrono::Local::now()),
+        })
+        .transpose()?;
+    let path = config
+        .audit_log_path()
+        .ok_or_else(|| AppError::Config("Invalid config path".to_string()))?;
+    if !config.audit_log {
+        eprintln!("{}", t!("cli-audit-log-disabled"));
+    }
+    let events = AuditLog::new(path, config.encrypt_audit_log).read(Some(pin))?;
+    for event in events
+        .iter()
+        .filter(|event| event.matches(uuid, since.as_ref()))
+    {
+        if json {
+            let line = serde_json::to_string(event)
+                .map_err(|e| AppError::Config(format!("Could not serialize the event: {e}")))?;
+            println!("{line}");
+        } else {
+            println!(
+                "{}\t{}\t{}\t{}",
+                dates.format(&event.at),
+                event.kind,
+                event.uuid.as_deref().unwrap_or("-"),
+                event.detail.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Shows what the CSV file at `path` changes in the metadata of the notes, and
+/// applies it once confirmed, or right away with `yes`.
+fn import_sheet(vault: &Vault, path: &Path, yes: bool) -> Result<(), AppError> {
+    let plan = sheet::read(fs::File::open(path)?, &vault.list()?)?;
+    if !plan.ignored_columns.is_empty() {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-sheet-ignored-columns",
+                columns = plan.ignored_columns.join(", ")
+            )
+        );
+    }
+    for change in &plan.changes {
+        println!("\"{}\" ({})", change.title, change.uuid);
+        for field in &change.fields {
+            println!(
+                "  {}: {:?} -> {:?}",
+                field.column, field.before, field.after
+            );
+        }
+    }
+    println!(
+        "{}",
+        t!(
+            "cli-sheet-plan",
+            count = plan.changes.len(),
+            unchanged = plan.unchanged
+        )
+    );
+    if plan.changes.is_empty() || !(yes || TerminalPrompt.confirm(&t!("cli-apply-changes"))?) {
+        return Ok(());
+    }
+    let applied = sheet::apply(vault, &plan)?;
+    println!("{}", t!("changed-notes", count = applied.changed.len()));
+    print_skipped_read_only(&applied.skipped_read_only);
+    Ok(())
+}
+
+/// Says which notes a bulk operation left alone because they're read-only.
+fn print_skipped_read_only(uuids: &[String]) {
+    if !uuids.is_empty() {
+        println!(
+            "{}",
+            t!(
+                "cli-skipped-read-only",
+                count = uuids.len(),
+                uuids = uuids.join(", ")
+            )
+        );
+    }
+}
+
+/// Which timestamps `touch` sets.
+#[derive(Clone, Copy)]
+enum Touch {
+    Updated(chrono::DateTime<chrono::Utc>),
+    Created(chrono::DateTime<chrono::Utc>),
+    /// Both, from the encrypted file of each note.
+    FromFile,
+}
+
+/// Sets the timestamps of `notes` as `change` says, after printing a table of
+/// them before and after. More than [`TOUCH_CONFIRM_ABOVE`] notes are only
+/// changed with `yes` or when the user agrees.
+fn touch(
+    vault: &Vault,
+    notes: &[Note],
+    change: Touch,
+    yes: bool,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    let mut changes = Vec::new();
+    let mut skipped_read_only = Vec::new();
+    for note in notes {
+        if note.metadata.read_only {
+            skipped_read_only.push(note.uuid.clone());
+            continue;
+        }
+        let before = (note.metadata.created_at, note.metadata.updated_at);
+        let after = match change {
+            Touch::Updated(at) => (before.0, at),
+            Touch::Created(at) => (at, before.1),
+            Touch::FromFile => vault.file_times(&note.uuid)?,
+        };
+        if after != before {
+            changes.push((note, before, after));
+        }
+    }
+    if changes.is_empty() {
+        println!("{}", t!("cli-nothing-to-change"));
+        print_skipped_read_only(&skipped_read_only);
+        return Ok(());
+    }
+
+    let title_header = t!("column-title");
+    let title_width = changes
+        .iter()
+        .map(|(note, ..)| text::width(&note.metadata.original_filename))
+        .max()
+        .unwrap_or(0)
+        .clamp(text::width(&title_header), LIST_TITLE_COLUMNS);
+    let mut rows = vec![[
+        format!("{title_header:title_width$}"),
+        t!("column-field"),
+        t!("column-before"),
+        t!("column-after"),
+    ]];
+    for (note, before, after) in &changes {
+        let title = text::truncate(&note.metadata.original_filename, title_width);
+        let title = format!("{title}{}", " ".repeat(title_width - text::width(title)));
+        for (field, before, after) in [
+            (t!("touch-created"), before.0, after.0),
+            (t!("touch-updated"), before.1, after.1),
+        ] {
+            if before != after {
+                rows.push([
+                    title.clone(),
+                    field,
+                    dates.format(&before),
+                    dates.format(&after),
+                ]);
+            }
+        }
+    }
+    let field_width = rows
+        .iter()
+        .map(|row| text::width(&row[1]))
+        .max()
+        .unwrap_or(0);
+    let date_width = rows
+        .iter()
+        .map(|row| text::width(&row[2]))
+        .max()
+        .unwrap_or(0);
+    for [title, field, before, after] in rows {
+        println!("{title}  {field:field_width$}  {before:date_width$}  {after}");
+    }
+
+    if changes.len() > TOUCH_CONFIRM_ABOVE
+        && !(yes || TerminalPrompt.confirm(&t!("cli-touch-confirm", count = changes.len()))?)
+    {
+        return Ok(());
+    }
+    let _lock = vault.lock()?;
+    for (note, _, (created_at, updated_at)) in &changes {
+        vault.set_timestamps(&note.uuid, *created_at, *updated_at)?;
+    }
+    println!("{}", t!("changed-notes", count = changes.len()));
+    print_skipped_read_only(&skipped_read_only);
+    Ok(())
+}
+
+/// Parses a time given on the command line, as RFC 3339 or as a day for
+/// [`datetime::parse_day`], which means its first instant.
+fn parse_timestamp(input: &str) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    match chrono::DateTime::parse_from_rfc3339(input) {
+        Ok(at) => Ok(at.with_timezone(&chrono::Utc)),
+        Err(_) => datetime::parse_day(input, &chrono::Local::now(), false),
+    }
+}
+
+/// Creates a note titled `title` with `content` and opens it, or the existing
+/// note with that title if the user prefers.
+fn new_note(
+    vault: &Vault,
+    title: &str,
+    note_type: NoteType,
+    content: &[u8],
+    editor: &str,
+    warn_duplicate: bool,
+) -> Result<(), AppError> {
+    let existing = vault
+        .index()?
+        .titles
+        .into_iter()
+        .find(|(_, existing)| vault::same_title(existing, title));
+    if warn_duplicate && let Some((uuid, _)) = existing {
+        let existing = vault.get(&uuid)?;
+        let age = tui::format_age(existing.metadata.created_at, chrono::Utc::now());
+        print!("{} ", t!("cli-duplicate-title", age = age));
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        match answer.trim() {
+            "o" => return edit_note(vault, &existing, editor, false, None),
+            "c" => {}
+            _ => return Ok(()),
+        }
+    }
+    let mut metadata = NoteMetadata::new(title);
+    metadata.note_type = note_type;
+    let note = vault.create_with_metadata(&metadata, content)?;
+    edit_note(vault, &note, editor, false, None)
+}
+
+/// Opens a note in the editor, asking first if its content is binary, and
+/// warns if the edit had to be saved as a conflict copy.
+fn edit_note(
+    vault: &Vault,
+    note: &Note,
+    editor: &str,
+    force: bool,
+    cursor: Option<editor::Cursor>,
+) -> Result<(), AppError> {
+    let outcome = match editor::edit_note(vault, &note.uuid, editor, force, false, cursor) {
+        Err(AppError::BinaryNote(_)) => {
+            let prompt = t!("cli-edit-binary", title = note.metadata.original_filename);
+            if !TerminalPrompt.confirm(&prompt)? {
+                return Ok(());
+            }
+            editor::edit_note(vault, &note.uuid, editor, force, true, cursor)?
+        }
+        result => result?,
+    };
+    if let EditOutcome::Conflict(copy) = outcome {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-edit-conflict",
+                title = note.metadata.original_filename,
+                copy = copy.metadata.original_filename
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Adds stdin, taken as raw bytes, at the end or the `start` of a note.
+fn insert_stdin(
+    vault: &Vault,
+    note: &str,
+    timestamp: bool,
+    create: bool,
+    start: bool,
+) -> Result<(), AppError> {
+    let mut data = Vec::new();
+    io::stdin().lock().read_to_end(&mut data)?;
+    let separator = timestamp.then(|| {
+        chrono::Local::now()
+            .format("--- %Y-%m-%d %H:%M:%S ---")
+            .to_string()
+    });
+
+    let uuid = match vault.find(note) {
+        Ok(found) => found.uuid,
+        Err(AppError::NoteNotFound(_)) if create => {
+            let mut content = separator
+                .map(|separator| format!("{separator}\n").into_bytes())
+                .unwrap_or_default();
+            content.extend_from_slice(&data);
+            vault.create(note, &content)?;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    if start {
+        vault.prepend(&uuid, &data, separator.as_deref())?;
+    } else {
+        vault.append(&uuid, &data, separator.as_deref())?;
+    }
+    Ok(())
+}
+
+/// Prints one `date<TAB>status<TAB>title` line per note due within `days`.
+fn print_due_notes(vault: &Vault, days: u64) -> Result<(), AppError> {
+    let now = chrono::Local::now();
+    let horizon = now
+        .date_naive()
+        .checked_add_days(chrono::Days::new(days))
+        .unwrap_or(chrono::NaiveDate::MAX);
+
+    let mut notes = vault.list()?;
+    SortMode::Due.sort(&mut notes);
+    for note in notes {
+        let Some(due_at) = note.metadata.due_at else {
+            break;
+        };
+        if due_at.with_timezone(&chrono::Local).date_naive() > horizon {
+            break;
+        }
+        let status = match due::due_status(&due_at, &now) {
+            DueStatus::Overdue => "overdue",
+            DueStatus::Today => "today",
+            DueStatus::Upcoming => "upcoming",
+        };
+        println!(
+            "{}\t{status}\t{}",
+            due::format_due_date(&due_at, &chrono::Local),
+            note.metadata.original_filename
+        );
+    }
+    Ok(())
+}
+
+fn print_todos(vault: &Vault) -> Result<(), AppError> {
+    let mut notes = vault.list()?;
+    SortMode::Title.sort(&mut notes);
+    let found = TodoCache::default().collect(vault, &notes)?;
+    for (
+        i,
+        NoteTodos {
+            note,
+            todos,
+            checked,
+        },
+    ) in found.iter().enumerate()
+    {
+        if i > 0 {
+            println!();
+        }
+        if note.metadata.note_type == NoteType::Todo {
+            let total = checked + todos.len();
+            println!("{} ({checked}/{total})", note.metadata.original_filename);
+        } else {
+            println!("{}", note.metadata.original_filename);
+        }
+        for todo in todos {
+            println!("  - [ ] {}", todo.text);
+        }
+    }
+    Ok(())
+}
+
+/// The newest version of `note` saved at or before `against`, or the newest
+/// one of all.
+fn choose_version(
+    vault: &Vault,
+    note: &Note,
+    against: Option<&str>,
+) -> Result<NoteVersion, AppError> {
+    let versions = vault.history(&note.uuid)?;
+    let title = &note.metadata.original_filename;
+    let Some(against) = against else {
+        return versions.into_iter().next().ok_or_else(|| {
+            AppError::NoteNotFound(format!("\"{title}\" has no previous versions"))
+        });
+    };
+    let before = match chrono::DateTime::parse_from_rfc3339(against) {
+        Ok(at) => at.with_timezone(&chrono::Utc) + chrono::TimeDelta::nanoseconds(1),
+        Err(_) => datetime::parse_day(against, &chrono::Local::now(), true)?,
+    };
+    // Newest first
+    versions
+        .into_iter()
+        .find(|version| version.saved_at < before)
+        .ok_or_else(|| {
+            AppError::NoteNotFound(format!("\"{title}\" has no version saved before {against}"))
+        })
+}
+
+/// Prints what changed in `note` since a previous version, see `diff`.
+fn print_diff(
+    vault: &Vault,
+    note: &Note,
+    against: Option<&str>,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    let version = choose_version(vault, note, against)?;
+    let old = Zeroizing::new(vault.read_version(&note.uuid, &version.id)?);
+    let new = Zeroizing::new(vault.read(&note.uuid)?);
+    let Some((old_text, new_text)) = diff::as_text(&old, &new) else {
+        if old != new {
+            println!("{}", t!("cli-binary-differ"));
+        }
+        return Ok(());
+    };
+    let title = &note.metadata.original_filename;
+    let unified = Zeroizing::new(diff::unified(
+        &diff::lines(old_text, new_text),
+        &format!("{title} ({})", dates.format(&version.saved_at)),
+        &t!("diff-current", title = title),
+        3,
+    ));
+    io::stdout().write_all(unified.as_bytes())?;
+    Ok(())
+}
+
+/// Writes `notes` to the directory `output` in `format`, a file per note, or
+/// to the single file `output` as JSON. Prints the files written, or that would
+/// be with `dry_run`, and a summary on stderr.
+fn export_notes(
+    vault: &Vault,
+    notes: &[Note],
+    format: ExportFormat,
+    output: &Path,
+    allow_html: bool,
+    dry_run: bool,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    let mut failed = 0;
+    let report_failure = |note: &Note, e: AppError| {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-export-failed",
+                title = note.metadata.original_filename,
+                uuid = note.uuid,
+                error = e
+            )
+        );
+    };
+    let exported = if format == ExportFormat::Json {
+        let path = if output.is_dir() {
+            filename::unique_path(output, "notes", format.extension(), &HashSet::new())
+        } else {
+            output.to_path_buf()
+        };
+        println!("{}", path.display());
+        if dry_run {
+            0
+        } else {
+            let mut exported = Vec::new();
+            for note in notes {
+                match vault
+                    .read(&note.uuid)
+                    .and_then(|content| export::JsonNote::new(note, &content, dates))
+                {
+                    Ok(json) => exported.push(json),
+                    Err(e) => {
+                        report_failure(note, e);
+                        failed += 1;
+                    }
+                }
+            }
+            fs::write(&path, export::to_json(&exported)?)?;
+            for json in &exported {
+                vault.record(
+                    Event::new(EventKind::Exported, Some(&json.uuid))
+                        .with_detail(format.to_string()),
+                );
+            }
+            exported.len()
+        }
+    } else {
+        if !dry_run {
+            permissions::create_private_dir(output)?;
+        }
+        let mut planned = HashSet::new();
+        let mut exported = 0;
+        for note in notes {
+            // Titles are free text, e.g. `../x`, so only a sanitized one is used
+            let name = filename::sanitize(&note.metadata.original_filename);
+            if dry_run {
+                let path = filename::unique_path(output, &name, format.extension(), &planned);
+                println!("{}", path.display());
+                planned.insert(path);
+                continue;
+            }
+            let written = vault
+                .read(&note.uuid)
+                .and_then(|content| export::render(format, note, &content, allow_html, dates))
+                .and_then(|rendered| {
+                    let (path, mut file) =
+                        filename::create_unique(output, &name, format.extension())?;
+                    file.write_all(&rendered)?;
+                    Ok(path)
+                });
+            match written {
+                Ok(path) => {
+                    println!("{}", path.display());
+                    vault.record(
+                        Event::new(EventKind::Exported, Some(&note.uuid))
+                            .with_detail(format.to_string()),
+                    );
+                    exported += 1;
+                }
+                Err(e) => {
+                    report_failure(note, e);
+                    failed += 1;
+                }
+            }
+        }
+        exported
+    };
+    if dry_run {
+        eprintln!("{}", t!("cli-export-dry-run", count = notes.len()));
+    } else {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-exported",
+                count = notes.len(),
+                exported = exported,
+                failed = failed
+            )
+        );
+    }
+    Ok(())
+}
+
+fn dedupe(
+    vault: &Vault,
+    dates: &DateFormat,
+    auto_keep: Option<KeepPolicy>,
+    dry_run: bool,
+    cache_hashes: bool,
+) -> Result<(), AppError> {
+    let scan = dedupe::scan(vault, cache_hashes)?;
+    for (i, group) in scan.groups.iter().enumerate() {
+        println!(
+            "{}",
+            t!(
+                "cli-dedupe-group",
+                group = i + 1,
+                count = group.notes.len(),
+                bytes = group.len
+            )
+        );
+        for (j, note) in group.notes.iter().enumerate() {
+            println!(
+                "{}",
+                t!(
+                    "cli-dedupe-note",
+                    n = j + 1,
+                    title = note.metadata.original_filename,
+                    uuid = note.uuid,
+                    created = dates.format(&note.metadata.created_at),
+                    updated = dates.format(&note.metadata.updated_at)
+                )
+            );
         }
-        info!("Encryption complete.");
+    }
+    for (note, error) in &scan.failed {
+        eprintln!(
+            "{}",
+            t!(
+                "cli-dedupe-left-out",
+                title = note.metadata.original_filename,
+                uuid = note.uuid,
+                error = error
+            )
+        );
+    }
+    if scan.groups.is_empty() {
+        println!("{}", t!("cli-no-duplicates"));
+        return Ok(());
+    }
+    if dry_run {
         return Ok(());
     }
 
-    info!("No unencrypted files found.");
+    let mut trashed = 0;
+    let mut skipped_read_only = Vec::new();
+    for (i, group) in scan.groups.iter().enumerate() {
+        let keeper = match auto_keep {
+            Some(policy) => group.keeper(policy),
+            None => {
+                print!(
+                    "{}",
+                    t!("cli-dedupe-keep", group = i + 1, count = group.notes.len())
+                );
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                if io::stdin().read_line(&mut answer)? == 0 {
+                    break;
+                }
+                match answer.trim() {
+                    "q" => break,
+                    "s" | "" => continue,
+                    answer => match answer
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|n| group.notes.get(n.checked_sub(1)?))
+                    {
+                        Some(note) => note,
+                        None => {
+                            eprintln!("{}", t!("cli-dedupe-no-note", answer = answer));
+                            continue;
+                        }
+                    },
+                }
+            }
+        };
+        let merged = dedupe::merge(vault, group, &keeper.uuid)?;
+        trashed += merged.trashed.len();
+        skipped_read_only.extend(merged.skipped_read_only);
+    }
+    println!("{}", t!("cli-dedupe-trashed", count = trashed));
+    print_skipped_read_only(&skipped_read_only);
+    Ok(())
+}
+
+/// Prints the snapshots of the notes directory, one
+/// `name<TAB>date<TAB>files<TAB>bytes` line each, oldest first.
+fn list_backups(backups: &Backups, dates: &DateFormat) -> Result<(), AppError> {
+    let snapshots = backups.list()?;
+    if snapshots.is_empty() {
+        eprintln!("{}", t!("cli-no-snapshots", dir = backups.dir().display()));
+    }
+    for snapshot in &snapshots {
+        let (files, bytes) = snapshot.size()?;
+        println!(
+            "{}\t{}\t{files}\t{bytes}",
+            snapshot.name,
+            dates.format(&snapshot.taken_at)
+        );
+    }
+    Ok(())
+}
+
+/// Prints how much space each category of [`gc`] takes and what `policy`
+/// deletes, and deletes it with `apply`, printing each file as it goes.
+fn gc(vault: &Vault, policy: &gc::Policy, apply: bool) -> Result<(), AppError> {
+    let plan = if apply {
+        vault.collect_garbage(policy, &mut |removal| {
+            println!(
+                "{}",
+                t!(
+                    "cli-gc-deleted-file",
+                    name = removal.name,
+                    bytes = removal.len,
+                    reason = removal.reason
+                )
+            );
+        })?
+    } else {
+        let plan = vault.gc_plan(policy)?;
+        for removal in &plan.removals {
+            println!(
+                "{}",
+                t!(
+                    "cli-gc-would-delete",
+                    name = removal.name,
+                    bytes = removal.len,
+                    reason = removal.reason
+                )
+            );
+        }
+        plan
+    };
+
+    let categories: Vec<_> = gc::Category::ALL
+        .into_iter()
+        .map(|category| (category, category.to_string()))
+        .collect();
+    let header = t!("column-category");
+    let width = categories
+        .iter()
+        .map(|(_, name)| name.chars().count())
+        .chain([header.chars().count()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "{header:width$}  {:>5}  {:>12}  {:>5}  {:>12}",
+        t!("column-files"),
+        t!("column-bytes"),
+        if apply {
+            t!("column-freed")
+        } else {
+            t!("column-frees")
+        },
+        t!("column-bytes")
+    );
+    let mut freed = gc::Usage::default();
+    for (category, name) in categories {
+        let usage = plan.usage.get(&category).copied().unwrap_or_default();
+        let deleted = plan.freed(category);
+        println!(
+            "{name:width$}  {:>5}  {:>12}  {:>5}  {:>12}",
+            usage.files, usage.bytes, deleted.files, deleted.bytes
+        );
+        freed.files += deleted.files;
+        freed.bytes += deleted.bytes;
+    }
+    println!(
+        "{}",
+        if apply {
+            t!("cli-gc-deleted", files = freed.files, bytes = freed.bytes)
+        } else if freed.files == 0 {
+            t!("cli-gc-nothing")
+        } else {
+            t!("cli-gc-dry-run", files = freed.files, bytes = freed.bytes)
+        }
+    );
+    Ok(())
+}
+
+/// Prints the figures of `ryokan stats`, the same the dashboard of the TUI
+/// shows.
+fn print_statistics(stats: &VaultStats) {
+    for (name, value) in stats.fields() {
+        println!("{name}: {value}");
+    }
+    println!("{}:", t!("stats-top-tags"));
+    let width = stats
+        .top_tags
+        .iter()
+        .map(|(tag, _)| tag.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (tag, count) in &stats.top_tags {
+        println!("  {tag:width$}  {count:>5}");
+    }
+    let saves: Vec<_> = stats
+        .saves_per_week
+        .iter()
+        .map(|saves| saves.to_string())
+        .collect();
+    println!(
+        "{}: {}",
+        t!("stats-saves", weeks = vaultstats::WEEKS),
+        saves.join(" ")
+    );
+}
+
+/// Asks for a new passphrase twice, until it's not empty and both match.
+fn ask_new_passphrase(
+    prompt: &mut TerminalPrompt,
+    message: &str,
+) -> Result<Zeroizing<String>, AppError> {
+    loop {
+        let passphrase = prompt.read_secret(message)?;
+        if passphrase.is_empty() {
+            prompt.notify(&t!("cli-passphrase-empty"));
+            continue;
+        }
+        if *prompt.read_secret(&t!("cli-passphrase-confirm"))? == *passphrase {
+            return Ok(passphrase);
+        }
+        prompt.notify(&t!("cli-passphrase-mismatch"));
+    }
+}
+
+fn run_bundle(vault: &Vault, action: &BundleAction) -> Result<(), AppError> {
+    let mut prompt = TerminalPrompt;
+    match action {
+        BundleAction::Export { output } => {
+            let passphrase = ask_new_passphrase(&mut prompt, &t!("cli-bundle-passphrase"))?;
+            let data = bundle::export(vault, &passphrase)?;
+            fs::write(output, data)?;
+            println!("{}", t!("cli-wrote", path = output.display()));
+        }
+        BundleAction::Import { path } => {
+            let data = fs::read(path)?;
+            let passphrase = prompt.read_secret(&t!("cli-bundle-open-passphrase"))?;
+            let notes = bundle::open(&data, &passphrase)?;
+            let report = bundle::import(vault, &notes)?;
+            println!(
+                "{}",
+                t!(
+                    "cli-bundle-imported",
+                    added = report.added.len(),
+                    replaced = report.replaced.len(),
+                    unchanged = report.unchanged.len()
+                )
+            );
+            for uuid in &report.replaced {
+                println!("{}", t!("cli-bundle-replaced", uuid = uuid));
+            }
+            for uuid in &report.kept_local {
+                println!("{}", t!("cli-bundle-kept-local", uuid = uuid));
+            }
+            print_skipped_read_only(&report.skipped_read_only);
+            for (uuid, error) in &report.failed {
+                eprintln!("{}", t!("cli-bundle-failed", uuid = uuid, error = error));
+            }
+            if !report.failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the notes `query` asks for in `format`.
+fn list_notes(
+    vault: &Vault,
+    query: &ListQuery,
+    format: ListFormat,
+    dates: &DateFormat,
+) -> Result<(), AppError> {
+    // Every note by title is what the index already has, without parsing metadata
+    if format == ListFormat::Tsv && *query == ListQuery::default() {
+        for (uuid, title) in vault.index()?.titles {
+            println!("{uuid}\t{}", picker::escape_field(&title));
+        }
+        return Ok(());
+    }
+
+    let notes = vault.query(query)?;
+    match format {
+        ListFormat::Tsv => {
+            for note in notes {
+                println!(
+                    "{}\t{}",
+                    note.uuid,
+                    picker::escape_field(&note.metadata.original_filename)
+                );
+            }
+        }
+        ListFormat::Plain => {
+            for note in notes {
+                println!("{}", picker::escape_field(&note.metadata.original_filename));
+            }
+        }
+        ListFormat::Json => {
+            let listed: Vec<_> = notes
+                .iter()
+                .map(|note| ListedNote {
+                    uuid: &note.uuid,
+                    metadata: &note.metadata,
+                })
+                .collect();
+            let json = serde_json::to_string_pretty(&listed)
+                .map_err(|e| AppError::Config(format!("Could not serialize notes: {e}")))?;
+            println!("{json}");
+        }
+        ListFormat::Table => {
+            let title_header = t!("column-title");
+            let title_width = notes
+                .iter()
+                .map(|note| text::width(&note.metadata.original_filename))
+                .max()
+                .unwrap_or(0)
+                .clamp(text::width(&title_header), LIST_TITLE_COLUMNS);
+            let rows = notes.iter().map(|note| {
+                let title = text::truncate(&note.metadata.original_filename, title_width);
+                [
+                    format!("{title}{}", " ".repeat(title_width - text::width(title))),
+                    dates.format(&note.metadata.updated_at),
+                    note.metadata
+                        .size
+                        .map_or_else(|| "-".to_string(), |size| size.to_string()),
+                    note.metadata.tags.join(", "),
+                ]
+            });
+            let header = [
+                format!("{title_header:title_width$}"),
+                t!("column-updated"),
+                t!("column-size"),
+                t!("column-tags"),
+            ];
+            let rows: Vec<_> = std::iter::once(header).chain(rows).collect();
+            let date_width = rows
+                .iter()
+                .map(|row| text::width(&row[1]))
+                .max()
+                .unwrap_or(0);
+            let size_width = rows
+                .iter()
+                .map(|row| row[2].chars().count())
+                .max()
+                .unwrap_or(0);
+            for [title, updated, size, tags] in rows {
+                let line = format!("{title}  {updated:date_width$}  {size:>size_width$}  {tags}");
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A note as printed by `list --json`.
+#[derive(Serialize)]
+struct ListedNote<'a> {
+    uuid: &'a str,
+    #[serde(flatten)]
+    metadata: &'a NoteMetadata,
+}
+
+fn show_settings(settings: &Settings) {
+    println!(
+        "config_file = {} ({})",
+        settings.config_file.value.display(),
+        settings.config_file.source
+    );
+    println!(
+        "notes_dir = {} ({})",
+        settings.notes_dir.value.display(),
+        settings.notes_dir.source
+    );
+    println!(
+        "editor = {} ({})",
+        settings.editor.value, settings.editor.source
+    );
+    if let Some(profile) = &settings.profile {
+        println!("profile = {} ({})", profile.value, profile.source);
+    }
+    let (count, description) = vault_summary(&settings.notes_dir.value);
+    println!("description = {description}");
+    println!("{}", t!("cli-note-count", count = count));
+    println!(
+        "read_only_mode = {} ({})",
+        settings.read_only_mode.value, settings.read_only_mode.source
+    );
+    println!(
+        "language = {} ({})",
+        settings.language.value, settings.language.source
+    );
+}
+
+/// The last known note count of the vault in `dir`, `?` if there's none, and
+/// its description, read without the PIN.
+fn vault_summary(dir: &Path) -> (String, String) {
+    match VaultInfo::read(dir) {
+        Ok(info) => {
+            let info = info.unwrap_or_default();
+            let count = info
+                .note_count
+                .map_or("?".to_string(), |count| count.to_string());
+            (count, info.description)
+        }
+        Err(e) => {
+            warn!("Could not read the description of {}: {e}", dir.display());
+            ("?".to_string(), String::new())
+        }
+    }
+}
+
+fn manage_profiles(config: &mut Config, action: &ProfileAction) -> Result<(), AppError> {
+    match action {
+        ProfileAction::List => {
+            for (name, profile) in &config.profiles {
+                let (count, description) = vault_summary(Path::new(&profile.notes_dir));
+                println!("{name}\t{}\t{count}\t{description}", profile.notes_dir);
+            }
+        }
+        ProfileAction::Add { name, notes_dir } => {
+            if config.profiles.contains_key(name) {
+                return Err(AppError::Config(format!(
+                    "Profile \"{name}\" already exists"
+                )));
+            }
+            let mut profile = Profile::new(name);
+            if let Some(notes_dir) = notes_dir {
+                profile.notes_dir = config::path_to_string(notes_dir)?;
+            }
+            config.profiles.insert(name.clone(), profile);
+            config.save()?;
+            println!("{}", t!("cli-profile-added", name = name));
+        }
+        ProfileAction::Remove { name } => {
+            let profile = config
+                .profiles
+                .remove(name)
+                .ok_or_else(|| AppError::Config(format!("Unknown profile \"{name}\"")))?;
+            config.save()?;
+            println!(
+                "{}",
+                t!("cli-profile-removed", name = name, dir = profile.notes_dir)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Offers to carry over the config of a cryptnote install, if there is one.
+fn migrate_legacy_config(without_asking: bool) -> Result<(), AppError> {
+    let Some(migration) = dirs::config_dir().and_then(|base| migrate::pending_migration(&base))
+    else {
+        return Ok(());
+    };
+
+    let prompt = t!(
+        "cli-migrate-legacy",
+        legacy = migration.legacy_config.display(),
+        target = migration.target_config.display()
+    );
+    if !without_asking && !TerminalPrompt.confirm(&prompt)? {
+        return Ok(());
+    }
+
+    for action in migrate::migrate(&migration)?.actions {
+        println!("{action}");
+    }
     Ok(())
 }
@@ -0,0 +1,101 @@
+//! A record of the encrypted content of every note, to notice files that changed
+//! without Ryokan, e.g. through bit-rot, long before the note is opened.
+//!
+//! The manifest maps each note to the BLAKE3 hash, size and modification time
+//! of its `.enc.txt` file as Ryokan last wrote or accepted it. It lives in
+//! [`MANIFEST_FILE_NAME`], encrypted with the PIN like the notes themselves, so
+//! it can't be rewritten to match a tampered file. The
+//! [`Vault`](crate::vault::Vault) updates it on every write and compares the
+//! files against it in [`Vault::check_integrity`](crate::vault::Vault::check_integrity).
+
+use crate::error::AppError;
+use crate::storage::Entry;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the encrypted manifest at the root of the vault.
+pub const MANIFEST_FILE_NAME: &str = ".ryokan-manifest.enc";
+
+/// The encrypted content of a note, as last recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Hex BLAKE3 hash of the whole file, header included.
+    pub hash: String,
+    /// Size of the file, in bytes.
+    pub len: u64,
+    /// Last modification time of the file, when the storage knows it.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl ManifestEntry {
+    /// Describes the file listed as `entry`, whose content is `data`.
+    pub fn new(data: &[u8], entry: &Entry) -> Self {
+        Self {
+            hash: blake3::hash(data).to_hex().to_string(),
+            len: data.len() as u64,
+            modified: entry.modified.map(DateTime::from),
+        }
+    }
+}
+
+/// Entries keyed by note UUID.
+pub type Manifest = BTreeMap<String, ManifestEntry>;
+
+/// How a file compares to what the manifest recorded for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Same content as recorded.
+    Unchanged,
+    /// Not recorded yet, e.g. a note that came with a sync.
+    Added,
+    /// Different content and a different modification time: another program,
+    /// such as a sync, replaced the file.
+    Updated,
+    /// Different content but the same modification time, which normal writes
+    /// don't produce: the file is likely corrupt.
+    Corrupt,
+}
+
+/// Compares a file, described as `current`, to what was `recorded` for it.
+pub fn compare(recorded: Option<&ManifestEntry>, current: &ManifestEntry) -> Change {
+    match recorded {
+        None => Change::Added,
+        Some(recorded) if recorded.hash == current.hash && recorded.len == current.len => {
+            Change::Unchanged
+        }
+        Some(recorded) if recorded.modified != current.modified => Change::Updated,
+        Some(_) => Change::Corrupt,
+    }
+}
+
+/// What [`Vault::check_integrity`](crate::vault::Vault::check_integrity) found.
+#[derive(Debug, Default)]
+pub struct IntegrityReport {
+    /// How many notes were checked.
+    pub checked: usize,
+    /// Notes that weren't in the manifest yet, which were added to it.
+    pub added: Vec<String>,
+    /// Notes replaced by another program, e.g. a sync, which were accepted.
+    pub updated: Vec<String>,
+    /// Notes in the manifest whose file is gone, which were dropped from it.
+    pub removed: Vec<String>,
+    /// Notes whose file changed without its modification time changing, or
+    /// can't be read at all. They stay flagged until they're saved again.
+    pub corrupt: Vec<String>,
+    /// Notes that failed to decrypt, when decrypting was asked for.
+    pub undecryptable: Vec<(String, AppError)>,
+    /// Why the manifest couldn't be used, in which case it was started over
+    /// from the notes as they are.
+    pub warning: Option<String>,
+}
+
+impl IntegrityReport {
+    /// Whether no note looks corrupt.
+    pub fn is_clean(&self) -> bool {
+        self.corrupt.is_empty() && self.undecryptable.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod manifest_test;
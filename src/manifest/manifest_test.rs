@@ -0,0 +1,44 @@
+#![cfg(test)]
+
+use super::*;
+use std::time::{Duration, SystemTime};
+
+fn entry(data: &[u8], modified: SystemTime) -> ManifestEntry {
+    ManifestEntry::new(
+        data,
+        &Entry {
+            name: "note.enc.txt".to_string(),
+            len: data.len() as u64,
+            modified: Some(modified),
+            created: None,
+        },
+    )
+}
+
+#[test]
+fn test_compare_tells_updates_from_corruption() {
+    let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let later = then + Duration::from_secs(60);
+    let recorded = entry(b"RYOKAN_ENCRYPTED abc", then);
+
+    assert_eq!(compare(None, &recorded), Change::Added);
+    assert_eq!(compare(Some(&recorded), &recorded), Change::Unchanged);
+    // Touched without changing the content
+    assert_eq!(
+        compare(Some(&recorded), &entry(b"RYOKAN_ENCRYPTED abc", later)),
+        Change::Unchanged
+    );
+    assert_eq!(
+        compare(Some(&recorded), &entry(b"RYOKAN_ENCRYPTED xyz", later)),
+        Change::Updated
+    );
+    // A flipped bit keeps both the size and the modification time
+    assert_eq!(
+        compare(Some(&recorded), &entry(b"RYOKAN_ENCRYPTED abd", then)),
+        Change::Corrupt
+    );
+    assert_eq!(
+        compare(Some(&recorded), &entry(b"RYOKAN_ENCRYPTED", then)),
+        Change::Corrupt
+    );
+}
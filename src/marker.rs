@@ -0,0 +1,82 @@
+//! The marker file telling a notes directory apart from an empty directory at
+//! the same path, e.g. the mount point of a drive that isn't mounted.
+//!
+//! Ryokan writes [`MARKER_FILE_NAME`] when it creates a vault, and refuses to
+//! start over in a directory that's missing or empty once a vault was set up.
+
+use crate::error::AppError;
+use crate::{file, permissions};
+use std::{fs, io, path::Path};
+
+/// Name of the marker file at the root of the vault.
+pub const MARKER_FILE_NAME: &str = ".ryokan-vault";
+
+/// What was found at the path of the notes directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirState {
+    Missing,
+    /// A directory without the marker, like vaults created by older versions.
+    Unmarked {
+        empty: bool,
+    },
+    Marked,
+}
+
+/// Looks at `dir` without changing anything.
+pub fn inspect(dir: &Path) -> Result<DirState, AppError> {
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(DirState::Missing),
+        Err(e) => return Err(AppError::Io(e)),
+    };
+    if dir.join(MARKER_FILE_NAME).is_file() {
+        Ok(DirState::Marked)
+    } else {
+        Ok(DirState::Unmarked {
+            empty: entries.next().is_none(),
+        })
+    }
+}
+
+/// Creates `dir` if needed, readable only by its owner, and writes the marker
+/// in it.
+pub fn mark(dir: &Path) -> Result<(), AppError> {
+    permissions::create_private_dir(dir)?;
+    fs::write(dir.join(MARKER_FILE_NAME), b"")?;
+    Ok(())
+}
+
+/// Whether `dir` holds any note file, telling a vault created by an older
+/// version apart from a directory that merely exists.
+fn has_notes(dir: &Path) -> Result<bool, AppError> {
+    for entry in fs::read_dir(dir)? {
+        if file::note_file_kind(&entry?.file_name().to_string_lossy()).is_some() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Gets `dir` ready to be opened as the notes directory, creating and marking it
+/// for a new vault. Returns whether a new vault was created, which also happens
+/// in an unmarked directory with files but no notes in it.
+///
+/// Fails with [`AppError::VaultNotFound`] rather than creating it when `must_exist`
+/// is set, or when a vault was `set_up` before (its PIN hash is in the config) but
+/// the directory is missing or empty and unmarked.
+pub fn prepare(dir: &Path, must_exist: bool, set_up: bool) -> Result<bool, AppError> {
+    match inspect(dir)? {
+        DirState::Marked => Ok(false),
+        DirState::Unmarked { empty: false } if set_up || has_notes(dir)? => Ok(false),
+        DirState::Unmarked { empty: true } if set_up => {
+            Err(AppError::VaultNotFound(dir.to_path_buf()))
+        }
+        DirState::Missing if must_exist || set_up => {
+            Err(AppError::VaultNotFound(dir.to_path_buf()))
+        }
+        DirState::Unmarked { .. } | DirState::Missing => mark(dir).map(|()| true),
+    }
+}
+
+#[cfg(test)]
+mod marker_test;
@@ -0,0 +1,66 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_prepare_creates_and_marks_a_new_vault() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = dir.path().join("notes");
+
+    assert!(prepare(&notes, false, false)?);
+    assert_eq!(inspect(&notes)?, DirState::Marked);
+    // Once marked, it's used as is even if empty
+    assert!(!prepare(&notes, true, true)?);
+    Ok(())
+}
+
+#[test]
+fn test_prepare_refuses_to_recreate_a_vault() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = dir.path().join("notes");
+
+    assert!(matches!(
+        prepare(&notes, true, false),
+        Err(AppError::VaultNotFound(path)) if path == notes
+    ));
+    assert!(matches!(
+        prepare(&notes, false, true),
+        Err(AppError::VaultNotFound(_))
+    ));
+    assert_eq!(inspect(&notes)?, DirState::Missing);
+
+    // An empty mount point
+    fs::create_dir(&notes)?;
+    assert!(matches!(
+        prepare(&notes, false, true),
+        Err(AppError::VaultNotFound(_))
+    ));
+    assert_eq!(inspect(&notes)?, DirState::Unmarked { empty: true });
+    Ok(())
+}
+
+#[test]
+fn test_prepare_keeps_unmarked_vaults() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join("note.enc.txt"), b"")?;
+
+    assert!(!prepare(dir.path(), true, true)?);
+    assert_eq!(inspect(dir.path())?, DirState::Unmarked { empty: false });
+    assert!(!prepare(dir.path(), false, false)?);
+    assert_eq!(inspect(dir.path())?, DirState::Unmarked { empty: false });
+
+    mark(dir.path())?;
+    assert_eq!(inspect(dir.path())?, DirState::Marked);
+    Ok(())
+}
+
+#[test]
+fn test_prepare_marks_a_directory_without_notes_as_a_new_vault() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    fs::write(dir.path().join("taxes.pdf"), b"")?;
+
+    assert!(prepare(dir.path(), false, false)?);
+    assert_eq!(inspect(dir.path())?, DirState::Marked);
+    Ok(())
+}
@@ -1,50 +1,370 @@
+//! Plaintext metadata stored next to every encrypted note.
+//!
+//! The format is versioned with `schema_version`. Fields added after the first
+//! version must have a serde default so older files still load, and keys this
+//! build doesn't know about are kept as they are, so a note touched by an older
+//! binary doesn't lose what a newer one recorded.
+
 use crate::error::AppError;
+use crate::stats;
+use crate::storage::Storage;
+use crate::text;
 use chrono::{DateTime, Utc};
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::{collections::HashSet, fmt};
+
+/// Version of the metadata format written by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Default of `max_title_length`, in characters.
+pub const DEFAULT_MAX_TITLE_LENGTH: usize = 256;
+/// How many tags a note keeps at most.
+pub const MAX_TAGS: usize = 64;
+/// How long a tag can be, in characters.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Color labels a note can have, in the order the TUI cycles through them.
+pub const COLOR_LABELS: [&str; 6] = ["red", "orange", "yellow", "green", "blue", "purple"];
+
+/// Checks that `name` is one of [`COLOR_LABELS`], ignoring case, or `none` for
+/// no label.
+///
+/// ```
+/// use ryokan::metadata::parse_color;
+///
+/// assert_eq!(parse_color("Green").ok(), Some(Some("green".to_string())));
+/// assert_eq!(parse_color("none").ok(), Some(None));
+/// assert!(parse_color("teal").is_err());
+/// ```
+pub fn parse_color(name: &str) -> Result<Option<String>, AppError> {
+    let name = name.trim().to_lowercase();
+    if name == "none" {
+        return Ok(None);
+    }
+    if COLOR_LABELS.contains(&name.as_str()) {
+        Ok(Some(name))
+    } else {
+        Err(AppError::Config(format!(
+            "Unknown color \"{name}\", expected one of {} or none",
+            COLOR_LABELS.join(", ")
+        )))
+    }
+}
+
+/// What kind of note a note is.
+///
+/// A type this build doesn't know, written by a newer one, loads as
+/// [`NoteType::Other`] and is saved back as it was.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum NoteType {
+    /// Text shown as it is.
+    #[default]
+    Plain,
+    /// Shown rendered in the preview.
+    Markdown,
+    /// A checklist: its items are counted in the preview, and it's listed in
+    /// the todos even once every item is checked.
+    Todo,
+    /// Listed in the journal view, whatever its title.
+    Journal,
+    /// A type of a newer version, shown as plain text.
+    Other(String),
+}
+
+impl NoteType {
+    /// The types a note can be given, in the order the TUI cycles through them.
+    pub const KNOWN: [NoteType; 4] = [
+        NoteType::Plain,
+        NoteType::Markdown,
+        NoteType::Todo,
+        NoteType::Journal,
+    ];
+
+    /// The name of the type, `other` for every unknown one.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoteType::Plain => "plain",
+            NoteType::Markdown => "markdown",
+            NoteType::Todo => "todo",
+            NoteType::Journal => "journal",
+            NoteType::Other(_) => "other",
+        }
+    }
+
+    /// Checks that `name` is the name of a type, ignoring case. `other` stands
+    /// for every unknown type.
+    ///
+    /// ```
+    /// use ryokan::metadata::NoteType;
+    ///
+    /// assert_eq!(NoteType::parse("Markdown").ok(), Some(NoteType::Markdown));
+    /// assert_eq!(NoteType::parse("other").map(|t| t.name()).ok(), Some("other"));
+    /// assert!(NoteType::parse("kanban").is_err());
+    /// ```
+    pub fn parse(name: &str) -> Result<Self, AppError> {
+        let name = name.trim().to_lowercase();
+        if name == "other" {
+            return Ok(NoteType::Other(name));
+        }
+        Self::KNOWN
+            .into_iter()
+            .find(|note_type| note_type.name() == name)
+            .ok_or_else(|| {
+                AppError::Config(format!(
+                    "Unknown note type \"{name}\", expected one of {} or other",
+                    Self::KNOWN.map(|note_type| note_type.name()).join(", ")
+                ))
+            })
+    }
+
+    /// The type after this one in [`NoteType::KNOWN`], going back to plain
+    /// after the last or from an unknown one.
+    pub fn next(&self) -> Self {
+        let next = Self::KNOWN
+            .iter()
+            .position(|note_type| note_type == self)
+            .map_or(0, |i| i + 1);
+        Self::KNOWN.get(next).cloned().unwrap_or_default()
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == NoteType::Plain
+    }
+}
+
+impl From<String> for NoteType {
+    fn from(name: String) -> Self {
+        Self::KNOWN
+            .into_iter()
+            .find(|note_type| note_type.name() == name)
+            .unwrap_or(NoteType::Other(name))
+    }
+}
+
+impl From<NoteType> for String {
+    fn from(note_type: NoteType) -> Self {
+        match note_type {
+            NoteType::Other(name) => name,
+            known => known.name().to_string(),
+        }
+    }
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+impl fmt::Display for NoteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct NoteMetadata {
+    /// Format version; files written before versioning have none and load as 0.
+    #[serde(default)]
+    pub schema_version: u32,
     pub original_filename: String,
+    /// The whole title, when it was longer than `max_title_length` and
+    /// `original_filename` only has its start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_title: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
     pub tags: Vec<String>,
+    /// Other titles the note can be found by.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Protects the note against accidental edits.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Length of the plaintext in bytes, unless recording it is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Number of words in the plaintext, see [`stats::word_count`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<u64>,
+    /// The local day the note is due, as the UTC instant of its midnight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    /// Color label for grouping notes at a glance, one of [`COLOR_LABELS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// For a conflict copy made while syncing, the UUID of the note it conflicts with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_of: Option<String>,
+    /// Quick-access slot, from 1 to 9: the digit key that jumps to the note in
+    /// the TUI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u8>,
+    /// Place of the note among the pinned notes, listed first in the TUI in
+    /// this order; `None` if it isn't pinned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_order: Option<u32>,
+    /// When the note was moved to the trash, while it's there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trashed_at: Option<DateTime<Utc>>,
+    /// What kind of note it is, which decides how the TUI shows it.
+    #[serde(default, skip_serializing_if = "NoteType::is_plain")]
+    pub note_type: NoteType,
+    /// An excerpt of the content, sealed, see [`snippet`](crate::snippet).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Keys written by a newer version, preserved on save.
+    #[serde(flatten)]
+    pub unknown: toml::Table,
 }
 
 impl NoteMetadata {
+    /// Creates metadata for a note created right now.
     pub fn new<S: Into<String>>(original_filename: S) -> Self {
         let now = Utc::now();
+        Self::with_timestamps(original_filename, now, now)
+    }
+
+    /// Creates metadata for a note that existed before, e.g. an imported file.
+    pub fn with_timestamps<S: Into<String>>(
+        original_filename: S,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             original_filename: original_filename.into(),
-            created_at: now,
-            updated_at: now,
+            long_title: None,
+            created_at,
+            updated_at,
             tags: Vec::new(),
+            aliases: Vec::new(),
+            read_only: false,
+            size: None,
+            word_count: None,
+            due_at: None,
+            color: None,
+            conflict_of: None,
+            slot: None,
+            pin_order: None,
+            trashed_at: None,
+            note_type: NoteType::Plain,
+            snippet: None,
+            unknown: toml::Table::new(),
         }
     }
 
-    pub fn save(&self, path: &Path) -> Result<(), AppError> {
-        let toml_string = toml::to_string(&self).map_err(AppError::TomlSerialize)?;
-
-        let parent = path
-            .parent()
-            .ok_or_else(|| AppError::Config("Invalid metadata path".to_string()))?;
+    /// Records the size and word count of `content`, or clears them for `None`.
+    pub fn update_content_stats(&mut self, content: Option<&[u8]>) {
+        self.size = content.map(|content| content.len() as u64);
+        self.word_count =
+            content.map(|content| stats::word_count(&String::from_utf8_lossy(content)) as u64);
+    }
 
-        let mut temp_file = tempfile::NamedTempFile::new_in(parent).map_err(AppError::Io)?;
+    /// Cuts a title longer than `max_title_length` characters, keeping it whole
+    /// in `long_title`, drops the tags past [`MAX_TAGS`] and cuts those longer
+    /// than [`MAX_TAG_LENGTH`], so a title or tags imported from anywhere can't
+    /// blow up the list or the metadata file. Returns whether anything was cut.
+    pub fn limit(&mut self, max_title_length: usize) -> bool {
+        let mut cut = false;
+        if let Some(short) = shorten(&self.original_filename, max_title_length) {
+            self.long_title = Some(std::mem::replace(&mut self.original_filename, short));
+            cut = true;
+        } else if self
+            .long_title
+            .as_deref()
+            .and_then(|long| shorten(long, max_title_length))
+            .is_none_or(|short| short != self.original_filename)
+        {
+            // Renamed since
+            self.long_title = None;
+        }
 
-        temp_file
-            .write_all(toml_string.as_bytes())
-            .map_err(AppError::Io)?;
-        temp_file.persist(path).map_err(|e| AppError::Io(e.error))?;
+        if self.tags.len() > MAX_TAGS {
+            self.tags.truncate(MAX_TAGS);
+            cut = true;
+        }
+        for tag in &mut self.tags {
+            let short = text::first_graphemes(tag, MAX_TAG_LENGTH);
+            if short.len() < tag.len() {
+                *tag = short.to_string();
+                cut = true;
+            }
+        }
+        if cut {
+            let mut seen = HashSet::new();
+            self.tags.retain(|tag| seen.insert(tag.clone()));
+        }
+        cut
+    }
 
-        Ok(())
+    /// Atomically writes the metadata as TOML to `name`.
+    pub fn save(&self, storage: &dyn Storage, name: &str) -> Result<(), AppError> {
+        toml::to_string(&self)
+            .map_err(AppError::TomlSerialize)
+            .and_then(|toml_string| storage.write(name, toml_string.as_bytes()))
+            .map_err(|e| Self::error(storage, name, e))
     }
 
-    pub fn load(path: &Path) -> Result<Self, AppError> {
-        let toml_string = fs::read_to_string(path).map_err(AppError::Io)?;
-        let metadata: NoteMetadata =
-            toml::from_str(&toml_string).map_err(AppError::TomlDeserialize)?;
+    /// Reads TOML metadata from `name`, upgrading it from older schema versions.
+    ///
+    /// The upgrade is only written back the next time the metadata is saved.
+    pub fn load(storage: &dyn Storage, name: &str) -> Result<Self, AppError> {
+        let mut metadata = storage
+            .read(name)
+            .and_then(|bytes| {
+                String::from_utf8(bytes).map_err(|e| {
+                    AppError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                })
+            })
+            .and_then(|toml_string| {
+                toml::from_str::<NoteMetadata>(&toml_string).map_err(AppError::TomlDeserialize)
+            })
+            .map_err(|e| Self::error(storage, name, e))?;
+
+        if metadata.schema_version > SCHEMA_VERSION {
+            warn!(
+                "{name} was written by a newer version of Ryokan (schema {}), fields it added are kept as is",
+                metadata.schema_version
+            );
+        }
+        metadata.upgrade();
         Ok(metadata)
     }
+
+    /// Brings metadata of an older schema version up to [`SCHEMA_VERSION`].
+    fn upgrade(&mut self) {
+        // Version 0 (unversioned) only lacks fields that all have a default, so
+        // there is nothing to convert yet. Later versions add their steps here.
+        self.schema_version = self.schema_version.max(SCHEMA_VERSION);
+    }
+
+    /// Attaches the location of the metadata file to `source`.
+    ///
+    /// Read-only mode isn't about this file in particular, so it's passed through.
+    fn error(storage: &dyn Storage, name: &str, source: AppError) -> AppError {
+        if matches!(source, AppError::ReadOnlyMode) {
+            return source;
+        }
+        let path = match storage.root() {
+            Some(root) => name
+                .split('/')
+                .fold(root.to_path_buf(), |path, part| path.join(part))
+                .display()
+                .to_string(),
+            None => name.to_string(),
+        };
+        AppError::Metadata {
+            path,
+            source: Box::new(source),
+        }
+    }
+}
+
+/// `title` cut to `max_length` characters with an ellipsis, if it's longer.
+fn shorten(title: &str, max_length: usize) -> Option<String> {
+    if text::first_graphemes(title, max_length).len() == title.len() {
+        return None;
+    }
+    Some(format!(
+        "{}…",
+        text::first_graphemes(title, max_length.saturating_sub(1)).trim_end()
+    ))
 }
@@ -0,0 +1,189 @@
+//! One-time migration from cryptnote, the name Ryokan had before.
+//!
+//! Old installs keep their config in `<config dir>/cryptnote/cryptnote.toml`. When
+//! no Ryokan config exists yet, it's copied over and the notes directory
+//! re-referenced in place. Its fields already have the names Ryokan uses.
+//!
+//! Some installs also have a `note_database.json` next to it, mapping note UUIDs
+//! to their titles. Notes without a metadata file get one from it, and it's
+//! renamed to `note_database.json.bak` so it isn't imported twice. Nothing else
+//! of the legacy install is modified or deleted.
+
+use crate::config::{self, Config};
+use crate::error::AppError;
+use crate::file;
+use crate::metadata::NoteMetadata;
+use crate::storage::{FsStorage, Storage};
+use crate::t;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the note database of cryptnote, next to its config.
+pub const LEGACY_DATABASE_FILE_NAME: &str = "note_database.json";
+
+/// A note as listed in the legacy note database: its title alone, or its
+/// metadata.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LegacyNote {
+    Title(String),
+    Metadata {
+        original_filename: String,
+        created_at: Option<DateTime<Utc>>,
+        updated_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+/// A legacy config waiting to be migrated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyMigration {
+    pub legacy_config: PathBuf,
+    pub target_config: PathBuf,
+}
+
+/// What [`migrate`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Every step taken, in order, meant to be shown to the user.
+    pub actions: Vec<String>,
+}
+
+/// Path of the cryptnote config below the platform config directory `config_base`.
+pub fn legacy_config_path(config_base: &Path) -> PathBuf {
+    config_base.join("cryptnote").join("cryptnote.toml")
+}
+
+/// Path of the Ryokan config below the platform config directory `config_base`.
+pub fn config_path(config_base: &Path) -> PathBuf {
+    config_base.join("ryokan").join("ryokan.toml")
+}
+
+/// Returns the migration to perform, if a legacy config exists and no Ryokan one does.
+pub fn pending_migration(config_base: &Path) -> Option<LegacyMigration> {
+    let legacy_config = legacy_config_path(config_base);
+    let target_config = config_path(config_base);
+    (legacy_config.is_file() && !target_config.exists()).then_some(LegacyMigration {
+        legacy_config,
+        target_config,
+    })
+}
+
+/// Copies the legacy config into the Ryokan location.
+///
+/// Running it again once the Ryokan config exists does nothing.
+pub fn migrate(migration: &LegacyMigration) -> Result<MigrationReport, AppError> {
+    let mut actions = Vec::new();
+    if migration.target_config.exists() {
+        return Ok(MigrationReport { actions });
+    }
+
+    let legacy_str = fs::read_to_string(&migration.legacy_config)?;
+
+    let mut config: Config = toml::from_str(&legacy_str)?;
+    if !config.pin_hash.is_empty() {
+        actions.push(t!("migrate-pin-hash"));
+    }
+
+    // Relative notes directories were resolved against the legacy config directory,
+    // so point at the same place instead of moving any notes
+    if !Path::new(&config.notes_dir).is_absolute()
+        && let Some(legacy_dir) = migration.legacy_config.parent()
+    {
+        let notes_dir = legacy_dir.join(&config.notes_dir);
+        config.notes_dir = config::path_to_string(&notes_dir)?;
+        actions.push(t!("migrate-notes-dir", dir = notes_dir.display()));
+    }
+
+    config.config_path = migration.target_config.clone();
+    config.save()?;
+    actions.push(t!(
+        "migrate-copied",
+        from = migration.legacy_config.display(),
+        to = migration.target_config.display()
+    ));
+
+    if let Some(legacy_dir) = migration.legacy_config.parent() {
+        let database = legacy_dir.join(LEGACY_DATABASE_FILE_NAME);
+        if database.is_file() {
+            import_database(&database, config.notes_dir_path(), &mut actions)?;
+        }
+    }
+
+    for action in &actions {
+        info!("Legacy migration: {action}");
+    }
+    Ok(MigrationReport { actions })
+}
+
+/// Writes metadata from the legacy note database at `database` for the notes
+/// in `notes_dir` that have none, then renames the database to `.bak`.
+fn import_database(
+    database: &Path,
+    notes_dir: &Path,
+    actions: &mut Vec<String>,
+) -> Result<(), AppError> {
+    let notes: BTreeMap<String, LegacyNote> = serde_json::from_str(&fs::read_to_string(database)?)
+        .map_err(|e| AppError::Config(format!("{}: {e}", database.display())))?;
+    let storage = FsStorage::new(notes_dir);
+    let mut imported = 0;
+    for (uuid, note) in notes {
+        if uuid::Uuid::parse_str(&uuid).is_err() {
+            actions.push(t!("migrate-not-a-uuid", uuid = uuid));
+            continue;
+        }
+        let (content_name, metadata_name) = file::note_file_names(&uuid);
+        if !storage.exists(&content_name) {
+            actions.push(t!("migrate-no-file", uuid = uuid));
+            continue;
+        }
+        if storage.exists(&metadata_name) {
+            continue;
+        }
+        let metadata = match note {
+            LegacyNote::Title(title) => NoteMetadata::new(title),
+            LegacyNote::Metadata {
+                original_filename,
+                created_at,
+                updated_at,
+                tags,
+            } => {
+                let created_at = created_at.unwrap_or_else(Utc::now);
+                let mut metadata = NoteMetadata::with_timestamps(
+                    original_filename,
+                    created_at,
+                    updated_at.unwrap_or(created_at),
+                );
+                metadata.tags = tags;
+                metadata
+            }
+        };
+        metadata.save(&storage, &metadata_name)?;
+        imported += 1;
+    }
+    actions.push(t!(
+        "migrate-imported",
+        count = imported,
+        database = database.display()
+    ));
+
+    let mut backup = database.as_os_str().to_owned();
+    backup.push(".bak");
+    fs::rename(database, &backup)?;
+    actions.push(t!(
+        "migrate-renamed",
+        from = database.display(),
+        to = Path::new(&backup).display()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod migrate_test;
@@ -0,0 +1,124 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+fn write_legacy(base: &Path, content: &str) -> Result<(), AppError> {
+    let legacy = legacy_config_path(base);
+    fs::create_dir_all(legacy.parent().unwrap_or(base))?;
+    fs::write(legacy, content)?;
+    Ok(())
+}
+
+#[test]
+fn test_no_migration_without_legacy_config() -> Result<(), AppError> {
+    let base = tempdir()?;
+    assert_eq!(pending_migration(base.path()), None);
+    Ok(())
+}
+
+#[test]
+fn test_migrate_copies_the_config() -> Result<(), AppError> {
+    let base = tempdir()?;
+    write_legacy(
+        base.path(),
+        "pin_hash = \"$argon2id$fake\"\nnotes_dir = \"my-notes\"\n",
+    )?;
+    let legacy_before = fs::read(legacy_config_path(base.path()))?;
+
+    let migration = pending_migration(base.path()).ok_or(AppError::Config("none".into()))?;
+    let report = migrate(&migration)?;
+    assert!(!report.actions.is_empty());
+
+    let config = Config::new(Some(&config_path(base.path())))?;
+    assert_eq!(config.pin_hash(), "$argon2id$fake");
+    assert_eq!(
+        config.notes_dir_path(),
+        base.path().join("cryptnote").join("my-notes")
+    );
+
+    // The legacy files are untouched and a second run does nothing
+    assert_eq!(fs::read(legacy_config_path(base.path()))?, legacy_before);
+    assert_eq!(pending_migration(base.path()), None);
+    assert!(migrate(&migration)?.actions.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate_keeps_absolute_notes_dir() -> Result<(), AppError> {
+    let base = tempdir()?;
+    let notes = base.path().join("elsewhere");
+    write_legacy(
+        base.path(),
+        &format!(
+            "pin_hash = \"\"\nnotes_dir = {:?}\n",
+            notes.to_string_lossy()
+        ),
+    )?;
+
+    let migration = pending_migration(base.path()).ok_or(AppError::Config("none".into()))?;
+    migrate(&migration)?;
+
+    let config = Config::new(Some(&config_path(base.path())))?;
+    assert_eq!(config.notes_dir_path(), notes);
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate_imports_the_note_database() -> Result<(), AppError> {
+    let base = tempdir()?;
+    write_legacy(base.path(), "pin_hash = \"\"\nnotes_dir = \"notes\"\n")?;
+    let legacy_dir = base.path().join("cryptnote");
+    let notes_dir = legacy_dir.join("notes");
+    fs::create_dir_all(&notes_dir)?;
+    let [titled, detailed, described, missing] = [(); 4].map(|_| file::generate_uuid());
+    for uuid in [&titled, &detailed, &described] {
+        fs::write(
+            notes_dir.join(file::note_file_names(uuid).0),
+            b"RYOKAN_ENCRYPTED",
+        )?;
+    }
+    let kept = NoteMetadata::new("Kept");
+    kept.save(
+        &FsStorage::new(&notes_dir),
+        &file::note_file_names(&described).1,
+    )?;
+    let database = legacy_dir.join(LEGACY_DATABASE_FILE_NAME);
+    fs::write(
+        &database,
+        format!(
+            r#"{{
+                "{titled}": "Groceries",
+                "{detailed}": {{
+                    "original_filename": "Trip",
+                    "created_at": "2020-01-02T03:04:05Z",
+                    "tags": ["travel"]
+                }},
+                "{described}": "Not this one",
+                "{missing}": "Gone"
+            }}"#
+        ),
+    )?;
+
+    let migration = pending_migration(base.path()).ok_or(AppError::Config("none".into()))?;
+    migrate(&migration)?;
+
+    let storage = FsStorage::new(&notes_dir);
+    let load = |uuid: &str| NoteMetadata::load(&storage, &file::note_file_names(uuid).1);
+    assert_eq!(load(&titled)?.original_filename, "Groceries");
+    let trip = load(&detailed)?;
+    assert_eq!(trip.original_filename, "Trip");
+    assert_eq!(trip.tags, ["travel"]);
+    assert_eq!(trip.created_at.to_rfc3339(), "2020-01-02T03:04:05+00:00");
+    assert_eq!(trip.updated_at, trip.created_at);
+    assert_eq!(load(&described)?.original_filename, "Kept");
+    assert!(!storage.exists(&file::note_file_names(&missing).1));
+
+    // Renamed, so it's kept but not imported again
+    assert!(!database.exists());
+    assert!(legacy_dir.join("note_database.json.bak").is_file());
+
+    Ok(())
+}
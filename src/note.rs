@@ -1,42 +1,189 @@
+//! Encryption and decryption of note contents.
+
 use crate::error::AppError;
-use crate::pin;
+use crate::pin::{self, ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM};
 use aes_gcm::{
     Aes256Gcm,
     aead::{Aead, KeyInit, Nonce},
 };
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use zeroize::Zeroizing;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const KDF_PARAMS: [u32; 3] = [ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM];
 
+/// Encrypts `content` with a key derived from `pin`.
+///
+/// The output is the random salt, followed by the nonce and the ciphertext.
+///
+/// ```
+/// use ryokan::note::{decrypt_note_content, encrypt_note_content};
+///
+/// let encrypted = encrypt_note_content(b"hello", "123456").unwrap();
+/// assert_eq!(decrypt_note_content(&encrypted, "123456").unwrap(), b"hello");
+/// ```
 pub fn encrypt_note_content(content: &[u8], pin: &str) -> Result<Vec<u8>, AppError> {
-    let mut salt = [0u8; 16];
-    StdRng::from_rng(&mut rand::rng()).fill_bytes(&mut salt);
+    NoteKey::new(pin)?.encrypt(content)
+}
+
+/// Decrypts data produced by [`encrypt_note_content`].
+pub fn decrypt_note_content(encrypted_data: &[u8], pin: &str) -> Result<Vec<u8>, AppError> {
+    NoteKey::of(encrypted_data, pin)?.decrypt(encrypted_data)
+}
+
+/// A key derived from the PIN, with its salt, to encrypt a file that's rewritten
+/// often, like the integrity manifest, without running Argon2 every time.
+///
+/// It writes the format of [`encrypt_note_content`], keeping the salt and
+/// drawing a new nonce each time.
+///
+/// ```
+/// use ryokan::note::{NoteKey, decrypt_note_content};
+///
+/// let key = NoteKey::new("123456").unwrap();
+/// let encrypted = key.encrypt(b"hello").unwrap();
+/// assert!(key.matches(&encrypted));
+/// assert_eq!(key.decrypt(&encrypted).unwrap(), b"hello");
+/// assert_eq!(decrypt_note_content(&encrypted, "123456").unwrap(), b"hello");
+/// ```
+#[derive(Clone)]
+pub struct NoteKey {
+    salt: [u8; SALT_LENGTH],
+    cipher: Aes256Gcm,
+}
+
+impl NoteKey {
+    /// Derives a key with a new random salt.
+    pub fn new(pin: &str) -> Result<Self, AppError> {
+        Self::with_params(pin, KDF_PARAMS)
+    }
 
-    let key = pin::derive_key_from_pin(pin, &salt)?;
-    let cipher = Aes256Gcm::new(&key);
+    /// Like [`NoteKey::new`], with other Argon2 parameters, see
+    /// [`pin::derive_key_with_params`].
+    pub fn with_params(pin: &str, params: [u32; 3]) -> Result<Self, AppError> {
+        let mut salt = [0u8; SALT_LENGTH];
+        StdRng::from_rng(&mut rand::rng()).fill_bytes(&mut salt);
+        Self::with_salt(salt, pin, params)
+    }
 
-    let mut nonce_bytes = [0u8; 12];
-    rand::rng().fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::<Aes256Gcm>::from_slice(&nonce_bytes);
+    /// Derives the key `encrypted_data` was encrypted with, from its salt.
+    pub fn of(encrypted_data: &[u8], pin: &str) -> Result<Self, AppError> {
+        Self::of_with_params(encrypted_data, pin, KDF_PARAMS)
+    }
 
-    let ciphertext = cipher
-        .encrypt(nonce, content)
-        .map_err(|e| AppError::Encryption(format!("Encryption failed: {e}")))?;
+    /// Like [`NoteKey::of`], for data encrypted with a key from
+    /// [`NoteKey::with_params`].
+    pub fn of_with_params(
+        encrypted_data: &[u8],
+        pin: &str,
+        params: [u32; 3],
+    ) -> Result<Self, AppError> {
+        if encrypted_data.len() < SALT_LENGTH + NONCE_LENGTH {
+            return Err(AppError::Decryption(
+                "Encrypted data is too short to contain a salt and nonce.".to_string(),
+            ));
+        }
+        let mut salt = [0u8; SALT_LENGTH];
+        salt.copy_from_slice(&encrypted_data[..SALT_LENGTH]);
+        Self::with_salt(salt, pin, params)
+    }
 
-    Ok([salt.as_slice(), nonce.as_slice(), &ciphertext].concat())
+    fn with_salt(salt: [u8; SALT_LENGTH], pin: &str, params: [u32; 3]) -> Result<Self, AppError> {
+        let key = pin::derive_key_with_params(pin, &salt, params)?;
+        Ok(Self {
+            salt,
+            cipher: Aes256Gcm::new(&key),
+        })
+    }
+
+    /// Whether `encrypted_data` has the salt of this key, i.e. this key can
+    /// decrypt it without deriving another one.
+    pub fn matches(&self, encrypted_data: &[u8]) -> bool {
+        encrypted_data.starts_with(&self.salt)
+    }
+
+    /// Encrypts `content` with a new nonce.
+    pub fn encrypt(&self, content: &[u8]) -> Result<Vec<u8>, AppError> {
+        let mut nonce_bytes = [0u8; NONCE_LENGTH];
+        rand::rng().fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::<Aes256Gcm>::from_slice(&nonce_bytes);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, content)
+            .map_err(|e| AppError::Encryption(format!("Encryption failed: {e}")))?;
+
+        Ok([self.salt.as_slice(), nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypts data with the salt of this key, see [`NoteKey::matches`].
+    pub fn decrypt(&self, encrypted_data: &[u8]) -> Result<Vec<u8>, AppError> {
+        if encrypted_data.len() < SALT_LENGTH + NONCE_LENGTH {
+            return Err(AppError::Decryption(
+                "Encrypted data is too short to contain a salt and nonce.".to_string(),
+            ));
+        }
+        if !self.matches(encrypted_data) {
+            return Err(AppError::Decryption(
+                "Encrypted data has a different salt than the key.".to_string(),
+            ));
+        }
+        let (nonce_slice, ciphertext) = encrypted_data[SALT_LENGTH..].split_at(NONCE_LENGTH);
+        let nonce = aes_gcm::Nonce::from_slice(nonce_slice);
+
+        self.cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|e| AppError::Decryption(format!("Decryption failed: {e}")))
+    }
 }
 
-pub fn decrypt_note_content(encrypted_data: &[u8], pin: &str) -> Result<Vec<u8>, AppError> {
-    let (salt, remainder) = encrypted_data.split_at(16);
-    let (nonce_slice, ciphertext) = remainder.split_at(12);
+/// Encrypts and decrypts a small sample in memory, to catch a broken random
+/// number generator or AES backend before any note is written with them.
+pub fn self_test() -> Result<(), AppError> {
+    self_test_with(&mut |bytes| rand::rng().fill_bytes(bytes))
+}
 
-    let key = pin::derive_key_from_pin(pin, salt)?;
-    let cipher = Aes256Gcm::new(&key);
+/// [`self_test`] with random bytes drawn from `fill`.
+pub fn self_test_with(fill: &mut dyn FnMut(&mut [u8])) -> Result<(), AppError> {
+    const SAMPLE: &[u8] = b"Ryokan self-test sample";
+    let failed = |reason: &str| AppError::Encryption(format!("Self-test failed: {reason}"));
 
-    let nonce = aes_gcm::Nonce::from_slice(nonce_slice);
+    let mut key = Zeroizing::new([0u8; 32]);
+    fill(key.as_mut_slice());
+    let mut nonces = [[0u8; NONCE_LENGTH]; 2];
+    for nonce in &mut nonces {
+        fill(nonce);
+    }
+    if nonces[0] == nonces[1] || key.iter().all(|byte| *byte == 0) {
+        return Err(failed(
+            "the random number generator doesn't give random bytes",
+        ));
+    }
 
-    let decrypted = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|e| AppError::Decryption(format!("Decryption failed: {e}")))?;
+    let cipher = Aes256Gcm::new_from_slice(key.as_slice()).map_err(|e| failed(&e.to_string()))?;
+    let seal = |nonce: &[u8; NONCE_LENGTH]| {
+        cipher
+            .encrypt(Nonce::<Aes256Gcm>::from_slice(nonce), SAMPLE)
+            .map_err(|e| failed(&e.to_string()))
+    };
+    let sealed = seal(&nonces[0])?;
+    if sealed == seal(&nonces[1])? || sealed.windows(SAMPLE.len()).any(|w| w == SAMPLE) {
+        return Err(failed("encryption leaves the sample readable"));
+    }
 
-    Ok(decrypted)
+    let open = |data: &[u8]| cipher.decrypt(Nonce::<Aes256Gcm>::from_slice(&nonces[0]), data);
+    if open(&sealed).ok().as_deref() != Some(SAMPLE) {
+        return Err(failed("decryption doesn't give the sample back"));
+    }
+    let mut tampered = sealed;
+    tampered[0] ^= 1;
+    if open(&tampered).is_ok() {
+        return Err(failed("a damaged sample still decrypts"));
+    }
+    Ok(())
 }
+
+#[cfg(test)]
+mod note_test;
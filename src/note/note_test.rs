@@ -0,0 +1,25 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_self_test_passes() -> Result<(), AppError> {
+    self_test()
+}
+
+#[test]
+fn test_self_test_catches_a_broken_rng() {
+    let mut zeros = |bytes: &mut [u8]| bytes.fill(0);
+    assert!(matches!(
+        self_test_with(&mut zeros),
+        Err(AppError::Encryption(_))
+    ));
+
+    // Random once, then stuck on the same bytes
+    let mut calls = 0u8;
+    let mut stuck = |bytes: &mut [u8]| {
+        calls += 1;
+        bytes.fill(if calls == 1 { 0xa5 } else { 7 });
+    };
+    assert!(self_test_with(&mut stuck).is_err());
+}
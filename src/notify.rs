@@ -0,0 +1,57 @@
+//! Desktop notifications, through the notifier of the platform: `osascript` on
+//! macOS and `notify-send` (libnotify) elsewhere. Windows has none that runs
+//! without a script, so nothing is sent there.
+//!
+//! Like links, the text is handed over as arguments, never through a shell, and
+//! `osascript` gets it as arguments of a fixed script rather than inside it.
+
+use crate::error::AppError;
+use std::process::{Command, Stdio};
+
+/// The program that shows a notification.
+pub const NOTIFIER: &str = if cfg!(target_os = "macos") {
+    "osascript"
+} else {
+    "notify-send"
+};
+
+/// The command showing a notification titled `summary` saying `body`, or
+/// `None` on Windows.
+pub fn command(summary: &str, body: &str) -> Option<Command> {
+    if cfg!(windows) {
+        return None;
+    }
+    let mut command = Command::new(NOTIFIER);
+    if cfg!(target_os = "macos") {
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+        ]);
+    } else {
+        command.arg("--app-name=Ryokan").arg("--");
+    }
+    command.args([summary, body]);
+    Some(command)
+}
+
+/// Shows a notification titled `summary` saying `body`, without waiting for
+/// the notifier to exit.
+pub fn send(summary: &str, body: &str) -> Result<(), AppError> {
+    let mut command = command(summary, body).ok_or_else(|| {
+        AppError::Config("Desktop notifications aren't supported on this platform".to_string())
+    })?;
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod notify_test;
@@ -0,0 +1,27 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_the_text_is_passed_as_arguments() {
+    let Some(command) = command("Ryokan", "Due: \"Taxes\"; rm -rf ~") else {
+        // There's no notifier on Windows
+        return;
+    };
+    let args: Vec<_> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    assert_eq!(command.get_program(), NOTIFIER);
+    assert_eq!(
+        args[args.len() - 2..],
+        ["Ryokan", "Due: \"Taxes\"; rm -rf ~"]
+    );
+    // Nothing of the text is in the script osascript runs
+    assert!(
+        args[..args.len() - 2]
+            .iter()
+            .all(|arg| !arg.contains("Taxes"))
+    );
+}
@@ -0,0 +1,610 @@
+//! Reading a decrypted note screen by screen, through the user's pager or the
+//! built-in [`Viewer`], and following the links in it with a [`LinkPicker`].
+
+use crate::{editor, theme::Theme, tui::TerminalGuard};
+use crossterm::{
+    clipboard::CopyToClipboard,
+    event::{self, Event, KeyCode},
+    execute,
+};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Clear, ListItem, ListState, Paragraph, Wrap},
+};
+use ryokan::{
+    error::AppError,
+    links,
+    stats::{self, NoteStats},
+    t, text,
+};
+use std::{
+    borrow::Cow,
+    env,
+    io::{self, Write},
+    ops::Range,
+    process::{Command, Stdio},
+};
+
+/// Pipes `content` into the user's pager (`PAGER`, or `less`), never through a file.
+///
+/// less is kept from writing a history file and from running commands or input
+/// preprocessors. Returns `false` if no pager could be started.
+pub fn run_external(content: &[u8]) -> Result<bool, AppError> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let Some((program, args)) = editor::split_command(&pager) else {
+        return Ok(false);
+    };
+
+    let program = editor::resolve_program(program).unwrap_or_else(|| program.into());
+    let child = Command::new(program)
+        .args(args)
+        .env("LESSSECURE", "1")
+        .env("LESSHISTFILE", "-")
+        .env_remove("LESSOPEN")
+        .env_remove("LESSCLOSE")
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(AppError::Io(e)),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(content) {
+            // The pager was closed before reading everything
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+    }
+    child.wait()?;
+    Ok(true)
+}
+
+/// Shows `content` in the built-in viewer until it's closed.
+pub fn run_internal(title: &str, content: String, theme: &Theme) -> Result<(), AppError> {
+    let mut guard = TerminalGuard::init()?;
+    let mut viewer = Viewer::new(title, content);
+    loop {
+        guard
+            .terminal
+            .draw(|f| viewer.render(f, theme))
+            .map_err(|e| AppError::Tui(e.to_string()))?;
+        if let Event::Key(key) = event::read()?
+            && !viewer.handle_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// A note opened for reading only, with scrolling and search.
+pub struct Viewer {
+    title: String,
+    content: String,
+    /// Index of the first screen row shown, counting wrapped lines.
+    scroll: usize,
+    /// The search being typed after `/`.
+    input: Option<String>,
+    /// The last search, repeated with `n`.
+    query: Option<String>,
+    /// Shown in place of the help line until the next key press.
+    message: Option<String>,
+    /// Size of the text area at the last render.
+    width: usize,
+    height: usize,
+    /// Shown next to the position, for text content.
+    stats: Option<NoteStats>,
+    links: Option<LinkPicker>,
+    /// Whether links can be copied to the clipboard, which privacy mode turns off.
+    can_copy: bool,
+}
+
+impl Viewer {
+    pub fn new(title: &str, content: String) -> Self {
+        Self {
+            title: title.to_string(),
+            content,
+            scroll: 0,
+            input: None,
+            query: None,
+            message: None,
+            width: 80,
+            height: 24,
+            stats: None,
+            links: None,
+            can_copy: true,
+        }
+    }
+
+    pub fn with_stats(mut self, stats: NoteStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Refuses to copy links to the clipboard.
+    pub fn without_copy(mut self) -> Self {
+        self.can_copy = false;
+        self
+    }
+
+    /// Handles a key press. Returns `false` once the viewer should close.
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(picker) = &mut self.links {
+            if !picker.handle_key(code) {
+                self.message = picker.message.take();
+                self.links = None;
+            }
+            return true;
+        }
+        if let Some(input) = &mut self.input {
+            match code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => text::pop_grapheme(input),
+                KeyCode::Enter => {
+                    let query = self.input.take().unwrap_or_default();
+                    if !query.is_empty() {
+                        self.query = Some(query);
+                        self.find(false);
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        self.message = None;
+        let max_scroll = self.max_scroll();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = (self.scroll + 1).min(max_scroll),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.scroll = (self.scroll + self.height).min(max_scroll);
+            }
+            KeyCode::PageUp | KeyCode::Char('b') => {
+                self.scroll = self.scroll.saturating_sub(self.height);
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => self.scroll = max_scroll,
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('n') => self.find(true),
+            KeyCode::Char('l') => match LinkPicker::new(links::urls(&self.content)) {
+                Some(picker) if !self.can_copy => self.links = Some(picker.without_copy()),
+                Some(picker) => self.links = Some(picker),
+                None => self.message = Some(t!("status-no-links")),
+            },
+            _ => {}
+        }
+        true
+    }
+
+    /// Screen rows taken by each line of the content once wrapped.
+    fn line_rows(&self) -> Vec<usize> {
+        let width = self.width.max(1);
+        self.content
+            .lines()
+            .map(|line| text::width(line).div_ceil(width).max(1))
+            .collect()
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.line_rows()
+            .iter()
+            .sum::<usize>()
+            .saturating_sub(self.height)
+    }
+
+    /// Scrolls to the next line matching the last search, case-insensitively,
+    /// starting at the top line or, with `skip_current`, after it.
+    fn find(&mut self, skip_current: bool) {
+        let Some(query) = self.query.as_ref().map(|query| query.to_lowercase()) else {
+            return;
+        };
+        let rows = self.line_rows();
+        let mut first_rows = Vec::with_capacity(rows.len());
+        let mut row = 0;
+        for line_rows in &rows {
+            first_rows.push(row);
+            row += line_rows;
+        }
+        let current = first_rows
+            .iter()
+            .rposition(|&first| first <= self.scroll)
+            .unwrap_or(0);
+        let start = if skip_current { current + 1 } else { current };
+
+        let lines: Vec<&str> = self.content.lines().collect();
+        let found = (start..lines.len())
+            .chain(0..start.min(lines.len()))
+            .find(|&index| lines[index].to_lowercase().contains(&query));
+        match found {
+            Some(index) => self.scroll = first_rows[index].min(self.max_scroll()),
+            None => self.message = Some(t!("viewer-not-found")),
+        }
+    }
+
+    /// How far through the content the bottom of the screen is.
+    fn percent(&self) -> usize {
+        let total = self.line_rows().iter().sum::<usize>();
+        if total <= self.height {
+            100
+        } else {
+            ((self.scroll + self.height) * 100 / total).min(100)
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(f.area());
+        self.width = usize::from(chunks[0].width.saturating_sub(2));
+        self.height = usize::from(chunks[0].height.saturating_sub(2));
+        self.scroll = self.scroll.min(self.max_scroll());
+
+        let content = Paragraph::new(highlight_links(&self.content))
+            .block(theme.block().title(format!(
+                "{}{} ({})",
+                theme.read_only_mark(),
+                self.title,
+                t!("mark-read-only")
+            )))
+            .wrap(Wrap { trim: false })
+            .scroll((u16::try_from(self.scroll).unwrap_or(u16::MAX), 0));
+        f.render_widget(content, chunks[0]);
+
+        let help = match (&self.input, &self.message) {
+            (Some(input), _) => format!("/{input}_"),
+            (None, Some(message)) => message.clone(),
+            (None, None) => t!("help-viewer"),
+        };
+        let position = match &self.stats {
+            Some(stats) => format!("{}%  {stats}", self.percent()),
+            None => format!("{}%", self.percent()),
+        };
+        let help = Paragraph::new(help).block(theme.block().title(position));
+        f.render_widget(help, chunks[1]);
+        if let Some(picker) = &mut self.links {
+            picker.render(f, theme);
+        }
+    }
+}
+
+/// How links are shown.
+fn link_style() -> Style {
+    Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::UNDERLINED)
+}
+
+/// `text` with its links underlined, borrowing from it.
+pub fn highlight_links(text: &str) -> Text<'_> {
+    text.lines()
+        .map(|line| {
+            let mut spans = Vec::new();
+            let mut shown = 0;
+            for range in links::find(line) {
+                spans.push(Span::raw(&line[shown..range.start]));
+                shown = range.end;
+                spans.push(Span::styled(&line[range], link_style()));
+            }
+            spans.push(Span::raw(&line[shown..]));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// `text` rendered as Markdown: headings, emphasis, code, quotes and list
+/// items are styled and their markers dropped, and links underlined as by
+/// [`highlight_links`]. Each line stays a line of its own, so scrolling and
+/// finding in the preview go by the same lines as for the raw text.
+pub fn render_markdown<'a>(text: &'a str, theme: &Theme) -> Text<'a> {
+    let code = Style::default().fg(Color::Yellow);
+    let mut in_code_block = false;
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                return Line::styled(line, code.add_modifier(Modifier::DIM));
+            }
+            if in_code_block {
+                return Line::styled(line, code);
+            }
+            markdown_line(line, theme)
+        })
+        .collect()
+}
+
+/// A line of Markdown outside of code blocks, see [`render_markdown`].
+fn markdown_line<'a>(line: &'a str, theme: &Theme) -> Line<'a> {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let level = rest.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&level) && (rest.len() == level || rest[level..].starts_with(' ')) {
+        let mut style = Style::default().add_modifier(Modifier::BOLD);
+        if level == 1 {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        return Line::from(inline(rest[level..].trim(), style));
+    }
+    if rest.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| rest.chars().all(|r| r == c || r == ' ') && rest.matches(c).count() >= 3)
+    {
+        return Line::styled(theme.rule(), dim);
+    }
+    if let Some(quote) = rest.strip_prefix('>') {
+        let bar = if theme.plain { "| " } else { "│ " };
+        let mut spans = vec![Span::raw(indent), Span::styled(bar, dim)];
+        spans.extend(inline(
+            quote.strip_prefix(' ').unwrap_or(quote),
+            Style::default().add_modifier(Modifier::ITALIC),
+        ));
+        return Line::from(spans);
+    }
+    if let Some((offset, checked)) = stats::checklist_item(line) {
+        let (mark, style) = match (checked, theme.plain) {
+            (false, false) => ("☐ ", Style::default()),
+            (false, true) => ("[ ] ", Style::default()),
+            (true, false) => ("☑ ", dim),
+            (true, true) => ("[x] ", dim),
+        };
+        let mut spans = vec![Span::raw(indent), Span::raw(mark)];
+        spans.extend(inline(line[offset + 3..].trim_start(), style));
+        return Line::from(spans);
+    }
+    if let Some(item) = rest
+        .strip_prefix(['-', '*', '+'])
+        .and_then(|item| item.strip_prefix(' '))
+    {
+        let bullet = if theme.plain { "- " } else { "• " };
+        let mut spans = vec![Span::raw(indent), Span::raw(bullet)];
+        spans.extend(inline(item, Style::default()));
+        return Line::from(spans);
+    }
+    Line::from(inline(line, Style::default()))
+}
+
+/// The spans of `text` in `style`, with `**strong**`, `*emphasis*`,
+/// `_emphasis_` and `` `code` `` shown without their markers, and links
+/// underlined. Markers within links are left alone.
+fn inline<'a>(text: &'a str, style: Style) -> Vec<Span<'a>> {
+    let links = links::find(text);
+    let mut spans = Vec::new();
+    let mut shown = 0;
+    let mut at = 0;
+    let push_plain = |spans: &mut Vec<Span<'a>>, range: Range<usize>| {
+        if !range.is_empty() {
+            spans.push(Span::styled(&text[range], style));
+        }
+    };
+    while at < text.len() {
+        let next_link = links.iter().find(|link| link.end > at);
+        if let Some(link) = next_link
+            && link.start <= at
+        {
+            push_plain(&mut spans, shown..at);
+            spans.push(Span::styled(&text[link.clone()], style.patch(link_style())));
+            at = link.end;
+            shown = at;
+            continue;
+        }
+        let limit = next_link.map_or(text.len(), |link| link.start);
+        match emphasis(text, at, limit) {
+            Some((marker, end, added)) => {
+                push_plain(&mut spans, shown..at);
+                spans.push(Span::styled(&text[at + marker..end], style.patch(added)));
+                at = end + marker;
+                shown = at;
+            }
+            None => at += text[at..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    push_plain(&mut spans, shown..text.len());
+    spans
+}
+
+/// The length of the marker of the emphasis or code starting at `at` in
+/// `text`, where its content ends and the style it adds, if it's closed
+/// before `limit`.
+fn emphasis(text: &str, at: usize, limit: usize) -> Option<(usize, usize, Style)> {
+    let rest = &text[at..limit];
+    let after_word = text[..at]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric);
+    let (marker, added) = if rest.starts_with('`') {
+        ("`", Style::default().fg(Color::Yellow))
+    } else if rest.starts_with("**") {
+        ("**", Style::default().add_modifier(Modifier::BOLD))
+    } else if rest.starts_with('*') || (rest.starts_with('_') && !after_word) {
+        (&rest[..1], Style::default().add_modifier(Modifier::ITALIC))
+    } else {
+        return None;
+    };
+    let content = &rest[marker.len()..];
+    let len = content.find(marker)?;
+    if len == 0 || content.starts_with(' ') || content[..len].ends_with(' ') {
+        return None;
+    }
+    Some((marker.len(), at + marker.len() + len, added))
+}
+
+/// `text` with every occurrence of `query` shown in `style`, ignoring case,
+/// over the style the spans it falls in already have, such as a link's.
+pub fn highlight_matches<'a>(mut text: Text<'a>, query: &str, style: Style) -> Text<'a> {
+    for line in &mut text.lines {
+        let plain: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        let found = text::find_ignoring_case(&plain, query);
+        if found.is_empty() {
+            continue;
+        }
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        for span in std::mem::take(&mut line.spans) {
+            let end = offset + span.content.len();
+            let mut shown = offset;
+            for range in found
+                .iter()
+                .filter(|range| range.start < end && range.end > offset)
+            {
+                let (start, stop) = (range.start.max(offset), range.end.min(end));
+                if shown < start {
+                    spans.push(part(&span, shown - offset..start - offset, span.style));
+                }
+                spans.push(part(
+                    &span,
+                    start - offset..stop - offset,
+                    span.style.patch(style),
+                ));
+                shown = stop;
+            }
+            if shown < end {
+                spans.push(part(&span, shown - offset..end - offset, span.style));
+            }
+            offset = end;
+        }
+        line.spans = spans;
+    }
+    text
+}
+
+/// The `range` of `span` in `style`, still borrowing if `span` does.
+fn part<'a>(span: &Span<'a>, range: Range<usize>, style: Style) -> Span<'a> {
+    match &span.content {
+        Cow::Borrowed(content) => Span::styled(&content[range], style),
+        Cow::Owned(content) => Span::styled(content[range].to_string(), style),
+    }
+}
+
+/// The links of a note in a numbered popup, to open one in the browser or copy
+/// it to the clipboard.
+pub struct LinkPicker {
+    urls: Vec<String>,
+    list_state: ListState,
+    /// What happened to the link picked, for the status line.
+    pub message: Option<String>,
+    can_copy: bool,
+}
+
+impl LinkPicker {
+    /// A picker of `urls`, or `None` if there are none.
+    pub fn new(urls: Vec<String>) -> Option<Self> {
+        (!urls.is_empty()).then(|| Self {
+            urls,
+            list_state: ListState::default().with_selected(Some(0)),
+            message: None,
+            can_copy: true,
+        })
+    }
+
+    /// Refuses to copy links, e.g. in privacy mode, where the clipboard could
+    /// be pasted somewhere the screen is shared.
+    pub fn without_copy(mut self) -> Self {
+        self.can_copy = false;
+        self
+    }
+
+    /// Handles a key press. Returns `false` once the picker should close, after
+    /// opening or copying a link or on Esc.
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let selected = self.list_state.selected().unwrap_or_default();
+        let last = self.urls.len() - 1;
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.list_state.select(Some((selected + 1).min(last)));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.list_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Enter | KeyCode::Char('o') => {
+                self.open(selected);
+                return false;
+            }
+            KeyCode::Char('c' | 'y') => {
+                self.copy(selected);
+                return false;
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                let index = digit as usize - '1' as usize;
+                if index <= last {
+                    self.open(index);
+                    return false;
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn open(&mut self, index: usize) {
+        let url = &self.urls[index];
+        self.message = Some(match links::open(url) {
+            Ok(()) => t!("link-opened", url = url),
+            Err(e) => t!("link-not-opened", opener = links::OPENER, error = e),
+        });
+    }
+
+    /// Copies through the terminal (OSC 52), which also works over SSH.
+    fn copy(&mut self, index: usize) {
+        if !self.can_copy {
+            self.message = Some(t!("link-copy-private"));
+            return;
+        }
+        let url = &self.urls[index];
+        self.message = Some(
+            match execute!(io::stdout(), CopyToClipboard::to_clipboard_from(url)) {
+                Ok(()) => t!("link-copied", url = url),
+                Err(e) => t!("link-not-copied", error = e),
+            },
+        );
+    }
+
+    pub fn render(&mut self, f: &mut Frame, theme: &Theme) {
+        let area = f.area();
+        let longest = self.urls.iter().map(|url| text::width(url)).max();
+        let width = u16::try_from(longest.unwrap_or_default() + 8)
+            .unwrap_or(u16::MAX)
+            .max(40)
+            .min(area.width);
+        let height = u16::try_from(self.urls.len() + 2)
+            .unwrap_or(u16::MAX)
+            .min(area.height);
+        let popup = Rect {
+            x: area.width.saturating_sub(width) / 2,
+            y: area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let items: Vec<_> = self
+            .urls
+            .iter()
+            .enumerate()
+            .map(|(i, url)| ListItem::new(format!("{}. {url}", i + 1)))
+            .collect();
+        let title = if self.can_copy {
+            t!("title-links-copy")
+        } else {
+            t!("title-links")
+        };
+        let list = theme.list(items, theme.block().title(title));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut self.list_state);
+    }
+}
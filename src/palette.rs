@@ -0,0 +1,163 @@
+//! The command palette of the TUI: every action of the note list by name,
+//! narrowed down by typing part of it.
+
+use crate::theme::Theme;
+use crossterm::event::KeyCode;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, ListItem, ListState},
+};
+use ryokan::{t, text};
+
+/// An action as listed in the palette.
+pub struct Entry {
+    pub name: String,
+    /// The key the action is bound to, e.g. `Enter` or `#`.
+    pub key: String,
+}
+
+/// What a key press in the palette led to.
+pub enum Outcome {
+    /// The palette stays open.
+    Open,
+    /// The palette was closed without choosing anything.
+    Closed,
+    /// The entry at this index was chosen.
+    Chosen(usize),
+}
+
+/// A popup listing `entries` that match what's typed, best match first.
+pub struct Palette {
+    entries: Vec<Entry>,
+    query: String,
+    /// Indexes of the entries matching `query`, in the order shown.
+    matches: Vec<usize>,
+    list_state: ListState,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let mut palette = Self {
+            entries,
+            query: String::new(),
+            matches: Vec::new(),
+            list_state: ListState::default(),
+        };
+        palette.update_matches();
+        palette
+    }
+
+    /// Handles a key press: typing filters, Up/Down choose and Enter runs.
+    pub fn handle_key(&mut self, code: KeyCode) -> Outcome {
+        let selected = self.list_state.selected().unwrap_or_default();
+        match code {
+            KeyCode::Esc => return Outcome::Closed,
+            KeyCode::Enter => {
+                return match self.matches.get(selected) {
+                    Some(&index) => Outcome::Chosen(index),
+                    None => Outcome::Closed,
+                };
+            }
+            KeyCode::Down => {
+                let last = self.matches.len().saturating_sub(1);
+                self.list_state.select(Some((selected + 1).min(last)));
+            }
+            KeyCode::Up => self.list_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Backspace => {
+                text::pop_grapheme(&mut self.query);
+                self.update_matches();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        Outcome::Open
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| fuzzy_score(&self.query, &entry.name).map(|score| (i, score)))
+            .collect();
+        // Stable, so equally good matches keep the order of the entries
+        scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.list_state
+            .select((!self.matches.is_empty()).then_some(0));
+    }
+
+    pub fn render(&mut self, f: &mut Frame, theme: &Theme) {
+        let area = f.area();
+        let width = 50.min(area.width);
+        let height = u16::try_from(self.entries.len() + 2)
+            .unwrap_or(u16::MAX)
+            .min(area.height);
+        let popup = Rect {
+            x: area.width.saturating_sub(width) / 2,
+            y: area.height.saturating_sub(height) / 3,
+            width,
+            height,
+        };
+        // Name on the left, key on the right, within the borders and after the
+        // selection marker
+        let inner = usize::from(width.saturating_sub(5));
+        let items: Vec<_> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let entry = &self.entries[i];
+                // Long names, e.g. of notes, are cut to keep the key in view
+                let key = text::width(&entry.key);
+                let name = text::truncate(&entry.name, inner.saturating_sub(key + 1));
+                let gap = inner.saturating_sub(text::width(name) + key);
+                ListItem::new(Line::from(vec![
+                    Span::raw(name),
+                    Span::raw(" ".repeat(gap)),
+                    Span::styled(
+                        entry.key.as_str(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ]))
+            })
+            .collect();
+        let list = theme.list(
+            items,
+            theme.block().title(t!("title-palette", query = self.query)),
+        );
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut self.list_state);
+    }
+}
+
+/// How well `name` matches `query`, or `None` if it doesn't contain the
+/// characters of `query` in order. Case is ignored, and characters matched at
+/// the start of a word or right after the previous one score higher.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut from = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = from + name[from..].iter().position(|&n| n == c)?;
+        if i == 0 || name[i - 1] == ' ' {
+            score += 3;
+        }
+        if previous.is_some_and(|previous| previous + 1 == i) {
+            score += 2;
+        }
+        score -= i32::try_from(i - from).unwrap_or(i32::MAX);
+        previous = Some(i);
+        from = i + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod palette_test;
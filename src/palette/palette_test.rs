@@ -0,0 +1,68 @@
+#![cfg(test)]
+
+use super::*;
+use ratatui::{Terminal, backend::TestBackend};
+use std::convert::Infallible;
+
+#[test]
+fn test_names_match_the_characters_typed_in_order() {
+    assert!(fuzzy_score("", "Delete note").is_some());
+    assert!(fuzzy_score("dln", "Delete note").is_some());
+    assert!(fuzzy_score("DELETE", "Delete note").is_some());
+    assert!(fuzzy_score("del note", "Delete note").is_some());
+    assert_eq!(fuzzy_score("nd", "Delete note"), None);
+    assert_eq!(fuzzy_score("x", "Delete note"), None);
+}
+
+#[test]
+fn test_word_starts_rank_first() {
+    let entries = ["Change sort order", "Show history", "Set due date"].map(|name| Entry {
+        name: name.to_string(),
+        key: String::new(),
+    });
+    let mut palette = Palette::new(entries.into());
+    assert_eq!(palette.matches, [0, 1, 2]);
+
+    for c in "sd".chars() {
+        palette.handle_key(KeyCode::Char(c));
+    }
+    assert_eq!(palette.matches[0], 2);
+    assert!(matches!(
+        palette.handle_key(KeyCode::Enter),
+        Outcome::Chosen(2)
+    ));
+
+    palette.handle_key(KeyCode::Char('z'));
+    assert!(palette.matches.is_empty());
+    assert!(matches!(
+        palette.handle_key(KeyCode::Enter),
+        Outcome::Closed
+    ));
+}
+
+#[test]
+fn test_wide_names_keep_the_key_in_view() -> Result<(), Infallible> {
+    let entries = vec![
+        Entry {
+            name: "Go to 📔日記 – cafe\u{301} and everything after it".to_string(),
+            key: "1".to_string(),
+        },
+        Entry {
+            name: "Delete note".to_string(),
+            key: "d".to_string(),
+        },
+    ];
+    let mut palette = Palette::new(entries);
+    for width in [12, 19, 20, 30] {
+        let mut terminal = Terminal::new(TestBackend::new(width, 6))?;
+        terminal.draw(|f| palette.render(f, &Theme::default()))?;
+        let buffer = terminal.backend().buffer();
+        for (y, key) in [(1, "1"), (2, "d")] {
+            let row: String = (0..width).map(|x| buffer[(x, y)].symbol()).collect();
+            // The key is right before the border, which nothing overwrote
+            assert_eq!(buffer[(width - 2, y)].symbol(), key, "{row:?}");
+            assert_eq!(buffer[(width - 1, y)].symbol(), "│", "{row:?}");
+        }
+    }
+    Ok(())
+}
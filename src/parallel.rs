@@ -0,0 +1,74 @@
+//! Running bulk operations, where every item pays a full Argon2 derivation, on
+//! several threads.
+//!
+//! The number of threads is capped so that the Argon2 instances running at once
+//! stay within [`ARGON2_MEMORY_BUDGET_KIB`].
+
+use crate::pin::ARGON2_MEMORY_KIB;
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Memory that concurrent Argon2 instances may take together, in KiB.
+pub const ARGON2_MEMORY_BUDGET_KIB: u32 = 512 * 1024;
+
+/// Where a bulk operation is at, passed to its progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Items finished so far, including this one.
+    pub done: usize,
+    pub total: usize,
+    /// The item just finished.
+    pub name: &'a str,
+}
+
+/// How many items needing an Argon2 derivation can be processed at once: one per
+/// CPU, as long as their memory fits in the budget.
+pub fn argon2_workers() -> usize {
+    let by_memory = (ARGON2_MEMORY_BUDGET_KIB / ARGON2_MEMORY_KIB.max(1)).max(1);
+    thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(usize::try_from(by_memory).unwrap_or(usize::MAX))
+}
+
+/// Applies `f` to every item on up to `workers` threads, returning the results
+/// in the order of `items`.
+pub fn map<T: Sync, R: Send>(items: &[T], workers: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return results;
+                        };
+                        results.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            // A panicking worker panics the caller, like the serial path would
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod parallel_test;
@@ -0,0 +1,28 @@
+#![cfg(test)]
+
+use super::*;
+use std::{collections::HashSet, sync::Mutex};
+
+#[test]
+fn test_map_keeps_the_order_of_items() {
+    let items: Vec<usize> = (0..100).collect();
+    let threads = Mutex::new(HashSet::new());
+
+    let results = map(&items, 4, |item| {
+        threads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(thread::current().id());
+        item * 2
+    });
+
+    assert_eq!(results, (0..100).map(|item| item * 2).collect::<Vec<_>>());
+    assert!(threads.into_inner().unwrap_or_default().len() <= 4);
+}
+
+#[test]
+fn test_argon2_workers_fit_the_memory_budget() {
+    let workers = argon2_workers();
+    assert!(workers >= 1);
+    assert!(workers as u64 * u64::from(ARGON2_MEMORY_KIB) <= u64::from(ARGON2_MEMORY_BUDGET_KIB));
+}
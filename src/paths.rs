@@ -0,0 +1,130 @@
+//! Where Ryokan keeps what it writes outside the notes directory.
+//!
+//! Everything goes in one of three places, following the XDG base directory
+//! spec on Linux and the platform conventions elsewhere, through `dirs`:
+//!
+//! - config: the config file, which people edit and back up;
+//! - state: what Ryokan records as it runs and that can't be rebuilt, like the
+//!   audit log and its own log file;
+//! - cache: what can be deleted at any time and rebuilt.
+//!
+//! With a config file given explicitly, e.g. with `--config-file`, the state and
+//! cache directories are next to it instead, so separate setups stay apart.
+//! Directories are only created when something is written in them, readable
+//! only by their owner.
+
+use crate::audit::AUDIT_FILE_NAME;
+use crate::error::AppError;
+use crate::permissions;
+use log::info;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Name of Ryokan's directory below the platform directories.
+pub const APP_DIR: &str = "ryokan";
+/// Name of the log file the TUI writes.
+pub const LOG_FILE_NAME: &str = "ryokan.log";
+
+/// The state directory when the config file is in its default place, e.g.
+/// `~/.local/state/ryokan/`, or the local data directory on platforms without
+/// one.
+pub fn default_state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join(APP_DIR))
+}
+
+/// The cache directory when the config file is in its default place, e.g.
+/// `~/.cache/ryokan/`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// The directories of a config file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Paths {
+    pub config_file: PathBuf,
+    pub state_dir: PathBuf,
+    pub cache_dir: PathBuf,
+}
+
+impl Paths {
+    /// The directories of `config_file`, whose default place is
+    /// `default_config_file`.
+    pub fn resolve(config_file: &Path, default_config_file: Option<&Path>) -> Self {
+        let beside = config_file
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+        let is_default = default_config_file == Some(config_file);
+        let pick = |dir: Option<PathBuf>| dir.filter(|_| is_default).unwrap_or(beside.clone());
+        Self {
+            config_file: config_file.to_path_buf(),
+            state_dir: pick(default_state_dir()),
+            cache_dir: pick(default_cache_dir()),
+        }
+    }
+
+    /// Where the state of `profile`, or of the top-level vault, is kept.
+    pub fn profile_state_dir(&self, profile: Option<&str>) -> PathBuf {
+        match profile {
+            Some(name) => self.state_dir.join("profiles").join(name),
+            None => self.state_dir.clone(),
+        }
+    }
+
+    /// Where the audit log of `profile`, or of the top-level vault, is kept.
+    pub fn audit_log(&self, profile: Option<&str>) -> PathBuf {
+        self.profile_state_dir(profile).join(AUDIT_FILE_NAME)
+    }
+
+    /// Moves the state files older versions kept next to the config file, i.e.
+    /// the audit logs of the vault and of `profiles`, to the state directory.
+    /// Files already there are left alone. Returns the files moved, by new path.
+    pub fn migrate_state<'a>(
+        &self,
+        profiles: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let Some(config_dir) = self.config_file.parent() else {
+            return Ok(Vec::new());
+        };
+        if config_dir == self.state_dir {
+            return Ok(Vec::new());
+        }
+        let old_dir = |profile: Option<&str>| match profile {
+            Some(name) => config_dir.join("profiles").join(name),
+            None => config_dir.to_path_buf(),
+        };
+        let mut moved = Vec::new();
+        for profile in std::iter::once(None).chain(profiles.into_iter().map(Some)) {
+            let from = old_dir(profile).join(AUDIT_FILE_NAME);
+            let to = self.audit_log(profile);
+            if !from.is_file() || to.exists() {
+                continue;
+            }
+            if let Some(parent) = to.parent() {
+                permissions::create_private_dir(parent)?;
+            }
+            move_file(&from, &to)?;
+            info!("Moved {} to {}", from.display(), to.display());
+            moved.push(to);
+        }
+        Ok(moved)
+    }
+}
+
+/// Renames `from` to `to`, copying it when they're on different file systems.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod paths_test;
@@ -0,0 +1,50 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_explicit_config_files_keep_their_state_beside_them() {
+    let config = Path::new("/tmp/setup/ryokan.toml");
+    let paths = Paths::resolve(
+        config,
+        Some(Path::new("/home/me/.config/ryokan/ryokan.toml")),
+    );
+    assert_eq!(paths.state_dir, Path::new("/tmp/setup"));
+    assert_eq!(paths.cache_dir, Path::new("/tmp/setup"));
+    assert_eq!(
+        paths.audit_log(Some("work")),
+        Path::new("/tmp/setup/profiles/work/audit.jsonl")
+    );
+}
+
+#[test]
+fn test_audit_logs_move_to_the_state_directory() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let config_dir = dir.path().join("config");
+    fs::create_dir_all(config_dir.join("profiles/work"))?;
+    fs::write(config_dir.join(AUDIT_FILE_NAME), "top\n")?;
+    fs::write(
+        config_dir.join("profiles/work").join(AUDIT_FILE_NAME),
+        "work\n",
+    )?;
+    let paths = Paths {
+        config_file: config_dir.join("ryokan.toml"),
+        state_dir: dir.path().join("state"),
+        cache_dir: dir.path().join("cache"),
+    };
+
+    let moved = paths.migrate_state(["work", "home"])?;
+    assert_eq!(
+        moved,
+        [paths.audit_log(None), paths.audit_log(Some("work"))]
+    );
+    assert_eq!(fs::read_to_string(paths.audit_log(Some("work")))?, "work\n");
+    assert!(!config_dir.join(AUDIT_FILE_NAME).exists());
+
+    // A log already in the state directory isn't replaced
+    fs::write(config_dir.join(AUDIT_FILE_NAME), "stale\n")?;
+    assert!(paths.migrate_state([])?.is_empty());
+    assert_eq!(fs::read_to_string(paths.audit_log(None))?, "top\n");
+    Ok(())
+}
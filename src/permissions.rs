@@ -0,0 +1,115 @@
+//! Keeping the config file, the notes directory and PIN files private to
+//! their owner.
+//!
+//! The config file holds the PIN hash, and even encrypted, the notes directory
+//! tells how many notes there are and when they changed. On Unix, Ryokan
+//! creates them readable only by their owner and warns about existing ones
+//! that other users can get into. Windows keeps other users out of the user
+//! profile with its ACL, so nothing is checked there.
+
+use crate::error::AppError;
+use std::{fmt, fs, io, path::Path, path::PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+/// Mode of private files, read and write for the owner only.
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+/// Mode of private directories, which also have to be searchable.
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// Bits that give the group or other users any access.
+const SHARED_BITS: u32 = 0o077;
+
+/// A file or directory other users have access to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loose {
+    pub path: PathBuf,
+    /// Its permission bits, e.g. `0o644`.
+    pub mode: u32,
+    /// The mode [`restrict`] gives it.
+    pub private_mode: u32,
+}
+
+impl fmt::Display for Loose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is open to other users (mode {:o}, expected {:o})",
+            self.path.display(),
+            self.mode,
+            self.private_mode
+        )
+    }
+}
+
+/// Creates `dir` and its missing parents, readable only by their owner.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(PRIVATE_DIR_MODE);
+    builder.create(dir)
+}
+
+/// `path` if it exists and other users have access to it. A file is expected
+/// to be [`PRIVATE_FILE_MODE`] and a directory [`PRIVATE_DIR_MODE`].
+#[cfg(unix)]
+pub fn check(path: &Path) -> Result<Option<Loose>, AppError> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::Io(e)),
+    };
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & SHARED_BITS == 0 {
+        return Ok(None);
+    }
+    let private_mode = if metadata.is_dir() {
+        PRIVATE_DIR_MODE
+    } else {
+        PRIVATE_FILE_MODE
+    };
+    Ok(Some(Loose {
+        path: path.to_path_buf(),
+        mode,
+        private_mode,
+    }))
+}
+
+/// Nothing is checked outside Unix.
+#[cfg(not(unix))]
+pub fn check(_path: &Path) -> Result<Option<Loose>, AppError> {
+    Ok(None)
+}
+
+/// Those of `paths` other users have access to, see [`check`].
+pub fn check_all<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Result<Vec<Loose>, AppError> {
+    paths
+        .into_iter()
+        .filter_map(|path| check(path).transpose())
+        .collect()
+}
+
+/// Takes away the access other users have to `loose`.
+pub fn restrict(loose: &Loose) -> Result<(), AppError> {
+    #[cfg(unix)]
+    fs::set_permissions(&loose.path, fs::Permissions::from_mode(loose.private_mode))?;
+    Ok(())
+}
+
+/// Fails if other users have access to a file holding a secret, such as a
+/// `--pin-file`, rather than using it.
+pub fn refuse_shared(path: &Path) -> Result<(), AppError> {
+    match check(path)? {
+        Some(loose) => Err(AppError::Permissions(format!(
+            "{loose}, run `chmod {:o} {}` first",
+            loose.private_mode,
+            path.display()
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod permissions_test;
@@ -0,0 +1,93 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), AppError> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn mode(path: &Path) -> Result<u32, AppError> {
+    Ok(fs::metadata(path)?.permissions().mode() & 0o777)
+}
+
+#[test]
+#[cfg(unix)]
+fn test_private_dirs_are_created_private() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let notes = dir.path().join("profiles/work/notes");
+
+    create_private_dir(&notes)?;
+    assert_eq!(mode(&notes)?, PRIVATE_DIR_MODE);
+    assert_eq!(mode(&dir.path().join("profiles"))?, PRIVATE_DIR_MODE);
+    assert_eq!(check(&notes)?, None);
+    // Already there is fine
+    create_private_dir(&notes)?;
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_shared_files_and_dirs_are_found_and_restricted() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let config = dir.path().join("config.toml");
+    let notes = dir.path().join("notes");
+    fs::write(&config, "")?;
+    fs::create_dir(&notes)?;
+    set_mode(&config, 0o644)?;
+    set_mode(&notes, 0o750)?;
+    set_mode(dir.path(), 0o700)?;
+
+    let loose = check_all([config.as_path(), notes.as_path(), dir.path()])?;
+    assert_eq!(
+        loose,
+        [
+            Loose {
+                path: config.clone(),
+                mode: 0o644,
+                private_mode: PRIVATE_FILE_MODE,
+            },
+            Loose {
+                path: notes.clone(),
+                mode: 0o750,
+                private_mode: PRIVATE_DIR_MODE,
+            },
+        ]
+    );
+    for loose in &loose {
+        restrict(loose)?;
+    }
+    assert_eq!(mode(&config)?, 0o600);
+    assert_eq!(mode(&notes)?, 0o700);
+    assert!(check_all([config.as_path(), notes.as_path()])?.is_empty());
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_shared_secret_files_are_refused() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let pin_file = dir.path().join("pin");
+    fs::write(&pin_file, "123456\n")?;
+
+    for shared in [0o640, 0o604, 0o620] {
+        set_mode(&pin_file, shared)?;
+        assert!(
+            matches!(refuse_shared(&pin_file), Err(AppError::Permissions(_))),
+            "mode {shared:o} was accepted"
+        );
+    }
+    set_mode(&pin_file, 0o400)?;
+    refuse_shared(&pin_file)?;
+    Ok(())
+}
+
+#[test]
+fn test_missing_paths_are_not_reported() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    assert_eq!(check(&dir.path().join("missing"))?, None);
+    Ok(())
+}
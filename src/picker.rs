@@ -0,0 +1,103 @@
+//! Choosing a note with an external fuzzy finder, such as fzf.
+//!
+//! The picker gets one `title<TAB>uuid` line per note on stdin and prints the
+//! chosen line on stdout. Titles are escaped so that each note is exactly one
+//! line with one tab, whatever its title holds.
+
+use crate::editor;
+use log::warn;
+use ryokan::{error::AppError, vault::Note};
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Escapes backslashes, tabs and line breaks, so a title is always one field
+/// of one line.
+pub fn escape_field(field: &str) -> Cow<'_, str> {
+    if !field.contains(['\\', '\t', '\n', '\r']) {
+        return Cow::Borrowed(field);
+    }
+    let mut escaped = String::with_capacity(field.len() + 2);
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// The `title<TAB>uuid` lines of `notes`.
+pub fn lines(notes: &[Note]) -> String {
+    notes
+        .iter()
+        .map(|note| {
+            format!(
+                "{}\t{}\n",
+                escape_field(&note.metadata.original_filename),
+                note.uuid
+            )
+        })
+        .collect()
+}
+
+/// The UUID of the note chosen in what the picker printed, or `None` if it
+/// printed nothing.
+pub fn parse_selection(output: &str) -> Result<Option<&str>, AppError> {
+    let Some(line) = output.lines().find(|line| !line.trim().is_empty()) else {
+        return Ok(None);
+    };
+    line.rsplit_once('\t')
+        .map(|(_, uuid)| uuid.trim())
+        .filter(|uuid| uuid::Uuid::parse_str(uuid).is_ok())
+        .map(Some)
+        .ok_or_else(|| AppError::Config(format!("The picker printed an unknown line: {line}")))
+}
+
+/// Runs `picker` on `lines` and returns the UUID of the note chosen, or `None`
+/// if the picker was cancelled, which it tells by exiting with an error.
+pub fn pick(picker: &str, lines: &str) -> Result<Option<String>, AppError> {
+    let (program, args) = editor::split_command(picker)
+        .ok_or_else(|| AppError::Config("The picker command is empty".to_string()))?;
+    let resolved = editor::resolve_program(program).ok_or_else(|| {
+        AppError::Config(format!(
+            "Picker \"{program}\" was not found on PATH, set another with `ryokan config set picker`"
+        ))
+    })?;
+    let mut child = Command::new(resolved)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let stdin = child.stdin.take();
+    let output = thread::scope(|scope| {
+        scope.spawn(move || {
+            if let Some(mut stdin) = stdin {
+                // The picker may quit before reading everything, e.g. on Esc
+                match stdin.write_all(lines.as_bytes()) {
+                    Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                        warn!("Could not give the notes to the picker: {e}")
+                    }
+                    _ => {}
+                }
+            }
+        });
+        child.wait_with_output()
+    })?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_selection(&output)?.map(str::to_string))
+}
+
+#[cfg(test)]
+mod picker_test;
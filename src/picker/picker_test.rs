@@ -0,0 +1,40 @@
+#![cfg(test)]
+
+use super::*;
+use ryokan::metadata::NoteMetadata;
+
+const UUID: &str = "0b5c6a4e-3f1d-4a8e-9c2b-7d1e5f3a9b10";
+
+#[test]
+fn test_titles_cant_break_the_lines() -> Result<(), AppError> {
+    let notes = [Note {
+        uuid: UUID.to_string(),
+        metadata: NoteMetadata::new("Tabs\there,\nlines and a \\t"),
+    }];
+    let lines = lines(&notes);
+    assert_eq!(lines, format!("Tabs\\there,\\nlines and a \\\\t\t{UUID}\n"));
+    assert_eq!(lines.lines().count(), 1);
+    assert_eq!(parse_selection(&lines)?, Some(UUID));
+    Ok(())
+}
+
+#[test]
+fn test_parse_selection() -> Result<(), AppError> {
+    assert_eq!(parse_selection("")?, None);
+    assert_eq!(parse_selection("\n")?, None);
+    assert_eq!(parse_selection(&format!("a\tb\t{UUID}\r\n"))?, Some(UUID));
+    assert!(parse_selection("a title without a uuid").is_err());
+    assert!(parse_selection("title\tnot-a-uuid").is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pick_runs_the_picker_and_treats_failure_as_cancelled() -> Result<(), AppError> {
+    let lines = format!("First\t{UUID}\nSecond\t{}\n", uuid::Uuid::new_v4());
+    assert_eq!(pick("head -n 1", &lines)?, Some(UUID.to_string()));
+    assert_eq!(pick("false", &lines)?, None);
+    assert_eq!(pick("true", &lines)?, None);
+    assert!(pick("ryokan-no-such-picker", &lines).is_err());
+    Ok(())
+}
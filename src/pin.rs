@@ -1,28 +1,172 @@
+//! PIN handling: hashing, verification and key derivation.
+
+use crate::audit::{AuditLog, Event, EventKind};
 use crate::config::Config;
 use crate::error::AppError;
+use crate::permissions;
+use crate::t;
 use aes_gcm::Key;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version};
 use log::info;
-use std::io::{self, Write};
+use std::{collections::VecDeque, fs, path::Path};
 use zeroize::Zeroizing;
 
-const MAX_PIN_LENGTH: usize = 6;
+/// Number of characters in a PIN.
+pub const PIN_LENGTH: usize = 6;
+
+/// Argon2 memory cost in KiB, used for PIN hashes and key derivation.
+pub const ARGON2_MEMORY_KIB: u32 = 65536;
+/// Argon2 iterations.
+pub const ARGON2_ITERATIONS: u32 = 10;
+/// Argon2 lanes.
+pub const ARGON2_PARALLELISM: u32 = 1;
+
+/// Source of secrets and yes/no answers for the PIN flows.
+///
+/// Frontends implement this to plug in their own input method, while
+/// [`ScriptedPrompt`] replays canned answers so the flows can be tested.
+pub trait PinPrompt {
+    /// Asks for a secret without echoing it back.
+    fn read_secret(&mut self, prompt: &str) -> Result<Zeroizing<String>, AppError>;
+    /// Asks a yes/no question.
+    fn confirm(&mut self, prompt: &str) -> Result<bool, AppError>;
+    /// Tells the user something went wrong without aborting the flow.
+    fn notify(&mut self, message: &str);
+}
+
+/// Replays a fixed script of answers, for tests.
+#[derive(Debug, Default)]
+pub struct ScriptedPrompt {
+    secrets: VecDeque<String>,
+    confirmations: VecDeque<bool>,
+    /// Every message passed to [`PinPrompt::notify`], in order.
+    pub notifications: Vec<String>,
+}
 
-pub fn ask_for_pin() -> Result<Zeroizing<String>, AppError> {
-    print!("Please enter your 6-digit PIN: ");
-    // Make sure prompt is displayed before reading
-    io::stdout().flush().map_err(AppError::Io)?;
-    let pin = rpassword::read_password().map_err(AppError::Io)?;
+impl ScriptedPrompt {
+    pub fn new<S: Into<String>>(
+        secrets: impl IntoIterator<Item = S>,
+        confirmations: impl IntoIterator<Item = bool>,
+    ) -> Self {
+        Self {
+            secrets: secrets.into_iter().map(Into::into).collect(),
+            confirmations: confirmations.into_iter().collect(),
+            notifications: Vec::new(),
+        }
+    }
+}
+
+impl PinPrompt for ScriptedPrompt {
+    fn read_secret(&mut self, prompt: &str) -> Result<Zeroizing<String>, AppError> {
+        self.secrets
+            .pop_front()
+            .map(Zeroizing::new)
+            .ok_or_else(|| AppError::Pin(format!("No scripted answer for \"{prompt}\"")))
+    }
+
+    fn confirm(&mut self, prompt: &str) -> Result<bool, AppError> {
+        self.confirmations
+            .pop_front()
+            .ok_or_else(|| AppError::Pin(format!("No scripted answer for \"{prompt}\"")))
+    }
+
+    fn notify(&mut self, message: &str) {
+        self.notifications.push(message.to_string());
+    }
+}
+
+/// Reads a PIN through `prompt` and checks that it has the expected length.
+pub fn ask_for_pin(
+    prompt: &mut dyn PinPrompt,
+    message: &str,
+) -> Result<Zeroizing<String>, AppError> {
+    let pin = prompt.read_secret(message)?;
+
+    let trimmed_pin = Zeroizing::new(pin.trim().to_string());
+    if trimmed_pin.len() != PIN_LENGTH {
+        return Err(AppError::Pin("PIN must be 6 digits.".to_string()));
+    }
+    Ok(trimmed_pin)
+}
 
-    let trimmed_pin = pin.trim().to_string();
-    if trimmed_pin.len() != MAX_PIN_LENGTH {
+/// Reads a PIN from the first line of a file, for non-interactive use.
+///
+/// Fails with [`AppError::Permissions`] if other users have access to the file.
+pub fn read_pin_file(path: &Path) -> Result<Zeroizing<String>, AppError> {
+    permissions::refuse_shared(path)?;
+    let contents = Zeroizing::new(fs::read_to_string(path)?);
+    let pin = Zeroizing::new(
+        contents
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    );
+    if pin.len() != PIN_LENGTH {
         return Err(AppError::Pin("PIN must be 6 digits.".to_string()));
     }
-    Ok(Zeroizing::new(trimmed_pin))
+    Ok(pin)
 }
 
+/// Asks for a new PIN twice until both entries match.
+pub fn ask_for_new_pin(prompt: &mut dyn PinPrompt) -> Result<Zeroizing<String>, AppError> {
+    loop {
+        let pin = ask_for_pin(prompt, &t!("pin-enter"))?;
+        let confirmation = ask_for_pin(prompt, &t!("pin-confirm"))?;
+        if *pin == *confirmation {
+            return Ok(pin);
+        }
+        if !prompt.confirm(&t!("pin-mismatch-retry"))? {
+            return Err(AppError::Pin("PIN setup cancelled.".to_string()));
+        }
+    }
+}
+
+/// Why `pin` is easy to guess, if it is: one digit repeated, consecutive
+/// digits, a short pattern repeated or a date.
+///
+/// ```
+/// use ryokan::pin::weakness;
+///
+/// assert!(weakness("123456").is_some());
+/// assert!(weakness("839201").is_none());
+/// ```
+pub fn weakness(pin: &str) -> Option<String> {
+    let digits: Vec<u32> = pin.chars().map(|c| c.to_digit(10)).collect::<Option<_>>()?;
+    if digits.len() != PIN_LENGTH {
+        return None;
+    }
+    let steps: Vec<i64> = digits
+        .windows(2)
+        .map(|pair| i64::from(pair[1]) - i64::from(pair[0]))
+        .collect();
+    if steps.iter().all(|&step| step == 0) {
+        return Some(t!("pin-weak-repeated"));
+    }
+    if steps.iter().all(|&step| step == 1) || steps.iter().all(|&step| step == -1) {
+        return Some(t!("pin-weak-consecutive"));
+    }
+    if [2, 3]
+        .into_iter()
+        .any(|len| digits.iter().zip(&digits[len..]).all(|(a, b)| a == b))
+    {
+        return Some(t!("pin-weak-pattern"));
+    }
+    // As DDMMYY, MMDDYY or YYMMDD
+    let [a, b, c] = [0, 2, 4].map(|i| digits[i] * 10 + digits[i + 1]);
+    let is_day = |n: u32| (1..=31).contains(&n);
+    let is_month = |n: u32| (1..=12).contains(&n);
+    if (is_day(a) && is_month(b)) || (is_month(a) && is_day(b)) || (is_month(b) && is_day(c)) {
+        return Some(t!("pin-weak-date"));
+    }
+    None
+}
+
+/// Returns the stored PIN hash, if one has been set.
 pub fn load_pin_hash(config: &Config) -> Option<String> {
-    (!config.pin_hash.is_empty()).then(|| config.pin_hash.clone())
+    let pin_hash = config.pin_hash();
+    (!pin_hash.is_empty()).then(|| pin_hash.to_string())
 }
 
 // Create a secure Argon2 instance with strong parameters.
@@ -31,20 +175,28 @@ pub fn load_pin_hash(config: &Config) -> Option<String> {
 // - t=10 iterations: Time cost for additional computational difficulty
 // - p=1 parallelism: Sequential to minimize side-channel attacks on PIN verification
 // These params balance security for low-entropy PINs against usability on typical hardware.
-fn create_argon2() -> Argon2<'static> {
-    let params = Params::new(65536, 10, 1, None).expect("hardcoded Argon2 params are valid");
-    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+fn create_argon2() -> Result<Argon2<'static>, AppError> {
+    argon2_with([ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM])
+}
+
+/// Argon2id with `params`: memory in KiB, iterations and lanes.
+fn argon2_with(params: [u32; 3]) -> Result<Argon2<'static>, AppError> {
+    let [memory_kib, iterations, parallelism] = params;
+    let params = Params::new(memory_kib, iterations, parallelism, None)
+        .map_err(|e| AppError::PinHash(format!("Invalid Argon2 parameters: {e}")))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
 }
 
+/// Hashes `pin` and persists the hash into the config file.
 pub fn store_pin(config: &mut Config, pin: &str) -> Result<(), AppError> {
-    let argon2 = create_argon2();
+    let argon2 = create_argon2()?;
 
     let password_hash = argon2
         .hash_password(pin.as_bytes())
         .map_err(|e| AppError::PinHash(format!("Failed to hash PIN: {e}")))?;
 
     // Store full PHC string (includes salt + params + hash)
-    config.pin_hash = password_hash.to_string();
+    config.set_pin_hash(password_hash.to_string());
 
     info!("Saving configuration file to config path");
 
@@ -55,19 +207,35 @@ pub fn store_pin(config: &mut Config, pin: &str) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Checks `pin` against the stored hash. Returns `false` if no PIN is set.
 pub fn verify_pin(config: &Config, pin: &str) -> Result<bool, AppError> {
     let Some(stored_hash) = load_pin_hash(config) else {
         return Ok(false);
     };
-    let argon2 = create_argon2();
+    let argon2 = create_argon2()?;
     let parsed_hash = argon2::PasswordHash::new(&stored_hash)
         .map_err(|e| AppError::PinHash(format!("Failed to parse stored PIN hash: {e}")))?;
     info!("Verifying PIN");
     Ok(argon2.verify_password(pin.as_bytes(), &parsed_hash).is_ok())
 }
 
+/// Derives the AES-256 key used for a note from `pin` and the note's salt.
 pub fn derive_key_from_pin(pin: &str, salt: &[u8]) -> Result<Key<aes_gcm::Aes256Gcm>, AppError> {
-    let argon2 = create_argon2();
+    derive_key_with_params(
+        pin,
+        salt,
+        [ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM],
+    )
+}
+
+/// Like [`derive_key_from_pin`], with other Argon2 parameters: memory in KiB,
+/// iterations and lanes, e.g. those a bundle was made with.
+pub fn derive_key_with_params(
+    pin: &str,
+    salt: &[u8],
+    params: [u32; 3],
+) -> Result<Key<aes_gcm::Aes256Gcm>, AppError> {
+    let argon2 = argon2_with(params)?;
     let mut key = [0u8; 32];
     argon2
         .hash_password_into(pin.as_bytes(), salt, &mut key)
@@ -75,24 +243,36 @@ pub fn derive_key_from_pin(pin: &str, salt: &[u8]) -> Result<Key<aes_gcm::Aes256
     Ok(*Key::<aes_gcm::Aes256Gcm>::from_slice(&key))
 }
 
+/// Verifies the PIN against the stored hash, or asks for a new one if none is set.
+///
+/// Wrong PINs and setting a new one are recorded in `audit_log`, if given.
 pub fn handle_pin_setup_and_verification(
     config: &mut Config,
+    prompt: &mut dyn PinPrompt,
+    audit_log: Option<&AuditLog>,
 ) -> Result<Zeroizing<String>, AppError> {
+    let record = |kind, pin: Option<&str>| {
+        if let Some(audit_log) = audit_log {
+            audit_log.record(&Event::new(kind, None), pin);
+        }
+    };
     let stored_pin_hash = load_pin_hash(config);
     let pin = if let Some(hash) = stored_pin_hash
         && !hash.is_empty()
     {
         loop {
-            let entered_pin = ask_for_pin()?;
+            let entered_pin = ask_for_pin(prompt, &t!("pin-enter"))?;
             if verify_pin(config, &entered_pin)? {
                 break entered_pin;
             }
-            eprintln!("Incorrect PIN. Please try again.");
+            record(EventKind::PinFailed, None);
+            prompt.notify(&t!("pin-incorrect-retry"));
         }
     } else {
-        eprintln!("No PIN found. Please set a new 6-digit PIN.");
-        let new_pin = ask_for_pin()?;
+        prompt.notify(&t!("pin-none-set"));
+        let new_pin = ask_for_new_pin(prompt)?;
         store_pin(config, &new_pin)?;
+        record(EventKind::PinSet, Some(&new_pin));
         new_pin
     };
     Ok(pin)
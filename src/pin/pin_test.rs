@@ -1,8 +1,8 @@
 #![cfg(test)]
 
 use super::*;
-use crate::config::Config;
-use std::assert_matches;
+use crate::config::{Config, Profile};
+use std::fs;
 use tempfile::tempdir;
 
 #[test]
@@ -14,8 +14,122 @@ fn test_store_and_verify_pin() -> Result<(), AppError> {
     let test_pin = "123456";
     store_pin(&mut config, test_pin)?;
 
-    assert_matches!(verify_pin(&config, test_pin), Ok(true));
-    assert_matches!(verify_pin(&config, "654321"), Ok(false));
+    assert!(matches!(verify_pin(&config, test_pin), Ok(true)));
+    assert!(matches!(verify_pin(&config, "654321"), Ok(false)));
+
+    Ok(())
+}
+
+#[test]
+fn test_storing_the_pin_keeps_unknown_keys_and_comments() -> Result<(), AppError> {
+    let dir = tempdir().map_err(AppError::Io)?;
+    let config_path = dir.path().join("test_config.toml");
+    fs::write(
+        &config_path,
+        "# Managed by my wrapper\nwrapper_backup = \"nightly\"\nhistory_versions = 9 # plenty\n\n[wrapper]\nretries = 3\n",
+    )?;
+    let mut config = Config::new(Some(&config_path))?;
+
+    store_pin(&mut config, "123456")?;
+
+    let saved = fs::read_to_string(&config_path)?;
+    assert!(
+        saved.starts_with("# Managed by my wrapper\nwrapper_backup = \"nightly\"\n"),
+        "{saved}"
+    );
+    assert!(saved.contains("history_versions = 9 # plenty\n"), "{saved}");
+    assert!(saved.contains("[wrapper]\nretries = 3\n"), "{saved}");
+    let config = Config::new(Some(&config_path))?;
+    assert!(matches!(verify_pin(&config, "123456"), Ok(true)));
+    assert_eq!(config.history_versions, 9);
+    Ok(())
+}
+
+#[test]
+fn test_handle_pin_setup_and_verification_new_pin() -> Result<(), AppError> {
+    let dir = tempdir().map_err(AppError::Io)?;
+    let config_path = dir.path().join("test_config.toml");
+    let mut config = Config::new(Some(&config_path))?;
+
+    // First attempt mismatches on confirmation, second one succeeds
+    let mut prompt = ScriptedPrompt::new(["123456", "123465", "123456", "123456"], [true]);
+    let pin = handle_pin_setup_and_verification(&mut config, &mut prompt, None)?;
+
+    assert_eq!(pin.as_str(), "123456");
+    assert!(matches!(verify_pin(&config, "123456"), Ok(true)));
+    assert_eq!(prompt.notifications.len(), 1);
+
+    // The hash was persisted, so a fresh config goes through verification
+    let mut config = Config::new(Some(&config_path))?;
+    let mut prompt = ScriptedPrompt::new(["654321", "123456"], []);
+    let pin = handle_pin_setup_and_verification(&mut config, &mut prompt, None)?;
+
+    assert_eq!(pin.as_str(), "123456");
+    assert_eq!(prompt.notifications, ["Incorrect PIN. Please try again."]);
+
+    Ok(())
+}
+
+#[test]
+fn test_new_pin_mismatch_cancelled() -> Result<(), AppError> {
+    let dir = tempdir().map_err(AppError::Io)?;
+    let config_path = dir.path().join("test_config.toml");
+    let mut config = Config::new(Some(&config_path))?;
+
+    let mut prompt = ScriptedPrompt::new(["123456", "000000"], [false]);
+    let result = handle_pin_setup_and_verification(&mut config, &mut prompt, None);
+
+    assert!(matches!(result, Err(AppError::Pin(_))));
+    assert!(config.pin_hash().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_ask_for_pin_rejects_wrong_length() {
+    let mut prompt = ScriptedPrompt::new(["12345"], []);
+    assert!(matches!(
+        ask_for_pin(&mut prompt, "PIN: "),
+        Err(AppError::Pin(_))
+    ));
+}
+
+#[test]
+fn test_guessable_pins_are_pointed_out() {
+    for pin in [
+        "000000", "777777", "123456", "987654", "121212", "123123", "250690",
+    ] {
+        assert!(weakness(pin).is_some(), "{pin}");
+    }
+    for pin in ["839201", "472958", "12345", "abcdef"] {
+        assert_eq!(weakness(pin), None, "{pin}");
+    }
+}
+
+#[test]
+fn test_profiles_have_separate_pins() -> Result<(), AppError> {
+    let dir = tempdir().map_err(AppError::Io)?;
+    let config_path = dir.path().join("test_config.toml");
+    let mut config = Config::new(Some(&config_path))?;
+    config
+        .profiles
+        .insert("work".to_string(), Profile::new("work"));
+    store_pin(&mut config, "111111")?;
+
+    config.select_profile(Some("work"))?;
+    assert!(load_pin_hash(&config).is_none());
+    store_pin(&mut config, "222222")?;
+
+    let mut config = Config::new(Some(&config_path))?;
+    assert!(matches!(verify_pin(&config, "111111"), Ok(true)));
+    config.select_profile(Some("work"))?;
+    assert!(matches!(verify_pin(&config, "222222"), Ok(true)));
+    assert!(matches!(verify_pin(&config, "111111"), Ok(false)));
+    assert_eq!(
+        config.notes_dir_path(),
+        dir.path().join("profiles").join("work").join("notes")
+    );
+    assert!(config.select_profile(Some("missing")).is_err());
 
     Ok(())
 }
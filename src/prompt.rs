@@ -0,0 +1,32 @@
+use rpassword::read_password;
+use ryokan::{error::AppError, pin::PinPrompt, t};
+use std::io::{self, Write};
+use zeroize::Zeroizing;
+
+/// Prompts on the controlling terminal with hidden input.
+pub struct TerminalPrompt;
+
+impl PinPrompt for TerminalPrompt {
+    fn read_secret(&mut self, prompt: &str) -> Result<Zeroizing<String>, AppError> {
+        print!("{prompt}");
+        // Make sure prompt is displayed before reading
+        io::stdout().flush().map_err(AppError::Io)?;
+        let secret = read_password().map_err(AppError::Io)?;
+        Ok(Zeroizing::new(secret))
+    }
+
+    fn confirm(&mut self, prompt: &str) -> Result<bool, AppError> {
+        print!("{prompt} {} ", t!("prompt-yes-no"));
+        io::stdout().flush().map_err(AppError::Io)?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).map_err(AppError::Io)?;
+        let answer = answer.trim().to_lowercase();
+        Ok(t!("prompt-yes-answers")
+            .split_whitespace()
+            .any(|yes| yes == answer))
+    }
+
+    fn notify(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+}
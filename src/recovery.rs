@@ -0,0 +1,164 @@
+//! A record of the bulk change running in a vault, so one cut short by Ctrl-C
+//! or a power cut can be finished or undone instead of leaving the vault half
+//! converted.
+//!
+//! The record lives in [`RECOVERY_FILE_NAME`] from the moment the operation
+//! starts until it's over, and is rewritten as each file goes through. It
+//! holds file names, note UUIDs and how far each file got, so it stays in
+//! plain JSON that can be inspected without the PIN: never any content, and
+//! never the PIN.
+
+use crate::error::AppError;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Mutex};
+
+/// Name of the record at the root of the vault, there only while an operation
+/// runs or after one was interrupted.
+pub const RECOVERY_FILE_NAME: &str = ".ryokan-recovery.json";
+
+/// The bulk operations that are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    /// Plaintext files are turned into notes, then deleted.
+    EncryptUnencrypted,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperationKind::EncryptUnencrypted => f.write_str("encrypt-unencrypted"),
+        }
+    }
+}
+
+/// How far an operation got with one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum FileStatus {
+    /// Not touched yet.
+    Pending,
+    /// The note `uuid` was being written, and may be incomplete.
+    Writing { uuid: String },
+    /// The note `uuid` is written, the file is still there.
+    Written { uuid: String },
+    /// The file is gone, replaced by the note `uuid`.
+    Done { uuid: String },
+}
+
+/// A file an operation goes through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRecord {
+    /// Name of the file in the vault.
+    pub name: String,
+    #[serde(flatten)]
+    pub status: FileStatus,
+}
+
+/// A bulk operation and how far it got with each of its files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operation {
+    pub kind: OperationKind,
+    pub started_at: DateTime<Utc>,
+    pub files: Vec<FileRecord>,
+}
+
+impl Operation {
+    /// The operation recorded in `storage`, if one didn't finish.
+    pub fn load(storage: &dyn Storage) -> Result<Option<Operation>, AppError> {
+        if !storage.exists(RECOVERY_FILE_NAME) {
+            return Ok(None);
+        }
+        let data = storage.read(RECOVERY_FILE_NAME)?;
+        serde_json::from_slice(&data).map(Some).map_err(|e| {
+            AppError::Config(format!(
+                "The record of an interrupted operation, {RECOVERY_FILE_NAME}, can't be read: {e}"
+            ))
+        })
+    }
+
+    /// How many files the operation was done with.
+    pub fn done(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.status, FileStatus::Done { .. }))
+            .count()
+    }
+
+    fn save(&self, storage: &dyn Storage) -> Result<(), AppError> {
+        let data = serde_json::to_vec_pretty(self).map_err(|e| {
+            AppError::Config(format!("Could not serialize the operation record: {e}"))
+        })?;
+        storage.write(RECOVERY_FILE_NAME, &data)
+    }
+}
+
+/// Keeps the record of a running operation up to date. Files can be updated
+/// from several threads at once.
+pub struct Recorder<'a> {
+    storage: &'a dyn Storage,
+    operation: Mutex<Operation>,
+}
+
+impl<'a> Recorder<'a> {
+    /// Records the start of `kind` over the files `names`, all pending.
+    ///
+    /// Fails if another operation is recorded already: it has to be resumed or
+    /// rolled back first.
+    pub fn start(
+        storage: &'a dyn Storage,
+        kind: OperationKind,
+        names: impl IntoIterator<Item = String>,
+    ) -> Result<Self, AppError> {
+        if let Some(operation) = Operation::load(storage)? {
+            return Err(AppError::Interrupted(operation.kind.to_string()));
+        }
+        let operation = Operation {
+            kind,
+            started_at: Utc::now(),
+            files: names
+                .into_iter()
+                .map(|name| FileRecord {
+                    name,
+                    status: FileStatus::Pending,
+                })
+                .collect(),
+        };
+        operation.save(storage)?;
+        Ok(Self {
+            storage,
+            operation: Mutex::new(operation),
+        })
+    }
+
+    /// Takes over the record of an interrupted `operation`, to finish or undo it.
+    pub fn resume(storage: &'a dyn Storage, operation: Operation) -> Self {
+        Self {
+            storage,
+            operation: Mutex::new(operation),
+        }
+    }
+
+    /// Records that the file `name` reached `status`.
+    pub fn set(&self, name: &str, status: FileStatus) -> Result<(), AppError> {
+        // A thread that panicked mid-update leaves the record as it was
+        let mut operation = self
+            .operation
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(file) = operation.files.iter_mut().find(|file| file.name == name) {
+            file.status = status;
+        }
+        operation.save(self.storage)
+    }
+
+    /// Removes the record: the operation is over.
+    pub fn finish(self) -> Result<(), AppError> {
+        self.storage.delete(RECOVERY_FILE_NAME)
+    }
+}
+
+#[cfg(test)]
+mod recovery_test;
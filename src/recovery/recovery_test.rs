@@ -0,0 +1,34 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+
+#[test]
+fn test_progress_is_recorded_until_the_operation_finishes() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    let names = ["a.txt", "b.txt"].map(String::from);
+
+    let recorder = Recorder::start(&storage, OperationKind::EncryptUnencrypted, names.clone())?;
+    recorder.set(
+        "a.txt",
+        FileStatus::Done {
+            uuid: "1234".to_string(),
+        },
+    )?;
+    let recorded = Operation::load(&storage)?;
+    assert!(
+        recorded
+            .as_ref()
+            .is_some_and(|operation| operation.done() == 1
+                && operation.files[1].status == FileStatus::Pending),
+        "{recorded:?}"
+    );
+    assert!(matches!(
+        Recorder::start(&storage, OperationKind::EncryptUnencrypted, names.clone()),
+        Err(AppError::Interrupted(_))
+    ));
+
+    recorder.finish()?;
+    assert_eq!(Operation::load(&storage)?, None);
+    Recorder::start(&storage, OperationKind::EncryptUnencrypted, names)?.finish()
+}
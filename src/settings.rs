@@ -0,0 +1,173 @@
+//! Effective settings, merged from command-line flags, environment variables,
+//! the config file and built-in defaults, in that order of precedence.
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::i18n;
+use crate::t;
+use std::{fmt, path::PathBuf};
+
+pub const CONFIG_ENV: &str = "RYOKAN_CONFIG";
+pub const NOTES_DIR_ENV: &str = "RYOKAN_NOTES_DIR";
+pub const EDITOR_ENV: &str = "RYOKAN_EDITOR";
+pub const PROFILE_ENV: &str = "RYOKAN_PROFILE";
+
+/// The editor used when nothing else is configured.
+pub const DEFAULT_EDITOR: &str = if cfg!(windows) { "notepad.exe" } else { "nano" };
+
+/// Where an effective setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env(&'static str),
+    ConfigFile,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Flag => write!(f, "{}", t!("source-flag")),
+            Source::Env(name) => write!(f, "{}", t!("source-env", name = name)),
+            Source::ConfigFile => write!(f, "{}", t!("source-config-file")),
+            Source::Default => write!(f, "{}", t!("source-default")),
+        }
+    }
+}
+
+/// A setting value together with its origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+/// Values given explicitly on the command line.
+#[derive(Debug, Default, Clone)]
+pub struct Flags {
+    pub config_file: Option<PathBuf>,
+    pub notes_dir: Option<PathBuf>,
+    pub editor: Option<String>,
+    pub profile: Option<String>,
+    /// `--read-only`, which can only turn read-only mode on.
+    pub read_only_mode: bool,
+}
+
+/// Looks up an environment variable; injectable so tests don't touch the real environment.
+pub type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Reads the process environment, treating empty variables as unset.
+pub fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Resolves the config file location, which has to be known before the config is read.
+pub fn config_file(flags: &Flags, env: EnvLookup) -> Result<Setting<PathBuf>, AppError> {
+    if let Some(path) = &flags.config_file {
+        return Ok(Setting::new(path.clone(), Source::Flag));
+    }
+    if let Some(path) = env(CONFIG_ENV) {
+        return Ok(Setting::new(PathBuf::from(path), Source::Env(CONFIG_ENV)));
+    }
+    Ok(Setting::new(
+        Config::default_config_file_path()?,
+        Source::Default,
+    ))
+}
+
+/// Resolves the profile to use, if any. Needed before the config is resolved further.
+pub fn profile(flags: &Flags, env: EnvLookup) -> Option<Setting<String>> {
+    if let Some(profile) = &flags.profile {
+        return Some(Setting::new(profile.clone(), Source::Flag));
+    }
+    env(PROFILE_ENV).map(|profile| Setting::new(profile, Source::Env(PROFILE_ENV)))
+}
+
+/// Every effective setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub config_file: Setting<PathBuf>,
+    pub notes_dir: Setting<PathBuf>,
+    pub editor: Setting<String>,
+    pub profile: Option<Setting<String>>,
+    pub read_only_mode: Setting<bool>,
+    /// Code of the language messages are shown in, one of
+    /// [`i18n::LANGUAGES`].
+    pub language: Setting<&'static str>,
+}
+
+impl Settings {
+    /// Merges `flags`, the environment and the loaded `config`.
+    ///
+    /// The profile has to be selected on `config` beforehand for its notes directory
+    /// to be picked up.
+    pub fn resolve(flags: &Flags, env: EnvLookup, config: &Config) -> Result<Settings, AppError> {
+        let config_file = config_file(flags, env)?;
+
+        let notes_dir = if let Some(dir) = &flags.notes_dir {
+            Setting::new(dir.clone(), Source::Flag)
+        } else if let Some(dir) = env(NOTES_DIR_ENV) {
+            Setting::new(PathBuf::from(dir), Source::Env(NOTES_DIR_ENV))
+        } else {
+            Setting::new(config.notes_dir_path().to_path_buf(), Source::ConfigFile)
+        };
+
+        // The generic EDITOR comes after the config file: it's set for every program,
+        // while the config is specific to Ryokan
+        let editor = if let Some(editor) = &flags.editor {
+            Setting::new(editor.clone(), Source::Flag)
+        } else if let Some(editor) = env(EDITOR_ENV) {
+            Setting::new(editor, Source::Env(EDITOR_ENV))
+        } else if let Some(editor) = config.editor.as_ref().filter(|e| !e.trim().is_empty()) {
+            Setting::new(editor.clone(), Source::ConfigFile)
+        } else if let Some(editor) = env("EDITOR") {
+            Setting::new(editor, Source::Env("EDITOR"))
+        } else {
+            Setting::new(DEFAULT_EDITOR.to_string(), Source::Default)
+        };
+
+        let read_only_mode = if flags.read_only_mode {
+            Setting::new(true, Source::Flag)
+        } else if config.read_only_mode {
+            Setting::new(true, Source::ConfigFile)
+        } else {
+            Setting::new(false, Source::Default)
+        };
+
+        Ok(Settings {
+            config_file,
+            notes_dir,
+            editor,
+            profile: profile(flags, env),
+            read_only_mode,
+            language: language(env, config),
+        })
+    }
+}
+
+/// The language of messages: the one in `config`, otherwise the first of
+/// [`i18n::LANGUAGE_VARS`] that's set. One without a catalog gives English.
+fn language(env: EnvLookup, config: &Config) -> Setting<&'static str> {
+    let asked = if let Some(language) = config.language.as_ref().filter(|l| !l.is_empty()) {
+        Some((language.clone(), Source::ConfigFile))
+    } else {
+        i18n::LANGUAGE_VARS.into_iter().find_map(|name| {
+            env(name)
+                .filter(|value| !value.is_empty())
+                .map(|value| (value, Source::Env(name)))
+        })
+    };
+    match asked.and_then(|(language, source)| Some((i18n::supported(&language)?, source))) {
+        Some((language, source)) => Setting::new(language, source),
+        None => Setting::new(i18n::DEFAULT_LANGUAGE, Source::Default),
+    }
+}
+
+#[cfg(test)]
+mod settings_test;
@@ -0,0 +1,168 @@
+#![cfg(test)]
+
+use super::*;
+use std::collections::HashMap;
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config.notes_dir = "/config/notes".to_string();
+    config
+}
+
+fn resolve(flags: &Flags, vars: &[(&str, &str)]) -> Result<Settings, AppError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Settings::resolve(flags, &|name| vars.get(name).cloned(), &config())
+}
+
+#[test]
+fn test_flag_beats_env() -> Result<(), AppError> {
+    let flags = Flags {
+        config_file: Some("/flag/ryokan.toml".into()),
+        notes_dir: Some("/flag/notes".into()),
+        editor: Some("vim".to_string()),
+        profile: Some("work".to_string()),
+        read_only_mode: true,
+    };
+    let settings = resolve(
+        &flags,
+        &[
+            (CONFIG_ENV, "/env/ryokan.toml"),
+            (NOTES_DIR_ENV, "/env/notes"),
+            (EDITOR_ENV, "hx"),
+            (PROFILE_ENV, "home"),
+        ],
+    )?;
+
+    assert_eq!(
+        settings.config_file,
+        Setting::new("/flag/ryokan.toml".into(), Source::Flag)
+    );
+    assert_eq!(
+        settings.profile,
+        Some(Setting::new("work".to_string(), Source::Flag))
+    );
+    assert_eq!(
+        settings.notes_dir,
+        Setting::new("/flag/notes".into(), Source::Flag)
+    );
+    assert_eq!(
+        settings.editor,
+        Setting::new("vim".to_string(), Source::Flag)
+    );
+    assert_eq!(settings.read_only_mode, Setting::new(true, Source::Flag));
+    Ok(())
+}
+
+#[test]
+fn test_env_beats_config() -> Result<(), AppError> {
+    let settings = resolve(
+        &Flags::default(),
+        &[
+            (CONFIG_ENV, "/env/ryokan.toml"),
+            (NOTES_DIR_ENV, "/env/notes"),
+            (EDITOR_ENV, "hx"),
+            ("EDITOR", "emacs"),
+            (PROFILE_ENV, "home"),
+        ],
+    )?;
+
+    assert_eq!(
+        settings.profile,
+        Some(Setting::new("home".to_string(), Source::Env(PROFILE_ENV)))
+    );
+    assert_eq!(
+        settings.config_file,
+        Setting::new("/env/ryokan.toml".into(), Source::Env(CONFIG_ENV))
+    );
+    assert_eq!(
+        settings.notes_dir,
+        Setting::new("/env/notes".into(), Source::Env(NOTES_DIR_ENV))
+    );
+    assert_eq!(
+        settings.editor,
+        Setting::new("hx".to_string(), Source::Env(EDITOR_ENV))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_config_beats_default() -> Result<(), AppError> {
+    let settings = resolve(&Flags::default(), &[])?;
+
+    assert_eq!(settings.config_file.source, Source::Default);
+    assert_eq!(settings.profile, None);
+    assert_eq!(
+        settings.notes_dir,
+        Setting::new("/config/notes".into(), Source::ConfigFile)
+    );
+    assert_eq!(
+        settings.editor,
+        Setting::new(DEFAULT_EDITOR.to_string(), Source::Default)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_generic_editor_env_is_last_resort() -> Result<(), AppError> {
+    let settings = resolve(&Flags::default(), &[("EDITOR", "emacs")])?;
+    assert_eq!(
+        settings.editor,
+        Setting::new("emacs".to_string(), Source::Env("EDITOR"))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_config_editor_beats_generic_editor_env() -> Result<(), AppError> {
+    let mut config = config();
+    config.editor = Some("hx".to_string());
+
+    let settings = Settings::resolve(
+        &Flags::default(),
+        &|name| (name == "EDITOR").then(|| "emacs".to_string()),
+        &config,
+    )?;
+    assert_eq!(
+        settings.editor,
+        Setting::new("hx".to_string(), Source::ConfigFile)
+    );
+
+    let settings = Settings::resolve(
+        &Flags::default(),
+        &|name| (name == EDITOR_ENV).then(|| "vim".to_string()),
+        &config,
+    )?;
+    assert_eq!(
+        settings.editor,
+        Setting::new("vim".to_string(), Source::Env(EDITOR_ENV))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_language_comes_from_the_config_then_the_locale() -> Result<(), AppError> {
+    let settings = resolve(&Flags::default(), &[("LANG", "de_DE.UTF-8")])?;
+    assert_eq!(settings.language, Setting::new("de", Source::Env("LANG")));
+
+    let settings = resolve(
+        &Flags::default(),
+        &[("LC_ALL", "C"), ("LC_MESSAGES", "de_AT"), ("LANG", "de_DE")],
+    )?;
+    assert_eq!(settings.language, Setting::new("en", Source::Env("LC_ALL")));
+
+    let settings = resolve(&Flags::default(), &[("LANG", "ja_JP.UTF-8")])?;
+    assert_eq!(settings.language, Setting::new("en", Source::Default));
+
+    let mut config = config();
+    config.language = Some("de".to_string());
+    let settings = Settings::resolve(
+        &Flags::default(),
+        &|name| (name == "LANG").then(|| "en_US".to_string()),
+        &config,
+    )?;
+    assert_eq!(settings.language, Setting::new("de", Source::ConfigFile));
+    Ok(())
+}
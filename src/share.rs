@@ -0,0 +1,133 @@
+//! Sharing single notes with people who don't use Ryokan, as [age] files.
+//!
+//! A shared note is its plaintext encrypted to an age X25519 recipient (an
+//! `age1…` public key) or to a passphrase, with scrypt as age does, so anyone
+//! can open it with the `age` tool. Only the content goes in the file; the
+//! title, tags and dates stay in the vault. The plaintext is kept in memory
+//! and never written to disk, in either direction.
+//!
+//! [age]: https://age-encryption.org
+
+use crate::audit::{Event, EventKind};
+use crate::error::AppError;
+use crate::vault::{Note, Vault};
+use age::{Identity, IdentityFile, secrecy::SecretString, x25519};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    iter,
+    path::Path,
+};
+use zeroize::Zeroizing;
+
+/// What a shared note is encrypted to.
+#[derive(Clone)]
+pub enum ShareKey {
+    /// An age public key.
+    Recipient(x25519::Recipient),
+    /// A passphrase, stretched with scrypt.
+    Passphrase(Zeroizing<String>),
+}
+
+/// What an age file is decrypted with.
+pub enum OpenKey {
+    /// The identities of an age identity file, e.g. made by `age-keygen`.
+    Identities(Vec<Box<dyn Identity>>),
+    /// The passphrase the file was encrypted with.
+    Passphrase(Zeroizing<String>),
+}
+
+/// Parses an age public key, such as `age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p`.
+pub fn parse_recipient(key: &str) -> Result<x25519::Recipient, AppError> {
+    key.trim().parse().map_err(|e| {
+        AppError::Age(format!(
+            "\"{key}\" is not an age public key ({e}), expected one starting with age1"
+        ))
+    })
+}
+
+/// Decrypts a note and encrypts it again to `key`, returning the age file.
+pub fn export(vault: &Vault, note: &Note, key: &ShareKey) -> Result<Vec<u8>, AppError> {
+    let content = Zeroizing::new(vault.read(&note.uuid)?);
+    let shared = encrypt(&content, key)?;
+    let detail = match key {
+        ShareKey::Recipient(_) => "age recipient",
+        ShareKey::Passphrase(_) => "age passphrase",
+    };
+    vault.record(Event::new(EventKind::Exported, Some(&note.uuid)).with_detail(detail));
+    Ok(shared)
+}
+
+/// Encrypts `plaintext` into an age file.
+pub fn encrypt(plaintext: &[u8], key: &ShareKey) -> Result<Vec<u8>, AppError> {
+    let encryptor = match key {
+        ShareKey::Recipient(recipient) => {
+            age::Encryptor::with_recipients(iter::once(recipient as &dyn age::Recipient))
+                .map_err(|e| AppError::Age(e.to_string()))?
+        }
+        ShareKey::Passphrase(passphrase) => {
+            age::Encryptor::with_user_passphrase(SecretString::from(passphrase.to_string()))
+        }
+    };
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut encrypted)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+    Ok(encrypted)
+}
+
+/// Whether an age file is encrypted to a passphrase rather than to recipients.
+pub fn needs_passphrase(data: &[u8]) -> Result<bool, AppError> {
+    let decryptor = age::Decryptor::new_buffered(data).map_err(open_error)?;
+    Ok(decryptor.is_scrypt())
+}
+
+/// Reads the identities of an age identity file.
+pub fn read_identities(path: &Path) -> Result<Vec<Box<dyn Identity>>, AppError> {
+    File::open(path)
+        .and_then(|file| IdentityFile::from_buffer(BufReader::new(file)))
+        .map_err(|e| {
+            AppError::Age(format!(
+                "Could not read the identity file {}: {e}",
+                path.display()
+            ))
+        })?
+        .into_identities()
+        .map_err(|e| AppError::Age(e.to_string()))
+}
+
+/// Decrypts an age file.
+pub fn decrypt(data: &[u8], key: &OpenKey) -> Result<Zeroizing<Vec<u8>>, AppError> {
+    let decryptor = age::Decryptor::new_buffered(data).map_err(open_error)?;
+    let passphrase;
+    let identities: Vec<&dyn Identity> = match key {
+        OpenKey::Identities(identities) => identities.iter().map(|i| i.as_ref()).collect(),
+        OpenKey::Passphrase(secret) => {
+            passphrase = age::scrypt::Identity::new(SecretString::from(secret.to_string()));
+            vec![&passphrase]
+        }
+    };
+    let mut reader = decryptor
+        .decrypt(identities.into_iter())
+        .map_err(open_error)?;
+    let mut plaintext = Zeroizing::new(Vec::new());
+    reader
+        .read_to_end(&mut plaintext)
+        .map_err(|_| AppError::Age("The age file is corrupted".to_string()))?;
+    Ok(plaintext)
+}
+
+fn open_error(e: age::DecryptError) -> AppError {
+    match e {
+        age::DecryptError::NoMatchingKeys => {
+            AppError::Age("No identity matches the age file".to_string())
+        }
+        age::DecryptError::DecryptionFailed | age::DecryptError::KeyDecryptionFailed => {
+            AppError::Age("Wrong passphrase, or the age file is corrupted".to_string())
+        }
+        e => AppError::Age(format!("Not a valid age file: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod share_test;
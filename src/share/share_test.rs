@@ -0,0 +1,65 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+
+fn vault() -> Vault {
+    Vault::with_storage(MemoryStorage::new(), Zeroizing::new("123456".to_string()))
+}
+
+#[test]
+fn test_shared_note_opens_with_the_recipients_identity() -> Result<(), AppError> {
+    let vault = vault();
+    let note = vault.create("Meeting", b"Agenda: budget")?;
+    let identity = x25519::Identity::generate();
+    let recipient = parse_recipient(&identity.to_public().to_string())?;
+
+    let shared = export(&vault, &note, &ShareKey::Recipient(recipient))?;
+    assert!(!shared.windows(6).any(|w| w == b"Agenda"));
+    assert!(!needs_passphrase(&shared)?);
+
+    let opened = decrypt(&shared, &OpenKey::Identities(vec![Box::new(identity)]))?;
+    assert_eq!(opened.as_slice(), b"Agenda: budget");
+
+    let stranger = x25519::Identity::generate();
+    assert!(matches!(
+        decrypt(&shared, &OpenKey::Identities(vec![Box::new(stranger)])),
+        Err(AppError::Age(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_passphrase_round_trip() -> Result<(), AppError> {
+    let key = ShareKey::Passphrase(Zeroizing::new("correct horse".to_string()));
+    let shared = encrypt(b"secret", &key)?;
+    assert!(needs_passphrase(&shared)?);
+
+    let opened = decrypt(
+        &shared,
+        &OpenKey::Passphrase(Zeroizing::new("correct horse".to_string())),
+    )?;
+    assert_eq!(opened.as_slice(), b"secret");
+    assert!(
+        decrypt(
+            &shared,
+            &OpenKey::Passphrase(Zeroizing::new("wrong".to_string()))
+        )
+        .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_recipients_and_files_are_rejected() {
+    for key in ["", "age1", "not-a-key", "AGE-SECRET-KEY-1QQQ"] {
+        assert!(
+            matches!(parse_recipient(key), Err(AppError::Age(_))),
+            "{key:?} was accepted"
+        );
+    }
+    assert!(matches!(
+        needs_passphrase(b"plain text"),
+        Err(AppError::Age(_))
+    ));
+}
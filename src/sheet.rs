@@ -0,0 +1,361 @@
+//! The metadata of notes as CSV, to edit titles and tags in bulk in a
+//! spreadsheet and apply the result back with `meta import`.
+//!
+//! Every note is a row of [`COLUMNS`], tags joined with `;`. Reading a sheet
+//! back only plans the changes: each row is checked against the note it names
+//! and compared with it, so the changes can be shown and confirmed before
+//! [`apply`] writes any. The UUID identifies the note and the creation and
+//! update times are only there to read; changing them is refused. Columns a
+//! spreadsheet adds are ignored, and columns left out are left alone.
+
+use crate::{
+    due,
+    error::AppError,
+    metadata::{self, NoteMetadata, NoteType},
+    tags,
+    vault::{Note, Vault},
+};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+/// The columns of a sheet, in order.
+pub const COLUMNS: [&str; 10] = [
+    "uuid",
+    "title",
+    "tags",
+    "created",
+    "updated",
+    "due",
+    "color",
+    "read_only",
+    "slot",
+    "type",
+];
+
+/// Joins the tags of a note in a single cell; tags can't contain it.
+const TAG_SEPARATOR: char = ';';
+
+/// Writes the metadata of `notes` as CSV, with a header row.
+pub fn write(notes: &[Note], out: impl io::Write) -> Result<(), AppError> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(COLUMNS).map_err(csv_error)?;
+    for note in notes {
+        writer
+            .write_record(COLUMNS.map(|column| cell(note, column)))
+            .map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The value of `column` for `note`, as written in a sheet.
+fn cell(note: &Note, column: &str) -> String {
+    let metadata = &note.metadata;
+    match column {
+        "uuid" => note.uuid.clone(),
+        "title" => metadata.original_filename.clone(),
+        "tags" => metadata.tags.join(&TAG_SEPARATOR.to_string()),
+        "created" => timestamp(&metadata.created_at),
+        "updated" => timestamp(&metadata.updated_at),
+        "due" => metadata
+            .due_at
+            .map(|due_at| due::format_due_date(&due_at, &Local))
+            .unwrap_or_default(),
+        "color" => metadata.color.clone().unwrap_or_default(),
+        "read_only" => metadata.read_only.to_string(),
+        "slot" => metadata
+            .slot
+            .map(|slot| slot.to_string())
+            .unwrap_or_default(),
+        "type" => metadata.note_type.clone().into(),
+        _ => String::new(),
+    }
+}
+
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn csv_error(e: csv::Error) -> AppError {
+    AppError::Import(format!("CSV: {e}"))
+}
+
+/// A column whose value changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub column: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+/// The changes a row makes to a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowChange {
+    pub uuid: String,
+    /// The title of the note before the change.
+    pub title: String,
+    pub fields: Vec<FieldChange>,
+    /// The metadata of the note with the changes made.
+    metadata: NoteMetadata,
+}
+
+/// What applying a sheet would change.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub changes: Vec<RowChange>,
+    /// Rows that change nothing.
+    pub unchanged: usize,
+    /// Columns of the sheet that aren't in [`COLUMNS`], as named in the sheet.
+    pub ignored_columns: Vec<String>,
+}
+
+/// Reads a sheet and compares each row with the note of its UUID among
+/// `notes`, without changing anything.
+///
+/// Fails on the first row naming an unknown or already named note, giving
+/// another creation or update time, or holding a value that isn't valid for
+/// its column, telling which row it is as a spreadsheet numbers them.
+pub fn read(input: impl io::Read, notes: &[Note]) -> Result<Plan, AppError> {
+    let mut reader = csv::Reader::from_reader(input);
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let mut plan = Plan::default();
+    let mut columns: Vec<Option<&'static str>> = Vec::new();
+    for header in &headers {
+        // Excel starts UTF-8 files with a byte order mark
+        let name = header.trim_start_matches('\u{feff}').trim();
+        let known = COLUMNS
+            .into_iter()
+            .find(|column| column.eq_ignore_ascii_case(name));
+        if known.is_some() && columns.contains(&known) {
+            return Err(AppError::Import(format!("The column {name} appears twice")));
+        }
+        if known.is_none() {
+            plan.ignored_columns.push(name.to_string());
+        }
+        columns.push(known);
+    }
+    if !columns.contains(&Some("uuid")) {
+        return Err(AppError::Import(
+            "The sheet has no uuid column to tell which note each row is".to_string(),
+        ));
+    }
+
+    let by_uuid: HashMap<&str, &Note> = notes
+        .iter()
+        .map(|note| (note.uuid.as_str(), note))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut slots: HashMap<u8, String> = HashMap::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        // The header is row 1
+        let row = i + 2;
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        let values: Vec<(&str, &str)> = columns
+            .iter()
+            .zip(&record)
+            .filter_map(|(column, value)| Some(((*column)?, value)))
+            .collect();
+        let uuid = values
+            .iter()
+            .find(|(column, _)| *column == "uuid")
+            .map_or("", |(_, uuid)| uuid.trim());
+        let note = by_uuid.get(uuid).ok_or_else(|| {
+            AppError::Import(format!("Row {row}: no note has the UUID \"{uuid}\""))
+        })?;
+        if !seen.insert(uuid.to_string()) {
+            return Err(AppError::Import(format!(
+                "Row {row}: the note {uuid} is in an earlier row already"
+            )));
+        }
+        let change = compare(note, &values)
+            .map_err(|e| AppError::Import(format!("Row {row}: {}", message(e))))?;
+        let slot = change
+            .as_ref()
+            .map_or(note.metadata.slot, |change| change.metadata.slot);
+        if let Some(slot) = slot
+            && let Some(other) = slots.insert(slot, uuid.to_string())
+        {
+            return Err(AppError::Import(format!(
+                "Row {row}: slot {slot} is given to the note {other} too"
+            )));
+        }
+        match change {
+            Some(change) => plan.changes.push(change),
+            None => plan.unchanged += 1,
+        }
+    }
+    Ok(plan)
+}
+
+/// The text of an error about a value, without the prefix of its variant.
+fn message(e: AppError) -> String {
+    match e {
+        AppError::Config(message) | AppError::Import(message) => message,
+        e => e.to_string(),
+    }
+}
+
+/// What the cells in `values` change in `note`, if anything.
+fn compare(note: &Note, values: &[(&str, &str)]) -> Result<Option<RowChange>, AppError> {
+    let mut metadata = note.metadata.clone();
+    for (column, value) in values {
+        let value = value.trim();
+        match *column {
+            "uuid" => {}
+            "created" | "updated" => {
+                let given = DateTime::parse_from_rfc3339(value).ok();
+                let at = if *column == "created" {
+                    metadata.created_at
+                } else {
+                    metadata.updated_at
+                };
+                if given.is_none_or(|given| given.timestamp() != at.timestamp()) {
+                    return Err(AppError::Import(format!(
+                        "the {column} time can't be changed, it's {}",
+                        timestamp(&at)
+                    )));
+                }
+            }
+            "title" => {
+                if value.is_empty() {
+                    return Err(AppError::Import("the title can't be empty".to_string()));
+                }
+                if value != metadata.original_filename {
+                    metadata.original_filename = value.to_string();
+                    metadata.long_title = None;
+                }
+            }
+            "tags" => {
+                let mut tags: Vec<String> = Vec::new();
+                for tag in value
+                    .split(TAG_SEPARATOR)
+                    .map(|tag| tag.trim().trim_start_matches('#'))
+                    .filter(|tag| !tag.is_empty())
+                {
+                    tags::validate(tag)?;
+                    if !tags.iter().any(|existing| existing == tag) {
+                        tags.push(tag.to_string());
+                    }
+                }
+                if tags.len() > metadata::MAX_TAGS {
+                    return Err(AppError::Import(format!(
+                        "a note can't have more than {} tags",
+                        metadata::MAX_TAGS
+                    )));
+                }
+                metadata.tags = tags;
+            }
+            "due" => {
+                metadata.due_at = match value {
+                    "" => None,
+                    value => Some(due::parse_due_date(value, &Local::now())?),
+                };
+            }
+            "color" => {
+                metadata.color = match value {
+                    "" => None,
+                    value => metadata::parse_color(value)?,
+                };
+            }
+            "read_only" => {
+                metadata.read_only = match value.to_ascii_lowercase().as_str() {
+                    "true" | "1" => true,
+                    "false" | "0" | "" => false,
+                    _ => {
+                        return Err(AppError::Import(format!(
+                            "expected true or false for read_only, got \"{value}\""
+                        )));
+                    }
+                };
+            }
+            "slot" => {
+                metadata.slot = match value.parse() {
+                    _ if value.is_empty() => None,
+                    Ok(slot @ 1..=9) => Some(slot),
+                    _ => {
+                        return Err(AppError::Import(format!(
+                            "expected a slot from 1 to 9, or nothing, got \"{value}\""
+                        )));
+                    }
+                };
+            }
+            // An unknown type is kept as long as it's left alone
+            "type" if value != String::from(metadata.note_type.clone()) => {
+                metadata.note_type = match value {
+                    "" => NoteType::Plain,
+                    value => NoteType::parse(value)?,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let changed = Note {
+        uuid: note.uuid.clone(),
+        metadata,
+    };
+    // Compared as written, so e.g. a due date is the same day in both
+    let fields: Vec<_> = COLUMNS
+        .into_iter()
+        .map(|column| FieldChange {
+            column,
+            before: cell(note, column),
+            after: cell(&changed, column),
+        })
+        .filter(|field| field.before != field.after)
+        .collect();
+    Ok((!fields.is_empty()).then(|| RowChange {
+        uuid: note.uuid.clone(),
+        title: note.metadata.original_filename.clone(),
+        fields,
+        metadata: changed.metadata,
+    }))
+}
+
+/// What [`apply`] did, by note UUID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Applied {
+    pub changed: Vec<String>,
+    /// Notes left as they were because they're read-only and the sheet doesn't
+    /// clear their read-only flag.
+    pub skipped_read_only: Vec<String>,
+}
+
+/// Saves the changes of `plan`. A read-only note is only changed by a row that
+/// clears its read-only flag.
+pub fn apply(vault: &Vault, plan: &Plan) -> Result<Applied, AppError> {
+    let _lock = vault.lock()?;
+    let mut applied = Applied::default();
+    for change in &plan.changes {
+        let after = &change.metadata;
+        if vault.get(&change.uuid)?.metadata.read_only && after.read_only {
+            applied.skipped_read_only.push(change.uuid.clone());
+            continue;
+        }
+        vault.metadata_mut(&change.uuid, |metadata| {
+            if metadata.original_filename != after.original_filename {
+                metadata.original_filename = after.original_filename.clone();
+                metadata.long_title = None;
+            }
+            metadata.tags = after.tags.clone();
+            metadata.due_at = after.due_at;
+            metadata.color = after.color.clone();
+            metadata.read_only = after.read_only;
+            metadata.note_type = after.note_type.clone();
+        })?;
+        if change.fields.iter().any(|field| field.column == "slot") {
+            vault.assign_slot(&change.uuid, after.slot)?;
+        }
+        applied.changed.push(change.uuid.clone());
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod sheet_test;
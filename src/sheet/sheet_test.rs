@@ -0,0 +1,166 @@
+#![cfg(test)]
+
+use super::*;
+
+fn note(uuid: &str, title: &str, tags: &[&str]) -> Note {
+    let mut metadata = NoteMetadata::new(title);
+    metadata.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    Note {
+        uuid: uuid.to_string(),
+        metadata,
+    }
+}
+
+fn written(notes: &[Note]) -> Result<String, AppError> {
+    let mut out = Vec::new();
+    write(notes, &mut out)?;
+    String::from_utf8(out).map_err(|e| AppError::Import(e.to_string()))
+}
+
+#[test]
+fn test_written_sheets_read_back_unchanged() -> Result<(), AppError> {
+    let notes = [
+        note(
+            "a",
+            "Milk, eggs and \"bread\"",
+            &["shopping", "home/kitchen"],
+        ),
+        note("b", "Two\nlines", &[]),
+    ];
+    let sheet = written(&notes)?;
+    assert!(sheet.starts_with("uuid,title,tags,created,updated,due,color,read_only,slot,type\n"));
+    assert!(sheet.contains("\"Milk, eggs and \"\"bread\"\"\",shopping;home/kitchen,"));
+
+    let plan = read(sheet.as_bytes(), &notes)?;
+    assert!(plan.changes.is_empty());
+    assert_eq!(plan.unchanged, 2);
+    Ok(())
+}
+
+#[test]
+fn test_changed_cells_are_planned_and_unknown_columns_ignored() -> Result<(), AppError> {
+    let notes = [
+        note("a", "Groceries", &["shopping"]),
+        note("b", "Plans", &[]),
+    ];
+    let created = timestamp(&notes[0].metadata.created_at);
+    let updated = timestamp(&notes[0].metadata.updated_at);
+    let sheet = format!(
+        "\u{feff}uuid,Title,tags,created,updated,Notes\n\
+         a,\"Groceries, weekly\",shopping; #food,{created},{updated},checked\n\
+         b,Plans,,,,\n"
+    );
+    // The times of b are left blank, which is refused
+    assert!(matches!(
+        read(sheet.as_bytes(), &notes),
+        Err(AppError::Import(message)) if message.starts_with("Row 3: the created time")
+    ));
+
+    let sheet = sheet.replace("b,Plans,,,,\n", "");
+    let plan = read(sheet.as_bytes(), &notes)?;
+    assert_eq!(plan.ignored_columns, ["Notes"]);
+    let [change] = &plan.changes[..] else {
+        return Err(AppError::Import(format!("{:?}", plan.changes)));
+    };
+    assert_eq!(change.title, "Groceries");
+    assert_eq!(
+        change.fields,
+        [
+            FieldChange {
+                column: "title",
+                before: "Groceries".to_string(),
+                after: "Groceries, weekly".to_string(),
+            },
+            FieldChange {
+                column: "tags",
+                before: "shopping".to_string(),
+                after: "shopping;food".to_string(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_rows_are_refused() {
+    let notes = [note("a", "Groceries", &[]), note("b", "Plans", &[])];
+    for (sheet, expected) in [
+        ("title\nGroceries\n", "The sheet has no uuid column"),
+        ("uuid,title\nc,Other\n", "Row 2: no note has the UUID \"c\""),
+        (
+            "uuid,title\na,One\na,Two\n",
+            "Row 3: the note a is in an earlier row",
+        ),
+        ("uuid,color\na,teal\n", "Row 2: Unknown color \"teal\""),
+        (
+            "uuid,slot\na,1\nb,1\n",
+            "Row 3: slot 1 is given to the note a too",
+        ),
+        ("uuid,tags\na,bad/\n", "Row 2: Invalid tag"),
+        (
+            "uuid,type\na,kanban\n",
+            "Row 2: Unknown note type \"kanban\"",
+        ),
+    ] {
+        match read(sheet.as_bytes(), &notes) {
+            Err(AppError::Import(message)) => {
+                assert!(message.starts_with(expected), "{message}")
+            }
+            other => panic!("{sheet:?} gave {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_unknown_types_are_kept_unless_changed() -> Result<(), AppError> {
+    let mut notes = [note("a", "Board", &[]), note("b", "Plans", &[])];
+    notes[0].metadata.note_type = NoteType::from("kanban".to_string());
+    let sheet = written(&notes)?;
+    assert!(sheet.contains(",kanban\n"));
+
+    let plan = read(sheet.as_bytes(), &notes)?;
+    assert_eq!(plan.unchanged, 2);
+
+    let sheet = "uuid,type\na,kanban\nb,Todo\n";
+    let plan = read(sheet.as_bytes(), &notes)?;
+    let [change] = &plan.changes[..] else {
+        return Err(AppError::Import(format!("{:?}", plan.changes)));
+    };
+    assert_eq!(change.uuid, "b");
+    assert_eq!(change.metadata.note_type, NoteType::Todo);
+    Ok(())
+}
+
+#[test]
+fn test_apply_leaves_read_only_notes_alone() -> Result<(), AppError> {
+    let vault = Vault::with_storage(
+        crate::storage::MemoryStorage::new(),
+        zeroize::Zeroizing::new("123456".to_string()),
+    );
+    let plain = vault.create("Plain", b"")?;
+    let locked = vault.create("Locked", b"")?;
+    let unlocked = vault.create("Unlocked", b"")?;
+    for note in [&locked, &unlocked] {
+        vault.metadata_mut(&note.uuid, |metadata| metadata.read_only = true)?;
+    }
+    let locked_before = vault.get(&locked.uuid)?.metadata;
+    let sheet = format!(
+        "uuid,title,read_only\n{},Plain 2,false\n{},Locked 2,true\n{},Unlocked 2,false\n",
+        plain.uuid, locked.uuid, unlocked.uuid
+    );
+
+    let plan = read(sheet.as_bytes(), &vault.list()?)?;
+    let applied = apply(&vault, &plan)?;
+
+    assert_eq!(applied.skipped_read_only, [locked.uuid.as_str()]);
+    assert_eq!(vault.get(&locked.uuid)?.metadata, locked_before);
+    assert_eq!(
+        vault.get(&plain.uuid)?.metadata.original_filename,
+        "Plain 2"
+    );
+    // Clearing the flag is how a read-only note is changed
+    let unlocked = vault.get(&unlocked.uuid)?.metadata;
+    assert_eq!(unlocked.original_filename, "Unlocked 2");
+    assert!(!unlocked.read_only);
+    Ok(())
+}
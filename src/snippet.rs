@@ -0,0 +1,101 @@
+//! Excerpts of notes, shown under their titles in the TUI list so it tells more
+//! than titles without decrypting any note while scrolling.
+//!
+//! With `list_snippets` on, the [`Vault`](crate::vault::Vault) takes a
+//! [`Snippet`] of a note every time it saves it and keeps it in its metadata,
+//! sealed with the key of the integrity manifest: it's encrypted with the PIN
+//! like the note itself, and listing decrypts every snippet with a single
+//! Argon2 run. The snippet holds the BLAKE3 hash of the content it was taken
+//! from, sealed as well, so a note replaced by another program, e.g. a sync,
+//! gets a new snippet the next time it's opened, see
+//! [`Vault::refresh_snippet`](crate::vault::Vault::refresh_snippet).
+
+use crate::audit::{from_hex, to_hex};
+use crate::error::AppError;
+use crate::note::NoteKey;
+use crate::text;
+use serde::{Deserialize, Serialize};
+
+/// How long a snippet is at most, in characters.
+pub const SNIPPET_LENGTH: usize = 200;
+
+/// An excerpt of a note with the hash of the content it was taken from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    /// Hex BLAKE3 hash of the decrypted content.
+    content_hash: String,
+}
+
+impl Snippet {
+    /// Takes a snippet of the note `title` with `content`, or `None` if the
+    /// content isn't text.
+    pub fn new(title: &str, content: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(content).ok()?;
+        Some(Self {
+            text: excerpt(title, text),
+            content_hash: hash(content),
+        })
+    }
+
+    /// Whether it was taken from `content`.
+    pub fn is_of(&self, content: &[u8]) -> bool {
+        self.content_hash == hash(content)
+    }
+
+    /// Encrypts it with `key`, as hex to keep in the metadata.
+    pub fn seal(&self, key: &NoteKey) -> Result<String, AppError> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| AppError::Config(format!("Could not serialize the snippet: {e}")))?;
+        Ok(to_hex(&key.encrypt(&json)?))
+    }
+
+    /// Decrypts a snippet sealed with `key`.
+    pub fn open(sealed: &str, key: &NoteKey) -> Result<Self, AppError> {
+        let sealed = from_hex(sealed)
+            .ok_or_else(|| AppError::Decryption("The snippet isn't valid hex".to_string()))?;
+        let json = key.decrypt(&sealed)?;
+        serde_json::from_slice(&json)
+            .map_err(|e| AppError::Decryption(format!("The snippet can't be parsed: {e}")))
+    }
+}
+
+/// The text of the note from its first non-empty line on, skipping a first line
+/// that repeats the title, e.g. as a Markdown heading, on one line and cut to
+/// [`SNIPPET_LENGTH`] characters.
+///
+/// ```
+/// use ryokan::snippet::excerpt;
+///
+/// let content = "\n# Groceries\n\n- milk\n- eggs\n";
+/// assert_eq!(excerpt("Groceries", content), "- milk - eggs");
+/// ```
+pub fn excerpt(title: &str, content: &str) -> String {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .peekable();
+    lines.next_if(|line| line.trim_start_matches('#').trim() == title.trim());
+    let mut excerpt = String::new();
+    for word in lines.flat_map(str::split_whitespace) {
+        // Enough for the longest snippet, whatever the characters
+        if excerpt.len() > SNIPPET_LENGTH * 4 {
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+    let cut = text::first_graphemes(&excerpt, SNIPPET_LENGTH).len();
+    excerpt.truncate(cut);
+    excerpt
+}
+
+fn hash(content: &[u8]) -> String {
+    blake3::hash(content).to_hex().to_string()
+}
+
+#[cfg(test)]
+mod snippet_test;
@@ -0,0 +1,37 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_the_excerpt_starts_after_the_title_on_one_line() {
+    assert_eq!(
+        excerpt("Trip", "Trip\nTickets  for\n\n  Kyoto\n"),
+        "Tickets for Kyoto"
+    );
+    // Only a first line repeating the title is skipped
+    assert_eq!(excerpt("Trip", "Tickets\nTrip"), "Tickets Trip");
+    assert_eq!(excerpt("Trip", "\n\n"), "");
+
+    let long = "語 ".repeat(1000);
+    assert_eq!(excerpt("Long", &long).chars().count(), SNIPPET_LENGTH);
+}
+
+#[test]
+fn test_snippets_open_with_the_key_they_were_sealed_with() -> Result<(), AppError> {
+    let key = NoteKey::new("123456")?;
+    let Some(snippet) = Snippet::new("Trip", b"Tickets for Kyoto") else {
+        panic!("no snippet of text");
+    };
+    assert!(Snippet::new("Photo", &[0xff, 0xd8, 0xff]).is_none());
+
+    let sealed = snippet.seal(&key)?;
+    assert!(!sealed.contains("Kyoto"));
+    assert_eq!(Snippet::open(&sealed, &key)?, snippet);
+    assert!(snippet.is_of(b"Tickets for Kyoto"));
+    assert!(!snippet.is_of(b"Tickets for Osaka"));
+
+    let other = NoteKey::new("123456")?;
+    assert!(Snippet::open(&sealed, &other).is_err());
+    assert!(Snippet::open("not hex", &key).is_err());
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Figures about the text of a note: lines, words, reading time and checklist
+//! progress, shown in the TUI and by `show --stats`.
+//!
+//! Words are split by Unicode rules rather than on whitespace, so text in
+//! languages written without spaces isn't counted as a handful of huge words.
+//! There, each CJK character counts as a word, read faster than words of
+//! spaced scripts.
+
+use crate::t;
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Reading speed for scripts that separate words with spaces.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Reading speed for Chinese, Japanese and Korean characters.
+pub const CJK_CHARS_PER_MINUTE: usize = 500;
+
+/// Figures about the text of one note.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NoteStats {
+    pub lines: usize,
+    pub words: usize,
+    /// User-perceived characters, i.e. grapheme clusters.
+    pub characters: usize,
+    /// Estimated time to read the note, rounded up.
+    pub reading_minutes: usize,
+    /// Checked items of Markdown checklists, like `- [x] done`.
+    pub checklist_done: usize,
+    /// Every checklist item, checked or not.
+    pub checklist_total: usize,
+}
+
+impl NoteStats {
+    pub fn of(text: &str) -> Self {
+        let (words, cjk_chars) = count_words(text);
+        let reading_seconds = (words * 60).div_ceil(WORDS_PER_MINUTE)
+            + (cjk_chars * 60).div_ceil(CJK_CHARS_PER_MINUTE);
+        let mut stats = Self {
+            lines: text.lines().count(),
+            words: words + cjk_chars,
+            characters: text.graphemes(true).count(),
+            reading_minutes: reading_seconds.div_ceil(60),
+            ..Self::default()
+        };
+        for (_, checked) in text.lines().filter_map(checklist_item) {
+            stats.checklist_total += 1;
+            stats.checklist_done += usize::from(checked);
+        }
+        stats
+    }
+
+    /// Each figure with its name, the checklist only if there's one.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            (t!("stats-lines"), self.lines.to_string()),
+            (t!("stats-words"), self.words.to_string()),
+            (t!("stats-characters"), self.characters.to_string()),
+            (
+                t!("stats-reading-time"),
+                t!("stats-minutes", minutes = self.reading_minutes),
+            ),
+        ];
+        if self.checklist_total > 0 {
+            fields.push((
+                t!("stats-checklist"),
+                t!(
+                    "stats-checklist-done",
+                    done = self.checklist_done,
+                    total = self.checklist_total
+                ),
+            ));
+        }
+        fields
+    }
+}
+
+/// One line, e.g. `Lines: 3  Words: 12  Characters: 64  Reading time: ~1 min`.
+impl fmt::Display for NoteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.fields().into_iter().enumerate() {
+            if i > 0 {
+                f.write_str("  ")?;
+            }
+            write!(f, "{name}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the words of `text`, each CJK character being one.
+///
+/// ```
+/// assert_eq!(ryokan::stats::word_count("Hello, world! 你好世界"), 6);
+/// ```
+pub fn word_count(text: &str) -> usize {
+    let (words, cjk_chars) = count_words(text);
+    words + cjk_chars
+}
+
+/// Counts the words of `text` written with spaces, and the CJK characters.
+fn count_words(text: &str) -> (usize, usize) {
+    let mut words = 0;
+    let mut cjk_chars = 0;
+    for word in text.unicode_words() {
+        match word.chars().filter(|c| is_cjk(*c)).count() {
+            0 => words += 1,
+            n => cjk_chars += n,
+        }
+    }
+    (words, cjk_chars)
+}
+
+/// Whether `c` is a Han ideograph or kana. Hangul is left out: Korean puts
+/// spaces between words.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+        | '\u{ff66}'..='\u{ff9f}' // Halfwidth Katakana
+        | '\u{20000}'..='\u{3134f}' // Extensions B to G
+    )
+}
+
+/// Whether `line` is a checked checklist item, if it's one at all, with the
+/// byte offset of its box.
+pub fn checklist_item(line: &str) -> Option<(usize, bool)> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let offset = line.len() - rest.len();
+    match rest.get(..3)? {
+        "[ ]" => Some((offset, false)),
+        "[x]" | "[X]" => Some((offset, true)),
+        _ => None,
+    }
+}
+
+/// Stats of notes by UUID, computed again only when the content changes, so
+/// moving through the list doesn't count the same text over and over.
+#[derive(Debug, Default)]
+pub struct StatsCache {
+    entries: HashMap<String, (blake3::Hash, NoteStats)>,
+}
+
+impl StatsCache {
+    /// The stats of `text`, the content of the note `uuid`.
+    pub fn get(&mut self, uuid: &str, text: &str) -> NoteStats {
+        let hash = blake3::hash(text.as_bytes());
+        match self.entries.get(uuid) {
+            Some((cached, stats)) if *cached == hash => *stats,
+            _ => {
+                let stats = NoteStats::of(text);
+                self.entries.insert(uuid.to_string(), (hash, stats));
+                stats
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod stats_test;
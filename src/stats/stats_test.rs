@@ -0,0 +1,54 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_counts_lines_words_and_characters() {
+    let stats = NoteStats::of("Shopping list\n\nmilk, eggs and café\n");
+
+    assert_eq!(stats.lines, 3);
+    assert_eq!(stats.words, 6);
+    assert_eq!(stats.characters, 35);
+    assert_eq!(stats.reading_minutes, 1);
+    assert_eq!(NoteStats::of(""), NoteStats::default());
+}
+
+#[test]
+fn test_cjk_characters_are_words() {
+    // Without spaces, splitting on whitespace would see one word per line
+    let stats = NoteStats::of("今日は東京タワーに行きました。\n楽しかった");
+
+    assert_eq!(stats.words, 19);
+    assert_eq!(stats.characters, 21);
+    assert_eq!(word_count("안녕하세요 세계"), 2);
+}
+
+#[test]
+fn test_reading_time_is_rounded_up() {
+    let words = "word ".repeat(WORDS_PER_MINUTE * 2 + 1);
+    assert_eq!(NoteStats::of(&words).reading_minutes, 3);
+
+    let chars = "字".repeat(CJK_CHARS_PER_MINUTE);
+    assert_eq!(NoteStats::of(&chars).reading_minutes, 1);
+}
+
+#[test]
+fn test_checklist_items_are_counted() {
+    let stats = NoteStats::of(
+        "- [x] milk\n  * [ ] eggs\n+ [X] bread\n- [] not an item\n-[ ] nor this\n1. [ ] nor this\n",
+    );
+
+    assert_eq!((stats.checklist_done, stats.checklist_total), (2, 3));
+    assert!(stats.to_string().ends_with("Checklist: 2/3 done"));
+    assert!(!NoteStats::of("no list").to_string().contains("Checklist"));
+}
+
+#[test]
+fn test_cache_follows_the_content() {
+    let mut cache = StatsCache::default();
+
+    assert_eq!(cache.get("a", "one two").words, 2);
+    assert_eq!(cache.get("a", "one two").words, 2);
+    assert_eq!(cache.get("a", "one two three").words, 3);
+    assert_eq!(cache.get("b", "one").words, 1);
+}
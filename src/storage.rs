@@ -0,0 +1,462 @@
+//! Where the bytes of a vault live.
+//!
+//! The [`Vault`](crate::vault::Vault) only talks to a [`Storage`], so the same
+//! logic runs against a real directory ([`FsStorage`]) or against memory
+//! ([`MemoryStorage`]), which keeps tests fast and leaves room for other backends.
+//!
+//! Entries are addressed by names relative to the root of the storage, using `/`
+//! as the separator for nested directories.
+
+use crate::error::AppError;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+/// A file found while listing a directory of a [`Storage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The name of the file within the listed directory.
+    pub name: String,
+    /// Size in bytes.
+    pub len: u64,
+    /// Last modification time, when the backend knows it.
+    pub modified: Option<SystemTime>,
+    /// Creation time, when the backend and platform know it.
+    pub created: Option<SystemTime>,
+}
+
+/// A flat key-value store of files.
+pub trait Storage: Send + Sync {
+    /// Lists the files (not subdirectories) directly inside `dir`; `""` is the root.
+    /// A missing directory lists as empty.
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError>;
+    /// Lists the names of the subdirectories directly inside `dir`. A missing
+    /// directory lists as empty.
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError>;
+    /// Describes the file `name` like [`Storage::list`] would.
+    fn entry(&self, name: &str) -> Result<Entry, AppError> {
+        let (dir, file_name) = name.rsplit_once('/').unwrap_or(("", name));
+        self.list(dir)?
+            .into_iter()
+            .find(|entry| entry.name == file_name)
+            .ok_or_else(|| {
+                AppError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{name} not found"),
+                ))
+            })
+    }
+    /// Reads the whole content of `name`.
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError>;
+    /// Reads at most the first `len` bytes of `name`, e.g. to check a header.
+    fn read_prefix(&self, name: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        let mut data = self.read(name)?;
+        data.truncate(len);
+        Ok(data)
+    }
+    /// Replaces the content of `name` atomically, creating parent directories as needed.
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError>;
+    /// Removes `name`.
+    fn delete(&self, name: &str) -> Result<(), AppError>;
+    /// Moves `from` to `to`, replacing `to` if it exists.
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError>;
+    /// Removes the directory `name` if it's empty. Backends without
+    /// directories of their own have nothing to remove.
+    fn remove_dir(&self, _name: &str) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// Returns whether `name` exists as a file.
+    fn exists(&self, name: &str) -> bool;
+    /// Checks that `name`, or anything for `""`, could be written now without
+    /// writing it, e.g. to find a read-only mount before editing a note.
+    fn check_writable(&self, _name: &str) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// The directory on disk backing this storage, if any.
+    fn root(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Shares one storage between several owners, e.g. a vault and a test inspecting it.
+impl<S: Storage + ?Sized> Storage for Arc<S> {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        (**self).list(dir)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        (**self).list_dirs(dir)
+    }
+
+    fn entry(&self, name: &str) -> Result<Entry, AppError> {
+        (**self).entry(name)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        (**self).read(name)
+    }
+
+    fn read_prefix(&self, name: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        (**self).read_prefix(name, len)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        (**self).write(name, data)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        (**self).delete(name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        (**self).rename(from, to)
+    }
+
+    fn remove_dir(&self, name: &str) -> Result<(), AppError> {
+        (**self).remove_dir(name)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        (**self).exists(name)
+    }
+
+    fn check_writable(&self, name: &str) -> Result<(), AppError> {
+        (**self).check_writable(name)
+    }
+
+    fn root(&self) -> Option<&Path> {
+        (**self).root()
+    }
+}
+
+/// Wraps another storage and refuses every change with [`AppError::ReadOnlyMode`].
+pub struct ReadOnlyStorage {
+    inner: Box<dyn Storage>,
+}
+
+impl ReadOnlyStorage {
+    pub fn new(inner: Box<dyn Storage>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Storage for ReadOnlyStorage {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        self.inner.list(dir)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        self.inner.list_dirs(dir)
+    }
+
+    fn entry(&self, name: &str) -> Result<Entry, AppError> {
+        self.inner.entry(name)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        self.inner.read(name)
+    }
+
+    fn read_prefix(&self, name: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        self.inner.read_prefix(name, len)
+    }
+
+    fn write(&self, _name: &str, _data: &[u8]) -> Result<(), AppError> {
+        Err(AppError::ReadOnlyMode)
+    }
+
+    fn delete(&self, _name: &str) -> Result<(), AppError> {
+        Err(AppError::ReadOnlyMode)
+    }
+
+    fn rename(&self, _from: &str, _to: &str) -> Result<(), AppError> {
+        Err(AppError::ReadOnlyMode)
+    }
+
+    fn remove_dir(&self, _name: &str) -> Result<(), AppError> {
+        Err(AppError::ReadOnlyMode)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.inner.exists(name)
+    }
+
+    fn check_writable(&self, _name: &str) -> Result<(), AppError> {
+        Err(AppError::ReadOnlyMode)
+    }
+
+    fn root(&self) -> Option<&Path> {
+        self.inner.root()
+    }
+}
+
+/// Stores entries as files below a root directory.
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    root: PathBuf,
+}
+
+impl FsStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The path of the entry `name` below the root, refusing names that would
+    /// leave it, such as ones with `..` parts.
+    fn path(&self, name: &str) -> Result<PathBuf, AppError> {
+        let mut path = self.root.clone();
+        for part in name.split('/').filter(|part| !part.is_empty()) {
+            if !Path::new(part)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(AppError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid storage name {name:?}"),
+                )));
+            }
+            path.push(part);
+        }
+        Ok(path)
+    }
+}
+
+impl Storage for FsStorage {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        let read_dir = match fs::read_dir(self.path(dir)?) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !dir.is_empty() => {
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(AppError::Io(e)),
+        };
+
+        let mut entries = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            // Names that aren't valid UTF-8 can't be addressed, so they're not listed
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            entries.push(Entry {
+                name,
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+                created: metadata.created().ok(),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        let read_dir = match fs::read_dir(self.path(dir)?) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(AppError::Io(e)),
+        };
+        let mut dirs = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            if entry.file_type()?.is_dir()
+                && let Ok(name) = entry.file_name().into_string()
+            {
+                dirs.push(name);
+            }
+        }
+        Ok(dirs)
+    }
+
+    fn entry(&self, name: &str) -> Result<Entry, AppError> {
+        let path = self.path(name)?;
+        let metadata = fs::metadata(&path)?;
+        Ok(Entry {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+        })
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        fs::read(self.path(name)?).map_err(AppError::Io)
+    }
+
+    fn read_prefix(&self, name: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        let file = fs::File::open(self.path(name)?)?;
+        let mut data = Vec::with_capacity(len);
+        file.take(len as u64).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        let path = self.path(name)?;
+
+        // Atomic write pattern
+        let parent = path
+            .parent()
+            .ok_or_else(|| AppError::Config("Invalid storage path".to_string()))?;
+        fs::create_dir_all(parent).map_err(AppError::Io)?;
+        let mut temp_file = tempfile::NamedTempFile::new_in(parent).map_err(AppError::Io)?;
+        temp_file.write_all(data).map_err(AppError::Io)?;
+        temp_file
+            .persist(&path)
+            .map_err(|e| AppError::Io(e.error))?;
+
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        fs::remove_file(self.path(name)?).map_err(AppError::Io)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let to = self.path(to)?;
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(AppError::Io)?;
+        }
+        fs::rename(self.path(from)?, to).map_err(AppError::Io)
+    }
+
+    fn remove_dir(&self, name: &str) -> Result<(), AppError> {
+        fs::remove_dir(self.path(name)?).map_err(AppError::Io)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.path(name).is_ok_and(|path| path.is_file())
+    }
+
+    fn check_writable(&self, name: &str) -> Result<(), AppError> {
+        // A write replaces the file with a temporary one from the same
+        // directory, so it's the directory that has to be writable
+        let mut dir = self.path(name)?;
+        if !name.is_empty() {
+            dir.pop();
+        }
+        while !dir.is_dir() && dir != self.root {
+            dir.pop();
+        }
+        tempfile::tempfile_in(&dir)?;
+        Ok(())
+    }
+
+    fn root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// Keeps every entry in memory. Mostly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<String, (Vec<u8>, SystemTime)>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, BTreeMap<String, (Vec<u8>, SystemTime)>> {
+        // A panic while holding the lock can't leave a map half-updated
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn not_found(name: &str) -> AppError {
+        AppError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{name} not found"),
+        ))
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        let prefix = if dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", dir.trim_end_matches('/'))
+        };
+        Ok(self
+            .entries()
+            .iter()
+            .filter_map(|(key, (data, modified))| {
+                let name = key.strip_prefix(&prefix)?;
+                (!name.contains('/')).then(|| Entry {
+                    name: name.to_string(),
+                    len: data.len() as u64,
+                    modified: Some(*modified),
+                    created: None,
+                })
+            })
+            .collect())
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        let prefix = if dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", dir.trim_end_matches('/'))
+        };
+        // Directories only exist through the entries below them
+        let dirs: BTreeSet<String> = self
+            .entries()
+            .keys()
+            .filter_map(|key| {
+                let (dir, _) = key.strip_prefix(&prefix)?.split_once('/')?;
+                Some(dir.to_string())
+            })
+            .collect();
+        Ok(dirs.into_iter().collect())
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        self.entries()
+            .get(name)
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn read_prefix(&self, name: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        self.entries()
+            .get(name)
+            .map(|(data, _)| data[..len.min(data.len())].to_vec())
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        self.entries()
+            .insert(name.to_string(), (data.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.entries()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let mut entries = self.entries();
+        let entry = entries.remove(from).ok_or_else(|| Self::not_found(from))?;
+        entries.insert(to.to_string(), entry);
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.entries().contains_key(name)
+    }
+}
@@ -0,0 +1,270 @@
+//! Syncing the notes directory through git.
+//!
+//! Ryokan shells out to the `git` binary, so the user's own credentials, remotes
+//! and hooks apply. Encrypted notes can't be merged line by line, so when both
+//! sides changed the same note during a pull, the upstream version wins and the
+//! local one is kept as a conflict copy: a new note whose `conflict_of` points at
+//! the original, for the user to keep or discard.
+
+use crate::error::AppError;
+use crate::file::{self, NoteFileKind};
+use crate::lock::LOCK_FILE_NAME;
+use crate::metadata::NoteMetadata;
+use crate::storage::{FsStorage, Storage};
+use chrono::Local;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    io,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Appended to the title of conflict copies.
+pub const CONFLICT_TITLE_SUFFIX: &str = " (conflict)";
+
+/// The `[sync]` section of the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncConfig {
+    pub backend: SyncBackend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncBackend {
+    Git,
+}
+
+/// How the local branch compares to its upstream, as of the last fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStatus {
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// What [`GitSync::sync`] did.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Number of notes changed in the commit made before pulling.
+    pub committed_notes: usize,
+    /// UUIDs of the conflict copies created while pulling.
+    pub conflicts: Vec<String>,
+    /// Whether there was an upstream to pull from and push to.
+    pub pushed: bool,
+}
+
+/// A notes directory inside a git working tree.
+#[derive(Debug, Clone)]
+pub struct GitSync {
+    dir: PathBuf,
+}
+
+impl GitSync {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Runs git in the notes directory.
+    fn git(&self, args: &[&str]) -> Result<Output, AppError> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    AppError::Sync("git is not installed or not on PATH".to_string())
+                }
+                _ => AppError::Io(e),
+            })
+    }
+
+    /// Runs git, failing with its error output if it doesn't succeed.
+    fn git_ok(&self, args: &[&str]) -> Result<Vec<u8>, AppError> {
+        let output = self.git(args)?;
+        if !output.status.success() {
+            return Err(AppError::Sync(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Paths printed by a git command run with `-z`.
+    fn git_paths(&self, args: &[&str]) -> Result<Vec<String>, AppError> {
+        Ok(self
+            .git_ok(args)?
+            .split(|&byte| byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect())
+    }
+
+    /// Ahead/behind counts, or `None` if the branch has no upstream.
+    pub fn status(&self) -> Result<Option<SyncStatus>, AppError> {
+        let output = self.git(&["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let counts = String::from_utf8_lossy(&output.stdout);
+        let mut counts = counts.split_whitespace().map(str::parse::<usize>);
+        match (counts.next(), counts.next()) {
+            (Some(Ok(ahead)), Some(Ok(behind))) => Ok(Some(SyncStatus { ahead, behind })),
+            _ => Err(AppError::Sync(format!(
+                "Unexpected output from git rev-list: {counts:?}"
+            ))),
+        }
+    }
+
+    /// Commits local changes, pulls with rebase and pushes.
+    pub fn sync(&self) -> Result<SyncReport, AppError> {
+        if self
+            .git(&["rev-parse", "--is-inside-work-tree"])?
+            .status
+            .success()
+        {
+            info!("Syncing {}", self.dir.display());
+        } else {
+            return Err(AppError::Sync(format!(
+                "{} is not in a git repository",
+                self.dir.display()
+            )));
+        }
+
+        let mut report = SyncReport::default();
+        let exclude_lock = format!(":(exclude){LOCK_FILE_NAME}");
+        self.git_ok(&["add", "-A", "--", ".", &exclude_lock])?;
+        let staged = self.git_paths(&["diff", "--cached", "--name-only", "--relative", "-z"])?;
+        if !staged.is_empty() {
+            report.committed_notes = note_uuids(&staged).len();
+            let message = format!(
+                "Sync {} note(s) at {}",
+                report.committed_notes,
+                Local::now().format("%Y-%m-%d %H:%M:%S")
+            );
+            self.git_ok(&["commit", "-m", &message])?;
+        }
+
+        if self.status()?.is_none() {
+            info!("No upstream branch, nothing to pull or push");
+            return Ok(report);
+        }
+
+        let pull = self.git(&["pull", "--rebase"])?;
+        if !pull.status.success() {
+            while self.rebase_in_progress()? {
+                let conflicted = self.git_paths(&[
+                    "diff",
+                    "--name-only",
+                    "--diff-filter=U",
+                    "--relative",
+                    "-z",
+                ])?;
+                if conflicted.is_empty() {
+                    return Err(AppError::Sync(
+                        "git pull --rebase stopped without conflicts in the notes directory, finish it by hand"
+                            .to_string(),
+                    ));
+                }
+                report
+                    .conflicts
+                    .extend(self.resolve_conflicts(&conflicted)?);
+                self.git_ok(&["-c", "core.editor=true", "rebase", "--continue"])
+                    .or_else(|e| {
+                        // Stopping again on the next commit is handled by the next iteration
+                        if self.rebase_in_progress()? {
+                            Ok(Vec::new())
+                        } else {
+                            Err(e)
+                        }
+                    })?;
+            }
+            // Without conflicts to resolve, the pull failed for another reason
+            if report.conflicts.is_empty() {
+                return Err(AppError::Sync(format!(
+                    "git pull --rebase failed: {}",
+                    String::from_utf8_lossy(&pull.stderr).trim()
+                )));
+            }
+        }
+
+        self.git_ok(&["push"])?;
+        report.pushed = true;
+        Ok(report)
+    }
+
+    /// Whether a rebase stopped and is waiting to be continued. `REBASE_HEAD`
+    /// can outlive the rebase, so this looks for its state directories instead.
+    fn rebase_in_progress(&self) -> Result<bool, AppError> {
+        for state_dir in ["rebase-merge", "rebase-apply"] {
+            let path = self.git_ok(&["rev-parse", "--git-path", state_dir])?;
+            let path = PathBuf::from(String::from_utf8_lossy(&path).trim());
+            if self.dir.join(path).is_dir() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Takes the upstream side of every conflicted file, keeping local versions of
+    /// notes as conflict copies. Returns the UUIDs of the copies.
+    fn resolve_conflicts(&self, conflicted: &[String]) -> Result<Vec<String>, AppError> {
+        let storage = FsStorage::new(&self.dir);
+        let mut copies = Vec::new();
+
+        for path in conflicted {
+            if let Some((uuid, NoteFileKind::Content)) = parse_note_path(path) {
+                // While rebasing, stage 3 ("theirs") is the local commit being replayed
+                let content = self.git_ok(&["show", &format!(":3:./{path}")])?;
+                let (_, metadata_name) = file::note_file_names(uuid);
+                let metadata = self
+                    .git_ok(&["show", &format!(":3:./{metadata_name}")])
+                    .or_else(|_| self.git_ok(&["show", &format!(":0:./{metadata_name}")]))?;
+                let mut metadata: NoteMetadata =
+                    toml::from_str(&String::from_utf8_lossy(&metadata))?;
+                metadata.original_filename.push_str(CONFLICT_TITLE_SUFFIX);
+                metadata.conflict_of = Some(uuid.to_string());
+
+                let copy_uuid = file::generate_uuid();
+                let (copy_content, copy_metadata) = file::note_file_names(&copy_uuid);
+                storage.write(&copy_content, &content)?;
+                metadata.save(&storage, &copy_metadata)?;
+                self.git_ok(&["add", "--", &copy_content, &copy_metadata])?;
+                info!("Kept the local version of {uuid} as {copy_uuid}");
+                copies.push(copy_uuid);
+            }
+
+            // A file deleted upstream can't be checked out, so the local one stays
+            if !self
+                .git(&["checkout", "--ours", "--", path])?
+                .status
+                .success()
+            {
+                info!("Keeping the local version of {path}");
+            }
+            self.git_ok(&["add", "-A", "--", path])?;
+        }
+        Ok(copies)
+    }
+}
+
+/// Like [`file::parse_note_file_name`] for a path relative to the notes directory,
+/// ignoring files in subdirectories such as the history.
+fn parse_note_path(path: &str) -> Option<(&str, NoteFileKind)> {
+    if path.contains('/') {
+        return None;
+    }
+    file::parse_note_file_name(path)
+}
+
+/// UUIDs of the notes among `paths`, relative to the notes directory.
+fn note_uuids(paths: &[String]) -> BTreeSet<&str> {
+    paths
+        .iter()
+        .filter_map(|path| parse_note_path(path))
+        .map(|(uuid, _)| uuid)
+        .collect()
+}
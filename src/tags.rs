@@ -0,0 +1,272 @@
+//! Completing tags from the ones already in use, so the same tag isn't typed
+//! three different ways, and editing the tags of several notes at once.
+//!
+//! A `/` in a tag separates namespaces, as in `project/ryokan`: filtering by
+//! `project` also finds notes tagged `project/ryokan`, and renaming `project`
+//! renames every tag below it. Tags are still stored as plain strings; the
+//! hierarchy is only how they're read.
+
+use crate::error::AppError;
+use crate::metadata::MAX_TAG_LENGTH;
+use crate::text;
+use crate::vault::Note;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Separates the levels of a tag.
+pub const SEPARATOR: char = '/';
+
+/// How many notes use each tag.
+pub type TagCounts = BTreeMap<String, usize>;
+
+/// Counts the tags of `notes`.
+pub fn count(notes: &[Note]) -> TagCounts {
+    let mut counts = TagCounts::new();
+    for note in notes {
+        retag(&mut counts, &[], &note.metadata.tags);
+    }
+    counts
+}
+
+/// Updates `counts` for a note whose tags went from `old` to `new`.
+pub fn retag(counts: &mut TagCounts, old: &[String], new: &[String]) {
+    for tag in old {
+        if let Some(count) = counts.get_mut(tag) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(tag);
+            }
+        }
+    }
+    for tag in new {
+        *counts.entry(tag.clone()).or_default() += 1;
+    }
+}
+
+/// `counts` with the namespaces of the tags in use added, counting the uses of
+/// every tag below them, so `project` is suggested for `project/home`.
+pub fn with_namespaces(counts: &TagCounts) -> TagCounts {
+    let mut all = counts.clone();
+    for (tag, count) in counts {
+        for (end, _) in tag.match_indices(SEPARATOR) {
+            *all.entry(tag[..end].to_string()).or_default() += count;
+        }
+    }
+    all
+}
+
+/// Checks that no level of `tag` is empty, i.e. that it has no leading,
+/// trailing or doubled `/`, and that it's at most [`MAX_TAG_LENGTH`]
+/// characters.
+///
+/// ```
+/// use ryokan::tags::validate;
+///
+/// assert!(validate("project/ryokan").is_ok());
+/// assert!(validate("project/").is_err());
+/// ```
+pub fn validate(tag: &str) -> Result<(), AppError> {
+    if tag.split(SEPARATOR).any(str::is_empty) {
+        return Err(AppError::Config(format!(
+            "Invalid tag \"{tag}\", the levels between slashes can't be empty"
+        )));
+    }
+    if text::first_graphemes(tag, MAX_TAG_LENGTH).len() < tag.len() {
+        return Err(AppError::Config(format!(
+            "Tags can't be longer than {MAX_TAG_LENGTH} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Checks every tag of typed text, as [`parse`] or [`apply_edit`] read it.
+pub fn validate_typed(text: &str) -> Result<(), AppError> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .map(|word| word.trim_start_matches(['+', '-']).trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .try_for_each(validate)
+}
+
+/// Whether `tag` is `parent` or below it, ignoring case.
+///
+/// ```
+/// use ryokan::tags::is_within;
+///
+/// assert!(is_within("Project/home", "project"));
+/// assert!(!is_within("projects", "project"));
+/// ```
+pub fn is_within(tag: &str, parent: &str) -> bool {
+    let (tag, parent) = (tag.to_lowercase(), parent.to_lowercase());
+    tag.strip_prefix(&parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+/// Renames `from` and every tag below it, ignoring case, keeping what's below:
+/// renaming `project` to `area` turns `project/home` into `area/home`. Returns
+/// whether a tag changed.
+pub fn rename(tags: &mut Vec<String>, from: &str, to: &str) -> bool {
+    let depth = from.split(SEPARATOR).count();
+    let mut changed = false;
+    let mut renamed: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.drain(..) {
+        let tag = if is_within(&tag, from) {
+            changed = true;
+            std::iter::once(to)
+                .chain(tag.split(SEPARATOR).skip(depth))
+                .collect::<Vec<_>>()
+                .join("/")
+        } else {
+            tag
+        };
+        if !renamed.contains(&tag) {
+            renamed.push(tag);
+        }
+    }
+    *tags = renamed;
+    changed
+}
+
+/// A tag or namespace in the tree of the tags in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// The whole tag, e.g. `project/home`.
+    pub tag: String,
+    /// How many namespaces it's below; 0 for `project`.
+    pub depth: usize,
+    /// Notes with this tag or one below it, each counted once.
+    pub notes: usize,
+}
+
+impl TreeEntry {
+    /// The last level of the tag, e.g. `home`.
+    pub fn name(&self) -> &str {
+        self.tag.rsplit(SEPARATOR).next().unwrap_or(&self.tag)
+    }
+}
+
+/// Every tag of `notes` and their namespaces, each after its parent, with the
+/// notes below them rolled up.
+pub fn tree(notes: &[Note]) -> Vec<TreeEntry> {
+    let mut totals: BTreeMap<Vec<&str>, usize> = BTreeMap::new();
+    for note in notes {
+        let mut levels = BTreeSet::new();
+        for tag in &note.metadata.tags {
+            let parts: Vec<_> = tag.split(SEPARATOR).collect();
+            for depth in 1..=parts.len() {
+                levels.insert(parts[..depth].to_vec());
+            }
+        }
+        for level in levels {
+            *totals.entry(level).or_default() += 1;
+        }
+    }
+    totals
+        .into_iter()
+        .map(|(parts, notes)| TreeEntry {
+            tag: parts.join("/"),
+            depth: parts.len() - 1,
+            notes,
+        })
+        .collect()
+}
+
+/// Tags that contain `partial`, ignoring case: those starting with it first,
+/// then the most used, then alphabetically.
+///
+/// ```
+/// use ryokan::tags::{TagCounts, complete};
+///
+/// let tags = TagCounts::from([("homework".into(), 5), ("work".into(), 1)]);
+/// assert_eq!(complete("wo", &tags), ["work", "homework"]);
+/// ```
+pub fn complete<'a>(partial: &str, counts: &'a TagCounts) -> Vec<&'a str> {
+    let partial = partial.to_lowercase();
+    let mut matches: Vec<_> = counts
+        .iter()
+        .filter_map(|(tag, count)| {
+            let lowercase = tag.to_lowercase();
+            let position = lowercase.find(&partial)?;
+            Some((position > 0, std::cmp::Reverse(*count), tag.as_str()))
+        })
+        .collect();
+    matches.sort();
+    matches.into_iter().map(|(_, _, tag)| tag).collect()
+}
+
+/// `tags` as `#tag` words, as many as fit in `columns`, followed by
+/// `+N more` for the others.
+pub fn fit(tags: &[String], columns: usize) -> String {
+    let mut shown = String::new();
+    for (i, tag) in tags.iter().enumerate() {
+        let word = format!("#{tag}");
+        let left = tags.len() - i - 1;
+        // Room is kept for the count of the tags after this one
+        let more = if left == 0 {
+            0
+        } else {
+            text::width(&format!(" +{left} more"))
+        };
+        let separator = usize::from(!shown.is_empty());
+        if text::width(&shown) + separator + text::width(&word) + more > columns {
+            let more = format!("+{} more", tags.len() - i);
+            return if shown.is_empty() {
+                more
+            } else {
+                format!("{shown} {more}")
+            };
+        }
+        if separator == 1 {
+            shown.push(' ');
+        }
+        shown.push_str(&word);
+    }
+    shown
+}
+
+/// Splits typed tags on whitespace and commas, without a leading `#`.
+pub fn parse(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|tag| tag.trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Applies an edit typed for several notes at once to `tags`: `tag` or `+tag`
+/// adds a tag, `-tag` removes it.
+pub fn apply_edit(tags: &mut Vec<String>, edit: &str) {
+    for word in edit.split(|c: char| c.is_whitespace() || c == ',') {
+        if let Some(removed) = word.strip_prefix('-') {
+            let removed = removed.trim_start_matches('#');
+            tags.retain(|tag| tag != removed);
+        } else {
+            let added = word.trim_start_matches('+').trim_start_matches('#');
+            if !added.is_empty() && !tags.iter().any(|tag| tag == added) {
+                tags.push(added.to_string());
+            }
+        }
+    }
+}
+
+/// The tag being typed at the end of `text`, without its `+`, `-` or `#`.
+pub fn partial(text: &str) -> &str {
+    let word = text
+        .rsplit(|c: char| c.is_whitespace() || c == ',')
+        .next()
+        .unwrap_or_default();
+    word.trim_start_matches(['+', '-']).trim_start_matches('#')
+}
+
+/// `text` with the tag being typed replaced by `tag`, ready for the next one.
+pub fn accept(text: &str, tag: &str) -> String {
+    let typed = text.len() - partial(text).len();
+    format!("{}{tag} ", &text[..typed])
+}
+
+#[cfg(test)]
+mod tags_test;
@@ -0,0 +1,142 @@
+#![cfg(test)]
+
+use super::*;
+
+fn counts(tags: &[(&str, usize)]) -> TagCounts {
+    tags.iter()
+        .map(|(tag, count)| (tag.to_string(), *count))
+        .collect()
+}
+
+#[test]
+fn test_prefix_matches_come_before_substring_matches() {
+    let tags = counts(&[("homework", 9), ("Work", 2), ("workshop", 3), ("travel", 1)]);
+
+    assert_eq!(complete("wor", &tags), ["workshop", "Work", "homework"]);
+    assert_eq!(complete("WORK", &tags), ["workshop", "Work", "homework"]);
+    assert_eq!(complete("shop", &tags), ["workshop"]);
+    assert!(complete("wrok", &tags).is_empty());
+    // Nothing typed yet: the most used first
+    assert_eq!(complete("", &tags)[0], "homework");
+}
+
+#[test]
+fn test_ties_are_alphabetical() {
+    let tags = counts(&[("work-b", 1), ("work-a", 1), ("a-work", 1)]);
+    assert_eq!(complete("work", &tags), ["work-a", "work-b", "a-work"]);
+}
+
+#[test]
+fn test_counts_follow_retagging() {
+    let mut tags = counts(&[("work", 2), ("home", 1)]);
+    retag(
+        &mut tags,
+        &["work".to_string(), "home".to_string()],
+        &["travel".to_string()],
+    );
+    assert_eq!(tags, counts(&[("work", 1), ("travel", 1)]));
+}
+
+#[test]
+fn test_partial_tag_is_completed_in_place() {
+    assert_eq!(partial("home +#wo"), "wo");
+    assert_eq!(partial("home -old"), "old");
+    assert_eq!(partial("home "), "");
+    assert_eq!(accept("home +wo", "work"), "home +work ");
+    assert_eq!(accept("", "work"), "work ");
+}
+
+#[test]
+fn test_bulk_edit_adds_and_removes() {
+    let mut tags = vec!["work".to_string(), "old".to_string()];
+    apply_edit(&mut tags, "+#urgent -old work, travel");
+    assert_eq!(tags, ["work", "urgent", "travel"]);
+    assert_eq!(parse("#a b,a  c"), ["a", "b", "c"]);
+}
+
+fn note(tags: &[&str]) -> Note {
+    let mut metadata = crate::metadata::NoteMetadata::new("Note");
+    metadata.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    Note {
+        uuid: String::new(),
+        metadata,
+    }
+}
+
+#[test]
+fn test_namespaces_contain_the_tags_below_them() {
+    assert!(is_within("project", "project"));
+    assert!(is_within("Project/Home", "project"));
+    assert!(is_within("project/home/garden", "project/home"));
+    assert!(!is_within("projects", "project"));
+    assert!(!is_within("project", "project/home"));
+}
+
+#[test]
+fn test_empty_levels_are_rejected() {
+    for tag in ["project/", "/project", "project//home", "/"] {
+        assert!(validate(tag).is_err(), "{tag:?} was accepted");
+    }
+    assert!(validate("project/home").is_ok());
+    assert!(validate(&"long".repeat(MAX_TAG_LENGTH)).is_err());
+    assert!(validate_typed("+#project/home -old, work").is_ok());
+    assert!(validate_typed("work project/").is_err());
+}
+
+#[test]
+fn test_renaming_moves_the_tags_below() {
+    let mut tags = vec![
+        "Project/home".to_string(),
+        "project".to_string(),
+        "area".to_string(),
+        "projects".to_string(),
+    ];
+    assert!(rename(&mut tags, "project", "area"));
+    assert_eq!(tags, ["area/home", "area", "projects"]);
+    assert!(!rename(&mut tags, "travel", "trips"));
+}
+
+#[test]
+fn test_tree_rolls_up_notes_per_namespace() {
+    let notes = [
+        note(&["project/home", "project/work"]),
+        note(&["project"]),
+        note(&["travel", "project/home/garden"]),
+    ];
+    let tree: Vec<_> = tree(&notes)
+        .into_iter()
+        .map(|entry| (entry.tag, entry.depth, entry.notes))
+        .collect();
+    assert_eq!(
+        tree,
+        [
+            ("project".to_string(), 0, 3),
+            ("project/home".to_string(), 1, 2),
+            ("project/home/garden".to_string(), 2, 1),
+            ("project/work".to_string(), 1, 1),
+            ("travel".to_string(), 0, 1),
+        ]
+    );
+}
+
+#[test]
+fn test_namespaces_are_suggested() {
+    let tags = with_namespaces(&counts(&[("project/home", 2), ("project/work", 1)]));
+    assert_eq!(tags.get("project"), Some(&3));
+    assert_eq!(
+        complete("proj", &tags),
+        ["project", "project/home", "project/work"]
+    );
+}
+
+#[test]
+fn test_long_tag_lists_are_cut_short() {
+    let tags: Vec<_> = ["rust", "notes", "ideas", "work"]
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect();
+    assert_eq!(fit(&tags, 80), "#rust #notes #ideas #work");
+    assert_eq!(fit(&tags, 24), "#rust #notes +2 more");
+    assert_eq!(fit(&tags, 8), "+4 more");
+    assert_eq!(fit(&[], 10), "");
+}
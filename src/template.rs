@@ -0,0 +1,148 @@
+//! Templates for the titles of new notes and the lines written above captured
+//! text, e.g. `{date} – untitled`.
+//!
+//! `{date}` is replaced with the local date as `YYYY-MM-DD` and `{time}` with
+//! the local time as `HH:MM`. In titles, `{n}` is replaced with the lowest
+//! number from 1 up that gives a title no note has yet, ignoring case as the
+//! duplicate title check does. `{{` and `}}` stand for braces. Templates are
+//! checked when the config is loaded, so a mistyped placeholder is reported
+//! before any note is created with it.
+//!
+//! The content of template notes, which journal notes start as, may also mark
+//! where the editor puts the cursor with [`CURSOR_MARKER`], see [`position`].
+
+use crate::error::AppError;
+use chrono::{DateTime, Local};
+
+/// The `new_note_title` used when none is configured.
+pub const DEFAULT_NEW_NOTE_TITLE: &str = "New Note";
+
+/// The `capture_header` used when none is configured.
+pub const DEFAULT_CAPTURE_HEADER: &str = "{date} {time}";
+
+/// Marks where the cursor goes in the content of a template note. It's removed
+/// from the note created.
+pub const CURSOR_MARKER: &str = "{{cursor}}";
+
+/// A place in a text, counted from 1 as editors do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    /// In characters.
+    pub column: usize,
+    /// In bytes, as Vim counts columns.
+    pub byte: usize,
+}
+
+/// The position right after `text`. Lines end with `\n`, so the `\r` of a
+/// Windows line ending is part of the line before.
+pub fn position(text: &str) -> Position {
+    let start = text.rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[start..];
+    Position {
+        line: text.matches('\n').count() + 1,
+        column: line.chars().count() + 1,
+        byte: line.len() + 1,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Date,
+    Time,
+    Counter,
+}
+
+/// A checked template, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Checks `template`, the value of the config key `key`, which errors name.
+    /// A `title` template may use `{n}`, and can't be empty.
+    pub fn new(key: &str, template: &str, title: bool) -> Result<Self, AppError> {
+        let invalid =
+            |reason: &str| AppError::Config(format!("Invalid {key} \"{template}\": {reason}"));
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(invalid("a { isn't closed, write {{ for a brace")),
+                        }
+                    }
+                    let part = match name.as_str() {
+                        "date" => Part::Date,
+                        "time" => Part::Time,
+                        "n" if title => Part::Counter,
+                        "n" => return Err(invalid("{n} only applies to titles")),
+                        _ if title => {
+                            return Err(invalid(&format!(
+                                "unknown placeholder {{{name}}}, expected {{date}}, {{time}} or {{n}}"
+                            )));
+                        }
+                        _ => {
+                            return Err(invalid(&format!(
+                                "unknown placeholder {{{name}}}, expected {{date}} or {{time}}"
+                            )));
+                        }
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                }
+                '}' => return Err(invalid("a } isn't opened, write }} for a brace")),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        if title && template.trim().is_empty() {
+            return Err(invalid("a title can't be empty"));
+        }
+        Ok(Self { parts })
+    }
+
+    /// The template filled in for `at`, with `n` for `{n}`.
+    fn fill(&self, at: &DateTime<Local>, n: usize) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Date => at.format("%Y-%m-%d").to_string(),
+                Part::Time => at.format("%H:%M").to_string(),
+                Part::Counter => n.to_string(),
+            })
+            .collect()
+    }
+
+    /// The template filled in for `at`. `{n}` is the lowest number from 1 up for
+    /// which `taken` is false, e.g. because no note has that title yet.
+    pub fn expand(&self, at: &DateTime<Local>, taken: impl Fn(&str) -> bool) -> String {
+        if !self.parts.contains(&Part::Counter) {
+            return self.fill(at, 1);
+        }
+        (1..)
+            .map(|n| self.fill(at, n))
+            .find(|filled| !taken(filled))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod template_test;
@@ -0,0 +1,89 @@
+#![cfg(test)]
+
+use super::*;
+use chrono::TimeZone;
+
+fn at() -> Result<DateTime<Local>, AppError> {
+    Local
+        .with_ymd_and_hms(2026, 3, 14, 9, 5, 0)
+        .single()
+        .ok_or_else(|| AppError::Config("Invalid date".to_string()))
+}
+
+#[test]
+fn test_placeholders_are_filled_in() -> Result<(), AppError> {
+    let template = Template::new("new_note_title", "{date} {time} – untitled", true)?;
+    assert_eq!(
+        template.expand(&at()?, |_| false),
+        "2026-03-14 09:05 – untitled"
+    );
+
+    let template = Template::new("new_note_title", "{{draft}} {date}", true)?;
+    assert_eq!(template.expand(&at()?, |_| false), "{draft} 2026-03-14");
+
+    let template = Template::new("new_note_title", DEFAULT_NEW_NOTE_TITLE, true)?;
+    assert_eq!(template.expand(&at()?, |_| true), "New Note");
+    Ok(())
+}
+
+#[test]
+fn test_counter_skips_titles_already_taken() -> Result<(), AppError> {
+    let template = Template::new("new_note_title", "Draft {n}", true)?;
+    let taken = ["Draft 1", "Draft 2", "Draft 4"];
+
+    assert_eq!(
+        template.expand(&at()?, |title| taken.contains(&title)),
+        "Draft 3"
+    );
+    assert_eq!(template.expand(&at()?, |_| false), "Draft 1");
+    Ok(())
+}
+
+#[test]
+fn test_invalid_templates_are_config_errors() {
+    for template in ["{title}", "{date", "date}", "{}", "  "] {
+        assert!(
+            matches!(
+                Template::new("new_note_title", template, true),
+                Err(AppError::Config(_))
+            ),
+            "{template}"
+        );
+    }
+    // Nothing to count in a line above captured text
+    assert!(matches!(
+        Template::new("capture_header", "{date} #{n}", false),
+        Err(AppError::Config(_))
+    ));
+    assert!(Template::new("capture_header", "", false).is_ok());
+}
+
+#[test]
+fn test_position_counts_characters_and_bytes() {
+    assert_eq!(
+        position(""),
+        Position {
+            line: 1,
+            column: 1,
+            byte: 1
+        }
+    );
+    assert_eq!(
+        position("first\nnaïve 日本"),
+        Position {
+            line: 2,
+            column: 9,
+            byte: 14
+        }
+    );
+    // The \r of a Windows line ending stays on the line it ends
+    assert_eq!(
+        position("one\r\ntwo\r\n"),
+        Position {
+            line: 3,
+            column: 1,
+            byte: 1
+        }
+    );
+    assert_eq!(position("one\r\nab").column, 3);
+}
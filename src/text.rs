@@ -0,0 +1,109 @@
+//! Measuring and cutting text by what a terminal shows of it.
+//!
+//! Counting characters gets both wrong: CJK characters and most emoji take
+//! two columns, and an accent written as a combining character, or an emoji
+//! joined from several, is one character on screen but several `char`s. Widths
+//! here are in terminal columns and text is only ever cut between graphemes,
+//! what a reader sees as one character, so nothing is drawn past the space it
+//! was given and no accent is left without its letter.
+
+use std::{borrow::Cow, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `text` takes in a terminal.
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// The longest start of `text` that fits in `columns`.
+pub fn truncate(text: &str, columns: usize) -> &str {
+    let mut taken = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        taken += grapheme.width();
+        if taken > columns {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// `text` cut to fit in `columns`, ending with `…` where it was cut.
+pub fn ellipsize(text: &str, columns: usize) -> Cow<'_, str> {
+    if width(text) <= columns {
+        Cow::Borrowed(text)
+    } else if columns == 0 {
+        Cow::Borrowed("")
+    } else {
+        Cow::Owned(format!("{}…", truncate(text, columns - 1)))
+    }
+}
+
+/// The longest end of `text` that fits in `columns`, e.g. to keep what's
+/// being typed in view.
+pub fn tail(text: &str, columns: usize) -> &str {
+    let mut taken = 0;
+    for (i, grapheme) in text.grapheme_indices(true).rev() {
+        taken += grapheme.width();
+        if taken > columns {
+            return &text[i + grapheme.len()..];
+        }
+    }
+    text
+}
+
+/// The first `count` graphemes of `text`.
+pub fn first_graphemes(text: &str, count: usize) -> &str {
+    match text.grapheme_indices(true).nth(count) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+/// Removes the last grapheme of `text`, as backspace does.
+pub fn pop_grapheme(text: &mut String) {
+    if let Some((i, _)) = text.grapheme_indices(true).next_back() {
+        text.truncate(i);
+    }
+}
+
+/// Where `query` occurs in `text` ignoring case, as byte ranges of `text` that
+/// don't overlap. An empty `query` occurs nowhere.
+pub fn find_ignoring_case(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let mut found = Vec::new();
+    if query.is_empty() {
+        return found;
+    }
+    let mut start = 0;
+    while let Some(c) = text[start..].chars().next() {
+        match match_len(&text[start..], &query) {
+            Some(len) => {
+                found.push(start..start + len);
+                start += len;
+            }
+            None => start += c.len_utf8(),
+        }
+    }
+    found
+}
+
+/// The length in bytes of the start of `text` that is `query` in lowercase.
+fn match_len(text: &str, query: &[char]) -> Option<usize> {
+    let mut rest = query;
+    for (i, c) in text.char_indices() {
+        for lower in c.to_lowercase() {
+            match rest.split_first() {
+                Some((&expected, tail)) if expected == lower => rest = tail,
+                _ => return None,
+            }
+        }
+        if rest.is_empty() {
+            return Some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod text_test;
@@ -0,0 +1,71 @@
+#![cfg(test)]
+
+use super::*;
+
+/// A notebook emoji, two CJK characters and an `é` written with a combining
+/// accent.
+const TITLE: &str = "📔日記 – cafe\u{301}";
+
+#[test]
+fn test_wide_characters_take_two_columns() {
+    assert_eq!(width("cafe"), 4);
+    assert_eq!(width("cafe\u{301}"), 4);
+    assert_eq!(width(TITLE), 13);
+}
+
+#[test]
+fn test_text_is_cut_to_fit_between_graphemes() {
+    for columns in 0..=width(TITLE) + 1 {
+        let start = truncate(TITLE, columns);
+        let end = tail(TITLE, columns);
+        assert!(width(start) <= columns, "{columns}: {start:?}");
+        assert!(width(end) <= columns, "{columns}: {end:?}");
+        // At most a wide character short of the space given
+        assert!(width(start) + 1 >= columns.min(width(TITLE)), "{start:?}");
+        assert!(width(end) + 1 >= columns.min(width(TITLE)), "{end:?}");
+    }
+    assert_eq!(truncate(TITLE, 3), "📔");
+    assert_eq!(truncate(TITLE, 12), "📔日記 – caf");
+    assert_eq!(tail(TITLE, 4), "cafe\u{301}");
+    assert_eq!(tail(TITLE, 3), "afe\u{301}");
+}
+
+#[test]
+fn test_cut_text_ends_with_an_ellipsis() {
+    assert_eq!(ellipsize(TITLE, 13), TITLE);
+    assert_eq!(ellipsize(TITLE, 6), "📔日…");
+    assert_eq!(ellipsize(TITLE, 1), "…");
+    assert_eq!(ellipsize(TITLE, 0), "");
+}
+
+#[test]
+fn test_backspace_removes_what_is_seen_as_one_character() {
+    let mut text = TITLE.to_string();
+    pop_grapheme(&mut text);
+    assert_eq!(text, "📔日記 – caf");
+
+    // A family emoji joined from four people
+    let mut text = "hi 👨\u{200d}👩\u{200d}👧\u{200d}👦".to_string();
+    pop_grapheme(&mut text);
+    assert_eq!(text, "hi ");
+
+    let mut text = String::new();
+    pop_grapheme(&mut text);
+    assert!(text.is_empty());
+
+    assert_eq!(first_graphemes(TITLE, 3), "📔日記");
+    assert_eq!(first_graphemes("cafe\u{301}", 4), "cafe\u{301}");
+    assert_eq!(first_graphemes("ab", 3), "ab");
+}
+
+#[test]
+fn test_find_ignoring_case() {
+    let line = "Café, CAFÉ and cafe\u{301}";
+    let found = find_ignoring_case(line, "café");
+    let matched: Vec<_> = found.iter().map(|range| &line[range.clone()]).collect();
+    assert_eq!(matched, ["Café", "CAFÉ"]);
+
+    assert_eq!(find_ignoring_case("aaaa", "aa"), [0..2, 2..4]);
+    assert!(find_ignoring_case("anything", "").is_empty());
+    assert!(find_ignoring_case("short", "shorter").is_empty());
+}
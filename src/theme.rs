@@ -0,0 +1,249 @@
+//! How the TUI and the other full-screen views draw selections, states and
+//! borders, in one place so every widget follows the same accessibility
+//! settings.
+//!
+//! By default, the selection is in bold yellow, some states, like a note being
+//! overdue, are only shown in color, and borders use box-drawing characters. In
+//! high-contrast mode, or with `NO_COLOR` set, the selection is in bold inverse
+//! video and every state shown in color is spelled out too. In plain mode,
+//! borders are drawn in ASCII and emoji become words, for screen readers that
+//! read the terminal buffer.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    symbols::{bar, border},
+    text::Span,
+    widgets::{Block, Borders, List, ListItem},
+};
+use ryokan::{due::DueStatus, t};
+
+/// Borders drawn with ASCII characters only.
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// Bars of charts drawn with ASCII characters only, in quarters.
+const ASCII_BAR: bar::Set = bar::Set {
+    full: "#",
+    seven_eighths: "#",
+    three_quarters: "=",
+    five_eighths: "=",
+    half: "-",
+    three_eighths: "-",
+    one_quarter: ".",
+    one_eighth: ".",
+    empty: " ",
+};
+
+/// What a message tells, which sets its color and, when colors aren't enough,
+/// a word in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Normal,
+    Good,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Theme {
+    /// Never rely on color alone, from `high_contrast` in the config.
+    pub high_contrast: bool,
+    /// No box-drawing characters or emoji, from `--plain`.
+    pub plain: bool,
+    /// Colors aren't shown, from the `NO_COLOR` environment variable.
+    pub no_color: bool,
+}
+
+impl Theme {
+    /// The theme for these settings, reading `NO_COLOR` from the environment.
+    pub fn new(high_contrast: bool, plain: bool) -> Self {
+        Self {
+            high_contrast,
+            plain,
+            no_color: std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+        }
+    }
+
+    /// Whether what colors tell has to be written out too.
+    fn spells_out(&self) -> bool {
+        self.high_contrast || self.no_color
+    }
+
+    /// A block with borders all around.
+    pub fn block<'a>(&self) -> Block<'a> {
+        let block = Block::default().borders(Borders::ALL);
+        if self.plain {
+            block.border_set(ASCII_BORDER)
+        } else {
+            block
+        }
+    }
+
+    /// The characters bars of charts are drawn with.
+    pub fn bar_set(&self) -> bar::Set<'static> {
+        if self.plain {
+            ASCII_BAR
+        } else {
+            bar::NINE_LEVELS
+        }
+    }
+
+    /// A list whose selected item is shown with [`Theme::selection`] after a
+    /// `>` marker.
+    pub fn list<'a>(&self, items: Vec<ListItem<'a>>, block: Block<'a>) -> List<'a> {
+        List::new(items)
+            .block(block)
+            .highlight_style(self.selection())
+            .highlight_symbol(if self.spells_out() { "> " } else { ">> " })
+    }
+
+    /// The style of the selected item of a list or of a choice.
+    pub fn selection(&self) -> Style {
+        if self.spells_out() {
+            Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        }
+    }
+
+    /// The style of text matching a search, over whatever style it has.
+    pub fn search_match(&self) -> Style {
+        if self.spells_out() {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        }
+    }
+
+    /// The style of a message with this tone.
+    pub fn tone(&self, tone: Tone) -> Style {
+        let color = match tone {
+            Tone::Normal => return Style::default(),
+            Tone::Good => Color::Green,
+            Tone::Warning => Color::Yellow,
+            Tone::Error => Color::Red,
+        };
+        if self.spells_out() {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(color)
+        }
+    }
+
+    /// The word put in front of a message with this tone when colors aren't
+    /// enough, e.g. `Error: `.
+    pub fn tone_prefix(&self, tone: Tone) -> String {
+        match tone {
+            _ if !self.spells_out() => String::new(),
+            Tone::Normal | Tone::Good => String::new(),
+            Tone::Warning => t!("tone-warning"),
+            Tone::Error => t!("tone-error"),
+        }
+    }
+
+    /// A horizontal line separating parts of a text.
+    pub fn rule(&self) -> &'static str {
+        if self.plain {
+            "---------------------------------"
+        } else {
+            "─────────────────────────────────"
+        }
+    }
+
+    /// What marks a pinned note.
+    pub fn pin_mark(&self) -> String {
+        if self.plain {
+            format!("[{}] ", t!("mark-pinned"))
+        } else {
+            "📌 ".to_string()
+        }
+    }
+
+    /// What marks a note that's read-only.
+    pub fn read_only_mark(&self) -> String {
+        if self.plain {
+            format!("[{}] ", t!("mark-read-only"))
+        } else {
+            "🔒 ".to_string()
+        }
+    }
+
+    /// What marks a note that came due while the TUI is open.
+    pub fn reminder_mark(&self) -> String {
+        if self.plain {
+            format!("[{}] ", t!("mark-reminder"))
+        } else {
+            "🔔 ".to_string()
+        }
+    }
+
+    /// What marks a conflict copy, or a warning.
+    pub fn warning_mark(&self) -> &'static str {
+        if self.plain { "[!] " } else { "⚠ " }
+    }
+
+    /// Ahead and behind counts of a sync, e.g. `↑1 ↓2`.
+    pub fn ahead_behind(&self, ahead: usize, behind: usize) -> String {
+        if self.plain {
+            t!("sync-ahead-behind", ahead = ahead, behind = behind)
+        } else {
+            format!("↑{ahead} ↓{behind}")
+        }
+    }
+
+    /// The style of a note due as `status`.
+    pub fn due(&self, status: DueStatus) -> Style {
+        match status {
+            DueStatus::Overdue => self.tone(Tone::Error),
+            DueStatus::Today => self.tone(Tone::Warning),
+            DueStatus::Upcoming => Style::default(),
+        }
+    }
+
+    /// What's written next to the due date of a note, when colors aren't
+    /// enough, e.g. `, overdue`.
+    pub fn due_suffix(&self, status: DueStatus) -> String {
+        match status {
+            _ if !self.spells_out() => String::new(),
+            DueStatus::Overdue => t!("due-suffix-overdue"),
+            DueStatus::Today => t!("due-suffix-today"),
+            DueStatus::Upcoming => String::new(),
+        }
+    }
+
+    /// The color label of a note: a bullet in its color, or its name when
+    /// colors aren't enough or in plain mode. Labels this build doesn't know
+    /// are left out.
+    pub fn color_label(&self, label: Option<&str>) -> Span<'static> {
+        let Some(label) = label else {
+            return Span::raw("");
+        };
+        let color = match label {
+            "red" => Color::Red,
+            "orange" => Color::Indexed(208),
+            "yellow" => Color::Yellow,
+            "green" => Color::Green,
+            "blue" => Color::Blue,
+            "purple" => Color::Magenta,
+            _ => return Span::raw(""),
+        };
+        if self.spells_out() || self.plain {
+            Span::raw(format!("[{label}] "))
+        } else {
+            Span::styled("● ", Style::default().fg(color))
+        }
+    }
+}
+
+#[cfg(test)]
+mod theme_test;
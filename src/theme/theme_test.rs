@@ -0,0 +1,57 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_plain_theme_only_writes_ascii() {
+    let theme = Theme {
+        plain: true,
+        ..Theme::default()
+    };
+    let marks = [
+        theme.read_only_mark().to_string(),
+        theme.pin_mark().to_string(),
+        theme.warning_mark().to_string(),
+        theme.ahead_behind(1, 2),
+        theme.rule().to_string(),
+        theme.color_label(Some("red")).content.into_owned(),
+    ];
+    for mark in marks {
+        assert!(mark.is_ascii(), "{mark}");
+    }
+    for symbol in [
+        ASCII_BORDER.top_left,
+        ASCII_BORDER.vertical_left,
+        ASCII_BORDER.horizontal_top,
+    ] {
+        assert!(symbol.is_ascii());
+    }
+}
+
+#[test]
+fn test_high_contrast_spells_out_what_colors_tell() {
+    let theme = Theme::default();
+    assert_eq!(theme.due_suffix(DueStatus::Overdue), "");
+    assert_eq!(theme.tone_prefix(Tone::Error), "");
+    assert_eq!(theme.selection().fg, Some(Color::Yellow));
+
+    for theme in [
+        Theme {
+            high_contrast: true,
+            ..Theme::default()
+        },
+        Theme {
+            no_color: true,
+            ..Theme::default()
+        },
+    ] {
+        assert_eq!(theme.due_suffix(DueStatus::Overdue), ", overdue");
+        assert_eq!(theme.due_suffix(DueStatus::Today), ", today");
+        assert_eq!(theme.tone_prefix(Tone::Error), "Error: ");
+        assert_eq!(theme.color_label(Some("green")).content, "[green] ");
+        let selection = theme.selection();
+        assert_eq!(selection.fg, None);
+        assert!(selection.add_modifier.contains(Modifier::REVERSED));
+        assert_eq!(theme.due(DueStatus::Overdue).fg, None);
+    }
+}
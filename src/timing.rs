@@ -0,0 +1,119 @@
+//! Wall-clock durations of the startup phases, printed to stderr with `--timing`,
+//! along with counts of what happened after, like frames drawn.
+
+use log::debug;
+use ryokan::{error::AppError, pin::PinPrompt};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// Records how long each phase took since the previous one.
+pub struct Timings {
+    enabled: bool,
+    last: Instant,
+    phases: Vec<(String, Duration)>,
+    counts: Vec<(String, usize)>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            enabled,
+            last: now,
+            phases: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    /// Ends the phase `name`, which started when the previous one ended.
+    pub fn phase(&mut self, name: impl Into<String>) {
+        self.phase_excluding(name, Duration::ZERO);
+    }
+
+    /// Like [`Timings::phase`], leaving out `idle` time, e.g. spent typing the PIN.
+    pub fn phase_excluding(&mut self, name: impl Into<String>, idle: Duration) {
+        let now = Instant::now();
+        let name = name.into();
+        let duration = now.duration_since(self.last).saturating_sub(idle);
+        debug!("{name}: {duration:.2?}");
+        self.last = now;
+        if self.enabled {
+            self.phases.push((name, duration));
+        }
+    }
+
+    /// Records that `name` happened `count` times, e.g. over the session.
+    pub fn count(&mut self, name: impl Into<String>, count: usize) {
+        let name = name.into();
+        debug!("{name}: {count}");
+        if self.enabled {
+            self.counts.push((name, count));
+        }
+    }
+
+    /// Prints the breakdown, if enabled.
+    pub fn print(&self) {
+        if !self.enabled {
+            return;
+        }
+        let width = self
+            .phases
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(self.counts.iter().map(|(name, _)| name.len()))
+            .max()
+            .unwrap_or(0);
+        for (name, duration) in &self.phases {
+            eprintln!("{name:<width$}  {duration:>10.2?}");
+        }
+        let total: Duration = self.phases.iter().map(|(_, duration)| *duration).sum();
+        eprintln!("{:<width$}  {total:>10.2?}", "total");
+        for (name, count) in &self.counts {
+            eprintln!("{name:<width$}  {count:>10}");
+        }
+    }
+}
+
+impl Drop for Timings {
+    /// Prints the breakdown when leaving `main`, whichever way it returns.
+    fn drop(&mut self) {
+        self.print();
+    }
+}
+
+/// Passes prompts through to another [`PinPrompt`], adding up the time spent
+/// waiting for answers.
+pub struct TimedPrompt<P> {
+    inner: P,
+    /// Total time spent in [`PinPrompt::read_secret`] and [`PinPrompt::confirm`].
+    pub waiting: Duration,
+}
+
+impl<P: PinPrompt> TimedPrompt<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            waiting: Duration::ZERO,
+        }
+    }
+}
+
+impl<P: PinPrompt> PinPrompt for TimedPrompt<P> {
+    fn read_secret(&mut self, prompt: &str) -> Result<Zeroizing<String>, AppError> {
+        let started = Instant::now();
+        let secret = self.inner.read_secret(prompt);
+        self.waiting += started.elapsed();
+        secret
+    }
+
+    fn confirm(&mut self, prompt: &str) -> Result<bool, AppError> {
+        let started = Instant::now();
+        let answer = self.inner.confirm(prompt);
+        self.waiting += started.elapsed();
+        answer
+    }
+
+    fn notify(&mut self, message: &str) {
+        self.inner.notify(message);
+    }
+}
@@ -0,0 +1,132 @@
+//! Unchecked checklist items gathered from every note, for the todo view of
+//! the TUI and `ryokan todos`.
+//!
+//! An item is a Markdown checklist line such as `- [ ] call the bank`. Checking
+//! one off writes its note again with `[x]` in the box and leaves the rest of
+//! the note as it was. Notes of the todo type are gathered even once every item
+//! is checked.
+
+use crate::error::AppError;
+use crate::metadata::NoteType;
+use crate::stats::checklist_item;
+use crate::vault::{Note, NoteRevision, Vault};
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+/// An unchecked item of a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Todo {
+    /// Index of its line in the note, from 0.
+    pub line: usize,
+    /// What follows the box.
+    pub text: String,
+}
+
+/// A note with unchecked items, or of the todo type.
+#[derive(Debug, Clone)]
+pub struct NoteTodos {
+    pub note: Note,
+    pub todos: Vec<Todo>,
+    /// How many of its items are checked.
+    pub checked: usize,
+}
+
+/// The unchecked items of `text`, in order.
+pub fn unchecked(text: &str) -> Vec<Todo> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, content)| match checklist_item(content)? {
+            (offset, false) => Some(Todo {
+                line,
+                text: content[offset + 3..].trim().to_string(),
+            }),
+            (_, true) => None,
+        })
+        .collect()
+}
+
+/// `text` with the item on `line` checked, or `None` if that line isn't an
+/// unchecked item, e.g. because the note changed since it was listed.
+pub fn check(text: &str, line: usize) -> Option<String> {
+    let mut start = 0;
+    for (i, content) in text.split_inclusive('\n').enumerate() {
+        if i == line {
+            let Some((offset, false)) = checklist_item(content) else {
+                return None;
+            };
+            let at = start + offset + 1;
+            let mut checked = text.to_string();
+            checked.replace_range(at..at + 1, "x");
+            return Some(checked);
+        }
+        start += content.len();
+    }
+    None
+}
+
+/// Checks off the item on `line` of the note `uuid`. Returns `false` if that
+/// line isn't an unchecked item anymore.
+///
+/// Fails with [`AppError::ReadOnlyNote`] if the note is marked read-only.
+pub fn check_off(vault: &Vault, uuid: &str, line: usize) -> Result<bool, AppError> {
+    let content = Zeroizing::new(vault.read(uuid)?);
+    let Some(checked) = std::str::from_utf8(&content)
+        .ok()
+        .and_then(|text| check(text, line))
+    else {
+        return Ok(false);
+    };
+    let checked = Zeroizing::new(checked);
+    vault.write(uuid, checked.as_bytes())?;
+    Ok(true)
+}
+
+/// The items of notes by UUID, found again only when a note changes, so
+/// opening the todo view again doesn't decrypt every note.
+#[derive(Debug, Default)]
+pub struct TodoCache {
+    entries: HashMap<String, (NoteRevision, Vec<Todo>, usize)>,
+}
+
+impl TodoCache {
+    /// Those of `notes` with unchecked items or of the todo type, in the same
+    /// order. Notes over the size limit of the vault are left out.
+    pub fn collect(&mut self, vault: &Vault, notes: &[Note]) -> Result<Vec<NoteTodos>, AppError> {
+        let mut found = Vec::new();
+        for note in notes {
+            let revision = vault.revision(&note.uuid)?;
+            let (todos, checked) = match self.entries.get(&note.uuid) {
+                Some((cached, todos, checked)) if *cached == revision => (todos.clone(), *checked),
+                _ => {
+                    let content = match vault.read(&note.uuid) {
+                        Ok(content) => Zeroizing::new(content),
+                        // Left out like binary notes, rather than failing the list
+                        Err(AppError::TooLarge { .. }) => continue,
+                        Err(e) => return Err(e),
+                    };
+                    // Binary notes have no checklists
+                    let text = std::str::from_utf8(&content).unwrap_or_default();
+                    let todos = unchecked(text);
+                    let checked = text
+                        .lines()
+                        .filter(|line| matches!(checklist_item(line), Some((_, true))))
+                        .count();
+                    self.entries
+                        .insert(note.uuid.clone(), (revision, todos.clone(), checked));
+                    (todos, checked)
+                }
+            };
+            if !todos.is_empty() || note.metadata.note_type == NoteType::Todo {
+                found.push(NoteTodos {
+                    note: note.clone(),
+                    todos,
+                    checked,
+                });
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod todos_test;
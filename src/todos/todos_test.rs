@@ -0,0 +1,90 @@
+#![cfg(test)]
+
+use super::*;
+use crate::metadata::NoteMetadata;
+use crate::storage::MemoryStorage;
+
+fn vault() -> Vault {
+    Vault::with_storage(MemoryStorage::new(), Zeroizing::new("123456".to_string()))
+}
+
+#[test]
+fn test_only_unchecked_items_are_listed() {
+    let todos =
+        unchecked("Errands\n- [ ] call the bank\n- [x] post\n  * [ ]  buy milk \n- [] not one\n");
+
+    assert_eq!(
+        todos,
+        vec![
+            Todo {
+                line: 1,
+                text: "call the bank".to_string()
+            },
+            Todo {
+                line: 3,
+                text: "buy milk".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_check_touches_only_the_box() {
+    let text = "- [ ] one\r\n  + [ ] two\r\n- [x] three";
+
+    assert_eq!(
+        check(text, 1).as_deref(),
+        Some("- [ ] one\r\n  + [x] two\r\n- [x] three")
+    );
+    assert_eq!(check(text, 2), None);
+    assert_eq!(check(text, 7), None);
+}
+
+#[test]
+fn test_checking_off_rewrites_the_note_and_the_cache_follows() -> Result<(), AppError> {
+    let vault = vault();
+    let note = vault.create("Errands", b"- [ ] call the bank\n- [ ] post\n")?;
+    vault.create("Ideas", b"no checklist here")?;
+    let mut cache = TodoCache::default();
+
+    let found = cache.collect(&vault, &vault.list()?)?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].todos.len(), 2);
+
+    assert!(check_off(&vault, &note.uuid, 0)?);
+    assert!(!check_off(&vault, &note.uuid, 0)?);
+    assert_eq!(
+        vault.read(&note.uuid)?,
+        b"- [x] call the bank\n- [ ] post\n"
+    );
+
+    let found = cache.collect(&vault, &vault.list()?)?;
+    assert_eq!(
+        found[0]
+            .todos
+            .iter()
+            .map(|todo| todo.line)
+            .collect::<Vec<_>>(),
+        [1]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_todo_notes_are_collected_with_every_item_checked() -> Result<(), AppError> {
+    let vault = vault();
+    let mut metadata = NoteMetadata::new("Packing");
+    metadata.note_type = NoteType::Todo;
+    vault.create_with_metadata(&metadata, b"- [x] passport\n- [x] tickets\n")?;
+    vault.create("Done", b"- [x] post\n")?;
+
+    let found = TodoCache::default().collect(&vault, &vault.list()?)?;
+
+    let [packing] = &found[..] else {
+        return Err(AppError::Config(format!("{} notes found", found.len())));
+    };
+    assert_eq!(packing.note.metadata.original_filename, "Packing");
+    assert!(packing.todos.is_empty());
+    assert_eq!(packing.checked, 2);
+    Ok(())
+}
@@ -1,70 +1,358 @@
-use crate::error::AppError;
-use crate::metadata::NoteMetadata;
-use crate::{args::Args, config::Config, file, note};
-use chrono::Utc;
+use crate::{
+    editor,
+    pager::{self, LinkPicker, Viewer},
+    palette::{self, Outcome, Palette},
+    theme::{Theme, Tone},
+    timing::Timings,
+    undo::{Change, Field, UndoStack},
+};
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use log::error;
+use log::{error, warn};
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Bar, BarChart, Clear, ListItem, ListState, Paragraph, Sparkline, Wrap},
+};
+use ryokan::{
+    backup::{Backups, Snapshot},
+    clipboard,
+    datetime::DateFormat,
+    diff::{self, DiffLine},
+    due::{self, Reminders},
+    error::AppError,
+    export, filename, gc, i18n,
+    journal::Journal,
+    metadata::{COLOR_LABELS, NoteMetadata, NoteType},
+    notify,
+    stats::{self, NoteStats, StatsCache},
+    sync::{GitSync, SyncStatus},
+    t, tags,
+    template::{Position, Template},
+    text,
+    todos::{self, NoteTodos, TodoCache},
+    vault::{
+        self, EditOutcome, FileStamp, Note, NoteRevision, NoteVersion, Orphan, SortMode, Vault,
+    },
+    vaultstats::{self, VaultStats},
+};
+#[cfg(unix)]
+use signal_hook::{
+    consts::signal::{SIGCONT, SIGHUP, SIGTERM, SIGTSTP},
+    iterator::Signals,
 };
 use std::{
-    cmp::Reverse,
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs,
     io::{self, Write},
-    path::PathBuf,
-    time::Duration,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Once,
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use zeroize::Zeroizing;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The undo record of `note`, whose `field` was as given before a change.
+fn changed(note: &Note, field: Field) -> (String, String, Field) {
+    (
+        note.uuid.clone(),
+        note.metadata.original_filename.clone(),
+        field,
+    )
+}
+
+/// Describes how long ago `then` was, e.g. "3 days ago".
+pub fn format_age(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let age = now - then;
+    match age {
+        age if age.num_minutes() < 1 => t!("age-just-now"),
+        age if age.num_hours() < 1 => match age.num_minutes() {
+            1 => t!("age-minute"),
+            n => t!("age-minutes", n = n),
+        },
+        age if age.num_days() < 1 => match age.num_hours() {
+            1 => t!("age-hour"),
+            n => t!("age-hours", n = n),
+        },
+        age if age.num_days() < 60 => match age.num_days() {
+            1 => t!("age-day"),
+            n => t!("age-days", n = n),
+        },
+        _ => t!(
+            "age-on-day",
+            day = then.with_timezone(&Local).format("%Y-%m-%d")
+        ),
+    }
+}
 
-use tempfile::NamedTempFile;
+/// Shows binary content, which has no text to show, as its size or as a hex
+/// dump of at most `max_bytes` of it.
+fn format_binary_content(content: &[u8], max_bytes: usize, hex: bool) -> String {
+    if !hex {
+        return t!("preview-binary", bytes = content.len());
+    }
+    let end = max_bytes.min(content.len());
+    let mut dump = export::hex_dump(&content[..end]);
+    if end < content.len() {
+        dump.push_str(&t!("preview-truncated", kb = content.len().div_ceil(1024)));
+    }
+    dump
+}
 
-pub struct Note {
-    pub uuid: String,
-    pub encrypted_file_path: PathBuf,
-    pub metadata: NoteMetadata,
+/// What changed from `old` to `new`, line by line, removed lines in red with
+/// `-` and added ones in green with `+`.
+fn diff_text(old: &[u8], new: &[u8], theme: &Theme) -> Text<'static> {
+    let Some((old, new)) = diff::as_text(old, new) else {
+        return Text::raw(if old == new {
+            t!("diff-binary-identical")
+        } else {
+            t!("diff-binary-differ")
+        });
+    };
+    let lines = diff::lines(old, new);
+    if lines.iter().all(|line| matches!(line, DiffLine::Same(_))) {
+        return Text::raw(t!("diff-no-changes"));
+    }
+    lines
+        .iter()
+        .map(|line| {
+            let style = match line {
+                DiffLine::Same(_) => Style::default(),
+                DiffLine::Removed(_) => theme.tone(Tone::Error),
+                DiffLine::Added(_) => theme.tone(Tone::Good),
+            };
+            Line::styled(format!("{}{}", line.prefix(), line.text()), style)
+        })
+        .collect()
 }
 
-fn format_preview_content(note: &Note, decrypted: &[u8]) -> String {
-    let content_str = String::from_utf8_lossy(decrypted);
-    let word_count = content_str.split_whitespace().count();
-    let tag_str = if note.metadata.tags.is_empty() {
+/// Shows the metadata of `note`, the `stats` of its text and at most
+/// `preview_max_bytes` of its content, as a hex dump with `hex` if it's binary.
+/// With `preview_header`, the title, last update and tags are left to the
+/// header.
+fn format_preview_content(
+    note: &Note,
+    decrypted: &[u8],
+    stats: Option<&NoteStats>,
+    hex: bool,
+    options: &AppOptions,
+) -> String {
+    let max_bytes = options.preview_max_bytes;
+    let content_str = match std::str::from_utf8(decrypted) {
+        Ok(text) => {
+            let mut end = max_bytes.min(text.len());
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end < text.len() {
+                format!(
+                    "{}\n{}",
+                    &text[..end],
+                    t!("preview-truncated", kb = decrypted.len().div_ceil(1024))
+                )
+            } else {
+                text.to_string()
+            }
+        }
+        Err(_) => format_binary_content(decrypted, max_bytes, hex),
+    };
+    let stats_str = stats.map_or_else(String::new, |stats| format!("{stats}\n"));
+    let size = note.metadata.size.unwrap_or(decrypted.len() as u64);
+    let tag_str = if note.metadata.tags.is_empty() || options.preview_header {
         String::new()
     } else {
         format!("#{}\n", note.metadata.tags.join(" #"))
     };
+    let due_str = note.metadata.due_at.map_or_else(String::new, |due_at| {
+        format!(
+            "{}\n",
+            t!("preview-due", due = due::format_due_date(&due_at, &Local))
+        )
+    });
 
+    let dates = &options.dates;
+    let rule = options.theme.rule();
+    let title_str = if options.preview_header {
+        String::new()
+    } else {
+        format!("{}\n{rule}\n", note.metadata.original_filename)
+    };
+    let updated_str = if options.preview_header {
+        String::new()
+    } else {
+        format!(
+            "{}\n",
+            t!(
+                "preview-updated",
+                at = dates.format(&note.metadata.updated_at)
+            )
+        )
+    };
     format!(
-        "{}\n\
-         ─────────────────────────────────\n\
-         Created: {}\n\
-         Updated: {}\n\
-         Words: {word_count}\n\
+        "{title_str}\
+         {}\n\
+         {updated_str}\
+         {stats_str}\
+         {}\n\
+         {due_str}\
          {tag_str}\
-         ─────────────────────────────────\n\
+         {rule}\n\
          {content_str}",
-        note.metadata.original_filename,
-        note.metadata.created_at.format("%Y-%m-%d %H:%M"),
-        note.metadata.updated_at.format("%Y-%m-%d %H:%M"),
+        t!(
+            "preview-created",
+            at = dates.format(&note.metadata.created_at)
+        ),
+        t!("preview-size", bytes = size),
+    )
+}
+
+/// Shows what privacy mode lets through of `note`: its title, as `title`, its
+/// dates and its size. With `header`, the title and last update are left to the
+/// header.
+fn format_private_preview(
+    note: &Note,
+    title: &str,
+    size: u64,
+    dates: &DateFormat,
+    header: bool,
+) -> String {
+    let title_str = if header {
+        String::new()
+    } else {
+        format!(
+            "{title}\n{}\n",
+            t!(
+                "preview-updated",
+                at = dates.format(&note.metadata.updated_at)
+            )
+        )
+    };
+    format!(
+        "{title_str}\
+         {}\n\
+         {}\n\
+         \n\
+         {}",
+        t!(
+            "preview-created",
+            at = dates.format(&note.metadata.created_at)
+        ),
+        t!("preview-size", bytes = size),
+        t!("preview-private"),
     )
 }
 
+/// The header of the preview of `note`, shown as `title`: the title, the last
+/// update and, unless `private`, as many tags as fit in `width` columns.
+fn format_preview_header(
+    note: &Note,
+    title: String,
+    content: &str,
+    private: bool,
+    width: usize,
+    options: &AppOptions,
+) -> Text<'static> {
+    let mut updated = t!(
+        "preview-header-updated",
+        at = options.dates.format(&note.metadata.updated_at)
+    );
+    if !note.metadata.note_type.is_plain() {
+        updated.push_str(&format!("  {}", note.metadata.note_type));
+    }
+    if note.metadata.note_type == NoteType::Todo && !private {
+        let (done, total) = content
+            .lines()
+            .filter_map(stats::checklist_item)
+            .fold((0, 0), |(done, total), (_, checked)| {
+                (done + usize::from(checked), total + 1)
+            });
+        updated.push_str(&format!(
+            "  {}",
+            t!("preview-header-checked", done = done, total = total)
+        ));
+    }
+    let tags = if private || note.metadata.tags.is_empty() {
+        String::new()
+    } else {
+        let room = width.saturating_sub(text::width(&updated) + 2);
+        format!("  {}", tags::fit(&note.metadata.tags, room))
+    };
+    let details = Style::default().add_modifier(Modifier::DIM);
+    Text::from(vec![
+        Line::styled(
+            text::ellipsize(&title, width).into_owned(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Line::from(vec![
+            Span::styled(updated, details),
+            Span::styled(tags, options.theme.tone(Tone::Good)),
+        ]),
+        Line::styled(options.theme.rule(), details),
+    ])
+}
+
+/// Lines taken by the header of the preview.
+const PREVIEW_HEADER_HEIGHT: u16 = 3;
+
+/// How many characters of a title privacy mode shows with `privacy_masks_titles`.
+const SHOWN_TITLE_CHARS: usize = 3;
+
+/// `title` with all but its first few characters hidden, always by the same
+/// mask so its length doesn't show either.
+fn mask_title(title: &str, theme: &Theme) -> String {
+    let shown = text::first_graphemes(title, SHOWN_TITLE_CHARS);
+    let mask = if theme.plain { "***" } else { "•••" };
+    format!("{shown}{mask}")
+}
+
 /// RAII guard for terminal raw mode and alternate screen
-struct TerminalGuard {
-    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+pub struct TerminalGuard {
+    pub terminal: Terminal<CrosstermBackend<io::Stdout>>,
+}
+
+/// Leaves raw mode and the alternate screen and shows the cursor again, which
+/// drawing hides, ignoring errors since the terminal may already be gone.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+}
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Send + Sync>;
+
+/// A panic hook that runs `cleanup` before `next`, the hook it replaces, so the
+/// panic message is printed on a usable terminal rather than lost on the
+/// alternate screen.
+fn panic_hook(cleanup: impl Fn() + Send + Sync + 'static, next: PanicHook) -> PanicHook {
+    Box::new(move |info| {
+        cleanup();
+        next(info);
+    })
 }
 
 impl TerminalGuard {
-    fn init() -> Result<Self, AppError> {
+    pub fn init() -> Result<Self, AppError> {
+        // Installed before raw mode, so no panic can happen in between
+        static PANIC_HOOK: Once = Once::new();
+        PANIC_HOOK.call_once(|| {
+            let cleanup = || {
+                restore_terminal();
+                editor::remove_plaintext();
+            };
+            std::panic::set_hook(panic_hook(cleanup, std::panic::take_hook()));
+        });
+
         enable_raw_mode().map_err(AppError::Io)?;
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen).map_err(AppError::Io)?;
@@ -74,10 +362,11 @@ impl TerminalGuard {
     }
 }
 
+/// Restores the terminal however the TUI ends, including when `?` returns an
+/// error from the middle of the run loop.
 impl Drop for TerminalGuard {
     fn drop(&mut self) {
-        let _ = disable_raw_mode();
-        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        restore_terminal();
     }
 }
 
@@ -107,113 +396,1385 @@ enum RunningState {
     Quit,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 enum Message {
     Tick,
+    /// The metadata of a note listed with a placeholder was parsed in the
+    /// background.
+    MetadataLoaded(String, Box<NoteMetadata>),
+    /// The metadata of a note listed with a placeholder couldn't be parsed.
+    MetadataFailed(String),
     Quit,
     NewNote,
+    /// Ask for the title of a new note of the text on the clipboard.
+    NewNoteFromClipboard,
+    /// Go on with clipboard text over `clipboard::LARGE_PASTE`.
+    ConfirmPaste,
     EditSelectedNote,
+    EditBinaryNote,
+    ToggleHexDump,
     ScrollUp,
     ScrollDown,
+    AskDeleteNote,
     DeleteNote,
+    ToggleReadOnly,
+    CycleColor,
+    CycleNoteType,
+    /// List only the notes of the next type, then every note after the last.
+    CycleTypeFilter,
+    /// Switch the preview of Markdown notes between rendered and raw text.
+    ToggleRawPreview,
+    ViewerKey(KeyCode),
+    ShowLinks,
+    LinkKey(KeyCode),
+    Suspend,
+    CycleSort,
+    Reload,
+    SetDueDate,
+    EditTags,
+    ToggleMark,
+    ClearMarks,
+    Filter,
+    ClearFilter,
+    OpenJournal,
+    ToggleJournal,
+    InputChar(char),
+    InputBackspace,
+    SubmitInput,
+    CancelInput,
+    ChooseSuggestion {
+        next: bool,
+    },
+    AcceptSuggestion,
+    OpenHistory,
+    RestoreVersion,
+    CloseHistory,
+    /// Switch the history preview between the version and what changed since.
+    ToggleDiff,
+    ScrollHistory {
+        down: bool,
+    },
+    ScrollPreview {
+        down: bool,
+    },
+    /// Scroll the preview to the next line with a match of the filter, or the
+    /// previous one.
+    FindInPreview {
+        next: bool,
+    },
+    Sync,
+    AskResolveConflict,
+    ResolveConflict {
+        keep: bool,
+    },
+    ResolveDuplicateTitle {
+        open: bool,
+    },
+    OpenPalette,
+    PaletteKey(KeyCode),
+    Undo,
+    TogglePrivacy,
+    ShowIssues,
+    IssueKey(KeyCode),
+    AskFixIssue,
+    FixIssue,
+    AskSlot,
+    TogglePin,
+    /// Move the selected pinned note up or down among the pinned notes.
+    MovePinned {
+        up: bool,
+    },
+    /// Give the selected note this slot, or clear its slot with 0.
+    AssignSlot(u8),
+    JumpToSlot(u8),
+    OpenSlot(u8),
+    /// Decrypt the preview of the selected note even though it's over
+    /// `preview_decrypt_limit`.
+    DecryptPreview,
+    ShowTodos,
+    TodoKey(KeyCode),
+    /// Select the note of the selected todo in the list.
+    OpenTodo,
+    CheckTodo,
+    /// Count the statistics of the vault in the background and show them.
+    ShowStatistics,
+    CloseStatistics,
+    /// Open the selected note in the viewer, since it can't be saved.
+    ViewSelectedNote,
+    /// Save the edit that couldn't be saved again.
+    RetrySave,
+    /// Ask where to write the edit that couldn't be saved.
+    AskExportUnsaved,
+    /// Prune the vault as `ryokan gc --apply` does. Only in the command
+    /// palette.
+    CollectGarbage,
+    /// Hide the notes that came due from the status area and the list.
+    DismissReminder,
+}
+
+impl Message {
+    /// Whether handling this changes the vault, which read-only mode refuses.
+    fn changes_vault(&self) -> bool {
+        matches!(
+            self,
+            Message::NewNote
+                | Message::NewNoteFromClipboard
+                | Message::ConfirmPaste
+                | Message::AskDeleteNote
+                | Message::DeleteNote
+                | Message::ToggleReadOnly
+                | Message::CycleColor
+                | Message::CycleNoteType
+                | Message::SetDueDate
+                | Message::EditTags
+                | Message::OpenJournal
+                | Message::RestoreVersion
+                | Message::Sync
+                | Message::AskResolveConflict
+                | Message::Undo
+                | Message::CollectGarbage
+                | Message::AskFixIssue
+                | Message::FixIssue
+                | Message::AskSlot
+                | Message::AssignSlot(_)
+                | Message::TogglePin
+                | Message::MovePinned { .. }
+                | Message::CheckTodo
+                | Message::ResolveConflict { .. }
+                | Message::ResolveDuplicateTitle { open: false }
+        )
+    }
+}
+
+/// An action of the note list, run by its key or from the command palette.
+struct Action {
+    /// Message key of its name in the command palette.
+    name: &'static str,
+    key: KeyCode,
+    message: Message,
+    /// Message key of its label in the help line, if it's listed there.
+    help: Option<&'static str>,
+}
+
+/// Every action of the note list, in the order of the help line. The key
+/// handler, the help line and the command palette all go through it.
+const ACTIONS: [Action; 37] = [
+    Action {
+        name: "action-open-note",
+        key: KeyCode::Enter,
+        message: Message::EditSelectedNote,
+        help: Some("help-open"),
+    },
+    Action {
+        name: "action-next-match-in-the-preview",
+        key: KeyCode::Char('n'),
+        message: Message::FindInPreview { next: true },
+        help: Some("help-next-match"),
+    },
+    Action {
+        name: "action-previous-match-in-the-preview",
+        key: KeyCode::Char('N'),
+        message: Message::FindInPreview { next: false },
+        help: Some("help-previous-match"),
+    },
+    Action {
+        name: "action-new-note",
+        key: KeyCode::Char('n'),
+        message: Message::NewNote,
+        help: Some("help-new-note"),
+    },
+    Action {
+        name: "action-new-note-from-clipboard",
+        key: KeyCode::Char('P'),
+        message: Message::NewNoteFromClipboard,
+        help: None,
+    },
+    Action {
+        name: "action-delete-note",
+        key: KeyCode::Char('d'),
+        message: Message::AskDeleteNote,
+        help: Some("help-delete"),
+    },
+    Action {
+        name: "action-undo-last-change",
+        key: KeyCode::Char('u'),
+        message: Message::Undo,
+        help: Some("help-undo"),
+    },
+    Action {
+        name: "action-toggle-read-only",
+        key: KeyCode::Char('r'),
+        message: Message::ToggleReadOnly,
+        help: Some("help-toggle-read-only"),
+    },
+    Action {
+        name: "action-change-color-label",
+        key: KeyCode::Char('L'),
+        message: Message::CycleColor,
+        help: Some("help-label"),
+    },
+    Action {
+        name: "action-change-note-type",
+        key: KeyCode::Char('K'),
+        message: Message::CycleNoteType,
+        help: None,
+    },
+    Action {
+        name: "action-assign-quick-access-slot",
+        key: KeyCode::Char('f'),
+        message: Message::AskSlot,
+        help: Some("help-slot"),
+    },
+    Action {
+        name: "action-pin-or-unpin-note",
+        key: KeyCode::Char('T'),
+        message: Message::TogglePin,
+        help: Some("help-pin"),
+    },
+    Action {
+        name: "action-change-sort-order",
+        key: KeyCode::Char('s'),
+        message: Message::CycleSort,
+        help: Some("help-sort"),
+    },
+    Action {
+        name: "action-set-due-date",
+        key: KeyCode::Char('t'),
+        message: Message::SetDueDate,
+        help: Some("help-due-date"),
+    },
+    Action {
+        name: "action-edit-tags",
+        key: KeyCode::Char('#'),
+        message: Message::EditTags,
+        help: Some("help-tags"),
+    },
+    Action {
+        name: "action-mark-note",
+        key: KeyCode::Char(' '),
+        message: Message::ToggleMark,
+        help: Some("help-mark"),
+    },
+    Action {
+        name: "action-dismiss-reminder",
+        key: KeyCode::Esc,
+        message: Message::DismissReminder,
+        help: None,
+    },
+    Action {
+        name: "action-clear-marks",
+        key: KeyCode::Esc,
+        message: Message::ClearMarks,
+        help: None,
+    },
+    Action {
+        name: "action-filter-notes-by-title",
+        key: KeyCode::Char('/'),
+        message: Message::Filter,
+        help: Some("help-filter"),
+    },
+    Action {
+        name: "action-clear-filter",
+        key: KeyCode::Esc,
+        message: Message::ClearFilter,
+        help: None,
+    },
+    Action {
+        name: "action-open-todays-journal",
+        key: KeyCode::Char('j'),
+        message: Message::OpenJournal,
+        help: Some("help-today"),
+    },
+    Action {
+        name: "action-toggle-journal-view",
+        key: KeyCode::Char('J'),
+        message: Message::ToggleJournal,
+        help: Some("help-journal"),
+    },
+    Action {
+        name: "action-filter-by-type",
+        key: KeyCode::Char('Y'),
+        message: Message::CycleTypeFilter,
+        help: None,
+    },
+    Action {
+        name: "action-show-history",
+        key: KeyCode::Char('h'),
+        message: Message::OpenHistory,
+        help: Some("help-history"),
+    },
+    Action {
+        name: "action-resolve-conflict",
+        key: KeyCode::Char('c'),
+        message: Message::AskResolveConflict,
+        help: Some("help-resolve-conflict"),
+    },
+    Action {
+        name: "action-show-links",
+        key: KeyCode::Char('l'),
+        message: Message::ShowLinks,
+        help: Some("help-links"),
+    },
+    Action {
+        name: "action-show-todos",
+        key: KeyCode::Char('x'),
+        message: Message::ShowTodos,
+        help: Some("help-todos"),
+    },
+    Action {
+        name: "action-show-statistics",
+        key: KeyCode::Char('S'),
+        message: Message::ShowStatistics,
+        help: None,
+    },
+    Action {
+        name: "action-toggle-hex-dump",
+        key: KeyCode::Char('X'),
+        message: Message::ToggleHexDump,
+        help: Some("help-hex"),
+    },
+    Action {
+        name: "action-toggle-rendered-markdown",
+        key: KeyCode::Char('M'),
+        message: Message::ToggleRawPreview,
+        help: None,
+    },
+    Action {
+        name: "action-decrypt-large-note-for-the-preview",
+        key: KeyCode::Char('v'),
+        message: Message::DecryptPreview,
+        help: None,
+    },
+    Action {
+        name: "action-toggle-privacy-mode",
+        key: KeyCode::Char('p'),
+        message: Message::TogglePrivacy,
+        help: Some("help-private"),
+    },
+    Action {
+        name: "action-sync",
+        key: KeyCode::Char('y'),
+        message: Message::Sync,
+        help: None,
+    },
+    Action {
+        name: "action-show-issues",
+        key: KeyCode::Char('i'),
+        message: Message::ShowIssues,
+        help: None,
+    },
+    Action {
+        name: "action-reload-notes",
+        key: KeyCode::F(5),
+        message: Message::Reload,
+        help: Some("help-reload"),
+    },
+    Action {
+        name: "action-command-palette",
+        key: KeyCode::Char(':'),
+        message: Message::OpenPalette,
+        help: Some("help-commands"),
+    },
+    Action {
+        name: "action-quit",
+        key: KeyCode::Char('q'),
+        message: Message::Quit,
+        help: Some("help-quit"),
+    },
+];
+
+/// How `key` is written in the help line and the palette.
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        key => key.to_string(),
+    }
+}
+
+/// The previous versions of a note, opened from the list.
+struct HistoryView {
+    uuid: String,
+    title: String,
+    versions: Vec<NoteVersion>,
+    list_state: ListState,
+    preview: Text<'static>,
+    /// Show what changed from the selected version to the current content,
+    /// rather than the version itself.
+    diff: bool,
+    /// Lines of the preview scrolled past.
+    scroll: u16,
+}
+
+impl HistoryView {
+    fn selected(&self) -> Option<&NoteVersion> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.versions.get(index))
+    }
+}
+
+/// The notes missing one of their files, listed to fix them one by one.
+struct IssuesView {
+    list_state: ListState,
+    /// The fix of the selected one waits for confirmation.
+    confirming: bool,
+}
+
+/// The statistics of the vault, opened from the list.
+enum Statistics {
+    /// Being counted in the background.
+    Counting(mpsc::Receiver<Result<VaultStats, AppError>>),
+    Counted(Box<VaultStats>),
+}
+
+/// The unchecked checklist items of every note, grouped by note.
+struct TodoView {
+    found: Vec<NoteTodos>,
+    /// One row per note, then one per item of it, as indexes into `found`.
+    rows: Vec<(usize, Option<usize>)>,
+    list_state: ListState,
+}
+
+impl TodoView {
+    fn new(found: Vec<NoteTodos>, selected: usize) -> Self {
+        let rows = found
+            .iter()
+            .enumerate()
+            .flat_map(|(i, note)| {
+                std::iter::once((i, None)).chain((0..note.todos.len()).map(move |j| (i, Some(j))))
+            })
+            .collect();
+        let mut view = Self {
+            found,
+            rows,
+            list_state: ListState::default(),
+        };
+        view.select(selected.min(view.rows.len().saturating_sub(1)), true);
+        view
+    }
+
+    /// Selects the first item from row `from`, looking forward or backward,
+    /// and keeps the selection if there's none that way.
+    fn select(&mut self, from: usize, forward: bool) {
+        let rows = self.rows.iter().enumerate();
+        let item = if forward {
+            rows.skip(from).find(|(_, (_, todo))| todo.is_some())
+        } else {
+            rows.take(from + 1).rfind(|(_, (_, todo))| todo.is_some())
+        };
+        if let Some((row, _)) = item {
+            self.list_state.select(Some(row));
+        }
+    }
+
+    /// The note of the selected row, and the line of its item.
+    fn selected(&self) -> Option<(&Note, usize)> {
+        let (i, j) = self.rows.get(self.list_state.selected()?)?;
+        let note = self.found.get(*i)?;
+        Some((&note.note, note.todos.get((*j)?)?.line))
+    }
+}
+
+/// What's wrong with `orphan`, naming it by `title` if its metadata gives one.
+fn describe_orphan(orphan: &Orphan, title: Option<String>) -> String {
+    match (orphan, title) {
+        (Orphan::Metadata { .. }, Some(title)) => t!("orphan-no-content", title = title),
+        (Orphan::Metadata { uuid, .. }, None) => t!("orphan-metadata-only", uuid = uuid),
+        (Orphan::Content { uuid }, _) => t!("orphan-content-only", uuid = uuid),
+    }
+}
+
+/// What a line of text typed at the bottom of the screen is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputPurpose {
+    DueDate,
+    /// The title of a new note of the text taken from the clipboard.
+    PastedTitle,
+    /// The text titles of listed notes contain.
+    Filter,
+    Title,
+    /// The tags of the selected note, or an edit to the tags of the marked notes.
+    Tags,
+    /// Where to write an edit that couldn't be saved.
+    ExportPath,
+}
+
+/// A line of text being typed.
+struct Input {
+    purpose: InputPurpose,
+    text: String,
+    /// Existing tags completing the one being typed, best first.
+    suggestions: Vec<String>,
+    /// Index of the suggestion Tab accepts.
+    choice: usize,
+    /// The type of the new note whose title is being typed, which Tab changes.
+    note_type: NoteType,
+}
+
+impl Input {
+    fn new(purpose: InputPurpose, text: String) -> Self {
+        Self {
+            purpose,
+            text,
+            suggestions: Vec::new(),
+            choice: 0,
+            note_type: NoteType::Plain,
+        }
+    }
+
+    /// Whether this is the title of a new note.
+    fn is_title(&self) -> bool {
+        matches!(
+            self.purpose,
+            InputPurpose::Title | InputPurpose::PastedTitle
+        )
+    }
+}
+
+/// How many tag suggestions are shown at most.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// A new note whose title is already taken, waiting for the user to choose
+/// between opening the existing note and creating another one.
+struct DuplicateTitle {
+    title: String,
+    note_type: NoteType,
+    content: Zeroizing<String>,
+    existing: Note,
+}
+
+/// An edit that couldn't be saved, kept in a plaintext file until it's saved
+/// or written elsewhere.
+struct UnsavedEdit {
+    uuid: String,
+    title: String,
+    path: PathBuf,
+    /// The revision the edit began at, to save it as a conflict copy if the
+    /// note changed since.
+    base: NoteRevision,
+}
+
+/// Settings of the TUI that come from outside the vault.
+pub struct AppOptions {
+    pub editor: String,
+    /// How to tell the editor where to put the cursor, if it's not one Ryokan
+    /// knows.
+    pub editor_cursor_arg: Option<String>,
+    /// Name of the active profile, shown in the list title.
+    pub profile: Option<String>,
+    /// Set when syncing is configured.
+    pub sync: Option<GitSync>,
+    /// How much of the selected note the preview shows, in bytes.
+    pub preview_max_bytes: usize,
+    /// Larger notes, in bytes, are only decrypted for the preview once asked to.
+    pub preview_decrypt_limit: u64,
+    /// Show the title, tags and last update of the selected note apart, above
+    /// its content.
+    pub preview_header: bool,
+    /// How long to wait for a key before checking on the selected note and
+    /// signals.
+    pub poll_interval: Duration,
+    /// Ask before creating a note with the title of an existing one.
+    pub warn_duplicate_titles: bool,
+    /// With more notes to parse than this, they're listed right away with
+    /// placeholder titles and parsed in the background, see [`LazyLoad`].
+    pub lazy_load_above: usize,
+    pub dates: DateFormat,
+    /// How journal notes are titled and created.
+    pub journal: Journal,
+    pub theme: Theme,
+    /// Start in privacy mode.
+    pub private: bool,
+    /// Hide titles after their first few characters in privacy mode.
+    pub privacy_masks_titles: bool,
+    /// Also announce notes coming due with a desktop notification.
+    pub desktop_notifications: bool,
+    /// Show an excerpt of each note under its title, see [`snippet`](ryokan::snippet).
+    pub list_snippets: bool,
+    /// Title of new notes when none is typed.
+    pub new_note_title: Template,
+    /// Shown on the welcome screen of an empty vault.
+    pub notes_dir: PathBuf,
+    /// What collecting garbage from the command palette deletes.
+    pub gc_policy: gc::Policy,
+    /// Set when snapshots are taken on a schedule, at startup and after the
+    /// vault changes.
+    pub backups: Option<Backups>,
+    /// Shown at the bottom of the screen on start, e.g. about file permissions.
+    pub startup_warnings: Vec<String>,
+}
+
+/// What the TUI starts at, instead of the first note.
+pub enum Start {
+    /// The note with this UUID, title or alias, as [`Vault::find`] finds it.
+    /// If several notes have that title, the list is filtered to them.
+    Note(String),
+    /// The list filtered by this text.
+    Search(String),
+}
+
+/// How often the screen is drawn again though nothing happened, for the
+/// relative times and due dates it shows.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the TUI looks for notes that came due, on a tick.
+const DUE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Decides when the screen is drawn: after an event or a change noticed on a
+/// tick, and every [`REFRESH_INTERVAL`], but not for ticks where nothing
+/// happened, so an idle TUI stays idle.
+struct Redraw {
+    dirty: bool,
+    last: Option<Instant>,
+    /// Frames drawn and ticks that didn't need one, for `--timing`.
+    draws: usize,
+    skipped: usize,
+}
+
+impl Redraw {
+    fn new() -> Self {
+        Self {
+            dirty: true,
+            last: None,
+            draws: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Something shown changed.
+    fn mark(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Every message but a tick changes something shown, if only the help line.
+    /// Metadata parsed in the background is only shown in batches, see
+    /// [`LazyLoad`].
+    fn after(&mut self, message: &Message) {
+        if !matches!(
+            message,
+            Message::Tick | Message::MetadataLoaded(..) | Message::MetadataFailed(_)
+        ) {
+            self.mark();
+        }
+    }
+
+    /// Whether to draw the screen at `now`, counting it as drawn if so.
+    fn take(&mut self, now: Instant) -> bool {
+        let stale = self
+            .last
+            .is_none_or(|last| now.duration_since(last) >= REFRESH_INTERVAL);
+        if !self.dirty && !stale {
+            self.skipped += 1;
+            return false;
+        }
+        self.dirty = false;
+        self.last = Some(now);
+        self.draws += 1;
+        true
+    }
+}
+
+/// How many notes parsed in the background are put into the list at once, at
+/// most, since the list is sorted again every time.
+const LAZY_BATCH: usize = 500;
+
+/// How long notes parsed in the background wait at most to be put into the
+/// list, for the progress to move along when parsing is slow.
+const LAZY_APPLY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long to wait for a key at most while notes are parsed in the
+/// background, so they're taken in without waiting for `poll_interval`.
+const LAZY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The metadata of a large vault, being parsed on a background thread after
+/// the notes were listed with placeholders by [`Vault::list_lazily`].
+struct LazyLoad {
+    /// The metadata of each note as it's parsed, or `None` if it can't be.
+    receiver: mpsc::Receiver<(String, Option<NoteMetadata>)>,
+    /// UUIDs of the notes whose metadata hasn't come in yet.
+    awaited: HashSet<String>,
+    total: usize,
+    /// Metadata that came in since the list was last sorted, or `None` for notes
+    /// left out since theirs can't be parsed.
+    arrived: HashMap<String, Option<NoteMetadata>>,
+    last_applied: Instant,
+}
+
+impl LazyLoad {
+    /// Starts parsing the metadata of `pending`, in order, on another thread.
+    fn start(vault: Arc<Vault>, pending: Vec<String>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let awaited = pending.iter().cloned().collect();
+        let total = pending.len();
+        thread::spawn(move || {
+            for uuid in pending {
+                let metadata = vault
+                    .load_metadata(&uuid)
+                    .inspect_err(|e| error!("Error loading metadata for {uuid}: {e}"))
+                    .ok();
+                // The TUI stopped waiting, e.g. the notes were reloaded
+                if sender.send((uuid, metadata)).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            receiver,
+            awaited,
+            total,
+            arrived: HashMap::new(),
+            last_applied: Instant::now(),
+        }
+    }
+
+    fn loaded(&self) -> usize {
+        self.total - self.awaited.len()
+    }
+
+    /// Whether what came in should be put into the list now.
+    fn due(&self) -> bool {
+        self.awaited.is_empty()
+            || self.arrived.len() >= LAZY_BATCH
+            || (!self.arrived.is_empty() && self.last_applied.elapsed() >= LAZY_APPLY_INTERVAL)
+    }
+}
+
+/// `n` with its digits grouped by thousands, e.g. `20,008`.
+fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// The first line of `content` after line `from` containing `query`, ignoring
+/// case, or the last one before it unless `forward`, wrapping around.
+fn find_match_line(content: &str, query: &str, from: usize, forward: bool) -> Option<usize> {
+    let lines: Vec<usize> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !text::find_ignoring_case(line, query).is_empty())
+        .map(|(index, _)| index)
+        .collect();
+    let found = if forward {
+        lines.iter().find(|&&line| line > from).or(lines.first())
+    } else {
+        lines
+            .iter()
+            .rev()
+            .find(|&&line| line < from)
+            .or(lines.last())
+    };
+    found.copied()
 }
 
 pub struct App {
-    config: Config,
-    pin: Zeroizing<String>,
-    args: Args,
+    vault: Arc<Vault>,
+    options: AppOptions,
     notes: Vec<Note>,
     list_state: ListState,
     selected_note_index: usize,
     note_preview_content: String,
+    /// Lines of the content of the preview scrolled past.
+    preview_scroll: u16,
+    /// The note the preview was decrypted from, and its file back then, to
+    /// notice when another program changes it.
+    preview_stamp: Option<(String, FileStamp)>,
     running_state: RunningState,
     show_delete_prompt: bool,
+    viewer: Option<Viewer>,
+    sort_mode: SortMode,
+    input: Option<Input>,
+    /// Shown in place of the help line until the next key press.
+    status_message: Option<String>,
+    history: Option<HistoryView>,
+    show_conflict_prompt: bool,
+    /// Waiting for the digit of the slot to give the selected note.
+    show_slot_prompt: bool,
+    duplicate_title: Option<DuplicateTitle>,
+    sync_status: Option<SyncStatus>,
+    /// Size of the content of the selected note, if it's binary.
+    selected_binary_len: Option<usize>,
+    /// Show binary content as a hex dump rather than only its size.
+    hex_dump: bool,
+    show_binary_prompt: bool,
+    /// UUIDs of the notes marked for a bulk tag edit.
+    marked: BTreeSet<String>,
+    /// Stats of the notes previewed so far.
+    stats: StatsCache,
+    /// The links of the selected note, when listed.
+    links: Option<LinkPicker>,
+    /// List only journal notes, by date.
+    journal_only: bool,
+    /// List only notes of this type; [`NoteType::Other`] stands for every
+    /// unknown type.
+    type_filter: Option<NoteType>,
+    /// Show Markdown notes as they are in the preview rather than rendered.
+    raw_preview: bool,
+    /// The notes announced as due this session, taken in at the first check.
+    reminders: Option<Reminders>,
+    last_due_check: Option<Instant>,
+    /// UUIDs and titles of the notes that came due, until dismissed.
+    due_reminder: Vec<(String, String)>,
+    /// Decrypted snippets by UUID, with the sealed text they came from.
+    snippets: HashMap<String, (String, String)>,
+    /// List only notes whose title or an alias contains this, ignoring case.
+    filter: Option<String>,
+    /// The command palette, when open, with the message of each entry.
+    palette: Option<(Palette, Vec<Message>)>,
+    /// Changes made this session that `u` can undo.
+    undo: UndoStack,
+    /// Hide the content of notes and don't copy links, e.g. while sharing the
+    /// screen. It's only a filter on what's shown, for this session.
+    private: bool,
+    /// Notes missing one of their files, found when the notes were last listed.
+    orphans: Vec<Orphan>,
+    /// The list of `orphans`, when open.
+    issues: Option<IssuesView>,
+    /// UUID of the note over `preview_decrypt_limit` decrypted for the preview
+    /// anyway.
+    decrypt_anyway: Option<String>,
+    /// The unchecked items of every note, when listed.
+    todos: Option<TodoView>,
+    /// Items of the notes listed so far.
+    todo_cache: TodoCache,
+    /// The statistics of the vault, when shown.
+    statistics: Option<Statistics>,
+    /// UUIDs of the notes found unwritable this session, e.g. on read-only
+    /// media.
+    unwritable: HashSet<String>,
+    /// The notes directory can't be written to, when the notes were last
+    /// listed, though the vault isn't in read-only mode.
+    read_only_media: bool,
+    /// Why the selected note can't be saved, while offering to view it instead.
+    unwritable_prompt: Option<String>,
+    unsaved: Option<UnsavedEdit>,
+    /// Text taken from the clipboard for a new note, while its title is typed.
+    pasted: Option<Zeroizing<String>>,
+    /// Waiting to confirm making a note of more than `clipboard::LARGE_PASTE`.
+    show_paste_prompt: bool,
+    redraw: Redraw,
+    /// The metadata being parsed in the background, while some notes are
+    /// listed with placeholders.
+    lazy: Option<LazyLoad>,
+    /// How the snapshot being taken in the background went, once it's done.
+    backup: Option<mpsc::Receiver<Result<Option<Snapshot>, AppError>>>,
 }
 
 impl App {
-    pub fn new(config: Config, pin: Zeroizing<String>, args: Args) -> Result<Self, AppError> {
+    pub fn new(vault: Vault, options: AppOptions, start: Option<Start>) -> Result<Self, AppError> {
+        let private = options.private;
         let mut app = Self {
-            config,
-            pin,
-            args,
+            vault: Arc::new(vault),
+            options,
             notes: Vec::new(),
             list_state: ListState::default(),
             selected_note_index: 0,
             note_preview_content: String::new(),
+            preview_scroll: 0,
+            preview_stamp: None,
             running_state: RunningState::Running,
             show_delete_prompt: false,
+            viewer: None,
+            sort_mode: SortMode::default(),
+            input: None,
+            status_message: None,
+            history: None,
+            show_conflict_prompt: false,
+            show_slot_prompt: false,
+            duplicate_title: None,
+            sync_status: None,
+            selected_binary_len: None,
+            hex_dump: false,
+            show_binary_prompt: false,
+            marked: BTreeSet::new(),
+            stats: StatsCache::default(),
+            links: None,
+            journal_only: false,
+            type_filter: None,
+            raw_preview: false,
+            reminders: None,
+            last_due_check: None,
+            due_reminder: Vec::new(),
+            snippets: HashMap::new(),
+            filter: None,
+            palette: None,
+            undo: UndoStack::default(),
+            private,
+            orphans: Vec::new(),
+            issues: None,
+            decrypt_anyway: None,
+            todos: None,
+            todo_cache: TodoCache::default(),
+            statistics: None,
+            unwritable: HashSet::new(),
+            read_only_media: false,
+            unwritable_prompt: None,
+            unsaved: None,
+            pasted: None,
+            show_paste_prompt: false,
+            redraw: Redraw::new(),
+            lazy: None,
+            backup: None,
         };
-        app.reload_notes()?;
+        app.refresh_sync_status();
+        app.start_backup();
+        // Finding the note to start at takes every title
+        if start.is_some() {
+            app.reload_notes()?;
+        } else {
+            app.load_notes()?;
+        }
+        app.report_unexpected_files()?;
+        match start {
+            Some(Start::Note(query)) => app.start_at(query)?,
+            Some(Start::Search(text)) => {
+                app.filter = Some(text);
+                app.reload_notes()?;
+            }
+            None => {}
+        }
+        if !app.options.startup_warnings.is_empty() {
+            app.status_message = Some(app.options.startup_warnings.join("; "));
+        }
 
         if !app.notes.is_empty() {
             app.list_state.select(Some(app.selected_note_index));
+            // Decrypted once the first frame is drawn, see `run`
+            app.note_preview_content = t!("preview-decrypting");
+        } else {
+            app.update_preview_content();
         }
 
-        app.note_preview_content =
-            Self::load_preview_content(&app.notes, app.selected_note_index, &app.pin);
-
         Ok(app)
     }
 
-    fn load_preview_content(notes: &[Note], index: usize, pin: &str) -> String {
-        if let Some(note) = notes.get(index) {
-            match file::load_and_decrypt_note_content(&note.encrypted_file_path, pin) {
-                Ok(content) => format_preview_content(note, &content),
-                Err(e) => format!("Error reading note: {e}"),
+    /// Selects the note `query` names, like [`Vault::find`], or filters the
+    /// list to the notes with that title if there are several.
+    fn start_at(&mut self, query: String) -> Result<(), AppError> {
+        let found: Vec<_> = self
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| {
+                note.uuid == query
+                    || std::iter::once(&note.metadata.original_filename)
+                        .chain(&note.metadata.aliases)
+                        .any(|title| title.eq_ignore_ascii_case(&query))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        match found[..] {
+            [] => return Err(AppError::NoteNotFound(query)),
+            [index] => self.selected_note_index = index,
+            _ => {
+                self.status_message =
+                    Some(t!("status-same-title", count = found.len(), title = query));
+                self.filter = Some(query);
+                self.reload_notes()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// How many metadata files were parsed to list the notes.
+    pub fn metadata_parses(&self) -> usize {
+        self.vault.metadata_parses()
+    }
+
+    /// The preview of the selected note, and the size of its content if it's
+    /// binary.
+    fn load_preview_content(&mut self) -> (String, Option<usize>) {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return (t!("preview-none-selected"), None);
+        };
+        if let Some(e) = self.preview_limit(note) {
+            return (format!("{e}\n\n{}", t!("preview-decrypt-anyway")), None);
+        }
+        match self.vault.read(&note.uuid) {
+            Ok(mut content) => {
+                let preview = if self.private {
+                    let size = note.metadata.size.unwrap_or(content.len() as u64);
+                    let title = self.shown_title(&note.metadata.original_filename);
+                    format_private_preview(
+                        note,
+                        &title,
+                        size,
+                        &self.options.dates,
+                        self.options.preview_header,
+                    )
+                } else {
+                    let stats = std::str::from_utf8(&content)
+                        .ok()
+                        .map(|text| self.stats.get(&note.uuid, text));
+                    format_preview_content(
+                        note,
+                        &content,
+                        stats.as_ref(),
+                        self.hex_dump,
+                        &self.options,
+                    )
+                };
+                let binary_len = (!export::is_text(&content)).then_some(content.len());
+                let refreshed = self.vault.refresh_snippet(&note.uuid, &content);
+                // Only the part shown is kept in memory
+                content.zeroize();
+                self.show_refreshed_snippet(refreshed);
+                (preview, binary_len)
+            }
+            Err(e) => (t!("preview-error", error = e), None),
+        }
+    }
+
+    /// Puts the metadata of the selected note with the snippet
+    /// [`Vault::refresh_snippet`] took anew into the list.
+    fn show_refreshed_snippet(&mut self, refreshed: Result<Option<NoteMetadata>, AppError>) {
+        match refreshed {
+            Ok(Some(metadata)) => {
+                if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+                    note.metadata = metadata;
+                    self.open_snippets();
+                }
             }
+            Ok(None) => {}
+            // Only the list is less informative
+            Err(e) => warn!("Could not refresh the snippet: {e}"),
+        }
+    }
+
+    /// Decrypts the snippets of the listed notes that changed since they were
+    /// last decrypted.
+    fn open_snippets(&mut self) {
+        if !self.options.list_snippets {
+            return;
+        }
+        let mut snippets = HashMap::new();
+        for note in &self.notes {
+            let Some(sealed) = &note.metadata.snippet else {
+                continue;
+            };
+            let text = match self.snippets.remove(&note.uuid) {
+                Some((known, text)) if known == *sealed => text,
+                _ => match self.vault.open_snippet(&note.metadata) {
+                    Some(snippet) => snippet.text,
+                    None => continue,
+                },
+            };
+            snippets.insert(note.uuid.clone(), (sealed.clone(), text));
+        }
+        self.snippets = snippets;
+    }
+
+    /// The snippet shown under the title of the note `uuid`, unless it's
+    /// hidden in privacy mode.
+    fn shown_snippet(&self, uuid: &str) -> Option<&str> {
+        if self.private {
+            return None;
+        }
+        self.snippets
+            .get(uuid)
+            .map(|(_, text)| text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Why the preview of `note` isn't decrypted without asking, if it's over
+    /// `preview_decrypt_limit`.
+    fn preview_limit(&self, note: &Note) -> Option<AppError> {
+        if self.decrypt_anyway.as_deref() == Some(note.uuid.as_str()) {
+            return None;
+        }
+        let size = self.vault.stored_size(&note.uuid).ok()?;
+        (size > self.options.preview_decrypt_limit).then(|| AppError::TooLarge {
+            uuid: note.uuid.clone(),
+            size,
+            limit: self.options.preview_decrypt_limit,
+            key: "preview_decrypt_limit",
+        })
+    }
+
+    /// How `title` is shown: masked in privacy mode with `privacy_masks_titles`.
+    fn shown_title(&self, title: &str) -> String {
+        if self.private && self.options.privacy_masks_titles {
+            mask_title(title, &self.options.theme)
         } else {
-            "No note selected.".to_string()
+            title.to_string()
         }
     }
 
     fn update_preview_content(&mut self) {
-        self.note_preview_content =
-            Self::load_preview_content(&self.notes, self.selected_note_index, &self.pin);
+        self.load_selected_metadata();
+        self.preview_stamp = self.notes.get(self.selected_note_index).and_then(|note| {
+            let stamp = self.vault.stamp(&note.uuid).ok()?;
+            Some((note.uuid.clone(), stamp))
+        });
+        self.note_preview_content.zeroize();
+        (self.note_preview_content, self.selected_binary_len) = self.load_preview_content();
+        self.preview_scroll = 0;
     }
 
-    pub fn run(&mut self) -> Result<(), AppError> {
+    pub fn run(&mut self, timings: &mut Timings) -> Result<(), AppError> {
         let mut guard = TerminalGuard::init()?;
+        let mut first_frame = true;
+        // Checked between events, which are polled with a timeout, so signals are
+        // handled within one poll interval
+        #[cfg(unix)]
+        let mut signals = Signals::new([SIGTSTP, SIGCONT, SIGTERM, SIGHUP])?;
 
         while self.running_state == RunningState::Running {
-            guard
-                .terminal
-                .draw(|f| self.view(f))
-                .map_err(|e| AppError::Tui(e.to_string()))?;
+            if self.redraw.take(Instant::now()) {
+                guard
+                    .terminal
+                    .draw(|f| self.view(f))
+                    .map_err(|e| AppError::Tui(e.to_string()))?;
+            }
+
+            // The list shows up without waiting for the first note to be decrypted
+            if first_frame {
+                first_frame = false;
+                self.redraw.mark();
+                timings.phase("first frame");
+                if !self.notes.is_empty() {
+                    self.update_preview_content();
+                    timings.phase("first preview");
+                }
+                self.check_integrity();
+                timings.phase("integrity check");
+                continue;
+            }
 
             let message = self.handle_event()?;
+            self.redraw.after(&message);
             self.update(message, &mut guard.terminal)?;
+
+            #[cfg(unix)]
+            for signal in signals.pending() {
+                self.redraw.mark();
+                match signal {
+                    SIGTSTP => self.suspend(&mut guard.terminal)?,
+                    // The terminal is restored when the guard is dropped
+                    SIGTERM | SIGHUP => self.running_state = RunningState::Quit,
+                    _ => {}
+                }
+            }
         }
 
+        timings.count("frames drawn", self.redraw.draws);
+        timings.count("idle ticks not drawn", self.redraw.skipped);
+        // Notes deleted this session leave the trash for good
+        self.undo.clear(&self.vault)
+    }
+
+    /// Stops the process like Ctrl-Z normally does, which raw mode prevents.
+    ///
+    /// The screen is cleared and the terminal restored first, so no decrypted text
+    /// stays visible, and the preview is dropped from memory until resuming.
+    fn suspend(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> Result<(), AppError> {
+        // Drawing a blank frame rather than calling `clear()`, which queries the
+        // cursor position and fails when the terminal is going away
+        let blank = |f: &mut ratatui::Frame| f.render_widget(Clear, f.area());
+        self.note_preview_content.zeroize();
+        terminal
+            .draw(blank)
+            .map_err(|e| AppError::Tui(e.to_string()))?;
+        restore_terminal();
+
+        #[cfg(unix)]
+        signal_hook::low_level::emulate_default_handler(SIGTSTP)?;
+
+        enable_raw_mode().map_err(AppError::Io)?;
+        execute!(terminal.backend_mut(), EnterAlternateScreen).map_err(AppError::Io)?;
+        // Leaves a blank buffer behind, so the next draw repaints everything
+        terminal
+            .draw(blank)
+            .map_err(|e| AppError::Tui(e.to_string()))?;
+        self.update_preview_content();
         Ok(())
     }
 
     fn handle_event(&mut self) -> Result<Message, AppError> {
-        event::poll(Duration::from_millis(250))
+        let mut timeout = self.options.poll_interval;
+        if matches!(self.statistics, Some(Statistics::Counting(_))) {
+            timeout = timeout.min(LAZY_POLL_INTERVAL);
+        }
+        // Keys come first, then metadata parsed in the background
+        if let Some(lazy) = &mut self.lazy {
+            timeout = timeout.min(LAZY_POLL_INTERVAL);
+            if !event::poll(Duration::ZERO).map_err(AppError::Io)? {
+                match lazy.receiver.try_recv() {
+                    Ok((uuid, Some(metadata))) => {
+                        return Ok(Message::MetadataLoaded(uuid, Box::new(metadata)));
+                    }
+                    Ok((uuid, None)) => return Ok(Message::MetadataFailed(uuid)),
+                    Err(TryRecvError::Empty) => {}
+                    // Whatever is still awaited is listed as usual on the next tick
+                    Err(TryRecvError::Disconnected) => lazy.awaited.clear(),
+                }
+            }
+        }
+        event::poll(timeout)
             .map_err(AppError::Io)?
             .then(|| event::read().map_err(AppError::Io))
             .transpose()
+            .inspect(|opt_event| {
+                // Keys that only close a prompt, and resizes, are ticks that
+                // still change the screen
+                if opt_event.is_some() {
+                    self.redraw.mark();
+                }
+            })
             .map(|opt_event| match opt_event {
-                Some(Event::Key(key)) => match key.code {
-                    KeyCode::Char('q') => Message::Quit,
-                    KeyCode::Char('n') => Message::NewNote,
+                Some(Event::Key(key))
+                    if cfg!(unix)
+                        && key.code == KeyCode::Char('z')
+                        && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    Message::Suspend
+                }
+                Some(Event::Key(key)) if self.input.is_some() => match key.code {
+                    KeyCode::Char(c) => Message::InputChar(c),
+                    KeyCode::Backspace => Message::InputBackspace,
+                    KeyCode::Enter => Message::SubmitInput,
+                    KeyCode::Esc => Message::CancelInput,
+                    KeyCode::Tab => Message::AcceptSuggestion,
+                    KeyCode::Up => Message::ChooseSuggestion { next: false },
+                    KeyCode::Down => Message::ChooseSuggestion { next: true },
+                    _ => Message::Tick,
+                },
+                Some(Event::Key(key)) if self.history.is_some() => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => Message::CloseHistory,
                     KeyCode::Down => Message::ScrollDown,
                     KeyCode::Up => Message::ScrollUp,
-                    KeyCode::Enter => Message::EditSelectedNote,
-                    KeyCode::Char('d') if self.show_delete_prompt => {
-                        self.show_delete_prompt = false;
-                        Message::DeleteNote
+                    KeyCode::Enter => Message::RestoreVersion,
+                    KeyCode::Char('X') => Message::ToggleHexDump,
+                    KeyCode::Char('d') => Message::ToggleDiff,
+                    KeyCode::PageDown => Message::ScrollHistory { down: true },
+                    KeyCode::PageUp => Message::ScrollHistory { down: false },
+                    _ => Message::Tick,
+                },
+                Some(Event::Key(key))
+                    if self.issues.as_ref().is_some_and(|issues| issues.confirming) =>
+                {
+                    if let Some(issues) = &mut self.issues {
+                        issues.confirming = false;
                     }
-                    KeyCode::Char('d') => {
-                        if !self.notes.is_empty() {
-                            self.show_delete_prompt = true;
-                        }
+                    match key.code {
+                        KeyCode::Char('y') => Message::FixIssue,
+                        _ => Message::Tick,
+                    }
+                }
+                Some(Event::Key(key)) if self.issues.is_some() => match key.code {
+                    KeyCode::Enter => Message::AskFixIssue,
+                    code => Message::IssueKey(code),
+                },
+                Some(Event::Key(key)) if self.statistics.is_some() => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => Message::CloseStatistics,
+                    _ => Message::Tick,
+                },
+                Some(Event::Key(key)) if self.todos.is_some() => match key.code {
+                    KeyCode::Enter => Message::OpenTodo,
+                    KeyCode::Char(' ') => Message::CheckTodo,
+                    code => Message::TodoKey(code),
+                },
+                Some(Event::Key(key)) if self.palette.is_some() => Message::PaletteKey(key.code),
+                Some(Event::Key(key)) if self.viewer.is_some() => Message::ViewerKey(key.code),
+                Some(Event::Key(key)) if self.links.is_some() => Message::LinkKey(key.code),
+                Some(Event::Key(key)) if self.duplicate_title.is_some() => match key.code {
+                    KeyCode::Char('o') => Message::ResolveDuplicateTitle { open: true },
+                    KeyCode::Char('c') => Message::ResolveDuplicateTitle { open: false },
+                    _ => {
+                        self.duplicate_title = None;
                         Message::Tick
                     }
+                },
+                Some(Event::Key(key)) if self.show_binary_prompt => {
+                    self.show_binary_prompt = false;
+                    match key.code {
+                        KeyCode::Char('e') => Message::EditBinaryNote,
+                        _ => Message::Tick,
+                    }
+                }
+                Some(Event::Key(key)) if self.show_slot_prompt => {
+                    self.show_slot_prompt = false;
+                    match key.code {
+                        KeyCode::Char(digit @ '0'..='9') => Message::AssignSlot(digit as u8 - b'0'),
+                        _ => Message::Tick,
+                    }
+                }
+                Some(Event::Key(key)) if self.show_paste_prompt => {
+                    self.show_paste_prompt = false;
+                    match key.code {
+                        KeyCode::Char('y') => Message::ConfirmPaste,
+                        _ => {
+                            self.pasted = None;
+                            Message::Tick
+                        }
+                    }
+                }
+                Some(Event::Key(key)) if self.unwritable_prompt.is_some() => {
+                    self.unwritable_prompt = None;
+                    match key.code {
+                        KeyCode::Char('v') => Message::ViewSelectedNote,
+                        _ => Message::Tick,
+                    }
+                }
+                Some(Event::Key(key)) if self.unsaved.is_some() => match key.code {
+                    KeyCode::Char('r') => Message::RetrySave,
+                    KeyCode::Char('e') => Message::AskExportUnsaved,
                     _ => {
-                        self.show_delete_prompt = false;
+                        if let Some(unsaved) = self.unsaved.take() {
+                            self.status_message =
+                                Some(t!("status-edit-kept", path = unsaved.path.display()));
+                        }
                         Message::Tick
                     }
                 },
+                Some(Event::Key(key)) if self.show_conflict_prompt => {
+                    self.show_conflict_prompt = false;
+                    match key.code {
+                        KeyCode::Char('k') => Message::ResolveConflict { keep: true },
+                        KeyCode::Char('x') => Message::ResolveConflict { keep: false },
+                        _ => Message::Tick,
+                    }
+                }
+                Some(Event::Key(key)) => {
+                    let confirming_delete = std::mem::take(&mut self.show_delete_prompt);
+                    match key.code {
+                        KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            Message::MovePinned { up: true }
+                        }
+                        KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            Message::MovePinned { up: false }
+                        }
+                        KeyCode::Down => Message::ScrollDown,
+                        KeyCode::Up => Message::ScrollUp,
+                        KeyCode::PageDown => Message::ScrollPreview { down: true },
+                        KeyCode::PageUp => Message::ScrollPreview { down: false },
+                        KeyCode::Char('d') if confirming_delete => Message::DeleteNote,
+                        KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            Message::OpenPalette
+                        }
+                        KeyCode::Char(digit @ '1'..='9')
+                            if key.modifiers.contains(KeyModifiers::ALT) =>
+                        {
+                            Message::OpenSlot(digit as u8 - b'0')
+                        }
+                        KeyCode::Char(digit @ '1'..='9') => Message::JumpToSlot(digit as u8 - b'0'),
+                        code => ACTIONS
+                            .iter()
+                            .find(|action| action.key == code && self.applies(action))
+                            .map_or(Message::Tick, |action| action.message.clone()),
+                    }
+                }
                 _ => Message::Tick,
             })
     }
@@ -223,47 +1784,1409 @@ impl App {
         message: Message,
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> Result<(), AppError> {
+        if !matches!(
+            message,
+            Message::Tick | Message::MetadataLoaded(..) | Message::MetadataFailed(_)
+        ) {
+            self.status_message = None;
+        }
+        if message.changes_vault() && self.vault.is_read_only_mode() {
+            self.status_message = Some(t!("status-read-only-mode"));
+            return Ok(());
+        }
+        let changes = message.changes_vault()
+            || matches!(
+                message,
+                Message::EditSelectedNote | Message::EditBinaryNote | Message::RetrySave
+            );
         match message {
             Message::Quit => {
                 self.running_state = RunningState::Quit;
             }
-            Message::NewNote => self.handle_new_note()?,
+            Message::NewNote => {
+                self.input = Some(Input::new(InputPurpose::Title, String::new()));
+            }
+            Message::NewNoteFromClipboard => self.handle_new_note_from_clipboard(),
+            Message::ConfirmPaste => self.ask_pasted_title(),
             Message::ScrollDown => self.handle_scroll_down(),
             Message::ScrollUp => self.handle_scroll_up(),
-            Message::EditSelectedNote => self.handle_edit_selected_note(terminal)?,
+            Message::EditSelectedNote => self.handle_edit_selected_note(false, None, terminal)?,
+            Message::EditBinaryNote => self.handle_edit_selected_note(true, None, terminal)?,
+            Message::ViewSelectedNote => self.open_viewer()?,
+            Message::RetrySave => self.handle_retry_save()?,
+            Message::AskExportUnsaved => {
+                let home = dirs::home_dir().map_or_else(String::new, |home| {
+                    format!("{}{}", home.display(), std::path::MAIN_SEPARATOR)
+                });
+                self.input = Some(Input::new(InputPurpose::ExportPath, home));
+            }
+            Message::ToggleHexDump => {
+                self.hex_dump = !self.hex_dump;
+                if self.history.is_some() {
+                    self.update_history_preview();
+                } else {
+                    self.update_preview_content();
+                }
+            }
+            Message::AskDeleteNote => self.show_delete_prompt = !self.notes.is_empty(),
             Message::DeleteNote => self.handle_delete_note()?,
-            Message::Tick => { /* No action on tick for now */ }
-        }
-        Ok(())
+            Message::ToggleReadOnly => self.handle_toggle_read_only()?,
+            Message::CycleColor => self.handle_cycle_color()?,
+            Message::CycleNoteType => self.handle_cycle_note_type()?,
+            Message::CycleTypeFilter => self.handle_cycle_type_filter()?,
+            Message::ToggleRawPreview => self.raw_preview = !self.raw_preview,
+            Message::DismissReminder => self.due_reminder.clear(),
+            Message::ViewerKey(code) => {
+                if let Some(viewer) = &mut self.viewer
+                    && !viewer.handle_key(code)
+                {
+                    self.viewer = None;
+                }
+            }
+            Message::ShowLinks => self.handle_show_links()?,
+            Message::LinkKey(code) => {
+                if let Some(picker) = &mut self.links
+                    && !picker.handle_key(code)
+                {
+                    self.status_message = picker.message.take();
+                    self.links = None;
+                }
+            }
+            Message::CycleSort => self.handle_cycle_sort(),
+            Message::Reload => self.handle_reload()?,
+            Message::SetDueDate => {
+                if !self.notes.is_empty() {
+                    self.input = Some(Input::new(InputPurpose::DueDate, String::new()));
+                }
+            }
+            Message::EditTags => self.handle_edit_tags()?,
+            Message::ToggleMark => {
+                if let Some(note) = self.notes.get(self.selected_note_index)
+                    && !self.marked.remove(&note.uuid)
+                {
+                    self.marked.insert(note.uuid.clone());
+                }
+                self.handle_scroll_down();
+            }
+            Message::ClearMarks => self.marked.clear(),
+            Message::Filter => {
+                let text = self.filter.clone().unwrap_or_default();
+                self.input = Some(Input::new(InputPurpose::Filter, text));
+            }
+            Message::ClearFilter => self.set_filter(None)?,
+            Message::OpenJournal => self.handle_open_journal(terminal)?,
+            Message::ToggleJournal => self.handle_toggle_journal()?,
+            Message::InputChar(c) => {
+                if let Some(input) = &mut self.input {
+                    input.text.push(c);
+                }
+                self.update_suggestions()?;
+            }
+            Message::InputBackspace => {
+                if let Some(input) = &mut self.input {
+                    text::pop_grapheme(&mut input.text);
+                }
+                self.update_suggestions()?;
+            }
+            Message::ChooseSuggestion { next } => {
+                if let Some(input) = &mut self.input
+                    && !input.suggestions.is_empty()
+                {
+                    let count = input.suggestions.len();
+                    input.choice = if next {
+                        (input.choice + 1) % count
+                    } else {
+                        (input.choice + count - 1) % count
+                    };
+                }
+            }
+            Message::AcceptSuggestion => {
+                if let Some(input) = &mut self.input {
+                    if input.is_title() {
+                        input.note_type = input.note_type.next();
+                    } else if let Some(tag) = input.suggestions.get(input.choice) {
+                        input.text = tags::accept(&input.text, tag);
+                    }
+                }
+                self.update_suggestions()?;
+            }
+            Message::SubmitInput => self.handle_submit_input()?,
+            Message::CancelInput => {
+                self.input = None;
+                self.pasted = None;
+            }
+            Message::OpenHistory => self.handle_open_history()?,
+            Message::RestoreVersion => self.handle_restore_version()?,
+            Message::CloseHistory => self.history = None,
+            Message::ToggleDiff => {
+                if let Some(history) = &mut self.history {
+                    history.diff = !history.diff;
+                }
+                self.update_history_preview();
+            }
+            Message::ScrollHistory { down } => {
+                if let Some(history) = &mut self.history {
+                    history.scroll = if down {
+                        history.scroll.saturating_add(10)
+                    } else {
+                        history.scroll.saturating_sub(10)
+                    };
+                }
+            }
+            Message::ScrollPreview { down } => {
+                self.preview_scroll = if down {
+                    self.preview_scroll.saturating_add(10)
+                } else {
+                    self.preview_scroll.saturating_sub(10)
+                };
+            }
+            Message::FindInPreview { next } => self.handle_find_in_preview(next),
+            Message::Sync => self.handle_sync()?,
+            Message::CollectGarbage => self.handle_collect_garbage()?,
+            Message::AskResolveConflict => {
+                self.show_conflict_prompt = self
+                    .notes
+                    .get(self.selected_note_index)
+                    .is_some_and(|note| note.metadata.conflict_of.is_some());
+            }
+            Message::ResolveConflict { keep } => self.handle_resolve_conflict(keep)?,
+            Message::OpenPalette => self.handle_open_palette(),
+            Message::Undo => self.handle_undo()?,
+            Message::TogglePrivacy => self.handle_toggle_privacy(),
+            Message::ShowIssues => self.handle_show_issues(),
+            Message::IssueKey(code) => self.handle_issue_key(code),
+            Message::AskFixIssue => {
+                if let Some(issues) = &mut self.issues {
+                    issues.confirming = issues.list_state.selected().is_some();
+                }
+            }
+            Message::FixIssue => self.handle_fix_issue()?,
+            Message::DecryptPreview => {
+                self.decrypt_anyway = self
+                    .notes
+                    .get(self.selected_note_index)
+                    .map(|note| note.uuid.clone());
+                self.update_preview_content();
+            }
+            Message::ShowTodos => self.handle_show_todos(0)?,
+            Message::TodoKey(code) => {
+                if let Some(view) = &mut self.todos {
+                    let selected = view.list_state.selected().unwrap_or_default();
+                    match code {
+                        KeyCode::Down | KeyCode::Char('j') => view.select(selected + 1, true),
+                        KeyCode::Up | KeyCode::Char('k') if selected > 0 => {
+                            view.select(selected - 1, false)
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => self.todos = None,
+                        _ => {}
+                    }
+                }
+            }
+            Message::ShowStatistics => self.handle_show_statistics(),
+            Message::CloseStatistics => self.statistics = None,
+            Message::OpenTodo => self.handle_open_todo(),
+            Message::CheckTodo => self.handle_check_todo()?,
+            Message::AskSlot => self.show_slot_prompt = !self.notes.is_empty(),
+            Message::AssignSlot(slot) => self.handle_assign_slot(slot)?,
+            Message::TogglePin => self.handle_toggle_pin()?,
+            Message::MovePinned { up } => self.handle_move_pinned(up)?,
+            Message::JumpToSlot(slot) => {
+                self.handle_jump_to_slot(slot);
+            }
+            Message::OpenSlot(slot) => {
+                if self.handle_jump_to_slot(slot) {
+                    self.handle_edit_selected_note(false, None, terminal)?;
+                }
+            }
+            Message::PaletteKey(code) => {
+                if let Some((palette, messages)) = &mut self.palette {
+                    match palette.handle_key(code) {
+                        Outcome::Open => {}
+                        Outcome::Closed => self.palette = None,
+                        Outcome::Chosen(i) => {
+                            let message = messages[i].clone();
+                            self.palette = None;
+                            return self.update(message, terminal);
+                        }
+                    }
+                }
+            }
+            Message::ResolveDuplicateTitle { open } => {
+                self.handle_resolve_duplicate_title(open, terminal)?;
+            }
+            Message::Suspend => self.suspend(terminal)?,
+            Message::MetadataLoaded(uuid, metadata) => {
+                self.handle_metadata_loaded(uuid, Some(*metadata))?
+            }
+            Message::MetadataFailed(uuid) => self.handle_metadata_loaded(uuid, None)?,
+            Message::Tick => self.handle_tick()?,
+        }
+        if changes {
+            self.start_backup();
+        }
+        Ok(())
+    }
+
+    /// Takes a snapshot of the notes on another thread if one is due, unless
+    /// one is being taken already.
+    fn start_backup(&mut self) {
+        let (Some(backups), Some(dir)) = (&self.options.backups, self.vault.dir()) else {
+            return;
+        };
+        if self.backup.is_some() {
+            return;
+        }
+        let (backups, dir) = (backups.clone(), dir.to_path_buf());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // The TUI may be gone already
+            let _ = sender.send(backups.take_if_due(&dir, Utc::now()));
+        });
+        self.backup = Some(receiver);
+    }
+
+    /// Takes in how the snapshot being taken went, warning if it failed.
+    fn poll_backup(&mut self) {
+        let Some(receiver) = &self.backup else {
+            return;
+        };
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => {}
+            Ok(Err(e)) => {
+                self.status_message = Some(t!("status-backup-failed", error = e));
+                self.redraw.mark();
+                self.backup = None;
+            }
+            Ok(Ok(_)) | Err(TryRecvError::Disconnected) => self.backup = None,
+        }
+    }
+
+    /// Counts the statistics of the vault on another thread, anew each time
+    /// they're shown, so a large vault doesn't keep the TUI waiting.
+    fn handle_show_statistics(&mut self) {
+        let vault = Arc::clone(&self.vault);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // The statistics may be closed already
+            let _ = sender.send(vault.statistics());
+        });
+        self.statistics = Some(Statistics::Counting(receiver));
+    }
+
+    /// Shows the statistics being counted once they're done.
+    fn poll_statistics(&mut self) {
+        let Some(Statistics::Counting(receiver)) = &self.statistics else {
+            return;
+        };
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Ok(Ok(stats)) => self.statistics = Some(Statistics::Counted(Box::new(stats))),
+            Ok(Err(e)) => {
+                self.status_message = Some(t!("status-statistics-failed", error = e));
+                self.statistics = None;
+            }
+            Err(TryRecvError::Disconnected) => self.statistics = None,
+        }
+        self.redraw.mark();
+    }
+
+    /// Takes the text on the clipboard for a new note, asking first if there's
+    /// a lot of it.
+    fn handle_new_note_from_clipboard(&mut self) {
+        match clipboard::read_text() {
+            Ok(text) => {
+                let large = text.len() > clipboard::LARGE_PASTE;
+                self.pasted = Some(text);
+                if large {
+                    self.show_paste_prompt = true;
+                } else {
+                    self.ask_pasted_title();
+                }
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Asks for the title of the note of the clipboard text, suggesting its
+    /// first line.
+    fn ask_pasted_title(&mut self) {
+        if let Some(text) = &self.pasted {
+            let title = clipboard::default_title(text);
+            self.input = Some(Input::new(InputPurpose::PastedTitle, title));
+        }
+    }
+
+    /// Creates a note of `note_type` titled `title`, or from `new_note_title`
+    /// if it's empty, or asks first if a note already has that title.
+    fn handle_new_note(
+        &mut self,
+        title: &str,
+        note_type: NoteType,
+        content: Zeroizing<String>,
+    ) -> Result<(), AppError> {
+        let title = match title.trim() {
+            "" => self
+                .vault
+                .next_title(&self.options.new_note_title, Local::now())?,
+            title => title.to_string(),
+        };
+        let title = title.as_str();
+        if self.options.warn_duplicate_titles
+            && let Some(existing) = self
+                .notes
+                .iter()
+                .find(|note| vault::same_title(&note.metadata.original_filename, title))
+        {
+            self.duplicate_title = Some(DuplicateTitle {
+                title: title.to_string(),
+                note_type,
+                content,
+                existing: existing.clone(),
+            });
+            return Ok(());
+        }
+
+        let mut metadata = NoteMetadata::new(title);
+        metadata.note_type = note_type;
+        let note = self
+            .vault
+            .create_with_metadata(&metadata, content.as_bytes())?;
+        self.reload_notes()?;
+        self.select_note(&note.uuid);
+        Ok(())
+    }
+
+    /// Either opens the note that already had the title of the new one, or
+    /// creates the new one anyway.
+    fn handle_resolve_duplicate_title(
+        &mut self,
+        open: bool,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> Result<(), AppError> {
+        let Some(duplicate) = self.duplicate_title.take() else {
+            return Ok(());
+        };
+        if open {
+            self.select_note(&duplicate.existing.uuid);
+            return self.handle_edit_selected_note(false, None, terminal);
+        }
+        let mut metadata = NoteMetadata::new(duplicate.title);
+        metadata.note_type = duplicate.note_type;
+        let note = self
+            .vault
+            .create_with_metadata(&metadata, duplicate.content.as_bytes())?;
+        self.reload_notes()?;
+        self.select_note(&note.uuid);
+        Ok(())
+    }
+
+    /// Moves the selection to the note `uuid`, if it's listed.
+    fn select_note(&mut self, uuid: &str) {
+        if let Some(index) = self.notes.iter().position(|note| note.uuid == uuid) {
+            self.selected_note_index = index;
+            self.list_state.select(Some(index));
+        }
+        self.update_preview_content();
+    }
+
+    fn handle_scroll_down(&mut self) {
+        if let Some(history) = &mut self.history {
+            let last = history.versions.len().saturating_sub(1);
+            let index = history
+                .list_state
+                .selected()
+                .map_or(0, |i| (i + 1).min(last));
+            history.list_state.select(Some(index));
+            self.update_history_preview();
+        } else if self.selected_note_index < self.notes.len().saturating_sub(1) {
+            self.selected_note_index += 1;
+            self.list_state.select(Some(self.selected_note_index));
+            self.update_preview_content();
+        }
+    }
+
+    fn handle_scroll_up(&mut self) {
+        if let Some(history) = &mut self.history {
+            let index = history
+                .list_state
+                .selected()
+                .map_or(0, |i| i.saturating_sub(1));
+            history.list_state.select(Some(index));
+            self.update_history_preview();
+        } else if self.selected_note_index > 0 {
+            self.selected_note_index -= 1;
+            self.list_state.select(Some(self.selected_note_index));
+            self.update_preview_content();
+        }
+    }
+
+    /// Moves the selected note to the trash, where it stays until it can no
+    /// longer be undone.
+    fn handle_delete_note(&mut self) -> Result<(), AppError> {
+        if let Some(note) = self.notes.get(self.selected_note_index) {
+            match self.vault.trash(&note.uuid) {
+                Ok(()) => {}
+                Err(e @ AppError::ReadOnlyNote(_)) => {
+                    self.status_message = Some(e.to_string());
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
+            let change = Change::Deleted {
+                uuid: note.uuid.clone(),
+                title: note.metadata.original_filename.clone(),
+            };
+            self.undo.push(&self.vault, change)?;
+        }
+        self.selected_note_index = self.selected_note_index.saturating_sub(1);
+        self.reload_notes()?;
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Opens the selected note in the editor, or in the viewer if it's read-only.
+    ///
+    /// Binary content, which a text editor may corrupt, is only opened with
+    /// `allow_binary`, after asking.
+    fn handle_edit_selected_note(
+        &mut self,
+        allow_binary: bool,
+        cursor: Option<Position>,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+
+        if note.metadata.read_only || self.vault.is_read_only_mode() {
+            return self.open_viewer();
+        }
+
+        if self.selected_binary_len.is_some() && !allow_binary {
+            self.show_binary_prompt = true;
+            return Ok(());
+        }
+        // To save the edit again if saving fails
+        let base = self.vault.revision(&note.uuid)?;
+        let mut binary = false;
+        let mut refused = None;
+        let mut not_writable = None;
+        let mut not_saved = None;
+        let mut outcome = EditOutcome::Unchanged;
+        terminal_mode_guard(terminal, || {
+            match editor::edit_note(
+                &self.vault,
+                &note.uuid,
+                &self.options.editor,
+                false,
+                allow_binary,
+                cursor.map(|position| editor::Cursor {
+                    position,
+                    arg: self.options.editor_cursor_arg.as_deref(),
+                }),
+            ) {
+                Ok(edited) => {
+                    outcome = edited;
+                    Ok(())
+                }
+                // Changed since the preview was decrypted
+                Err(AppError::BinaryNote(_)) => {
+                    binary = true;
+                    Ok(())
+                }
+                Err(e @ (AppError::TooLarge { .. } | AppError::EditorNotFound(_))) => {
+                    refused = Some(e.to_string());
+                    Ok(())
+                }
+                Err(AppError::NotWritable { reason, .. }) => {
+                    not_writable = Some(reason);
+                    Ok(())
+                }
+                Err(AppError::EditNotSaved { path, .. }) => {
+                    not_saved = Some(path);
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        })?;
+        if refused.is_some() {
+            self.status_message = refused;
+            return Ok(());
+        }
+        if let Some(reason) = not_writable {
+            self.unwritable.insert(note.uuid.clone());
+            self.unwritable_prompt = Some(reason);
+            return Ok(());
+        }
+        if let Some(path) = not_saved {
+            self.unwritable.insert(note.uuid.clone());
+            self.unsaved = Some(UnsavedEdit {
+                uuid: note.uuid.clone(),
+                title: note.metadata.original_filename.clone(),
+                path,
+                base,
+            });
+            return Ok(());
+        }
+        self.unwritable.remove(&note.uuid);
+        if binary {
+            self.update_preview_content();
+            self.show_binary_prompt = true;
+            return Ok(());
+        }
+        if let EditOutcome::Conflict(copy) = outcome {
+            self.reload_notes()?;
+            self.select_note(&copy.uuid);
+            self.status_message = Some(t!(
+                "status-edit-conflict",
+                title = self.shown_title(&copy.metadata.original_filename)
+            ));
+            return Ok(());
+        }
+        let metadata = self.vault.get(&note.uuid)?.metadata;
+        if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+            note.metadata = metadata;
+        }
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Opens the selected note in the viewer, where it can't be changed.
+    fn open_viewer(&mut self) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        let content = match self.vault.read(&note.uuid) {
+            Ok(content) => content,
+            Err(e @ AppError::TooLarge { .. }) => {
+                self.status_message = Some(e.to_string());
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let viewer = match String::from_utf8(content) {
+            Ok(text) => {
+                let stats = self.stats.get(&note.uuid, &text);
+                Viewer::new(&note.metadata.original_filename, text).with_stats(stats)
+            }
+            Err(e) => Viewer::new(
+                &note.metadata.original_filename,
+                export::hex_dump(e.as_bytes()),
+            ),
+        };
+        self.viewer = Some(if self.private {
+            viewer.without_copy()
+        } else {
+            viewer
+        });
+        Ok(())
+    }
+
+    /// Saves the edit that couldn't be saved again, e.g. once the notes
+    /// directory is writable again, and deletes the file it was kept in.
+    fn handle_retry_save(&mut self) -> Result<(), AppError> {
+        let Some(unsaved) = self.unsaved.take() else {
+            return Ok(());
+        };
+        let content = Zeroizing::new(fs::read(&unsaved.path)?);
+        let outcome = match self.vault.save_edit(&unsaved.uuid, &unsaved.base, &content) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.status_message = Some(t!("status-still-unsaved", error = e));
+                self.unsaved = Some(unsaved);
+                return Ok(());
+            }
+        };
+        fs::remove_file(&unsaved.path)?;
+        self.unwritable.remove(&unsaved.uuid);
+        self.reload_notes()?;
+        match outcome {
+            EditOutcome::Conflict(copy) => {
+                self.select_note(&copy.uuid);
+                self.status_message = Some(t!(
+                    "status-edit-conflict",
+                    title = self.shown_title(&copy.metadata.original_filename)
+                ));
+            }
+            _ => {
+                self.select_note(&unsaved.uuid);
+                self.status_message = Some(t!("status-edit-saved"));
+            }
+        }
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Writes the edit that couldn't be saved to `path`, or to a new file in
+    /// it if it's a directory, then deletes the file it was kept in.
+    fn handle_export_unsaved(&mut self, path: &str) -> Result<(), AppError> {
+        let Some(unsaved) = self.unsaved.take() else {
+            return Ok(());
+        };
+        let path = Path::new(path.trim());
+        let result = fs::read(&unsaved.path)
+            .map_err(AppError::Io)
+            .and_then(|content| {
+                let content = Zeroizing::new(content);
+                let (path, mut file) = if path.is_dir() {
+                    filename::create_unique(path, &filename::sanitize(&unsaved.title), "md")?
+                } else {
+                    let file = fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(path)?;
+                    (path.to_path_buf(), file)
+                };
+                io::Write::write_all(&mut file, &content)?;
+                Ok(path)
+            });
+        match result {
+            Ok(written) => {
+                fs::remove_file(&unsaved.path)?;
+                self.status_message = Some(t!("status-edit-written", path = written.display()));
+            }
+            Err(e) => {
+                self.status_message = Some(t!("status-edit-not-written", error = e));
+                self.unsaved = Some(unsaved);
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the journal note of the local day, creating it first if needed.
+    fn handle_open_journal(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> Result<(), AppError> {
+        let today = Local::now().date_naive();
+        let (note, cursor) = match self.options.journal.open(&self.vault, today) {
+            Ok(opened) => opened,
+            Err(e) => {
+                self.status_message = Some(t!("status-journal-failed", error = e));
+                return Ok(());
+            }
+        };
+        self.reload_notes()?;
+        self.select_note(&note.uuid);
+        self.handle_edit_selected_note(false, cursor, terminal)
+    }
+
+    /// Switches between listing every note and only journal notes, keeping the
+    /// selected note if it's still listed.
+    fn handle_toggle_journal(&mut self) -> Result<(), AppError> {
+        self.journal_only = !self.journal_only;
+        self.relist()
+    }
+
+    /// Lists only the notes of the next type, going from every note through
+    /// each type and `other` back to every note, keeping the selected note if
+    /// it's still listed.
+    fn handle_cycle_type_filter(&mut self) -> Result<(), AppError> {
+        self.type_filter = match &self.type_filter {
+            None => Some(NoteType::Plain),
+            Some(NoteType::Journal) => Some(NoteType::Other("other".to_string())),
+            Some(NoteType::Other(_)) => None,
+            Some(note_type) => Some(note_type.next()),
+        };
+        self.relist()
+    }
+
+    /// Lists only the notes matching `filter`, or every note, keeping the
+    /// selected note if it's still listed.
+    fn set_filter(&mut self, filter: Option<String>) -> Result<(), AppError> {
+        self.filter = filter;
+        self.relist()
+    }
+
+    /// Lists the notes again after the view or a filter changed, keeping the
+    /// selected note if it's still listed.
+    fn relist(&mut self) -> Result<(), AppError> {
+        let selected = self
+            .notes
+            .get(self.selected_note_index)
+            .map(|note| note.uuid.clone());
+        self.reload_notes()?;
+        self.selected_note_index = 0;
+        self.list_state
+            .select((!self.notes.is_empty()).then_some(0));
+        match selected {
+            Some(uuid) => self.select_note(&uuid),
+            None => self.update_preview_content(),
+        }
+        Ok(())
+    }
+
+    /// Lists the links of the selected note, to open or copy one.
+    fn handle_show_links(&mut self) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        let mut content = match self.vault.read(&note.uuid) {
+            Ok(content) => content,
+            Err(e @ AppError::TooLarge { .. }) => {
+                self.status_message = Some(e.to_string());
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let urls = std::str::from_utf8(&content)
+            .map(ryokan::links::urls)
+            .unwrap_or_default();
+        content.zeroize();
+        self.links = LinkPicker::new(urls).map(|picker| {
+            if self.private {
+                picker.without_copy()
+            } else {
+                picker
+            }
+        });
+        if self.links.is_none() {
+            self.status_message = Some(t!("status-no-links"));
+        }
+        Ok(())
+    }
+
+    fn handle_toggle_read_only(&mut self) -> Result<(), AppError> {
+        if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+            let before = Field::ReadOnly(note.metadata.read_only);
+            note.metadata = self.vault.metadata_mut(&note.uuid, |metadata| {
+                metadata.read_only = !metadata.read_only
+            })?;
+            self.undo
+                .push(&self.vault, Change::Metadata(vec![changed(note, before)]))?;
+        }
+        Ok(())
+    }
+
+    /// Reverts the most recent change made this session.
+    fn handle_undo(&mut self) -> Result<(), AppError> {
+        let Some(change) = self.undo.pop() else {
+            self.status_message = Some(t!("status-nothing-to-undo"));
+            return Ok(());
+        };
+        self.status_message = Some(match change.revert(&self.vault) {
+            Ok(description) => description,
+            Err(e @ (AppError::ReadOnlyNote(_) | AppError::NoteNotFound(_))) => {
+                t!("status-undo-failed", error = e)
+            }
+            Err(e) => return Err(e),
+        });
+        self.reload_notes()?;
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Turns privacy mode on or off. It only changes what's shown, so turning it
+    /// off doesn't ask for the PIN.
+    fn handle_toggle_privacy(&mut self) {
+        self.private = !self.private;
+        self.status_message = Some(if self.private {
+            t!("status-privacy-on")
+        } else {
+            t!("status-privacy-off")
+        });
+        self.update_preview_content();
+    }
+
+    fn handle_show_issues(&mut self) {
+        if self.orphans.is_empty() {
+            self.status_message = Some(t!("status-no-orphans"));
+            return;
+        }
+        self.issues = Some(IssuesView {
+            list_state: ListState::default().with_selected(Some(0)),
+            confirming: false,
+        });
+    }
+
+    fn handle_issue_key(&mut self, code: KeyCode) {
+        let Some(issues) = &mut self.issues else {
+            return;
+        };
+        let selected = issues.list_state.selected().unwrap_or_default();
+        match code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                let last = self.orphans.len().saturating_sub(1);
+                issues.list_state.select(Some((selected + 1).min(last)));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                issues.list_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.issues = None,
+            _ => {}
+        }
+    }
+
+    /// Deletes the selected orphaned metadata, or adopts the selected orphaned
+    /// content as a new note, once confirmed.
+    fn handle_fix_issue(&mut self) -> Result<(), AppError> {
+        let Some(orphan) = self
+            .issues
+            .as_ref()
+            .and_then(|issues| issues.list_state.selected())
+            .and_then(|index| self.orphans.get(index))
+            .cloned()
+        else {
+            return Ok(());
+        };
+        let fixed = match &orphan {
+            Orphan::Metadata { uuid, .. } => self
+                .vault
+                .delete_orphaned_metadata(uuid)
+                .map(|()| t!("status-orphan-deleted")),
+            Orphan::Content { uuid } => self.vault.adopt(uuid).map(|note| {
+                t!(
+                    "status-orphan-adopted",
+                    title = self.shown_title(&note.metadata.original_filename)
+                )
+            }),
+        };
+        self.status_message = Some(match fixed {
+            Ok(message) => message,
+            Err(e @ (AppError::Decryption(_) | AppError::NoteNotFound(_))) => {
+                t!("status-orphan-not-fixed", error = e)
+            }
+            Err(e) => return Err(e),
+        });
+        self.reload_notes()?;
+        match &mut self.issues {
+            Some(_) if self.orphans.is_empty() => self.issues = None,
+            Some(issues) => {
+                let last = self.orphans.len() - 1;
+                let selected = issues.list_state.selected().unwrap_or_default();
+                issues.list_state.select(Some(selected.min(last)));
+            }
+            None => {}
+        }
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Lists the unchecked items of every note, selecting the first one from
+    /// row `selected`. Notes are only decrypted again if they changed since the
+    /// last time.
+    fn handle_show_todos(&mut self, selected: usize) -> Result<(), AppError> {
+        if self.private {
+            self.status_message = Some(t!("status-todos-private"));
+            return Ok(());
+        }
+        let mut notes = self.vault.list()?;
+        self.sort_mode.sort(&mut notes);
+        let found = self.todo_cache.collect(&self.vault, &notes)?;
+        if found.is_empty() {
+            self.todos = None;
+            self.status_message = Some(t!("status-no-todos"));
+        } else {
+            self.todos = Some(TodoView::new(found, selected));
+        }
+        Ok(())
+    }
+
+    /// Closes the todos and selects the note of the selected item.
+    fn handle_open_todo(&mut self) {
+        let Some(uuid) = self
+            .todos
+            .as_ref()
+            .and_then(TodoView::selected)
+            .map(|(note, _)| note.uuid.clone())
+        else {
+            return;
+        };
+        self.todos = None;
+        if self.notes.iter().any(|note| note.uuid == uuid) {
+            self.select_note(&uuid);
+        } else {
+            self.status_message = Some(t!("status-todo-not-listed"));
+        }
+    }
+
+    /// Checks off the selected item, writing its note again.
+    fn handle_check_todo(&mut self) -> Result<(), AppError> {
+        let Some(view) = &self.todos else {
+            return Ok(());
+        };
+        let Some((note, line)) = view.selected() else {
+            return Ok(());
+        };
+        let (uuid, title) = (note.uuid.clone(), note.metadata.original_filename.clone());
+        let row = view.list_state.selected().unwrap_or_default();
+        let message = match todos::check_off(&self.vault, &uuid, line) {
+            Ok(true) => t!("status-todo-checked", title = self.shown_title(&title)),
+            Ok(false) => t!("status-todo-gone"),
+            Err(e @ AppError::ReadOnlyNote(_)) => t!("status-todo-not-checked", error = e),
+            Err(e) => return Err(e),
+        };
+        self.reload_notes()?;
+        self.update_preview_content();
+        self.handle_show_todos(row)?;
+        self.status_message = Some(message);
+        Ok(())
+    }
+
+    /// Scrolls the preview to the next line after the top one containing the
+    /// filter, or the previous one, wrapping around.
+    fn handle_find_in_preview(&mut self, next: bool) {
+        let Some(query) = &self.filter else {
+            return;
+        };
+        let from = usize::from(self.preview_scroll);
+        match find_match_line(&self.note_preview_content, query, from, next) {
+            Some(line) => self.preview_scroll = u16::try_from(line).unwrap_or(u16::MAX),
+            None => self.status_message = Some(t!("status-not-in-preview", query = query)),
+        }
+    }
+
+    /// Whether `action` applies right now: syncing has to be configured, notes
+    /// marked to clear the marks, a filter set to clear it or find it in the
+    /// preview, notes missing a file to show them, and the selected note over
+    /// `preview_decrypt_limit` to decrypt it anyway.
+    fn applies(&self, action: &Action) -> bool {
+        match action.message {
+            Message::Sync => self.options.sync.is_some(),
+            Message::ClearMarks => !self.marked.is_empty(),
+            Message::DismissReminder => !self.due_reminder.is_empty(),
+            Message::ClearFilter | Message::FindInPreview { .. } => self.filter.is_some(),
+            Message::ToggleRawPreview => self
+                .notes
+                .get(self.selected_note_index)
+                .is_some_and(|note| note.metadata.note_type == NoteType::Markdown),
+            Message::ShowIssues => !self.orphans.is_empty(),
+            Message::DecryptPreview => self
+                .notes
+                .get(self.selected_note_index)
+                .is_some_and(|note| self.preview_limit(note).is_some()),
+            _ => true,
+        }
+    }
+
+    /// The actions to offer in the help line and the palette: those that
+    /// apply, without the ones that change the vault in read-only mode.
+    fn offered_actions(&self) -> impl Iterator<Item = &'static Action> + '_ {
+        ACTIONS.iter().filter(|action| {
+            self.applies(action)
+                && !(self.vault.is_read_only_mode() && action.message.changes_vault())
+        })
+    }
+
+    fn handle_open_palette(&mut self) {
+        let (entries, messages) = self
+            .offered_actions()
+            .filter(|action| action.message != Message::OpenPalette)
+            .map(|action| {
+                let entry = palette::Entry {
+                    name: i18n::message(action.name, &[]),
+                    key: key_label(action.key),
+                };
+                (entry, action.message.clone())
+            })
+            .chain(self.slots().into_iter().map(|(slot, note)| {
+                let entry = palette::Entry {
+                    name: t!(
+                        "action-go-to",
+                        title = self.shown_title(&note.metadata.original_filename)
+                    ),
+                    key: slot.to_string(),
+                };
+                (entry, Message::JumpToSlot(slot))
+            }))
+            .chain((!self.vault.is_read_only_mode()).then(|| {
+                let entry = palette::Entry {
+                    name: t!("action-collect-garbage"),
+                    key: String::new(),
+                };
+                (entry, Message::CollectGarbage)
+            }))
+            .unzip();
+        self.palette = Some((Palette::new(entries), messages));
+    }
+
+    /// The listed notes with a quick-access slot, by slot. If several notes have
+    /// the same one, e.g. after a sync, the first listed gets it.
+    fn slots(&self) -> BTreeMap<u8, &Note> {
+        let mut slots = BTreeMap::new();
+        for note in &self.notes {
+            if let Some(slot) = note.metadata.slot {
+                slots.entry(slot).or_insert(note);
+            }
+        }
+        slots
+    }
+
+    /// Gives the selected note the quick-access `slot`, taking it from the note
+    /// that had it, or clears its slot with 0.
+    /// Pins the selected note at the end of the pinned notes, or unpins it.
+    fn handle_toggle_pin(&mut self) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        let uuid = note.uuid.clone();
+        let pin = note.metadata.pin_order.is_none();
+        let before = self.vault.set_pinned(&uuid, pin)?;
+        self.status_message = Some(if pin {
+            t!("status-pinned")
+        } else {
+            t!("status-unpinned")
+        });
+        self.finish_pin_change(&uuid, before)
+    }
+
+    /// Moves the selected pinned note one place up or down.
+    fn handle_move_pinned(&mut self, up: bool) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        if note.metadata.pin_order.is_none() {
+            self.status_message = Some(t!("status-not-pinned"));
+            return Ok(());
+        }
+        let uuid = note.uuid.clone();
+        let before = self.vault.move_pinned(&uuid, up)?;
+        self.finish_pin_change(&uuid, before)
+    }
+
+    /// Makes the pinned order changed `before` undoable and lists the notes in
+    /// it, keeping the note `uuid` selected.
+    fn finish_pin_change(&mut self, uuid: &str, before: Vec<Note>) -> Result<(), AppError> {
+        if before.is_empty() {
+            return Ok(());
+        }
+        let changes = before
+            .iter()
+            .map(|note| changed(note, Field::PinOrder(note.metadata.pin_order)))
+            .collect();
+        self.undo.push(&self.vault, Change::Metadata(changes))?;
+        self.reload_notes()?;
+        self.select_note(uuid);
+        Ok(())
+    }
+
+    fn handle_assign_slot(&mut self, slot: u8) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        let slot = (slot != 0).then_some(slot);
+        let before = self.vault.assign_slot(&note.uuid, slot)?;
+        let changes = before
+            .iter()
+            .map(|note| changed(note, Field::Slot(note.metadata.slot)))
+            .collect();
+        self.undo.push(&self.vault, Change::Metadata(changes))?;
+        self.status_message = Some(match slot {
+            Some(slot) => t!("status-slot-assigned", slot = slot),
+            None => t!("status-slot-cleared"),
+        });
+        self.reload_notes()?;
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Selects the note in `slot`. Returns whether there's one.
+    fn handle_jump_to_slot(&mut self, slot: u8) -> bool {
+        let Some(uuid) = self.slots().get(&slot).map(|note| note.uuid.clone()) else {
+            self.status_message = Some(t!("status-no-slot", slot = slot));
+            return false;
+        };
+        self.select_note(&uuid);
+        true
+    }
+
+    /// Gives the selected note the next color label, then none after the last.
+    fn handle_cycle_color(&mut self) -> Result<(), AppError> {
+        if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+            let before = Field::Color(note.metadata.color.clone());
+            note.metadata = self.vault.metadata_mut(&note.uuid, |metadata| {
+                let next = match &metadata.color {
+                    Some(color) => COLOR_LABELS
+                        .iter()
+                        .position(|label| label == color)
+                        .map_or(0, |i| i + 1),
+                    None => 0,
+                };
+                metadata.color = COLOR_LABELS.get(next).map(|label| label.to_string());
+            })?;
+            self.undo
+                .push(&self.vault, Change::Metadata(vec![changed(note, before)]))?;
+        }
+        Ok(())
+    }
+
+    /// Gives the selected note the next type, then plain after the last.
+    fn handle_cycle_note_type(&mut self) -> Result<(), AppError> {
+        if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+            let before = Field::NoteType(note.metadata.note_type.clone());
+            note.metadata = self.vault.metadata_mut(&note.uuid, |metadata| {
+                metadata.note_type = metadata.note_type.next();
+            })?;
+            self.status_message = Some(t!("status-note-type", note_type = note.metadata.note_type));
+            self.undo
+                .push(&self.vault, Change::Metadata(vec![changed(note, before)]))?;
+        }
+        Ok(())
     }
 
-    fn handle_new_note(&mut self) -> Result<(), AppError> {
-        file::create_new_note(self.config.notes_dir_path(), &self.pin, "New Note", &[])?;
+    /// Starts editing the tags of the marked notes, or else of the selected one.
+    fn handle_edit_tags(&mut self) -> Result<(), AppError> {
+        let text = if !self.marked.is_empty() {
+            String::new()
+        } else if let Some(note) = self.notes.get(self.selected_note_index) {
+            note.metadata
+                .tags
+                .iter()
+                .map(|tag| format!("{tag} "))
+                .collect()
+        } else {
+            return Ok(());
+        };
+        self.input = Some(Input::new(InputPurpose::Tags, text));
+        self.update_suggestions()
+    }
 
-        self.reload_notes()?;
+    /// Completes the tag being typed from the tags in use, which the vault
+    /// keeps track of, so nothing is read per key press.
+    fn update_suggestions(&mut self) -> Result<(), AppError> {
+        let Some(input) = &mut self.input else {
+            return Ok(());
+        };
+        if input.purpose != InputPurpose::Tags {
+            return Ok(());
+        }
+        let counts = tags::with_namespaces(&self.vault.tags()?);
+        let partial = tags::partial(&input.text);
+        input.suggestions = tags::complete(partial, &counts)
+            .into_iter()
+            .filter(|tag| *tag != partial)
+            .take(MAX_SUGGESTIONS)
+            .map(str::to_string)
+            .collect();
+        input.choice = 0;
+        Ok(())
+    }
+
+    /// Replaces the tags of the selected note with the typed ones, or applies
+    /// the typed edit to every marked note.
+    fn handle_submit_tags(&mut self, text: &str) -> Result<(), AppError> {
+        if let Err(e) = tags::validate_typed(text) {
+            self.status_message = Some(e.to_string());
+            return Ok(());
+        }
+        let mut changes = Vec::new();
+        if self.marked.is_empty() {
+            if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+                let before = Field::Tags(note.metadata.tags.clone());
+                note.metadata = self
+                    .vault
+                    .metadata_mut(&note.uuid, |metadata| metadata.tags = tags::parse(text))?;
+                changes.push(changed(note, before));
+            }
+        } else {
+            let marked = std::mem::take(&mut self.marked);
+            for note in self
+                .notes
+                .iter_mut()
+                .filter(|note| marked.contains(&note.uuid))
+            {
+                let before = Field::Tags(note.metadata.tags.clone());
+                note.metadata = self.vault.metadata_mut(&note.uuid, |metadata| {
+                    tags::apply_edit(&mut metadata.tags, text)
+                })?;
+                changes.push(changed(note, before));
+            }
+            self.status_message = Some(t!("status-tags-updated", count = marked.len()));
+        }
+        if !changes.is_empty() {
+            self.undo.push(&self.vault, Change::Metadata(changes))?;
+        }
         self.update_preview_content();
         Ok(())
     }
 
-    fn handle_scroll_down(&mut self) {
-        if self.selected_note_index < self.notes.len().saturating_sub(1) {
-            self.selected_note_index += 1;
-            self.list_state.select(Some(self.selected_note_index));
-            self.update_preview_content();
+    fn handle_submit_input(&mut self) -> Result<(), AppError> {
+        let Some(input) = self.input.take() else {
+            return Ok(());
+        };
+        match input.purpose {
+            InputPurpose::Title => {
+                self.handle_new_note(&input.text, input.note_type, Zeroizing::default())?
+            }
+            InputPurpose::PastedTitle => {
+                if let Some(content) = self.pasted.take() {
+                    self.handle_new_note(&input.text, input.note_type, content)?;
+                }
+            }
+            InputPurpose::Tags => self.handle_submit_tags(&input.text)?,
+            InputPurpose::ExportPath => self.handle_export_unsaved(&input.text)?,
+            InputPurpose::Filter => {
+                let text = input.text.trim();
+                self.set_filter((!text.is_empty()).then(|| text.to_string()))?;
+            }
+            InputPurpose::DueDate => {
+                let due_at = match input.text.trim() {
+                    "" => None,
+                    text => match due::parse_due_date(text, &Local::now()) {
+                        Ok(due_at) => Some(due_at),
+                        Err(e) => {
+                            self.status_message = Some(e.to_string());
+                            return Ok(());
+                        }
+                    },
+                };
+                if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+                    let before = Field::DueAt(note.metadata.due_at);
+                    note.metadata = self
+                        .vault
+                        .metadata_mut(&note.uuid, |metadata| metadata.due_at = due_at)?;
+                    // Due already, which needs no reminder
+                    if let Some(reminders) = &mut self.reminders {
+                        reminders.acknowledge(std::slice::from_ref(note), &Local::now());
+                    }
+                    self.undo
+                        .push(&self.vault, Change::Metadata(vec![changed(note, before)]))?;
+                }
+                self.update_preview_content();
+            }
         }
+        Ok(())
     }
 
-    fn handle_scroll_up(&mut self) {
-        if self.selected_note_index > 0 {
-            self.selected_note_index -= 1;
-            self.list_state.select(Some(self.selected_note_index));
-            self.update_preview_content();
+    fn handle_open_history(&mut self) -> Result<(), AppError> {
+        let Some(note) = self.notes.get(self.selected_note_index) else {
+            return Ok(());
+        };
+        let versions = self.vault.history(&note.uuid)?;
+        let mut list_state = ListState::default();
+        list_state.select((!versions.is_empty()).then_some(0));
+        self.history = Some(HistoryView {
+            uuid: note.uuid.clone(),
+            title: note.metadata.original_filename.clone(),
+            versions,
+            list_state,
+            preview: Text::default(),
+            diff: false,
+            scroll: 0,
+        });
+        self.update_history_preview();
+        Ok(())
+    }
+
+    fn update_history_preview(&mut self) {
+        let Some(history) = &mut self.history else {
+            return;
+        };
+        history.scroll = 0;
+        history.preview = match history.selected() {
+            Some(_) if self.private => Text::raw(t!("history-private")),
+            Some(version) if history.diff => {
+                let contents = self
+                    .vault
+                    .read_version(&history.uuid, &version.id)
+                    .and_then(|old| Ok((Zeroizing::new(old), self.vault.read(&history.uuid)?)));
+                match contents {
+                    Ok((old, new)) => diff_text(&old, &Zeroizing::new(new), &self.options.theme),
+                    Err(e) => Text::raw(t!("history-error", error = e)),
+                }
+            }
+            Some(version) => match self.vault.read_version(&history.uuid, &version.id) {
+                Ok(content) => Text::raw(String::from_utf8(content).unwrap_or_else(|e| {
+                    format_binary_content(e.as_bytes(), usize::MAX, self.hex_dump)
+                })),
+                Err(e) => Text::raw(t!("history-error", error = e)),
+            },
+            None => Text::raw(t!("history-empty")),
+        };
+    }
+
+    fn handle_restore_version(&mut self) -> Result<(), AppError> {
+        let Some(history) = self.history.take() else {
+            return Ok(());
+        };
+        let Some(version) = history.selected() else {
+            return Ok(());
+        };
+        let kept: Vec<_> = self.vault.history(&history.uuid)?;
+        match self.vault.restore_version(&history.uuid, &version.id) {
+            Ok(metadata) => {
+                if let Some(note) = self.notes.get_mut(self.selected_note_index) {
+                    note.metadata = metadata;
+                }
+                // The replaced content is the version that wasn't there before,
+                // unless the history is disabled
+                let replaced = self
+                    .vault
+                    .history(&history.uuid)?
+                    .into_iter()
+                    .find(|version| !kept.iter().any(|kept| kept.id == version.id));
+                if let Some(replaced) = replaced {
+                    let change = Change::Restored {
+                        uuid: history.uuid.clone(),
+                        title: history.title.clone(),
+                        version_id: replaced.id,
+                    };
+                    self.undo.push(&self.vault, change)?;
+                }
+                self.status_message = Some(t!(
+                    "status-version-restored",
+                    at = self.options.dates.format(&version.saved_at)
+                ));
+            }
+            Err(e @ AppError::ReadOnlyNote(_)) => self.status_message = Some(e.to_string()),
+            Err(e) => return Err(e),
         }
+        self.update_preview_content();
+        Ok(())
     }
 
-    fn handle_delete_note(&mut self) -> Result<(), AppError> {
+    fn refresh_sync_status(&mut self) {
+        self.sync_status = self
+            .options
+            .sync
+            .as_ref()
+            .and_then(|sync| sync.status().ok().flatten());
+    }
+
+    fn handle_sync(&mut self) -> Result<(), AppError> {
+        let Some(sync) = &self.options.sync else {
+            return Ok(());
+        };
+        self.status_message = Some(match sync.sync() {
+            Ok(report) if report.conflicts.is_empty() => {
+                t!("status-synced", count = report.committed_notes)
+            }
+            Ok(report) => t!("status-synced-conflicts", count = report.conflicts.len()),
+            Err(e) => e.to_string(),
+        });
+        self.refresh_sync_status();
+        self.reload_notes()?;
+        self.selected_note_index = self
+            .selected_note_index
+            .min(self.notes.len().saturating_sub(1));
+        self.update_preview_content();
+        Ok(())
+    }
+
+    /// Deletes what the garbage collection policy allows and tells how much.
+    fn handle_collect_garbage(&mut self) -> Result<(), AppError> {
+        self.status_message = Some(
+            match self
+                .vault
+                .collect_garbage(&self.options.gc_policy, &mut |_| {})
+            {
+                Ok(plan) if plan.removals.is_empty() => t!("status-gc-nothing"),
+                Ok(plan) => t!(
+                    "status-gc-done",
+                    files = plan.removals.len(),
+                    bytes = plan.removals.iter().map(|removal| removal.len).sum::<u64>()
+                ),
+                Err(e) => e.to_string(),
+            },
+        );
+        self.reload_notes()?;
+        self.update_preview_content();
+        Ok(())
+    }
+
+    fn handle_resolve_conflict(&mut self, keep: bool) -> Result<(), AppError> {
         if let Some(note) = self.notes.get(self.selected_note_index) {
-            file::delete_note_files(self.config.notes_dir_path(), &note.uuid)?;
+            self.vault.resolve_conflict(&note.uuid, keep)?;
         }
         self.selected_note_index = self.selected_note_index.saturating_sub(1);
         self.reload_notes()?;
@@ -271,89 +3194,335 @@ impl App {
         Ok(())
     }
 
-    fn handle_edit_selected_note(
-        &mut self,
-        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    ) -> Result<(), AppError> {
-        if let Some(note) = self.notes.get(self.selected_note_index) {
-            let mut temp_file =
-                NamedTempFile::new_in(self.config.notes_dir_path()).map_err(AppError::Io)?;
-            let note_path = note.encrypted_file_path.clone();
-            let decrypted_content = file::load_and_decrypt_note_content(&note_path, &self.pin)?;
-            temp_file
-                .write_all(&decrypted_content)
-                .map_err(AppError::Io)?;
-
-            terminal_mode_guard(terminal, || {
-                file::open_in_editor(&self.args, temp_file.path())
-            })?;
+    fn handle_cycle_sort(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+        self.arrange_notes();
+        self.update_preview_content();
+    }
 
-            let updated_content = fs::read(temp_file.path()).map_err(AppError::Io)?;
-            let encrypted_content = note::encrypt_note_content(&updated_content, &self.pin)?;
+    /// Decrypts the preview again when another program changed its note, e.g.
+    /// `ryokan edit` in another terminal. Only the file of the selected note is
+    /// looked at, without reading it.
+    fn handle_tick(&mut self) -> Result<(), AppError> {
+        self.poll_backup();
+        self.poll_statistics();
+        self.check_reminders()?;
+        if self
+            .lazy
+            .as_ref()
+            .is_some_and(|lazy| lazy.awaited.is_empty() || !lazy.arrived.is_empty())
+        {
+            self.apply_loaded_metadata()?;
+        }
+        let Some((uuid, stamp)) = &self.preview_stamp else {
+            return Ok(());
+        };
+        if self.vault.stamp(uuid).ok() == Some(*stamp) {
+            return Ok(());
+        }
+        let uuid = uuid.clone();
+        self.redraw.mark();
+        self.reload_notes()?;
+        if self.notes.iter().any(|note| note.uuid == uuid) {
+            self.select_note(&uuid);
+            self.status_message
+                .get_or_insert_with(|| t!("status-changed-on-disk"));
+        } else {
+            self.selected_note_index = self
+                .selected_note_index
+                .min(self.notes.len().saturating_sub(1));
+            self.list_state
+                .select((!self.notes.is_empty()).then_some(self.selected_note_index));
+            self.update_preview_content();
+        }
+        Ok(())
+    }
 
-            file::save_note_to_file(&encrypted_content, &note_path)?;
+    /// Announces the notes that came due since the last check, at most every
+    /// [`DUE_CHECK_INTERVAL`]: rings the bell, lists them in the status area
+    /// and marks them in the list until dismissed, and with
+    /// `desktop_notifications`, sends a desktop notification. Notes already
+    /// due at the first check aren't announced.
+    fn check_reminders(&mut self) -> Result<(), AppError> {
+        // Every note has to be parsed to know its due date
+        if self.lazy.is_some()
+            || self
+                .last_due_check
+                .is_some_and(|last| last.elapsed() < DUE_CHECK_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_due_check = Some(Instant::now());
+        let notes = self.vault.list()?;
+        let now = Local::now();
+        let Some(reminders) = &mut self.reminders else {
+            self.reminders = Some(Reminders::new(&notes, &now));
+            return Ok(());
+        };
+        let due = reminders.newly_due(&notes, &now);
+        if due.is_empty() {
+            return Ok(());
+        }
+        for note in &due {
+            self.due_reminder
+                .push((note.uuid.clone(), note.metadata.original_filename.clone()));
+        }
+        self.redraw.mark();
+        // The bell goes through the terminal like the screen does
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+        if self.options.desktop_notifications {
+            let body = if self.private {
+                t!("notification-due-private", count = due.len())
+            } else {
+                let titles: Vec<_> = due
+                    .iter()
+                    .map(|note| self.shown_title(&note.metadata.original_filename))
+                    .collect();
+                t!("notification-due", titles = titles.join(", "))
+            };
+            if let Err(e) = notify::send("Ryokan", &body) {
+                error!("Could not send a desktop notification: {e}");
+                self.status_message = Some(t!("status-notification-failed", error = e));
+            }
+        }
+        Ok(())
+    }
 
-            let mut metadata = note.metadata.clone();
-            metadata.updated_at = Utc::now();
-            let (_, metadata_path) = file::note_paths(self.config.notes_dir_path(), &note.uuid);
-            metadata.save(&metadata_path)?;
+    /// Reads every note from disk again, for changes made by other programs.
+    fn handle_reload(&mut self) -> Result<(), AppError> {
+        self.vault.forget_metadata();
+        self.load_notes()?;
+        self.report_unexpected_files()?;
+        self.selected_note_index = self
+            .selected_note_index
+            .min(self.notes.len().saturating_sub(1));
+        self.update_preview_content();
+        Ok(())
+    }
 
-            self.update_preview_content();
-        }
+    /// Mentions files in the notes directory that aren't notes, which the list
+    /// leaves out.
+    fn report_unexpected_files(&mut self) -> Result<(), AppError> {
+        let names = self.vault.unexpected_files()?;
+        self.status_message = match names.as_slice() {
+            [] => None,
+            [name] => Some(t!("status-ignoring-file", name = name)),
+            [first, rest @ ..] => Some(t!(
+                "status-ignoring-files",
+                first = first,
+                count = rest.len()
+            )),
+        };
         Ok(())
     }
 
-    fn reload_notes(&mut self) -> Result<(), AppError> {
-        let notes_dir = self.config.notes_dir_path();
-        let mut files_by_uuid: HashMap<String, (Option<PathBuf>, Option<PathBuf>)> = HashMap::new();
+    /// Compares the notes with the integrity manifest, mentioning what it finds
+    /// at the bottom of the screen. Failing to check is only mentioned too.
+    fn check_integrity(&mut self) {
+        let report = match self.vault.check_integrity(false) {
+            Ok(report) => report,
+            Err(e) => {
+                self.status_message = Some(t!("status-integrity-failed", error = e));
+                return;
+            }
+        };
+        if !report.corrupt.is_empty() {
+            self.status_message = Some(format!(
+                "{}{}",
+                self.options.theme.warning_mark(),
+                t!("status-maybe-corrupt", count = report.corrupt.len())
+            ));
+        } else if !report.updated.is_empty() {
+            self.status_message = Some(t!("status-accepted-changes", count = report.updated.len()));
+        } else if let Some(warning) = report.warning
+            && report.checked > 0
+        {
+            self.status_message = Some(t!("status-manifest-rebuilt", warning = warning));
+        }
+    }
 
-        for entry in fs::read_dir(notes_dir).map_err(AppError::Io)? {
-            let entry = entry?;
-            let path = entry.path();
+    /// Sorts the listed notes, or in the journal view, keeps only journal notes,
+    /// newest day first. Then drops those the filter leaves out.
+    fn arrange_notes(&mut self) {
+        self.open_snippets();
+        if let Some(filter) = &self.filter {
+            let filter = filter.to_lowercase();
+            let private = self.private;
+            let snippets = &self.snippets;
+            self.notes.retain(|note| {
+                std::iter::once(&note.metadata.original_filename)
+                    .chain(&note.metadata.aliases)
+                    .any(|title| title.to_lowercase().contains(&filter))
+                    // What the list shows of the content matches too
+                    || !private
+                        && snippets
+                            .get(&note.uuid)
+                            .is_some_and(|(_, text)| text.to_lowercase().contains(&filter))
+            });
+        }
+        if let Some(note_type) = &self.type_filter {
+            self.notes
+                .retain(|note| note.metadata.note_type.name() == note_type.name());
+        }
+        if !self.journal_only {
+            self.sort_mode.sort(&mut self.notes);
+            vault::pinned_first(&mut self.notes);
+            return;
+        }
+        let journal = &self.options.journal;
+        self.notes.retain(|note| journal.contains(note));
+        self.notes.sort_by_key(|note| {
+            std::cmp::Reverse(journal.date_of(&note.metadata.original_filename))
+        });
+    }
 
-            if !path.is_file() {
-                continue;
+    fn reload_notes(&mut self) -> Result<(), AppError> {
+        let notes = match &self.lazy {
+            Some(lazy) => {
+                self.vault
+                    .list_lazily(|uuid| lazy.awaited.contains(uuid))?
+                    .notes
             }
+            None => self.vault.list()?,
+        };
+        self.show_notes(notes)
+    }
 
-            let Some(file_name) = path.file_name().and_then(|s| s.to_str()) else {
-                continue;
-            };
+    /// Lists the notes like [`App::reload_notes`], except that if more than
+    /// `lazy_load_above` have to be parsed, they're listed with placeholders
+    /// and parsed in the background.
+    fn load_notes(&mut self) -> Result<(), AppError> {
+        // Anything still coming in from before is dropped with its receiver
+        self.lazy = None;
+        let listing = self.vault.list_lazily(|_| true)?;
+        if listing.pending.len() <= self.options.lazy_load_above {
+            return self.reload_notes();
+        }
+        self.lazy = Some(LazyLoad::start(Arc::clone(&self.vault), listing.pending));
+        self.show_notes(listing.notes)
+    }
 
-            // Tuple `.0` = encrypted file path, `.1` = metadata file path
-            if let Some(uuid) = file_name.strip_suffix(".enc.txt") {
-                files_by_uuid.entry(uuid.to_string()).or_default().0 = Some(path.clone());
-            } else if let Some(uuid) = file_name.strip_suffix(".meta.toml") {
-                files_by_uuid.entry(uuid.to_string()).or_default().1 = Some(path.clone());
+    /// Parses the metadata of the selected note at once if it's still listed
+    /// with a placeholder, so its preview and actions have it.
+    fn load_selected_metadata(&mut self) {
+        let (Some(lazy), Some(note)) =
+            (&mut self.lazy, self.notes.get_mut(self.selected_note_index))
+        else {
+            return;
+        };
+        if !lazy.awaited.remove(&note.uuid) {
+            return;
+        }
+        match self.vault.load_metadata(&note.uuid) {
+            Ok(metadata) => note.metadata = metadata,
+            Err(e) => {
+                error!("Error loading metadata for {}: {e}", note.uuid);
+                lazy.arrived.insert(note.uuid.clone(), None);
             }
         }
+    }
 
-        let mut loaded_notes = Vec::new();
-        for (uuid, (enc_path_opt, meta_path_opt)) in files_by_uuid {
-            if let (Some(encrypted_file_path), Some(metadata_path)) = (enc_path_opt, meta_path_opt)
-            {
-                match NoteMetadata::load(&metadata_path) {
-                    Ok(metadata) => {
-                        loaded_notes.push(Note {
-                            uuid,
-                            encrypted_file_path,
-                            metadata,
-                        });
-                    }
-                    Err(e) => {
-                        error!("Error loading metadata for {uuid}: {e}");
+    /// Takes in the metadata of a note parsed in the background, or `None` if
+    /// it can't be, putting what came in into the list now and then.
+    fn handle_metadata_loaded(
+        &mut self,
+        uuid: String,
+        metadata: Option<NoteMetadata>,
+    ) -> Result<(), AppError> {
+        let Some(lazy) = &mut self.lazy else {
+            return Ok(());
+        };
+        // Unless it was parsed already, for the preview
+        if lazy.awaited.remove(&uuid) {
+            lazy.arrived.insert(uuid, metadata);
+        }
+        if lazy.due() {
+            self.apply_loaded_metadata()?;
+        }
+        Ok(())
+    }
+
+    /// Puts the metadata parsed in the background so far into the list and sorts
+    /// it again, keeping the selected note selected. Once every note is parsed,
+    /// they're listed as usual.
+    fn apply_loaded_metadata(&mut self) -> Result<(), AppError> {
+        let Some(lazy) = &mut self.lazy else {
+            return Ok(());
+        };
+        let mut arrived = std::mem::take(&mut lazy.arrived);
+        lazy.last_applied = Instant::now();
+        if lazy.awaited.is_empty() {
+            self.lazy = None;
+        }
+        let selected = self
+            .notes
+            .get(self.selected_note_index)
+            .map(|note| note.uuid.clone());
+        if self.lazy.is_none()
+            || self.filter.is_some()
+            || self.type_filter.is_some()
+            || self.journal_only
+        {
+            // Notes the filter left out with their placeholder may match now
+            self.reload_notes()?;
+        } else {
+            self.notes
+                .retain_mut(|note| match arrived.remove(&note.uuid) {
+                    Some(Some(metadata)) => {
+                        note.metadata = metadata;
+                        true
                     }
-                }
+                    Some(None) => false,
+                    None => true,
+                });
+            self.arrange_notes();
+        }
+        self.redraw.mark();
+        match selected.and_then(|uuid| self.notes.iter().position(|note| note.uuid == uuid)) {
+            Some(index) => {
+                self.selected_note_index = index;
+                self.list_state.select(Some(index));
+            }
+            None => {
+                self.selected_note_index = self
+                    .selected_note_index
+                    .min(self.notes.len().saturating_sub(1));
+                self.list_state
+                    .select((!self.notes.is_empty()).then_some(self.selected_note_index));
+                self.update_preview_content();
             }
         }
+        Ok(())
+    }
 
-        // Sort notes by updated_at, newest first
-        loaded_notes.sort_by_key(|b| Reverse(b.metadata.updated_at));
-
-        self.notes = loaded_notes;
+    /// Shows `notes`, as listed by the vault, with the notes missing a file.
+    fn show_notes(&mut self, notes: Vec<Note>) -> Result<(), AppError> {
+        self.notes = notes;
+        self.orphans = self.vault.orphaned_files()?;
+        self.read_only_media = !self.vault.is_read_only_mode() && !self.vault.is_writable();
+        self.arrange_notes();
+        let notes = &self.notes;
+        self.marked
+            .retain(|uuid| notes.iter().any(|note| note.uuid == *uuid));
         Ok(())
     }
 
     fn view(&mut self, f: &mut ratatui::Frame) {
+        let theme = self.options.theme;
+        let history_title = self
+            .history
+            .as_ref()
+            .map(|history| self.shown_title(&history.title));
+        if let (Some(history), Some(title)) = (&mut self.history, history_title) {
+            Self::view_history(f, history, &title, &self.options.dates, &theme);
+            return;
+        }
+        if let Some(viewer) = &mut self.viewer {
+            viewer.render(f, &theme);
+            return;
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
@@ -367,44 +3536,695 @@ impl App {
             )
             .split(f.area());
 
+        let now = Local::now();
+        // Inside the borders, after the highlight symbol
+        let row_width = usize::from(chunks[0].width.saturating_sub(2)).saturating_sub(3);
         let items: Vec<_> = self
             .notes
             .iter()
-            .map(|note| ListItem::new(note.metadata.original_filename.as_str()))
+            .map(|note| {
+                let mark = if self.marked.contains(&note.uuid) {
+                    "* "
+                } else {
+                    ""
+                };
+                let read_only = note.metadata.read_only
+                    || self.read_only_media
+                    || self.unwritable.contains(&note.uuid);
+                let lock = match (note.metadata.conflict_of.is_some(), read_only) {
+                    (true, _) => theme.warning_mark().to_string(),
+                    (false, true) => theme.read_only_mark(),
+                    (false, false) => String::new(),
+                };
+                let label = theme.color_label(note.metadata.color.as_deref());
+                let slot = note
+                    .metadata
+                    .slot
+                    .map_or_else(String::new, |slot| format!("[{slot}] "));
+                let pin = if note.metadata.pin_order.is_some() {
+                    theme.pin_mark()
+                } else {
+                    String::new()
+                };
+                let reminder = if self.due_reminder.iter().any(|(uuid, _)| *uuid == note.uuid) {
+                    theme.reminder_mark()
+                } else {
+                    String::new()
+                };
+                let lock = format!("{mark}{reminder}{slot}{pin}{lock}");
+                let title = self.shown_title(&note.metadata.original_filename);
+                let due = note.metadata.due_at.map(|due_at| {
+                    let status = due::due_status(&due_at, &now);
+                    let due = format!(
+                        "  [{}{}]",
+                        t!("list-due", due = due::format_due_date(&due_at, &Local)),
+                        theme.due_suffix(status)
+                    );
+                    (due, status)
+                });
+                // The due date stays in view after a long title
+                let room = row_width.saturating_sub(
+                    label.width()
+                        + text::width(&lock)
+                        + due.as_ref().map_or(0, |(due, _)| text::width(due)),
+                );
+                let title = text::ellipsize(&title, room);
+                let snippet = self.shown_snippet(&note.uuid).map(|snippet| {
+                    Line::styled(
+                        format!(
+                            "  {}",
+                            text::ellipsize(snippet, row_width.saturating_sub(2))
+                        ),
+                        Style::default().add_modifier(Modifier::DIM),
+                    )
+                });
+                let Some((due, status)) = due else {
+                    let line = Line::from(vec![label, Span::raw(format!("{lock}{title}"))]);
+                    return ListItem::new(Text::from_iter(std::iter::once(line).chain(snippet)));
+                };
+                let line = Line::from(vec![label, Span::raw(format!("{lock}{title}{due}"))]);
+                ListItem::new(Text::from_iter(std::iter::once(line).chain(snippet)))
+                    .style(theme.due(status))
+            })
             .collect();
-        let notes_list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Notes"))
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
-        f.render_stateful_widget(notes_list, chunks[0], &mut self.list_state);
+        let (name, order) = if self.journal_only {
+            (t!("list-journal"), t!("sort-date"))
+        } else {
+            let order = match self.sort_mode {
+                SortMode::Updated => t!("sort-updated"),
+                SortMode::Created => t!("sort-created"),
+                SortMode::Title => t!("sort-title"),
+                SortMode::Size => t!("sort-size"),
+                SortMode::Due => t!("sort-due"),
+            };
+            (t!("list-notes"), order)
+        };
+        let mut title = match &self.options.profile {
+            Some(profile) => format!("{name} [{profile}] ({})", t!("list-by", order = order)),
+            None => format!("{name} ({})", t!("list-by", order = order)),
+        };
+        if let Some(filter) = &self.filter {
+            title.push_str(&format!(" ({})", t!("list-matching", filter = filter)));
+        }
+        if let Some(note_type) = &self.type_filter {
+            title.push_str(&format!(" ({})", t!("list-of-type", note_type = note_type)));
+        }
+        if !self.marked.is_empty() {
+            title.push_str(&format!(
+                " ({})",
+                t!("list-marked", count = self.marked.len())
+            ));
+        }
+        if self.notes.is_empty() {
+            let area = chunks[0].union(chunks[1]);
+            f.render_widget(theme.block().title(title), area);
+            self.view_empty_list(f, area);
+        } else {
+            let notes_list = theme.list(items, theme.block().title(title));
+            f.render_stateful_widget(notes_list, chunks[0], &mut self.list_state);
 
-        let preview_paragraph = Paragraph::new(self.note_preview_content.as_str())
-            .block(Block::default().borders(Borders::ALL).title("Preview"));
-        f.render_widget(preview_paragraph, chunks[1]);
+            let block = theme.block().title(t!("preview-title"));
+            let mut content_area = block.inner(chunks[1]);
+            f.render_widget(block, chunks[1]);
+            if self.options.preview_header
+                && let Some(note) = self.notes.get(self.selected_note_index)
+            {
+                let [header_area, rest] = Layout::vertical([
+                    Constraint::Length(PREVIEW_HEADER_HEIGHT),
+                    Constraint::Min(0),
+                ])
+                .areas(content_area);
+                let header = format_preview_header(
+                    note,
+                    self.shown_title(&note.metadata.original_filename),
+                    &self.note_preview_content,
+                    self.private,
+                    usize::from(header_area.width),
+                    &self.options,
+                );
+                f.render_widget(Paragraph::new(header), header_area);
+                content_area = rest;
+            }
+            // Only the content scrolls, under the header
+            let rendered = !self.raw_preview
+                && self.selected_binary_len.is_none()
+                && self
+                    .notes
+                    .get(self.selected_note_index)
+                    .is_some_and(|note| note.metadata.note_type == NoteType::Markdown);
+            let mut preview = if rendered {
+                pager::render_markdown(&self.note_preview_content, &theme)
+            } else {
+                pager::highlight_links(&self.note_preview_content)
+            };
+            if let Some(filter) = &self.filter {
+                preview = pager::highlight_matches(preview, filter, theme.search_match());
+            }
+            let preview_paragraph = Paragraph::new(preview).scroll((self.preview_scroll, 0));
+            f.render_widget(preview_paragraph, content_area);
+        }
 
-        let help_text = if self.show_delete_prompt {
+        let help_text = if let Some(input) = &self.input {
+            let label = match input.purpose {
+                InputPurpose::DueDate => t!("input-due-date"),
+                InputPurpose::Title => t!("input-title"),
+                InputPurpose::PastedTitle => t!("input-pasted-title"),
+                InputPurpose::ExportPath => t!("input-export-path"),
+                InputPurpose::Filter => t!("input-filter"),
+                InputPurpose::Tags if self.marked.is_empty() => t!("input-tags"),
+                InputPurpose::Tags => t!("input-marked-tags", count = self.marked.len()),
+            };
+            let note_type = if input.is_title() {
+                format!(
+                    "   Tab: {}",
+                    t!("input-note-type", note_type = input.note_type)
+                )
+            } else {
+                String::new()
+            };
+            // The end of a long input stays in view, along with the cursor
+            let room = usize::from(chunks[2].width.saturating_sub(2))
+                .saturating_sub(text::width(&label) + text::width(&note_type) + 1);
+            let typed = text::tail(&input.text, room);
+            let mut spans = vec![
+                Span::raw(label),
+                Span::raw(format!("{typed}_")),
+                Span::raw(note_type),
+            ];
+            if !input.suggestions.is_empty() {
+                spans.push(Span::raw("   Tab:"));
+                for (i, tag) in input.suggestions.iter().enumerate() {
+                    let style = if i == input.choice {
+                        theme.selection()
+                    } else {
+                        Style::default()
+                    };
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(tag.as_str(), style));
+                }
+            }
+            Line::from(spans)
+        } else if let Some(message) = &self.status_message {
+            Line::from(Span::styled(
+                message.as_str(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        } else if let Some(duplicate) = &self.duplicate_title {
             Line::from(vec![
-                Span::raw("Delete this note? "),
-                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": Confirm  "),
-                Span::styled("any other key", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": Cancel"),
+                Span::raw(t!(
+                    "prompt-duplicate-title",
+                    age = format_age(duplicate.existing.metadata.created_at, Utc::now())
+                )),
+                Span::styled("o", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-open-it"))),
+                Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-create-anyway"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-abort"))),
             ])
-        } else {
+        } else if self.show_binary_prompt {
+            Line::from(vec![
+                Span::raw(t!(
+                    "prompt-binary",
+                    bytes = self.selected_binary_len.unwrap_or_default()
+                )),
+                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-edit-anyway"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if self.show_slot_prompt {
+            Line::from(vec![
+                Span::raw(t!("prompt-slot")),
+                Span::styled("1-9", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-assign"))),
+                Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-clear"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if self.show_paste_prompt {
+            Line::from(vec![
+                Span::raw(t!(
+                    "prompt-paste",
+                    bytes = self.pasted.as_ref().map_or(0, |text| text.len())
+                )),
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-make-note"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if let Some(reason) = &self.unwritable_prompt {
+            Line::from(vec![
+                Span::raw(t!("prompt-unwritable", reason = reason)),
+                Span::styled("v", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-view-read-only"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if let Some(unsaved) = &self.unsaved {
             Line::from(vec![
-                Span::raw("Up/Down: Navigate  "),
-                Span::raw("Enter: Open/Edit  "),
-                Span::raw("n: New Note  "),
+                Span::raw(t!("prompt-unsaved", path = unsaved.path.display())),
+                Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-retry"))),
+                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-write-elsewhere"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-leave-it"))),
+            ])
+        } else if self.show_conflict_prompt {
+            Line::from(vec![
+                Span::raw(t!("prompt-conflict")),
+                Span::styled("k", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-keep-copy"))),
+                Span::styled("x", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-discard-copy"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if let Some(orphan) = self
+            .issues
+            .as_ref()
+            .filter(|issues| issues.confirming)
+            .and_then(|issues| issues.list_state.selected())
+            .and_then(|index| self.orphans.get(index))
+        {
+            let question = match orphan {
+                Orphan::Metadata { .. } => t!("prompt-delete-orphan"),
+                Orphan::Content { .. } => t!("prompt-adopt-orphan"),
+            };
+            Line::from(vec![
+                Span::raw(question),
+                Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}  ", t!("prompt-confirm"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if self.show_delete_prompt {
+            Line::from(vec![
+                Span::raw(t!("prompt-delete")),
                 Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(": Delete  "),
-                Span::raw("q: Quit"),
+                Span::raw(format!(": {}  ", t!("prompt-confirm"))),
+                Span::styled(
+                    t!("prompt-any-other-key"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(": {}", t!("prompt-cancel"))),
+            ])
+        } else if !self.due_reminder.is_empty() {
+            let titles: Vec<_> = self
+                .due_reminder
+                .iter()
+                .map(|(_, title)| self.shown_title(title))
+                .collect();
+            Line::from(vec![
+                Span::styled(
+                    format!(
+                        "{}{}  ",
+                        theme.reminder_mark(),
+                        t!("status-now-due", titles = titles.join(", "))
+                    ),
+                    theme.due(due::DueStatus::Today),
+                ),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(": {}", t!("prompt-dismiss"))),
+            ])
+        } else {
+            let mut spans = vec![Span::raw(t!("help-navigate"))];
+            let mut shown_keys = Vec::new();
+            for action in self.offered_actions() {
+                let Some(help) = action.help else {
+                    continue;
+                };
+                // Taken by an action listed before, e.g. `n` while filtering
+                if shown_keys.contains(&action.key) {
+                    continue;
+                }
+                shown_keys.push(action.key);
+                let key = key_label(action.key);
+                spans.push(Span::raw("  "));
+                if action.message == Message::AskDeleteNote {
+                    spans.push(Span::styled(
+                        key,
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ));
+                    spans.push(Span::raw(format!(": {}", i18n::message(help, &[]))));
+                } else {
+                    spans.push(Span::raw(format!("{key}: {}", i18n::message(help, &[]))));
+                }
+            }
+            Line::from(spans)
+        };
+        let mut help_block = theme.block();
+        if self.vault.is_read_only_mode() {
+            let warning = theme.tone(Tone::Error).add_modifier(Modifier::BOLD);
+            help_block = help_block.border_style(warning).title(Span::styled(
+                format!(" {} ", t!("title-read-only-mode")),
+                warning,
+            ));
+        } else if self.options.sync.is_some() {
+            let sync_title = match self.sync_status {
+                Some(status) => t!(
+                    "title-sync",
+                    status = theme.ahead_behind(status.ahead, status.behind)
+                ),
+                None => t!("title-sync-no-upstream"),
+            };
+            help_block = help_block.title(sync_title);
+        }
+        if !self.orphans.is_empty() {
+            help_block = help_block.title(Span::styled(
+                format!(
+                    " {}{} ",
+                    theme.warning_mark(),
+                    t!("title-orphans", count = self.orphans.len())
+                ),
+                theme.tone(Tone::Warning),
+            ));
+        }
+        if let Some(lazy) = &self.lazy {
+            help_block = help_block.title(Span::styled(
+                format!(
+                    " {} ",
+                    t!(
+                        "title-loaded",
+                        loaded = group_digits(lazy.loaded()),
+                        total = group_digits(lazy.total)
+                    )
+                ),
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        if self.private {
+            help_block = help_block.title(Span::styled(
+                format!(" {} ", t!("title-privacy-mode")),
+                theme.tone(Tone::Warning).add_modifier(Modifier::BOLD),
+            ));
+        }
+        let help = Paragraph::new(help_text).block(help_block);
+        f.render_widget(help, chunks[2]);
+
+        if let Some((palette, _)) = &mut self.palette {
+            palette.render(f, &theme);
+        }
+        if let Some(picker) = &mut self.links {
+            picker.render(f, &theme);
+        }
+        if let Some(view) = &mut self.todos {
+            Self::view_todos(f, view, &theme);
+        }
+        if let Some(statistics) = &self.statistics {
+            Self::view_statistics(f, statistics, &theme);
+        }
+        if self.issues.is_some() {
+            let items: Vec<_> = self
+                .orphans
+                .iter()
+                .map(|orphan| {
+                    let title = match orphan {
+                        Orphan::Metadata { title, .. } => title.as_deref(),
+                        Orphan::Content { .. } => None,
+                    };
+                    describe_orphan(orphan, title.map(|title| self.shown_title(title)))
+                })
+                .collect();
+            if let Some(issues) = &mut self.issues {
+                Self::view_issues(f, issues, items, &theme);
+            }
+        }
+    }
+
+    /// Draws the notes missing a file, described by `items`, in a popup.
+    fn view_issues(
+        f: &mut ratatui::Frame,
+        issues: &mut IssuesView,
+        items: Vec<String>,
+        theme: &Theme,
+    ) {
+        let area = f.area();
+        let longest = items.iter().map(|item| text::width(item)).max();
+        let width = u16::try_from(longest.unwrap_or_default() + 6)
+            .unwrap_or(u16::MAX)
+            .max(50)
+            .min(area.width);
+        let height = u16::try_from(items.len() + 2)
+            .unwrap_or(u16::MAX)
+            .min(area.height);
+        let popup = Rect {
+            x: area.width.saturating_sub(width) / 2,
+            y: area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let list = theme.list(
+            items.into_iter().map(ListItem::new).collect(),
+            theme.block().title(t!("title-issues")),
+        );
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut issues.list_state);
+    }
+
+    /// Draws what to do next in place of the list and preview when no note is
+    /// listed: how to get started if the vault is empty, or why nothing matches.
+    fn view_empty_list(&self, f: &mut ratatui::Frame, area: Rect) {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let lines = if self.filter.is_some() || self.type_filter.is_some() || self.journal_only {
+            let mut lines = vec![Line::styled(t!("empty-no-match"), bold), Line::raw("")];
+            if let Some(filter) = &self.filter {
+                lines.push(Line::raw(t!("empty-filter", filter = filter)));
+            }
+            if let Some(note_type) = &self.type_filter {
+                lines.push(Line::raw(t!("empty-type", note_type = note_type)));
+            }
+            if self.journal_only {
+                lines.push(Line::raw(t!("empty-journal")));
+            }
+            lines
+        } else {
+            vec![
+                Line::styled(t!("empty-welcome"), bold),
+                Line::raw(""),
+                Line::raw(t!("empty-no-notes")),
+                Line::raw(""),
+                Line::raw(t!("empty-kept-in")),
+                Line::raw(self.options.notes_dir.display().to_string()),
+                Line::raw(""),
+                Line::raw(t!("empty-import")),
+                Line::raw(t!("empty-import-how")),
+                Line::raw(t!("empty-import-help")),
+                Line::raw(""),
+                Line::raw(t!("empty-palette")),
+            ]
+        };
+        let inner = area.inner(Margin::new(2, 1));
+        // Rows once wrapped, roughly, to center them
+        let rows: usize = lines
+            .iter()
+            .map(|line| {
+                line.width()
+                    .div_ceil(usize::from(inner.width).max(1))
+                    .max(1)
+            })
+            .sum();
+        let height = u16::try_from(rows).unwrap_or(u16::MAX).min(inner.height);
+        let paragraph = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        let centered = Rect {
+            y: inner.y + (inner.height - height) / 2,
+            height,
+            ..inner
+        };
+        f.render_widget(paragraph, centered);
+    }
+
+    /// Draws the unchecked items of every note in a popup.
+    fn view_todos(f: &mut ratatui::Frame, view: &mut TodoView, theme: &Theme) {
+        let area = f.area();
+        let popup = Rect {
+            x: area.width / 10,
+            y: area.height / 10,
+            width: area.width - area.width / 5,
+            height: area.height - area.height / 5,
+        };
+        let items = view
+            .rows
+            .iter()
+            .map(|(i, j)| {
+                let note = &view.found[*i];
+                match j {
+                    None if note.note.metadata.note_type == NoteType::Todo => {
+                        let total = note.checked + note.todos.len();
+                        ListItem::new(Line::styled(
+                            format!(
+                                "{} ({}/{total})",
+                                note.note.metadata.original_filename, note.checked
+                            ),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ))
+                    }
+                    None => ListItem::new(Line::styled(
+                        note.note.metadata.original_filename.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    )),
+                    Some(j) => ListItem::new(format!("  [ ] {}", note.todos[*j].text)),
+                }
+            })
+            .collect();
+        let list = theme.list(items, theme.block().title(t!("title-todos")));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut view.list_state);
+    }
+
+    /// Draws the statistics of the vault in a popup: the figures, a bar chart
+    /// of the tags most notes have and a sparkline of the saves per week.
+    fn view_statistics(f: &mut ratatui::Frame, statistics: &Statistics, theme: &Theme) {
+        let area = f.area();
+        let popup = Rect {
+            x: area.width / 10,
+            y: area.height / 10,
+            width: area.width - area.width / 5,
+            height: area.height - area.height / 5,
+        };
+        let block = theme.block().title(t!("title-statistics"));
+        let inner = block.inner(popup);
+        f.render_widget(Clear, popup);
+        f.render_widget(block, popup);
+        let stats = match statistics {
+            Statistics::Counting(_) => {
+                let counting = Paragraph::new(t!("statistics-counting"))
+                    .alignment(Alignment::Center)
+                    .style(Style::default().add_modifier(Modifier::DIM));
+                f.render_widget(counting, inner);
+                return;
+            }
+            Statistics::Counted(stats) => stats,
+        };
+
+        let fields = stats.fields();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(fields.len() as u16),
+                Constraint::Min(3),
+                Constraint::Length(6),
+            ])
+            .split(inner);
+        let lines: Vec<_> = fields
+            .into_iter()
+            .map(|(name, value)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{name}: "),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(value),
+                ])
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), chunks[0]);
+
+        let bars: Vec<_> = stats
+            .top_tags
+            .iter()
+            .map(|(tag, count)| Bar::with_label(tag.as_str(), *count as u64))
+            .collect();
+        let tags = BarChart::horizontal(bars)
+            .block(theme.block().title(t!("stats-top-tags")))
+            .bar_set(theme.bar_set())
+            .bar_gap(0);
+        f.render_widget(tags, chunks[1]);
+
+        let most = stats.saves_per_week.iter().max().copied().unwrap_or(0);
+        let saves = Sparkline::default()
+            .block(theme.block().title(format!(
+                "{} ({})",
+                t!("stats-saves", weeks = vaultstats::WEEKS),
+                t!("statistics-most-saves", saves = most)
+            )))
+            .bar_set(theme.bar_set())
+            .data(stats.saves_per_week);
+        f.render_widget(saves, chunks[2]);
+    }
+
+    fn view_history(
+        f: &mut ratatui::Frame,
+        history: &mut HistoryView,
+        title: &str,
+        dates: &DateFormat,
+        theme: &Theme,
+    ) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Percentage(40),
+                Constraint::Min(3),
+                Constraint::Length(3),
             ])
+            .split(f.area());
+
+        let items: Vec<_> = history
+            .versions
+            .iter()
+            .map(|version| {
+                ListItem::new(format!(
+                    "{}  ({})",
+                    dates.format(&version.saved_at),
+                    t!("history-bytes", bytes = version.len)
+                ))
+            })
+            .collect();
+        let versions = theme.list(
+            items,
+            theme.block().title(t!("title-history", title = title)),
+        );
+        f.render_stateful_widget(versions, chunks[0], &mut history.list_state);
+
+        let title = if history.diff {
+            t!("title-history-changes")
+        } else {
+            t!("title-history-version")
         };
-        let help = Paragraph::new(help_text).block(Block::default().borders(Borders::ALL));
+        let preview = Paragraph::new(history.preview.clone())
+            .block(theme.block().title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((history.scroll, 0));
+        f.render_widget(preview, chunks[1]);
+
+        let diff = if history.diff {
+            t!("help-history-version")
+        } else {
+            t!("help-history-changes")
+        };
+        let help = Paragraph::new(t!("help-history-popup", diff = diff)).block(theme.block());
         f.render_widget(help, chunks[2]);
     }
 }
+
+#[cfg(test)]
+mod tui_test;
@@ -0,0 +1,158 @@
+#![cfg(test)]
+
+use super::*;
+
+#[test]
+fn test_idle_ticks_dont_redraw() {
+    let mut redraw = Redraw::new();
+    let start = Instant::now();
+    assert!(redraw.take(start));
+
+    for i in 1..=100 {
+        redraw.after(&Message::Tick);
+        assert!(!redraw.take(start + Duration::from_secs(i) / 2));
+    }
+    redraw.after(&Message::ScrollDown);
+    assert!(redraw.take(start + Duration::from_secs(51)));
+    redraw.after(&Message::Tick);
+    assert!(!redraw.take(start + Duration::from_secs(52)));
+    // Relative times are brought up to date even when idle
+    assert!(redraw.take(start + Duration::from_secs(51) + REFRESH_INTERVAL));
+    assert_eq!((redraw.draws, redraw.skipped), (3, 101));
+}
+
+#[test]
+fn test_panic_hook_cleans_up_before_reporting() -> Result<(), AppError> {
+    use ratatui::backend::TestBackend;
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    thread_local! {
+        // Only this thread's panic is ours, other tests may panic meanwhile
+        static STEPS: RefCell<Option<Vec<&'static str>>> = const { RefCell::new(None) };
+    }
+    let record = |step| {
+        STEPS.with_borrow_mut(|steps| steps.as_mut().map(|steps| steps.push(step)).is_some())
+    };
+    let previous: Arc<PanicHook> = Arc::new(std::panic::take_hook());
+    let next = {
+        let previous = Arc::clone(&previous);
+        Box::new(move |info: &PanicHookInfo<'_>| {
+            if !record("report") {
+                previous(info);
+            }
+        })
+    };
+    std::panic::set_hook(panic_hook(move || _ = record("cleanup"), next));
+
+    STEPS.set(Some(Vec::new()));
+    let mut terminal =
+        Terminal::new(TestBackend::new(20, 5)).map_err(|e| AppError::Tui(e.to_string()))?;
+    let result = std::panic::catch_unwind(move || {
+        let _ = terminal.draw(|_| panic!("drawing failed"));
+    });
+
+    let _ = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| previous(info)));
+    assert!(result.is_err());
+    assert_eq!(STEPS.take(), Some(vec!["cleanup", "report"]));
+    Ok(())
+}
+
+#[test]
+fn test_metadata_loaded_in_the_background_is_drawn_in_batches() {
+    let mut redraw = Redraw::new();
+    let start = Instant::now();
+    assert!(redraw.take(start));
+
+    let metadata = Box::new(NoteMetadata::new("Groceries"));
+    redraw.after(&Message::MetadataLoaded("uuid".to_string(), metadata));
+    redraw.after(&Message::MetadataFailed("uuid".to_string()));
+    assert!(!redraw.take(start + Duration::from_secs(1)));
+}
+
+#[test]
+fn test_group_digits() {
+    assert_eq!(group_digits(0), "0");
+    assert_eq!(group_digits(999), "999");
+    assert_eq!(group_digits(3214), "3,214");
+    assert_eq!(group_digits(20008), "20,008");
+    assert_eq!(group_digits(1234567), "1,234,567");
+}
+
+#[test]
+fn test_finding_the_filter_in_the_preview_wraps_around() {
+    let content = "Milk\nbread\nMILK again\nEggs";
+    assert_eq!(find_match_line(content, "milk", 0, true), Some(2));
+    assert_eq!(find_match_line(content, "milk", 2, true), Some(0));
+    assert_eq!(find_match_line(content, "milk", 2, false), Some(0));
+    assert_eq!(find_match_line(content, "milk", 0, false), Some(2));
+    assert_eq!(find_match_line(content, "butter", 0, true), None);
+}
+
+#[test]
+fn test_matches_are_highlighted_within_links() {
+    let style = Style::default().add_modifier(Modifier::REVERSED);
+    let text = pager::highlight_matches(
+        pager::highlight_links("see https://example.com/Milk for milk"),
+        "milk",
+        style,
+    );
+    let spans: Vec<_> = text.lines[0]
+        .spans
+        .iter()
+        .filter(|span| span.style.add_modifier.contains(Modifier::REVERSED))
+        .map(|span| (span.content.as_ref(), span.style.add_modifier))
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            ("Milk", Modifier::UNDERLINED | Modifier::REVERSED),
+            ("milk", Modifier::REVERSED)
+        ]
+    );
+}
+
+#[test]
+fn test_every_action_is_in_the_english_catalog() {
+    for action in &ACTIONS {
+        for key in std::iter::once(action.name).chain(action.help) {
+            assert!(
+                i18n::lookup(i18n::DEFAULT_LANGUAGE, key).is_some(),
+                "\"{key}\" isn't in locales/en.toml"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_markdown_is_rendered_line_for_line() {
+    let text =
+        "# Plan\n- [ ] **call** the bank\n> see https://example.com/a_b\n```\n# not a heading\n```";
+    let rendered = pager::render_markdown(text, &Theme::default());
+
+    let lines: Vec<String> = rendered
+        .lines
+        .iter()
+        .map(|line| {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "Plan",
+            "☐ call the bank",
+            "│ see https://example.com/a_b",
+            "```",
+            "# not a heading",
+            "```"
+        ]
+    );
+    let bold = &rendered.lines[1].spans[2];
+    assert_eq!(bold.content, "call");
+    assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+}
@@ -0,0 +1,155 @@
+//! Undoing changes made from the note list, most recent first, for as long as
+//! the TUI runs.
+//!
+//! Only what it takes to put things back is kept: a deleted note waits in the
+//! trash, still encrypted, until it falls off the stack or the TUI exits, and a
+//! metadata change keeps the previous value of the field. Nothing holds
+//! plaintext. Edits made in the editor aren't covered; the history is.
+
+use chrono::{DateTime, Utc};
+use ryokan::{
+    error::AppError,
+    metadata::{NoteMetadata, NoteType},
+    t,
+    vault::Vault,
+};
+use std::collections::VecDeque;
+
+/// How many changes can be undone.
+pub const MAX_UNDO: usize = 20;
+
+/// A metadata field as it was before a change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Tags(Vec<String>),
+    ReadOnly(bool),
+    Color(Option<String>),
+    DueAt(Option<DateTime<Utc>>),
+    Slot(Option<u8>),
+    PinOrder(Option<u32>),
+    NoteType(NoteType),
+}
+
+impl Field {
+    fn describe(&self) -> String {
+        match self {
+            Field::Tags(_) => t!("field-tags"),
+            Field::ReadOnly(_) => t!("field-read-only"),
+            Field::Color(_) => t!("field-color"),
+            Field::DueAt(_) => t!("field-due-date"),
+            Field::Slot(_) => t!("field-slot"),
+            Field::PinOrder(_) => t!("field-pin-order"),
+            Field::NoteType(_) => t!("field-note-type"),
+        }
+    }
+
+    fn restore(self, metadata: &mut NoteMetadata) {
+        match self {
+            Field::Tags(tags) => metadata.tags = tags,
+            Field::ReadOnly(read_only) => metadata.read_only = read_only,
+            Field::Color(color) => metadata.color = color,
+            Field::DueAt(due_at) => metadata.due_at = due_at,
+            Field::Slot(slot) => metadata.slot = slot,
+            Field::PinOrder(pin_order) => metadata.pin_order = pin_order,
+            Field::NoteType(note_type) => metadata.note_type = note_type,
+        }
+    }
+}
+
+/// A change that can be undone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The note was moved to the trash.
+    Deleted { uuid: String, title: String },
+    /// A field of one or more notes changed; each note is given by UUID and
+    /// title, with the field as it was.
+    Metadata(Vec<(String, String, Field)>),
+    /// A previous version of the note was restored, and the content it replaced
+    /// was kept as the version `version_id`.
+    Restored {
+        uuid: String,
+        title: String,
+        version_id: String,
+    },
+}
+
+impl Change {
+    /// Puts things back as they were before the change. Returns what was undone,
+    /// for the status line.
+    pub fn revert(self, vault: &Vault) -> Result<String, AppError> {
+        match self {
+            Change::Deleted { uuid, title } => {
+                vault.untrash(&uuid)?;
+                Ok(t!("undo-deleted", title = title))
+            }
+            Change::Metadata(notes) => {
+                let description = match notes.as_slice() {
+                    [(_, title, field)] => {
+                        t!("undo-field", field = field.describe(), title = title)
+                    }
+                    [(_, _, field), ..] => t!(
+                        "undo-field-notes",
+                        field = field.describe(),
+                        count = notes.len()
+                    ),
+                    [] => t!("status-nothing-to-undo"),
+                };
+                for (uuid, _, field) in notes {
+                    vault.metadata_mut(&uuid, |metadata| field.restore(metadata))?;
+                }
+                Ok(description)
+            }
+            Change::Restored {
+                uuid,
+                title,
+                version_id,
+            } => {
+                vault.restore_version(&uuid, &version_id)?;
+                Ok(t!("undo-restored", title = title))
+            }
+        }
+    }
+
+    /// Makes the change final once it can no longer be undone: a deleted note
+    /// is deleted from the trash for good.
+    fn finish(self, vault: &Vault) -> Result<(), AppError> {
+        match self {
+            Change::Deleted { uuid, .. } => vault.delete_trashed(&uuid),
+            Change::Metadata(_) | Change::Restored { .. } => Ok(()),
+        }
+    }
+}
+
+/// The changes that can be undone, at most [`MAX_UNDO`] of them.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    changes: VecDeque<Change>,
+}
+
+impl UndoStack {
+    /// Records `change`, finishing the oldest one if there are too many.
+    pub fn push(&mut self, vault: &Vault, change: Change) -> Result<(), AppError> {
+        self.changes.push_back(change);
+        if self.changes.len() > MAX_UNDO
+            && let Some(oldest) = self.changes.pop_front()
+        {
+            oldest.finish(vault)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the most recent change, to revert it.
+    pub fn pop(&mut self) -> Option<Change> {
+        self.changes.pop_back()
+    }
+
+    /// Finishes every change, e.g. when the TUI exits.
+    pub fn clear(&mut self, vault: &Vault) -> Result<(), AppError> {
+        self.changes
+            .drain(..)
+            .try_for_each(|change| change.finish(vault))
+    }
+}
+
+#[cfg(test)]
+mod undo_test;
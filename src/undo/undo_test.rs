@@ -0,0 +1,103 @@
+#![cfg(test)]
+
+use super::*;
+use ryokan::storage::MemoryStorage;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+fn vault() -> Vault {
+    Vault::with_storage(
+        Arc::new(MemoryStorage::new()),
+        Zeroizing::new("123456".to_string()),
+    )
+}
+
+#[test]
+fn test_undo_brings_back_a_deleted_note() -> Result<(), AppError> {
+    let vault = vault();
+    let note = vault.create("Groceries", b"milk")?;
+    let mut stack = UndoStack::default();
+
+    vault.trash(&note.uuid)?;
+    let change = Change::Deleted {
+        uuid: note.uuid.clone(),
+        title: "Groceries".to_string(),
+    };
+    stack.push(&vault, change)?;
+    assert!(vault.list()?.is_empty());
+
+    let description = stack
+        .pop()
+        .map(|change| change.revert(&vault))
+        .transpose()?;
+    assert_eq!(description.as_deref(), Some("Undid deleting \"Groceries\""));
+    assert_eq!(vault.read(&note.uuid)?, b"milk");
+    assert!(stack.pop().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_undo_restores_the_previous_field() -> Result<(), AppError> {
+    let vault = vault();
+    let first = vault.create("First", b"")?;
+    let second = vault.create("Second", b"")?;
+    for note in [&first, &second] {
+        vault.metadata_mut(&note.uuid, |metadata| metadata.tags = vec!["old".into()])?;
+    }
+    let mut stack = UndoStack::default();
+
+    let mut changes = Vec::new();
+    for note in [&first, &second] {
+        vault.metadata_mut(&note.uuid, |metadata| metadata.tags = vec!["new".into()])?;
+        let before = Field::Tags(vec!["old".into()]);
+        changes.push((
+            note.uuid.clone(),
+            note.metadata.original_filename.clone(),
+            before,
+        ));
+    }
+    stack.push(&vault, Change::Metadata(changes))?;
+
+    let description = stack
+        .pop()
+        .map(|change| change.revert(&vault))
+        .transpose()?;
+    assert_eq!(
+        description.as_deref(),
+        Some("Undid the change to the tags of 2 notes")
+    );
+    for note in vault.list()? {
+        assert_eq!(note.metadata.tags, ["old"]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_notes_deleted_for_good_once_they_cannot_be_undone() -> Result<(), AppError> {
+    let vault = vault();
+    let mut stack = UndoStack::default();
+    let mut uuids = Vec::new();
+    for i in 0..=MAX_UNDO {
+        let note = vault.create(&format!("Note {i}"), b"")?;
+        vault.trash(&note.uuid)?;
+        let change = Change::Deleted {
+            uuid: note.uuid.clone(),
+            title: note.metadata.original_filename,
+        };
+        stack.push(&vault, change)?;
+        uuids.push(note.uuid);
+    }
+
+    // The oldest fell off the stack
+    assert!(matches!(
+        vault.untrash(&uuids[0]),
+        Err(AppError::NoteNotFound(_))
+    ));
+    stack.clear(&vault)?;
+    assert!(stack.pop().is_none());
+    assert!(matches!(
+        vault.untrash(&uuids[MAX_UNDO]),
+        Err(AppError::NoteNotFound(_))
+    ));
+    Ok(())
+}
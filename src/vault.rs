@@ -0,0 +1,2528 @@
+//! The [`Vault`]: a notes directory together with the PIN that unlocks it.
+
+use crate::audit::{AuditLog, Event, EventKind};
+use crate::dedupe::HASH_CACHE_FILE_NAME;
+use crate::error::AppError;
+use crate::file::{self, NoteFileKind};
+use crate::gc::{self, Plan, Policy, Reason, Removal};
+use crate::ignorefile::{self, IgnoreRules};
+use crate::index::NoteIndex;
+use crate::lock::VaultLock;
+use crate::manifest::{self, Change, IntegrityReport, MANIFEST_FILE_NAME, Manifest, ManifestEntry};
+use crate::metadata::{self, NoteMetadata, NoteType};
+use crate::note::{self, NoteKey};
+use crate::parallel::{self, Progress};
+use crate::recovery::{FileStatus, Operation, OperationKind, Recorder};
+use crate::snippet::Snippet;
+use crate::storage::{Entry, FsStorage, MemoryStorage, ReadOnlyStorage, Storage};
+use crate::sync;
+use crate::tags::{self, TagCounts};
+use crate::template::Template;
+use crate::vaultinfo::{VAULT_INFO_FILE_NAME, VaultInfo};
+use crate::vaultstats::{self, VaultStats};
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error, info, warn};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Instant, SystemTime},
+};
+use zeroize::Zeroizing;
+
+/// A note whose encrypted content and metadata were both found in the vault.
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub uuid: String,
+    pub metadata: NoteMetadata,
+}
+
+/// What [`Vault::encrypt_unencrypted_files`] did to the notes directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EncryptionSummary {
+    /// Encrypted files that weren't named after a UUID, by new name. Each is
+    /// given a UUID and metadata titled after its old name; one that doesn't
+    /// decrypt with the PIN is put back and reported in `failed`.
+    pub renamed: Vec<String>,
+    /// Names of the plaintext files that were turned into notes.
+    pub encrypted: Vec<String>,
+    /// Plaintext files that couldn't be encrypted, with the reason. They're left
+    /// in place.
+    pub failed: Vec<(String, String)>,
+    /// Files left alone because they're empty or can't be read, with the reason.
+    pub skipped: Vec<(String, String)>,
+    /// Plaintext files left alone because the ignore file matches them, see
+    /// [`ignorefile`].
+    pub ignored: Vec<String>,
+}
+
+/// What [`Vault::encrypt_unencrypted_files`] would do, found without changing
+/// anything.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EncryptionScan {
+    /// Plaintext files to turn into notes.
+    pub unencrypted: Vec<Entry>,
+    /// Encrypted files not named after a UUID, by old and new name.
+    pub misnamed: Vec<(String, String)>,
+    /// Files left alone because they're empty or can't be read, with the reason.
+    pub skipped: Vec<(String, String)>,
+    /// Files left alone because the ignore file matches them.
+    pub ignored: Vec<String>,
+}
+
+impl EncryptionScan {
+    /// The size of the files to turn into notes, in bytes.
+    pub fn unencrypted_bytes(&self) -> u64 {
+        self.unencrypted.iter().map(|entry| entry.len).sum()
+    }
+}
+
+/// The notes as [`Vault::list_lazily`] lists them, some with metadata still
+/// to be parsed.
+#[derive(Debug, Default, Clone)]
+pub struct LazyListing {
+    /// Every complete note, most recently updated first. Those in `pending` have
+    /// placeholder metadata: the start of their UUID as title, dated by their
+    /// metadata file.
+    pub notes: Vec<Note>,
+    /// UUIDs of the notes whose metadata wasn't parsed, in the order listed, to
+    /// parse with [`Vault::load_metadata`].
+    pub pending: Vec<String>,
+}
+
+/// What [`Vault::rename_tag`] did, by note UUID.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagRenameSummary {
+    pub renamed: Vec<String>,
+    /// Notes with the tag that were left alone because they're read-only.
+    pub skipped_read_only: Vec<String>,
+}
+
+/// What [`Vault::rekey`] did, by name of the encrypted file in the notes
+/// directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RekeySummary {
+    /// Files encrypted again that decrypted fine afterwards: notes, their
+    /// previous versions, trashed notes and the hash cache.
+    pub rekeyed: Vec<String>,
+    /// Files that couldn't be encrypted again, with the reason. They're left as
+    /// they were.
+    pub failed: Vec<(String, String)>,
+    /// Files that were encrypted again but didn't decrypt on the final check,
+    /// with the reason.
+    pub unverified: Vec<(String, String)>,
+}
+
+impl RekeySummary {
+    /// Whether every file was encrypted again and checked.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.unverified.is_empty()
+    }
+}
+
+/// Whether two note titles are the same, ignoring case and surrounding whitespace.
+pub fn same_title(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// Half of a note whose other file is missing, e.g. deleted by hand. Listing
+/// the notes leaves them out, see [`Vault::orphaned_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Orphan {
+    /// Metadata without content, with the title it gives if it can be read.
+    /// [`Vault::delete_orphaned_metadata`] deletes it.
+    Metadata { uuid: String, title: Option<String> },
+    /// Content without metadata, which [`Vault::adopt`] gives new metadata.
+    Content { uuid: String },
+}
+
+impl Orphan {
+    pub fn uuid(&self) -> &str {
+        match self {
+            Orphan::Metadata { uuid, .. } | Orphan::Content { uuid } => uuid,
+        }
+    }
+}
+
+/// A previous version of a note, kept when it was overwritten.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteVersion {
+    pub id: String,
+    /// When this version was written, i.e. the `updated_at` it had back then.
+    pub saved_at: DateTime<Utc>,
+    /// Size of the encrypted file.
+    pub len: u64,
+}
+
+/// The encrypted content of a note as it was at some point, to tell whether
+/// something else replaced it since, e.g. a sync from another machine. Every
+/// write encrypts with a fresh nonce, so rewriting the same content counts too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteRevision(blake3::Hash);
+
+/// The size and modification time of the encrypted file of a note, to notice
+/// cheaply, without reading it, that another program changed it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What [`Vault::save_edit`] did with an edit.
+#[derive(Debug, Clone)]
+pub enum EditOutcome {
+    /// The content didn't change, so nothing was written.
+    Unchanged,
+    /// The note was saved.
+    Saved,
+    /// The note changed since the edit began, so the edit was saved as this
+    /// conflict copy instead, for the user to keep or discard.
+    Conflict(Box<Note>),
+}
+
+/// Orders in which notes can be listed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// Most recently updated first.
+    #[default]
+    Updated,
+    /// Most recently created first.
+    Created,
+    /// Alphabetically by title, ignoring case.
+    Title,
+    /// Largest first; notes without a recorded size come last.
+    Size,
+    /// Soonest due first; notes without a due date come last.
+    Due,
+}
+
+impl SortMode {
+    /// Every mode, in the order they're cycled through.
+    pub const ALL: [SortMode; 5] = [
+        SortMode::Updated,
+        SortMode::Created,
+        SortMode::Title,
+        SortMode::Size,
+        SortMode::Due,
+    ];
+
+    /// The mode after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let position = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(position + 1) % Self::ALL.len()]
+    }
+
+    /// Sorts `notes` in this order.
+    pub fn sort(self, notes: &mut [Note]) {
+        match self {
+            SortMode::Updated => notes.sort_by_key(|note| Reverse(note.metadata.updated_at)),
+            SortMode::Created => notes.sort_by_key(|note| Reverse(note.metadata.created_at)),
+            SortMode::Title => {
+                notes.sort_by_key(|note| note.metadata.original_filename.to_lowercase())
+            }
+            SortMode::Size => notes.sort_by_key(|note| Reverse(note.metadata.size)),
+            SortMode::Due => {
+                notes.sort_by_key(|note| (note.metadata.due_at.is_none(), note.metadata.due_at))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortMode::Updated => write!(f, "updated"),
+            SortMode::Created => write!(f, "created"),
+            SortMode::Title => write!(f, "title"),
+            SortMode::Size => write!(f, "size"),
+            SortMode::Due => write!(f, "due"),
+        }
+    }
+}
+
+impl FromStr for SortMode {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AppError::Config(format!("Unknown sort mode \"{s}\"")))
+    }
+}
+
+/// Moves the pinned notes to the front of `notes`, by their
+/// [`pin_order`](NoteMetadata::pin_order), keeping the order of the others.
+pub fn pinned_first(notes: &mut [Note]) {
+    notes.sort_by_key(|note| (note.metadata.pin_order.is_none(), note.metadata.pin_order));
+}
+
+/// Which notes to list and in what order, e.g. as given to `list`.
+///
+/// Every filter given has to match; the default lists every note, most
+/// recently updated first.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ListQuery {
+    pub sort: SortMode,
+    /// Turn the order of `sort` around.
+    pub reverse: bool,
+    /// List at most this many notes, the first ones in order.
+    pub limit: Option<usize>,
+    /// Only notes updated at or after this instant.
+    pub since: Option<DateTime<Utc>>,
+    /// Only notes updated before this instant.
+    pub until: Option<DateTime<Utc>>,
+    /// Only notes with every one of these tags, ignoring case.
+    pub tags: Vec<String>,
+    /// Only notes with at least one of these tags, ignoring case, if any are given.
+    pub any_tags: Vec<String>,
+    /// Only notes with this color label, or `none` for those without one.
+    pub color: Option<String>,
+    /// Only notes of this type; [`NoteType::Other`] stands for every unknown
+    /// type.
+    pub note_type: Option<NoteType>,
+}
+
+impl ListQuery {
+    /// Whether `note` passes every filter.
+    pub fn matches(&self, note: &Note) -> bool {
+        let metadata = &note.metadata;
+        let has_tag =
+            |wanted: &String| metadata.tags.iter().any(|tag| tags::is_within(tag, wanted));
+        self.since.is_none_or(|since| metadata.updated_at >= since)
+            && self.until.is_none_or(|until| metadata.updated_at < until)
+            && self.tags.iter().all(has_tag)
+            && (self.any_tags.is_empty() || self.any_tags.iter().any(has_tag))
+            && self
+                .color
+                .as_ref()
+                .is_none_or(|color| metadata.color.as_deref().unwrap_or("none") == color)
+            && self
+                .note_type
+                .as_ref()
+                .is_none_or(|note_type| metadata.note_type.name() == note_type.name())
+    }
+
+    /// Keeps the notes that match, in order and up to the limit.
+    pub fn apply(&self, mut notes: Vec<Note>) -> Vec<Note> {
+        notes.retain(|note| self.matches(note));
+        self.sort.sort(&mut notes);
+        if self.reverse {
+            notes.reverse();
+        }
+        if let Some(limit) = self.limit {
+            notes.truncate(limit);
+        }
+        notes
+    }
+}
+
+/// A notes directory (or any other [`Storage`]) unlocked with a PIN.
+///
+/// Every read and write of notes goes through here, so frontends never deal with
+/// file naming or encryption themselves.
+///
+/// ```no_run
+/// use ryokan::vault::Vault;
+/// use zeroize::Zeroizing;
+///
+/// # fn main() -> Result<(), ryokan::error::AppError> {
+/// let vault = Vault::open("notes", Zeroizing::new("123456".to_string()));
+/// let note = vault.create("Groceries", b"milk")?;
+/// vault.write(&note.uuid, b"milk\neggs")?;
+/// assert_eq!(vault.read(&note.uuid)?, b"milk\neggs");
+/// vault.delete(&note.uuid)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Vault {
+    storage: Box<dyn Storage>,
+    pin: Zeroizing<String>,
+    record_stats: bool,
+    /// Keep a sealed excerpt of every note saved, see [`snippet`].
+    snippets: bool,
+    history_limit: usize,
+    /// Notes larger than this, in bytes, are refused rather than decrypted.
+    max_note_size: u64,
+    /// Longer titles are cut when saved, see [`NoteMetadata::limit`].
+    max_title_length: usize,
+    /// Read back and decrypt every note file right after writing it.
+    paranoid_writes: bool,
+    read_only_mode: bool,
+    /// Parsed metadata files by name, with the size and modification time they
+    /// had, so listing again only parses the files that changed.
+    metadata_cache: Mutex<HashMap<String, CachedMetadata>>,
+    metadata_parses: AtomicUsize,
+    /// Bumped whenever listing finds notes added, removed or changed since the
+    /// previous listing, so callers can tell their copy of the list is stale.
+    generation: AtomicU64,
+    /// The integrity manifest, once loaded, so writes don't decrypt it again.
+    manifest: Mutex<Option<LoadedManifest>>,
+    audit_log: Option<AuditLog>,
+    /// Tags in use, counted when listing and kept up to date by writes, for
+    /// completion.
+    tag_counts: Mutex<Option<TagCounts>>,
+    /// Notes were added or removed since the note count of the [`VaultInfo`]
+    /// was last written.
+    count_stale: AtomicBool,
+    /// The note count the [`VaultInfo`] is known to hold, so it's only written
+    /// when it changes.
+    recorded_count: Mutex<Option<usize>>,
+}
+
+/// The integrity manifest with the key it's encrypted with, so saving it again
+/// doesn't run Argon2.
+struct LoadedManifest {
+    entries: Manifest,
+    key: NoteKey,
+}
+
+struct CachedMetadata {
+    len: u64,
+    modified: Option<SystemTime>,
+    metadata: NoteMetadata,
+}
+
+impl Vault {
+    /// Opens the vault stored in the directory `dir`.
+    pub fn open(dir: impl Into<PathBuf>, pin: Zeroizing<String>) -> Self {
+        Self::with_storage(FsStorage::new(dir), pin)
+    }
+
+    /// Opens a vault kept in an arbitrary [`Storage`].
+    pub fn with_storage(storage: impl Storage + 'static, pin: Zeroizing<String>) -> Self {
+        Self {
+            storage: Box::new(storage),
+            pin,
+            record_stats: true,
+            snippets: false,
+            history_limit: 5,
+            max_note_size: u64::MAX,
+            max_title_length: metadata::DEFAULT_MAX_TITLE_LENGTH,
+            paranoid_writes: false,
+            read_only_mode: false,
+            metadata_cache: Mutex::new(HashMap::new()),
+            metadata_parses: AtomicUsize::new(0),
+            generation: AtomicU64::new(0),
+            manifest: Mutex::new(None),
+            audit_log: None,
+            tag_counts: Mutex::new(None),
+            count_stale: AtomicBool::new(false),
+            recorded_count: Mutex::new(None),
+        }
+    }
+
+    /// Refuses every change to the vault from now on, to browse it without risk,
+    /// e.g. while sharing the screen. Changes fail with [`AppError::ReadOnlyMode`].
+    ///
+    /// It's enforced on the storage itself, so no operation can bypass it.
+    pub fn enter_read_only_mode(&mut self) {
+        if self.read_only_mode {
+            return;
+        }
+        let storage = std::mem::replace(&mut self.storage, Box::new(MemoryStorage::new()));
+        self.storage = Box::new(ReadOnlyStorage::new(storage));
+        self.read_only_mode = true;
+    }
+
+    /// Whether [`Vault::enter_read_only_mode`] was called.
+    pub fn is_read_only_mode(&self) -> bool {
+        self.read_only_mode
+    }
+
+    /// Sets how many previous versions of each note are kept; 0 disables the history.
+    pub fn set_history_limit(&mut self, history_limit: usize) {
+        self.history_limit = history_limit;
+    }
+
+    /// Sets the size in bytes above which notes are refused with
+    /// [`AppError::TooLarge`] rather than read into memory, e.g. a corrupted
+    /// file of several gigabytes.
+    pub fn set_max_note_size(&mut self, max_note_size: u64) {
+        self.max_note_size = max_note_size;
+    }
+
+    /// Sets how many characters of a title are saved, e.g. of a file imported
+    /// with a 10,000-character name. Titles already saved are only cut the next
+    /// time their note is saved.
+    pub fn set_max_title_length(&mut self, max_title_length: usize) {
+        self.max_title_length = max_title_length;
+    }
+
+    /// Sets whether every note file written is read back and decrypted at once,
+    /// failing with [`AppError::WriteVerification`] if it doesn't give back the
+    /// content meant to be stored, e.g. because of a faulty disk.
+    pub fn set_paranoid_writes(&mut self, paranoid_writes: bool) {
+        self.paranoid_writes = paranoid_writes;
+    }
+
+    /// Sets whether the size and word count of notes are recorded in their metadata.
+    ///
+    /// They're derived from the plaintext, so recording them leaks a little about
+    /// the content. Disabling it clears them from every note saved afterwards.
+    pub fn set_record_stats(&mut self, record_stats: bool) {
+        self.record_stats = record_stats;
+    }
+
+    fn content_stats<'a>(&self, content: &'a [u8]) -> Option<&'a [u8]> {
+        self.record_stats.then_some(content)
+    }
+
+    /// Sets whether an excerpt of every note saved is kept in its metadata,
+    /// sealed with the PIN, see [`snippet`]. Disabling it clears them from
+    /// every note saved afterwards.
+    pub fn set_snippets(&mut self, snippets: bool) {
+        self.snippets = snippets;
+    }
+
+    /// Takes a new snippet of `content` into `metadata`, or clears it if
+    /// snippets are disabled or it can't be sealed.
+    fn seal_snippet(&self, metadata: &mut NoteMetadata, content: &[u8]) {
+        metadata.snippet = None;
+        if !self.snippets {
+            return;
+        }
+        let Some(snippet) = Snippet::new(&metadata.original_filename, content) else {
+            return;
+        };
+        match self.manifest_key().and_then(|key| snippet.seal(&key)) {
+            Ok(sealed) => metadata.snippet = Some(sealed),
+            Err(e) => warn!(
+                "Could not seal the snippet of \"{}\": {e}",
+                metadata.original_filename
+            ),
+        }
+    }
+
+    /// The snippet kept in `metadata`, or `None` if it has none or it was
+    /// sealed with a key that's been replaced since, e.g. by a rekey.
+    pub fn open_snippet(&self, metadata: &NoteMetadata) -> Option<Snippet> {
+        let sealed = metadata.snippet.as_deref()?;
+        let key = self
+            .manifest_key()
+            .inspect_err(|e| warn!("Could not open the snippets: {e}"))
+            .ok()?;
+        Snippet::open(sealed, &key).ok()
+    }
+
+    /// Takes a new snippet of the note `uuid` from `content`, its decrypted
+    /// content, if its snippet is missing or was taken from other content,
+    /// e.g. before a sync replaced it. Returns the metadata if it changed.
+    pub fn refresh_snippet(
+        &self,
+        uuid: &str,
+        content: &[u8],
+    ) -> Result<Option<NoteMetadata>, AppError> {
+        if !self.snippets || !self.is_writable() {
+            return Ok(None);
+        }
+        let note = self.get(uuid)?;
+        let fresh = match self.open_snippet(&note.metadata) {
+            Some(snippet) => snippet.is_of(content),
+            None => Snippet::new(&note.metadata.original_filename, content).is_none(),
+        };
+        if fresh {
+            return Ok(None);
+        }
+        debug!("Refreshing the snippet of {uuid}");
+        self.update_metadata(uuid, |metadata| self.seal_snippet(metadata, content))
+            .map(Some)
+    }
+
+    /// Cuts the title and tags of `metadata` about to be saved, see
+    /// [`NoteMetadata::limit`].
+    fn limit(&self, metadata: &mut NoteMetadata) {
+        if metadata.limit(self.max_title_length) {
+            warn!(
+                "Cut the title or tags of \"{}\", which were too long",
+                metadata.original_filename
+            );
+        }
+    }
+
+    /// Records an event in `audit_log` for every change to the notes from now on.
+    pub fn set_audit_log(&mut self, audit_log: AuditLog) {
+        self.audit_log = Some(audit_log);
+    }
+
+    /// Records `event` in the audit log, if there's one, sealed with the PIN if
+    /// the log is encrypted. Changes made through the vault are recorded already.
+    pub fn record(&self, event: Event) {
+        if matches!(
+            event.kind,
+            EventKind::Created
+                | EventKind::Imported
+                | EventKind::Restored
+                | EventKind::ConflictResolved
+                | EventKind::Trashed
+                | EventKind::Deleted
+        ) {
+            self.count_stale.store(true, Ordering::Relaxed);
+        }
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(&event, Some(&self.pin));
+        }
+    }
+
+    /// The plaintext description of the vault, if it has one yet.
+    pub fn info(&self) -> Result<Option<VaultInfo>, AppError> {
+        VaultInfo::load(self.storage.as_ref())
+    }
+
+    /// Writes `count` as the number of notes to the [`VaultInfo`], creating it
+    /// if needed, unless it's there already. A failure only costs a stale
+    /// count, so it's logged rather than returned.
+    fn record_note_count(&self, count: usize) {
+        self.count_stale.store(false, Ordering::Relaxed);
+        let mut recorded = self
+            .recorded_count
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.read_only_mode || *recorded == Some(count) {
+            return;
+        }
+        let result = self.info().and_then(|info| {
+            let mut info = info.unwrap_or_default();
+            if info.note_count != Some(count) {
+                info.note_count = Some(count);
+                info.save(self.storage.as_ref())?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => *recorded = Some(count),
+            Err(e) => warn!("Could not record the note count in {VAULT_INFO_FILE_NAME}: {e}"),
+        }
+    }
+
+    /// The notes directory backing this vault, if it lives on disk.
+    pub fn dir(&self) -> Option<&Path> {
+        self.storage.root()
+    }
+
+    /// Lists every complete note, most recently updated first.
+    ///
+    /// Notes missing either their content or their metadata file are skipped.
+    /// Metadata files whose size and modification time didn't change since the
+    /// last listing aren't parsed again.
+    pub fn list(&self) -> Result<Vec<Note>, AppError> {
+        Ok(self.list_lazily(|_| false)?.notes)
+    }
+
+    /// Like [`Vault::list`], but notes for which `defer` returns true are listed
+    /// with placeholder metadata instead of parsing theirs, unless it's cached,
+    /// e.g. to show a list of thousands of notes before parsing them all.
+    pub fn list_lazily(&self, defer: impl Fn(&str) -> bool) -> Result<LazyListing, AppError> {
+        let started = Instant::now();
+        let parses_before = self.metadata_parses();
+        let complete = self.complete_notes()?;
+        let count = complete.len();
+        let mut cache = self.metadata_cache();
+        let cached_before = cache.len();
+        cache.retain(|name, _| {
+            file::parse_note_file_name(name).is_some_and(|(uuid, _)| complete.contains_key(uuid))
+        });
+        let mut changed = cache.len() != cached_before;
+
+        let mut notes = Vec::new();
+        let mut deferred = HashSet::new();
+        for (uuid, entry) in complete {
+            let cached = cache
+                .get(&entry.name)
+                .filter(|cached| cached.len == entry.len && cached.modified == entry.modified);
+            if let Some(cached) = cached {
+                notes.push(Note {
+                    uuid,
+                    metadata: cached.metadata.clone(),
+                });
+                continue;
+            }
+            if defer(&uuid) {
+                let updated_at = entry.modified.map_or_else(Utc::now, DateTime::from);
+                let short: String = uuid.chars().take(8).collect();
+                notes.push(Note {
+                    uuid: uuid.clone(),
+                    metadata: NoteMetadata::with_timestamps(short, updated_at, updated_at),
+                });
+                deferred.insert(uuid);
+                continue;
+            }
+
+            self.metadata_parses.fetch_add(1, Ordering::Relaxed);
+            changed = true;
+            match NoteMetadata::load(self.storage.as_ref(), &entry.name) {
+                Ok(metadata) => {
+                    cache.insert(
+                        entry.name,
+                        CachedMetadata {
+                            len: entry.len,
+                            modified: entry.modified,
+                            metadata: metadata.clone(),
+                        },
+                    );
+                    notes.push(Note { uuid, metadata });
+                }
+                Err(e) => {
+                    cache.remove(&entry.name);
+                    error!("Error loading metadata for {uuid}: {e}");
+                }
+            }
+        }
+
+        drop(cache);
+        if changed {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+        // Also corrects a count left stale by another program
+        self.record_note_count(count);
+        // Placeholders have no tags
+        if deferred.is_empty() {
+            *self.tag_cache() = Some(tags::count(&notes));
+        }
+        SortMode::Updated.sort(&mut notes);
+        debug!(
+            "Listed {} notes, parsing {} metadata files and leaving {} for later, in {:.2?}",
+            notes.len(),
+            self.metadata_parses() - parses_before,
+            deferred.len(),
+            started.elapsed()
+        );
+
+        let pending = notes
+            .iter()
+            .filter(|note| deferred.contains(&note.uuid))
+            .map(|note| note.uuid.clone())
+            .collect();
+        Ok(LazyListing { notes, pending })
+    }
+
+    /// The metadata of the note `uuid`, parsed unless its file didn't change
+    /// since it was last, and kept for listing afterwards. It's how notes
+    /// [`Vault::list_lazily`] left for later are parsed, possibly on another
+    /// thread.
+    pub fn load_metadata(&self, uuid: &str) -> Result<NoteMetadata, AppError> {
+        let (_, name) = file::note_file_names(uuid);
+        let entry = self.storage.entry(&name)?;
+        if let Some(cached) = self
+            .metadata_cache()
+            .get(&name)
+            .filter(|cached| cached.len == entry.len && cached.modified == entry.modified)
+        {
+            return Ok(cached.metadata.clone());
+        }
+        // Parsed without holding the cache, so listing isn't held up meanwhile
+        self.metadata_parses.fetch_add(1, Ordering::Relaxed);
+        let metadata = NoteMetadata::load(self.storage.as_ref(), &name)?;
+        self.metadata_cache().insert(
+            name,
+            CachedMetadata {
+                len: entry.len,
+                modified: entry.modified,
+                metadata: metadata.clone(),
+            },
+        );
+        Ok(metadata)
+    }
+
+    /// Lists the notes `query` asks for, in its order.
+    pub fn query(&self, query: &ListQuery) -> Result<Vec<Note>, AppError> {
+        Ok(query.apply(self.list()?))
+    }
+
+    /// How many metadata files listing the notes parsed so far.
+    pub fn metadata_parses(&self) -> usize {
+        self.metadata_parses.load(Ordering::Relaxed)
+    }
+
+    /// How many times listing found the notes changed, by this process or
+    /// another one. It only moves forward.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Like [`Vault::list`], but parses every metadata file again.
+    pub fn force_list(&self) -> Result<Vec<Note>, AppError> {
+        self.forget_metadata();
+        self.list()
+    }
+
+    /// Drops the parsed metadata files, so listing parses every one again, e.g.
+    /// to take in changes that kept their size and modification time.
+    pub fn forget_metadata(&self) {
+        self.metadata_cache().clear();
+    }
+
+    fn metadata_cache(&self) -> MutexGuard<'_, HashMap<String, CachedMetadata>> {
+        // A panic while holding the lock can't leave the cache half-updated
+        self.metadata_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn tag_cache(&self) -> MutexGuard<'_, Option<TagCounts>> {
+        self.tag_counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Renames the tag `from`, and the tags below it, to `to` on every note
+    /// that isn't read-only, see [`tags::rename`].
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<TagRenameSummary, AppError> {
+        tags::validate(from)?;
+        tags::validate(to)?;
+        let _lock = self.lock()?;
+        let mut summary = TagRenameSummary::default();
+        for note in self.list()? {
+            if !note
+                .metadata
+                .tags
+                .iter()
+                .any(|tag| tags::is_within(tag, from))
+            {
+                continue;
+            }
+            if note.metadata.read_only {
+                summary.skipped_read_only.push(note.uuid);
+                continue;
+            }
+            self.metadata_mut(&note.uuid, |metadata| {
+                tags::rename(&mut metadata.tags, from, to);
+            })?;
+            summary.renamed.push(note.uuid);
+        }
+        Ok(summary)
+    }
+
+    /// Every tag in use, with how many notes use it. Only the first call lists
+    /// the notes, writes keep it up to date afterwards.
+    pub fn tags(&self) -> Result<TagCounts, AppError> {
+        if let Some(counts) = self.tag_cache().as_ref() {
+            return Ok(counts.clone());
+        }
+        Ok(tags::count(&self.list()?))
+    }
+
+    /// Updates the tags in use for a note whose tags went from `old` to `new`.
+    fn retag(&self, old: &[String], new: &[String]) {
+        if let Some(counts) = self.tag_cache().as_mut() {
+            tags::retag(counts, old, new);
+        }
+    }
+
+    /// The metadata file of every note that has both its content and metadata
+    /// files, by UUID.
+    fn complete_notes(&self) -> Result<BTreeMap<String, Entry>, AppError> {
+        let mut has_content = HashSet::new();
+        let mut metadata_entries = BTreeMap::new();
+
+        for entry in self.storage.list("")? {
+            let Some((uuid, kind)) = file::parse_note_file_name(&entry.name) else {
+                continue;
+            };
+            let uuid = uuid.to_string();
+            match kind {
+                NoteFileKind::Content => {
+                    has_content.insert(uuid);
+                }
+                NoteFileKind::Metadata => {
+                    metadata_entries.insert(uuid, entry);
+                }
+            }
+        }
+
+        metadata_entries.retain(|uuid, _| has_content.contains(uuid));
+        Ok(metadata_entries)
+    }
+
+    /// Files at the root of the notes directory that are neither part of a note
+    /// nor one of Ryokan's own files, e.g. plaintext waiting to be encrypted or
+    /// a stray backup. They're left out of [`Vault::list`].
+    pub fn unexpected_files(&self) -> Result<Vec<String>, AppError> {
+        let mut names: Vec<_> = self
+            .storage
+            .list("")?
+            .into_iter()
+            .map(|entry| entry.name)
+            .filter(|name| file::is_unexpected_file(name))
+            .collect();
+        names.sort();
+        for name in &names {
+            warn!("Ignoring {name}, it isn't part of a note");
+        }
+        Ok(names)
+    }
+
+    /// Notes with only one of their two files, sorted by UUID.
+    pub fn orphaned_files(&self) -> Result<Vec<Orphan>, AppError> {
+        let mut content = BTreeSet::new();
+        let mut metadata = BTreeSet::new();
+        for entry in self.storage.list("")? {
+            match file::parse_note_file_name(&entry.name) {
+                Some((uuid, NoteFileKind::Content)) => content.insert(uuid.to_string()),
+                Some((uuid, NoteFileKind::Metadata)) => metadata.insert(uuid.to_string()),
+                None => continue,
+            };
+        }
+        let orphans = metadata
+            .symmetric_difference(&content)
+            .map(|uuid| {
+                let (_, metadata_name) = file::note_file_names(uuid);
+                if metadata.contains(uuid) {
+                    let title = NoteMetadata::load(self.storage.as_ref(), &metadata_name)
+                        .ok()
+                        .map(|metadata| metadata.original_filename);
+                    Orphan::Metadata {
+                        uuid: uuid.clone(),
+                        title,
+                    }
+                } else {
+                    Orphan::Content { uuid: uuid.clone() }
+                }
+            })
+            .collect();
+        Ok(orphans)
+    }
+
+    /// Deletes the metadata of the note `uuid`, whose content is missing, and
+    /// its history.
+    pub fn delete_orphaned_metadata(&self, uuid: &str) -> Result<(), AppError> {
+        let _lock = self.lock()?;
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        if self.storage.exists(&encrypted_name) || !self.storage.exists(&metadata_name) {
+            return Err(AppError::NoteNotFound(format!(
+                "No metadata without content for {uuid}"
+            )));
+        }
+        self.remove(uuid)?;
+        self.record(Event::new(EventKind::Deleted, Some(uuid)));
+        Ok(())
+    }
+
+    /// Gives the content `uuid`, whose metadata is missing, new metadata, making
+    /// it a note again. It's titled after its UUID and dated by its file.
+    ///
+    /// The content has to decrypt with the PIN of this vault, so files from
+    /// another vault aren't taken in.
+    pub fn adopt(&self, uuid: &str) -> Result<Note, AppError> {
+        let _lock = self.lock()?;
+        let short: String = uuid.chars().take(8).collect();
+        self.adopt_locked(uuid, format!("Recovered {short}"))
+    }
+
+    /// Does the work of [`Vault::adopt`] for a caller already holding the vault
+    /// lock, giving the note `title`.
+    fn adopt_locked(&self, uuid: &str, title: String) -> Result<Note, AppError> {
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        if self.storage.exists(&metadata_name) {
+            return Err(AppError::NoteNotFound(format!(
+                "No content without metadata for {uuid}"
+            )));
+        }
+        let content = Zeroizing::new(file::load_and_decrypt_note_content(
+            self.storage.as_ref(),
+            &encrypted_name,
+            &self.pin,
+        )?);
+        let (created_at, updated_at) = self.file_times(uuid)?;
+        let mut metadata = NoteMetadata::with_timestamps(title, created_at, updated_at);
+        metadata.update_content_stats(self.content_stats(&content));
+        self.seal_snippet(&mut metadata, &content);
+        metadata.save(self.storage.as_ref(), &metadata_name)?;
+
+        self.update_manifest([uuid]);
+        self.record(Event::new(EventKind::Created, Some(uuid)));
+        let title = metadata.original_filename.clone();
+        self.update_index(|index| {
+            index.titles.insert(uuid.to_string(), title);
+        })?;
+        Ok(Note {
+            uuid: uuid.to_string(),
+            metadata,
+        })
+    }
+
+    /// UUIDs of the notes that have both their content and metadata files.
+    fn complete_uuids(&self) -> Result<BTreeSet<String>, AppError> {
+        Ok(self.complete_notes()?.into_keys().collect())
+    }
+
+    /// Returns the uuid → title index, rebuilding it if it's missing or stale.
+    pub fn index(&self) -> Result<NoteIndex, AppError> {
+        match NoteIndex::load(self.storage.as_ref()) {
+            Some(index) if index.matches(&self.complete_uuids()?) => Ok(index),
+            // The index is only a cache, so it's rebuilt without being saved
+            _ if self.read_only_mode => Ok(NoteIndex::from_notes(&self.list()?)),
+            _ => self.reindex(),
+        }
+    }
+
+    /// Rebuilds the index from the metadata files and saves it.
+    pub fn reindex(&self) -> Result<NoteIndex, AppError> {
+        info!("Rebuilding note index");
+        let index = NoteIndex::from_notes(&self.list()?);
+        index.save(self.storage.as_ref())?;
+        Ok(index)
+    }
+
+    /// Applies `update` to the saved index, or rebuilds it if there's none.
+    fn update_index(&self, update: impl FnOnce(&mut NoteIndex)) -> Result<(), AppError> {
+        match NoteIndex::load(self.storage.as_ref()) {
+            Some(mut index) => {
+                update(&mut index);
+                index.save(self.storage.as_ref())
+            }
+            None => self.reindex().map(|_| ()),
+        }
+    }
+
+    /// Looks up a single note by UUID.
+    pub fn get(&self, uuid: &str) -> Result<Note, AppError> {
+        // Anything else, e.g. with `../`, could name files outside the vault
+        if uuid::Uuid::parse_str(uuid).is_err() {
+            return Err(AppError::NoteNotFound(uuid.to_string()));
+        }
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        if !self.storage.exists(&encrypted_name) || !self.storage.exists(&metadata_name) {
+            return Err(AppError::NoteNotFound(uuid.to_string()));
+        }
+        let metadata = NoteMetadata::load(self.storage.as_ref(), &metadata_name)?;
+        Ok(Note {
+            uuid: uuid.to_string(),
+            metadata,
+        })
+    }
+
+    /// Finds a note by UUID or, failing that, by title or alias, compared with
+    /// [`same_title`].
+    pub fn find(&self, query: &str) -> Result<Note, AppError> {
+        if let Ok(note) = self.get(query) {
+            return Ok(note);
+        }
+        let mut matches = self.list()?.into_iter().filter(|note| {
+            std::iter::once(&note.metadata.original_filename)
+                .chain(&note.metadata.aliases)
+                .any(|title| same_title(title, query))
+        });
+        match (matches.next(), matches.next()) {
+            (Some(note), None) => Ok(note),
+            (Some(_), Some(_)) => Err(AppError::AmbiguousNote(query.to_string())),
+            (None, _) => Err(AppError::NoteNotFound(query.to_string())),
+        }
+    }
+
+    /// Encrypts `content` into a new note titled `title`.
+    pub fn create(&self, title: &str, content: &[u8]) -> Result<Note, AppError> {
+        self.create_with_metadata(&NoteMetadata::new(title), content)
+    }
+
+    /// Encrypts `content` into a new note with existing metadata, e.g. to keep the
+    /// timestamps of an imported file.
+    pub fn create_with_metadata(
+        &self,
+        metadata: &NoteMetadata,
+        content: &[u8],
+    ) -> Result<Note, AppError> {
+        let mut metadata = metadata.clone();
+        metadata.update_content_stats(self.content_stats(content));
+        self.limit(&mut metadata);
+        self.seal_snippet(&mut metadata, content);
+        let uuid = file::create_new_note(self.storage.as_ref(), &self.pin, &metadata, content)?;
+        if let Err(e) = self.check_written(&uuid, content) {
+            let _ = file::delete_note_files(self.storage.as_ref(), &uuid);
+            return Err(e);
+        }
+        self.update_manifest([uuid.as_str()]);
+        self.record(Event::new(EventKind::Created, Some(&uuid)));
+        self.retag(&[], &metadata.tags);
+        let note = self.get(&uuid)?;
+        self.update_index(|index| {
+            index
+                .titles
+                .insert(uuid, metadata.original_filename.clone());
+        })?;
+        Ok(note)
+    }
+
+    /// Creates a note from each of `items` on several threads, see [`parallel`],
+    /// calling `progress` after each one. `load` gives the metadata and content of
+    /// an item, and `name` what to call it in the progress.
+    ///
+    /// Returns the result of each item in order: one failing doesn't stop the
+    /// others. Only updating the index afterwards can fail the whole call.
+    pub fn create_many<T: Sync>(
+        &self,
+        items: &[T],
+        name: fn(&T) -> &str,
+        load: impl Fn(&T) -> Result<(NoteMetadata, Vec<u8>), AppError> + Sync,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<Vec<Result<Note, AppError>>, AppError> {
+        self.create_many_recorded(items, name, load, progress, None)
+    }
+
+    /// Like [`Vault::create_many`], recording how far each item got with
+    /// `recorder`, under the name `name` gives it.
+    fn create_many_recorded<T: Sync>(
+        &self,
+        items: &[T],
+        name: fn(&T) -> &str,
+        load: impl Fn(&T) -> Result<(NoteMetadata, Vec<u8>), AppError> + Sync,
+        progress: &(dyn Fn(Progress) + Sync),
+        recorder: Option<&Recorder>,
+    ) -> Result<Vec<Result<Note, AppError>>, AppError> {
+        let total = items.len();
+        let done = AtomicUsize::new(0);
+        let record = |item: &T, status: FileStatus| match recorder {
+            Some(recorder) => recorder.set(name(item), status),
+            None => Ok(()),
+        };
+        let results = parallel::map(items, parallel::argon2_workers(), |item| {
+            let result = load(item).and_then(|(mut metadata, content)| {
+                let content = Zeroizing::new(content);
+                metadata.update_content_stats(self.content_stats(&content));
+                self.limit(&mut metadata);
+                self.seal_snippet(&mut metadata, &content);
+                let uuid = file::generate_uuid();
+                record(item, FileStatus::Writing { uuid: uuid.clone() })?;
+                let written =
+                    file::write_note(self.storage.as_ref(), &self.pin, &uuid, &metadata, &content)
+                        .and_then(|()| {
+                            // The file goes away afterwards, so the note has to be readable
+                            if recorder.is_some() {
+                                self.verify_written(&uuid, &content)
+                            } else {
+                                self.check_written(&uuid, &content)
+                            }
+                        });
+                if let Err(e) = written {
+                    // Don't leave half a note behind
+                    let _ = file::delete_note_files(self.storage.as_ref(), &uuid);
+                    return Err(e);
+                }
+                record(item, FileStatus::Written { uuid: uuid.clone() })?;
+                Ok(Note { uuid, metadata })
+            });
+            progress(Progress {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                name: name(item),
+            });
+            result
+        });
+
+        // Updated once at the end, since the threads would overwrite each other
+        let titles: Vec<_> = results
+            .iter()
+            .flatten()
+            .map(|note| (note.uuid.clone(), note.metadata.original_filename.clone()))
+            .collect();
+        if !titles.is_empty() {
+            self.update_manifest(titles.iter().map(|(uuid, _)| uuid.as_str()));
+            for note in results.iter().flatten() {
+                self.record(Event::new(EventKind::Created, Some(&note.uuid)));
+                self.retag(&[], &note.metadata.tags);
+            }
+            self.update_index(|index| index.titles.extend(titles))?;
+        }
+        Ok(results)
+    }
+
+    /// Checks that the content of the note `uuid`, just written, decrypts back to
+    /// `content`.
+    fn verify_written(&self, uuid: &str, content: &[u8]) -> Result<(), AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        self.verify_file(&encrypted_name, &self.pin, content)
+    }
+
+    /// Like [`Vault::verify_written`], only with paranoid writes on.
+    fn check_written(&self, uuid: &str, content: &[u8]) -> Result<(), AppError> {
+        if self.paranoid_writes {
+            self.verify_written(uuid, content)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that the file `name`, just written, decrypts with `pin` back to
+    /// `content`.
+    fn verify_file(&self, name: &str, pin: &str, content: &[u8]) -> Result<(), AppError> {
+        let read = file::load_and_decrypt_note_content(self.storage.as_ref(), name, pin)
+            .map_err(|e| AppError::WriteVerification(format!("{name} doesn't decrypt: {e}")))?;
+        if Zeroizing::new(read).as_slice() == content {
+            Ok(())
+        } else {
+            Err(AppError::WriteVerification(format!(
+                "{name} doesn't decrypt to what was written"
+            )))
+        }
+    }
+
+    /// Creates the note `uuid` or replaces it entirely, e.g. with a note coming
+    /// from another machine. A replaced note keeps its previous content in its history.
+    ///
+    /// Fails with [`AppError::ReadOnlyNote`] if the note exists and is marked
+    /// read-only.
+    pub fn import_note(
+        &self,
+        uuid: &str,
+        metadata: &NoteMetadata,
+        content: &[u8],
+    ) -> Result<Note, AppError> {
+        if self.get(uuid).is_ok_and(|note| note.metadata.read_only) {
+            return Err(AppError::ReadOnlyNote(uuid.to_string()));
+        }
+        let mut metadata = metadata.clone();
+        metadata.update_content_stats(self.content_stats(content));
+        self.limit(&mut metadata);
+        self.seal_snippet(&mut metadata, content);
+        if self.get(uuid).is_ok() {
+            self.write_content(uuid, content)?;
+            self.update_metadata(uuid, |saved| *saved = metadata)?;
+        } else {
+            file::write_note(self.storage.as_ref(), &self.pin, uuid, &metadata, content)?;
+            self.check_written(uuid, content)?;
+            self.update_manifest([uuid]);
+            self.retag(&[], &metadata.tags);
+            self.update_index(|index| {
+                index
+                    .titles
+                    .insert(uuid.to_string(), metadata.original_filename.clone());
+            })?;
+        }
+        self.record(Event::new(EventKind::Imported, Some(uuid)));
+        self.get(uuid)
+    }
+
+    /// Takes the lock that serializes read-modify-write changes to the vault
+    /// between processes, waiting for it if needed.
+    ///
+    /// Fails in read-only mode, where nothing may change. The lock isn't
+    /// reentrant, so methods that take it don't call one another.
+    pub fn lock(&self) -> Result<VaultLock, AppError> {
+        if self.read_only_mode {
+            return Err(AppError::ReadOnlyMode);
+        }
+        VaultLock::acquire(self.dir())
+    }
+
+    /// The title `template` gives a note created at `at`, with `{n}` counting
+    /// past the titles notes already have.
+    pub fn next_title(&self, template: &Template, at: DateTime<Local>) -> Result<String, AppError> {
+        let titles = self.index()?.titles;
+        Ok(template.expand(&at, |title| {
+            titles.values().any(|existing| same_title(existing, title))
+        }))
+    }
+
+    /// Appends `text` under a line made from `header`, e.g. a timestamp, to the
+    /// note `inbox`, given by UUID or title, creating a note titled `inbox` if
+    /// there's none. Returns its UUID.
+    pub fn capture(
+        &self,
+        inbox: &str,
+        text: &str,
+        header: &Template,
+        at: DateTime<Local>,
+    ) -> Result<String, AppError> {
+        let _lock = self.lock()?;
+        let entry = format!("{}\n{}\n", header.expand(&at, |_| false), text.trim_end());
+        let note = match self.find(inbox) {
+            Ok(note) => note,
+            Err(AppError::NoteNotFound(_)) => {
+                return Ok(self.create(inbox, entry.as_bytes())?.uuid);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut content = self.read(&note.uuid)?;
+        if !content.is_empty() {
+            // Keep a blank line between entries
+            while !content.ends_with(b"\n\n") {
+                content.push(b'\n');
+            }
+        }
+        content.extend_from_slice(entry.as_bytes());
+        self.write(&note.uuid, &content)?;
+        Ok(note.uuid)
+    }
+
+    /// Adds `data` at the end of a note, as raw bytes. With a `separator`, it goes
+    /// on a line of its own between the existing content and `data`.
+    ///
+    /// Holds the vault lock, so concurrent changes from other processes aren't lost.
+    pub fn append(
+        &self,
+        uuid: &str,
+        data: &[u8],
+        separator: Option<&str>,
+    ) -> Result<NoteMetadata, AppError> {
+        let _lock = self.lock()?;
+        let mut content = self.read(uuid)?;
+        if let Some(separator) = separator {
+            if !content.is_empty() && !content.ends_with(b"\n") {
+                content.push(b'\n');
+            }
+            content.extend_from_slice(separator.as_bytes());
+            content.push(b'\n');
+        }
+        content.extend_from_slice(data);
+        self.write(uuid, &content)
+    }
+
+    /// Like [`Vault::append`], but adds `data`, after the `separator` line if any,
+    /// at the start of the note.
+    pub fn prepend(
+        &self,
+        uuid: &str,
+        data: &[u8],
+        separator: Option<&str>,
+    ) -> Result<NoteMetadata, AppError> {
+        let _lock = self.lock()?;
+        let existing = self.read(uuid)?;
+        let mut content = Vec::with_capacity(data.len() + existing.len());
+        if let Some(separator) = separator {
+            content.extend_from_slice(separator.as_bytes());
+            content.push(b'\n');
+        }
+        content.extend_from_slice(data);
+        if separator.is_some() && !content.ends_with(b"\n") && !existing.is_empty() {
+            content.push(b'\n');
+        }
+        content.extend_from_slice(&existing);
+        self.write(uuid, &content)
+    }
+
+    /// Decrypts the content of a note.
+    ///
+    /// Fails with [`AppError::TooLarge`] if its file is larger than the limit
+    /// set with [`Vault::set_max_note_size`].
+    pub fn read(&self, uuid: &str) -> Result<Vec<u8>, AppError> {
+        self.get(uuid)?;
+        let started = Instant::now();
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        self.check_size(uuid, &encrypted_name)?;
+        let content =
+            file::load_and_decrypt_note_content(self.storage.as_ref(), &encrypted_name, &self.pin);
+        debug!("Read {uuid} in {:.2?}", started.elapsed());
+        content
+    }
+
+    /// Encrypts `content` with the PIN into a file of the vault that isn't a note,
+    /// such as a cache.
+    pub fn write_private_file(&self, name: &str, content: &[u8]) -> Result<(), AppError> {
+        let encrypted_content = note::encrypt_note_content(content, &self.pin)?;
+        file::save_note_to_file(self.storage.as_ref(), &encrypted_content, name)
+    }
+
+    /// Decrypts a file written with [`Vault::write_private_file`], or returns
+    /// `None` if there's none.
+    pub fn read_private_file(&self, name: &str) -> Result<Option<Vec<u8>>, AppError> {
+        if !self.storage.exists(name) {
+            return Ok(None);
+        }
+        file::load_and_decrypt_note_content(self.storage.as_ref(), name, &self.pin).map(Some)
+    }
+
+    /// Replaces the content of a note and bumps its `updated_at` timestamp.
+    ///
+    /// Fails with [`AppError::ReadOnlyNote`] if the note is marked read-only.
+    pub fn write(&self, uuid: &str, content: &[u8]) -> Result<NoteMetadata, AppError> {
+        if self.get(uuid)?.metadata.read_only {
+            return Err(AppError::ReadOnlyNote(uuid.to_string()));
+        }
+        self.force_write(uuid, content)
+    }
+
+    /// Like [`Vault::write`], but also overwrites read-only notes.
+    ///
+    /// The previous content is kept in the history of the note.
+    pub fn force_write(&self, uuid: &str, content: &[u8]) -> Result<NoteMetadata, AppError> {
+        let metadata = self.write_content(uuid, content)?;
+        self.record(Event::new(EventKind::Edited, Some(uuid)));
+        Ok(metadata)
+    }
+
+    /// The current revision of a note, taken when an edit begins.
+    pub fn revision(&self, uuid: &str) -> Result<NoteRevision, AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        Ok(NoteRevision(blake3::hash(
+            &self.storage.read(&encrypted_name)?,
+        )))
+    }
+
+    /// Checks that the note `uuid` can be saved, e.g. that its directory isn't
+    /// on read-only media, before an edit is spent on it.
+    pub fn check_writable(&self, uuid: &str) -> Result<(), AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        self.storage
+            .check_writable(&encrypted_name)
+            .map_err(|e| match e {
+                AppError::Io(e) => AppError::NotWritable {
+                    uuid: uuid.to_string(),
+                    reason: e.to_string(),
+                },
+                e => e,
+            })
+    }
+
+    /// Whether notes can be saved at all, i.e. the vault isn't in read-only mode
+    /// and the notes directory isn't on read-only media.
+    pub fn is_writable(&self) -> bool {
+        self.storage.check_writable("").is_ok()
+    }
+
+    /// Saves an edit that began at revision `base`, like [`Vault::force_write`].
+    ///
+    /// If the note changed since, its content isn't overwritten: the edit is
+    /// saved as a conflict copy, like a conflict met while syncing.
+    pub fn save_edit(
+        &self,
+        uuid: &str,
+        base: &NoteRevision,
+        content: &[u8],
+    ) -> Result<EditOutcome, AppError> {
+        if self.revision(uuid)? == *base {
+            self.force_write(uuid, content)?;
+            return Ok(EditOutcome::Saved);
+        }
+        let note = self.get(uuid)?;
+        let mut metadata = NoteMetadata::new(format!(
+            "{}{}",
+            note.metadata.original_filename,
+            sync::CONFLICT_TITLE_SUFFIX
+        ));
+        metadata.tags = note.metadata.tags;
+        metadata.conflict_of = Some(note.uuid);
+        let copy = self.create_with_metadata(&metadata, content)?;
+        warn!(
+            "{uuid} changed while it was being edited, saved the edit as {}",
+            copy.uuid
+        );
+        Ok(EditOutcome::Conflict(Box::new(copy)))
+    }
+
+    /// Replaces the content of a note, keeping the previous one in its history,
+    /// without recording it in the audit log.
+    fn write_content(&self, uuid: &str, content: &[u8]) -> Result<NoteMetadata, AppError> {
+        let started = Instant::now();
+        let note = self.get(uuid)?;
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        if self.history_limit > 0 {
+            // Copied rather than moved, so a failed write still leaves the note intact
+            let version_id = file::version_id(note.metadata.updated_at);
+            let previous = self.storage.read(&encrypted_name)?;
+            self.storage
+                .write(&file::version_file_name(uuid, &version_id), &previous)?;
+            self.prune_history(uuid)?;
+        }
+        let encrypted_content = note::encrypt_note_content(content, &self.pin)?;
+        file::save_note_to_file(self.storage.as_ref(), &encrypted_content, &encrypted_name)?;
+        self.check_written(uuid, content)?;
+        self.update_manifest([uuid]);
+
+        let metadata = self.update_metadata(uuid, |metadata| {
+            metadata.updated_at = Utc::now();
+            metadata.update_content_stats(self.content_stats(content));
+            self.seal_snippet(metadata, content);
+        });
+        debug!("Wrote {uuid} in {:.2?}", started.elapsed());
+        metadata
+    }
+
+    /// What [`Vault::collect_garbage`] would delete under `policy` now.
+    pub fn gc_plan(&self, policy: &Policy) -> Result<Plan, AppError> {
+        let notes = self.list()?.into_iter().map(|note| note.uuid).collect();
+        gc::plan(self.storage.as_ref(), &notes, policy, Utc::now())
+    }
+
+    /// Deletes the old versions, trashed notes, temporary files and caches
+    /// `policy` lets go of, see [`gc`], calling `removed` after each file.
+    /// Returns what it set out to delete.
+    ///
+    /// Holds the vault lock throughout, so nothing is trashed or restored
+    /// between finding what to delete and deleting it.
+    pub fn collect_garbage(
+        &self,
+        policy: &Policy,
+        removed: &mut dyn FnMut(&Removal),
+    ) -> Result<Plan, AppError> {
+        let _lock = self.lock()?;
+        let plan = self.gc_plan(policy)?;
+        gc::apply(self.storage.as_ref(), &plan, &mut |removal| {
+            if removal.reason == Reason::TrashExpired
+                && let Some((uuid, NoteFileKind::Content)) = removal
+                    .name
+                    .rsplit('/')
+                    .next()
+                    .and_then(file::parse_note_file_name)
+            {
+                self.record(Event::new(EventKind::Deleted, Some(uuid)));
+            }
+            removed(removal);
+        })?;
+        info!("Collected garbage: {} file(s)", plan.removals.len());
+        Ok(plan)
+    }
+
+    /// Figures about the whole vault now, counted without decrypting anything,
+    /// see [`vaultstats`].
+    pub fn statistics(&self) -> Result<VaultStats, AppError> {
+        vaultstats::collect(self.storage.as_ref(), &self.list()?, Utc::now())
+    }
+
+    /// Lists the previous versions of a note, newest first.
+    pub fn history(&self, uuid: &str) -> Result<Vec<NoteVersion>, AppError> {
+        let mut versions: Vec<_> = self
+            .storage
+            .list(&file::history_dir(uuid))?
+            .into_iter()
+            .filter_map(|entry| {
+                let (id, saved_at) = file::parse_version_file_name(&entry.name)?;
+                Some(NoteVersion {
+                    id: id.to_string(),
+                    saved_at,
+                    len: entry.len,
+                })
+            })
+            .collect();
+        versions.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(versions)
+    }
+
+    /// Decrypts a previous version of a note.
+    pub fn read_version(&self, uuid: &str, version_id: &str) -> Result<Vec<u8>, AppError> {
+        let name = file::version_file_name(uuid, version_id);
+        if !self.storage.exists(&name) {
+            return Err(AppError::NoteNotFound(format!(
+                "{uuid} version {version_id}"
+            )));
+        }
+        self.check_size(uuid, &name)?;
+        file::load_and_decrypt_note_content(self.storage.as_ref(), &name, &self.pin)
+    }
+
+    /// The stamp of the encrypted file of a note as it is now.
+    pub fn stamp(&self, uuid: &str) -> Result<FileStamp, AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        let entry = self.storage.entry(&encrypted_name)?;
+        Ok(FileStamp {
+            len: entry.len,
+            modified: entry.modified,
+        })
+    }
+
+    /// Sets when a note was created and last updated, e.g. to repair them.
+    ///
+    /// Fails with [`AppError::ReadOnlyNote`] if the note is marked read-only.
+    pub fn set_timestamps(
+        &self,
+        uuid: &str,
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    ) -> Result<NoteMetadata, AppError> {
+        if self.get(uuid)?.metadata.read_only {
+            return Err(AppError::ReadOnlyNote(uuid.to_string()));
+        }
+        self.metadata_mut(uuid, |metadata| {
+            metadata.created_at = created_at;
+            metadata.updated_at = updated_at;
+        })
+    }
+
+    /// When the encrypted file of a note was created and last modified
+    /// according to the filesystem, for metadata that has to be made up again.
+    /// Filesystems that don't keep a creation time give the modification time
+    /// for both, as does one that claims the file was created after it changed.
+    pub fn file_times(&self, uuid: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        let entry = self.storage.entry(&encrypted_name)?;
+        let updated_at = entry.modified.map_or_else(Utc::now, DateTime::from);
+        let created_at = entry
+            .created
+            .map_or(updated_at, |created| updated_at.min(created.into()));
+        Ok((created_at, updated_at))
+    }
+
+    /// The size of the encrypted file of a note, known without decrypting it.
+    pub fn stored_size(&self, uuid: &str) -> Result<u64, AppError> {
+        let (encrypted_name, _) = file::note_file_names(uuid);
+        Ok(self.storage.entry(&encrypted_name)?.len)
+    }
+
+    /// Refuses the file `name` of the note `uuid` if it's over `max_note_size`.
+    fn check_size(&self, uuid: &str, name: &str) -> Result<(), AppError> {
+        let size = self.storage.entry(name)?.len;
+        if size > self.max_note_size {
+            return Err(AppError::TooLarge {
+                uuid: uuid.to_string(),
+                size,
+                limit: self.max_note_size,
+                key: "max_note_size",
+            });
+        }
+        Ok(())
+    }
+
+    /// Makes a previous version the current content, keeping the replaced one as a
+    /// new version.
+    pub fn restore_version(&self, uuid: &str, version_id: &str) -> Result<NoteMetadata, AppError> {
+        let _lock = self.lock()?;
+        let content = Zeroizing::new(self.read_version(uuid, version_id)?);
+        if self.get(uuid)?.metadata.read_only {
+            return Err(AppError::ReadOnlyNote(uuid.to_string()));
+        }
+        let metadata = self.write_content(uuid, &content)?;
+        self.record(Event::new(EventKind::Restored, Some(uuid)).with_detail(version_id));
+        Ok(metadata)
+    }
+
+    /// Settles a conflict copy made while syncing, either replacing the note it
+    /// conflicts with (`keep`) or throwing it away. The copy is deleted either way.
+    pub fn resolve_conflict(&self, copy_uuid: &str, keep: bool) -> Result<(), AppError> {
+        let _lock = self.lock()?;
+        let copy = self.get(copy_uuid)?;
+        let original = copy
+            .metadata
+            .conflict_of
+            .ok_or_else(|| AppError::Config(format!("Note {copy_uuid} is not a conflict copy")))?;
+
+        let resolved = Event::new(EventKind::ConflictResolved, Some(copy_uuid))
+            .with_detail(if keep { "kept" } else { "discarded" });
+        if keep {
+            match self.get(&original) {
+                Ok(_) => {
+                    let content = Zeroizing::new(self.read(copy_uuid)?);
+                    self.write_content(&original, &content)?;
+                }
+                // The original is gone, so the copy simply becomes a regular note
+                Err(AppError::NoteNotFound(_)) => {
+                    self.update_metadata(copy_uuid, |metadata| {
+                        metadata.conflict_of = None;
+                        if let Some(title) = metadata
+                            .original_filename
+                            .strip_suffix(sync::CONFLICT_TITLE_SUFFIX)
+                        {
+                            metadata.original_filename = title.to_string();
+                        }
+                    })?;
+                    self.record(resolved);
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.remove(copy_uuid)?;
+        self.record(resolved);
+        Ok(())
+    }
+
+    /// Deletes the oldest versions of a note beyond the history limit.
+    fn prune_history(&self, uuid: &str) -> Result<(), AppError> {
+        for version in self.history(uuid)?.iter().skip(self.history_limit) {
+            self.storage
+                .delete(&file::version_file_name(uuid, &version.id))?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the content, the metadata and the history of a note.
+    pub fn delete(&self, uuid: &str) -> Result<(), AppError> {
+        self.remove(uuid)?;
+        self.record(Event::new(EventKind::Deleted, Some(uuid)));
+        Ok(())
+    }
+
+    /// Deletes a note without recording it in the audit log.
+    fn remove(&self, uuid: &str) -> Result<(), AppError> {
+        let tags = self.get(uuid).map(|note| note.metadata.tags);
+        file::delete_note_files(self.storage.as_ref(), uuid)?;
+        self.retag(&tags.unwrap_or_default(), &[]);
+        self.update_manifest([uuid]);
+        for version in self.history(uuid)? {
+            self.storage
+                .delete(&file::version_file_name(uuid, &version.id))?;
+        }
+        self.update_index(|index| {
+            index.titles.remove(uuid);
+        })
+    }
+
+    /// Moves the content and metadata of a note into the trash directory, where
+    /// it's no longer listed. Its history is kept.
+    ///
+    /// Fails with [`AppError::ReadOnlyNote`] if the note is marked read-only.
+    pub fn trash(&self, uuid: &str) -> Result<(), AppError> {
+        let note = self.get(uuid)?;
+        if note.metadata.read_only {
+            return Err(AppError::ReadOnlyNote(uuid.to_string()));
+        }
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        self.storage
+            .rename(&encrypted_name, &file::trash_file_name(&encrypted_name))?;
+        let trashed_metadata = file::trash_file_name(&metadata_name);
+        self.storage.rename(&metadata_name, &trashed_metadata)?;
+        // Only `gc` needs the date, which falls back to when the files were
+        // last changed, so the note is trashed even if this fails
+        let mut metadata = note.metadata.clone();
+        metadata.trashed_at = Some(Utc::now());
+        if let Err(e) = metadata.save(self.storage.as_ref(), &trashed_metadata) {
+            warn!("Could not record when {uuid} was trashed: {e}");
+        }
+        self.update_manifest([uuid]);
+        self.record(Event::new(EventKind::Trashed, Some(uuid)));
+        self.retag(&note.metadata.tags, &[]);
+        info!("Trashed note {uuid}");
+        self.update_index(|index| {
+            index.titles.remove(uuid);
+        })
+    }
+
+    /// Brings a note back from the trash, where [`Vault::trash`] put it.
+    pub fn untrash(&self, uuid: &str) -> Result<Note, AppError> {
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        let trashed_encrypted = file::trash_file_name(&encrypted_name);
+        if !self.storage.exists(&trashed_encrypted) {
+            return Err(AppError::NoteNotFound(uuid.to_string()));
+        }
+        let trashed_metadata = file::trash_file_name(&metadata_name);
+        let mut metadata = NoteMetadata::load(self.storage.as_ref(), &trashed_metadata)?;
+        if metadata.trashed_at.take().is_some() {
+            metadata.save(self.storage.as_ref(), &trashed_metadata)?;
+        }
+        self.storage.rename(&trashed_encrypted, &encrypted_name)?;
+        self.storage.rename(&trashed_metadata, &metadata_name)?;
+        let note = self.get(uuid)?;
+        self.update_manifest([uuid]);
+        self.record(Event::new(EventKind::Restored, Some(uuid)));
+        self.retag(&[], &note.metadata.tags);
+        info!("Restored note {uuid} from the trash");
+        self.update_index(|index| {
+            index
+                .titles
+                .insert(uuid.to_string(), note.metadata.original_filename.clone());
+        })?;
+        Ok(note)
+    }
+
+    /// Deletes a note from the trash for good, with its history.
+    pub fn delete_trashed(&self, uuid: &str) -> Result<(), AppError> {
+        let (encrypted_name, metadata_name) = file::note_file_names(uuid);
+        for name in [encrypted_name, metadata_name] {
+            let trashed = file::trash_file_name(&name);
+            if self.storage.exists(&trashed) {
+                self.storage.delete(&trashed)?;
+            }
+        }
+        for version in self.history(uuid)? {
+            self.storage
+                .delete(&file::version_file_name(uuid, &version.id))?;
+        }
+        self.record(Event::new(EventKind::Deleted, Some(uuid)));
+        Ok(())
+    }
+
+    fn manifest_cache(&self) -> MutexGuard<'_, Option<LoadedManifest>> {
+        // A panic while holding the lock can't leave the manifest half-updated
+        self.manifest
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Reads the integrity manifest, `None` if there's none yet, or says why it
+    /// can't be used. `known_key` is used if the manifest is still encrypted with
+    /// it, instead of deriving the key again.
+    fn load_manifest(&self, known_key: Option<&NoteKey>) -> Result<Option<LoadedManifest>, String> {
+        if !self.storage.exists(MANIFEST_FILE_NAME) {
+            return Ok(None);
+        }
+        let data = self
+            .storage
+            .read(MANIFEST_FILE_NAME)
+            .map_err(|e| format!("The integrity manifest can't be read: {e}"))?;
+        let encrypted = data
+            .strip_prefix(file::MAGIC_BYTES)
+            .ok_or("The integrity manifest isn't encrypted")?;
+        let key = match known_key {
+            Some(key) if key.matches(encrypted) => key.clone(),
+            _ => NoteKey::of(encrypted, &self.pin)
+                .map_err(|e| format!("The integrity manifest can't be decrypted: {e}"))?,
+        };
+        let content = key
+            .decrypt(encrypted)
+            .map_err(|e| format!("The integrity manifest can't be decrypted: {e}"))?;
+        let entries = serde_json::from_slice(&content)
+            .map_err(|e| format!("The integrity manifest can't be parsed: {e}"))?;
+        Ok(Some(LoadedManifest { entries, key }))
+    }
+
+    fn save_manifest(&self, manifest: &LoadedManifest) -> Result<(), AppError> {
+        let content = serde_json::to_vec(&manifest.entries).map_err(|e| {
+            AppError::Config(format!("Could not serialize the integrity manifest: {e}"))
+        })?;
+        let encrypted_content = manifest.key.encrypt(&content)?;
+        file::save_note_to_file(
+            self.storage.as_ref(),
+            &encrypted_content,
+            MANIFEST_FILE_NAME,
+        )
+    }
+
+    /// Starts an empty manifest, reusing `known_key` if there's one.
+    fn new_manifest(&self, known_key: Option<NoteKey>) -> Result<LoadedManifest, AppError> {
+        let key = match known_key {
+            Some(key) => key,
+            None => NoteKey::new(&self.pin)?,
+        };
+        Ok(LoadedManifest {
+            entries: Manifest::new(),
+            key,
+        })
+    }
+
+    /// Takes the manifest out of `cache`, loading it or starting it over if it
+    /// isn't there.
+    fn take_manifest(
+        &self,
+        cache: &mut Option<LoadedManifest>,
+    ) -> Result<LoadedManifest, AppError> {
+        match cache.take() {
+            Some(manifest) => Ok(manifest),
+            None => match self.load_manifest(None) {
+                Ok(Some(manifest)) => Ok(manifest),
+                Ok(None) => self.new_manifest(None),
+                Err(e) => {
+                    warn!("{e}, starting it over");
+                    self.new_manifest(None)
+                }
+            },
+        }
+    }
+
+    /// The key of the integrity manifest, which snippets are sealed with too.
+    fn manifest_key(&self) -> Result<NoteKey, AppError> {
+        let mut cache = self.manifest_cache();
+        let manifest = self.take_manifest(&mut cache)?;
+        let key = manifest.key.clone();
+        *cache = Some(manifest);
+        Ok(key)
+    }
+
+    /// Records the encrypted content of the notes `uuids`, as it is now, in the
+    /// integrity manifest, dropping the notes that are gone.
+    ///
+    /// The notes are already saved by then, so failing is only logged: the next
+    /// [`Vault::check_integrity`] accepts them as updated by another program.
+    fn update_manifest<'a>(&self, uuids: impl IntoIterator<Item = &'a str>) {
+        let mut cache = self.manifest_cache();
+        let mut manifest = match self.take_manifest(&mut cache) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!("Could not update the integrity manifest: {e}");
+                return;
+            }
+        };
+
+        for uuid in uuids {
+            let (encrypted_name, _) = file::note_file_names(uuid);
+            let current = self.storage.read(&encrypted_name).and_then(|data| {
+                let entry = self.storage.entry(&encrypted_name)?;
+                Ok(ManifestEntry::new(&data, &entry))
+            });
+            match current {
+                Ok(current) => manifest.entries.insert(uuid.to_string(), current),
+                Err(_) => manifest.entries.remove(uuid),
+            };
+        }
+
+        if let Err(e) = self.save_manifest(&manifest) {
+            warn!("Could not save the integrity manifest: {e}");
+        }
+        *cache = Some(manifest);
+    }
+
+    /// Compares the encrypted content of every note with the integrity manifest,
+    /// see [`manifest`]. Files replaced by another program, e.g. a sync, are
+    /// accepted, while files that changed but kept their modification time are
+    /// reported as likely corrupt.
+    ///
+    /// With `decrypt`, every note is also decrypted, on several threads, which
+    /// catches damage the manifest can't see, and clears a flagged note that
+    /// decrypts fine. A missing or unreadable manifest only sets
+    /// [`IntegrityReport::warning`] and is started over from the notes as they are.
+    pub fn check_integrity(&self, decrypt: bool) -> Result<IntegrityReport, AppError> {
+        let started = Instant::now();
+        let mut report = IntegrityReport::default();
+        let mut cache = self.manifest_cache();
+        let known_key = cache.take().map(|manifest| manifest.key);
+        // Read again, since other processes may have written notes meanwhile
+        let mut manifest = match self.load_manifest(known_key.as_ref()) {
+            Ok(Some(manifest)) => manifest,
+            Ok(None) => {
+                report.warning = Some("There's no integrity manifest yet".to_string());
+                self.new_manifest(known_key)?
+            }
+            Err(e) => {
+                report.warning = Some(e);
+                self.new_manifest(known_key)?
+            }
+        };
+        let saved = manifest.entries.clone();
+        let mut flagged = BTreeMap::new();
+        let mut present = BTreeSet::new();
+        for entry in self.storage.list("")? {
+            let Some((uuid, NoteFileKind::Content)) = file::parse_note_file_name(&entry.name)
+            else {
+                continue;
+            };
+            let uuid = uuid.to_string();
+            present.insert(uuid.clone());
+            let current = match self.storage.read(&entry.name) {
+                Ok(data) => ManifestEntry::new(&data, &entry),
+                Err(e) => {
+                    warn!("Note {uuid} can't be read: {e}");
+                    report.corrupt.push(uuid);
+                    continue;
+                }
+            };
+            match manifest::compare(manifest.entries.get(&uuid), &current) {
+                Change::Unchanged => {}
+                Change::Added => report.added.push(uuid.clone()),
+                Change::Updated => {
+                    info!("Note {uuid} was changed by another program");
+                    report.updated.push(uuid.clone());
+                }
+                Change::Corrupt => {
+                    warn!("Note {uuid} changed without its modification time changing");
+                    report.corrupt.push(uuid.clone());
+                    flagged.insert(uuid, current);
+                    continue;
+                }
+            }
+            manifest.entries.insert(uuid, current);
+        }
+        manifest.entries.retain(|uuid, _| {
+            let present = present.contains(uuid);
+            if !present {
+                report.removed.push(uuid.clone());
+            }
+            present
+        });
+        report.checked = present.len();
+
+        if decrypt {
+            let uuids: Vec<_> = present.into_iter().collect();
+            let results = parallel::map(&uuids, parallel::argon2_workers(), |uuid| {
+                let (encrypted_name, _) = file::note_file_names(uuid);
+                file::load_and_decrypt_note_content(
+                    self.storage.as_ref(),
+                    &encrypted_name,
+                    &self.pin,
+                )
+                .map(|content| drop(Zeroizing::new(content)))
+            });
+            for (uuid, result) in uuids.into_iter().zip(results) {
+                let was_flagged = report.corrupt.contains(&uuid);
+                report.corrupt.retain(|corrupt| *corrupt != uuid);
+                match result {
+                    Ok(()) => {
+                        if let Some(current) = flagged.remove(&uuid) {
+                            manifest.entries.insert(uuid, current);
+                        }
+                    }
+                    Err(e) => {
+                        if !was_flagged {
+                            warn!("Note {uuid} can't be decrypted: {e}");
+                        }
+                        report.undecryptable.push((uuid, e));
+                    }
+                }
+            }
+        }
+
+        if (manifest.entries != saved || report.warning.is_some())
+            && !self.read_only_mode
+            && let Err(e) = self.save_manifest(&manifest)
+        {
+            warn!("Could not save the integrity manifest: {e}");
+        }
+        *cache = Some(manifest);
+        debug!(
+            "Checked the integrity of {} notes in {:.2?}",
+            report.checked,
+            started.elapsed()
+        );
+        Ok(report)
+    }
+
+    /// Gives the note `uuid` the quick-access `slot`, from 1 to 9, taking it from
+    /// any other note that had it, or clears its slot with `None`. Returns the
+    /// notes changed, as they were before.
+    pub fn assign_slot(&self, uuid: &str, slot: Option<u8>) -> Result<Vec<Note>, AppError> {
+        if let Some(slot) = slot
+            && !(1..=9).contains(&slot)
+        {
+            return Err(AppError::Config(format!(
+                "Invalid slot {slot}, expected 1 to 9"
+            )));
+        }
+        let note = self.get(uuid)?;
+        let mut changed = Vec::new();
+        if slot.is_some() {
+            for other in self.list()? {
+                if other.uuid != uuid && other.metadata.slot == slot {
+                    self.metadata_mut(&other.uuid, |metadata| metadata.slot = None)?;
+                    changed.push(other);
+                }
+            }
+        }
+        self.metadata_mut(uuid, |metadata| metadata.slot = slot)?;
+        changed.push(note);
+        Ok(changed)
+    }
+
+    /// Pins the note `uuid` after the other pinned notes, or unpins it. Returns
+    /// the notes changed, as they were before.
+    pub fn set_pinned(&self, uuid: &str, pinned: bool) -> Result<Vec<Note>, AppError> {
+        let note = self.get(uuid)?;
+        if !pinned {
+            self.metadata_mut(uuid, |metadata| metadata.pin_order = None)?;
+            return Ok(vec![note]);
+        }
+        if note.metadata.pin_order.is_some() {
+            return Ok(Vec::new());
+        }
+        let mut order = self.pinned()?;
+        order.push(note);
+        self.write_pin_order(order)
+    }
+
+    /// Moves the pinned note `uuid` one place up or down among the pinned
+    /// notes. Returns the notes changed, as they were before, none if it's
+    /// already at that end or isn't pinned.
+    pub fn move_pinned(&self, uuid: &str, up: bool) -> Result<Vec<Note>, AppError> {
+        let mut order = self.pinned()?;
+        let Some(index) = order.iter().position(|note| note.uuid == uuid) else {
+            return Ok(Vec::new());
+        };
+        let other = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&other| other < order.len())
+        };
+        let Some(other) = other else {
+            return Ok(Vec::new());
+        };
+        order.swap(index, other);
+        self.write_pin_order(order)
+    }
+
+    /// The pinned notes, in order. Notes sharing a number are ordered by UUID,
+    /// until [`Vault::write_pin_order`] numbers them again.
+    fn pinned(&self) -> Result<Vec<Note>, AppError> {
+        let mut pinned: Vec<_> = self
+            .list()?
+            .into_iter()
+            .filter(|note| note.metadata.pin_order.is_some())
+            .collect();
+        pinned
+            .sort_by(|a, b| (a.metadata.pin_order, &a.uuid).cmp(&(b.metadata.pin_order, &b.uuid)));
+        Ok(pinned)
+    }
+
+    /// Numbers the pinned notes `order` from 1, closing gaps and collisions
+    /// left by unpinning or other machines. Only notes whose number changes are
+    /// written, and returned as they were before.
+    fn write_pin_order(&self, order: Vec<Note>) -> Result<Vec<Note>, AppError> {
+        let mut changed = Vec::new();
+        for (number, note) in (1..).zip(order) {
+            if note.metadata.pin_order != Some(number) {
+                self.metadata_mut(&note.uuid, |metadata| metadata.pin_order = Some(number))?;
+                changed.push(note);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Applies `update` to the metadata of a note and saves it.
+    pub fn metadata_mut(
+        &self,
+        uuid: &str,
+        update: impl FnOnce(&mut NoteMetadata),
+    ) -> Result<NoteMetadata, AppError> {
+        let mut renamed = false;
+        let metadata = self.update_metadata(uuid, |metadata| {
+            let title = metadata.original_filename.clone();
+            update(metadata);
+            renamed = metadata.original_filename != title;
+        })?;
+        let kind = if renamed {
+            EventKind::Renamed
+        } else {
+            EventKind::MetadataChanged
+        };
+        self.record(Event::new(kind, Some(uuid)));
+        Ok(metadata)
+    }
+
+    /// Like [`Vault::metadata_mut`], without recording it in the audit log.
+    fn update_metadata(
+        &self,
+        uuid: &str,
+        update: impl FnOnce(&mut NoteMetadata),
+    ) -> Result<NoteMetadata, AppError> {
+        let (_, metadata_name) = file::note_file_names(uuid);
+        if !self.storage.exists(&metadata_name) {
+            return Err(AppError::NoteNotFound(uuid.to_string()));
+        }
+        let mut metadata = NoteMetadata::load(self.storage.as_ref(), &metadata_name)?;
+        let old_tags = metadata.tags.clone();
+        update(&mut metadata);
+        self.limit(&mut metadata);
+        metadata.save(self.storage.as_ref(), &metadata_name)?;
+        self.retag(&old_tags, &metadata.tags);
+        // Filesystems with a coarse modification time could hide the change
+        self.metadata_cache().remove(&metadata_name);
+        self.update_index(|index| {
+            index
+                .titles
+                .insert(uuid.to_string(), metadata.original_filename.clone());
+        })?;
+        Ok(metadata)
+    }
+
+    /// Encrypts every plaintext file in the vault into a new note.
+    ///
+    /// Files that are already encrypted but not named `<uuid>.enc.txt` are renamed
+    /// to a new UUID and given metadata titled after their old name instead.
+    /// Metadata files, the index, the lock file, the vault marker, the hash cache
+    /// of [`dedupe`](crate::dedupe), the integrity manifest and the record of an
+    /// interrupted operation are left alone. Only the header of each
+    /// file is read to classify it; empty and unreadable files are skipped.
+    ///
+    /// Files matched by the ignore file are kept as plaintext, see
+    /// [`ignorefile`]; it's written with its default rules if it's missing.
+    ///
+    /// Progress is recorded as files go through, see
+    /// [`recovery`](crate::recovery), and a run that was interrupted has to be
+    /// resumed or rolled back before another starts.
+    pub fn encrypt_unencrypted_files(&self) -> Result<EncryptionSummary, AppError> {
+        self.encrypt_unencrypted_files_with_progress(&|_| {})
+    }
+
+    /// Like [`Vault::encrypt_unencrypted_files`], calling `progress` after each
+    /// file, from whichever thread encrypted it.
+    ///
+    /// Files are encrypted on several threads, see [`parallel`]. A file that fails
+    /// is reported in [`EncryptionSummary::failed`] without stopping the others.
+    pub fn encrypt_unencrypted_files_with_progress(
+        &self,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<EncryptionSummary, AppError> {
+        let _lock = self.lock()?;
+        self.encrypt_unencrypted_files_locked(progress)
+    }
+
+    /// Does the work of [`Vault::encrypt_unencrypted_files_with_progress`] for
+    /// a caller already holding the vault lock.
+    fn encrypt_unencrypted_files_locked(
+        &self,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<EncryptionSummary, AppError> {
+        if ignorefile::write_default(self.storage.as_ref())? {
+            info!("Wrote the default {}", ignorefile::IGNORE_FILE_NAME);
+        }
+        let scan = self.scan_unencrypted_files()?;
+        let mut summary = EncryptionSummary {
+            skipped: scan.skipped,
+            ignored: scan.ignored,
+            ..EncryptionSummary::default()
+        };
+        for (name, new_name) in scan.misnamed {
+            info!("Renaming encrypted file: {name} -> {new_name}");
+            self.storage.rename(&name, &new_name)?;
+            let uuid = new_name.split('.').next().unwrap_or_default();
+            match self.adopt_locked(uuid, name.clone()) {
+                Ok(_) => summary.renamed.push(new_name),
+                Err(e) => {
+                    // Not one of this vault's, so it goes back to how it was found
+                    error!("Failed to take in {name}: {e}");
+                    self.storage.rename(&new_name, &name)?;
+                    summary.failed.push((name, e.to_string()));
+                }
+            }
+        }
+        let unencrypted_files = scan.unencrypted;
+
+        if unencrypted_files.is_empty() {
+            info!("No unencrypted files found.");
+            return Ok(summary);
+        }
+
+        info!(
+            "Found {} unencrypted files. Encrypting...",
+            unencrypted_files.len()
+        );
+        let recorder = Recorder::start(
+            self.storage.as_ref(),
+            OperationKind::EncryptUnencrypted,
+            unencrypted_files.iter().map(|entry| entry.name.clone()),
+        )?;
+        let created = self.create_many_recorded(
+            &unencrypted_files,
+            |entry| &entry.name,
+            |entry| {
+                let content = self.storage.read(&entry.name)?;
+                // Keep the age of the original file instead of claiming it was just written
+                let updated_at = entry.modified.map_or_else(Utc::now, DateTime::from);
+                let created_at = entry
+                    .created
+                    .map_or(updated_at, |created| updated_at.min(created.into()));
+                let metadata = NoteMetadata::with_timestamps(&entry.name, created_at, updated_at);
+                Ok((metadata, content))
+            },
+            progress,
+            Some(&recorder),
+        )?;
+        for (entry, result) in unencrypted_files.into_iter().zip(created) {
+            let deleted = result.and_then(|note| {
+                if let Err(e) = self.storage.delete(&entry.name) {
+                    // The file is still there, so the note would be a second copy of it.
+                    // It's only removed once the record no longer points to it, or
+                    // resuming would delete the file as well.
+                    let removed = recorder
+                        .set(&entry.name, FileStatus::Pending)
+                        .and_then(|()| self.remove(&note.uuid));
+                    if let Err(e) = removed {
+                        warn!("Could not remove note {} again: {e}", note.uuid);
+                    }
+                    return Err(e);
+                }
+                // A record left at `Written` is resumed or rolled back the same way
+                if let Err(e) = recorder.set(&entry.name, FileStatus::Done { uuid: note.uuid }) {
+                    warn!("Could not record that {} is encrypted: {e}", entry.name);
+                }
+                Ok(())
+            });
+            match deleted {
+                Ok(()) => {
+                    info!("Encrypted {}", entry.name);
+                    summary.encrypted.push(entry.name);
+                }
+                Err(e) => {
+                    error!("Failed to encrypt {}: {e}", entry.name);
+                    summary.failed.push((entry.name, e.to_string()));
+                }
+            }
+        }
+        recorder.finish()?;
+        info!("Encryption complete.");
+
+        Ok(summary)
+    }
+
+    /// The rules of the ignore file of the notes directory, see [`ignorefile`].
+    pub fn ignore_rules(&self) -> Result<IgnoreRules, AppError> {
+        IgnoreRules::load(self.storage.as_ref())
+    }
+
+    /// Sorts the files at the root of the notes directory the way
+    /// [`Vault::encrypt_unencrypted_files`] would, reading only their header.
+    pub fn scan_unencrypted_files(&self) -> Result<EncryptionScan, AppError> {
+        info!("Scanning for unencrypted files...");
+        let rules = self.ignore_rules()?;
+        let mut scan = EncryptionScan::default();
+
+        for entry in self.storage.list("")? {
+            let kind = file::note_file_kind(&entry.name);
+            if kind == Some(NoteFileKind::Metadata) || file::is_own_file(&entry.name) {
+                continue;
+            }
+
+            if rules.is_ignored(Path::new(&entry.name), false) {
+                debug!(
+                    "Keeping {} as it is, the ignore file matches it",
+                    entry.name
+                );
+                scan.ignored.push(entry.name);
+                continue;
+            }
+            if entry.len == 0 {
+                warn!("Skipping empty file {}", entry.name);
+                scan.skipped
+                    .push((entry.name, "the file is empty".to_string()));
+                continue;
+            }
+            let encrypted = match file::has_magic_header(self.storage.as_ref(), &entry.name) {
+                Ok(encrypted) => encrypted,
+                Err(e) => {
+                    warn!("Skipping {}, it can't be read: {e}", entry.name);
+                    scan.skipped.push((entry.name, e.to_string()));
+                    continue;
+                }
+            };
+            if encrypted {
+                // It's an encrypted file
+                if file::parse_note_file_name(&entry.name).is_none() {
+                    // Not named after a UUID, so it gets one of its own
+                    let (new_name, _) = file::note_file_names(&file::generate_uuid());
+                    scan.misnamed.push((entry.name, new_name));
+                }
+            } else {
+                // It's truly unencrypted, so add to list
+                scan.unencrypted.push(entry);
+            }
+        }
+        scan.ignored.sort();
+        Ok(scan)
+    }
+
+    /// The bulk operation that was interrupted in this vault, if any, see
+    /// [`recovery`](crate::recovery).
+    pub fn interrupted_operation(&self) -> Result<Option<Operation>, AppError> {
+        Operation::load(self.storage.as_ref())
+    }
+
+    /// Finishes the interrupted operation. Notes it may have left half written
+    /// are deleted, and the files it hadn't got to yet are processed again.
+    ///
+    /// Returns what the operation did in all, including before it was
+    /// interrupted.
+    pub fn resume_operation(&self) -> Result<EncryptionSummary, AppError> {
+        let _lock = self.lock()?;
+        let Some(operation) = self.interrupted_operation()? else {
+            return Ok(EncryptionSummary::default());
+        };
+        let storage = self.storage.as_ref();
+        let recorder = Recorder::resume(storage, operation.clone());
+        let mut finished = Vec::new();
+        for record in &operation.files {
+            match &record.status {
+                FileStatus::Pending => {}
+                FileStatus::Writing { uuid } => {
+                    file::delete_note_files(storage, uuid)?;
+                    recorder.set(&record.name, FileStatus::Pending)?;
+                }
+                FileStatus::Written { uuid } => {
+                    if storage.exists(&record.name) {
+                        storage.delete(&record.name)?;
+                    }
+                    recorder.set(&record.name, FileStatus::Done { uuid: uuid.clone() })?;
+                    finished.push((uuid.clone(), record.name.clone()));
+                }
+                FileStatus::Done { uuid } => finished.push((uuid.clone(), record.name.clone())),
+            }
+        }
+        // The interruption may have come before the new notes were registered
+        self.update_manifest(finished.iter().map(|(uuid, _)| uuid.as_str()));
+        self.update_index(|index| index.titles.extend(finished.iter().cloned()))?;
+        recorder.finish()?;
+
+        let mut summary = self.encrypt_unencrypted_files_locked(&|_| {})?;
+        let mut encrypted: Vec<_> = finished.into_iter().map(|(_, name)| name).collect();
+        encrypted.append(&mut summary.encrypted);
+        summary.encrypted = encrypted;
+        Ok(summary)
+    }
+
+    /// Undoes the interrupted operation, putting back every file it had turned
+    /// into a note. Returns the names of the files put back.
+    pub fn roll_back_operation(&self) -> Result<Vec<String>, AppError> {
+        let _lock = self.lock()?;
+        let Some(operation) = self.interrupted_operation()? else {
+            return Ok(Vec::new());
+        };
+        let storage = self.storage.as_ref();
+        let recorder = Recorder::resume(storage, operation.clone());
+        let mut restored = Vec::new();
+        for record in &operation.files {
+            match &record.status {
+                FileStatus::Pending => continue,
+                FileStatus::Writing { uuid } => file::delete_note_files(storage, uuid)?,
+                FileStatus::Written { uuid } if storage.exists(&record.name) => {
+                    self.remove(uuid)?;
+                }
+                FileStatus::Written { uuid } | FileStatus::Done { uuid } => {
+                    let content = Zeroizing::new(self.read(uuid)?);
+                    storage.write(&record.name, &content)?;
+                    self.remove(uuid)?;
+                    restored.push(record.name.clone());
+                }
+            }
+            recorder.set(&record.name, FileStatus::Pending)?;
+        }
+        recorder.finish()?;
+        Ok(restored)
+    }
+
+    /// Encrypts every encrypted file of the vault again, with a fresh salt and
+    /// nonce, in case a key or nonce was used more than a cautious user likes.
+    /// The PIN stays the same.
+    ///
+    /// See [`Vault::rekey_with_progress`].
+    pub fn rekey(&self) -> Result<RekeySummary, AppError> {
+        self.rekey_with_progress(&|_| {})
+    }
+
+    /// Like [`Vault::rekey`], calling `progress` after each file, from whichever
+    /// thread encrypted it.
+    ///
+    /// Each file is replaced atomically, so an interrupted run leaves every file
+    /// either as it was or encrypted again, all readable with the PIN. Once all
+    /// are written, they're decrypted once more to check them. The vault lock is
+    /// held throughout, so no other change is overwritten with what was read
+    /// before it.
+    pub fn rekey_with_progress(
+        &self,
+        progress: &(dyn Fn(Progress) + Sync),
+    ) -> Result<RekeySummary, AppError> {
+        let _lock = self.lock()?;
+        let summary = self.reencrypt(progress)?;
+        self.record(Event::new(EventKind::Rekeyed, None));
+        Ok(summary)
+    }
+
+    /// Decrypts every encrypted file of the vault with its PIN and encrypts it
+    /// again with a fresh salt and nonce, on several threads, then checks that
+    /// every file written decrypts. The integrity manifest is started over with
+    /// a newly derived key.
+    fn reencrypt(&self, progress: &(dyn Fn(Progress) + Sync)) -> Result<RekeySummary, AppError> {
+        let started = Instant::now();
+        let names = self.encrypted_files()?;
+        let total = names.len();
+        let done = AtomicUsize::new(0);
+        let results = parallel::map(&names, parallel::argon2_workers(), |name| {
+            let result =
+                file::load_and_decrypt_note_content(self.storage.as_ref(), name, &self.pin)
+                    .and_then(|content| {
+                        let content = Zeroizing::new(content);
+                        let encrypted_content = note::encrypt_note_content(&content, &self.pin)?;
+                        file::save_note_to_file(self.storage.as_ref(), &encrypted_content, name)?;
+                        if self.paranoid_writes {
+                            self.verify_file(name, &self.pin, &content)?;
+                        }
+                        Ok(())
+                    });
+            progress(Progress {
+                done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                name,
+            });
+            result
+        });
+
+        let mut summary = RekeySummary::default();
+        let mut written = Vec::new();
+        for (name, result) in names.into_iter().zip(results) {
+            match result {
+                Ok(()) => written.push(name),
+                Err(e) => {
+                    error!("Failed to encrypt {name} again: {e}");
+                    summary.failed.push((name, e.to_string()));
+                }
+            }
+        }
+
+        // Every note file changed, so the manifest is rebuilt under the new key
+        let old_key = self.manifest_key().ok();
+        let key = NoteKey::new(&self.pin)?;
+        *self.manifest_cache() = Some(self.new_manifest(Some(key))?);
+        let uuids: Vec<_> = self
+            .storage
+            .list("")?
+            .into_iter()
+            .filter_map(|entry| match file::parse_note_file_name(&entry.name) {
+                Some((uuid, NoteFileKind::Content)) => Some(uuid.to_string()),
+                _ => None,
+            })
+            .collect();
+        self.update_manifest(uuids.iter().map(String::as_str));
+        self.reseal_snippets(old_key.as_ref());
+
+        let checked = parallel::map(&written, parallel::argon2_workers(), |name| {
+            file::load_and_decrypt_note_content(self.storage.as_ref(), name, &self.pin)
+                .map(|content| drop(Zeroizing::new(content)))
+        });
+        for (name, result) in written.into_iter().zip(checked) {
+            match result {
+                Ok(()) => summary.rekeyed.push(name),
+                Err(e) => {
+                    error!("{name} doesn't decrypt after being encrypted again: {e}");
+                    summary.unverified.push((name, e.to_string()));
+                }
+            }
+        }
+        info!(
+            "Encrypted {} file(s) again in {:.2?}",
+            summary.rekeyed.len(),
+            started.elapsed()
+        );
+        Ok(summary)
+    }
+
+    /// Seals the snippets of the notes again with the key of the manifest, once
+    /// it's replaced, dropping those `old_key` can't open.
+    fn reseal_snippets(&self, old_key: Option<&NoteKey>) {
+        let resealed = self.manifest_key().and_then(|key| {
+            for note in self.list()? {
+                let Some(sealed) = &note.metadata.snippet else {
+                    continue;
+                };
+                let snippet = old_key.and_then(|old_key| Snippet::open(sealed, old_key).ok());
+                let sealed = snippet.map(|snippet| snippet.seal(&key)).transpose()?;
+                self.update_metadata(&note.uuid, |metadata| metadata.snippet = sealed)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = resealed {
+            warn!("Could not seal the snippets again, they're taken anew as notes are opened: {e}");
+        }
+    }
+
+    /// Names of the files encrypted with the PIN: the content of notes, trashed
+    /// or not, their previous versions and the hash cache. The integrity
+    /// manifest is left out, since it's rebuilt rather than encrypted again.
+    fn encrypted_files(&self) -> Result<Vec<String>, AppError> {
+        let mut uuids = BTreeSet::new();
+        let mut names = Vec::new();
+        for entry in self.storage.list("")? {
+            if let Some((uuid, NoteFileKind::Content)) = file::parse_note_file_name(&entry.name) {
+                uuids.insert(uuid.to_string());
+                names.push(entry.name);
+            }
+        }
+        for entry in self.storage.list(file::TRASH_DIR)? {
+            if let Some((uuid, NoteFileKind::Content)) = file::parse_note_file_name(&entry.name) {
+                uuids.insert(uuid.to_string());
+                names.push(file::trash_file_name(&entry.name));
+            }
+        }
+        for uuid in &uuids {
+            for version in self.history(uuid)? {
+                names.push(file::version_file_name(uuid, &version.id));
+            }
+        }
+        if self.storage.exists(HASH_CACHE_FILE_NAME) {
+            names.push(HASH_CACHE_FILE_NAME.to_string());
+        }
+        Ok(names)
+    }
+}
+
+impl Drop for Vault {
+    /// Records the note count if notes were added or removed since the last
+    /// listing, e.g. by a subcommand that never lists them.
+    fn drop(&mut self) {
+        if !self.count_stale.load(Ordering::Relaxed) {
+            return;
+        }
+        match self.complete_notes() {
+            Ok(complete) => self.record_note_count(complete.len()),
+            Err(e) => warn!("Could not count the notes: {e}"),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! A plaintext description of the vault, readable without the PIN.
+//!
+//! [`VAULT_INFO_FILE_NAME`] sits at the root of the notes directory, so
+//! `profile list` and `config show` can tell vaults apart without unlocking
+//! them. It only holds what the user chose to write in the description, when
+//! the file was created, its version and how many notes the vault had when it
+//! last changed. Nothing is derived from the notes themselves.
+//!
+//! The note count is a cache: another program deleting notes leaves it stale
+//! until the [`Vault`](crate::vault::Vault) next lists the notes, which writes
+//! it again.
+
+use crate::error::AppError;
+use crate::storage::{FsStorage, Storage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the description file at the root of the vault.
+pub const VAULT_INFO_FILE_NAME: &str = "vault.toml";
+
+/// Version of the description file this build writes.
+pub const VAULT_INFO_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultInfo {
+    /// What the vault is for, set with `ryokan vault describe`.
+    pub description: String,
+    /// When the file was first written.
+    pub created: DateTime<Utc>,
+    pub format_version: u32,
+    /// How many notes the vault had when it last changed, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_count: Option<usize>,
+    /// Keys written by a newer version, preserved on save.
+    #[serde(flatten)]
+    pub unknown: toml::Table,
+}
+
+impl Default for VaultInfo {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            created: Utc::now(),
+            format_version: VAULT_INFO_VERSION,
+            note_count: None,
+            unknown: toml::Table::new(),
+        }
+    }
+}
+
+impl VaultInfo {
+    /// Reads the description file of `storage`, `None` if there's none yet.
+    pub fn load(storage: &dyn Storage) -> Result<Option<Self>, AppError> {
+        if !storage.exists(VAULT_INFO_FILE_NAME) {
+            return Ok(None);
+        }
+        let data = storage.read(VAULT_INFO_FILE_NAME)?;
+        let text = String::from_utf8_lossy(&data);
+        toml::from_str(&text)
+            .map(Some)
+            .map_err(AppError::TomlDeserialize)
+    }
+
+    /// Reads the description file of the notes directory `dir`, e.g. of a
+    /// profile that isn't open.
+    pub fn read(dir: &Path) -> Result<Option<Self>, AppError> {
+        Self::load(&FsStorage::new(dir))
+    }
+
+    /// Writes the description file of `storage`, with this build's version.
+    pub fn save(&mut self, storage: &dyn Storage) -> Result<(), AppError> {
+        self.format_version = self.format_version.max(VAULT_INFO_VERSION);
+        let text = toml::to_string(self).map_err(AppError::TomlSerialize)?;
+        storage.write(VAULT_INFO_FILE_NAME, text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod vaultinfo_test;
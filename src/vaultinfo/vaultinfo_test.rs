@@ -0,0 +1,29 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+
+#[test]
+fn test_vault_info_round_trips_and_keeps_unknown_keys() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    assert_eq!(VaultInfo::load(&storage)?, None);
+
+    storage.write(
+        VAULT_INFO_FILE_NAME,
+        b"description = \"Work\"\ncreated = \"2024-03-01T09:00:00Z\"\nformat_version = 1\nnote_count = 12\nsynced_from = \"laptop\"\n",
+    )?;
+    let mut info = VaultInfo::load(&storage)?.ok_or(AppError::Config("missing".into()))?;
+    assert_eq!(info.description, "Work");
+    assert_eq!(info.note_count, Some(12));
+
+    info.note_count = Some(13);
+    info.save(&storage)?;
+    let saved = String::from_utf8_lossy(&storage.read(VAULT_INFO_FILE_NAME)?).into_owned();
+    assert!(saved.contains("note_count = 13"), "{saved}");
+    assert!(saved.contains("synced_from = \"laptop\""), "{saved}");
+    assert!(
+        saved.contains("created = \"2024-03-01T09:00:00Z\""),
+        "{saved}"
+    );
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Figures about a whole vault: how many notes there are, the space they take,
+//! the tags used most and how often notes were saved lately, printed by
+//! `ryokan stats` and shown in the dashboard of the TUI.
+//!
+//! Everything comes from the metadata and from the names and sizes of files,
+//! so nothing is decrypted. Saves are counted from the last save of each note
+//! and the previous versions in the history, which are named after when they
+//! were saved; versions deleted beyond `history_limit` or by `ryokan gc` aren't
+//! counted anymore.
+
+use crate::error::AppError;
+use crate::file::{self, HISTORY_DIR, NoteFileKind, TRASH_DIR};
+use crate::storage::Storage;
+use crate::t;
+use crate::tags;
+use crate::vault::Note;
+use chrono::{DateTime, Utc};
+
+/// How many of the tags used most are listed.
+pub const TOP_TAGS: usize = 10;
+
+/// How many weeks saves are counted for.
+pub const WEEKS: usize = 12;
+
+/// Figures about one vault, see the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultStats {
+    pub notes: usize,
+    pub read_only: usize,
+    pub trashed: usize,
+    /// How many different tags the notes have.
+    pub tags: usize,
+    /// The [`TOP_TAGS`] tags most notes have, with how many, most used first.
+    pub top_tags: Vec<(String, usize)>,
+    /// Saves in each of the last [`WEEKS`] periods of seven days up to now,
+    /// oldest first.
+    pub saves_per_week: [u64; WEEKS],
+    /// Size of the files of the notes, in bytes.
+    pub notes_bytes: u64,
+    pub history_bytes: u64,
+    pub trash_bytes: u64,
+    /// Size of the other files at the root of the notes directory, such as
+    /// caches and the index.
+    pub other_bytes: u64,
+}
+
+impl VaultStats {
+    /// The size of the notes directory, in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.notes_bytes + self.history_bytes + self.trash_bytes + self.other_bytes
+    }
+
+    /// Each figure with its name, the tags and saves aside.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let bytes = |bytes: u64| t!("stats-bytes", bytes = bytes);
+        vec![
+            (t!("stats-notes"), self.notes.to_string()),
+            (t!("stats-read-only"), self.read_only.to_string()),
+            (t!("stats-trashed"), self.trashed.to_string()),
+            (t!("stats-tags"), self.tags.to_string()),
+            (t!("stats-size"), bytes(self.bytes())),
+            (t!("stats-size-notes"), bytes(self.notes_bytes)),
+            (t!("stats-size-history"), bytes(self.history_bytes)),
+            (t!("stats-size-trash"), bytes(self.trash_bytes)),
+            (t!("stats-size-other"), bytes(self.other_bytes)),
+        ]
+    }
+}
+
+/// Counts the figures of a vault whose files are in `storage` and whose notes
+/// are `notes`, at `now`.
+pub fn collect(
+    storage: &dyn Storage,
+    notes: &[Note],
+    now: DateTime<Utc>,
+) -> Result<VaultStats, AppError> {
+    let mut top_tags: Vec<_> = tags::count(notes).into_iter().collect();
+    let mut stats = VaultStats {
+        notes: notes.len(),
+        read_only: notes.iter().filter(|note| note.metadata.read_only).count(),
+        tags: top_tags.len(),
+        ..VaultStats::default()
+    };
+    // Ties go by name, so both frontends list the same tags
+    top_tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_tags.truncate(TOP_TAGS);
+    stats.top_tags = top_tags;
+
+    for entry in storage.list("")? {
+        if file::parse_note_file_name(&entry.name).is_some() {
+            stats.notes_bytes += entry.len;
+        } else {
+            stats.other_bytes += entry.len;
+        }
+    }
+    for entry in storage.list(TRASH_DIR)? {
+        stats.trash_bytes += entry.len;
+        if let Some((_, NoteFileKind::Content)) = file::parse_note_file_name(&entry.name) {
+            stats.trashed += 1;
+        }
+    }
+
+    let mut saves: Vec<_> = notes.iter().map(|note| note.metadata.updated_at).collect();
+    for uuid in storage.list_dirs(HISTORY_DIR)? {
+        for entry in storage.list(&file::history_dir(&uuid))? {
+            stats.history_bytes += entry.len;
+            if let Some((_, saved_at)) = file::parse_version_file_name(&entry.name) {
+                saves.push(saved_at);
+            }
+        }
+    }
+    for saved_at in saves {
+        // Saved after now by a clock a little ahead counts as this week
+        let weeks_ago = ((now - saved_at).num_days().max(0) / 7) as usize;
+        if weeks_ago < WEEKS {
+            stats.saves_per_week[WEEKS - 1 - weeks_ago] += 1;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod vaultstats_test;
@@ -0,0 +1,102 @@
+#![cfg(test)]
+
+use super::*;
+use crate::storage::MemoryStorage;
+use crate::vault::Vault;
+use chrono::Duration;
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// The total size of the files listed in `dir` whose name contains `part`.
+fn size(storage: &dyn Storage, dir: &str, part: &str) -> Result<u64, AppError> {
+    Ok(storage
+        .list(dir)?
+        .iter()
+        .filter(|entry| entry.name.contains(part))
+        .map(|entry| entry.len)
+        .sum())
+}
+
+#[test]
+fn test_figures_come_from_metadata_and_files() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("123456".to_string()));
+    let groceries = vault.create("Groceries", b"milk")?;
+    vault.write(&groceries.uuid, b"milk, eggs")?;
+    vault.metadata_mut(&groceries.uuid, |m| {
+        m.tags = vec!["food".to_string(), "home".to_string()];
+    })?;
+    let recipes = vault.create("Recipes", b"pancakes")?;
+    vault.metadata_mut(&recipes.uuid, |m| {
+        m.tags = vec!["food".to_string()];
+        m.read_only = true;
+    })?;
+    let old = vault.create("Old", b"gone")?;
+    vault.trash(&old.uuid)?;
+    let now = Utc::now() + Duration::hours(1);
+    for days_ago in [20, 700] {
+        let id = file::version_id(now - Duration::days(days_ago));
+        storage.write(
+            &file::version_file_name(&groceries.uuid, &id),
+            b"RYOKAN_ENCRYPTED old",
+        )?;
+    }
+    storage.write("stray.bak", b"12345")?;
+
+    let stats = collect(storage.as_ref(), &vault.list()?, now)?;
+
+    assert_eq!(stats.notes, 2);
+    assert_eq!(stats.read_only, 1);
+    assert_eq!(stats.trashed, 1);
+    assert_eq!(stats.tags, 2);
+    assert_eq!(
+        stats.top_tags,
+        [("food".to_string(), 2), ("home".to_string(), 1)]
+    );
+    // Both notes were saved this week, and the groceries once before that
+    let mut saves = [0; WEEKS];
+    saves[WEEKS - 1] = 3;
+    saves[WEEKS - 3] = 1;
+    assert_eq!(stats.saves_per_week, saves);
+    assert_eq!(
+        stats.notes_bytes,
+        size(storage.as_ref(), "", &groceries.uuid)? + size(storage.as_ref(), "", &recipes.uuid)?
+    );
+    assert_eq!(stats.trash_bytes, size(storage.as_ref(), TRASH_DIR, "")?);
+    assert_eq!(
+        stats.history_bytes,
+        size(storage.as_ref(), &file::history_dir(&groceries.uuid), "")?
+    );
+    assert!(stats.other_bytes >= 5);
+    assert_eq!(vault.statistics()?.fields(), stats.fields());
+    Ok(())
+}
+
+#[test]
+fn test_top_tags_are_the_most_used() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    let notes: Vec<_> = (0..TOP_TAGS + 2)
+        .map(|i| {
+            let mut metadata = crate::metadata::NoteMetadata::new(format!("Note {i}"));
+            // Tag `t0` on every note, `t1` on all but one and so on
+            metadata.tags = (0..TOP_TAGS + 2 - i).map(|j| format!("t{j:02}")).collect();
+            Note {
+                uuid: file::generate_uuid(),
+                metadata,
+            }
+        })
+        .collect();
+
+    let stats = collect(&storage, &notes, Utc::now())?;
+
+    assert_eq!(stats.tags, TOP_TAGS + 2);
+    let top: Vec<_> = stats.top_tags.iter().map(|(tag, _)| tag.as_str()).collect();
+    assert_eq!(
+        top,
+        [
+            "t00", "t01", "t02", "t03", "t04", "t05", "t06", "t07", "t08", "t09"
+        ]
+    );
+    assert_eq!(stats.top_tags[0].1, TOP_TAGS + 2);
+    Ok(())
+}
@@ -0,0 +1,502 @@
+//! First-run setup, shown when there's no configuration file yet: what Ryokan
+//! stores and where, then the notes directory, the PIN and notes to import.
+//!
+//! Every step can be skipped with Esc. A skipped step falls back to what
+//! happens without the wizard: the default notes directory, and the PIN asked
+//! for on the terminal.
+
+use crate::{
+    theme::{Theme, Tone},
+    tui::TerminalGuard,
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+};
+use ryokan::{
+    audit::{AuditLog, Event as AuditEvent, EventKind},
+    config::{self, Config},
+    error::AppError,
+    ignorefile::{DEFAULT_RULES, IgnoreRules},
+    import::{self, ImportFormat},
+    marker, pin,
+    settings::{Setting, Source},
+    t, text,
+};
+use std::{
+    fs,
+    path::{MAIN_SEPARATOR, PathBuf},
+    time::{Duration, Instant},
+};
+use zeroize::Zeroizing;
+
+/// What the wizard set up, for the rest of the startup.
+#[derive(Default)]
+pub struct WizardOutcome {
+    /// The PIN just set, so it isn't asked for again.
+    pub pin: Option<Zeroizing<String>>,
+    /// A directory of notes to import once the vault is open.
+    pub import: Option<(PathBuf, ImportFormat)>,
+    /// Set when the user quit with Ctrl-C.
+    pub quit: bool,
+    /// Time spent waiting for keys, left out of `--timing`.
+    pub waiting: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Welcome,
+    NotesDir,
+    Pin,
+    ConfirmPin,
+    Import,
+    Done,
+}
+
+impl Step {
+    const COUNT: usize = 5;
+
+    fn number(self) -> usize {
+        match self {
+            Step::Welcome => 1,
+            Step::NotesDir => 2,
+            Step::Pin | Step::ConfirmPin => 3,
+            Step::Import => 4,
+            Step::Done => 5,
+        }
+    }
+}
+
+struct Wizard<'a> {
+    config: &'a mut Config,
+    audit_log: Option<&'a AuditLog>,
+    notes_dir: Setting<PathBuf>,
+    step: Step,
+    input: Zeroizing<String>,
+    /// The first entry of the PIN, while it's confirmed.
+    new_pin: Zeroizing<String>,
+    /// A problem with the last entry, or the completions of a path.
+    feedback: Option<(String, Tone)>,
+    outcome: WizardOutcome,
+    /// How many notes the import will create.
+    import_count: usize,
+    theme: Theme,
+}
+
+/// Runs the wizard, saving the notes directory and the PIN to `config` as they
+/// are chosen. `notes_dir` is the effective notes directory, which can only be
+/// changed here if it isn't set by a flag or an environment variable.
+pub fn run(
+    config: &mut Config,
+    notes_dir: Setting<PathBuf>,
+    audit_log: Option<&AuditLog>,
+    theme: Theme,
+) -> Result<WizardOutcome, AppError> {
+    let mut guard = TerminalGuard::init()?;
+    let mut wizard = Wizard {
+        config,
+        audit_log,
+        notes_dir,
+        step: Step::Welcome,
+        input: Zeroizing::new(String::new()),
+        new_pin: Zeroizing::new(String::new()),
+        feedback: None,
+        outcome: WizardOutcome::default(),
+        import_count: 0,
+        theme,
+    };
+
+    while !wizard.outcome.quit {
+        guard
+            .terminal
+            .draw(|f| wizard.view(f))
+            .map_err(|e| AppError::Tui(e.to_string()))?;
+
+        let waiting_since = Instant::now();
+        let event = event::read()?;
+        wizard.outcome.waiting += waiting_since.elapsed();
+        let Event::Key(key) = event else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            wizard.outcome.quit = true;
+            break;
+        }
+        if wizard.step == Step::Done && matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
+            break;
+        }
+        if wizard.step == Step::ConfirmPin && key.code == KeyCode::Enter {
+            // Hashing takes a moment
+            wizard.feedback = Some((t!("wizard-saving-pin"), Tone::Normal));
+            guard
+                .terminal
+                .draw(|f| wizard.view(f))
+                .map_err(|e| AppError::Tui(e.to_string()))?;
+        }
+        wizard.handle_key(key.code)?;
+    }
+    Ok(wizard.outcome)
+}
+
+impl Wizard<'_> {
+    fn handle_key(&mut self, code: KeyCode) -> Result<(), AppError> {
+        match code {
+            KeyCode::Esc => self.next(),
+            KeyCode::Enter => self.submit()?,
+            KeyCode::Tab if self.takes_path() => {
+                let completion = complete_dir(&self.input);
+                *self.input = completion.text;
+                self.feedback = (!completion.candidates.is_empty())
+                    .then(|| (completion.candidates.join("  "), Tone::Normal));
+            }
+            KeyCode::Backspace => {
+                text::pop_grapheme(&mut self.input);
+                self.update_pin_feedback();
+            }
+            // Only digits, which are valid PINs on any keyboard
+            KeyCode::Char(c) if matches!(self.step, Step::Pin | Step::ConfirmPin) => {
+                if c.is_ascii_digit() && self.input.len() < pin::PIN_LENGTH {
+                    self.input.push(c);
+                }
+                self.update_pin_feedback();
+            }
+            KeyCode::Char(c) if self.takes_input() => {
+                self.input.push(c);
+                self.update_pin_feedback();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Whether the notes directory can be chosen here.
+    fn notes_dir_editable(&self) -> bool {
+        matches!(self.notes_dir.source, Source::ConfigFile | Source::Default)
+    }
+
+    fn takes_input(&self) -> bool {
+        match self.step {
+            Step::NotesDir => self.notes_dir_editable(),
+            Step::Pin | Step::ConfirmPin | Step::Import => true,
+            Step::Welcome | Step::Done => false,
+        }
+    }
+
+    fn takes_path(&self) -> bool {
+        self.takes_input() && matches!(self.step, Step::NotesDir | Step::Import)
+    }
+
+    /// Moves to the next step, skipping the rest of this one.
+    fn next(&mut self) {
+        self.step = match self.step {
+            Step::Welcome => Step::NotesDir,
+            Step::NotesDir => Step::Pin,
+            Step::Pin | Step::ConfirmPin => Step::Import,
+            Step::Import | Step::Done => Step::Done,
+        };
+        self.input.clear();
+        self.new_pin.clear();
+        self.feedback = None;
+        if self.step == Step::NotesDir && self.notes_dir_editable() {
+            *self.input = self.notes_dir.value.display().to_string();
+        }
+    }
+
+    fn submit(&mut self) -> Result<(), AppError> {
+        match self.step {
+            Step::Welcome | Step::Done => self.next(),
+            Step::NotesDir if !self.notes_dir_editable() => self.next(),
+            Step::NotesDir => {
+                let Some(dir) = expand_path(&self.input) else {
+                    return self.fail(t!("wizard-enter-directory"));
+                };
+                if dir.exists() && !dir.is_dir() {
+                    return self.fail(t!("wizard-not-a-directory", dir = dir.display()));
+                }
+                if dir != self.notes_dir.value {
+                    self.config.notes_dir = config::path_to_string(&dir)?;
+                    self.config.save()?;
+                    self.notes_dir.value = dir;
+                }
+                self.next();
+            }
+            Step::Pin if self.input.len() != pin::PIN_LENGTH => {
+                return self.fail(t!("wizard-pin-length", digits = pin::PIN_LENGTH));
+            }
+            Step::Pin => {
+                std::mem::swap(&mut self.new_pin, &mut self.input);
+                self.input.clear();
+                self.feedback = None;
+                self.step = Step::ConfirmPin;
+            }
+            Step::ConfirmPin => {
+                if *self.input != *self.new_pin {
+                    self.input.clear();
+                    self.new_pin.clear();
+                    self.step = Step::Pin;
+                    return self.fail(t!("wizard-pin-mismatch"));
+                }
+                // The PIN makes the vault set up, after which a missing
+                // directory is taken for an unmounted drive
+                if let Err(e) = marker::prepare(
+                    &self.notes_dir.value,
+                    self.config.notes_dir_must_exist,
+                    false,
+                ) {
+                    return self.fail(e.to_string());
+                }
+                pin::store_pin(self.config, &self.new_pin)?;
+                if let Some(audit_log) = self.audit_log {
+                    audit_log.record(
+                        &AuditEvent::new(EventKind::PinSet, None),
+                        Some(self.new_pin.as_str()),
+                    );
+                }
+                self.outcome.pin = Some(self.new_pin.clone());
+                self.next();
+            }
+            Step::Import if self.input.trim().is_empty() => self.next(),
+            Step::Import => {
+                let Some(dir) = expand_path(&self.input) else {
+                    return self.fail(t!("wizard-enter-directory"));
+                };
+                if !dir.is_dir() {
+                    return self.fail(t!("wizard-not-a-directory", dir = dir.display()));
+                }
+                let format = if dir.join(".obsidian").is_dir() {
+                    ImportFormat::Obsidian
+                } else {
+                    ImportFormat::Markdown
+                };
+                let plan = IgnoreRules::parse(DEFAULT_RULES)
+                    .and_then(|rules| import::plan(&dir, format, &rules));
+                match plan {
+                    Ok(plan) if plan.notes.is_empty() => {
+                        return self.fail(t!("wizard-no-markdown", dir = dir.display()));
+                    }
+                    Ok(plan) => {
+                        self.import_count = plan.notes.len();
+                        self.outcome.import = Some((dir, format));
+                        self.next();
+                    }
+                    Err(e) => return self.fail(e.to_string()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows what's wrong with the entry, staying on the step.
+    fn fail(&mut self, message: String) -> Result<(), AppError> {
+        self.feedback = Some((message, Tone::Error));
+        Ok(())
+    }
+
+    /// Tells how guessable the PIN being typed is.
+    fn update_pin_feedback(&mut self) {
+        if self.step != Step::Pin {
+            self.feedback = None;
+            return;
+        }
+        let length = self.input.chars().count();
+        self.feedback = Some(match pin::weakness(&self.input) {
+            _ if length < pin::PIN_LENGTH => (
+                t!("wizard-pin-typed", typed = length, digits = pin::PIN_LENGTH),
+                Tone::Normal,
+            ),
+            Some(weakness) => (t!("wizard-pin-weak", weakness = weakness), Tone::Warning),
+            None => (t!("wizard-pin-strong"), Tone::Good),
+        });
+    }
+
+    fn body(&self) -> Vec<Line<'_>> {
+        let config_file = self.config.config_path.display();
+        let notes_dir = self.notes_dir.value.display();
+        let text = match self.step {
+            Step::Welcome => t!("wizard-welcome", config_file = config_file),
+            Step::NotesDir if self.notes_dir_editable() => t!("wizard-notes-dir"),
+            Step::NotesDir => t!(
+                "wizard-notes-dir-set",
+                notes_dir = notes_dir,
+                source = self.notes_dir.source
+            ),
+            Step::Pin => t!("wizard-pin"),
+            Step::ConfirmPin => t!("wizard-confirm-pin"),
+            Step::Import => t!("wizard-import"),
+            Step::Done => {
+                let pin = if self.outcome.pin.is_some() {
+                    t!("wizard-done-pin-set")
+                } else {
+                    t!("wizard-done-pin-later")
+                };
+                let import = match &self.outcome.import {
+                    Some((dir, _)) => t!(
+                        "wizard-done-import",
+                        count = self.import_count,
+                        dir = dir.display()
+                    ),
+                    None => t!("wizard-done-no-import"),
+                };
+                t!(
+                    "wizard-done",
+                    notes_dir = notes_dir,
+                    pin = pin,
+                    import = import
+                )
+            }
+        };
+        text.lines()
+            .map(|line| Line::from(line.to_string()))
+            .collect()
+    }
+
+    fn view(&self, f: &mut ratatui::Frame) {
+        let area = centered(f.area());
+        let title = format!(
+            " {} ",
+            t!(
+                "wizard-title",
+                step = self.step.number(),
+                steps = Step::COUNT
+            )
+        );
+        let block = self.theme.block().title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(1),
+                Constraint::Length(2),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        f.render_widget(
+            Paragraph::new(self.body()).wrap(Wrap { trim: false }),
+            chunks[0],
+        );
+        if self.takes_input() {
+            let shown = match self.step {
+                Step::Pin | Step::ConfirmPin => "•".repeat(self.input.chars().count()),
+                _ => self.input.to_string(),
+            };
+            let line = Line::from(vec![
+                Span::styled("> ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(shown),
+                Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+            ]);
+            f.render_widget(Paragraph::new(line), chunks[1]);
+        }
+        if let Some((message, tone)) = &self.feedback {
+            let message = format!("{}{message}", self.theme.tone_prefix(*tone));
+            f.render_widget(
+                Paragraph::new(message)
+                    .style(self.theme.tone(*tone))
+                    .wrap(Wrap { trim: true }),
+                chunks[2],
+            );
+        }
+        let help = match self.step {
+            Step::Welcome => t!("wizard-help-welcome"),
+            Step::NotesDir | Step::Import if self.takes_path() => t!("wizard-help-path"),
+            Step::Done => t!("wizard-help-done"),
+            _ => t!("wizard-help"),
+        };
+        f.render_widget(
+            Paragraph::new(help).style(Style::default().add_modifier(Modifier::DIM)),
+            chunks[3],
+        );
+    }
+}
+
+/// A box of at most 80×20 in the middle of `area`.
+fn centered(area: Rect) -> Rect {
+    let width = area.width.min(80);
+    let height = area.height.min(20);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// `input` as an absolute path, with a leading `~` for the home directory.
+fn expand_path(input: &str) -> Option<PathBuf> {
+    let input = input.trim();
+    let path = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', MAIN_SEPARATOR]) => {
+            dirs::home_dir()?.join(rest.trim_start_matches(['/', MAIN_SEPARATOR]))
+        }
+        _ => PathBuf::from(input),
+    };
+    // Fails for an empty path
+    std::path::absolute(path).ok()
+}
+
+/// The text of a path being typed, after completing it.
+#[derive(Debug, PartialEq, Eq)]
+struct Completion {
+    text: String,
+    /// The directories it could go on with, when there's more than one.
+    candidates: Vec<String>,
+}
+
+/// Completes the last part of `input` with the names of the directories it
+/// starts, as far as they agree. A single match gets a trailing separator.
+fn complete_dir(input: &str) -> Completion {
+    let unchanged = || Completion {
+        text: input.to_string(),
+        candidates: Vec::new(),
+    };
+    let split = input.rfind(['/', MAIN_SEPARATOR]).map_or(0, |i| i + 1);
+    let (parent, prefix) = input.split_at(split);
+    let Some(dir) = expand_path(if parent.is_empty() { "." } else { parent }) else {
+        return unchanged();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return unchanged();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix))
+        .filter(|name| prefix.starts_with('.') || !name.starts_with('.'))
+        .collect();
+    names.sort();
+
+    let [first, rest @ ..] = names.as_slice() else {
+        return unchanged();
+    };
+    if rest.is_empty() {
+        return Completion {
+            text: format!("{parent}{first}{MAIN_SEPARATOR}"),
+            candidates: Vec::new(),
+        };
+    }
+    let common = rest.iter().fold(first.as_str(), |common, name| {
+        let len = common
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(common.len().min(name.len()), |((i, _), _)| i);
+        &common[..len]
+    });
+    Completion {
+        text: format!("{parent}{common}"),
+        candidates: names.clone(),
+    }
+}
+
+#[cfg(test)]
+mod wizard_test;
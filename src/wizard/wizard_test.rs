@@ -0,0 +1,43 @@
+#![cfg(test)]
+
+use super::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_directories_are_completed_as_far_as_they_agree() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    for name in ["notes", "novels", "archive", ".hidden"] {
+        fs::create_dir(dir.path().join(name))?;
+    }
+    fs::write(dir.path().join("archive.md"), "not a directory")?;
+    let base = format!("{}{MAIN_SEPARATOR}", dir.path().display());
+
+    let completion = complete_dir(&format!("{base}n"));
+    assert_eq!(completion.text, format!("{base}no"));
+    assert_eq!(completion.candidates, ["notes", "novels"]);
+
+    let completion = complete_dir(&format!("{base}ar"));
+    assert_eq!(completion.text, format!("{base}archive{MAIN_SEPARATOR}"));
+    assert!(completion.candidates.is_empty());
+
+    assert_eq!(
+        complete_dir(&format!("{base}.h")).text,
+        format!("{base}.hidden{MAIN_SEPARATOR}")
+    );
+    assert_eq!(complete_dir(&format!("{base}x")).text, format!("{base}x"));
+    assert_eq!(
+        complete_dir(&base).candidates,
+        ["archive", "notes", "novels"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_paths_are_made_absolute() {
+    let home = dirs::home_dir().unwrap_or_default();
+
+    assert_eq!(expand_path("~"), Some(home.clone()));
+    assert_eq!(expand_path("~/notes"), Some(home.join("notes")));
+    assert_eq!(expand_path("  "), None);
+    assert!(expand_path("notes").is_some_and(|path| path.is_absolute()));
+}
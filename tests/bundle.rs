@@ -0,0 +1,176 @@
+use ryokan::{
+    AppError, bundle, file,
+    note::NoteKey,
+    storage::{MemoryStorage, Storage},
+    vault::Vault,
+};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+fn vault(pin: &str) -> Vault {
+    Vault::with_storage(MemoryStorage::new(), Zeroizing::new(pin.to_string()))
+}
+
+#[test]
+fn test_bundle_round_trip_between_pins() -> Result<(), AppError> {
+    let source = vault("111111");
+    let fresh = source.create("Only in the bundle", b"hello")?;
+    let shared = source.create("Shared", b"from the old laptop")?;
+
+    let target = vault("222222");
+    target.import_note(&shared.uuid, &shared.metadata, b"stale")?;
+    // Make the bundled copy newer than the local one
+    source.write(&shared.uuid, b"from the old laptop, edited")?;
+
+    let data = bundle::export(&source, "correct horse")?;
+    let notes = bundle::open(&data, "correct horse")?;
+    let report = bundle::import(&target, &notes)?;
+
+    assert_eq!(report.added, [fresh.uuid.as_str()]);
+    assert_eq!(report.replaced, [shared.uuid.as_str()]);
+    assert_eq!(target.read(&fresh.uuid)?, b"hello");
+    assert_eq!(target.read(&shared.uuid)?, b"from the old laptop, edited");
+
+    // Importing again changes nothing
+    let report = bundle::import(&target, &notes)?;
+    assert_eq!(report.unchanged.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_newer_local_notes_are_kept() -> Result<(), AppError> {
+    let source = vault("111111");
+    let note = source.create("Shared", b"old")?;
+    let data = bundle::export(&source, "pass")?;
+
+    let target = vault("222222");
+    let mut newer = note.metadata.clone();
+    newer.updated_at += chrono::Duration::hours(1);
+    target.import_note(&note.uuid, &newer, b"new")?;
+
+    let report = bundle::import(&target, &bundle::open(&data, "pass")?)?;
+    assert_eq!(report.kept_local, [note.uuid.as_str()]);
+    assert_eq!(target.read(&note.uuid)?, b"new");
+
+    Ok(())
+}
+
+#[test]
+fn test_a_failing_note_does_not_stop_the_import() -> Result<(), AppError> {
+    let source = vault("111111");
+    let first = source.create("First", b"one")?;
+    let broken = source.create("Broken", b"two")?;
+    let last = source.create("Last", b"three")?;
+    let data = bundle::export(&source, "pass")?;
+    let mut notes = bundle::open(&data, "pass")?;
+    let order = [&first.uuid, &broken.uuid, &last.uuid];
+    notes.sort_by_key(|note| order.iter().position(|uuid| **uuid == note.uuid));
+
+    // The local copy of the second note can't be decrypted to compare it
+    let storage = Arc::new(MemoryStorage::new());
+    let target = Vault::with_storage(Arc::clone(&storage), Zeroizing::new("222222".to_string()));
+    target.import_note(&broken.uuid, &broken.metadata, b"local")?;
+    let (encrypted_name, _) = file::note_file_names(&broken.uuid);
+    storage.write(&encrypted_name, b"RYOKAN_ENCRYPTED garbage")?;
+
+    let report = bundle::import(&target, &notes)?;
+
+    assert_eq!(report.added, [first.uuid.as_str(), last.uuid.as_str()]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, broken.uuid);
+    assert_eq!(target.read(&last.uuid)?, b"three");
+    assert_eq!(storage.read(&encrypted_name)?, b"RYOKAN_ENCRYPTED garbage");
+
+    Ok(())
+}
+
+#[test]
+fn test_read_only_local_notes_are_not_replaced() -> Result<(), AppError> {
+    let source = vault("111111");
+    let note = source.create("Shared", b"old")?;
+    let target = vault("222222");
+    target.import_note(&note.uuid, &note.metadata, b"old")?;
+    target.metadata_mut(&note.uuid, |metadata| metadata.read_only = true)?;
+    let before = target.get(&note.uuid)?.metadata;
+    source.write(&note.uuid, b"newer")?;
+
+    let data = bundle::export(&source, "pass")?;
+    let report = bundle::import(&target, &bundle::open(&data, "pass")?)?;
+
+    assert_eq!(report.skipped_read_only, [note.uuid.as_str()]);
+    assert!(report.replaced.is_empty());
+    assert_eq!(target.read(&note.uuid)?, b"old");
+    assert_eq!(target.get(&note.uuid)?.metadata, before);
+    assert!(matches!(
+        target.import_note(&note.uuid, &note.metadata, b"forced"),
+        Err(AppError::ReadOnlyNote(_))
+    ));
+
+    Ok(())
+}
+
+/// A bundle of one note whose key is derived with the Argon2 parameters
+/// `params`, as another build could make it.
+fn bundle_with_params(params: [u32; 3], uuid: &str) -> Result<Vec<u8>, AppError> {
+    let metadata = toml::to_string(&ryokan::metadata::NoteMetadata::new("Elsewhere"))
+        .map_err(AppError::TomlSerialize)?;
+    let mut payload = Vec::new();
+    for field in [uuid.as_bytes(), metadata.as_bytes(), b"hello"] {
+        payload.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        payload.extend_from_slice(field);
+    }
+    let mut data = [b"RYOKAN_BUNDLE".as_slice(), &[bundle::BUNDLE_VERSION]].concat();
+    for param in params {
+        data.extend_from_slice(&param.to_be_bytes());
+    }
+    data.extend(NoteKey::with_params("pass", params)?.encrypt(&payload)?);
+    Ok(data)
+}
+
+#[test]
+fn test_bundles_open_with_the_parameters_they_were_made_with() -> Result<(), AppError> {
+    let uuid = file::generate_uuid();
+
+    let data = bundle_with_params([1024, 2, 1], &uuid)?;
+    let notes = bundle::open(&data, "pass")?;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].uuid, uuid);
+    assert_eq!(*notes[0].content, b"hello");
+
+    // A header asking for more memory than allowed is refused before deriving
+    let mut greedy = data;
+    let memory = b"RYOKAN_BUNDLE".len() + 1;
+    greedy[memory..memory + 4].copy_from_slice(&(8 * 1024 * 1024u32).to_be_bytes());
+    assert!(matches!(
+        bundle::open(&greedy, "pass"),
+        Err(AppError::Bundle(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_corrupted_bundles_are_rejected() -> Result<(), AppError> {
+    let source = vault("111111");
+    source.create("Note", b"content")?;
+    let data = bundle::export(&source, "pass")?;
+
+    assert!(matches!(
+        bundle::open(&data, "wrong"),
+        Err(AppError::Bundle(_))
+    ));
+
+    let mut corrupted = data.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 1;
+    assert!(matches!(
+        bundle::open(&corrupted, "pass"),
+        Err(AppError::Bundle(_))
+    ));
+
+    assert!(bundle::open(&data[..20], "pass").is_err());
+    assert!(bundle::open(b"not a bundle at all", "pass").is_err());
+
+    Ok(())
+}
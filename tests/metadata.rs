@@ -0,0 +1,126 @@
+use ryokan::{
+    AppError,
+    metadata::{NoteMetadata, NoteType, SCHEMA_VERSION},
+    storage::{FsStorage, MemoryStorage, Storage},
+};
+use tempfile::tempdir;
+
+const NAME: &str = "note.meta.toml";
+
+#[test]
+fn test_unversioned_metadata_is_upgraded() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    storage.write(
+        NAME,
+        br#"
+original_filename = "Old"
+created_at = "2020-01-01T00:00:00Z"
+updated_at = "2020-01-02T00:00:00Z"
+"#,
+    )?;
+
+    let metadata = NoteMetadata::load(&storage, NAME)?;
+    assert_eq!(metadata.schema_version, SCHEMA_VERSION);
+    assert!(metadata.tags.is_empty());
+
+    // Loading alone doesn't rewrite the file
+    let saved = String::from_utf8_lossy(&storage.read(NAME)?).into_owned();
+    assert!(!saved.contains("schema_version"));
+
+    metadata.save(&storage, NAME)?;
+    let saved = String::from_utf8_lossy(&storage.read(NAME)?).into_owned();
+    assert!(saved.contains(&format!("schema_version = {SCHEMA_VERSION}")));
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_fields_are_kept() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    storage.write(
+        NAME,
+        br#"
+schema_version = 99
+original_filename = "From the future"
+created_at = "2020-01-01T00:00:00Z"
+updated_at = "2020-01-02T00:00:00Z"
+tags = []
+color = "teal"
+priority = "high"
+"#,
+    )?;
+
+    let mut metadata = NoteMetadata::load(&storage, NAME)?;
+    assert_eq!(metadata.schema_version, 99);
+    metadata.tags.push("kept".to_string());
+    metadata.save(&storage, NAME)?;
+
+    let reloaded = NoteMetadata::load(&storage, NAME)?;
+    assert_eq!(reloaded.schema_version, 99);
+    assert_eq!(reloaded.tags, ["kept"]);
+    assert_eq!(
+        reloaded.unknown.get("priority").and_then(|v| v.as_str()),
+        Some("high")
+    );
+    // A color label this build doesn't know isn't dropped either
+    assert_eq!(reloaded.color.as_deref(), Some("teal"));
+
+    Ok(())
+}
+
+#[test]
+fn test_note_types_default_to_plain_and_unknown_ones_are_kept() -> Result<(), AppError> {
+    let storage = MemoryStorage::new();
+    storage.write(
+        NAME,
+        br#"
+original_filename = "Old"
+created_at = "2020-01-01T00:00:00Z"
+updated_at = "2020-01-02T00:00:00Z"
+"#,
+    )?;
+    let mut metadata = NoteMetadata::load(&storage, NAME)?;
+    assert_eq!(metadata.note_type, NoteType::Plain);
+
+    metadata.note_type = NoteType::Markdown;
+    metadata.save(&storage, NAME)?;
+    assert_eq!(
+        NoteMetadata::load(&storage, NAME)?.note_type,
+        NoteType::Markdown
+    );
+
+    storage.write(
+        NAME,
+        br#"
+original_filename = "From the future"
+created_at = "2020-01-01T00:00:00Z"
+updated_at = "2020-01-02T00:00:00Z"
+note_type = "kanban"
+"#,
+    )?;
+    let metadata = NoteMetadata::load(&storage, NAME)?;
+    assert_eq!(metadata.note_type.name(), "other");
+    metadata.save(&storage, NAME)?;
+    let saved = String::from_utf8_lossy(&storage.read(NAME)?).into_owned();
+    assert!(saved.contains("note_type = \"kanban\""), "{saved}");
+
+    Ok(())
+}
+
+#[test]
+fn test_errors_name_the_file() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let storage = FsStorage::new(dir.path());
+    storage.write(NAME, b"original_filename = ")?;
+
+    let Err(e) = NoteMetadata::load(&storage, NAME) else {
+        panic!("invalid metadata loaded");
+    };
+    assert!(matches!(e, AppError::Metadata { .. }));
+    assert!(
+        e.to_string()
+            .contains(&dir.path().join(NAME).display().to_string())
+    );
+
+    Ok(())
+}
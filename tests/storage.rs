@@ -0,0 +1,63 @@
+use ryokan::{
+    AppError,
+    storage::{FsStorage, MemoryStorage, Storage},
+};
+use tempfile::tempdir;
+
+/// Runs the same sequence of operations against a backend.
+fn exercise(storage: &dyn Storage) -> Result<(), AppError> {
+    assert!(storage.list("")?.is_empty());
+    assert!(storage.list("history")?.is_empty());
+    assert!(!storage.exists("a.txt"));
+    assert!(storage.read("a.txt").is_err());
+
+    storage.write("a.txt", b"first")?;
+    storage.write("a.txt", b"second")?;
+    storage.write("history/a.txt", b"old")?;
+    assert_eq!(storage.read("a.txt")?, b"second");
+    assert_eq!(storage.read_prefix("a.txt", 3)?, b"sec");
+    assert_eq!(storage.read_prefix("a.txt", 100)?, b"second");
+    assert!(storage.read_prefix("missing.txt", 3).is_err());
+
+    let root = storage.list("")?;
+    assert_eq!(root.len(), 1);
+    assert_eq!(root[0].name, "a.txt");
+    assert_eq!(root[0].len, 6);
+    assert_eq!(storage.list("history")?[0].name, "a.txt");
+
+    storage.rename("a.txt", "b.txt")?;
+    assert!(!storage.exists("a.txt"));
+    assert_eq!(storage.read("b.txt")?, b"second");
+
+    storage.delete("b.txt")?;
+    assert!(!storage.exists("b.txt"));
+    assert!(storage.delete("b.txt").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_memory_storage() -> Result<(), AppError> {
+    exercise(&MemoryStorage::new())
+}
+
+#[test]
+fn test_fs_storage() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    exercise(&FsStorage::new(dir.path()))
+}
+
+#[test]
+fn test_fs_storage_stays_below_its_root() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    std::fs::write(dir.path().join("outside.txt"), b"not mine")?;
+    let storage = FsStorage::new(dir.path().join("notes"));
+
+    assert!(storage.read("../outside.txt").is_err());
+    assert!(!storage.exists("../outside.txt"));
+    assert!(storage.write("history/../../outside.txt", b"mine").is_err());
+    assert!(storage.delete("../outside.txt").is_err());
+    assert_eq!(std::fs::read(dir.path().join("outside.txt"))?, b"not mine");
+
+    Ok(())
+}
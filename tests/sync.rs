@@ -0,0 +1,73 @@
+use ryokan::{AppError, sync::GitSync, vault::Vault};
+use std::{path::Path, process::Command};
+use tempfile::tempdir;
+use zeroize::Zeroizing;
+
+fn git(dir: &Path, args: &[&str]) -> Result<(), AppError> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(AppError::Sync(format!("git {args:?} failed")));
+    }
+    Ok(())
+}
+
+/// Clones `remote` into `dir` with an identity to commit with.
+fn clone(remote: &Path, dir: &Path) -> Result<(), AppError> {
+    let remote = remote.to_string_lossy();
+    let dir_str = dir.to_string_lossy();
+    git(Path::new("."), &["clone", "-q", &remote, &dir_str])?;
+    git(dir, &["config", "user.name", "Test"])?;
+    git(dir, &["config", "user.email", "test@example.com"])?;
+    git(dir, &["config", "commit.gpgsign", "false"])
+}
+
+fn vault(dir: &Path) -> Vault {
+    Vault::open(dir, Zeroizing::new("123456".to_string()))
+}
+
+#[test]
+fn test_sync_keeps_both_sides_of_a_conflict() -> Result<(), AppError> {
+    if Command::new("git").arg("--version").output().is_err() {
+        return Ok(());
+    }
+
+    let root = tempdir()?;
+    let remote = root.path().join("remote.git");
+    let (a, b) = (root.path().join("a"), root.path().join("b"));
+    git(
+        root.path(),
+        &["init", "-q", "--bare", &remote.to_string_lossy()],
+    )?;
+    clone(&remote, &a)?;
+
+    let note = vault(&a).create("Shared", b"original")?;
+    let report = GitSync::new(&a).sync()?;
+    assert_eq!(report.committed_notes, 1);
+    // The first push has no upstream to compare against yet
+    git(&a, &["push", "-q", "-u", "origin", "HEAD"])?;
+
+    clone(&remote, &b)?;
+    assert_eq!(vault(&b).read(&note.uuid)?, b"original");
+
+    vault(&a).write(&note.uuid, b"edited on a")?;
+    GitSync::new(&a).sync()?;
+    vault(&b).write(&note.uuid, b"edited on b")?;
+    let report = GitSync::new(&b).sync()?;
+    assert!(report.pushed);
+    assert_eq!(report.conflicts.len(), 1);
+
+    let b_vault = vault(&b);
+    assert_eq!(b_vault.read(&note.uuid)?, b"edited on a");
+    let copy = b_vault.get(&report.conflicts[0])?;
+    assert_eq!(
+        copy.metadata.conflict_of.as_deref(),
+        Some(note.uuid.as_str())
+    );
+    assert_eq!(b_vault.read(&copy.uuid)?, b"edited on b");
+
+    b_vault.resolve_conflict(&copy.uuid, true)?;
+    assert_eq!(b_vault.read(&note.uuid)?, b"edited on b");
+    assert!(b_vault.get(&copy.uuid).is_err());
+
+    Ok(())
+}
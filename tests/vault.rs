@@ -0,0 +1,1775 @@
+use chrono::{DateTime, Local, TimeZone, Utc};
+use ryokan::{
+    AppError,
+    audit::{AUDIT_FILE_NAME, AuditLog, EventKind},
+    dedupe, file,
+    ignorefile::{DEFAULT_RULES, IGNORE_FILE_NAME},
+    index::{INDEX_FILE_NAME, NoteIndex},
+    manifest::MANIFEST_FILE_NAME,
+    metadata::{NoteMetadata, NoteType},
+    recovery::{FileStatus, OperationKind, RECOVERY_FILE_NAME, Recorder},
+    storage::{Entry, MemoryStorage, Storage},
+    template::{DEFAULT_CAPTURE_HEADER, Template},
+    vault::{self, Orphan, SortMode, Vault},
+    vaultinfo::{VAULT_INFO_FILE_NAME, VaultInfo},
+};
+use std::{
+    fs::File,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+use tempfile::tempdir;
+use zeroize::Zeroizing;
+
+fn pin(pin: &str) -> Zeroizing<String> {
+    Zeroizing::new(pin.to_string())
+}
+
+#[test]
+fn test_create_edit_list_delete() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+
+    let note = vault.create("Groceries", b"milk")?;
+
+    let notes = vault.list()?;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].uuid, note.uuid);
+    assert_eq!(notes[0].metadata.original_filename, "Groceries");
+    assert_eq!(vault.read(&note.uuid)?, b"milk");
+
+    let metadata = vault.write(&note.uuid, b"milk\neggs")?;
+    assert!(metadata.updated_at >= note.metadata.updated_at);
+    assert_eq!(vault.read(&note.uuid)?, b"milk\neggs");
+
+    let wrong_pin = Vault::with_storage(storage, pin("000000"));
+    assert!(wrong_pin.read(&note.uuid).is_err());
+
+    let metadata = vault.metadata_mut(&note.uuid, |m| m.tags.push("food".to_string()))?;
+    assert_eq!(vault.get(&note.uuid)?.metadata, metadata);
+
+    vault.delete(&note.uuid)?;
+    assert!(vault.list()?.is_empty());
+    assert!(matches!(
+        vault.read(&note.uuid),
+        Err(AppError::NoteNotFound(_))
+    ));
+
+    Ok(())
+}
+
+/// Counts how many metadata files are read, i.e. parsed.
+#[derive(Default)]
+struct CountingStorage {
+    inner: MemoryStorage,
+    metadata_reads: AtomicUsize,
+}
+
+impl CountingStorage {
+    fn take_metadata_reads(&self) -> usize {
+        self.metadata_reads.swap(0, Ordering::SeqCst)
+    }
+}
+
+impl Storage for CountingStorage {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        self.inner.list(dir)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        self.inner.list_dirs(dir)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        if name.ends_with(".meta.toml") {
+            self.metadata_reads.fetch_add(1, Ordering::SeqCst);
+        }
+        self.inner.read(name)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        self.inner.write(name, data)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        self.inner.delete(name)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.inner.rename(from, to)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.inner.exists(name)
+    }
+}
+
+#[test]
+fn test_list_only_parses_changed_metadata() -> Result<(), AppError> {
+    let storage = Arc::new(CountingStorage::default());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    // Written directly, since encrypting 5,000 notes would take a while
+    let mut uuids = Vec::new();
+    for i in 0..5000 {
+        let uuid = file::generate_uuid();
+        let (content_name, metadata_name) = file::note_file_names(&uuid);
+        storage.write(&content_name, b"")?;
+        NoteMetadata::new(format!("Note {i}")).save(storage.as_ref(), &metadata_name)?;
+        uuids.push(uuid);
+    }
+
+    assert_eq!(vault.list()?.len(), 5000);
+    assert_eq!(storage.take_metadata_reads(), 5000);
+    assert_eq!(vault.list()?.len(), 5000);
+    assert_eq!(storage.take_metadata_reads(), 0);
+
+    // One changed, one gone, one new
+    let (_, changed) = file::note_file_names(&uuids[0]);
+    NoteMetadata::new("Renamed").save(storage.as_ref(), &changed)?;
+    file::delete_note_files(storage.as_ref(), &uuids[1])?;
+    let added = file::generate_uuid();
+    let (content_name, metadata_name) = file::note_file_names(&added);
+    storage.write(&content_name, b"")?;
+    NoteMetadata::new("Added").save(storage.as_ref(), &metadata_name)?;
+
+    let notes = vault.list()?;
+    assert_eq!(storage.take_metadata_reads(), 2);
+    assert_eq!(notes.len(), 5000);
+    assert!(notes.iter().all(|note| note.uuid != uuids[1]));
+    assert!(
+        notes
+            .iter()
+            .any(|note| note.uuid == uuids[0] && note.metadata.original_filename == "Renamed")
+    );
+    assert!(notes.iter().any(|note| note.uuid == added));
+
+    vault.force_list()?;
+    assert_eq!(storage.take_metadata_reads(), 5000);
+    Ok(())
+}
+
+#[test]
+fn test_list_ignores_unrelated_files() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let note = vault.create("a.b", b"")?;
+
+    storage.write("a.b.enc.txt.orig", b"")?;
+    storage.write(".enc.txt", b"")?;
+    storage.write("README.md", b"")?;
+
+    let notes = vault.list()?;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].uuid, note.uuid);
+    assert_eq!(
+        vault.unexpected_files()?,
+        [".enc.txt", "README.md", "a.b.enc.txt.orig"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_note_count_is_cached_in_the_vault_info() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let count = || -> Result<Option<usize>, AppError> {
+        Ok(VaultInfo::load(storage.as_ref())?.and_then(|info| info.note_count))
+    };
+    let (groceries, ideas) = {
+        let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+        (
+            vault.create("Groceries", b"milk")?,
+            vault.create("Ideas", b"")?,
+        )
+    };
+    assert_eq!(count()?, Some(2));
+    {
+        let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+        vault.delete(&ideas.uuid)?;
+    }
+    assert_eq!(count()?, Some(1));
+
+    // Deleted behind Ryokan's back, then corrected by the next listing
+    file::delete_note_files(storage.as_ref(), &groceries.uuid)?;
+    assert_eq!(count()?, Some(1));
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    assert!(vault.list()?.is_empty());
+    assert_eq!(count()?, Some(0));
+    assert!(vault.unexpected_files()?.is_empty());
+
+    // Nothing but the description, dates and count is in plaintext
+    let info = String::from_utf8_lossy(&storage.read(VAULT_INFO_FILE_NAME)?).into_owned();
+    assert!(!info.contains("Ideas"), "{info}");
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_unencrypted_files() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    std::fs::write(dir.path().join("todo.txt"), b"buy milk")?;
+
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert_eq!(summary.encrypted, ["todo.txt"]);
+    assert!(!dir.path().join("todo.txt").exists());
+
+    let notes = vault.list()?;
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].metadata.original_filename, "todo.txt");
+    assert_eq!(vault.read(&notes[0].uuid)?, b"buy milk");
+
+    // Metadata files must not be picked up on a second run
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert_eq!(summary, ryokan::vault::EncryptionSummary::default());
+
+    std::fs::write(dir.path().join("empty.txt"), b"")?;
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert!(summary.encrypted.is_empty());
+    assert_eq!(summary.skipped.len(), 1);
+    assert_eq!(summary.skipped[0].0, "empty.txt");
+    assert!(dir.path().join("empty.txt").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_unencrypted_files_keeps_ignored_files() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    for name in ["README.md", ".gitignore", "todo.txt"] {
+        storage.write(name, name.as_bytes())?;
+    }
+
+    let scan = vault.scan_unencrypted_files()?;
+    assert_eq!(scan.ignored, [".gitignore", "README.md"]);
+    assert_eq!(scan.unencrypted.len(), 1);
+    assert_eq!(scan.unencrypted_bytes(), "todo.txt".len() as u64);
+    assert!(!storage.exists(IGNORE_FILE_NAME));
+
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert_eq!(summary.encrypted, ["todo.txt"]);
+    assert_eq!(summary.ignored, [".gitignore", "README.md"]);
+    assert_eq!(storage.read("README.md")?, b"README.md");
+    assert_eq!(storage.read(IGNORE_FILE_NAME)?, DEFAULT_RULES.as_bytes());
+
+    // The file is the user's to edit once written
+    storage.write(IGNORE_FILE_NAME, b".gitignore\n")?;
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert_eq!(summary.encrypted, ["README.md"]);
+    assert_eq!(summary.ignored, [".gitignore"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_unencrypted_files_takes_in_misnamed_notes() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let note = vault.create("todo", b"buy milk")?;
+    let (encrypted_name, _) = file::note_file_names(&note.uuid);
+    storage.write("todo.bak", &storage.read(&encrypted_name)?)?;
+    let other = Arc::new(MemoryStorage::new());
+    let foreign = Vault::with_storage(Arc::clone(&other), pin("654321")).create("other", b"x")?;
+    let (foreign_name, _) = file::note_file_names(&foreign.uuid);
+    storage.write("other.enc.txt", &other.read(&foreign_name)?)?;
+
+    let summary = vault.encrypt_unencrypted_files()?;
+    assert_eq!(summary.renamed.len(), 1);
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].0, "other.enc.txt");
+    assert!(!storage.exists("todo.bak"));
+    assert!(storage.exists("other.enc.txt"));
+
+    // The renamed file is a note of its own, not a stray file
+    let copy = vault.find("todo.bak")?;
+    assert_eq!(summary.renamed, [file::note_file_names(&copy.uuid).0]);
+    assert_eq!(vault.read(&copy.uuid)?, b"buy milk");
+    assert_eq!(vault.list()?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_unencrypted_files_reports_progress() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.create("Existing", b"")?;
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        storage.write(name, name.as_bytes())?;
+    }
+
+    let seen = std::sync::Mutex::new(Vec::new());
+    let summary = vault.encrypt_unencrypted_files_with_progress(&|progress| {
+        assert_eq!(progress.total, 3);
+        if let Ok(mut seen) = seen.lock() {
+            seen.push((progress.done, progress.name.to_string()));
+        }
+    })?;
+    assert_eq!(summary.encrypted, ["a.txt", "b.txt", "c.txt"]);
+    assert!(summary.failed.is_empty());
+
+    let mut seen = seen.into_inner().unwrap_or_default();
+    seen.sort();
+    assert_eq!(
+        seen.iter().map(|(done, _)| *done).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    // Every new note made it into the index, not just the last one written
+    let index = NoteIndex::load(storage.as_ref()).unwrap_or_default();
+    let mut titles: Vec<_> = index.titles.into_values().collect();
+    titles.sort();
+    assert_eq!(titles, ["Existing", "a.txt", "b.txt", "c.txt"]);
+
+    Ok(())
+}
+
+/// A step of encrypting a file that [`FailingStorage`] makes go wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fault {
+    /// Writing the metadata of the note fails.
+    WriteMetadata,
+    /// Writing the content of the note fails.
+    WriteContent,
+    /// The content of the note is written, but damaged.
+    CorruptContent,
+    /// Deleting the original file fails.
+    DeleteSource,
+}
+
+/// Storage where one step of encrypting files goes wrong, while `fault` is set.
+#[derive(Default)]
+struct FailingStorage {
+    inner: MemoryStorage,
+    fault: std::sync::Mutex<Option<Fault>>,
+}
+
+impl FailingStorage {
+    fn fault(&self) -> Option<Fault> {
+        self.fault.lock().ok().and_then(|fault| *fault)
+    }
+
+    fn set_fault(&self, fault: Option<Fault>) {
+        if let Ok(mut current) = self.fault.lock() {
+            *current = fault;
+        }
+    }
+}
+
+fn injected() -> AppError {
+    std::io::Error::other("injected failure").into()
+}
+
+impl Storage for FailingStorage {
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, AppError> {
+        self.inner.list(dir)
+    }
+
+    fn list_dirs(&self, dir: &str) -> Result<Vec<String>, AppError> {
+        self.inner.list_dirs(dir)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, AppError> {
+        self.inner.read(name)
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<(), AppError> {
+        match self.fault() {
+            Some(Fault::WriteMetadata) if name.ends_with(".meta.toml") => Err(injected()),
+            Some(Fault::WriteContent) if name.ends_with(".enc.txt") => Err(injected()),
+            Some(Fault::CorruptContent) if name.ends_with(".enc.txt") => {
+                let mut data = data.to_vec();
+                if let Some(last) = data.last_mut() {
+                    *last ^= 0xff;
+                }
+                self.inner.write(name, &data)
+            }
+            _ => self.inner.write(name, data),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<(), AppError> {
+        match self.fault() {
+            Some(Fault::DeleteSource) if name == "todo.txt" => Err(injected()),
+            _ => self.inner.delete(name),
+        }
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.inner.rename(from, to)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.inner.exists(name)
+    }
+}
+
+#[test]
+fn test_failed_encryption_leaves_the_file_untouched() -> Result<(), AppError> {
+    for fault in [
+        Fault::WriteMetadata,
+        Fault::WriteContent,
+        Fault::CorruptContent,
+        Fault::DeleteSource,
+    ] {
+        let storage = Arc::new(FailingStorage::default());
+        let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+        storage.write("todo.txt", b"buy milk")?;
+
+        storage.set_fault(Some(fault));
+        let summary = vault.encrypt_unencrypted_files()?;
+        assert!(summary.encrypted.is_empty(), "{fault:?}: {summary:?}");
+        assert_eq!(summary.failed.len(), 1, "{fault:?}: {summary:?}");
+        assert_eq!(summary.failed[0].0, "todo.txt");
+
+        // The file is still there, and nothing of the note it was going to be
+        assert_eq!(storage.read("todo.txt")?, b"buy milk", "{fault:?}");
+        let leftovers: Vec<_> = storage
+            .list("")?
+            .into_iter()
+            .map(|entry| entry.name)
+            .filter(|name| name.ends_with(".enc.txt") || name.ends_with(".meta.toml"))
+            .collect();
+        assert!(leftovers.is_empty(), "{fault:?}: {leftovers:?}");
+        assert!(vault.list()?.is_empty(), "{fault:?}");
+        assert!(vault.interrupted_operation()?.is_none(), "{fault:?}");
+
+        // Once the storage behaves, the file is encrypted as usual
+        storage.set_fault(None);
+        let summary = vault.encrypt_unencrypted_files()?;
+        assert_eq!(summary.encrypted, ["todo.txt"], "{fault:?}");
+        let notes = vault.list()?;
+        assert_eq!(notes.len(), 1, "{fault:?}");
+        assert_eq!(vault.read(&notes[0].uuid)?, b"buy milk");
+        assert!(!storage.exists("todo.txt"));
+    }
+
+    Ok(())
+}
+
+/// Leaves the vault as if `encrypt-unencrypted` was cut short while going
+/// through `a.txt`, `b.txt` and `c.txt`: `a.txt` is done, `b.txt` is written
+/// but still there, and `c.txt` wasn't reached.
+fn interrupt_encryption(storage: &MemoryStorage) -> Result<(), AppError> {
+    let names = ["a.txt", "b.txt", "c.txt"];
+    for name in names {
+        storage.write(name, name.as_bytes())?;
+    }
+    let recorder = Recorder::start(
+        storage,
+        OperationKind::EncryptUnencrypted,
+        names.map(String::from),
+    )?;
+    for name in ["a.txt", "b.txt"] {
+        let uuid = file::generate_uuid();
+        file::write_note(
+            storage,
+            "123456",
+            &uuid,
+            &NoteMetadata::new(name),
+            name.as_bytes(),
+        )?;
+        let status = if name == "a.txt" {
+            storage.delete(name)?;
+            FileStatus::Done { uuid }
+        } else {
+            FileStatus::Written { uuid }
+        };
+        recorder.set(name, status)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_interrupted_encryption_can_be_resumed() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    interrupt_encryption(&storage)?;
+
+    let operation = vault.interrupted_operation()?;
+    assert!(
+        operation
+            .as_ref()
+            .is_some_and(|operation| operation.done() == 1),
+        "{operation:?}"
+    );
+    assert!(matches!(
+        vault.encrypt_unencrypted_files(),
+        Err(AppError::Interrupted(_))
+    ));
+
+    let summary = vault.resume_operation()?;
+    assert_eq!(summary.encrypted, ["a.txt", "b.txt", "c.txt"]);
+    assert!(vault.interrupted_operation()?.is_none());
+    let mut titles: Vec<_> = vault.index()?.titles.into_values().collect();
+    titles.sort();
+    assert_eq!(titles, ["a.txt", "b.txt", "c.txt"]);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(!storage.exists(name), "{name}");
+    }
+    // Nothing is left to recover
+    assert!(!storage.exists(RECOVERY_FILE_NAME));
+    Ok(())
+}
+
+#[test]
+fn test_interrupted_encryption_can_be_rolled_back() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    interrupt_encryption(&storage)?;
+    let record = storage.read(RECOVERY_FILE_NAME)?;
+    assert!(
+        !String::from_utf8_lossy(&record).contains("123456"),
+        "{record:?}"
+    );
+
+    let mut restored = vault.roll_back_operation()?;
+    restored.sort();
+    assert_eq!(restored, ["a.txt"]);
+    assert!(vault.interrupted_operation()?.is_none());
+    assert!(vault.list()?.is_empty());
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert_eq!(storage.read(name)?, name.as_bytes());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_encrypted_files_keep_their_timestamps() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let path = dir.path().join("old.txt");
+    std::fs::write(&path, b"written long ago")?;
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(mtime)?;
+
+    let vault = Vault::open(dir.path(), pin("123456"));
+    vault.encrypt_unencrypted_files()?;
+
+    let notes = vault.list()?;
+    assert_eq!(notes.len(), 1);
+    let expected = DateTime::<Utc>::from(mtime);
+    assert_eq!(notes[0].metadata.updated_at, expected);
+    assert_eq!(notes[0].metadata.created_at, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_timestamps_can_be_repaired_from_the_encrypted_file() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    let note = vault.create("Imported", b"from elsewhere")?;
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    File::options()
+        .write(true)
+        .open(dir.path().join(file::note_file_names(&note.uuid).0))?
+        .set_modified(mtime)?;
+
+    let (created_at, updated_at) = vault.file_times(&note.uuid)?;
+    let expected = DateTime::<Utc>::from(mtime);
+    assert_eq!(updated_at, expected);
+    // Not after the last change, whatever the filesystem says
+    assert!(created_at <= expected);
+
+    vault.metadata_mut(&note.uuid, |metadata| {
+        metadata.created_at = created_at;
+        metadata.updated_at = updated_at;
+    })?;
+    let repaired = vault.get(&note.uuid)?;
+    assert_eq!(repaired.metadata.created_at, created_at);
+    assert_eq!(repaired.metadata.updated_at, expected);
+    Ok(())
+}
+
+#[test]
+fn test_create_with_metadata() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let created = DateTime::<Utc>::from(SystemTime::UNIX_EPOCH);
+    let updated = created + chrono::Duration::days(1);
+    let metadata = NoteMetadata::with_timestamps("Diary", created, updated);
+
+    let note = vault.create_with_metadata(&metadata, b"dear diary")?;
+
+    let saved = vault.get(&note.uuid)?.metadata;
+    assert_eq!(saved.created_at, created);
+    assert_eq!(saved.updated_at, updated);
+    assert_eq!(vault.index()?.titles[&note.uuid], "Diary");
+
+    Ok(())
+}
+
+#[test]
+fn test_index_tracks_metadata() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let fresh_index =
+        || -> Result<NoteIndex, AppError> { Ok(NoteIndex::from_notes(&vault.list()?)) };
+
+    let first = vault.create("First", b"")?;
+    let second = vault.create("Second", b"")?;
+    assert_eq!(NoteIndex::load(storage.as_ref()), Some(fresh_index()?));
+
+    vault.metadata_mut(&first.uuid, |m| m.original_filename = "Renamed".to_string())?;
+    assert_eq!(NoteIndex::load(storage.as_ref()), Some(fresh_index()?));
+    assert_eq!(vault.index()?.titles[&first.uuid], "Renamed");
+
+    vault.delete(&second.uuid)?;
+    assert_eq!(NoteIndex::load(storage.as_ref()), Some(fresh_index()?));
+
+    // Deleting files behind the vault's back makes the index stale
+    storage.delete(&format!("{}.enc.txt", first.uuid))?;
+    assert!(vault.index()?.titles.is_empty());
+
+    // A missing index is rebuilt on the next read
+    vault.create("Third", b"")?;
+    storage.delete(INDEX_FILE_NAME)?;
+    assert_eq!(vault.index()?, fresh_index()?);
+
+    Ok(())
+}
+
+#[test]
+fn test_read_only_notes_refuse_writes() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let note = vault.create("Reference", b"keep")?;
+    vault.metadata_mut(&note.uuid, |m| m.read_only = true)?;
+
+    assert!(matches!(
+        vault.write(&note.uuid, b"oops"),
+        Err(AppError::ReadOnlyNote(_))
+    ));
+    assert_eq!(vault.read(&note.uuid)?, b"keep");
+
+    vault.force_write(&note.uuid, b"on purpose")?;
+    assert_eq!(vault.read(&note.uuid)?, b"on purpose");
+
+    Ok(())
+}
+
+#[test]
+fn test_find_by_uuid_or_title() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let note = vault.create("Groceries", b"")?;
+    vault.create("Twin", b"")?;
+    vault.create("twin", b"")?;
+
+    assert_eq!(vault.find(&note.uuid)?.uuid, note.uuid);
+    assert_eq!(vault.find("groceries")?.uuid, note.uuid);
+    assert!(matches!(
+        vault.find("Twin"),
+        Err(AppError::AmbiguousNote(_))
+    ));
+    assert!(matches!(vault.find("Nope"), Err(AppError::NoteNotFound(_))));
+
+    // Beyond ASCII, titles compare like duplicates are found
+    let emile = vault.create("Émile", b"")?;
+    assert_eq!(vault.find("émile")?.uuid, emile.uuid);
+
+    Ok(())
+}
+
+#[test]
+fn test_notes_outside_the_vault_are_not_found() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let outside = Vault::open(dir.path().join("other"), pin("123456"));
+    let note = outside.create("Secret", b"")?;
+    let vault = Vault::open(dir.path().join("notes"), pin("123456"));
+    vault.create("Groceries", b"")?;
+
+    let query = format!("../other/{}", note.uuid);
+    assert!(matches!(vault.get(&query), Err(AppError::NoteNotFound(_))));
+    assert!(matches!(vault.find(&query), Err(AppError::NoteNotFound(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_same_title_ignores_case_and_whitespace() {
+    assert!(vault::same_title(
+        "Meeting notes 2024-06-12",
+        " meeting NOTES 2024-06-12"
+    ));
+    assert!(vault::same_title("Réunion", "RÉUNION"));
+    assert!(!vault::same_title("Meeting notes", "Meeting notes 2"));
+}
+
+#[test]
+fn test_content_stats_are_recorded() -> Result<(), AppError> {
+    let mut vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let note = vault.create("Poem", b"roses are red")?;
+    assert_eq!(note.metadata.size, Some(13));
+    assert_eq!(note.metadata.word_count, Some(3));
+
+    let metadata = vault.write(&note.uuid, b"violets are blue, sugar is sweet")?;
+    assert_eq!(metadata.size, Some(32));
+    assert_eq!(metadata.word_count, Some(6));
+
+    vault.set_record_stats(false);
+    let metadata = vault.write(&note.uuid, b"and so are you")?;
+    assert_eq!(metadata.size, None);
+    assert_eq!(metadata.word_count, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_snippets_are_sealed_and_refreshed() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let note = vault.create("Trip", b"# Trip\n\nTickets for Kyoto")?;
+    assert_eq!(note.metadata.snippet, None);
+
+    vault.set_snippets(true);
+    let metadata = vault.write(&note.uuid, b"# Trip\n\nTickets for Kyoto")?;
+    let (_, metadata_name) = file::note_file_names(&note.uuid);
+    let saved = String::from_utf8(storage.read(&metadata_name)?).unwrap_or_default();
+    assert!(
+        saved.contains("snippet") && !saved.contains("Kyoto"),
+        "{saved}"
+    );
+    let snippet = vault.open_snippet(&metadata).map(|snippet| snippet.text);
+    assert_eq!(snippet.as_deref(), Some("Tickets for Kyoto"));
+    assert_eq!(
+        vault.refresh_snippet(&note.uuid, b"# Trip\n\nTickets for Kyoto")?,
+        None
+    );
+
+    // Replaced by another program, e.g. a sync, keeping the old snippet
+    file::write_note(
+        storage.as_ref(),
+        "123456",
+        &note.uuid,
+        &metadata,
+        b"Tickets for Osaka",
+    )?;
+    let Some(refreshed) = vault.refresh_snippet(&note.uuid, b"Tickets for Osaka")? else {
+        panic!("the stale snippet wasn't refreshed");
+    };
+    let snippet = vault.open_snippet(&refreshed).map(|snippet| snippet.text);
+    assert_eq!(snippet.as_deref(), Some("Tickets for Osaka"));
+
+    // Sealed again with the new key of the manifest, as another process reads it
+    assert!(vault.rekey()?.is_complete());
+    let other = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let snippet = other
+        .open_snippet(&other.get(&note.uuid)?.metadata)
+        .map(|snippet| snippet.text);
+    assert_eq!(snippet.as_deref(), Some("Tickets for Osaka"));
+
+    vault.set_snippets(false);
+    let metadata = vault.write(&note.uuid, b"Tickets for Nara")?;
+    assert_eq!(metadata.snippet, None);
+    Ok(())
+}
+
+#[test]
+fn test_sort_modes() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let b = vault.create("b", b"a little longer")?;
+    let c = vault.create("C", b"")?;
+    vault.create("a", b"short")?;
+    let due = DateTime::<Utc>::from(SystemTime::UNIX_EPOCH);
+    vault.metadata_mut(&b.uuid, |m| {
+        m.due_at = Some(due + chrono::Duration::days(1))
+    })?;
+    vault.metadata_mut(&c.uuid, |m| m.due_at = Some(due))?;
+
+    let titles = |mode: SortMode| -> Result<Vec<String>, AppError> {
+        let mut notes = vault.list()?;
+        mode.sort(&mut notes);
+        Ok(notes
+            .into_iter()
+            .map(|note| note.metadata.original_filename)
+            .collect())
+    };
+    assert_eq!(titles(SortMode::Title)?, ["a", "b", "C"]);
+    assert_eq!(titles(SortMode::Size)?, ["b", "a", "C"]);
+    assert_eq!(titles(SortMode::Due)?, ["C", "b", "a"]);
+
+    assert_eq!("Size".parse::<SortMode>()?, SortMode::Size);
+    assert_eq!(SortMode::Due.next(), SortMode::Updated);
+
+    Ok(())
+}
+
+#[test]
+fn test_list_query_filters_sorts_and_limits() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let day = |n: i64| DateTime::<Utc>::from(SystemTime::UNIX_EPOCH) + chrono::Duration::days(n);
+    for (title, created, updated, tags) in [
+        ("a", 1, 5, &["work", "urgent"][..]),
+        ("b", 2, 3, &["Work"][..]),
+        ("c", 3, 4, &["home"][..]),
+        ("d", 4, 9, &[][..]),
+    ] {
+        let note = vault.create(title, b"")?;
+        vault.metadata_mut(&note.uuid, |m| {
+            m.created_at = day(created);
+            m.updated_at = day(updated);
+            m.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            m.note_type = match title {
+                "b" => NoteType::Todo,
+                "c" => NoteType::from("kanban".to_string()),
+                _ => NoteType::Plain,
+            };
+        })?;
+    }
+    let titles = |query: vault::ListQuery| -> Result<Vec<String>, AppError> {
+        Ok(vault
+            .query(&query)?
+            .into_iter()
+            .map(|note| note.metadata.original_filename)
+            .collect())
+    };
+
+    assert_eq!(titles(vault::ListQuery::default())?, ["d", "a", "c", "b"]);
+    assert_eq!(
+        titles(vault::ListQuery {
+            sort: SortMode::Created,
+            reverse: true,
+            limit: Some(3),
+            ..Default::default()
+        })?,
+        ["a", "b", "c"]
+    );
+    assert_eq!(
+        titles(vault::ListQuery {
+            since: Some(day(4)),
+            until: Some(day(9)),
+            ..Default::default()
+        })?,
+        ["a", "c"]
+    );
+    assert_eq!(
+        titles(vault::ListQuery {
+            tags: vec!["work".to_string(), "URGENT".to_string()],
+            ..Default::default()
+        })?,
+        ["a"]
+    );
+    assert_eq!(
+        titles(vault::ListQuery {
+            any_tags: vec!["work".to_string(), "home".to_string()],
+            ..Default::default()
+        })?,
+        ["a", "c", "b"]
+    );
+    assert_eq!(
+        titles(vault::ListQuery {
+            tags: vec!["work".to_string()],
+            any_tags: vec!["home".to_string(), "urgent".to_string()],
+            ..Default::default()
+        })?,
+        ["a"]
+    );
+    for (note_type, expected) in [
+        ("todo", &["b"][..]),
+        ("other", &["c"]),
+        ("plain", &["d", "a"]),
+    ] {
+        assert_eq!(
+            titles(vault::ListQuery {
+                note_type: Some(NoteType::parse(note_type)?),
+                ..Default::default()
+            })?,
+            expected
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_history_keeps_previous_versions() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.set_history_limit(2);
+    let note = vault.create("Draft", b"v1")?;
+
+    for content in [&b"v2"[..], b"v3", b"v4"] {
+        // Versions are named after the time they were saved
+        std::thread::sleep(Duration::from_millis(2));
+        vault.write(&note.uuid, content)?;
+    }
+
+    let history = vault.history(&note.uuid)?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(vault.read_version(&note.uuid, &history[0].id)?, b"v3");
+    assert_eq!(vault.read_version(&note.uuid, &history[1].id)?, b"v2");
+
+    vault.restore_version(&note.uuid, &history[1].id)?;
+    assert_eq!(vault.read(&note.uuid)?, b"v2");
+    let history = vault.history(&note.uuid)?;
+    assert_eq!(vault.read_version(&note.uuid, &history[0].id)?, b"v4");
+
+    vault.delete(&note.uuid)?;
+    assert!(vault.history(&note.uuid)?.is_empty());
+    assert!(storage.list(&format!("history/{}", note.uuid))?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_history_can_be_disabled() -> Result<(), AppError> {
+    let mut vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    vault.set_history_limit(0);
+    let note = vault.create("Scratch", b"v1")?;
+    vault.write(&note.uuid, b"v2")?;
+
+    assert!(vault.history(&note.uuid)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_capture_appends_to_the_inbox() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let at = Local
+        .with_ymd_and_hms(2026, 3, 14, 9, 5, 0)
+        .single()
+        .ok_or_else(|| AppError::Config("Invalid date".to_string()))?;
+
+    let header = Template::new("capture_header", DEFAULT_CAPTURE_HEADER, false)?;
+
+    let uuid = vault.capture("Inbox", "Call the plumber\n", &header, at)?;
+    assert_eq!(vault.get(&uuid)?.metadata.original_filename, "Inbox");
+    assert_eq!(vault.capture("inbox", "Buy milk", &header, at)?, uuid);
+    assert_eq!(
+        vault.read(&uuid)?,
+        b"2026-03-14 09:05\nCall the plumber\n\n2026-03-14 09:05\nBuy milk\n"
+    );
+    assert_eq!(vault.list()?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_new_note_titles_count_past_existing_ones() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let template = Template::new("new_note_title", "Draft {n}", true)?;
+    let now = Local::now();
+
+    assert_eq!(vault.next_title(&template, now)?, "Draft 1");
+    vault.create("draft 1", b"")?;
+    vault.create("Draft 3", b"")?;
+    assert_eq!(vault.next_title(&template, now)?, "Draft 2");
+    vault.create(&vault.next_title(&template, now)?, b"")?;
+    assert_eq!(vault.next_title(&template, now)?, "Draft 4");
+
+    Ok(())
+}
+
+#[test]
+fn test_orphaned_files_are_found_and_fixed() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let kept = vault.create("Kept", b"")?;
+    let lost_content = vault.create("Groceries", b"milk")?;
+    vault.write(&lost_content.uuid, b"milk\neggs")?;
+    let lost_metadata = vault.create("Plans", b"trip")?;
+    let (content_name, _) = file::note_file_names(&lost_content.uuid);
+    storage.delete(&content_name)?;
+    let (_, metadata_name) = file::note_file_names(&lost_metadata.uuid);
+    storage.delete(&metadata_name)?;
+
+    let mut expected = vec![
+        Orphan::Metadata {
+            uuid: lost_content.uuid.clone(),
+            title: Some("Groceries".to_string()),
+        },
+        Orphan::Content {
+            uuid: lost_metadata.uuid.clone(),
+        },
+    ];
+    expected.sort_by(|a, b| a.uuid().cmp(b.uuid()));
+    assert_eq!(vault.orphaned_files()?, expected);
+    assert_eq!(vault.list()?.len(), 1);
+
+    // Each fix only applies to its own kind of orphan
+    assert!(vault.adopt(&lost_content.uuid).is_err());
+    assert!(vault.delete_orphaned_metadata(&lost_metadata.uuid).is_err());
+    assert!(vault.delete_orphaned_metadata(&kept.uuid).is_err());
+
+    vault.delete_orphaned_metadata(&lost_content.uuid)?;
+    assert!(vault.history(&lost_content.uuid)?.is_empty());
+    let adopted = vault.adopt(&lost_metadata.uuid)?;
+    assert!(adopted.metadata.original_filename.starts_with("Recovered "));
+    assert_eq!(vault.read(&lost_metadata.uuid)?, b"trip");
+
+    assert!(vault.orphaned_files()?.is_empty());
+    assert_eq!(vault.list()?.len(), 2);
+    assert_eq!(vault.index()?.titles.len(), 2);
+
+    // Content that doesn't decrypt with this PIN isn't taken in
+    let other = Vault::with_storage(Arc::clone(&storage), pin("000000"));
+    let foreign = other.create("Foreign", b"secret")?;
+    let (_, metadata_name) = file::note_file_names(&foreign.uuid);
+    storage.delete(&metadata_name)?;
+    assert!(vault.adopt(&foreign.uuid).is_err());
+    assert!(!storage.exists(&metadata_name));
+
+    Ok(())
+}
+
+#[test]
+fn test_quick_access_slots_move_between_notes() -> Result<(), AppError> {
+    let vault = Vault::with_storage(Arc::new(MemoryStorage::new()), pin("123456"));
+    let first = vault.create("First", b"")?;
+    let second = vault.create("Second", b"")?;
+    let slot = |uuid: &str| -> Result<Option<u8>, AppError> { Ok(vault.get(uuid)?.metadata.slot) };
+
+    let before = vault.assign_slot(&first.uuid, Some(1))?;
+    assert_eq!(before.len(), 1);
+    assert_eq!(slot(&first.uuid)?, Some(1));
+
+    // A slot belongs to one note at a time
+    let before = vault.assign_slot(&second.uuid, Some(1))?;
+    assert_eq!(before.len(), 2);
+    assert_eq!(before[0].metadata.slot, Some(1));
+    assert_eq!(slot(&first.uuid)?, None);
+    assert_eq!(slot(&second.uuid)?, Some(1));
+
+    for invalid in [0, 10] {
+        assert!(matches!(
+            vault.assign_slot(&first.uuid, Some(invalid)),
+            Err(AppError::Config(_))
+        ));
+    }
+
+    vault.assign_slot(&second.uuid, None)?;
+    assert_eq!(slot(&second.uuid)?, None);
+
+    // Deleted notes give their slot up with their metadata
+    vault.assign_slot(&second.uuid, Some(2))?;
+    vault.delete(&second.uuid)?;
+    assert!(
+        vault
+            .list()?
+            .iter()
+            .all(|note| note.metadata.slot.is_none())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_pinned_notes_keep_the_order_chosen() -> Result<(), AppError> {
+    let vault = Vault::with_storage(Arc::new(MemoryStorage::new()), pin("123456"));
+    let a = vault.create("A", b"")?;
+    let b = vault.create("B", b"")?;
+    let c = vault.create("C", b"")?;
+    let order = || -> Result<Vec<String>, AppError> {
+        let mut notes = vault.list()?;
+        vault::pinned_first(&mut notes);
+        Ok(notes
+            .into_iter()
+            .map(|note| note.metadata.original_filename)
+            .collect())
+    };
+
+    // Pinned at the end, before the others, which stay newest first
+    vault.set_pinned(&a.uuid, true)?;
+    vault.set_pinned(&b.uuid, true)?;
+    assert_eq!(order()?, ["A", "B", "C"]);
+    assert!(vault.set_pinned(&a.uuid, true)?.is_empty());
+
+    let before = vault.move_pinned(&b.uuid, true)?;
+    assert_eq!(before.len(), 2);
+    assert_eq!(order()?, ["B", "A", "C"]);
+    // Editing a pinned note doesn't move it
+    vault.write(&a.uuid, b"edited")?;
+    assert_eq!(order()?, ["B", "A", "C"]);
+    assert!(vault.move_pinned(&b.uuid, true)?.is_empty());
+    assert!(vault.move_pinned(&c.uuid, false)?.is_empty());
+
+    // Unpinning leaves a gap, closed by the next change to the order
+    vault.set_pinned(&b.uuid, false)?;
+    assert_eq!(vault.get(&b.uuid)?.metadata.pin_order, None);
+    vault.set_pinned(&c.uuid, true)?;
+    let numbers: Vec<_> = [&a, &c]
+        .into_iter()
+        .map(|note| Ok(vault.get(&note.uuid)?.metadata.pin_order))
+        .collect::<Result<_, AppError>>()?;
+    assert_eq!(numbers, [Some(1), Some(2)]);
+    assert_eq!(order()?, ["A", "C", "B"]);
+    Ok(())
+}
+
+#[test]
+fn test_append_and_prepend_raw_bytes() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let note = vault.create("Log", b"start")?;
+
+    vault.append(&note.uuid, b"\xff\xfe raw", None)?;
+    assert_eq!(vault.read(&note.uuid)?, b"start\xff\xfe raw");
+
+    vault.append(&note.uuid, b"later\n", Some("--- t1 ---"))?;
+    vault.prepend(&note.uuid, b"first", Some("--- t0 ---"))?;
+    assert_eq!(
+        vault.read(&note.uuid)?,
+        b"--- t0 ---\nfirst\nstart\xff\xfe raw\n--- t1 ---\nlater\n"
+    );
+
+    let read_only = vault.create("Frozen", b"")?;
+    vault.metadata_mut(&read_only.uuid, |metadata| metadata.read_only = true)?;
+    assert!(matches!(
+        vault.append(&read_only.uuid, b"x", None),
+        Err(AppError::ReadOnlyNote(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_append_waits_for_the_vault_lock() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    let note = vault.create("Log", b"")?;
+
+    let lock = vault.lock()?;
+    let appender = {
+        let dir = dir.path().to_path_buf();
+        let uuid = note.uuid.clone();
+        std::thread::spawn(move || Vault::open(dir, pin("123456")).append(&uuid, b"line", None))
+    };
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(vault.read(&note.uuid)?, b"");
+
+    drop(lock);
+    appender
+        .join()
+        .map_err(|_| AppError::Config("Appending thread panicked".to_string()))??;
+    assert_eq!(vault.read(&note.uuid)?, b"line");
+
+    // The lock file is not a plaintext note waiting to be encrypted
+    assert!(vault.encrypt_unencrypted_files()?.encrypted.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_rekey_waits_for_the_vault_lock() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    let note = vault.create("Log", b"draft")?;
+    let (name, _) = file::note_file_names(&note.uuid);
+    let before = std::fs::read(dir.path().join(&name))?;
+
+    let lock = vault.lock()?;
+    let rekey = {
+        let dir = dir.path().to_path_buf();
+        std::thread::spawn(move || Vault::open(dir, pin("123456")).rekey())
+    };
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(std::fs::read(dir.path().join(&name))?, before);
+
+    drop(lock);
+    let summary = rekey
+        .join()
+        .map_err(|_| AppError::Config("Rekeying thread panicked".to_string()))??;
+    assert_eq!(summary.rekeyed, std::slice::from_ref(&name));
+    assert_ne!(std::fs::read(dir.path().join(&name))?, before);
+    assert_eq!(vault.read(&note.uuid)?, b"draft");
+    Ok(())
+}
+
+#[test]
+fn test_encrypting_plaintext_files_waits_for_the_vault_lock() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    std::fs::write(dir.path().join("todo.txt"), b"buy milk")?;
+
+    let lock = vault.lock()?;
+    let encrypter = {
+        let dir = dir.path().to_path_buf();
+        std::thread::spawn(move || Vault::open(dir, pin("123456")).encrypt_unencrypted_files())
+    };
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(dir.path().join("todo.txt").exists());
+
+    drop(lock);
+    let summary = encrypter
+        .join()
+        .map_err(|_| AppError::Config("Encrypting thread panicked".to_string()))??;
+    assert_eq!(summary.encrypted, ["todo.txt"]);
+    assert!(!dir.path().join("todo.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn test_read_only_mode_refuses_every_change() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut vault = Vault::with_storage(storage.clone(), pin("123456"));
+    let note = vault.create("Groceries", b"milk")?;
+    storage.delete(INDEX_FILE_NAME)?;
+
+    vault.enter_read_only_mode();
+    assert!(vault.is_read_only_mode());
+    assert!(matches!(
+        vault.create("New", b""),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(
+        vault.write(&note.uuid, b"eggs"),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(
+        vault.append(&note.uuid, b"eggs", None),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(
+        vault.metadata_mut(&note.uuid, |metadata| metadata.read_only = true),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(
+        vault.delete(&note.uuid),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(vault.rekey(), Err(AppError::ReadOnlyMode)));
+    assert!(matches!(
+        vault.rename_tag("a", "b"),
+        Err(AppError::ReadOnlyMode)
+    ));
+    assert!(matches!(
+        vault.encrypt_unencrypted_files(),
+        Err(AppError::ReadOnlyMode)
+    ));
+
+    // Reading still works, and the missing index is rebuilt without being saved
+    assert_eq!(vault.read(&note.uuid)?, b"milk");
+    assert_eq!(vault.index()?.titles.len(), 1);
+    assert!(!storage.exists(INDEX_FILE_NAME));
+    assert!(!vault.get(&note.uuid)?.metadata.read_only);
+
+    Ok(())
+}
+
+#[test]
+fn test_integrity_check_accepts_updates_and_flags_corruption() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let vault = Vault::open(dir.path(), pin("123456"));
+    let kept = vault.create("Kept", b"milk")?;
+    let rotten = vault.create("Rotten", b"eggs")?;
+    let synced = vault.create("Synced", b"bread")?;
+    let gone = vault.create("Gone", b"jam")?;
+
+    let report = vault.check_integrity(false)?;
+    assert_eq!(report.checked, 4);
+    assert!(report.is_clean() && report.updated.is_empty() && report.warning.is_none());
+
+    // A flipped bit keeps the size and the modification time
+    let (rotten_content, _) = file::note_file_names(&rotten.uuid);
+    let path = dir.path().join(&rotten_content);
+    let modified = path.metadata()?.modified()?;
+    let mut data = std::fs::read(&path)?;
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    std::fs::write(&path, &data)?;
+    File::options()
+        .write(true)
+        .open(&path)?
+        .set_modified(modified)?;
+    // Another program, e.g. a sync, replaces a note and deletes another
+    let (synced_content, _) = file::note_file_names(&synced.uuid);
+    let (kept_content, _) = file::note_file_names(&kept.uuid);
+    std::fs::copy(
+        dir.path().join(&kept_content),
+        dir.path().join(&synced_content),
+    )?;
+    File::options()
+        .write(true)
+        .open(dir.path().join(&synced_content))?
+        .set_modified(modified + Duration::from_secs(60))?;
+    std::fs::remove_file(dir.path().join(file::note_file_names(&gone.uuid).0))?;
+
+    let report = vault.check_integrity(false)?;
+    assert_eq!(report.corrupt, [rotten.uuid.as_str()]);
+    assert_eq!(report.updated, [synced.uuid.as_str()]);
+    assert_eq!(report.removed, [gone.uuid.as_str()]);
+    // Accepted updates aren't reported again, corruption is until it's fixed
+    let report = vault.check_integrity(false)?;
+    assert_eq!(report.corrupt, [rotten.uuid.as_str()]);
+    assert!(report.updated.is_empty());
+
+    let report = vault.check_integrity(true)?;
+    assert!(report.corrupt.is_empty());
+    let undecryptable: Vec<_> = report.undecryptable.iter().map(|(uuid, _)| uuid).collect();
+    assert_eq!(undecryptable, [&rotten.uuid]);
+
+    // Saving the note again records its new content
+    vault.write(&rotten.uuid, b"fresh eggs")?;
+    assert!(vault.check_integrity(false)?.corrupt.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_missing_or_corrupt_manifest_is_only_a_warning() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.create("Groceries", b"milk")?;
+    assert!(storage.exists(MANIFEST_FILE_NAME));
+
+    storage.write(MANIFEST_FILE_NAME, b"garbage")?;
+    let report = vault.check_integrity(false)?;
+    assert!(report.warning.is_some());
+    assert!(report.is_clean());
+    assert_eq!(report.added.len(), 1);
+    assert!(vault.check_integrity(false)?.warning.is_none());
+
+    storage.delete(MANIFEST_FILE_NAME)?;
+    let report = vault.check_integrity(false)?;
+    assert!(report.warning.is_some());
+    assert!(report.is_clean());
+    Ok(())
+}
+
+#[test]
+fn test_every_change_records_one_audit_event() -> Result<(), AppError> {
+    let dir = tempdir()?;
+    let log_path = dir.path().join(AUDIT_FILE_NAME);
+    let mut vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    vault.set_audit_log(AuditLog::new(&log_path, false));
+
+    let a = vault.create("Groceries", b"milk")?;
+    vault.append(&a.uuid, b"\neggs", None)?;
+    vault.metadata_mut(&a.uuid, |metadata| {
+        metadata.original_filename = "Shopping".into()
+    })?;
+    vault.metadata_mut(&a.uuid, |metadata| metadata.tags.push("home".into()))?;
+    let version = vault.history(&a.uuid)?.remove(0);
+    vault.restore_version(&a.uuid, &version.id)?;
+    vault.metadata_mut(&a.uuid, |metadata| metadata.read_only = true)?;
+    // Refused changes aren't recorded
+    assert!(vault.write(&a.uuid, b"bread").is_err());
+    vault.force_write(&a.uuid, b"bread")?;
+    let b = vault.import_note(
+        &file::generate_uuid(),
+        &NoteMetadata::new("Imported"),
+        b"content",
+    )?;
+    vault.trash(&b.uuid)?;
+    vault.delete(&a.uuid)?;
+
+    let events: Vec<_> = AuditLog::new(&log_path, false)
+        .read(None)?
+        .into_iter()
+        .map(|event| (event.kind, event.uuid.unwrap_or_default()))
+        .collect();
+    let expected = [
+        (EventKind::Created, &a.uuid),
+        (EventKind::Edited, &a.uuid),
+        (EventKind::Renamed, &a.uuid),
+        (EventKind::MetadataChanged, &a.uuid),
+        (EventKind::Restored, &a.uuid),
+        (EventKind::MetadataChanged, &a.uuid),
+        (EventKind::Edited, &a.uuid),
+        (EventKind::Imported, &b.uuid),
+        (EventKind::Trashed, &b.uuid),
+        (EventKind::Deleted, &a.uuid),
+    ];
+    assert_eq!(
+        events,
+        expected.map(|(kind, uuid)| (kind, uuid.clone())).to_vec()
+    );
+    // Titles and content stay out of the log
+    let log = std::fs::read_to_string(&log_path)?;
+    assert!(!log.contains("Groceries") && !log.contains("milk"));
+    Ok(())
+}
+
+#[test]
+fn test_trashed_notes_can_be_brought_back_or_deleted() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let mut metadata = NoteMetadata::new("Report");
+    metadata.tags = vec!["work".to_string()];
+    let a = vault.create_with_metadata(&metadata, b"draft")?;
+    vault.write(&a.uuid, b"final")?;
+    let b = vault.create("Trip", b"")?;
+
+    vault.trash(&a.uuid)?;
+    vault.trash(&b.uuid)?;
+    assert!(vault.list()?.is_empty());
+    assert!(vault.tags()?.is_empty());
+
+    let restored = vault.untrash(&a.uuid)?;
+    assert_eq!(restored.metadata.original_filename, "Report");
+    assert_eq!(vault.read(&a.uuid)?, b"final");
+    assert_eq!(vault.history(&a.uuid)?.len(), 1);
+    assert_eq!(
+        vault.tags()?.into_iter().collect::<Vec<_>>(),
+        [("work".to_string(), 1)]
+    );
+    assert!(matches!(
+        vault.untrash(&a.uuid),
+        Err(AppError::NoteNotFound(_))
+    ));
+
+    vault.delete_trashed(&b.uuid)?;
+    assert!(matches!(
+        vault.untrash(&b.uuid),
+        Err(AppError::NoteNotFound(_))
+    ));
+    let (encrypted_name, metadata_name) = file::note_file_names(&b.uuid);
+    for name in [encrypted_name, metadata_name] {
+        assert!(!storage.exists(&file::trash_file_name(&name)));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rekey_encrypts_every_file_again() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let a = vault.create("Report", b"draft")?;
+    vault.write(&a.uuid, b"final")?;
+    let b = vault.create("Trip", b"tickets")?;
+    vault.trash(&b.uuid)?;
+    vault.write_private_file(dedupe::HASH_CACHE_FILE_NAME, b"hashes")?;
+
+    let (a_name, _) = file::note_file_names(&a.uuid);
+    let (b_name, _) = file::note_file_names(&b.uuid);
+    let version = vault.history(&a.uuid)?.remove(0);
+    let mut expected = vec![
+        a_name,
+        file::trash_file_name(&b_name),
+        file::version_file_name(&a.uuid, &version.id),
+        dedupe::HASH_CACHE_FILE_NAME.to_string(),
+    ];
+    let before: Vec<_> = expected
+        .iter()
+        .map(|name| storage.read(name))
+        .collect::<Result<_, _>>()?;
+
+    let summary = vault.rekey()?;
+    assert!(summary.is_complete(), "{summary:?}");
+    let mut rekeyed = summary.rekeyed;
+    rekeyed.sort();
+    expected.sort();
+    assert_eq!(rekeyed, expected);
+    for (name, before) in expected.iter().zip(&before) {
+        assert_ne!(&storage.read(name)?, before, "{name}");
+    }
+
+    assert_eq!(vault.read(&a.uuid)?, b"final");
+    assert_eq!(vault.read_version(&a.uuid, &version.id)?, b"draft");
+    assert_eq!(
+        vault.read_private_file(dedupe::HASH_CACHE_FILE_NAME)?,
+        Some(b"hashes".to_vec())
+    );
+    vault.untrash(&b.uuid)?;
+    assert_eq!(vault.read(&b.uuid)?, b"tickets");
+    // The manifest matches the new files, as another process sees it
+    let other = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let report = other.check_integrity(true)?;
+    assert!(report.is_clean() && report.warning.is_none(), "{report:?}");
+    Ok(())
+}
+
+#[test]
+fn test_tags_in_use_follow_metadata_writes() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let mut metadata = NoteMetadata::new("Report");
+    metadata.tags = vec!["work".to_string()];
+    let a = vault.create_with_metadata(&metadata, b"")?;
+    let b = vault.create("Trip", b"")?;
+    let tags = |vault: &Vault| -> Result<Vec<(String, usize)>, AppError> {
+        Ok(vault.tags()?.into_iter().collect())
+    };
+    assert_eq!(tags(&vault)?, [("work".to_string(), 1)]);
+
+    vault.metadata_mut(&b.uuid, |metadata| {
+        metadata.tags = vec!["work".to_string(), "travel".to_string()]
+    })?;
+    assert_eq!(
+        tags(&vault)?,
+        [("travel".to_string(), 1), ("work".to_string(), 2)]
+    );
+    vault.trash(&b.uuid)?;
+    assert_eq!(tags(&vault)?, [("work".to_string(), 1)]);
+    vault.delete(&a.uuid)?;
+    assert!(tags(&vault)?.is_empty());
+
+    // Changes made by other programs show up once the notes are listed again
+    let other = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let mut metadata = NoteMetadata::new("Elsewhere");
+    metadata.tags = vec!["synced".to_string()];
+    other.create_with_metadata(&metadata, b"")?;
+    vault.list()?;
+    assert_eq!(tags(&vault)?, [("synced".to_string(), 1)]);
+    Ok(())
+}
+
+#[test]
+fn test_renaming_a_tag_moves_its_namespace() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    for (title, tags) in [
+        ("Kitchen", vec!["project/home"]),
+        ("Plan", vec!["Project", "work"]),
+        ("Other", vec!["projects"]),
+    ] {
+        let mut metadata = NoteMetadata::new(title);
+        metadata.tags = tags.into_iter().map(str::to_string).collect();
+        vault.create_with_metadata(&metadata, b"")?;
+    }
+    let titles = |tag: &str| -> Result<Vec<String>, AppError> {
+        let mut titles: Vec<_> = vault
+            .query(&vault::ListQuery {
+                tags: vec![tag.to_string()],
+                ..Default::default()
+            })?
+            .into_iter()
+            .map(|note| note.metadata.original_filename)
+            .collect();
+        titles.sort();
+        Ok(titles)
+    };
+    assert_eq!(titles("project")?, ["Kitchen", "Plan"]);
+
+    assert_eq!(vault.rename_tag("project", "area")?.renamed.len(), 2);
+    assert!(titles("project")?.is_empty());
+    assert_eq!(titles("area")?, ["Kitchen", "Plan"]);
+    assert_eq!(titles("area/home")?, ["Kitchen"]);
+    assert_eq!(titles("projects")?, ["Other"]);
+    assert!(matches!(
+        vault.rename_tag("area", "area/"),
+        Err(AppError::Config(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_read_only_notes_survive_bulk_changes() -> Result<(), AppError> {
+    let vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let mut metadata = NoteMetadata::new("Reference");
+    metadata.tags = vec!["project".to_string()];
+    metadata.read_only = true;
+    let locked = vault.create_with_metadata(&metadata, b"keep")?;
+    metadata.original_filename = "Draft".to_string();
+    metadata.read_only = false;
+    let draft = vault.create_with_metadata(&metadata, b"")?;
+    let before = vault.get(&locked.uuid)?.metadata;
+
+    let summary = vault.rename_tag("project", "area")?;
+    assert_eq!(summary.renamed, [draft.uuid.as_str()]);
+    assert_eq!(summary.skipped_read_only, [locked.uuid.as_str()]);
+    let epoch = DateTime::UNIX_EPOCH;
+    assert!(matches!(
+        vault.set_timestamps(&locked.uuid, epoch, epoch),
+        Err(AppError::ReadOnlyNote(_))
+    ));
+    assert!(matches!(
+        vault.trash(&locked.uuid),
+        Err(AppError::ReadOnlyNote(_))
+    ));
+
+    assert_eq!(vault.get(&locked.uuid)?.metadata, before);
+    assert_eq!(vault.read(&locked.uuid)?, b"keep");
+    Ok(())
+}
+
+#[test]
+fn test_notes_over_the_size_limit_are_refused() -> Result<(), AppError> {
+    let mut vault = Vault::with_storage(MemoryStorage::new(), pin("123456"));
+    let note = vault.create("Large", &[b'a'; 4096])?;
+    let size = vault.stored_size(&note.uuid)?;
+    assert!(size > 4096);
+
+    vault.set_max_note_size(size);
+    assert_eq!(vault.read(&note.uuid)?.len(), 4096);
+
+    vault.set_max_note_size(1024);
+    match vault.read(&note.uuid) {
+        Err(e @ AppError::TooLarge { .. }) => {
+            assert!(e.to_string().contains("ryokan config set max-note-size"));
+        }
+        other => {
+            return Err(AppError::Config(format!(
+                "Expected TooLarge, got {other:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_changes_by_another_process_are_noticed() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let other = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let note = vault.create("Draft", b"first")?;
+
+    vault.list()?;
+    let generation = vault.generation();
+    let stamp = vault.stamp(&note.uuid)?;
+    vault.list()?;
+    assert_eq!(vault.generation(), generation);
+    assert_eq!(vault.stamp(&note.uuid)?, stamp);
+
+    other.write(&note.uuid, b"second draft")?;
+    assert_ne!(vault.stamp(&note.uuid)?, stamp);
+    vault.list()?;
+    assert!(vault.generation() > generation);
+
+    let generation = vault.generation();
+    other.delete(&note.uuid)?;
+    assert!(vault.list()?.is_empty());
+    assert!(vault.generation() > generation);
+    Ok(())
+}
+
+#[test]
+fn test_listing_lazily_leaves_metadata_for_later() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let writer = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let groceries = writer.create("Groceries", b"milk")?;
+    let ideas = writer.create("Ideas", b"")?;
+
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let listing = vault.list_lazily(|uuid| uuid == groceries.uuid)?;
+    assert_eq!(listing.pending, [groceries.uuid.as_str()]);
+    assert_eq!(vault.metadata_parses(), 1);
+    let placeholder = listing
+        .notes
+        .iter()
+        .find(|note| note.uuid == groceries.uuid)
+        .map(|note| note.metadata.original_filename.clone());
+    assert_eq!(placeholder.as_deref(), Some(&groceries.uuid[..8]));
+    assert!(
+        listing
+            .notes
+            .iter()
+            .any(|note| note.uuid == ideas.uuid && note.metadata.original_filename == "Ideas")
+    );
+
+    // Parsed once, then listed from the cache
+    assert_eq!(
+        vault.load_metadata(&groceries.uuid)?.original_filename,
+        "Groceries"
+    );
+    assert!(vault.list_lazily(|_| true)?.pending.is_empty());
+    assert_eq!(vault.list()?.len(), 2);
+    assert_eq!(vault.metadata_parses(), 2);
+
+    vault.forget_metadata();
+    assert_eq!(vault.list_lazily(|_| true)?.pending.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_paranoid_writes_catch_corrupted_files() -> Result<(), AppError> {
+    // Without paranoid writes, the damage goes unnoticed until the next read
+    let storage = Arc::new(FailingStorage::default());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let note = vault.create("Diary", b"first entry")?;
+    storage.set_fault(Some(Fault::CorruptContent));
+    vault.write(&note.uuid, b"second entry")?;
+    assert!(vault.read(&note.uuid).is_err());
+
+    let storage = Arc::new(FailingStorage::default());
+    let mut vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.set_paranoid_writes(true);
+    storage.set_fault(Some(Fault::CorruptContent));
+    assert!(matches!(
+        vault.create("Ideas", b"a boat"),
+        Err(AppError::WriteVerification(_))
+    ));
+    assert!(
+        storage.list("")?.is_empty(),
+        "the damaged note is left behind"
+    );
+
+    storage.set_fault(None);
+    let note = vault.create("Diary", b"first entry")?;
+    storage.set_fault(Some(Fault::CorruptContent));
+    assert!(matches!(
+        vault.write(&note.uuid, b"second entry"),
+        Err(AppError::WriteVerification(_))
+    ));
+
+    let storage = Arc::new(FailingStorage::default());
+    let mut vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.set_paranoid_writes(true);
+    vault.create("Diary", b"first entry")?;
+    storage.set_fault(Some(Fault::CorruptContent));
+    let summary = vault.rekey()?;
+    assert_eq!(summary.failed.len(), 1, "{summary:?}");
+    assert!(summary.failed[0].1.starts_with("Write verification failed"));
+    Ok(())
+}
+
+#[test]
+fn test_long_titles_and_tags_are_cut_when_saved() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let mut vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    vault.set_max_title_length(10);
+    let long_title = "Imported ".repeat(1000);
+
+    // Saved by an older version, without limits: loads as is until saved again
+    let old = file::create_new_note(
+        storage.as_ref(),
+        "123456",
+        &NoteMetadata::new(long_title.as_str()),
+        b"",
+    )?;
+    let (_, metadata_name) = file::note_file_names(&old);
+    let saved = storage.read(&metadata_name)?;
+    assert_eq!(vault.get(&old)?.metadata.original_filename, long_title);
+    assert_eq!(storage.read(&metadata_name)?, saved);
+
+    let mut metadata = NoteMetadata::new(long_title.as_str());
+    metadata.tags = (0..100).map(|i| format!("tag{i}")).collect();
+    metadata.tags.push("x".repeat(100));
+    let note = vault.create_with_metadata(&metadata, b"")?;
+    let metadata = vault.get(&note.uuid)?.metadata;
+    assert_eq!(metadata.original_filename, "Imported…");
+    assert_eq!(metadata.long_title.as_deref(), Some(long_title.as_str()));
+    assert_eq!(metadata.tags.len(), ryokan::metadata::MAX_TAGS);
+
+    // Other changes keep the whole title, renaming drops it
+    let metadata = vault.metadata_mut(&note.uuid, |m| m.read_only = true)?;
+    assert_eq!(metadata.long_title.as_deref(), Some(long_title.as_str()));
+    let metadata = vault.metadata_mut(&note.uuid, |m| m.original_filename = "Short".into())?;
+    assert_eq!(metadata.original_filename, "Short");
+    assert_eq!(metadata.long_title, None);
+    Ok(())
+}
+
+#[test]
+fn test_metadata_sheets_round_trip_and_apply() -> Result<(), AppError> {
+    let storage = Arc::new(MemoryStorage::new());
+    let vault = Vault::with_storage(Arc::clone(&storage), pin("123456"));
+    let groceries = vault.create("Groceries", b"milk")?;
+    let plans = vault.create("Plans", b"")?;
+    vault.assign_slot(&plans.uuid, Some(1))?;
+
+    let mut sheet = Vec::new();
+    ryokan::sheet::write(&vault.list()?, &mut sheet)?;
+    let sheet = String::from_utf8(sheet).map_err(|e| AppError::Import(e.to_string()))?;
+    assert!(
+        ryokan::sheet::read(sheet.as_bytes(), &vault.list()?)?
+            .changes
+            .is_empty()
+    );
+
+    // Edited in a spreadsheet: a title with a comma and quotes, and the slot
+    // moved, leaving the other columns out
+    let edited = format!(
+        "uuid,title,slot\n{},\"Groceries, \"\"weekly\"\"\",1\n{},Plans,\n",
+        groceries.uuid, plans.uuid
+    );
+    let plan = ryokan::sheet::read(edited.as_bytes(), &vault.list()?)?;
+    assert_eq!(plan.changes.len(), 2);
+    assert_eq!(ryokan::sheet::apply(&vault, &plan)?.changed.len(), 2);
+
+    let groceries = vault.get(&groceries.uuid)?.metadata;
+    assert_eq!(groceries.original_filename, "Groceries, \"weekly\"");
+    assert_eq!(groceries.slot, Some(1));
+    assert_eq!(vault.get(&plans.uuid)?.metadata.slot, None);
+    assert_eq!(vault.read(&plans.uuid)?, b"");
+    Ok(())
+}